@@ -37,8 +37,8 @@ fn default_delay() -> std::iter::Take<ExponentialBackoff> {
 pub struct CallApi<A, L, R>
 where
     A: Action,
-    L: (FnOnce(Duration, usize) -> String) + Copy + Send + 'static,
-    R: (FnOnce(Duration) -> String) + Copy + Send + 'static,
+    L: (Fn(Duration, usize) -> String) + Clone + Send + 'static,
+    R: (FnOnce(Duration) -> String) + Clone + Send + 'static,
 {
     pub action: A,
     pub log_progress_message: L,
@@ -48,12 +48,12 @@ where
 impl<A, L, R> CallApi<A, L, R>
 where
     A: Action,
-    L: (FnOnce(Duration, usize) -> String) + Copy + Send + 'static,
-    R: (FnOnce(Duration) -> String) + Copy + Send + 'static,
+    L: (Fn(Duration, usize) -> String) + Clone + Send + 'static,
+    R: (FnOnce(Duration) -> String) + Clone + Send + 'static,
 {
     pub async fn call_api(&mut self) -> Result<A::Item, A::Error> {
         let report_slow_progress_start = time::Instant::now();
-        let report_slow_progress_message = self.report_slow_progress_message;
+        let report_slow_progress_message = self.report_slow_progress_message.clone();
         let report_slow_progress_handle = tokio::spawn(async move {
             let duration = Duration::from_secs(REPORT_SLOW_PROGRESS_TIMEOUT_SECS);
             time::sleep(duration).await;
@@ -65,7 +65,7 @@ where
         });
 
         let check_progress_start = time::Instant::now();
-        let log_progress_message = self.log_progress_message;
+        let log_progress_message = self.log_progress_message.clone();
         let check_progress_handle = tokio::spawn(async move {
             let mut log_count = 0;
             let duration = Duration::from_secs(CHECK_PROGRESS_INTERVAL_SECS);
@@ -1,33 +1,148 @@
-use std::path::Path;
+use std::{path::Path, time::Duration};
 
 use anyhow::Context;
-use constants::{DEFAULT_ORIGIN, TRUNK_PUBLIC_API_ADDRESS_ENV};
+use async_trait::async_trait;
+use constants::{
+    DEFAULT_ORIGIN, TRUNK_API_TIMEOUT_SECS_ENV, TRUNK_CONNECT_TIMEOUT_SECS_ENV,
+    TRUNK_PUBLIC_API_ADDRESS_ENV, TRUNK_S3_TIMEOUT_SECS_ENV,
+};
 use http::{header::HeaderMap, HeaderValue};
-use reqwest::{header, Client, Response, StatusCode};
+use reqwest::{header, Body, Client, Response, StatusCode};
 use tokio::fs;
+use tokio_util::io::ReaderStream;
 
 use crate::call_api::CallApi;
+use crate::error_code::{ErrorCode, WithErrorCode};
 use crate::message;
+use crate::upload_progress::UploadProgress;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiClientTimeouts {
+    /// Overall timeout for requests to Trunk services
+    pub api: Duration,
+    /// Read timeout for S3 uploads, which have no overall timeout so that large bundles are not
+    /// cut off mid-stream
+    pub s3_read: Duration,
+    /// Timeout for establishing a connection, shared by both clients
+    pub connect: Duration,
+}
+
+impl Default for ApiClientTimeouts {
+    fn default() -> Self {
+        Self {
+            api: Duration::from_secs(30),
+            s3_read: Duration::from_secs(60),
+            connect: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ApiClientTimeouts {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let from_env = |env_var: &str, default: Duration| {
+            std::env::var(env_var)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+        Self {
+            api: from_env(TRUNK_API_TIMEOUT_SECS_ENV, default.api),
+            s3_read: from_env(TRUNK_S3_TIMEOUT_SECS_ENV, default.s3_read),
+            connect: from_env(TRUNK_CONNECT_TIMEOUT_SECS_ENV, default.connect),
+        }
+    }
+}
+
+/// The calls to Trunk services and S3 made while uploading a bundle, so that the upload can run
+/// against something other than [`ApiClient`], e.g. an in-memory client in tests or when embedded.
+#[async_trait]
+pub trait ApiClientTrait: Send + Sync {
+    async fn create_repo(
+        &self,
+        request: &message::CreateRepoRequest,
+    ) -> anyhow::Result<message::CreateRepoResponse>;
+
+    async fn create_bundle_upload(
+        &self,
+        request: &message::CreateBundleUploadRequest,
+    ) -> anyhow::Result<message::CreateBundleUploadResponse>;
+
+    async fn get_quarantining_config(
+        &self,
+        request: &message::GetQuarantineConfigRequest,
+    ) -> anyhow::Result<message::GetQuarantineConfigResponse>;
+
+    async fn validate_token_org(
+        &self,
+        request: &message::ValidateTokenOrgRequest,
+    ) -> anyhow::Result<message::ValidateTokenOrgResponse>;
+
+    async fn put_bundle_to_s3(&self, url: &str, bundle_path: &Path) -> anyhow::Result<()>;
+
+    /// Like [`ApiClientTrait::put_bundle_to_s3`], counting the bytes sent towards
+    /// `upload_progress`. Clients that can't tell how much was sent leave it untouched.
+    async fn put_bundle_to_s3_with_progress(
+        &self,
+        url: &str,
+        bundle_path: &Path,
+        _upload_progress: &UploadProgress,
+    ) -> anyhow::Result<()> {
+        self.put_bundle_to_s3(url, bundle_path).await
+    }
+
+    async fn update_bundle_upload(
+        &self,
+        request: &message::UpdateBundleUploadRequest,
+    ) -> anyhow::Result<message::UpdateBundleUploadResponse>;
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClientKind {
+    Trunk,
+    S3,
+}
 
 pub struct ApiClient {
     host: String,
     s3_client: Client,
     trunk_client: Client,
+    timeouts: ApiClientTimeouts,
     version_path_prefix: String,
 }
 
 impl ApiClient {
-    const TRUNK_API_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
     const TRUNK_API_TOKEN_HEADER: &'static str = "x-api-token";
 
     pub fn new<T: AsRef<str>>(api_token: T) -> anyhow::Result<Self> {
+        Self::new_with_timeouts(api_token, ApiClientTimeouts::from_env())
+    }
+
+    pub fn new_with_timeouts<T: AsRef<str>>(
+        api_token: T,
+        timeouts: ApiClientTimeouts,
+    ) -> anyhow::Result<Self> {
         let api_token = api_token.as_ref();
         if api_token.trim().is_empty() {
-            return Err(anyhow::anyhow!("Trunk API token is required."));
+            return Err(anyhow::anyhow!("Trunk API token is required."))
+                .error_code(ErrorCode::InvalidToken);
         }
         let api_token_header_value = HeaderValue::from_str(api_token)
-            .map_err(|_| anyhow::Error::msg("Trunk API token is not ASCII"))?;
+            .map_err(|_| anyhow::Error::msg("Trunk API token is not ASCII"))
+            .error_code(ErrorCode::InvalidToken)?;
+        Self::build(Some(api_token_header_value), timeouts)
+    }
+
+    /// A client without a token, which can only get one with [`ApiClient::exchange_oidc_token`].
+    pub fn new_for_token_exchange(timeouts: ApiClientTimeouts) -> anyhow::Result<Self> {
+        Self::build(None, timeouts)
+    }
 
+    fn build(
+        api_token_header_value: Option<HeaderValue>,
+        timeouts: ApiClientTimeouts,
+    ) -> anyhow::Result<Self> {
         let host = std::env::var(TRUNK_PUBLIC_API_ADDRESS_ENV)
             .ok()
             .and_then(|s| if s.is_empty() { None } else { Some(s) })
@@ -38,10 +153,21 @@ impl ApiClient {
             header::CONTENT_TYPE,
             HeaderValue::from_static("application/json"),
         );
-        trunk_client_default_headers.append(Self::TRUNK_API_TOKEN_HEADER, api_token_header_value);
+        if let Some(api_token_header_value) = api_token_header_value {
+            trunk_client_default_headers
+                .append(Self::TRUNK_API_TOKEN_HEADER, api_token_header_value);
+        }
+
+        log::debug!(
+            "API client timeouts: api={}s, s3 read={}s, connect={}s",
+            timeouts.api.as_secs(),
+            timeouts.s3_read.as_secs(),
+            timeouts.connect.as_secs()
+        );
 
         let trunk_client = Client::builder()
-            .timeout(Self::TRUNK_API_TIMEOUT)
+            .timeout(timeouts.api)
+            .connect_timeout(timeouts.connect)
             .default_headers(trunk_client_default_headers)
             .build()?;
 
@@ -51,6 +177,8 @@ impl ApiClient {
             HeaderValue::from_static("application/octet-stream"),
         );
         let s3_client = Client::builder()
+            .read_timeout(timeouts.s3_read)
+            .connect_timeout(timeouts.connect)
             .default_headers(s3_client_default_headers)
             .build()?;
 
@@ -64,10 +192,34 @@ impl ApiClient {
             host,
             s3_client,
             trunk_client,
+            timeouts,
             version_path_prefix,
         })
     }
 
+    /// Overrides the address of Trunk services, which otherwise comes from
+    /// `TRUNK_PUBLIC_API_ADDRESS`.
+    pub fn with_host<T: Into<String>>(mut self, host: T) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    fn timeout_help(&self, error: reqwest::Error, client_kind: ClientKind) -> anyhow::Error {
+        if !error.is_timeout() {
+            return error.into();
+        }
+        let (timeout, knob) = match (error.is_connect(), client_kind) {
+            (true, _) => (self.timeouts.connect, TRUNK_CONNECT_TIMEOUT_SECS_ENV),
+            (false, ClientKind::Trunk) => (self.timeouts.api, TRUNK_API_TIMEOUT_SECS_ENV),
+            (false, ClientKind::S3) => (self.timeouts.s3_read, TRUNK_S3_TIMEOUT_SECS_ENV),
+        };
+        anyhow::Error::new(error).context(format!(
+            "Request timed out after {} seconds. Set {} to increase the timeout.",
+            timeout.as_secs(),
+            knob
+        ))
+    }
+
     pub async fn create_repo(
         &self,
         request: &message::CreateRepoRequest,
@@ -79,7 +231,8 @@ impl ApiClient {
                     .post(format!("{}{}/repo/create", self.host, self.version_path_prefix))
                     .json(&request)
                     .send()
-                    .await?;
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
 
                 status_code_help(
                     &response,
@@ -115,7 +268,8 @@ impl ApiClient {
                     .post(format!("{}{}/metrics/createBundleUpload", self.host, self.version_path_prefix))
                     .json(&request)
                     .send()
-                    .await?;
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
 
                 status_code_help(
                     &response,
@@ -151,7 +305,8 @@ impl ApiClient {
                     .post(format!("{}{}/metrics/getQuarantineConfig", self.host, self.version_path_prefix))
                     .json(&request)
                     .send()
-                    .await?;
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
 
                 status_code_help(
                     &response,
@@ -182,23 +337,160 @@ impl ApiClient {
         .await
     }
 
+    pub async fn list_recent_uploads(
+        &self,
+        request: &message::ListBundleUploadsRequest,
+    ) -> anyhow::Result<message::ListBundleUploadsResponse> {
+        CallApi {
+            action: || async {
+                let response = self
+                    .trunk_client
+                    .post(format!("{}{}/metrics/listBundleUploads", self.host, self.version_path_prefix))
+                    .json(&request)
+                    .send()
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
+
+                status_code_help(
+                    &response,
+                    CheckUnauthorized::Check,
+                    CheckNotFound::Check,
+                    |_| String::from("Failed to list recent bundle uploads."),
+                )?;
+
+                response
+                    .json::<message::ListBundleUploadsResponse>()
+                    .await
+                    .context("Failed to get response body as json.")
+            },
+            log_progress_message: |time_elapsed, _| {
+                format!("Listing recent uploads from Trunk services is taking longer than expected. It has taken {} seconds so far.", time_elapsed.as_secs())
+            },
+            report_slow_progress_message: |time_elapsed| {
+                format!("Listing recent Trunk bundle uploads is taking longer than {} seconds", time_elapsed.as_secs())
+            },
+        }
+        .call_api()
+        .await
+    }
+
+    /// Looks up the organization of the token, so that a wrong org url slug is caught before any
+    /// test results are parsed.
+    pub async fn validate_token_org(
+        &self,
+        request: &message::ValidateTokenOrgRequest,
+    ) -> anyhow::Result<message::ValidateTokenOrgResponse> {
+        CallApi {
+            action: || async {
+                let response = self
+                    .trunk_client
+                    .post(format!("{}{}/metrics/validateTokenOrg", self.host, self.version_path_prefix))
+                    .json(&request)
+                    .send()
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
+
+                status_code_help(
+                    &response,
+                    CheckUnauthorized::Check,
+                    CheckNotFound::DoNotCheck,
+                    |_| String::from("Failed to validate the organization of the token."),
+                )?;
+
+                response
+                    .json::<message::ValidateTokenOrgResponse>()
+                    .await
+                    .context("Failed to get response body as json.")
+            },
+            log_progress_message: |time_elapsed, _| {
+                format!("Communicating with Trunk services is taking longer than expected. It has taken {} seconds so far.", time_elapsed.as_secs())
+            },
+            report_slow_progress_message: |time_elapsed| {
+                format!("Validating the organization of a Trunk token is taking longer than {} seconds", time_elapsed.as_secs())
+            },
+        }
+        .call_api()
+        .await
+    }
+
+    /// Exchanges the OIDC token of a CI job for a short-lived API token of the organization.
+    pub async fn exchange_oidc_token(
+        &self,
+        request: &message::ExchangeOidcTokenRequest,
+    ) -> anyhow::Result<message::ExchangeOidcTokenResponse> {
+        CallApi {
+            action: || async {
+                let response = self
+                    .trunk_client
+                    .post(format!("{}{}/metrics/exchangeOidcToken", self.host, self.version_path_prefix))
+                    .json(&request)
+                    .send()
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
+
+                if let StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN = response.status() {
+                    return Err(anyhow::anyhow!(
+                        "Trunk rejected the OIDC token of the job. Check that the repository is allowed to authenticate with OIDC for organization {:?}.",
+                        request.org_url_slug
+                    ))
+                    .error_code(ErrorCode::InvalidToken);
+                }
+                status_code_help(
+                    &response,
+                    CheckUnauthorized::DoNotCheck,
+                    CheckNotFound::Check,
+                    |_| String::from("Failed to exchange the OIDC token for an API token."),
+                )?;
+
+                response
+                    .json::<message::ExchangeOidcTokenResponse>()
+                    .await
+                    .context("Failed to get response body as json.")
+            },
+            log_progress_message: |time_elapsed, _| {
+                format!("Communicating with Trunk services is taking longer than expected. It has taken {} seconds so far.", time_elapsed.as_secs())
+            },
+            report_slow_progress_message: |time_elapsed| {
+                format!("Exchanging an OIDC token is taking longer than {} seconds", time_elapsed.as_secs())
+            },
+        }
+        .call_api()
+        .await
+    }
+
     pub async fn put_bundle_to_s3<U: AsRef<str>, B: AsRef<Path>>(
         &self,
         url: U,
         bundle_path: B,
     ) -> anyhow::Result<()> {
+        self.put_bundle_to_s3_with_progress(url, bundle_path, &UploadProgress::default())
+            .await
+    }
+
+    /// Clones of `upload_progress` tell how much of the bundle was sent while it is being put.
+    pub async fn put_bundle_to_s3_with_progress<U: AsRef<str>, B: AsRef<Path>>(
+        &self,
+        url: U,
+        bundle_path: B,
+        upload_progress: &UploadProgress,
+    ) -> anyhow::Result<()> {
+        let log_upload_progress = upload_progress.clone();
+        let report_upload_progress = upload_progress.clone();
         CallApi {
             action: || async {
                 let file = fs::File::open(bundle_path.as_ref()).await?;
                 let file_size = file.metadata().await?.len();
+                upload_progress.reset(file_size);
+                let body = Body::wrap_stream(ReaderStream::new(upload_progress.reader(file)));
 
                 let response = self
                     .s3_client
                     .put(url.as_ref())
                     .header(header::CONTENT_LENGTH, file_size)
-                    .body(file)
+                    .body(body)
                     .send()
-                    .await?;
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::S3))?;
 
                 status_code_help(
                     &response,
@@ -207,11 +499,17 @@ impl ApiClient {
                     |_| String::from("Failed to upload bundle to S3."),
                 )
             },
-            log_progress_message: |time_elapsed, _| {
-                format!("Uploading bundle to S3 is taking longer than expected. It has taken {} seconds so far.", time_elapsed.as_secs())
+            log_progress_message: move |time_elapsed, _| {
+                match log_upload_progress.summary() {
+                    Some(summary) => format!("Uploading bundle to S3 is taking longer than expected. It has taken {} seconds so far, {}.", time_elapsed.as_secs(), summary),
+                    None => format!("Uploading bundle to S3 is taking longer than expected. It has taken {} seconds so far.", time_elapsed.as_secs()),
+                }
             },
-            report_slow_progress_message: |time_elapsed| {
-                format!("Uploading bundle to S3 is taking longer than {} seconds", time_elapsed.as_secs())
+            report_slow_progress_message: move |time_elapsed| {
+                match report_upload_progress.percent() {
+                    Some(percent) => format!("Uploading bundle to S3 is taking longer than {} seconds, {}% uploaded", time_elapsed.as_secs(), percent),
+                    None => format!("Uploading bundle to S3 is taking longer than {} seconds", time_elapsed.as_secs()),
+                }
             },
         }
         .call_api()
@@ -229,7 +527,8 @@ impl ApiClient {
                     .patch(format!("{}{}/metrics/updateBundleUpload", self.host, self.version_path_prefix))
                     .json(request)
                     .send()
-                    .await?;
+                    .await
+                    .map_err(|e| self.timeout_help(e, ClientKind::Trunk))?;
 
                 status_code_help(
                     &response,
@@ -259,6 +558,57 @@ impl ApiClient {
     }
 }
 
+#[async_trait]
+impl ApiClientTrait for ApiClient {
+    async fn create_repo(
+        &self,
+        request: &message::CreateRepoRequest,
+    ) -> anyhow::Result<message::CreateRepoResponse> {
+        ApiClient::create_repo(self, request).await
+    }
+
+    async fn create_bundle_upload(
+        &self,
+        request: &message::CreateBundleUploadRequest,
+    ) -> anyhow::Result<message::CreateBundleUploadResponse> {
+        ApiClient::create_bundle_upload(self, request).await
+    }
+
+    async fn get_quarantining_config(
+        &self,
+        request: &message::GetQuarantineConfigRequest,
+    ) -> anyhow::Result<message::GetQuarantineConfigResponse> {
+        ApiClient::get_quarantining_config(self, request).await
+    }
+
+    async fn validate_token_org(
+        &self,
+        request: &message::ValidateTokenOrgRequest,
+    ) -> anyhow::Result<message::ValidateTokenOrgResponse> {
+        ApiClient::validate_token_org(self, request).await
+    }
+
+    async fn put_bundle_to_s3(&self, url: &str, bundle_path: &Path) -> anyhow::Result<()> {
+        ApiClient::put_bundle_to_s3(self, url, bundle_path).await
+    }
+
+    async fn put_bundle_to_s3_with_progress(
+        &self,
+        url: &str,
+        bundle_path: &Path,
+        upload_progress: &UploadProgress,
+    ) -> anyhow::Result<()> {
+        ApiClient::put_bundle_to_s3_with_progress(self, url, bundle_path, upload_progress).await
+    }
+
+    async fn update_bundle_upload(
+        &self,
+        request: &message::UpdateBundleUploadRequest,
+    ) -> anyhow::Result<message::UpdateBundleUploadResponse> {
+        ApiClient::update_bundle_upload(self, request).await
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum CheckUnauthorized {
     Check,
@@ -281,22 +631,29 @@ fn status_code_help<T: FnMut(&Response) -> String>(
         return Ok(());
     }
 
-    let error_message = match (response.status(), check_unauthorized, check_not_found) {
-        (StatusCode::UNAUTHORIZED, CheckUnauthorized::Check, _) => concat!(
-            "Your Trunk token may be incorrect - find it on the Trunk app ",
-            "(Settings -> Manage Organization -> Organization API Token -> View).",
+    let (error_message, error_code) = match (response.status(), check_unauthorized, check_not_found)
+    {
+        (StatusCode::UNAUTHORIZED, CheckUnauthorized::Check, _) => (
+            concat!(
+                "Your Trunk token may be incorrect - find it on the Trunk app ",
+                "(Settings -> Manage Organization -> Organization API Token -> View).",
+            ),
+            ErrorCode::InvalidToken,
         ),
-        (StatusCode::NOT_FOUND, _, CheckNotFound::Check) => concat!(
-            "Your Trunk organization URL slug may be incorrect - find it on the Trunk app ",
-            "(Settings -> Manage Organization -> Organization Slug).",
+        (StatusCode::NOT_FOUND, _, CheckNotFound::Check) => (
+            concat!(
+                "Your Trunk organization URL slug may be incorrect - find it on the Trunk app ",
+                "(Settings -> Manage Organization -> Organization Slug).",
+            ),
+            ErrorCode::OrgNotFound,
         ),
-        _ => &create_error_message(response),
+        _ => (&*create_error_message(response), ErrorCode::UploadFailed),
     };
 
     let error_message_with_help =
         format!("{error_message}\n\nFor more help, contact us at https://slack.trunk.io/");
 
-    Err(anyhow::Error::msg(error_message_with_help))
+    Err(anyhow::Error::msg(error_message_with_help)).error_code(error_code)
 }
 
 #[cfg(test)]
@@ -309,7 +666,7 @@ mod tests {
     use test_utils::{mock_logger, mock_sentry, mock_server::MockServerBuilder};
     use tokio::time;
 
-    use super::ApiClient;
+    use super::{ApiClient, ApiClientTimeouts};
     use crate::message;
 
     #[tokio::test(start_paused = true)]
@@ -393,4 +750,36 @@ mod tests {
             .to_string()
             .contains("Quarantining config not found"));
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_error_names_timeout_env_var() {
+        let mut mock_server_builder = MockServerBuilder::new();
+
+        async fn slow_s3_upload_handler() -> Response<String> {
+            time::sleep(Duration::from_secs(5)).await;
+            Response::new(String::from("OK"))
+        }
+        mock_server_builder.set_s3_upload_handler(slow_s3_upload_handler);
+
+        let state = mock_server_builder.spawn_mock_server().await;
+
+        let mut api_client = ApiClient::new_with_timeouts(
+            String::from("mock-token"),
+            ApiClientTimeouts {
+                s3_read: Duration::from_secs(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        api_client.host.clone_from(&state.host);
+
+        let bundle_file = NamedTempFile::new().unwrap();
+        let error = api_client
+            .put_bundle_to_s3(format!("{}/s3upload", state.host), bundle_file)
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Request timed out after 1 seconds. Set TRUNK_S3_TIMEOUT_SECS"));
+    }
 }
@@ -0,0 +1,147 @@
+//! Codes that say why a command failed.
+//!
+//! CI wrappers can tell failures apart by their code without matching on messages. The code of an
+//! error is printed as `error_code=...`, written to the results file, and decides the exit code.
+
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// The token is missing, malformed or rejected by Trunk
+    InvalidToken,
+    /// The org url slug does not match an organization of the token
+    OrgNotFound,
+    /// No test result files were found and empty test results are not allowed
+    NoTestFiles,
+    /// The repo root is not a git repository
+    GitRepoNotFound,
+    /// The bundle could not be registered with or uploaded to Trunk
+    UploadFailed,
+    /// The CLI is older than the minimum version recommended by the org
+    CliOutdated,
+    /// Anything else, which is likely a bug
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::OrgNotFound => "org_not_found",
+            ErrorCode::NoTestFiles => "no_test_files",
+            ErrorCode::GitRepoNotFound => "git_repo_not_found",
+            ErrorCode::UploadFailed => "upload_failed",
+            ErrorCode::CliOutdated => "cli_outdated",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    /// The one table exit codes of failed commands come from.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCode::InvalidToken => exitcode::NOPERM,
+            ErrorCode::OrgNotFound => exitcode::NOUSER,
+            ErrorCode::NoTestFiles => exitcode::NOINPUT,
+            ErrorCode::GitRepoNotFound => exitcode::CONFIG,
+            ErrorCode::UploadFailed => exitcode::UNAVAILABLE,
+            ErrorCode::CliOutdated => exitcode::CONFIG,
+            ErrorCode::Internal => exitcode::SOFTWARE,
+        }
+    }
+
+    /// The code of `error`, or [`ErrorCode::Internal`] if nothing in its chain has one.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<CodedError>())
+            .map(|coded_error| coded_error.code)
+            .unwrap_or(ErrorCode::Internal)
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An error with an [`ErrorCode`], which displays and chains like the error it wraps.
+#[derive(Debug)]
+pub struct CodedError {
+    code: ErrorCode,
+    error: anyhow::Error,
+}
+
+impl CodedError {
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl Error for CodedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+pub trait WithErrorCode<T> {
+    /// Gives the error a code, unless something it was caused by already has a more specific one.
+    fn error_code(self, code: ErrorCode) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> WithErrorCode<T> for Result<T, E> {
+    fn error_code(self, code: ErrorCode) -> anyhow::Result<T> {
+        self.map_err(|error| {
+            let error = error.into();
+            if error
+                .chain()
+                .any(|cause| cause.downcast_ref::<CodedError>().is_some())
+            {
+                return error;
+            }
+            CodedError { code, error }.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn coded_error_displays_like_the_error_it_wraps() {
+        let error = Err::<(), _>(anyhow::anyhow!("root cause"))
+            .context("failed to do something")
+            .error_code(ErrorCode::UploadFailed)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "failed to do something");
+        assert_eq!(format!("{:#}", error), "failed to do something: root cause");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::UploadFailed);
+    }
+
+    #[test]
+    fn keeps_the_most_specific_code() {
+        let error = Err::<(), _>(anyhow::anyhow!("unauthorized"))
+            .error_code(ErrorCode::InvalidToken)
+            .context("failed to create bundle upload")
+            .error_code(ErrorCode::UploadFailed)
+            .unwrap_err();
+        assert_eq!(ErrorCode::of(&error), ErrorCode::InvalidToken);
+    }
+
+    #[test]
+    fn uncoded_errors_are_internal() {
+        assert_eq!(
+            ErrorCode::of(&anyhow::anyhow!("something went wrong")),
+            ErrorCode::Internal
+        );
+    }
+}
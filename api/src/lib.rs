@@ -1,3 +1,5 @@
 mod call_api;
 pub mod client;
+pub mod error_code;
 pub mod message;
+pub mod upload_progress;
@@ -14,6 +14,18 @@ pub enum BundleUploadStatus {
     DryRun,
 }
 
+impl std::fmt::Display for BundleUploadStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            BundleUploadStatus::Pending => "PENDING",
+            BundleUploadStatus::UploadComplete => "UPLOAD_COMPLETE",
+            BundleUploadStatus::UploadFailed => "UPLOAD_FAILED",
+            BundleUploadStatus::DryRun => "DRY_RUN",
+        };
+        write!(f, "{}", status)
+    }
+}
+
 #[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
 pub struct CreateBundleUploadRequest {
     pub repo: RepoUrlParts,
@@ -30,6 +42,13 @@ pub struct CreateBundleUploadResponse {
     pub id_v2: String,
     pub url: String,
     pub key: String,
+    /// Oldest CLI version that produces everything the org relies on, if the org has one
+    #[serde(
+        rename = "minimumRecommendedCliVersion",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub minimum_recommended_cli_version: Option<String>,
 }
 
 #[derive(Debug, Serialize, Clone, Deserialize, Default)]
@@ -88,3 +107,60 @@ pub struct CreateBundleUploadIntentResponse {
     #[serde(rename = "clientVersion")]
     pub client_version: String,
 }
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct ListBundleUploadsRequest {
+    pub repo: RepoUrlParts,
+    #[serde(rename = "orgUrlSlug")]
+    pub org_url_slug: String,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct BundleUploadSummary {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "uploadStatus")]
+    pub upload_status: BundleUploadStatus,
+    pub variant: Option<String>,
+    #[serde(rename = "numTests")]
+    pub num_tests: Option<usize>,
+    #[serde(rename = "numFailures")]
+    pub num_failures: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq, Default)]
+pub struct ListBundleUploadsResponse {
+    #[serde(rename = "bundleUploads")]
+    pub bundle_uploads: Vec<BundleUploadSummary>,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct ValidateTokenOrgRequest {
+    #[serde(rename = "orgUrlSlug")]
+    pub org_url_slug: String,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct ValidateTokenOrgResponse {
+    /// Slug of the organization the token belongs to
+    #[serde(rename = "orgUrlSlug")]
+    pub org_url_slug: String,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct ExchangeOidcTokenRequest {
+    /// OIDC token issued by the CI provider to the job, e.g. by GitHub Actions
+    #[serde(rename = "oidcToken")]
+    pub oidc_token: String,
+    #[serde(rename = "orgUrlSlug")]
+    pub org_url_slug: String,
+}
+
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
+pub struct ExchangeOidcTokenResponse {
+    /// Short-lived API token of the organization, used in place of the organization token
+    #[serde(rename = "apiToken")]
+    pub api_token: String,
+}
@@ -0,0 +1,186 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    time::Instant,
+};
+
+const BYTES_PER_MB: f64 = 1_000_000.0;
+
+/// Tracks how many bytes of a bundle upload have been sent.
+///
+/// Clones share the same counters, and every reader created with [`UploadProgress::reader`] adds
+/// to them, so the progress of multiple parts of an upload is aggregated.
+#[derive(Debug, Clone, Default)]
+pub struct UploadProgress {
+    inner: Arc<UploadProgressInner>,
+}
+
+#[derive(Debug, Default)]
+struct UploadProgressInner {
+    bytes_sent: AtomicU64,
+    total_bytes: AtomicU64,
+    started_at: Mutex<Option<Instant>>,
+}
+
+impl UploadProgress {
+    /// Starts tracking a new upload attempt of `total_bytes`, discarding the progress of any
+    /// previous attempt.
+    pub fn reset(&self, total_bytes: u64) {
+        self.inner.bytes_sent.store(0, Ordering::Relaxed);
+        self.inner.total_bytes.store(total_bytes, Ordering::Relaxed);
+        *self.inner.started_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    pub fn reader<R: AsyncRead + Unpin>(&self, reader: R) -> ProgressReader<R> {
+        ProgressReader {
+            reader,
+            upload_progress: self.clone(),
+        }
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.inner.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.inner.total_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn percent(&self) -> Option<u64> {
+        let total_bytes = self.total_bytes();
+        if total_bytes == 0 {
+            return None;
+        }
+        Some(self.bytes_sent().min(total_bytes) * 100 / total_bytes)
+    }
+
+    /// A summary such as `45% uploaded (675.0 MB of 1500.0 MB at 12.3 MB/s, ETA 67s)`, or `None`
+    /// if there is nothing to upload.
+    pub fn summary(&self) -> Option<String> {
+        let elapsed = self
+            .inner
+            .started_at
+            .lock()
+            .unwrap()
+            .map(|started_at| started_at.elapsed())
+            .unwrap_or_default();
+        self.summary_with_elapsed(elapsed)
+    }
+
+    fn summary_with_elapsed(&self, elapsed: Duration) -> Option<String> {
+        let percent = self.percent()?;
+        let bytes_sent = self.bytes_sent().min(self.total_bytes());
+        let bytes_per_sec = if elapsed.is_zero() {
+            0.0
+        } else {
+            bytes_sent as f64 / elapsed.as_secs_f64()
+        };
+        let eta = if bytes_per_sec > 0.0 {
+            format!(
+                "{}s",
+                ((self.total_bytes() - bytes_sent) as f64 / bytes_per_sec).ceil()
+            )
+        } else {
+            String::from("unknown")
+        };
+        Some(format!(
+            "{}% uploaded ({:.1} MB of {:.1} MB at {:.1} MB/s, ETA {})",
+            percent,
+            bytes_sent as f64 / BYTES_PER_MB,
+            self.total_bytes() as f64 / BYTES_PER_MB,
+            bytes_per_sec / BYTES_PER_MB,
+            eta
+        ))
+    }
+}
+
+/// Counts the bytes read from `reader` towards its [`UploadProgress`].
+pub struct ProgressReader<R> {
+    reader: R,
+    upload_progress: UploadProgress,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled_before = buf.filled().len();
+        let poll = Pin::new(&mut self.reader).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            let bytes_read = (buf.filled().len() - filled_before) as u64;
+            self.upload_progress
+                .inner
+                .bytes_sent
+                .fetch_add(bytes_read, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::UploadProgress;
+
+    #[tokio::test]
+    async fn counts_bytes_read_and_resets() {
+        let upload_progress = UploadProgress::default();
+        assert_eq!(upload_progress.summary(), None);
+
+        let bundle = vec![0u8; 4_000_000];
+        upload_progress.reset(bundle.len() as u64);
+        let mut reader = upload_progress.reader(&bundle[..1_000_000]);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(upload_progress.bytes_sent(), 1_000_000);
+        assert_eq!(upload_progress.percent(), Some(25));
+        assert_eq!(
+            upload_progress.summary_with_elapsed(Duration::from_secs(2)),
+            Some(String::from(
+                "25% uploaded (1.0 MB of 4.0 MB at 0.5 MB/s, ETA 6s)"
+            ))
+        );
+
+        upload_progress.reset(bundle.len() as u64);
+        assert_eq!(upload_progress.bytes_sent(), 0);
+        assert_eq!(
+            upload_progress.summary_with_elapsed(Duration::ZERO),
+            Some(String::from(
+                "0% uploaded (0.0 MB of 4.0 MB at 0.0 MB/s, ETA unknown)"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn aggregates_progress_of_multiple_readers() {
+        let upload_progress = UploadProgress::default();
+        let bundle = [0u8; 100];
+        upload_progress.reset(bundle.len() as u64);
+
+        for part in bundle.chunks(50) {
+            let mut buf = Vec::new();
+            upload_progress
+                .reader(part)
+                .read_to_end(&mut buf)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(upload_progress.percent(), Some(100));
+    }
+}
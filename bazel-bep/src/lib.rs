@@ -18,6 +18,8 @@ pub mod server {
     pub use super::google::devtools::build::v1::publish_build_event_server::*;
 }
 
+pub mod stream;
+
 pub mod types {
     pub mod build_event_stream {
         pub use crate::build_event_stream::*;
@@ -0,0 +1,427 @@
+//! Reads a stream of [`BuildEvent`]s as written by `--build_event_json_file` (one JSON object per
+//! line) or `--build_event_binary_file` (varint length-delimited protobuf messages).
+//!
+//! The framing is detected from the first bytes of the stream, so callers don't need to know which
+//! flag produced the file.
+
+use std::{
+    fmt,
+    io::{self, BufRead, Read},
+};
+
+use prost::Message;
+
+use crate::types::build_event_stream::{
+    build_event::Payload, build_event_id, BuildEvent, TestResult, TestSummary,
+};
+
+/// The tag of `BuildEvent.id`, which is the first field bazel writes for every event
+const BINARY_FIRST_FIELD_TAG: u8 = 0x0a;
+/// The most bytes a varint takes to encode a `u64`
+const MAX_VARINT_LEN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// One JSON object per line, as written by `--build_event_json_file`
+    JsonLines,
+    /// Varint length-delimited protobuf messages, as written by `--build_event_binary_file`
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum BepError {
+    Io(io::Error),
+    /// A line that isn't a valid JSON build event
+    Json {
+        line: usize,
+        source: serde_json::Error,
+    },
+    /// A message that isn't a valid protobuf build event
+    Protobuf {
+        index: usize,
+        source: prost::DecodeError,
+    },
+    /// The stream ended within a message, so no more events can be read
+    Truncated {
+        index: usize,
+    },
+}
+
+impl fmt::Display for BepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(source) => write!(f, "{}", source),
+            Self::Json { line, source } => {
+                // each line is parsed on its own, so the position is relative to the line
+                let message = source.to_string();
+                let message = message
+                    .strip_suffix(&format!(
+                        " at line {} column {}",
+                        source.line(),
+                        source.column()
+                    ))
+                    .unwrap_or(&message);
+                write!(f, "{} at line {} column {}", message, line, source.column())
+            }
+            Self::Protobuf { index, source } => write!(f, "{} in message {}", source, index),
+            Self::Truncated { index } => write!(f, "stream ended within message {}", index),
+        }
+    }
+}
+
+impl std::error::Error for BepError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(source) => Some(source),
+            Self::Json { source, .. } => Some(source),
+            Self::Protobuf { source, .. } => Some(source),
+            Self::Truncated { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for BepError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl BuildEvent {
+    pub fn as_test_result(&self) -> Option<(&build_event_id::TestResultId, &TestResult)> {
+        match (self.id.as_ref()?.id.as_ref()?, self.payload.as_ref()?) {
+            (build_event_id::Id::TestResult(id), Payload::TestResult(test_result)) => {
+                Some((id, test_result))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_test_summary(&self) -> Option<(&build_event_id::TestSummaryId, &TestSummary)> {
+        match (self.id.as_ref()?.id.as_ref()?, self.payload.as_ref()?) {
+            (build_event_id::Id::TestSummary(id), Payload::TestSummary(test_summary)) => {
+                Some((id, test_summary))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether bazel will write no events after this one
+    pub fn is_last_message(&self) -> bool {
+        self.last_message
+    }
+}
+
+/// Iterates over the [`BuildEvent`]s of a stream.
+///
+/// A malformed event yields an error and reading continues with the next one, unless the reader
+/// is strict. Errors that leave the rest of the stream unreadable, e.g. [`BepError::Truncated`],
+/// always end it.
+#[derive(Debug)]
+pub struct BepReader<R> {
+    reader: R,
+    framing: Option<Framing>,
+    strict: bool,
+    /// Lines read so far for JSON lines, or messages read so far for binary
+    position: usize,
+    done: bool,
+}
+
+impl<R: BufRead> BepReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            framing: None,
+            strict: false,
+            position: 0,
+            done: false,
+        }
+    }
+
+    /// Stops reading at the first malformed event.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Skips detection, e.g. for a stream that starts with a malformed event.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = Some(framing);
+        self
+    }
+
+    /// The framing of the stream, which is detected on first use.
+    pub fn framing(&mut self) -> io::Result<Framing> {
+        if let Some(framing) = self.framing {
+            return Ok(framing);
+        }
+        let framing = detect_framing(self.reader.fill_buf()?);
+        self.framing = Some(framing);
+        Ok(framing)
+    }
+
+    fn read_json_line(&mut self) -> Option<Result<BuildEvent, BepError>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => self.position += 1,
+                Err(e) => return Some(Err(BepError::Io(e))),
+            }
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        Some(
+            serde_json::from_str::<BuildEvent>(line.trim_end()).map_err(|source| BepError::Json {
+                line: self.position,
+                source,
+            }),
+        )
+    }
+
+    fn read_binary_message(&mut self) -> Option<Result<BuildEvent, BepError>> {
+        let index = self.position;
+        let len = match read_varint(&mut self.reader) {
+            Ok(None) => return None,
+            Ok(Some(len)) => len,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Some(Err(BepError::Truncated { index }))
+            }
+            Err(e) => return Some(Err(BepError::Io(e))),
+        };
+        self.position += 1;
+
+        let mut message = Vec::new();
+        match (&mut self.reader).take(len).read_to_end(&mut message) {
+            Ok(read) if (read as u64) < len => return Some(Err(BepError::Truncated { index })),
+            Ok(_) => {}
+            Err(e) => return Some(Err(BepError::Io(e))),
+        }
+        Some(
+            BuildEvent::decode(message.as_slice())
+                .map_err(|source| BepError::Protobuf { index, source }),
+        )
+    }
+}
+
+impl<R: BufRead> Iterator for BepReader<R> {
+    type Item = Result<BuildEvent, BepError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = match self.framing() {
+            Ok(Framing::JsonLines) => self.read_json_line(),
+            Ok(Framing::Binary) => self.read_binary_message(),
+            Err(e) => Some(Err(BepError::Io(e))),
+        };
+        match &next {
+            None | Some(Err(BepError::Io(_) | BepError::Truncated { .. })) => self.done = true,
+            Some(Err(_)) if self.strict => self.done = true,
+            _ => {}
+        }
+        next
+    }
+}
+
+/// A JSON build event starts with `{"id":`, which never reads as a varint length followed by the
+/// tag of `BuildEvent.id`.
+pub fn detect_framing(start: &[u8]) -> Framing {
+    let Some(len_end) = start
+        .iter()
+        .take(MAX_VARINT_LEN)
+        .position(|byte| byte & 0x80 == 0)
+    else {
+        return Framing::JsonLines;
+    };
+    match start.get(len_end + 1) {
+        Some(&BINARY_FIRST_FIELD_TAG) => Framing::Binary,
+        _ => Framing::JsonLines,
+    }
+}
+
+/// Returns `None` at the end of the stream, and errors if it ends within the varint.
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<Option<u64>> {
+    let mut value = 0u64;
+    for i in 0..MAX_VARINT_LEN {
+        let mut byte = [0u8];
+        if reader.read(&mut byte)? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        value |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "varint is longer than 10 bytes",
+    ))
+}
+
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+impl BepReader<io::Cursor<Vec<u8>>> {
+    /// Reads the whole stream up front, since bazel only finishes writing it once the build is
+    /// done anyway.
+    pub async fn from_async_read<R: tokio::io::AsyncRead + Unpin>(
+        mut reader: R,
+    ) -> io::Result<Self> {
+        let mut buf = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut buf).await?;
+        Ok(Self::new(io::Cursor::new(buf)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::types::build_event_stream::{
+        build_event::Payload, build_event_id, BuildEvent, BuildEventId, TestResult, TestStatus,
+        TestSummary,
+    };
+
+    fn test_result(label: &str) -> BuildEvent {
+        BuildEvent {
+            id: Some(BuildEventId {
+                id: Some(build_event_id::Id::TestResult(
+                    build_event_id::TestResultId {
+                        label: String::from(label),
+                        run: 1,
+                        shard: 1,
+                        attempt: 1,
+                        ..Default::default()
+                    },
+                )),
+            }),
+            payload: Some(Payload::TestResult(TestResult {
+                status: TestStatus::Passed.into(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn test_summary(label: &str) -> BuildEvent {
+        BuildEvent {
+            id: Some(BuildEventId {
+                id: Some(build_event_id::Id::TestSummary(
+                    build_event_id::TestSummaryId {
+                        label: String::from(label),
+                        ..Default::default()
+                    },
+                )),
+            }),
+            payload: Some(Payload::TestSummary(TestSummary {
+                overall_status: TestStatus::Flaky.into(),
+                ..Default::default()
+            })),
+            last_message: true,
+            ..Default::default()
+        }
+    }
+
+    fn json_lines(events: &[BuildEvent]) -> String {
+        events
+            .iter()
+            .map(|event| serde_json::to_string(event).unwrap() + "\n")
+            .collect()
+    }
+
+    fn binary(events: &[BuildEvent]) -> Vec<u8> {
+        events
+            .iter()
+            .flat_map(|event| event.encode_length_delimited_to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn reads_json_lines() {
+        let events = vec![test_result("//a:test"), test_summary("//a:test")];
+        let mut reader = BepReader::new(Cursor::new(json_lines(&events)));
+
+        assert_eq!(reader.framing().unwrap(), Framing::JsonLines);
+        let read: Vec<BuildEvent> = reader.map(Result::unwrap).collect();
+        assert_eq!(read, events);
+        assert_eq!(read[0].as_test_result().unwrap().0.label, "//a:test");
+        assert!(read[0].as_test_summary().is_none());
+        assert_eq!(
+            read[1].as_test_summary().unwrap().1.overall_status(),
+            TestStatus::Flaky
+        );
+        assert!(!read[0].is_last_message());
+        assert!(read[1].is_last_message());
+    }
+
+    #[test]
+    fn reads_binary() {
+        let events = vec![test_result("//a:test"), test_summary("//a:test")];
+        let mut reader = BepReader::new(Cursor::new(binary(&events)));
+
+        assert_eq!(reader.framing().unwrap(), Framing::Binary);
+        let read: Vec<BuildEvent> = reader.map(Result::unwrap).collect();
+        assert_eq!(read, events);
+    }
+
+    #[test]
+    fn reads_nothing_from_empty_stream() {
+        assert_eq!(BepReader::new(Cursor::new("")).count(), 0);
+    }
+
+    #[test]
+    fn continues_past_malformed_json_line() {
+        let json = format!(
+            "{}{{\"id\":{{\"testResult\":\n\n{}",
+            json_lines(&[test_result("//a:test")]),
+            json_lines(&[test_result("//b:test")])
+        );
+
+        let read: Vec<Result<BuildEvent, BepError>> = BepReader::new(Cursor::new(&json)).collect();
+        assert_eq!(read.len(), 3);
+        assert!(read[0].is_ok());
+        assert_eq!(
+            read[1].as_ref().unwrap_err().to_string(),
+            "EOF while parsing a value at line 2 column 20"
+        );
+        assert_eq!(
+            read[2].as_ref().unwrap().as_test_result().unwrap().0.label,
+            "//b:test"
+        );
+
+        let strict: Vec<Result<BuildEvent, BepError>> = BepReader::new(Cursor::new(&json))
+            .with_strict(true)
+            .collect();
+        assert_eq!(strict.len(), 2);
+        assert!(strict[1].is_err());
+    }
+
+    #[test]
+    fn continues_past_malformed_binary_message() {
+        let mut bytes = binary(&[test_result("//a:test")]);
+        // a length-delimited message of an invalid wire type
+        bytes.extend([2, 0x0f, 0x00]);
+        bytes.extend(binary(&[test_result("//b:test")]));
+
+        let read: Vec<Result<BuildEvent, BepError>> = BepReader::new(Cursor::new(bytes)).collect();
+        assert_eq!(read.len(), 3);
+        assert!(matches!(read[1], Err(BepError::Protobuf { index: 1, .. })));
+        assert!(read[2].is_ok());
+    }
+
+    #[test]
+    fn stops_at_truncated_binary_message() {
+        let mut bytes = binary(&[test_result("//a:test"), test_result("//b:test")]);
+        bytes.truncate(bytes.len() - 1);
+
+        let read: Vec<Result<BuildEvent, BepError>> = BepReader::new(Cursor::new(bytes)).collect();
+        assert_eq!(read.len(), 2);
+        assert!(matches!(read[1], Err(BepError::Truncated { index: 1 })));
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::bufread::GzDecoder;
+use zip::ZipArchive;
+
+/// Largest junit file that is extracted from an archive
+pub const MAX_ARCHIVE_ENTRY_BYTES: u64 = 100 * 1024 * 1024;
+/// Largest total size of the junit files extracted from a single archive
+pub const MAX_ARCHIVE_TOTAL_BYTES: u64 = 500 * 1024 * 1024;
+
+const ARCHIVE_PATH_SEPARATOR: &str = "!";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn from_path<T: AsRef<Path>>(path: T) -> Option<Self> {
+        let file_name = path.as_ref().file_name()?.to_str()?.to_ascii_lowercase();
+        if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if file_name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// A junit file that was extracted from an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the entry inside the archive
+    pub name: String,
+    pub extracted_path: PathBuf,
+}
+
+/// Formats the path of an entry inside an archive, i.e. `test-results.zip!nested/junit.xml`
+pub fn archive_entry_path<T: AsRef<str>, U: AsRef<str>>(archive_path: T, entry_name: U) -> String {
+    format!(
+        "{}{}{}",
+        archive_path.as_ref(),
+        ARCHIVE_PATH_SEPARATOR,
+        entry_name.as_ref()
+    )
+}
+
+/// Extracts the `*.xml` entries of an archive into `dest_dir`, which are named by their index
+/// so that entry names are never used as paths on disk.
+pub fn extract_junit_archive<T: AsRef<Path>, U: AsRef<Path>>(
+    archive_path: T,
+    kind: ArchiveKind,
+    dest_dir: U,
+) -> anyhow::Result<Vec<ArchiveEntry>> {
+    let archive_path = archive_path.as_ref();
+    let file = File::open(archive_path)?;
+    let mut extractor = Extractor {
+        archive_path,
+        dest_dir: dest_dir.as_ref(),
+        entries: Vec::new(),
+        total_bytes: 0,
+    };
+    match kind {
+        ArchiveKind::Zip => extractor.extract_zip(file)?,
+        ArchiveKind::Tar => extractor.extract_tar(BufReader::new(file))?,
+        ArchiveKind::TarGz => extractor.extract_tar(GzDecoder::new(BufReader::new(file)))?,
+    }
+    Ok(extractor.entries)
+}
+
+fn is_junit_entry(name: &str) -> bool {
+    !name.ends_with('/') && name.to_ascii_lowercase().ends_with(".xml")
+}
+
+struct Extractor<'a> {
+    archive_path: &'a Path,
+    dest_dir: &'a Path,
+    entries: Vec<ArchiveEntry>,
+    total_bytes: u64,
+}
+
+impl<'a> Extractor<'a> {
+    fn extract_tar<R: Read>(&mut self, reader: R) -> anyhow::Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().to_string();
+            if !is_junit_entry(&name) {
+                continue;
+            }
+            self.check_declared_size(&name, entry.header().size()?)?;
+            self.write_entry(name, &mut entry)?;
+        }
+        Ok(())
+    }
+
+    fn extract_zip(&mut self, file: File) -> anyhow::Result<()> {
+        let mut archive = ZipArchive::new(BufReader::new(file))
+            .map_err(|e| self.error(format!("is not a valid zip file: {}", e)))?;
+        for index in 0..archive.len() {
+            // Inspect the entry without decompressing it, reading an encrypted entry fails
+            let (name, size) = {
+                let entry = archive.by_index_raw(index)?;
+                if !entry.is_file() || !is_junit_entry(entry.name()) {
+                    continue;
+                }
+                if entry.encrypted() {
+                    return Err(self.error(format!(
+                        "contains encrypted entry {}, encrypted zip files are not supported",
+                        entry.name()
+                    )));
+                }
+                (entry.name().to_string(), entry.size())
+            };
+            self.check_declared_size(&name, size)?;
+            let mut entry = archive
+                .by_index(index)
+                .map_err(|e| self.error(format!("cannot read entry {}: {}", name, e)))?;
+            self.write_entry(name, &mut entry)?;
+        }
+        Ok(())
+    }
+
+    fn check_declared_size(&self, name: &str, size: u64) -> anyhow::Result<()> {
+        if size > MAX_ARCHIVE_ENTRY_BYTES {
+            return Err(self.error(format!(
+                "entry {} is larger than the limit of {} bytes",
+                name, MAX_ARCHIVE_ENTRY_BYTES
+            )));
+        }
+        Ok(())
+    }
+
+    /// Copies an entry to disk, enforcing the size limits on the bytes actually read since the
+    /// sizes declared by an archive cannot be trusted.
+    fn write_entry<R: Read>(&mut self, name: String, reader: &mut R) -> anyhow::Result<()> {
+        let extracted_path = self.dest_dir.join(format!("{}.xml", self.entries.len()));
+        let mut limited = reader.take(MAX_ARCHIVE_ENTRY_BYTES + 1);
+        let mut output = File::create(&extracted_path)?;
+        let bytes = std::io::copy(&mut limited, &mut output)?;
+        output.flush()?;
+        if bytes > MAX_ARCHIVE_ENTRY_BYTES {
+            return Err(self.error(format!(
+                "entry {} is larger than the limit of {} bytes",
+                name, MAX_ARCHIVE_ENTRY_BYTES
+            )));
+        }
+        self.total_bytes += bytes;
+        if self.total_bytes > MAX_ARCHIVE_TOTAL_BYTES {
+            return Err(self.error(format!(
+                "contains more than the limit of {} bytes of junit files",
+                MAX_ARCHIVE_TOTAL_BYTES
+            )));
+        }
+        log::debug!(
+            "Extracted {} ({} bytes)",
+            archive_entry_path(self.archive_path.to_string_lossy(), &name),
+            bytes
+        );
+        self.entries.push(ArchiveEntry {
+            name,
+            extracted_path,
+        });
+        Ok(())
+    }
+
+    fn error(&self, message: String) -> anyhow::Error {
+        anyhow::anyhow!("Archive {:?} {}", self.archive_path, message)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    /// Writes a zip file with deflated entries.
+    pub fn write_zip(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn extracts_xml_entries_from_zip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive_path = temp_dir.path().join("test-results.zip");
+        fs::write(
+            &archive_path,
+            test_utils::write_zip(&[
+                ("nested/junit1.xml", "<testsuites />"),
+                ("README.md", "not junit"),
+                ("junit2.XML", "<testsuites></testsuites>"),
+            ]),
+        )
+        .unwrap();
+        let dest_dir = tempfile::tempdir().unwrap();
+
+        let entries =
+            extract_junit_archive(&archive_path, ArchiveKind::Zip, dest_dir.path()).unwrap();
+
+        assert_eq!(
+            entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+            ["nested/junit1.xml", "junit2.XML"]
+        );
+        assert_eq!(
+            fs::read_to_string(&entries[0].extracted_path).unwrap(),
+            "<testsuites />"
+        );
+        assert_eq!(
+            fs::read_to_string(&entries[1].extracted_path).unwrap(),
+            "<testsuites></testsuites>"
+        );
+    }
+
+    #[test]
+    fn rejects_encrypted_zip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive_path = temp_dir.path().join("test-results.zip");
+        let mut zip = test_utils::write_zip(&[("junit.xml", "<testsuites />")]);
+        // `ZipWriter` cannot encrypt without a password, so set the encrypted flag of the
+        // central directory header by hand.
+        let central_header = zip
+            .windows(4)
+            .position(|window| window == 0x02014b50u32.to_le_bytes())
+            .unwrap();
+        zip[central_header + 8] |= 1;
+        fs::write(&archive_path, zip).unwrap();
+
+        let error = extract_junit_archive(&archive_path, ArchiveKind::Zip, temp_dir.path())
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            error.contains("encrypted zip files are not supported"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn enforces_entry_size_limit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive_path = temp_dir.path().join("test-results.tar");
+        // Only the header is written, the declared size alone must be rejected.
+        let mut header = tar::Header::new_gnu();
+        header.set_path("junit.xml").unwrap();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(MAX_ARCHIVE_ENTRY_BYTES + 1);
+        header.set_cksum();
+        fs::write(&archive_path, header.as_bytes()).unwrap();
+
+        let error = extract_junit_archive(&archive_path, ArchiveKind::Tar, temp_dir.path())
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("is larger than the limit"), "{error}");
+    }
+
+    #[test]
+    fn detects_archive_kind() {
+        assert_eq!(ArchiveKind::from_path("a/b.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::from_path("b.tar"), Some(ArchiveKind::Tar));
+        assert_eq!(ArchiveKind::from_path("b.tar.gz"), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_path("b.TGZ"), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_path("b.xml"), None);
+    }
+}
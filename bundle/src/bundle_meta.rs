@@ -7,19 +7,24 @@ use std::collections::HashMap;
 
 use codeowners::CodeOwners;
 use context::repo::BundleRepo;
+#[cfg(feature = "ruby")]
+use magnus::Module;
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
-use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pymethods};
+use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyclass_enum, gen_stub_pymethods};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "wasm")]
 use tsify_next::Tsify;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use crate::{files::FileSet, CustomTag, Test};
+#[cfg(feature = "ruby")]
+use crate::files::BundledFile;
+use crate::{files::FileSet, CustomTag, QuarantineOutcome, Test};
 
-pub const META_VERSION: &str = "1";
+/// Version 2 bundles may reference a file with `dedup_of` instead of including it
+pub const META_VERSION: &str = "2";
 // 0.5.29 was first version to include bundle_upload_id and serves as the base
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
@@ -61,6 +66,15 @@ impl From<BundleMetaV0_5_34> for BundleMetaV0_5_29 {
 pub struct BundleMetaJunitProps {
     pub num_files: usize,
     pub num_tests: usize,
+    /// Number of junit files found, when they were merged into `num_files` files
+    #[serde(default)]
+    pub num_original_files: Option<usize>,
+    /// Number of files matched by the junit globs that were skipped for not being junit
+    #[serde(default)]
+    pub num_skipped_non_junit_files: usize,
+    /// Number of junit files left out of `num_files` for being byte-identical to another one
+    #[serde(default)]
+    pub num_deduplicated_files: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -122,6 +136,560 @@ pub struct BundleMetaV0_6_3 {
     pub bundle_upload_id_v2: String,
 }
 
+/// How the CLI was invoked, without any secrets or free-form values. Only the names of overridden
+/// values are kept.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaInvocation {
+    pub subcommand: String,
+    pub flags: HashMap<String, String>,
+    pub overrides: Vec<String>,
+    pub junit_globs: Vec<String>,
+    pub num_internal_bin_paths: usize,
+    pub num_tags: usize,
+    /// Whether `repo_url` was overridden with the URL of another repo than the git remote
+    #[serde(default)]
+    pub repo_url_overridden_mismatch: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_4 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+}
+
+/// The host that ran the CLI, to correlate flakes with runner hardware. Every field is best-effort
+/// and missing if it could not be detected.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaHostInfo {
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub arch: Option<String>,
+    pub num_logical_cores: Option<usize>,
+    pub total_memory_bytes: Option<u64>,
+    /// Whether the CLI ran in a container, e.g. Docker or a Kubernetes pod
+    pub in_container: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_5 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+}
+
+/// A raw test runner output, e.g. a BEP file, that was left out of the bundle because of its size.
+/// Its hash still ties the bundle to the original file.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaExcludedFile {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_6 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+    pub excluded_files: Vec<BundleMetaExcludedFile>,
+}
+
+/// The algorithm of the `sha256` checksums recorded on each `BundledFile`
+pub const CHECKSUM_ALGORITHM: &str = "sha256";
+
+/// `repo_metadata_source` of uploads without any git metadata
+pub const REPO_METADATA_SOURCE_NONE: &str = "none";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_7 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+    pub excluded_files: Vec<BundleMetaExcludedFile>,
+    pub checksum_algorithm: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum BundleMetaQuarantineOutcomeKind {
+    #[default]
+    NoFailures,
+    AllQuarantined,
+    SomeUnquarantined,
+    QuarantiningUnavailable,
+}
+
+/// `QuarantineOutcome` with its counts flattened, so that it can be read from the bindings
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaQuarantineOutcome {
+    pub outcome: BundleMetaQuarantineOutcomeKind,
+    pub num_quarantined: usize,
+    pub num_unquarantined: usize,
+    /// Quarantined test IDs that matched no test case of the upload, if the quarantine config was
+    /// fetched
+    #[serde(default)]
+    pub num_unmatched_quarantined_ids: Option<usize>,
+}
+
+impl From<QuarantineOutcome> for BundleMetaQuarantineOutcome {
+    fn from(quarantine_outcome: QuarantineOutcome) -> Self {
+        let (outcome, num_quarantined, num_unquarantined) = match quarantine_outcome {
+            QuarantineOutcome::NoFailures => (BundleMetaQuarantineOutcomeKind::NoFailures, 0, 0),
+            QuarantineOutcome::AllQuarantined { count } => {
+                (BundleMetaQuarantineOutcomeKind::AllQuarantined, count, 0)
+            }
+            QuarantineOutcome::SomeUnquarantined {
+                quarantined,
+                unquarantined,
+            } => (
+                BundleMetaQuarantineOutcomeKind::SomeUnquarantined,
+                quarantined,
+                unquarantined,
+            ),
+            QuarantineOutcome::QuarantiningUnavailable => (
+                BundleMetaQuarantineOutcomeKind::QuarantiningUnavailable,
+                0,
+                0,
+            ),
+        };
+        Self {
+            outcome,
+            num_quarantined,
+            num_unquarantined,
+            num_unmatched_quarantined_ids: None,
+        }
+    }
+}
+
+impl From<BundleMetaQuarantineOutcome> for QuarantineOutcome {
+    fn from(quarantine_outcome: BundleMetaQuarantineOutcome) -> Self {
+        match quarantine_outcome.outcome {
+            BundleMetaQuarantineOutcomeKind::NoFailures => QuarantineOutcome::NoFailures,
+            BundleMetaQuarantineOutcomeKind::AllQuarantined => QuarantineOutcome::AllQuarantined {
+                count: quarantine_outcome.num_quarantined,
+            },
+            BundleMetaQuarantineOutcomeKind::SomeUnquarantined => {
+                QuarantineOutcome::SomeUnquarantined {
+                    quarantined: quarantine_outcome.num_quarantined,
+                    unquarantined: quarantine_outcome.num_unquarantined,
+                }
+            }
+            BundleMetaQuarantineOutcomeKind::QuarantiningUnavailable => {
+                QuarantineOutcome::QuarantiningUnavailable
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_8 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+    pub excluded_files: Vec<BundleMetaExcludedFile>,
+    pub checksum_algorithm: String,
+    pub quarantine_outcome: BundleMetaQuarantineOutcome,
+}
+
+/// A test case `id` shared by so many test cases that it was likely templated wrong
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaDuplicateTestCaseId {
+    pub id: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_9 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+    pub excluded_files: Vec<BundleMetaExcludedFile>,
+    pub checksum_algorithm: String,
+    pub quarantine_outcome: BundleMetaQuarantineOutcome,
+    pub duplicate_test_case_ids: Vec<BundleMetaDuplicateTestCaseId>,
+}
+
+/// A test result file left out of the bundle because it could not be copied into it
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaSkippedFile {
+    pub original_path: String,
+    pub reason: String,
+}
+
+/// A failed test treated as quarantined by the local flake detection of the CLI, from its history
+/// in a local state file. Unlike `quarantined_tests`, it is not quarantined by Trunk.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaLocallyQuarantinedTest {
+    pub id: String,
+    pub name: String,
+    pub parent_name: String,
+    /// Which flaky pattern its history matched
+    pub reason: String,
+}
+
+/// Who quarantined a failed test of `quarantined_tests`
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum BundleMetaQuarantineSource {
+    /// The quarantine config of Trunk
+    #[default]
+    Server,
+    /// The test runner, which marked the test case as quarantined in the junit or internal bin
+    Runner,
+    ServerAndRunner,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaQuarantinedTestSource {
+    pub id: String,
+    pub source: BundleMetaQuarantineSource,
+}
+
+/// Resources used by the test command of `trunk test`, summed over its commands. Only the direct
+/// child is measured, along with the subprocesses it waited for where the platform accounts them to
+/// it, as `wait4` does on unix. Where usage can't be collected, only the wall clock is recorded.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaTestCommandStats {
+    pub wall_time_ms: u64,
+    /// The highest peak RSS of its commands
+    pub peak_rss_bytes: Option<u64>,
+    pub user_cpu_time_ms: Option<u64>,
+    pub system_cpu_time_ms: Option<u64>,
+}
+
+/// The command that converted test result files of a custom format to junit, see `--converter`
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaConverter {
+    pub command: String,
+    /// First line the converter printed for `--version`, unset when it printed nothing or failed
+    pub version: Option<String>,
+    pub conversions: Vec<BundleMetaConversion>,
+}
+
+/// A file the converter was run on. A failed conversion is left out of the bundle.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaConversion {
+    pub input_path: String,
+    /// Where the junit was written, the `original_path` of its bundled file. Unset when the
+    /// conversion failed.
+    pub output_path: Option<String>,
+    pub duration_ms: u64,
+    /// Why the conversion failed, including what the converter wrote to stderr
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_10 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+    pub excluded_files: Vec<BundleMetaExcludedFile>,
+    pub checksum_algorithm: String,
+    pub quarantine_outcome: BundleMetaQuarantineOutcome,
+    pub duplicate_test_case_ids: Vec<BundleMetaDuplicateTestCaseId>,
+    pub skipped_files: Vec<BundleMetaSkippedFile>,
+    /// Variant of every test of the upload, when a single one was given for all of them
+    #[serde(default)]
+    pub variant: Option<String>,
+    /// Variants of the file sets, when they were given per junit glob
+    #[serde(default)]
+    pub variants: Vec<String>,
+    /// Where the repo metadata came from, [`REPO_METADATA_SOURCE_NONE`] for repo-less uploads
+    /// without a sha or branch. Unset when it was read from git or given with the `--repo-*` flags.
+    #[serde(default)]
+    pub repo_metadata_source: Option<String>,
+    /// Failed tests that the experimental local flake detection treated as quarantined
+    #[serde(default)]
+    pub locally_quarantined_tests: Vec<BundleMetaLocallyQuarantinedTest>,
+    /// Which env vars were captured into `envs`, e.g. `ci-only`. Unset for bundles of CLIs that
+    /// always captured the known CI env vars.
+    #[serde(default)]
+    pub env_capture_profile: Option<String>,
+    /// Resources used by the test command, unset for uploads outside of `trunk test`
+    #[serde(default)]
+    pub test_command_stats: Option<BundleMetaTestCommandStats>,
+    /// Who quarantined each of `quarantined_tests`. Empty for bundles of CLIs that only quarantined
+    /// tests of the quarantine config.
+    #[serde(default)]
+    pub quarantined_test_sources: Vec<BundleMetaQuarantinedTestSource>,
+    /// Files the internal bin was sharded into, in order, when it was too large for a single
+    /// `internal.bin`. Their test case runs concatenate into those of the internal bin.
+    #[serde(default)]
+    pub internal_bin_shards: Vec<String>,
+    /// The converter of test result files of a custom format to junit, when one was given
+    #[serde(default)]
+    pub converter: Option<BundleMetaConverter>,
+}
+
+impl From<BundleMetaV0_6_10> for BundleMetaV0_6_9 {
+    fn from(bundle_meta: BundleMetaV0_6_10) -> Self {
+        BundleMetaV0_6_9 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+            host_info: bundle_meta.host_info,
+            excluded_files: bundle_meta.excluded_files,
+            checksum_algorithm: bundle_meta.checksum_algorithm,
+            quarantine_outcome: bundle_meta.quarantine_outcome,
+            duplicate_test_case_ids: bundle_meta.duplicate_test_case_ids,
+        }
+    }
+}
+
+/// Uncommitted changes of the repo working tree the bundle was uploaded from
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaWorkingTree {
+    /// Whether tracked files have uncommitted changes, unset when the working tree status could not
+    /// be read
+    pub dirty: Option<bool>,
+    /// Number of tracked files with uncommitted changes, read along with `dirty`
+    pub num_modified_files: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub struct BundleMetaV0_6_11 {
+    #[serde(flatten)]
+    pub base_props: BundleMetaBaseProps,
+    #[serde(flatten)]
+    pub junit_props: BundleMetaJunitProps,
+    #[serde(flatten)]
+    pub debug_props: BundleMetaDebugProps,
+    pub bundle_upload_id_v2: String,
+    pub invocation: BundleMetaInvocation,
+    pub host_info: BundleMetaHostInfo,
+    pub excluded_files: Vec<BundleMetaExcludedFile>,
+    pub checksum_algorithm: String,
+    pub quarantine_outcome: BundleMetaQuarantineOutcome,
+    pub duplicate_test_case_ids: Vec<BundleMetaDuplicateTestCaseId>,
+    pub skipped_files: Vec<BundleMetaSkippedFile>,
+    /// Variant of every test of the upload, when a single one was given for all of them
+    #[serde(default)]
+    pub variant: Option<String>,
+    /// Variants of the file sets, when they were given per junit glob
+    #[serde(default)]
+    pub variants: Vec<String>,
+    /// Where the repo metadata came from, [`REPO_METADATA_SOURCE_NONE`] for repo-less uploads
+    /// without a sha or branch. Unset when it was read from git or given with the `--repo-*` flags.
+    #[serde(default)]
+    pub repo_metadata_source: Option<String>,
+    /// Failed tests that the experimental local flake detection treated as quarantined
+    #[serde(default)]
+    pub locally_quarantined_tests: Vec<BundleMetaLocallyQuarantinedTest>,
+    /// Which env vars were captured into `envs`, e.g. `ci-only`. Unset for bundles of CLIs that
+    /// always captured the known CI env vars.
+    #[serde(default)]
+    pub env_capture_profile: Option<String>,
+    /// Resources used by the test command, unset for uploads outside of `trunk test`
+    #[serde(default)]
+    pub test_command_stats: Option<BundleMetaTestCommandStats>,
+    /// Who quarantined each of `quarantined_tests`. Empty for bundles of CLIs that only quarantined
+    /// tests of the quarantine config.
+    #[serde(default)]
+    pub quarantined_test_sources: Vec<BundleMetaQuarantinedTestSource>,
+    /// Files the internal bin was sharded into, in order, when it was too large for a single
+    /// `internal.bin`. Their test case runs concatenate into those of the internal bin.
+    #[serde(default)]
+    pub internal_bin_shards: Vec<String>,
+    /// The converter of test result files of a custom format to junit, when one was given
+    #[serde(default)]
+    pub converter: Option<BundleMetaConverter>,
+    pub working_tree: BundleMetaWorkingTree,
+}
+
+impl From<BundleMetaV0_6_11> for BundleMetaV0_6_10 {
+    fn from(bundle_meta: BundleMetaV0_6_11) -> Self {
+        BundleMetaV0_6_10 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+            host_info: bundle_meta.host_info,
+            excluded_files: bundle_meta.excluded_files,
+            checksum_algorithm: bundle_meta.checksum_algorithm,
+            quarantine_outcome: bundle_meta.quarantine_outcome,
+            duplicate_test_case_ids: bundle_meta.duplicate_test_case_ids,
+            skipped_files: bundle_meta.skipped_files,
+            variant: bundle_meta.variant,
+            variants: bundle_meta.variants,
+            repo_metadata_source: bundle_meta.repo_metadata_source,
+            locally_quarantined_tests: bundle_meta.locally_quarantined_tests,
+            env_capture_profile: bundle_meta.env_capture_profile,
+            test_command_stats: bundle_meta.test_command_stats,
+            quarantined_test_sources: bundle_meta.quarantined_test_sources,
+            internal_bin_shards: bundle_meta.internal_bin_shards,
+            converter: bundle_meta.converter,
+        }
+    }
+}
+
+impl From<BundleMetaV0_6_9> for BundleMetaV0_6_8 {
+    fn from(bundle_meta: BundleMetaV0_6_9) -> Self {
+        BundleMetaV0_6_8 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+            host_info: bundle_meta.host_info,
+            excluded_files: bundle_meta.excluded_files,
+            checksum_algorithm: bundle_meta.checksum_algorithm,
+            quarantine_outcome: bundle_meta.quarantine_outcome,
+        }
+    }
+}
+
+impl From<BundleMetaV0_6_8> for BundleMetaV0_6_7 {
+    fn from(bundle_meta: BundleMetaV0_6_8) -> Self {
+        BundleMetaV0_6_7 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+            host_info: bundle_meta.host_info,
+            excluded_files: bundle_meta.excluded_files,
+            checksum_algorithm: bundle_meta.checksum_algorithm,
+        }
+    }
+}
+
+impl From<BundleMetaV0_6_7> for BundleMetaV0_6_6 {
+    fn from(bundle_meta: BundleMetaV0_6_7) -> Self {
+        BundleMetaV0_6_6 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+            host_info: bundle_meta.host_info,
+            excluded_files: bundle_meta.excluded_files,
+        }
+    }
+}
+
+impl From<BundleMetaV0_6_6> for BundleMetaV0_6_5 {
+    fn from(bundle_meta: BundleMetaV0_6_6) -> Self {
+        BundleMetaV0_6_5 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+            host_info: bundle_meta.host_info,
+        }
+    }
+}
+
+impl From<BundleMetaV0_6_5> for BundleMetaV0_6_4 {
+    fn from(bundle_meta: BundleMetaV0_6_5) -> Self {
+        BundleMetaV0_6_4 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+            invocation: bundle_meta.invocation,
+        }
+    }
+}
+
+impl From<BundleMetaV0_6_4> for BundleMetaV0_6_3 {
+    fn from(bundle_meta: BundleMetaV0_6_4) -> Self {
+        BundleMetaV0_6_3 {
+            base_props: bundle_meta.base_props,
+            junit_props: bundle_meta.junit_props,
+            debug_props: bundle_meta.debug_props,
+            bundle_upload_id_v2: bundle_meta.bundle_upload_id_v2,
+        }
+    }
+}
+
 impl From<BundleMetaV0_6_3> for BundleMetaV0_6_2 {
     fn from(bundle_meta: BundleMetaV0_6_3) -> Self {
         BundleMetaV0_6_2 {
@@ -153,17 +721,58 @@ impl From<BundleMetaV0_6_3> for BundleMetaV0_5_29 {
 #[cfg_attr(feature = "wasm", derive(Tsify))]
 #[cfg_attr(feature = "wasm", tsify(into_wasm_abi, from_wasm_abi))]
 #[serde(tag = "schema")]
+// bundles are read one at a time, so boxing the latest version isn't worth the churn
+#[allow(clippy::large_enum_variant)]
 pub enum VersionedBundle {
     V0_5_29(BundleMetaV0_5_29),
     V0_5_34(BundleMetaV0_5_34),
     V0_6_2(BundleMetaV0_6_2),
     V0_6_3(BundleMetaV0_6_3),
+    V0_6_4(BundleMetaV0_6_4),
+    V0_6_5(BundleMetaV0_6_5),
+    V0_6_6(BundleMetaV0_6_6),
+    V0_6_7(BundleMetaV0_6_7),
+    V0_6_8(BundleMetaV0_6_8),
+    V0_6_9(BundleMetaV0_6_9),
+    V0_6_10(BundleMetaV0_6_10),
+    V0_6_11(BundleMetaV0_6_11),
+}
+
+impl VersionedBundle {
+    pub fn base_props(&self) -> &BundleMetaBaseProps {
+        match self {
+            VersionedBundle::V0_5_29(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_5_34(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_2(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_3(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_4(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_5(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_6(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_7(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_8(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_9(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_10(bundle_meta) => &bundle_meta.base_props,
+            VersionedBundle::V0_6_11(bundle_meta) => &bundle_meta.base_props,
+        }
+    }
+
+    /// Files the internal bin was sharded into, empty when it is a single `internal.bin`
+    pub fn internal_bin_shards(&self) -> &[String] {
+        match self {
+            VersionedBundle::V0_6_10(bundle_meta) => &bundle_meta.internal_bin_shards,
+            VersionedBundle::V0_6_11(bundle_meta) => &bundle_meta.internal_bin_shards,
+            _ => &[],
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
-#[cfg(feature = "pyo3")]
-#[gen_stub_pyclass]
-#[pyclass]
+#[cfg(any(feature = "pyo3", feature = "ruby"))]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass)]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "VersionedBundle", free_immediately, size)
+)]
 pub struct BindingsVersionedBundle(pub VersionedBundle);
 
 #[cfg(feature = "pyo3")]
@@ -172,6 +781,54 @@ pub struct BindingsVersionedBundle(pub VersionedBundle);
 impl BindingsVersionedBundle {
     pub fn get_v0_5_29(&self) -> BundleMetaV0_5_29 {
         match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => {
+                BundleMetaV0_5_29::from(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                        BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(
+                            BundleMetaV0_6_10::from(bundle_meta.clone()),
+                        ))),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_10(bundle_meta) => BundleMetaV0_5_29::from(
+                BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(
+                    BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(
+                        BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(bundle_meta.clone())),
+                    ))),
+                ))),
+            ),
+            VersionedBundle::V0_6_9(bundle_meta) => {
+                BundleMetaV0_5_29::from(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                        BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(bundle_meta.clone())),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_8(bundle_meta) => {
+                BundleMetaV0_5_29::from(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                        BundleMetaV0_6_7::from(bundle_meta.clone()),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_7(bundle_meta) => BundleMetaV0_5_29::from(
+                BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(
+                    BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(bundle_meta.clone())),
+                ))),
+            ),
+            VersionedBundle::V0_6_6(bundle_meta) => {
+                BundleMetaV0_5_29::from(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_5(bundle_meta) => {
+                BundleMetaV0_5_29::from(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(bundle_meta.clone()),
+                )))
+            }
+            VersionedBundle::V0_6_4(bundle_meta) => BundleMetaV0_5_29::from(
+                BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(bundle_meta.clone())),
+            ),
             VersionedBundle::V0_6_3(bundle_meta) => {
                 BundleMetaV0_5_29::from(BundleMetaV0_6_2::from(bundle_meta.clone()))
             }
@@ -184,6 +841,48 @@ impl BindingsVersionedBundle {
     }
     pub fn get_v0_5_34(&self) -> Option<BundleMetaV0_5_34> {
         match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_5_34::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                        BundleMetaV0_6_9::from(BundleMetaV0_6_10::from(bundle_meta.clone())),
+                    ))),
+                ))),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => Some(BundleMetaV0_5_34::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                        BundleMetaV0_6_9::from(bundle_meta.clone()),
+                    ))),
+                ))),
+            )),
+            VersionedBundle::V0_6_9(bundle_meta) => {
+                Some(BundleMetaV0_5_34::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                        BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(bundle_meta.clone())),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_8(bundle_meta) => Some(BundleMetaV0_5_34::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(bundle_meta.clone())),
+                ))),
+            )),
+            VersionedBundle::V0_6_7(bundle_meta) => Some(BundleMetaV0_5_34::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(bundle_meta.clone()),
+                ))),
+            )),
+            VersionedBundle::V0_6_6(bundle_meta) => {
+                Some(BundleMetaV0_5_34::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_5(bundle_meta) => Some(BundleMetaV0_5_34::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_4(bundle_meta) => Some(BundleMetaV0_5_34::from(
+                BundleMetaV0_6_3::from(bundle_meta.clone()),
+            )),
             VersionedBundle::V0_6_3(bundle_meta) => {
                 Some(BundleMetaV0_5_34::from(bundle_meta.clone()))
             }
@@ -196,6 +895,48 @@ impl BindingsVersionedBundle {
     }
     pub fn get_v0_6_2(&self) -> Option<BundleMetaV0_6_2> {
         match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_6_2::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                        BundleMetaV0_6_9::from(BundleMetaV0_6_10::from(bundle_meta.clone())),
+                    ))),
+                ))),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => Some(BundleMetaV0_6_2::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                        BundleMetaV0_6_9::from(bundle_meta.clone()),
+                    ))),
+                ))),
+            )),
+            VersionedBundle::V0_6_9(bundle_meta) => {
+                Some(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                        BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(bundle_meta.clone())),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_8(bundle_meta) => Some(BundleMetaV0_6_2::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(bundle_meta.clone())),
+                ))),
+            )),
+            VersionedBundle::V0_6_7(bundle_meta) => Some(BundleMetaV0_6_2::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(bundle_meta.clone()),
+                ))),
+            )),
+            VersionedBundle::V0_6_6(bundle_meta) => {
+                Some(BundleMetaV0_6_2::from(BundleMetaV0_6_3::from(
+                    BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_5(bundle_meta) => Some(BundleMetaV0_6_2::from(
+                BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_4(bundle_meta) => Some(BundleMetaV0_6_2::from(
+                BundleMetaV0_6_3::from(bundle_meta.clone()),
+            )),
             VersionedBundle::V0_6_3(bundle_meta) => {
                 Some(BundleMetaV0_6_2::from(bundle_meta.clone()))
             }
@@ -205,11 +946,331 @@ impl BindingsVersionedBundle {
     }
     pub fn get_v0_6_3(&self) -> Option<BundleMetaV0_6_3> {
         match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_6_3::from(
+                BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                    BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(
+                        BundleMetaV0_6_10::from(bundle_meta.clone()),
+                    ))),
+                ))),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => {
+                Some(BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(
+                    BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(
+                        BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(bundle_meta.clone())),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_9(bundle_meta) => Some(BundleMetaV0_6_3::from(
+                BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                    BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(bundle_meta.clone())),
+                ))),
+            )),
+            VersionedBundle::V0_6_8(bundle_meta) => Some(BundleMetaV0_6_3::from(
+                BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                    BundleMetaV0_6_7::from(bundle_meta.clone()),
+                ))),
+            )),
+            VersionedBundle::V0_6_7(bundle_meta) => {
+                Some(BundleMetaV0_6_3::from(BundleMetaV0_6_4::from(
+                    BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_6(bundle_meta) => Some(BundleMetaV0_6_3::from(
+                BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_5(bundle_meta) => Some(BundleMetaV0_6_3::from(
+                BundleMetaV0_6_4::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_4(bundle_meta) => {
+                Some(BundleMetaV0_6_3::from(bundle_meta.clone()))
+            }
             VersionedBundle::V0_6_3(bundle_meta) => Some(bundle_meta.clone()),
             _ => None,
         }
     }
+    pub fn get_v0_6_4(&self) -> Option<BundleMetaV0_6_4> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => {
+                Some(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                        BundleMetaV0_6_9::from(BundleMetaV0_6_10::from(bundle_meta.clone())),
+                    ))),
+                )))
+            }
+            VersionedBundle::V0_6_10(bundle_meta) => Some(BundleMetaV0_6_4::from(
+                BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(
+                    BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(bundle_meta.clone())),
+                ))),
+            )),
+            VersionedBundle::V0_6_9(bundle_meta) => Some(BundleMetaV0_6_4::from(
+                BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(
+                    BundleMetaV0_6_8::from(bundle_meta.clone()),
+                ))),
+            )),
+            VersionedBundle::V0_6_8(bundle_meta) => {
+                Some(BundleMetaV0_6_4::from(BundleMetaV0_6_5::from(
+                    BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_7(bundle_meta) => Some(BundleMetaV0_6_4::from(
+                BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_6(bundle_meta) => Some(BundleMetaV0_6_4::from(
+                BundleMetaV0_6_5::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_5(bundle_meta) => {
+                Some(BundleMetaV0_6_4::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_4(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_5(&self) -> Option<BundleMetaV0_6_5> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_6_5::from(
+                BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                    BundleMetaV0_6_9::from(BundleMetaV0_6_10::from(bundle_meta.clone())),
+                ))),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => Some(BundleMetaV0_6_5::from(
+                BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                    BundleMetaV0_6_9::from(bundle_meta.clone()),
+                ))),
+            )),
+            VersionedBundle::V0_6_9(bundle_meta) => {
+                Some(BundleMetaV0_6_5::from(BundleMetaV0_6_6::from(
+                    BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_8(bundle_meta) => Some(BundleMetaV0_6_5::from(
+                BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_7(bundle_meta) => Some(BundleMetaV0_6_5::from(
+                BundleMetaV0_6_6::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_6(bundle_meta) => {
+                Some(BundleMetaV0_6_5::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_5(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_6(&self) -> Option<BundleMetaV0_6_6> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_6_6::from(
+                BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(
+                    BundleMetaV0_6_10::from(bundle_meta.clone()),
+                ))),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => {
+                Some(BundleMetaV0_6_6::from(BundleMetaV0_6_7::from(
+                    BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_9(bundle_meta) => Some(BundleMetaV0_6_6::from(
+                BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_8(bundle_meta) => Some(BundleMetaV0_6_6::from(
+                BundleMetaV0_6_7::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_7(bundle_meta) => {
+                Some(BundleMetaV0_6_6::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_6(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_7(&self) -> Option<BundleMetaV0_6_7> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => {
+                Some(BundleMetaV0_6_7::from(BundleMetaV0_6_8::from(
+                    BundleMetaV0_6_9::from(BundleMetaV0_6_10::from(bundle_meta.clone())),
+                )))
+            }
+            VersionedBundle::V0_6_10(bundle_meta) => Some(BundleMetaV0_6_7::from(
+                BundleMetaV0_6_8::from(BundleMetaV0_6_9::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_9(bundle_meta) => Some(BundleMetaV0_6_7::from(
+                BundleMetaV0_6_8::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_8(bundle_meta) => {
+                Some(BundleMetaV0_6_7::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_7(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_8(&self) -> Option<BundleMetaV0_6_8> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_6_8::from(
+                BundleMetaV0_6_9::from(BundleMetaV0_6_10::from(bundle_meta.clone())),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => Some(BundleMetaV0_6_8::from(
+                BundleMetaV0_6_9::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_9(bundle_meta) => {
+                Some(BundleMetaV0_6_8::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_8(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_9(&self) -> Option<BundleMetaV0_6_9> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(BundleMetaV0_6_9::from(
+                BundleMetaV0_6_10::from(bundle_meta.clone()),
+            )),
+            VersionedBundle::V0_6_10(bundle_meta) => {
+                Some(BundleMetaV0_6_9::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_9(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_10(&self) -> Option<BundleMetaV0_6_10> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => {
+                Some(BundleMetaV0_6_10::from(bundle_meta.clone()))
+            }
+            VersionedBundle::V0_6_10(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+    pub fn get_v0_6_11(&self) -> Option<BundleMetaV0_6_11> {
+        match &self.0 {
+            VersionedBundle::V0_6_11(bundle_meta) => Some(bundle_meta.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "ruby")]
+impl BindingsVersionedBundle {
+    /// The schema of the meta.json, e.g. `V0_6_11`
+    pub fn schema(&self) -> String {
+        String::from(match &self.0 {
+            VersionedBundle::V0_5_29(..) => "V0_5_29",
+            VersionedBundle::V0_5_34(..) => "V0_5_34",
+            VersionedBundle::V0_6_2(..) => "V0_6_2",
+            VersionedBundle::V0_6_3(..) => "V0_6_3",
+            VersionedBundle::V0_6_4(..) => "V0_6_4",
+            VersionedBundle::V0_6_5(..) => "V0_6_5",
+            VersionedBundle::V0_6_6(..) => "V0_6_6",
+            VersionedBundle::V0_6_7(..) => "V0_6_7",
+            VersionedBundle::V0_6_8(..) => "V0_6_8",
+            VersionedBundle::V0_6_9(..) => "V0_6_9",
+            VersionedBundle::V0_6_10(..) => "V0_6_10",
+            VersionedBundle::V0_6_11(..) => "V0_6_11",
+        })
+    }
+    pub fn version(&self) -> String {
+        self.0.base_props().version.clone()
+    }
+    pub fn cli_version(&self) -> String {
+        self.0.base_props().cli_version.clone()
+    }
+    pub fn org(&self) -> String {
+        self.0.base_props().org.clone()
+    }
+    pub fn repo(&self) -> BundleRepo {
+        self.0.base_props().repo.clone()
+    }
+    pub fn bundle_upload_id(&self) -> String {
+        self.0.base_props().bundle_upload_id.clone()
+    }
+    pub fn file_sets(&self) -> Vec<FileSet> {
+        self.0.base_props().file_sets.clone()
+    }
+    pub fn envs(&self) -> HashMap<String, String> {
+        self.0.base_props().envs.clone()
+    }
+    pub fn upload_time_epoch(&self) -> u64 {
+        self.0.base_props().upload_time_epoch
+    }
+    pub fn test_command(&self) -> Option<String> {
+        self.0.base_props().test_command.clone()
+    }
+    /// Variant of every test of the upload, which older schemas don't have
+    pub fn variant(&self) -> Option<String> {
+        match &self.0 {
+            VersionedBundle::V0_6_10(bundle_meta) => bundle_meta.variant.clone(),
+            VersionedBundle::V0_6_11(bundle_meta) => bundle_meta.variant.clone(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "ruby")]
+pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let versioned_bundle = ruby.define_class("VersionedBundle", ruby.class_object())?;
+    versioned_bundle.define_method(
+        "schema",
+        magnus::method!(BindingsVersionedBundle::schema, 0),
+    )?;
+    versioned_bundle.define_method(
+        "version",
+        magnus::method!(BindingsVersionedBundle::version, 0),
+    )?;
+    versioned_bundle.define_method(
+        "cli_version",
+        magnus::method!(BindingsVersionedBundle::cli_version, 0),
+    )?;
+    versioned_bundle.define_method("org", magnus::method!(BindingsVersionedBundle::org, 0))?;
+    versioned_bundle.define_method("repo", magnus::method!(BindingsVersionedBundle::repo, 0))?;
+    versioned_bundle.define_method(
+        "bundle_upload_id",
+        magnus::method!(BindingsVersionedBundle::bundle_upload_id, 0),
+    )?;
+    versioned_bundle.define_method(
+        "file_sets",
+        magnus::method!(BindingsVersionedBundle::file_sets, 0),
+    )?;
+    versioned_bundle.define_method("envs", magnus::method!(BindingsVersionedBundle::envs, 0))?;
+    versioned_bundle.define_method(
+        "upload_time_epoch",
+        magnus::method!(BindingsVersionedBundle::upload_time_epoch, 0),
+    )?;
+    versioned_bundle.define_method(
+        "test_command",
+        magnus::method!(BindingsVersionedBundle::test_command, 0),
+    )?;
+    versioned_bundle.define_method(
+        "variant",
+        magnus::method!(BindingsVersionedBundle::variant, 0),
+    )?;
+    let file_set = ruby.define_class("FileSet", ruby.class_object())?;
+    file_set.define_method("file_set_type", magnus::method!(FileSet::file_set_type, 0))?;
+    file_set.define_method("files", magnus::method!(FileSet::files, 0))?;
+    file_set.define_method("glob", magnus::method!(FileSet::glob, 0))?;
+    file_set.define_method(
+        "resolved_status",
+        magnus::method!(FileSet::resolved_status, 0),
+    )?;
+    file_set.define_method("variant", magnus::method!(FileSet::variant, 0))?;
+    let bundled_file = ruby.define_class("BundledFile", ruby.class_object())?;
+    bundled_file.define_method(
+        "original_path",
+        magnus::method!(BundledFile::original_path, 0),
+    )?;
+    bundled_file.define_method(
+        "original_path_rel",
+        magnus::method!(BundledFile::original_path_rel, 0),
+    )?;
+    bundled_file.define_method("path", magnus::method!(BundledFile::path, 0))?;
+    bundled_file.define_method("owners", magnus::method!(BundledFile::owners, 0))?;
+    bundled_file.define_method("team", magnus::method!(BundledFile::team, 0))?;
+    bundled_file.define_method("sha256", magnus::method!(BundledFile::sha256, 0))?;
+    bundled_file.define_method("dedup_of", magnus::method!(BundledFile::dedup_of, 0))?;
+    bundled_file.define_method(
+        "junit_sanitized",
+        magnus::method!(BundledFile::junit_sanitized, 0),
+    )?;
+    bundled_file.define_method(
+        "junit_sanitized_size_delta",
+        magnus::method!(BundledFile::junit_sanitized_size_delta, 0),
+    )?;
+    Ok(())
 }
 
 /// Signifies the latest BundleMeta version
-pub type BundleMeta = BundleMetaV0_6_3;
+pub type BundleMeta = BundleMetaV0_6_11;
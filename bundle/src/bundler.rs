@@ -1,14 +1,21 @@
 use std::{
+    collections::HashMap,
+    fmt,
     fs::File,
-    io::{Seek, Write},
-    path::PathBuf,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
 
+use anyhow::Context;
 use async_compression::futures::bufread::ZstdDecoder;
 use async_std::{io::ReadExt, stream::StreamExt};
 use async_tar_wasm::Archive;
 use codeowners::CodeOwners;
-use context::bazel_bep::parser::BepParseResult;
+use context::{
+    bazel_bep::parser::BepParseResult,
+    junit::sanitize::{sanitize_junit_xml, JunitSanitization},
+};
 use futures_io::AsyncBufRead;
 use tempfile::TempDir;
 #[cfg(feature = "wasm")]
@@ -16,7 +23,10 @@ use tsify_next::Tsify;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use crate::bundle_meta::{BundleMeta, VersionedBundle};
+use crate::{
+    bundle_meta::{BundleMeta, BundleMetaSkippedFile, VersionedBundle},
+    tar_entry::{check_tar_entry_name, tar_entry_name},
+};
 
 /// Utility type for packing files into tarball.
 ///
@@ -24,51 +34,253 @@ use crate::bundle_meta::{BundleMeta, VersionedBundle};
 pub struct BundlerUtil {
     meta: BundleMeta,
     bep_result: Option<BepParseResult>,
+    internal_bin_shards: Vec<Vec<u8>>,
+    xcresult_summary: Option<Vec<u8>>,
+    sanitize_junit: bool,
+    truncate_junit_text: bool,
+    num_tests_by_path: HashMap<String, usize>,
 }
 
 const META_FILENAME: &'static str = "meta.json";
+const INTERNAL_BIN_FILENAME: &str = "internal.bin";
+const XCRESULT_SUMMARY_FILENAME: &str = "xcresult_summary.json";
+const COPY_BUFFER_LEN: usize = 64 * 1024;
+
+fn internal_bin_shard_filename(index: usize) -> String {
+    format!("internal_{}.bin", index)
+}
 
 impl BundlerUtil {
     const ZSTD_COMPRESSION_LEVEL: i32 = 15; // This gives roughly 10x compression for text, 22 gives 11x.
 
     pub fn new(meta: BundleMeta, bep_result: Option<BepParseResult>) -> Self {
-        Self { meta, bep_result }
+        Self {
+            meta,
+            bep_result,
+            internal_bin_shards: Vec::new(),
+            xcresult_summary: None,
+            sanitize_junit: false,
+            truncate_junit_text: true,
+            num_tests_by_path: HashMap::new(),
+        }
+    }
+
+    /// Adds an encoded internal bin, a serialized `TestResult` proto, to the tarball.
+    ///
+    pub fn with_internal_bin(mut self, internal_bin: Option<Vec<u8>>) -> Self {
+        self.internal_bin_shards = internal_bin.into_iter().collect();
+        self
+    }
+
+    /// Adds an internal bin encoded as shards, serialized `TestResult` protos whose test case runs
+    /// concatenate into those of the internal bin. A single shard is added as `internal.bin`, while
+    /// several are added as `internal_0.bin`, `internal_1.bin` etc., listed in order in the meta.
+    ///
+    pub fn with_internal_bin_shards(mut self, internal_bin_shards: Vec<Vec<u8>>) -> Self {
+        self.internal_bin_shards = internal_bin_shards;
+        self
+    }
+
+    /// Adds a JSON summary of the xcresult bundle the test results were generated from to the
+    /// tarball.
+    ///
+    pub fn with_xcresult_summary(mut self, xcresult_summary: Option<Vec<u8>>) -> Self {
+        self.xcresult_summary = xcresult_summary;
+        self
+    }
+
+    /// Writes the junit files to the tarball through [`sanitize_junit_xml`] instead of copying
+    /// them, recording in the meta whether that changed them.
+    ///
+    pub fn with_sanitize_junit(mut self, sanitize_junit: bool) -> Self {
+        self.sanitize_junit = sanitize_junit;
+        self
+    }
+
+    /// Whether sanitizing the junit files also truncates their texts and attribute values to the
+    /// length the parser keeps, which it does by default.
+    ///
+    pub fn with_truncate_junit_text(mut self, truncate_junit_text: bool) -> Self {
+        self.truncate_junit_text = truncate_junit_text;
+        self
+    }
+
+    /// The number of tests of each bundled file by its `path`, which are taken out of `num_tests`
+    /// when the file is left out of the bundle.
+    ///
+    pub fn with_num_tests_by_path(mut self, num_tests_by_path: HashMap<String, usize>) -> Self {
+        self.num_tests_by_path = num_tests_by_path;
+        self
     }
 
-    /// Writes compressed tarball to disk.
+    /// Writes compressed tarball to disk, recording the checksum of each bundled file in the meta.
     ///
-    pub fn make_tarball(&self, bundle_path: &PathBuf) -> anyhow::Result<()> {
+    pub fn make_tarball(&mut self, bundle_path: &PathBuf) -> anyhow::Result<()> {
         let mut total_bytes_in: u64 = 0;
 
+        // A name that could be extracted outside of the bundle's directory fails the whole bundle
+        for bundled_file in self
+            .meta
+            .base_props
+            .file_sets
+            .iter()
+            .flat_map(|file_set| &file_set.files)
+            .filter(|bundled_file| bundled_file.dedup_of.is_none())
+        {
+            check_tar_entry_name(&bundled_file.path)?;
+        }
+
+        // meta.json has to be the first entry of the tarball, so the bundled files are copied
+        // aside first, recording the checksum of the exact bytes that end up in the tarball. A file
+        // that can't be read is left out and recorded as skipped, rather than failing the bundle.
+        let bundled_files_temp_dir = tempfile::tempdir()?;
+        let mut hashing_duration = Duration::ZERO;
+        let mut bundled_files_temp_paths = Vec::new();
+        let mut num_files = 0;
+        let mut num_skipped_tests = 0;
+        for file_set in self.meta.base_props.file_sets.iter_mut() {
+            let mut skipped_paths = Vec::new();
+            for bundled_file in file_set.files.iter_mut() {
+                if bundled_file.dedup_of.is_some() {
+                    continue;
+                }
+                num_files += 1;
+                let temp_path = bundled_files_temp_dir.path().join(num_files.to_string());
+                let copied = if self.sanitize_junit && bundled_file.path.starts_with("junit/") {
+                    sanitize_hashing(
+                        bundled_file.read_path(),
+                        &temp_path,
+                        self.truncate_junit_text,
+                    )
+                } else {
+                    copy_hashing(bundled_file.read_path(), &temp_path).map(|copied| (copied, None))
+                };
+                match copied {
+                    Ok(((bytes_copied, sha256, duration), sanitization)) => {
+                        total_bytes_in += bytes_copied;
+                        hashing_duration += duration;
+                        bundled_file.sha256 = Some(sha256);
+                        if let Some(sanitization) = sanitization {
+                            bundled_file.junit_sanitized = Some(sanitization.changed());
+                            bundled_file.junit_sanitized_size_delta =
+                                sanitization.changed().then(|| sanitization.size_delta());
+                        }
+                        bundled_files_temp_paths.push((bundled_file.path.clone(), temp_path));
+                    }
+                    Err(e) if e.is::<UnreadableFile>() => {
+                        log::warn!(
+                            "Leaving {} out of the bundle: {:#}",
+                            bundled_file.get_print_path(),
+                            e
+                        );
+                        num_skipped_tests += self
+                            .num_tests_by_path
+                            .get(&bundled_file.path)
+                            .copied()
+                            .unwrap_or_default();
+                        skipped_paths.push(bundled_file.path.clone());
+                        self.meta.skipped_files.push(BundleMetaSkippedFile {
+                            original_path: bundled_file.original_path.clone(),
+                            reason: format!("{:#}", e),
+                        });
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            file_set
+                .files
+                .retain(|bundled_file| !skipped_paths.contains(&bundled_file.path));
+        }
+        // Copies of a file, which are not part of `num_files`, share its checksum and
+        // sanitization, unless it was left out, in which case so are they
+        let num_skipped_files = self.meta.skipped_files.len();
+        let bundled_originals: HashMap<String, (String, Option<bool>, Option<i64>)> = self
+            .meta
+            .base_props
+            .file_sets
+            .iter()
+            .flat_map(|file_set| &file_set.files)
+            .filter_map(|bundled_file| {
+                bundled_file.sha256.clone().map(|sha256| {
+                    (
+                        bundled_file.path.clone(),
+                        (
+                            sha256,
+                            bundled_file.junit_sanitized,
+                            bundled_file.junit_sanitized_size_delta,
+                        ),
+                    )
+                })
+            })
+            .collect();
+        for file_set in self.meta.base_props.file_sets.iter_mut() {
+            file_set.files.retain_mut(|bundled_file| {
+                let Some(dedup_of) = bundled_file.dedup_of.as_ref() else {
+                    return true;
+                };
+                match bundled_originals.get(dedup_of) {
+                    Some((sha256, junit_sanitized, junit_sanitized_size_delta)) => {
+                        bundled_file.sha256 = Some(sha256.clone());
+                        bundled_file.junit_sanitized = *junit_sanitized;
+                        bundled_file.junit_sanitized_size_delta = *junit_sanitized_size_delta;
+                        true
+                    }
+                    None => {
+                        self.meta.skipped_files.push(BundleMetaSkippedFile {
+                            original_path: bundled_file.original_path.clone(),
+                            reason: format!("identical to {}, which was left out", dedup_of),
+                        });
+                        false
+                    }
+                }
+            });
+        }
+        if num_files > 0 && bundled_files_temp_paths.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to bundle any of the {} test result files: {}",
+                num_files,
+                self.meta
+                    .skipped_files
+                    .iter()
+                    .map(|skipped_file| format!(
+                        "{} ({})",
+                        skipped_file.original_path, skipped_file.reason
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        self.meta.junit_props.num_files = self
+            .meta
+            .junit_props
+            .num_files
+            .saturating_sub(num_skipped_files);
+        self.meta.junit_props.num_tests = self
+            .meta
+            .junit_props
+            .num_tests
+            .saturating_sub(num_skipped_tests);
+
         let tar_file = File::create(bundle_path)?;
         let zstd_encoder = zstd::Encoder::new(tar_file, Self::ZSTD_COMPRESSION_LEVEL)?;
         let mut tar = tar::Builder::new(zstd_encoder);
 
+        self.meta.internal_bin_shards = if self.internal_bin_shards.len() > 1 {
+            (0..self.internal_bin_shards.len())
+                .map(internal_bin_shard_filename)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         // Serialize meta and add it to the tarball.
-        {
-            let meta_json_bytes = serde_json::to_vec(&self.meta)?;
-            total_bytes_in += meta_json_bytes.len() as u64;
-            let mut meta_temp = tempfile::tempfile()?;
-            meta_temp.write_all(&meta_json_bytes)?;
-            meta_temp.seek(std::io::SeekFrom::Start(0))?;
-            tar.append_file(META_FILENAME, &mut meta_temp)?;
-        }
+        total_bytes_in += append_meta_json(&mut tar, &self.meta)?;
 
         // Add all files to the tarball.
-        self.meta
-            .base_props
-            .file_sets
-            .iter()
-            .try_for_each(|file_set| {
-                file_set.files.iter().try_for_each(|bundled_file| {
-                    let path = std::path::Path::new(&bundled_file.original_path);
-                    let mut file = File::open(path)?;
-                    tar.append_file(&bundled_file.path, &mut file)?;
-                    total_bytes_in += std::fs::metadata(path)?.len();
-                    Ok::<(), anyhow::Error>(())
-                })?;
-                Ok::<(), anyhow::Error>(())
-            })?;
+        for (path, temp_path) in bundled_files_temp_paths {
+            let mut file = File::open(temp_path)?;
+            tar.append_file(path, &mut file)?;
+        }
 
         if let Some(CodeOwners { ref path, .. }) = self.meta.base_props.codeowners {
             let mut file = File::open(path)?;
@@ -93,6 +305,29 @@ impl BundlerUtil {
             total_bytes_in += bep_events_file.seek(std::io::SeekFrom::End(0))?;
         }
 
+        let internal_bin_filename = [String::from(INTERNAL_BIN_FILENAME)];
+        let internal_bin_filenames = if self.meta.internal_bin_shards.is_empty() {
+            &internal_bin_filename
+        } else {
+            self.meta.internal_bin_shards.as_slice()
+        };
+        for (internal_bin, filename) in self.internal_bin_shards.iter().zip(internal_bin_filenames)
+        {
+            total_bytes_in += internal_bin.len() as u64;
+            let mut internal_bin_temp = tempfile::tempfile()?;
+            internal_bin_temp.write_all(internal_bin)?;
+            internal_bin_temp.seek(std::io::SeekFrom::Start(0))?;
+            tar.append_file(filename, &mut internal_bin_temp)?;
+        }
+
+        if let Some(xcresult_summary) = self.xcresult_summary.as_ref() {
+            total_bytes_in += xcresult_summary.len() as u64;
+            let mut xcresult_summary_temp = tempfile::tempfile()?;
+            xcresult_summary_temp.write_all(xcresult_summary)?;
+            xcresult_summary_temp.seek(std::io::SeekFrom::Start(0))?;
+            tar.append_file(XCRESULT_SUMMARY_FILENAME, &mut xcresult_summary_temp)?;
+        }
+
         // Flush to disk.
         tar.into_inner()?.finish()?;
 
@@ -105,11 +340,17 @@ impl BundlerUtil {
             total_bytes_out,
             size_reduction * 100.0,
         );
+        log::info!("Spent {:?} computing file checksums", hashing_duration);
 
         Ok(())
     }
 
-    pub fn make_tarball_in_temp_dir(&self) -> anyhow::Result<(PathBuf, TempDir)> {
+    /// The meta as written to the tarball, without the files that were skipped
+    pub fn meta(&self) -> &BundleMeta {
+        &self.meta
+    }
+
+    pub fn make_tarball_in_temp_dir(&mut self) -> anyhow::Result<(PathBuf, TempDir)> {
         let bundle_temp_dir = tempfile::tempdir()?;
         let bundle_temp_file = bundle_temp_dir.path().join("bundle.tar.zstd");
         self.make_tarball(&bundle_temp_file)?;
@@ -117,6 +358,283 @@ impl BundlerUtil {
     }
 }
 
+/// Adds the meta to the tarball as `meta.json`, returning the number of bytes of its JSON.
+///
+/// The JSON is streamed straight into the entry, after serializing it once without keeping it to
+/// size the entry's header, since the compressed tarball can't be seeked back into.
+fn append_meta_json<W: Write>(tar: &mut tar::Builder<W>, meta: &BundleMeta) -> anyhow::Result<u64> {
+    let meta_len = write_meta_json(meta, std::io::sink())?;
+    let mut header = tar::Header::new_gnu();
+    header.set_path(META_FILENAME)?;
+    header.set_size(meta_len);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default(),
+    );
+    header.set_cksum();
+
+    let writer = tar.get_mut();
+    writer.write_all(header.as_bytes())?;
+    let written = write_meta_json(meta, &mut *writer)?;
+    if written != meta_len {
+        return Err(anyhow::anyhow!(
+            "meta.json was {} bytes, but {} were written",
+            meta_len,
+            written
+        ));
+    }
+    // entries are padded to the next 512 byte block
+    let padding = (512 - meta_len % 512) % 512;
+    writer.write_all(&[0; 512][..padding as usize])?;
+    Ok(meta_len)
+}
+
+/// Rewrites the `meta.json` of the .tar.zstd file at `bundle_path` with `update`, e.g. to give the
+/// bundle of an upload registered again the new upload's ID. The other entries are copied as they
+/// are, and the file is only replaced once the new tarball is complete.
+pub fn rewrite_tarball_meta<F: FnOnce(&mut BundleMeta)>(
+    bundle_path: &Path,
+    update: F,
+) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(bundle_path)?)?);
+    let dir = bundle_path.parent().unwrap_or(Path::new("."));
+    let rewritten = tempfile::NamedTempFile::new_in(dir)?;
+    let zstd_encoder =
+        zstd::Encoder::new(rewritten.as_file(), BundlerUtil::ZSTD_COMPRESSION_LEVEL)?;
+    let mut tar = tar::Builder::new(zstd_encoder);
+    let mut update = Some(update);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_str() == Some(META_FILENAME) {
+            let mut meta: BundleMeta = serde_json::from_reader(&mut entry)?;
+            if let Some(update) = update.take() {
+                update(&mut meta);
+            }
+            append_meta_json(&mut tar, &meta)?;
+        } else {
+            let header = entry.header().clone();
+            tar.append(&header, &mut entry)?;
+        }
+    }
+    if update.is_some() {
+        return Err(anyhow::anyhow!("No meta.json file found in the tarball"));
+    }
+    tar.into_inner()?.finish()?;
+    rewritten.persist(bundle_path)?;
+    Ok(())
+}
+
+/// A test result file that could not be read, which is left out of the bundle rather than failing
+/// it. Check for it with `error.is::<UnreadableFile>()`.
+#[derive(Debug)]
+struct UnreadableFile {
+    path: PathBuf,
+    action: &'static str,
+    source: std::io::Error,
+}
+
+impl UnreadableFile {
+    fn new(path: &Path, action: &'static str, source: std::io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            action,
+            source,
+        }
+    }
+}
+
+impl fmt::Display for UnreadableFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to {} {:?}: {}",
+            self.action, self.path, self.source
+        )
+    }
+}
+
+impl std::error::Error for UnreadableFile {}
+
+/// Copies the file at `from` to `to`, returning the number of bytes copied, their sha256 and the
+/// time spent hashing them. Fails with [`UnreadableFile`] when `from` can't be read, while failing
+/// to write `to` is an I/O error of the bundle itself.
+fn copy_hashing<T: AsRef<Path>, U: AsRef<Path>>(
+    from: T,
+    to: U,
+) -> anyhow::Result<(u64, String, Duration)> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+    let mut file = File::open(from).map_err(|e| UnreadableFile::new(from, "open", e))?;
+    let mut sha256_writer =
+        Sha256Writer::new(File::create(to).with_context(|| format!("failed to create {:?}", to))?);
+    let mut buf = vec![0; COPY_BUFFER_LEN];
+    let mut bytes_copied = 0;
+    loop {
+        let len = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(UnreadableFile::new(from, "read", e).into()),
+        };
+        sha256_writer
+            .write_all(&buf[..len])
+            .with_context(|| format!("failed to write {:?}", to))?;
+        bytes_copied += len as u64;
+    }
+    let (_, sha256, duration) = sha256_writer.finish();
+    Ok((bytes_copied, sha256, duration))
+}
+
+/// Like [`copy_hashing`], streaming the junit file at `from` through [`sanitize_junit_xml`]. A file
+/// that can't be sanitized, like malformed XML, is copied as it is instead, for the backend to
+/// report on.
+fn sanitize_hashing<T: AsRef<Path>, U: AsRef<Path>>(
+    from: T,
+    to: U,
+    truncate_text: bool,
+) -> anyhow::Result<((u64, String, Duration), Option<JunitSanitization>)> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+    let file = File::open(from).map_err(|e| UnreadableFile::new(from, "open", e))?;
+    let mut writer = std::io::BufWriter::new(Sha256Writer::new(
+        File::create(to).with_context(|| format!("failed to create {:?}", to))?,
+    ));
+    match sanitize_junit_xml(file, &mut writer, truncate_text) {
+        Ok(sanitization) => {
+            let sha256_writer = writer
+                .into_inner()
+                .map_err(|e| anyhow::anyhow!("failed to write {:?}: {}", to, e.error()))?;
+            let (_, sha256, duration) = sha256_writer.finish();
+            Ok((
+                (sanitization.bytes_out, sha256, duration),
+                Some(sanitization),
+            ))
+        }
+        Err(e) => {
+            log::debug!(
+                "Bundling {:?} as it is, since it could not be sanitized: {:#}",
+                from,
+                e
+            );
+            drop(writer);
+            copy_hashing(from, to).map(|copied| (copied, None))
+        }
+    }
+}
+
+/// Serializes the meta straight into `writer` through a buffer, so that the JSON of a meta with many
+/// files is never held in memory. The bytes are the same as those of `serde_json::to_vec`.
+///
+/// Returns the number of bytes written.
+pub fn write_meta_json<W: Write>(meta: &BundleMeta, writer: W) -> anyhow::Result<u64> {
+    let mut counting_writer = std::io::BufWriter::new(CountingWriter {
+        inner: writer,
+        count: 0,
+    });
+    serde_json::to_writer(&mut counting_writer, meta)?;
+    let counting_writer = counting_writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("Failed to write meta: {}", e.error()))?;
+    Ok(counting_writer.count)
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer that computes the SHA-256 of the bytes written through it.
+///
+pub struct Sha256Writer<W: Write> {
+    inner: W,
+    context: ring::digest::Context,
+    hashing_duration: Duration,
+}
+
+impl<W: Write> Sha256Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            context: ring::digest::Context::new(&ring::digest::SHA256),
+            hashing_duration: Duration::ZERO,
+        }
+    }
+
+    /// Returns the inner writer, the hex encoded SHA-256 of the bytes written and the time spent
+    /// hashing them.
+    ///
+    pub fn finish(self) -> (W, String, Duration) {
+        (
+            self.inner,
+            hex::encode(self.context.finish()),
+            self.hashing_duration,
+        )
+    }
+}
+
+impl<W: Write> Write for Sha256Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let bytes_written = self.inner.write(buf)?;
+        let start = Instant::now();
+        self.context.update(&buf[..bytes_written]);
+        self.hashing_duration += start.elapsed();
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads and decompresses a .tar.zstd file into its `meta.json` file and the hex encoded SHA-256
+/// of every other entry, keyed by path.
+///
+pub fn read_tarball_checksums<R: Read>(
+    input: R,
+) -> anyhow::Result<(VersionedBundle, HashMap<String, String>)> {
+    let zstd_decoder = zstd::Decoder::new(input)?;
+    let mut archive = tar::Archive::new(zstd_decoder);
+
+    let mut meta = None;
+    let mut checksums = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        let path_str = tar_entry_name(
+            &entry.path_bytes(),
+            entry_type.is_symlink() || entry_type.is_hard_link(),
+        )?;
+        if path_str == META_FILENAME {
+            let mut meta_bytes = Vec::new();
+            entry.read_to_end(&mut meta_bytes)?;
+            meta = Some(parse_meta(meta_bytes)?);
+        } else {
+            let mut sha256_writer = Sha256Writer::new(std::io::sink());
+            std::io::copy(&mut entry, &mut sha256_writer)?;
+            let (_, sha256, _) = sha256_writer.finish();
+            checksums.insert(path_str, sha256);
+        }
+    }
+
+    let meta = meta.ok_or_else(|| anyhow::anyhow!("No meta.json file found in the tarball"))?;
+    Ok((meta, checksums))
+}
+
 /// Reads and decompresses a .tar.zstd file from an input stream into just a `meta.json` file
 ///
 pub async fn parse_meta_from_tarball<R: AsyncBufRead>(input: R) -> anyhow::Result<VersionedBundle> {
@@ -125,11 +643,11 @@ pub async fn parse_meta_from_tarball<R: AsyncBufRead>(input: R) -> anyhow::Resul
 
     if let Some(first_entry) = archive.entries()?.next().await {
         let mut owned_first_entry = first_entry?;
-        let path_str = owned_first_entry
-            .path()?
-            .to_str()
-            .unwrap_or_default()
-            .to_owned();
+        let entry_type = owned_first_entry.header().entry_type();
+        let path_str = tar_entry_name(
+            &owned_first_entry.path_bytes(),
+            entry_type.is_symlink() || entry_type.is_hard_link(),
+        )?;
 
         if path_str == META_FILENAME {
             let mut meta_bytes = Vec::new();
@@ -141,7 +659,103 @@ pub async fn parse_meta_from_tarball<R: AsyncBufRead>(input: R) -> anyhow::Resul
     Err(anyhow::anyhow!("No meta.json file found in the tarball"))
 }
 
+/// Reads and decompresses a .tar.zstd file from an input stream into just its `internal.bin`, an
+/// encoded `TestResult` proto
+///
+pub async fn parse_internal_bin_from_tarball<R: AsyncBufRead>(input: R) -> anyhow::Result<Vec<u8>> {
+    let (_, internal_bin) = parse_meta_and_internal_bin_from_tarball(input).await?;
+    internal_bin.ok_or_else(|| anyhow::anyhow!("No internal.bin file found in the tarball"))
+}
+
+/// Reads and decompresses a .tar.zstd file from an input stream into its `meta.json` file, and its
+/// `internal.bin` when it has one. The shards of a sharded internal bin are concatenated in the
+/// order of the meta, which decodes as the `TestResult` they were sharded from.
+///
+pub async fn parse_meta_and_internal_bin_from_tarball<R: AsyncBufRead>(
+    input: R,
+) -> anyhow::Result<(VersionedBundle, Option<Vec<u8>>)> {
+    let zstd_decoder = ZstdDecoder::new(Box::pin(input));
+    let archive = Archive::new(zstd_decoder);
+
+    let mut meta: Option<VersionedBundle> = None;
+    let mut internal_bin_shards: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut entries = archive.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        let path_str = tar_entry_name(
+            &entry.path_bytes(),
+            entry_type.is_symlink() || entry_type.is_hard_link(),
+        )?;
+        if path_str == META_FILENAME {
+            let mut meta_bytes = Vec::new();
+            entry.read_to_end(&mut meta_bytes).await?;
+            meta = Some(parse_meta(meta_bytes)?);
+        } else if path_str == INTERNAL_BIN_FILENAME {
+            let meta =
+                meta.ok_or_else(|| anyhow::anyhow!("No meta.json file found in the tarball"))?;
+            let mut internal_bin = Vec::new();
+            entry.read_to_end(&mut internal_bin).await?;
+            return Ok((meta, Some(internal_bin)));
+        } else if let Some(meta) = meta.as_ref() {
+            if meta.internal_bin_shards().contains(&path_str) {
+                let mut internal_bin_shard = Vec::new();
+                entry.read_to_end(&mut internal_bin_shard).await?;
+                internal_bin_shards.insert(path_str, internal_bin_shard);
+            }
+        }
+    }
+
+    let meta = meta.ok_or_else(|| anyhow::anyhow!("No meta.json file found in the tarball"))?;
+    if meta.internal_bin_shards().is_empty() {
+        return Ok((meta, None));
+    }
+    let mut internal_bin = Vec::new();
+    for filename in meta.internal_bin_shards() {
+        let internal_bin_shard = internal_bin_shards.remove(filename).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No {} file found in the tarball, a shard of the internal bin",
+                filename
+            )
+        })?;
+        internal_bin.extend(internal_bin_shard);
+    }
+    Ok((meta, Some(internal_bin)))
+}
+
 pub fn parse_meta(meta_bytes: Vec<u8>) -> anyhow::Result<VersionedBundle> {
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_11(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_10(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_9(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_8(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_7(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_6(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_5(message));
+    }
+
+    if let Ok(message) = serde_json::from_slice(&meta_bytes) {
+        return Ok(VersionedBundle::V0_6_4(message));
+    }
+
     if let Ok(message) = serde_json::from_slice(&meta_bytes) {
         return Ok(VersionedBundle::V0_6_3(message));
     }
@@ -157,3 +771,515 @@ pub fn parse_meta(meta_bytes: Vec<u8>) -> anyhow::Result<VersionedBundle> {
     let base_bundle = serde_json::from_slice(&meta_bytes)?;
     return Ok(VersionedBundle::V0_5_29(base_bundle));
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::{
+        tar_entry::tests::append_raw_entry, BundleMetaBaseProps, BundleMetaDebugProps,
+        BundleMetaWorkingTree, BundledFile, FileSet, UnsafeTarEntry,
+    };
+
+    fn meta_with_files(files: Vec<BundledFile>) -> BundleMeta {
+        BundleMeta {
+            base_props: BundleMetaBaseProps {
+                version: String::from("1"),
+                cli_version: String::from("0.0.0"),
+                org: String::from("org"),
+                repo: Default::default(),
+                bundle_upload_id: String::from("id"),
+                tags: Vec::new(),
+                file_sets: vec![FileSet::new(files, String::from("**/*.xml"), None, None)],
+                envs: HashMap::from([(String::from("CI"), String::from("true"))]),
+                upload_time_epoch: 0,
+                test_command: None,
+                os_info: None,
+                quarantined_tests: Vec::new(),
+                codeowners: None,
+            },
+            junit_props: Default::default(),
+            debug_props: BundleMetaDebugProps {
+                command_line: String::from("trunk-analytics-cli upload"),
+            },
+            bundle_upload_id_v2: String::from("id"),
+            invocation: Default::default(),
+            host_info: Default::default(),
+            excluded_files: Vec::new(),
+            checksum_algorithm: String::from("sha256"),
+            quarantine_outcome: Default::default(),
+            duplicate_test_case_ids: Vec::new(),
+            skipped_files: Vec::new(),
+            variant: None,
+            variants: Vec::new(),
+            repo_metadata_source: None,
+            locally_quarantined_tests: Vec::new(),
+            env_capture_profile: None,
+            test_command_stats: None,
+            quarantined_test_sources: Vec::new(),
+            internal_bin_shards: Vec::new(),
+            converter: None,
+            working_tree: Default::default(),
+        }
+    }
+
+    /// Records the largest single write, to check that the JSON is never written in one go.
+    #[derive(Default)]
+    struct RecordingWriter {
+        bytes: Vec<u8>,
+        max_write_len: usize,
+    }
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.max_write_len = self.max_write_len.max(buf.len());
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parses_meta_without_working_tree_as_previous_version() {
+        let mut meta = meta_with_files(Vec::new());
+        meta.base_props.repo.dirty = Some(true);
+        meta.base_props.repo.num_modified_files = Some(2);
+        meta.working_tree = BundleMetaWorkingTree {
+            dirty: Some(true),
+            num_modified_files: Some(2),
+        };
+        let mut meta_json = serde_json::to_value(&meta).unwrap();
+        assert_eq!(meta_json["repo"].get("dirty"), None);
+        assert_eq!(meta_json["working_tree"]["num_modified_files"], 2);
+
+        meta_json.as_object_mut().unwrap().remove("working_tree");
+        let parsed_meta = parse_meta(serde_json::to_vec(&meta_json).unwrap()).unwrap();
+        assert!(matches!(parsed_meta, VersionedBundle::V0_6_10(..)));
+        assert_eq!(parsed_meta.base_props().repo.dirty, None);
+    }
+
+    #[test]
+    fn streams_meta_json_with_many_files() {
+        let files = (0..100_000)
+            .map(|i| BundledFile {
+                original_path: format!("/repo/test-results/{i}/junit.xml"),
+                original_path_rel: Some(format!("test-results/{i}/junit.xml")),
+                path: format!("junit/{i}"),
+                owners: vec![String::from("@owner")],
+                sha256: Some(format!("{i:064x}")),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let meta = meta_with_files(files);
+
+        let mut writer = RecordingWriter::default();
+        let bytes_written = write_meta_json(&meta, &mut writer).unwrap();
+
+        assert_eq!(bytes_written, writer.bytes.len() as u64);
+        assert!(writer.max_write_len <= 8 * 1024, "{}", writer.max_write_len);
+        assert_eq!(writer.bytes, serde_json::to_vec(&meta).unwrap());
+        assert_eq!(
+            parse_meta(writer.bytes).unwrap(),
+            VersionedBundle::V0_6_11(meta)
+        );
+    }
+
+    #[test]
+    fn copy_hashing_fails_on_write_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let from = temp_dir.path().join("junit.xml");
+        std::fs::write(&from, "<testsuites />").unwrap();
+
+        let e = copy_hashing(&from, temp_dir.path().join("missing").join("junit.xml")).unwrap_err();
+        assert!(!e.is::<UnreadableFile>(), "{:#}", e);
+
+        let e = copy_hashing(
+            temp_dir.path().join("missing.xml"),
+            temp_dir.path().join("copy"),
+        )
+        .unwrap_err();
+        assert!(e.is::<UnreadableFile>(), "{:#}", e);
+    }
+
+    #[test]
+    fn skips_files_that_cannot_be_read() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let files = (0..3)
+            .map(|i| {
+                let original_path = temp_dir.path().join(format!("junit-{i}.xml"));
+                std::fs::write(&original_path, format!("<testsuites name=\"{i}\" />")).unwrap();
+                BundledFile {
+                    original_path: original_path.to_string_lossy().to_string(),
+                    original_path_rel: Some(format!("junit-{i}.xml")),
+                    path: format!("junit/{i}"),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut meta = meta_with_files(files);
+        meta.junit_props.num_files = 3;
+        meta.junit_props.num_tests = 6;
+        // vanished between scanning and bundling
+        std::fs::remove_file(temp_dir.path().join("junit-1.xml")).unwrap();
+
+        let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+        let mut bundler = BundlerUtil::new(meta, None).with_num_tests_by_path(HashMap::from([
+            (String::from("junit/0"), 1),
+            (String::from("junit/1"), 2),
+            (String::from("junit/2"), 3),
+        ]));
+        bundler.make_tarball(&bundle_path).unwrap();
+
+        let (versioned_bundle, checksums) =
+            read_tarball_checksums(File::open(&bundle_path).unwrap()).unwrap();
+        let VersionedBundle::V0_6_11(meta) = versioned_bundle else {
+            panic!("unexpected meta version");
+        };
+        assert_eq!(&meta, bundler.meta());
+        assert_eq!(meta.skipped_files.len(), 1);
+        assert_eq!(
+            meta.skipped_files[0].original_path,
+            temp_dir.path().join("junit-1.xml").to_string_lossy()
+        );
+        assert!(meta.skipped_files[0].reason.contains("failed to open"));
+        assert_eq!(meta.junit_props.num_files, 2);
+        assert_eq!(meta.junit_props.num_tests, 4);
+        assert_eq!(
+            meta.base_props.file_sets[0]
+                .files
+                .iter()
+                .map(|bundled_file| bundled_file.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["junit/0", "junit/2"]
+        );
+        let mut bundled_paths = checksums.keys().cloned().collect::<Vec<_>>();
+        bundled_paths.sort();
+        assert_eq!(bundled_paths, vec!["junit/0", "junit/2"]);
+    }
+
+    #[test]
+    fn rewrites_the_meta_of_a_tarball() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let original_path = temp_dir.path().join("junit.xml");
+        std::fs::write(&original_path, "<testsuites name=\"suite\" />").unwrap();
+        let meta = meta_with_files(vec![BundledFile {
+            original_path: original_path.to_string_lossy().to_string(),
+            path: String::from("junit/0"),
+            ..Default::default()
+        }]);
+        let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+        let mut bundler = BundlerUtil::new(meta, None);
+        bundler.make_tarball(&bundle_path).unwrap();
+        let (_, checksums) = read_tarball_checksums(File::open(&bundle_path).unwrap()).unwrap();
+
+        rewrite_tarball_meta(&bundle_path, |meta| {
+            meta.base_props.bundle_upload_id = String::from("new-id");
+        })
+        .unwrap();
+
+        let (versioned_bundle, rewritten_checksums) =
+            read_tarball_checksums(File::open(&bundle_path).unwrap()).unwrap();
+        let VersionedBundle::V0_6_11(rewritten_meta) = versioned_bundle else {
+            panic!("unexpected meta version");
+        };
+        assert_eq!(rewritten_meta.base_props.bundle_upload_id, "new-id");
+        assert_eq!(
+            rewritten_meta.base_props.file_sets,
+            bundler.meta().base_props.file_sets
+        );
+        assert_eq!(rewritten_checksums, checksums);
+    }
+
+    #[test]
+    fn fails_when_no_file_can_be_read() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let files = (0..2)
+            .map(|i| BundledFile {
+                original_path: temp_dir
+                    .path()
+                    .join(format!("junit-{i}.xml"))
+                    .to_string_lossy()
+                    .to_string(),
+                path: format!("junit/{i}"),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let mut bundler = BundlerUtil::new(meta_with_files(files), None);
+        let error = bundler
+            .make_tarball(&temp_dir.path().join("bundle.tar.zstd"))
+            .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .starts_with("Failed to bundle any of the 2 test result files"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn sanitizes_junit_files_while_bundling() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let junit_xmls = [
+            "<testsuites name=\"\x1b[31mred\x1b[0m\" />",
+            "<testsuites name=\"clean\" />",
+            "<testsuites></testsuite>",
+        ];
+        let mut files = junit_xmls
+            .iter()
+            .enumerate()
+            .map(|(i, junit_xml)| {
+                let original_path = temp_dir.path().join(format!("junit-{i}.xml"));
+                std::fs::write(&original_path, junit_xml).unwrap();
+                BundledFile {
+                    original_path: original_path.to_string_lossy().to_string(),
+                    path: format!("junit/{i}"),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        files.push(BundledFile {
+            original_path: files[0].original_path.clone(),
+            path: String::from("junit/3"),
+            dedup_of: Some(String::from("junit/0")),
+            ..Default::default()
+        });
+
+        let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+        let mut bundler = BundlerUtil::new(meta_with_files(files), None).with_sanitize_junit(true);
+        bundler.make_tarball(&bundle_path).unwrap();
+
+        let (_, checksums) = read_tarball_checksums(File::open(&bundle_path).unwrap()).unwrap();
+        let sha256_of = |bytes: &[u8]| {
+            let mut sha256_writer = Sha256Writer::new(std::io::sink());
+            sha256_writer.write_all(bytes).unwrap();
+            sha256_writer.finish().1
+        };
+        assert_eq!(
+            checksums.get("junit/0"),
+            Some(&sha256_of(b"<testsuites name=\"[31mred[0m\"/>"))
+        );
+        assert_eq!(
+            checksums.get("junit/1"),
+            Some(&sha256_of(junit_xmls[1].as_bytes()))
+        );
+        // malformed XML is bundled as it is
+        assert_eq!(
+            checksums.get("junit/2"),
+            Some(&sha256_of(junit_xmls[2].as_bytes()))
+        );
+        let sanitizations = bundler.meta().base_props.file_sets[0]
+            .files
+            .iter()
+            .map(|bundled_file| {
+                (
+                    bundled_file.junit_sanitized,
+                    bundled_file.junit_sanitized_size_delta,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sanitizations,
+            vec![
+                (Some(true), Some(-3)),
+                (Some(false), None),
+                (None, None),
+                (Some(true), Some(-3))
+            ]
+        );
+
+        let mut bundler = BundlerUtil::new(meta_with_files(Vec::new()), None);
+        bundler.meta.base_props.file_sets[0].files = vec![BundledFile {
+            original_path: temp_dir
+                .path()
+                .join("junit-0.xml")
+                .to_string_lossy()
+                .to_string(),
+            path: String::from("junit/0"),
+            ..Default::default()
+        }];
+        bundler.make_tarball(&bundle_path).unwrap();
+        let (_, checksums) = read_tarball_checksums(File::open(&bundle_path).unwrap()).unwrap();
+        assert_eq!(
+            checksums.get("junit/0"),
+            Some(&sha256_of(junit_xmls[0].as_bytes()))
+        );
+        assert_eq!(
+            bundler.meta().base_props.file_sets[0].files[0].junit_sanitized,
+            None
+        );
+    }
+
+    #[async_std::test]
+    async fn parses_meta_and_internal_bin_from_tarball() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+        let internal_bin = vec![8, 1, 16, 2];
+        let mut bundler = BundlerUtil::new(meta_with_files(Vec::new()), None)
+            .with_internal_bin(Some(internal_bin.clone()));
+        bundler.make_tarball(&bundle_path).unwrap();
+
+        let open_bundle = || async {
+            async_std::io::BufReader::new(async_std::fs::File::open(&bundle_path).await.unwrap())
+        };
+        let (meta, parsed_internal_bin) =
+            parse_meta_and_internal_bin_from_tarball(open_bundle().await)
+                .await
+                .unwrap();
+        assert_eq!(meta, VersionedBundle::V0_6_11(bundler.meta().clone()));
+        assert_eq!(parsed_internal_bin, Some(internal_bin.clone()));
+        assert_eq!(
+            parse_internal_bin_from_tarball(open_bundle().await)
+                .await
+                .unwrap(),
+            internal_bin
+        );
+    }
+
+    #[async_std::test]
+    async fn parses_sharded_internal_bin_from_tarball() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let parse_bundle = |internal_bin_shards: Vec<Vec<u8>>| {
+            let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+            let mut bundler = BundlerUtil::new(meta_with_files(Vec::new()), None)
+                .with_internal_bin_shards(internal_bin_shards);
+            bundler.make_tarball(&bundle_path).unwrap();
+            let meta = bundler.meta().clone();
+            async move {
+                let file = async_std::fs::File::open(&bundle_path).await.unwrap();
+                let parsed =
+                    parse_meta_and_internal_bin_from_tarball(async_std::io::BufReader::new(file))
+                        .await
+                        .unwrap();
+                (meta, parsed)
+            }
+        };
+
+        let (meta, (parsed_meta, parsed_internal_bin)) =
+            parse_bundle(vec![vec![8, 1], vec![16, 2], vec![24, 3]]).await;
+        assert_eq!(
+            meta.internal_bin_shards,
+            vec!["internal_0.bin", "internal_1.bin", "internal_2.bin"]
+        );
+        assert_eq!(parsed_meta, VersionedBundle::V0_6_11(meta));
+        assert_eq!(parsed_internal_bin, Some(vec![8, 1, 16, 2, 24, 3]));
+
+        // A single shard is bundled as it was before sharding
+        let (meta, (_, parsed_internal_bin)) = parse_bundle(vec![vec![8, 1]]).await;
+        assert!(meta.internal_bin_shards.is_empty());
+        assert_eq!(parsed_internal_bin, Some(vec![8, 1]));
+
+        let (meta, (_, parsed_internal_bin)) = parse_bundle(Vec::new()).await;
+        assert!(meta.internal_bin_shards.is_empty());
+        assert_eq!(parsed_internal_bin, None);
+    }
+
+    #[test]
+    fn refuses_to_bundle_unsafe_file_names() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let original_path = temp_dir.path().join("junit.xml");
+        std::fs::write(&original_path, "<testsuites />").unwrap();
+        let files = vec![BundledFile {
+            original_path: original_path.to_string_lossy().to_string(),
+            path: String::from("junit/../../etc/passwd"),
+            ..Default::default()
+        }];
+
+        let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+        let error = BundlerUtil::new(meta_with_files(files), None)
+            .make_tarball(&bundle_path)
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<UnsafeTarEntry>(),
+            Some(&UnsafeTarEntry::ParentDir {
+                name: String::from("junit/../../etc/passwd")
+            })
+        );
+        assert!(!bundle_path.exists());
+    }
+
+    /// A bundle whose entries after `meta.json` are written as given, without any checks.
+    fn crafted_bundle(entries: &[(&str, tar::EntryType)]) -> Vec<u8> {
+        let mut meta_json = Vec::new();
+        write_meta_json(&meta_with_files(Vec::new()), &mut meta_json).unwrap();
+        let mut tar = tar::Builder::new(Vec::new());
+        for (name, entry_type) in [(META_FILENAME, tar::EntryType::Regular)]
+            .iter()
+            .chain(entries)
+        {
+            let data: &[u8] = if *name == META_FILENAME {
+                &meta_json
+            } else {
+                b"root:x:0:0"
+            };
+            append_raw_entry(&mut tar, name, *entry_type, data);
+        }
+        zstd::encode_all(tar.into_inner().unwrap().as_slice(), 0).unwrap()
+    }
+
+    #[async_std::test]
+    async fn rejects_unsafe_entries_when_reading() {
+        let cases = [
+            (
+                "../../etc/passwd",
+                tar::EntryType::Regular,
+                UnsafeTarEntry::ParentDir {
+                    name: String::from("../../etc/passwd"),
+                },
+            ),
+            (
+                "/etc/passwd",
+                tar::EntryType::Regular,
+                UnsafeTarEntry::AbsolutePath {
+                    name: String::from("/etc/passwd"),
+                },
+            ),
+            (
+                "junit/0",
+                tar::EntryType::Symlink,
+                UnsafeTarEntry::Link {
+                    name: String::from("junit/0"),
+                },
+            ),
+            (
+                INTERNAL_BIN_FILENAME,
+                tar::EntryType::Link,
+                UnsafeTarEntry::Link {
+                    name: String::from(INTERNAL_BIN_FILENAME),
+                },
+            ),
+        ];
+        for (name, entry_type, expected) in cases {
+            let bundle = crafted_bundle(&[(name, entry_type)]);
+
+            let error = read_tarball_checksums(bundle.as_slice()).unwrap_err();
+            assert_eq!(error.downcast_ref(), Some(&expected));
+            let error = parse_meta_and_internal_bin_from_tarball(bundle.as_slice())
+                .await
+                .unwrap_err();
+            assert_eq!(error.downcast_ref(), Some(&expected));
+            // only the first entry is read for the meta
+            assert!(parse_meta_from_tarball(bundle.as_slice()).await.is_ok());
+        }
+
+        let mut tar = tar::Builder::new(Vec::new());
+        append_raw_entry(&mut tar, META_FILENAME, tar::EntryType::Symlink, b"");
+        let bundle = zstd::encode_all(tar.into_inner().unwrap().as_slice(), 0).unwrap();
+        let error = parse_meta_from_tarball(bundle.as_slice())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref(),
+            Some(&UnsafeTarEntry::Link {
+                name: String::from(META_FILENAME)
+            })
+        );
+    }
+}
@@ -1,13 +1,22 @@
 use std::{
+    collections::{hash_map::Entry, HashMap},
     fmt::Debug,
     format,
     path::{Path, PathBuf},
-    time::SystemTime,
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
 
 use codeowners::{CodeOwners, Owners, OwnersOfPath};
 use constants::ALLOW_LIST;
-use context::junit::junit_path::{JunitReportFileWithStatus, JunitReportStatus};
+use context::junit::{
+    encoding::decode_xml,
+    junit_path::{JunitReportFileWithStatus, JunitReportStatus},
+    merge::{
+        extract_test_suites, merge_test_suites, JunitMergeGrouping, MERGED_JUNIT_FILE_MAX_BYTES,
+    },
+    root_sniff::may_be_junit,
+};
 use glob::glob;
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
@@ -20,11 +29,77 @@ use tsify_next::Tsify;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+use crate::{
+    archive::{archive_entry_path, extract_junit_archive, ArchiveEntry, ArchiveKind},
+    bundler::Sha256Writer,
+    gitignore::GitignoreFilter,
+};
+
 #[derive(Debug, Default, Clone)]
 pub struct FileSetBuilder {
     count: usize,
     file_sets: Vec<FileSet>,
     codeowners: Option<CodeOwners>,
+    /// Holds the junit files extracted from archives, removed on drop
+    archive_extract_dir: Option<Arc<tempfile::TempDir>>,
+    /// Holds the junit files written by [`FileSetBuilder::merge_junit_files`], removed on drop
+    merged_junit_dir: Option<Arc<tempfile::TempDir>>,
+    /// Files matched by the globs whose root element is not `testsuites` or `testsuite`
+    num_skipped_non_junit_files: usize,
+    /// Files matched by the globs that are ignored by git, with `respect_gitignore`
+    num_gitignored_files: usize,
+    /// Files matched by the globs that were last modified longer than `max_age` ago
+    num_expired_files: usize,
+    /// Files marked by [`FileSetBuilder::dedup_identical_files`], and their total size
+    num_deduplicated_files: usize,
+    num_deduplicated_bytes: u64,
+    /// Why each file matched by the globs was included or not, in scan order
+    file_decisions: Vec<FileDecision>,
+}
+
+/// The rule that decided whether a file matched by a junit glob is uploaded. Rules are listed in
+/// the order they are evaluated, the first one that excludes a file decides.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileDecisionRule {
+    Gitignored,
+    Expired,
+    Stale,
+    NotAllowed,
+    UnsupportedExtension,
+    NotJunit,
+    Included,
+}
+
+impl FileDecisionRule {
+    pub fn is_included(&self) -> bool {
+        *self == Self::Included
+    }
+}
+
+impl std::fmt::Display for FileDecisionRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let explanation = match self {
+            Self::Gitignored => "ignored by git, with --respect-gitignore",
+            Self::Expired => "last modified longer than --junit-max-age ago",
+            Self::Stale => "last modified before the test command started",
+            Self::NotAllowed => "not a .xml, .junit or .bin file",
+            Self::UnsupportedExtension => "not a .xml or .bin file",
+            Self::NotJunit => "root element is not testsuites or testsuite",
+            Self::Included => "included",
+        };
+        write!(f, "{}", explanation)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileDecision {
+    /// Repo-relative path of the file, `path/to/archive.zip!path/in/archive.xml` for files in
+    /// archives
+    pub path: String,
+    /// The junit glob that matched it
+    pub glob: String,
+    pub rule: FileDecisionRule,
 }
 
 impl FileSetBuilder {
@@ -34,16 +109,32 @@ impl FileSetBuilder {
         team: &Option<String>,
         codeowners_path: &Option<U>,
         exec_start: Option<SystemTime>,
+        respect_gitignore: bool,
+        max_age: Option<Duration>,
     ) -> anyhow::Result<Self> {
         let repo_root = repo_root.as_ref();
 
         let codeowners = CodeOwners::find_file(repo_root, codeowners_path);
+        let mut gitignore_filter = respect_gitignore.then(|| GitignoreFilter::new(repo_root));
+        // Ages are relative to the start of the test run when there is one
+        let max_age = max_age.map(|max_age| (max_age, exec_start.unwrap_or_else(SystemTime::now)));
 
-        let file_set_builder =
-            Self::file_sets_from_glob(repo_root, junit_paths, team, codeowners, exec_start)?;
+        let file_set_builder = Self::file_sets_from_glob(
+            repo_root,
+            junit_paths,
+            team,
+            codeowners,
+            exec_start,
+            &mut gitignore_filter,
+            max_age,
+        )?;
 
         // Handle case when junit paths are not globs.
-        if file_set_builder.count == 0 {
+        if file_set_builder.count == 0
+            && file_set_builder.num_skipped_non_junit_files == 0
+            && file_set_builder.num_gitignored_files == 0
+            && file_set_builder.num_expired_files == 0
+        {
             let junit_paths_with_glob = junit_paths
                 .iter()
                 .cloned()
@@ -62,6 +153,8 @@ impl FileSetBuilder {
                 team,
                 file_set_builder.codeowners,
                 exec_start,
+                &mut gitignore_filter,
+                max_age,
             );
         }
 
@@ -74,6 +167,8 @@ impl FileSetBuilder {
         team: &Option<String>,
         codeowners: Option<CodeOwners>,
         exec_start: Option<SystemTime>,
+        gitignore_filter: &mut Option<GitignoreFilter>,
+        max_age: Option<(Duration, SystemTime)>,
     ) -> anyhow::Result<Self> {
         junit_paths.iter().try_fold(
             Self {
@@ -81,31 +176,123 @@ impl FileSetBuilder {
                 ..Self::default()
             },
             |mut acc, junit_wrapper| -> anyhow::Result<Self> {
-                let files = Self::scan_from_glob(&junit_wrapper.junit_path, repo_root)?;
+                let glob = &junit_wrapper.junit_path;
+                let mut files = Self::scan_from_glob(glob, repo_root)?;
+                let mut file_decisions = Vec::new();
+                let mut decide = |file: &Path, rule: FileDecisionRule| {
+                    file_decisions.push(FileDecision {
+                        path: file
+                            .strip_prefix(repo_root)
+                            .unwrap_or(file)
+                            .to_string_lossy()
+                            .to_string(),
+                        glob: glob.clone(),
+                        rule,
+                    });
+                };
+                if let Some(gitignore_filter) = gitignore_filter.as_mut() {
+                    let num_files = files.len();
+                    files.retain(|file| {
+                        let is_ignored = gitignore_filter.is_ignored(file);
+                        if is_ignored {
+                            decide(file, FileDecisionRule::Gitignored);
+                        }
+                        !is_ignored
+                    });
+                    acc.num_gitignored_files += num_files - files.len();
+                }
+                if let Some((max_age, since)) = max_age {
+                    let num_files = files.len();
+                    files.retain(|file| {
+                        let is_expired = is_expired(file, max_age, since);
+                        if is_expired {
+                            decide(file, FileDecisionRule::Expired);
+                        }
+                        !is_expired
+                    });
+                    acc.num_expired_files += num_files - files.len();
+                }
+                if acc.archive_extract_dir.is_none()
+                    && files
+                        .iter()
+                        .any(|file| ArchiveKind::from_path(file).is_some())
+                {
+                    acc.archive_extract_dir = Some(Arc::new(tempfile::tempdir()?));
+                }
+                let archive_extract_dir = acc.archive_extract_dir.clone();
                 let codeowners = &acc.codeowners;
+                let mut num_skipped_non_junit_files = 0;
                 let (count, bundled_files) = files.iter().try_fold(
                     (acc.count, Vec::new()),
                     |mut acc, file| -> anyhow::Result<(usize, Vec<BundledFile>)> {
-                        if let Some(bundled_file) = BundledFile::from_path(
+                        if let (Some(kind), Some(archive_extract_dir)) =
+                            (ArchiveKind::from_path(file), &archive_extract_dir)
+                        {
+                            let dest_dir = tempfile::tempdir_in(archive_extract_dir.path())?;
+                            let entries = extract_junit_archive(file, kind, dest_dir.path())?;
+                            // Kept until archive_extract_dir is dropped
+                            let _ = dest_dir.into_path();
+                            for entry in entries {
+                                let entry_path =
+                                    archive_entry_path(file.to_string_lossy(), &entry.name);
+                                match BundledFile::from_archive_entry(
+                                    file.as_path(),
+                                    &entry,
+                                    acc.0,
+                                    repo_root,
+                                    glob,
+                                    team.clone(),
+                                    codeowners,
+                                    exec_start,
+                                )? {
+                                    Ok(bundled_file) => {
+                                        decide(Path::new(&entry_path), FileDecisionRule::Included);
+                                        acc.0 += 1;
+                                        acc.1.push(bundled_file);
+                                    }
+                                    Err(rule) => decide(Path::new(&entry_path), rule),
+                                }
+                            }
+                            return Ok(acc);
+                        }
+                        match BundledFile::from_path(
                             file.as_path(),
                             acc.0,
                             repo_root,
-                            &junit_wrapper.junit_path,
+                            glob,
                             team.clone(),
                             codeowners,
                             exec_start,
                         )? {
-                            acc.0 += 1;
-                            acc.1.push(bundled_file);
+                            Ok(bundled_file) => {
+                                // Broad globs match other XML files, e.g. checkstyle reports,
+                                // which are ruled out without parsing them in full
+                                if !std::fs::File::open(file)
+                                    .and_then(may_be_junit)
+                                    .unwrap_or(true)
+                                {
+                                    log::debug!("Skipping {:?}, it is not a junit file", file);
+                                    decide(file, FileDecisionRule::NotJunit);
+                                    num_skipped_non_junit_files += 1;
+                                    return Ok(acc);
+                                }
+                                decide(file, FileDecisionRule::Included);
+                                acc.0 += 1;
+                                acc.1.push(bundled_file);
+                            }
+                            Err(rule) => decide(file, rule),
                         }
                         Ok(acc)
                     },
                 )?;
                 acc.count = count;
+                acc.num_skipped_non_junit_files += num_skipped_non_junit_files;
+                acc.file_decisions.append(&mut file_decisions);
                 acc.file_sets.push(FileSet::new(
                     bundled_files,
                     junit_wrapper.junit_path.clone(),
                     junit_wrapper.status.clone(),
+                    junit_wrapper.variant.clone(),
                 ));
                 Ok(acc)
             },
@@ -120,6 +307,30 @@ impl FileSetBuilder {
         &self.file_sets
     }
 
+    pub fn num_skipped_non_junit_files(&self) -> usize {
+        self.num_skipped_non_junit_files
+    }
+
+    pub fn num_gitignored_files(&self) -> usize {
+        self.num_gitignored_files
+    }
+
+    pub fn num_expired_files(&self) -> usize {
+        self.num_expired_files
+    }
+
+    pub fn num_deduplicated_files(&self) -> usize {
+        self.num_deduplicated_files
+    }
+
+    pub fn num_deduplicated_bytes(&self) -> u64 {
+        self.num_deduplicated_bytes
+    }
+
+    pub fn file_decisions(&self) -> &[FileDecision] {
+        &self.file_decisions
+    }
+
     pub fn codeowners(&self) -> &Option<CodeOwners> {
         &self.codeowners
     }
@@ -132,6 +343,168 @@ impl FileSetBuilder {
         self.count() == 0 || self.file_sets().is_empty()
     }
 
+    /// Replaces the junit files of each file set with merged ones, for test runners that write a
+    /// junit file per test case. Their `<testsuite>` elements are copied as written, so nothing
+    /// is lost to parsing. Files are only merged within a file set, so that its resolved status
+    /// still applies, and files that aren't well-formed junit are kept as they are. Bundle paths
+    /// are renumbered afterwards.
+    ///
+    /// Returns the number of files before merging.
+    pub fn merge_junit_files(&mut self, grouping: JunitMergeGrouping) -> anyhow::Result<usize> {
+        let num_original_files = self.count;
+        let mut num_merged_files = 0;
+        for file_set in self.file_sets.iter_mut() {
+            let (junit_files, mut other_files): (Vec<BundledFile>, Vec<BundledFile>) = file_set
+                .files
+                .drain(..)
+                .partition(|bundled_file| bundled_file.path.starts_with("junit/"));
+            if junit_files.len() <= 1 {
+                file_set.files = junit_files;
+                file_set.files.append(&mut other_files);
+                continue;
+            }
+
+            let mut extracted_files = Vec::new();
+            let mut merged_files = Vec::new();
+            for bundled_file in junit_files {
+                // Test suites are copied as written, transcoded to UTF-8
+                let extracted = std::fs::read(bundled_file.read_path())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|bytes| extract_test_suites(&decode_xml(bytes).xml));
+                match extracted {
+                    Ok(extracted) => {
+                        extracted_files.push(extracted);
+                        merged_files.push(bundled_file);
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Not merging {}, it could not be parsed: {:#}",
+                            bundled_file.get_print_path(),
+                            e
+                        );
+                        other_files.push(bundled_file);
+                    }
+                }
+            }
+            if merged_files.is_empty() {
+                file_set.files = other_files;
+                continue;
+            }
+
+            let merged_junit_dir = match &self.merged_junit_dir {
+                Some(merged_junit_dir) => merged_junit_dir.clone(),
+                None => {
+                    let merged_junit_dir = Arc::new(tempfile::tempdir()?);
+                    self.merged_junit_dir = Some(merged_junit_dir.clone());
+                    merged_junit_dir
+                }
+            };
+            let mut owners: Vec<String> = merged_files
+                .iter()
+                .flat_map(|bundled_file| bundled_file.owners.iter().cloned())
+                .collect();
+            owners.sort();
+            owners.dedup();
+            let team = merged_files
+                .iter()
+                .find_map(|bundled_file| bundled_file.team.clone());
+            #[cfg(not(feature = "wasm"))]
+            let last_modified_epoch_ns = merged_files
+                .iter()
+                .map(|bundled_file| bundled_file.last_modified_epoch_ns)
+                .max()
+                .unwrap_or_default();
+
+            for merged_xml in
+                merge_test_suites(extracted_files, grouping, MERGED_JUNIT_FILE_MAX_BYTES)
+            {
+                num_merged_files += 1;
+                let merged_path = merged_junit_dir
+                    .path()
+                    .join(format!("{}.xml", num_merged_files));
+                std::fs::write(&merged_path, merged_xml)?;
+                let merged_path = merged_path
+                    .to_str()
+                    .ok_or_else(|| anyhow::Error::msg("failed to convert path to string"))?
+                    .to_string();
+                file_set.files.push(BundledFile {
+                    original_path: merged_path,
+                    original_path_rel: None,
+                    path: String::new(),
+                    #[cfg(not(feature = "wasm"))]
+                    last_modified_epoch_ns,
+                    owners: owners.clone(),
+                    team: team.clone(),
+                    sha256: None,
+                    dedup_of: None,
+                    junit_sanitized: None,
+                    junit_sanitized_size_delta: None,
+                    extracted_path: None,
+                });
+            }
+            file_set.files.append(&mut other_files);
+        }
+
+        // Save files under junit/0, junit/1, etc. again, now that some are gone
+        let mut file_index = 0;
+        for bundled_file in self
+            .file_sets
+            .iter_mut()
+            .flat_map(|file_set| file_set.files.iter_mut())
+        {
+            bundled_file.path = if bundled_file.path.starts_with("internal/") {
+                format!("internal/{}", file_index)
+            } else {
+                format!("junit/{}", file_index)
+            };
+            file_index += 1;
+        }
+        self.count = file_index;
+
+        Ok(num_original_files)
+    }
+
+    /// Marks the junit files that are byte-identical to an earlier one with `dedup_of`, e.g. when
+    /// a pipeline copies the same file into several matched directories, so that they are neither
+    /// bundled nor parsed again. Files of file sets with a different variant or resolved status are
+    /// never treated as copies, since their test cases differ. Files that can't be read are left to
+    /// the bundler to report.
+    pub fn dedup_identical_files(&mut self) {
+        let mut originals: HashMap<(Option<String>, Option<String>, String), String> =
+            HashMap::new();
+        for file_set in self.file_sets.iter_mut() {
+            let variant = file_set.variant.clone();
+            let resolved_status = file_set
+                .resolved_status
+                .as_ref()
+                .map(|resolved_status| resolved_status.to_string());
+            for bundled_file in file_set
+                .files
+                .iter_mut()
+                .filter(|bundled_file| bundled_file.path.starts_with("junit/"))
+            {
+                let Ok((num_bytes, sha256)) = sha256_of_file(bundled_file.read_path()) else {
+                    continue;
+                };
+                match originals.entry((variant.clone(), resolved_status.clone(), sha256)) {
+                    Entry::Occupied(original) => {
+                        log::debug!(
+                            "{} is identical to bundled file {}",
+                            bundled_file.get_print_path(),
+                            original.get()
+                        );
+                        bundled_file.dedup_of = Some(original.get().clone());
+                        self.num_deduplicated_files += 1;
+                        self.num_deduplicated_bytes += num_bytes;
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(bundled_file.path.clone());
+                    }
+                }
+            }
+        }
+    }
+
     fn scan_from_glob<T: AsRef<str>, U: AsRef<str>>(
         glob_path: T,
         repo_root: U,
@@ -143,9 +516,18 @@ impl FileSetBuilder {
             Path::new(repo_root.as_ref()).join(glob_path)
         };
 
-        let paths = glob(&path_to_scan.to_string_lossy())?
+        let mut paths: Vec<PathBuf> = glob(&path_to_scan.to_string_lossy())?
             .filter_map(|entry| entry.ok().filter(|path| path.is_file()))
             .collect();
+        // Glob iteration order varies between filesystems, so number files in a stable,
+        // locale-independent order of their repo-relative paths.
+        paths.sort_by(|a, b| {
+            let a = a.strip_prefix(repo_root.as_ref()).unwrap_or(a);
+            let b = b.strip_prefix(repo_root.as_ref()).unwrap_or(b);
+            a.as_os_str()
+                .as_encoded_bytes()
+                .cmp(b.as_os_str().as_encoded_bytes())
+        });
 
         Ok(paths)
     }
@@ -154,12 +536,19 @@ impl FileSetBuilder {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", derive(Tsify))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "FileSet", free_immediately, size)
+)]
 pub struct FileSet {
     pub file_set_type: FileSetType,
     pub files: Vec<BundledFile>,
     pub glob: String,
     /// Added in v0.6.11. Populated when parsing from BEP, not from junit globs
     pub resolved_status: Option<JunitReportStatus>,
+    /// Variant of the test cases of the file set, which is part of their IDs
+    #[serde(default)]
+    pub variant: Option<String>,
 }
 
 impl FileSet {
@@ -167,14 +556,39 @@ impl FileSet {
         files: Vec<BundledFile>,
         glob: String,
         resolved_status: Option<JunitReportStatus>,
+        variant: Option<String>,
     ) -> Self {
         Self {
             file_set_type: FileSetType::Junit,
             files,
             glob,
             resolved_status,
+            variant,
+        }
+    }
+}
+
+#[cfg(feature = "ruby")]
+impl FileSet {
+    pub fn file_set_type(&self) -> String {
+        match self.file_set_type {
+            FileSetType::Junit => String::from("Junit"),
         }
     }
+    pub fn files(&self) -> Vec<BundledFile> {
+        self.files.clone()
+    }
+    pub fn glob(&self) -> String {
+        self.glob.clone()
+    }
+    pub fn resolved_status(&self) -> Option<String> {
+        self.resolved_status
+            .as_ref()
+            .map(|resolved_status| resolved_status.to_string())
+    }
+    pub fn variant(&self) -> Option<String> {
+        self.variant.clone()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
@@ -197,10 +611,32 @@ pub struct BundledFile {
     pub path: String,
     pub owners: Vec<String>,
     pub team: Option<String>,
+    /// Added in v0.6.7, hex encoded SHA-256 of the file as written to the tarball
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// `path` of the byte-identical file that is in the tarball in place of this one, which
+    /// isn't bundled itself. Added in meta version 2.
+    #[serde(default)]
+    pub dedup_of: Option<String>,
+    /// Whether sanitizing the junit file while bundling it changed it, unset when it wasn't
+    /// sanitized. Added in v0.6.10
+    #[serde(default)]
+    pub junit_sanitized: Option<bool>,
+    /// Size of the junit file as bundled minus its size before it was sanitized, when sanitizing
+    /// changed it. Added in v0.6.10
+    #[serde(default)]
+    pub junit_sanitized_size_delta: Option<i64>,
+    /// Where the file was extracted to when `original_path` points inside an archive
+    #[serde(skip)]
+    pub extracted_path: Option<String>,
 }
 
 #[cfg(not(feature = "wasm"))]
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "BundledFile", free_immediately, size)
+)]
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
 pub struct BundledFile {
     pub original_path: String,
@@ -213,9 +649,28 @@ pub struct BundledFile {
     pub last_modified_epoch_ns: u128,
     pub owners: Vec<String>,
     pub team: Option<String>,
+    /// Added in v0.6.7, hex encoded SHA-256 of the file as written to the tarball
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// `path` of the byte-identical file that is in the tarball in place of this one, which
+    /// isn't bundled itself. Added in meta version 2.
+    #[serde(default)]
+    pub dedup_of: Option<String>,
+    /// Whether sanitizing the junit file while bundling it changed it, unset when it wasn't
+    /// sanitized. Added in v0.6.10
+    #[serde(default)]
+    pub junit_sanitized: Option<bool>,
+    /// Size of the junit file as bundled minus its size before it was sanitized, when sanitizing
+    /// changed it. Added in v0.6.10
+    #[serde(default)]
+    pub junit_sanitized_size_delta: Option<i64>,
+    /// Where the file was extracted to when `original_path` points inside an archive
+    #[serde(skip)]
+    pub extracted_path: Option<String>,
 }
 
 impl BundledFile {
+    /// Files that are not uploaded are returned as the rule that excluded them.
     pub fn from_path<T: AsRef<Path>, U: Debug>(
         path: &Path,
         file_index: usize,
@@ -224,17 +679,82 @@ impl BundledFile {
         team: Option<String>,
         codeowners: &Option<CodeOwners>,
         start: Option<SystemTime>,
-    ) -> anyhow::Result<Option<Self>> {
+    ) -> anyhow::Result<Result<Self, FileDecisionRule>> {
+        let (original_path_abs, original_path_rel) = Self::original_paths(path, repo_root)?;
+        Self::new(
+            path,
+            original_path_abs,
+            original_path_rel,
+            None,
+            file_index,
+            glob_path,
+            team,
+            codeowners,
+            start,
+        )
+    }
+
+    /// Creates a file for a junit file extracted from an archive, recording its original path as
+    /// `path/to/archive.zip!path/in/archive.xml`. Owners and staleness come from the archive.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_archive_entry<T: AsRef<Path>, U: Debug>(
+        archive_path: &Path,
+        entry: &ArchiveEntry,
+        file_index: usize,
+        repo_root: T,
+        glob_path: U,
+        team: Option<String>,
+        codeowners: &Option<CodeOwners>,
+        start: Option<SystemTime>,
+    ) -> anyhow::Result<Result<Self, FileDecisionRule>> {
+        let (archive_path_abs, archive_path_rel) = Self::original_paths(archive_path, repo_root)?;
+        let extracted_path = entry
+            .extracted_path
+            .to_str()
+            .ok_or_else(|| anyhow::Error::msg("failed to convert path to string"))?
+            .to_string();
+        Self::new(
+            archive_path,
+            archive_entry_path(archive_path_abs, &entry.name),
+            archive_entry_path(archive_path_rel, &entry.name),
+            Some(extracted_path),
+            file_index,
+            glob_path,
+            team,
+            codeowners,
+            start,
+        )
+    }
+
+    fn original_paths<T: AsRef<Path>>(
+        path: &Path,
+        repo_root: T,
+    ) -> anyhow::Result<(String, String)> {
         let original_path_abs = path
             .to_str()
             .ok_or_else(|| anyhow::Error::msg("failed to convert path to string"))?
             .to_string();
         let original_path_rel = path
             .strip_prefix(repo_root)
-            .unwrap_or(&path)
+            .unwrap_or(path)
             .to_str()
             .ok_or_else(|| anyhow::Error::msg("failed to convert path to string"))?
             .to_string();
+        Ok((original_path_abs, original_path_rel))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new<U: Debug>(
+        path: &Path,
+        original_path_abs: String,
+        original_path_rel: String,
+        extracted_path: Option<String>,
+        file_index: usize,
+        glob_path: U,
+        team: Option<String>,
+        codeowners: &Option<CodeOwners>,
+        start: Option<SystemTime>,
+    ) -> anyhow::Result<Result<Self, FileDecisionRule>> {
         // Check if file is allowed.
         let mut is_allowed = false;
         for allow in ALLOW_LIST {
@@ -246,7 +766,7 @@ impl BundledFile {
         }
         if !is_allowed {
             log::warn!("File {:?} from glob {:?} is not allowed", path, glob_path);
-            return Ok(None);
+            return Ok(Err(FileDecisionRule::NotAllowed));
         }
 
         // When start is provided, check if file is stale
@@ -254,7 +774,7 @@ impl BundledFile {
             let modified = path.metadata()?.modified()?;
             if modified < start {
                 log::warn!("File {:?} from glob {:?} is stale", path, glob_path);
-                return Ok(None);
+                return Ok(Err(FileDecisionRule::Stale));
             }
         }
 
@@ -281,9 +801,9 @@ impl BundledFile {
         } else if original_path_abs.ends_with(".bin") {
             path_formatted = format!("internal/{}", file_index);
         } else {
-            return Ok(None);
+            return Ok(Err(FileDecisionRule::UnsupportedExtension));
         }
-        Ok(Some(Self {
+        Ok(Ok(Self {
             original_path: original_path_abs,
             original_path_rel: Some(original_path_rel),
             path: path_formatted,
@@ -295,6 +815,11 @@ impl BundledFile {
                 .as_nanos(),
             owners,
             team,
+            sha256: None,
+            dedup_of: None,
+            junit_sanitized: None,
+            junit_sanitized_size_delta: None,
+            extracted_path,
         }))
     }
 
@@ -303,4 +828,451 @@ impl BundledFile {
             .as_ref()
             .unwrap_or(&self.original_path)
     }
+
+    /// Path to read the file's contents from, which differs from `original_path` for files
+    /// extracted from an archive
+    pub fn read_path(&self) -> &str {
+        self.extracted_path.as_ref().unwrap_or(&self.original_path)
+    }
+}
+
+#[cfg(feature = "ruby")]
+impl BundledFile {
+    pub fn original_path(&self) -> String {
+        self.original_path.clone()
+    }
+    pub fn original_path_rel(&self) -> Option<String> {
+        self.original_path_rel.clone()
+    }
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+    pub fn owners(&self) -> Vec<String> {
+        self.owners.clone()
+    }
+    pub fn team(&self) -> Option<String> {
+        self.team.clone()
+    }
+    pub fn sha256(&self) -> Option<String> {
+        self.sha256.clone()
+    }
+    pub fn dedup_of(&self) -> Option<String> {
+        self.dedup_of.clone()
+    }
+    pub fn junit_sanitized(&self) -> Option<bool> {
+        self.junit_sanitized
+    }
+    pub fn junit_sanitized_size_delta(&self) -> Option<i64> {
+        self.junit_sanitized_size_delta
+    }
+}
+
+/// The size and hex encoded SHA-256 of the file at `path`
+fn sha256_of_file(path: &str) -> std::io::Result<(u64, String)> {
+    let mut sha256_writer = Sha256Writer::new(std::io::sink());
+    let num_bytes = std::io::copy(&mut std::fs::File::open(path)?, &mut sha256_writer)?;
+    let (_, sha256, _) = sha256_writer.finish();
+    Ok((num_bytes, sha256))
+}
+
+/// Whether `file` was last modified longer than `max_age` before `since`. Files modified after
+/// `since`, e.g. written by the test run, are kept; only those modified after the current time
+/// point at clock skew.
+fn is_expired(file: &Path, max_age: Duration, since: SystemTime) -> bool {
+    let Ok(modified) = file.metadata().and_then(|metadata| metadata.modified()) else {
+        return false;
+    };
+    match since.duration_since(modified) {
+        Ok(age) if age > max_age => {
+            log::info!(
+                "Skipping {:?}, it was last modified {} ago",
+                file,
+                humantime::format_duration(Duration::from_secs(age.as_secs()))
+            );
+            true
+        }
+        Ok(..) => false,
+        Err(..) => {
+            if let Err(e) = SystemTime::now().duration_since(modified) {
+                log::warn!(
+                    "File {:?} was last modified {} in the future, check the system clock",
+                    file,
+                    humantime::format_duration(Duration::from_secs(e.duration().as_secs()))
+                );
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use context::junit::junit_path::JunitReportFileWithStatus;
+    use filetime::FileTime;
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{FileDecisionRule, FileSetBuilder};
+    use crate::archive::test_utils::write_zip;
+
+    fn file_listing(repo_root: &str) -> Vec<(String, Option<String>)> {
+        let file_set_builder = FileSetBuilder::build_file_sets(
+            repo_root,
+            &[JunitReportFileWithStatus {
+                junit_path: String::from("**/*.xml"),
+                status: None,
+                variant: None,
+            }],
+            &None,
+            &None::<&str>,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+        file_set_builder
+            .file_sets()
+            .iter()
+            .flat_map(|file_set| &file_set.files)
+            .map(|file| (file.path.clone(), file.original_path_rel.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn numbers_files_by_repo_relative_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for dir in ["b", "a/b", "a-b", "A"] {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("junit.xml"), "").unwrap();
+        }
+        let repo_root = temp_dir.path().to_str().unwrap();
+
+        let file_listing_1 = file_listing(repo_root);
+        let file_listing_2 = file_listing(repo_root);
+
+        assert_eq!(file_listing_1, file_listing_2);
+        assert_eq!(
+            file_listing_1,
+            [
+                ("junit/0", "A/junit.xml"),
+                ("junit/1", "a-b/junit.xml"),
+                ("junit/2", "a/b/junit.xml"),
+                ("junit/3", "b/junit.xml"),
+            ]
+            .map(|(path, original_path_rel)| (
+                String::from(path),
+                Some(String::from(original_path_rel))
+            ))
+        );
+    }
+
+    #[test]
+    fn skips_non_junit_files_matched_by_broad_globs() {
+        const NUM_NON_JUNIT_FILES: usize = 1_000;
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("config")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("test-results")).unwrap();
+        // junit-looking content past the sniffed bytes is never read
+        let non_junit_xml = format!(
+            "<?xml version=\"1.0\"?>\n<checkstyle>{}<testsuite name=\"not\"/></checkstyle>",
+            "<file name=\"Main.java\"/>".repeat(1_000)
+        );
+        for i in 0..NUM_NON_JUNIT_FILES {
+            fs::write(
+                temp_dir.path().join("config").join(format!("{i}.xml")),
+                &non_junit_xml,
+            )
+            .unwrap();
+        }
+        fs::write(
+            temp_dir.path().join("test-results/a.xml"),
+            "\u{feff}<?xml version=\"1.0\"?>\n<!-- by a test runner -->\n<testsuites/>",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("test-results/b.xml"),
+            "<testsuite name=\"b\"/>",
+        )
+        .unwrap();
+        // can't tell without a root element, so left to the parser
+        fs::write(temp_dir.path().join("test-results/empty.xml"), "").unwrap();
+        let repo_root = temp_dir.path().to_str().unwrap();
+
+        let file_set_builder = FileSetBuilder::build_file_sets(
+            repo_root,
+            &[JunitReportFileWithStatus {
+                junit_path: String::from("**/*.xml"),
+                status: None,
+                variant: None,
+            }],
+            &None,
+            &None::<&str>,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(file_set_builder.count(), 3);
+        assert_eq!(
+            file_set_builder.num_skipped_non_junit_files(),
+            NUM_NON_JUNIT_FILES
+        );
+        assert_eq!(
+            file_set_builder
+                .file_sets()
+                .iter()
+                .flat_map(|file_set| &file_set.files)
+                .map(|file| file.get_print_path())
+                .collect::<Vec<_>>(),
+            [
+                "test-results/a.xml",
+                "test-results/b.xml",
+                "test-results/empty.xml"
+            ]
+        );
+    }
+
+    #[test]
+    fn respects_gitignore_when_asked_to() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join(".idea")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("test-results")).unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), ".idea/\n").unwrap();
+        fs::write(temp_dir.path().join(".idea/junit.xml"), "<testsuites/>").unwrap();
+        fs::write(
+            temp_dir.path().join("test-results/junit.xml"),
+            "<testsuites/>",
+        )
+        .unwrap();
+        let repo_root = temp_dir.path().to_str().unwrap();
+
+        let build_file_sets = |respect_gitignore| {
+            FileSetBuilder::build_file_sets(
+                repo_root,
+                &[JunitReportFileWithStatus {
+                    junit_path: String::from("**/*.xml"),
+                    status: None,
+                    variant: None,
+                }],
+                &None,
+                &None::<&str>,
+                None,
+                respect_gitignore,
+                None,
+            )
+            .unwrap()
+        };
+
+        let file_set_builder = build_file_sets(false);
+        assert_eq!(file_set_builder.count(), 2);
+        assert_eq!(file_set_builder.num_gitignored_files(), 0);
+
+        let file_set_builder = build_file_sets(true);
+        assert_eq!(file_set_builder.count(), 1);
+        assert_eq!(file_set_builder.num_gitignored_files(), 1);
+        assert_eq!(
+            file_set_builder.file_sets()[0].files[0].get_print_path(),
+            "test-results/junit.xml"
+        );
+    }
+
+    #[test]
+    fn skips_files_older_than_max_age() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for (name, modified) in [
+            ("stale.xml", now - Duration::from_secs(3 * 60 * 60)),
+            ("fresh.xml", now - Duration::from_secs(60)),
+            ("skewed.xml", now + Duration::from_secs(60 * 60)),
+        ] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, "<testsuites/>").unwrap();
+            filetime::set_file_mtime(&path, FileTime::from_system_time(modified)).unwrap();
+        }
+        let repo_root = temp_dir.path().to_str().unwrap();
+
+        let build_file_sets = |exec_start, max_age| {
+            FileSetBuilder::build_file_sets(
+                repo_root,
+                &[JunitReportFileWithStatus {
+                    junit_path: String::from("*.xml"),
+                    status: None,
+                    variant: None,
+                }],
+                &None,
+                &None::<&str>,
+                exec_start,
+                false,
+                max_age,
+            )
+            .unwrap()
+        };
+
+        let file_set_builder = build_file_sets(None, None);
+        assert_eq!(file_set_builder.count(), 3);
+        assert_eq!(file_set_builder.num_expired_files(), 0);
+
+        let file_set_builder = build_file_sets(None, Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(file_set_builder.count(), 2);
+        assert_eq!(file_set_builder.num_expired_files(), 1);
+        let mut print_paths = file_set_builder.file_sets()[0]
+            .files
+            .iter()
+            .map(|file| file.get_print_path())
+            .collect::<Vec<_>>();
+        print_paths.sort();
+        assert_eq!(print_paths, vec!["fresh.xml", "skewed.xml"]);
+
+        // Files written after the tests started are fresh
+        let exec_start = now - Duration::from_secs(5 * 60 * 60);
+        let file_set_builder =
+            build_file_sets(Some(exec_start), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(file_set_builder.count(), 3);
+        assert_eq!(file_set_builder.num_expired_files(), 0);
+    }
+
+    #[test]
+    fn decides_each_file_by_the_first_rule_that_excludes_it() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        fs::create_dir_all(temp_dir.path().join("ignored")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("other")).unwrap();
+        fs::write(temp_dir.path().join(".gitignore"), "ignored/\n").unwrap();
+        for (name, contents, age) in [
+            ("junit.xml", "<testsuites/>", 0),
+            ("ignored/junit.xml", "<testsuites/>", 0),
+            ("expired.xml", "<testsuites/>", 4 * 60 * 60),
+            ("stale.xml", "<testsuites/>", 90 * 60),
+            ("checkstyle.xml", "<checkstyle/>", 0),
+            ("other/notes.txt", "", 0),
+            ("other/results.junit", "<testsuites/>", 0),
+        ] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            filetime::set_file_mtime(
+                &path,
+                FileTime::from_system_time(now - Duration::from_secs(age)),
+            )
+            .unwrap();
+        }
+        let repo_root = temp_dir.path().to_str().unwrap();
+
+        let file_set_builder = FileSetBuilder::build_file_sets(
+            repo_root,
+            &["**/*.xml", "other/*"].map(|junit_path| JunitReportFileWithStatus {
+                junit_path: String::from(junit_path),
+                status: None,
+                variant: None,
+            }),
+            &None,
+            &None::<&str>,
+            Some(now - Duration::from_secs(60 * 60)),
+            true,
+            Some(Duration::from_secs(2 * 60 * 60)),
+        )
+        .unwrap();
+
+        let mut file_decisions = file_set_builder
+            .file_decisions()
+            .iter()
+            .map(|file_decision| {
+                (
+                    file_decision.glob.as_str(),
+                    file_decision.path.as_str(),
+                    file_decision.rule,
+                )
+            })
+            .collect::<Vec<_>>();
+        file_decisions.sort_by_key(|(glob, path, _)| (*glob, *path));
+        assert_eq!(
+            file_decisions,
+            [
+                ("**/*.xml", "checkstyle.xml", FileDecisionRule::NotJunit),
+                ("**/*.xml", "expired.xml", FileDecisionRule::Expired),
+                (
+                    "**/*.xml",
+                    "ignored/junit.xml",
+                    FileDecisionRule::Gitignored
+                ),
+                ("**/*.xml", "junit.xml", FileDecisionRule::Included),
+                ("**/*.xml", "stale.xml", FileDecisionRule::Stale),
+                ("other/*", "other/notes.txt", FileDecisionRule::NotAllowed),
+                (
+                    "other/*",
+                    "other/results.junit",
+                    FileDecisionRule::UnsupportedExtension
+                ),
+            ]
+        );
+        assert_eq!(file_set_builder.count(), 1);
+    }
+
+    #[test]
+    fn extracts_junit_files_from_archives() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("results.zip"),
+            write_zip(&[
+                ("nested/junit1.xml", "<testsuites name=\"zip\" />"),
+                ("notes.txt", ""),
+            ]),
+        )
+        .unwrap();
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            fs::File::create(temp_dir.path().join("results.tgz")).unwrap(),
+            Compression::default(),
+        ));
+        let contents = "<testsuites name=\"tgz\" />";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, "junit2.xml", contents.as_bytes())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+        let repo_root = temp_dir.path().to_str().unwrap();
+
+        let file_set_builder = FileSetBuilder::build_file_sets(
+            repo_root,
+            &[JunitReportFileWithStatus {
+                junit_path: String::from("results.*"),
+                status: None,
+                variant: None,
+            }],
+            &None,
+            &None::<&str>,
+            None,
+            false,
+            None,
+        )
+        .unwrap();
+
+        let files = file_set_builder
+            .file_sets()
+            .iter()
+            .flat_map(|file_set| &file_set.files)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| (file.path.as_str(), file.get_print_path()))
+                .collect::<Vec<_>>(),
+            [
+                ("junit/0", "results.tgz!junit2.xml"),
+                ("junit/1", "results.zip!nested/junit1.xml"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(files[0].read_path()).unwrap(),
+            "<testsuites name=\"tgz\" />"
+        );
+        assert_eq!(
+            fs::read_to_string(files[1].read_path()).unwrap(),
+            "<testsuites name=\"zip\" />"
+        );
+    }
 }
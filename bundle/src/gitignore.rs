@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+
+/// Matches paths against the `.gitignore` files of a repo and its `.git/info/exclude`, like git
+/// does for untracked files.
+#[derive(Debug)]
+pub(crate) struct GitignoreFilter {
+    repo_root: PathBuf,
+    info_exclude: Gitignore,
+    /// The `.gitignore` of each directory looked at so far, if it has one
+    gitignores: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl GitignoreFilter {
+    pub fn new<T: AsRef<Path>>(repo_root: T) -> Self {
+        let repo_root = repo_root.as_ref().to_path_buf();
+        let info_exclude = Self::build(
+            &repo_root,
+            &repo_root.join(".git").join("info").join("exclude"),
+        )
+        .unwrap_or_else(Gitignore::empty);
+        Self {
+            repo_root,
+            info_exclude,
+            gitignores: HashMap::new(),
+        }
+    }
+
+    /// The `.gitignore` of the deepest directory with a matching pattern decides, and
+    /// `.git/info/exclude` applies when none has one. Paths outside of the repo are never ignored.
+    pub fn is_ignored(&mut self, path: &Path) -> bool {
+        let Ok(path_rel) = path.strip_prefix(&self.repo_root) else {
+            return false;
+        };
+        let dirs = path_rel
+            .ancestors()
+            .skip(1)
+            .map(|dir_rel| self.repo_root.join(dir_rel))
+            .collect::<Vec<_>>();
+        for dir in dirs {
+            let gitignore = self
+                .gitignores
+                .entry(dir.clone())
+                .or_insert_with(|| Self::build(&dir, &dir.join(".gitignore")));
+            match gitignore
+                .as_ref()
+                .map(|gitignore| gitignore.matched_path_or_any_parents(path, false))
+            {
+                Some(Match::Ignore(..)) => return true,
+                Some(Match::Whitelist(..)) => return false,
+                Some(Match::None) | None => (),
+            }
+        }
+        self.info_exclude
+            .matched_path_or_any_parents(path, false)
+            .is_ignore()
+    }
+
+    fn build(root: &Path, gitignore_path: &Path) -> Option<Gitignore> {
+        if !gitignore_path.is_file() {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(e) = builder.add(gitignore_path) {
+            log::debug!("Failed to read {}: {}", gitignore_path.display(), e);
+        }
+        builder
+            .build()
+            .map_err(|e| log::debug!("Failed to read {}: {}", gitignore_path.display(), e))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_nested_gitignores_and_info_exclude() {
+        let repo_root = tempfile::tempdir().unwrap();
+        let repo_root = repo_root.path();
+        std::fs::create_dir_all(repo_root.join(".git/info")).unwrap();
+        std::fs::create_dir_all(repo_root.join("app/build/test-results")).unwrap();
+        std::fs::write(repo_root.join(".git/info/exclude"), ".idea/\n").unwrap();
+        std::fs::write(repo_root.join(".gitignore"), "*.xml\nbuild/\n").unwrap();
+        std::fs::write(
+            repo_root.join("app/.gitignore"),
+            "!build/\n!build/test-results/*.xml\n",
+        )
+        .unwrap();
+
+        let mut gitignore_filter = GitignoreFilter::new(repo_root);
+        assert!(gitignore_filter.is_ignored(&repo_root.join("checkstyle.xml")));
+        assert!(gitignore_filter.is_ignored(&repo_root.join("lib/build/junit.xml")));
+        assert!(gitignore_filter.is_ignored(&repo_root.join(".idea/workspace.xml")));
+        assert!(!gitignore_filter.is_ignored(&repo_root.join("app/build/test-results/junit.xml")));
+        assert!(!gitignore_filter.is_ignored(&repo_root.join("README.md")));
+        assert!(!gitignore_filter.is_ignored(Path::new("/elsewhere/junit.xml")));
+    }
+}
@@ -1,11 +1,16 @@
+mod archive;
 mod bundle_meta;
 mod bundler;
 mod custom_tag;
 mod files;
+mod gitignore;
+mod tar_entry;
 mod types;
 
+pub use archive::*;
 pub use bundle_meta::*;
 pub use bundler::*;
 pub use custom_tag::*;
 pub use files::*;
+pub use tar_entry::*;
 pub use types::*;
@@ -0,0 +1,118 @@
+use std::fmt;
+
+/// A tar entry that must not be written to or read from a bundle, since extracting it could write
+/// outside of the destination directory and its name could collide with the entries we look up
+/// by name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsafeTarEntry {
+    AbsolutePath { name: String },
+    ParentDir { name: String },
+    Link { name: String },
+}
+
+impl UnsafeTarEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::AbsolutePath { name } | Self::ParentDir { name } | Self::Link { name } => name,
+        }
+    }
+}
+
+impl fmt::Display for UnsafeTarEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AbsolutePath { name } => {
+                write!(f, "bundle entry {:?} has an absolute path", name)
+            }
+            Self::ParentDir { name } => write!(f, "bundle entry {:?} has a `..` component", name),
+            Self::Link { name } => write!(f, "bundle entry {:?} is a link", name),
+        }
+    }
+}
+
+impl std::error::Error for UnsafeTarEntry {}
+
+/// Checks a name given to a bundle entry. Both separators are checked regardless of the platform,
+/// since bundles are read on other platforms than the one that made them.
+///
+pub fn check_tar_entry_name<T: AsRef<str>>(name: T) -> Result<(), UnsafeTarEntry> {
+    let name = name.as_ref();
+    let is_absolute = name.starts_with(['/', '\\'])
+        || matches!(name.as_bytes(), [drive, b':', ..] if drive.is_ascii_alphabetic());
+    if is_absolute {
+        return Err(UnsafeTarEntry::AbsolutePath {
+            name: name.to_string(),
+        });
+    }
+    if name.split(['/', '\\']).any(|component| component == "..") {
+        return Err(UnsafeTarEntry::ParentDir {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The name of an entry read from a bundle, when it is safe to use.
+///
+pub fn tar_entry_name(path_bytes: &[u8], is_link: bool) -> Result<String, UnsafeTarEntry> {
+    let name = String::from_utf8_lossy(path_bytes).into_owned();
+    check_tar_entry_name(&name)?;
+    if is_link {
+        return Err(UnsafeTarEntry::Link { name });
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Appends an entry without the checks `tar::Builder` makes on paths, like a crafted bundle.
+    pub fn append_raw_entry<W: std::io::Write>(
+        tar: &mut tar::Builder<W>,
+        name: &str,
+        entry_type: tar::EntryType,
+        data: &[u8],
+    ) {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_mode(0o644);
+        header.set_size(data.len() as u64);
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            header.set_link_name("/etc/passwd").unwrap();
+        }
+        header.set_cksum();
+        tar.append(&header, data).unwrap();
+    }
+
+    #[test]
+    fn checks_entry_names() {
+        for name in ["meta.json", "junit/0", "junit/a..b.xml", "./junit/0"] {
+            assert_eq!(check_tar_entry_name(name), Ok(()), "{name}");
+        }
+        for name in ["/etc/passwd", "\\share\\file", "C:\\file", "c:/file"] {
+            assert_eq!(
+                check_tar_entry_name(name),
+                Err(UnsafeTarEntry::AbsolutePath {
+                    name: name.to_string()
+                })
+            );
+        }
+        for name in ["../../etc/passwd", "junit/../../x", "junit\\..\\x", ".."] {
+            assert_eq!(
+                check_tar_entry_name(name),
+                Err(UnsafeTarEntry::ParentDir {
+                    name: name.to_string()
+                })
+            );
+        }
+        assert_eq!(
+            tar_entry_name(b"meta.json", true),
+            Err(UnsafeTarEntry::Link {
+                name: String::from("meta.json")
+            })
+        );
+    }
+}
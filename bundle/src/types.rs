@@ -1,4 +1,4 @@
-use context::repo::RepoUrlParts;
+use context::{info_id::gen_info_id, repo::RepoUrlParts};
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
@@ -33,6 +33,7 @@ impl Test {
         org_slug: T,
         repo: &RepoUrlParts,
         timestamp_millis: Option<i64>,
+        variant: Option<&str>,
     ) -> Self {
         let mut test = Self {
             parent_name,
@@ -43,24 +44,69 @@ impl Test {
             timestamp_millis,
         };
 
-        test.set_id(org_slug, repo);
+        test.set_id(org_slug, repo, variant);
 
         test
     }
 
-    pub fn set_id<T: AsRef<str>>(&mut self, org_slug: T, repo: &RepoUrlParts) {
-        let info_id_input = [
+    /// Tests of a variant, e.g. the platform they ran on, get IDs of their own. Without a variant,
+    /// the ID is the same as before variants were supported.
+    pub fn set_id<T: AsRef<str>>(
+        &mut self,
+        org_slug: T,
+        repo: &RepoUrlParts,
+        variant: Option<&str>,
+    ) {
+        self.id = gen_info_id(
             org_slug.as_ref(),
-            repo.repo_full_name().as_str(),
-            self.file.as_deref().unwrap_or(""),
-            self.class_name.as_deref().unwrap_or(""),
+            &repo.repo_full_name(),
+            self.file.as_deref(),
+            self.class_name.as_deref(),
             &self.parent_name,
             &self.name,
-            "JUNIT_TESTCASE",
-        ]
-        .join("#");
-        self.id =
-            uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, info_id_input.as_bytes()).to_string()
+            None,
+            variant,
+        );
+    }
+}
+
+/// Why a test run passed or failed after quarantining, which decides its exit code
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(tag = "outcome")]
+pub enum QuarantineOutcome {
+    #[default]
+    NoFailures,
+    AllQuarantined {
+        count: usize,
+    },
+    SomeUnquarantined {
+        quarantined: usize,
+        unquarantined: usize,
+    },
+    /// There were failures, but quarantining was disabled or its config could not be fetched
+    QuarantiningUnavailable,
+}
+
+impl std::fmt::Display for QuarantineOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuarantineOutcome::NoFailures => write!(f, "No test failures"),
+            QuarantineOutcome::AllQuarantined { count } => {
+                write!(f, "All {} test failure(s) were quarantined", count)
+            }
+            QuarantineOutcome::SomeUnquarantined {
+                quarantined,
+                unquarantined,
+            } => write!(
+                f,
+                "{} test failure(s) were not quarantined, {} were quarantined",
+                unquarantined, quarantined
+            ),
+            QuarantineOutcome::QuarantiningUnavailable => write!(
+                f,
+                "Quarantining was unavailable, no test failures were quarantined"
+            ),
+        }
     }
 }
 
@@ -198,12 +244,18 @@ mod tests {
             org_slug,
             &repo,
             Some(0),
+            None,
         );
         assert_eq!(result.name, name);
         assert_eq!(result.parent_name, parent_name);
         assert_eq!(result.class_name, class_name);
         assert_eq!(result.file, file);
         assert_eq!(result.id, "aad1f138-09ab-5ea9-9c21-af48a03d6edd");
+        let mut with_variant = result.clone();
+        with_variant.set_id(org_slug, &repo, Some("ios"));
+        assert_ne!(with_variant.id, result.id);
+        with_variant.set_id(org_slug, &repo, Some(""));
+        assert_eq!(with_variant.id, result.id);
         let result = Test {
             name: name.clone(),
             parent_name: parent_name.clone(),
@@ -0,0 +1,176 @@
+use std::sync::{Arc, Mutex};
+
+use api::{
+    error_code::ErrorCode,
+    message::{ValidateTokenOrgRequest, ValidateTokenOrgResponse},
+};
+use assert_cmd::Command;
+use assert_matches::assert_matches;
+use axum::{extract::State, http::HeaderMap, http::StatusCode, Json};
+use github_actions::oidc::{ID_TOKEN_REQUEST_TOKEN_ENV, ID_TOKEN_REQUEST_URL_ENV};
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{
+    MockServerBuilder, RequestPayload, SharedMockServerState, GITHUB_ID_TOKEN_PATH,
+    MOCK_EXCHANGED_API_TOKEN, MOCK_GITHUB_OIDC_TOKEN,
+};
+
+use crate::command_builder::CommandBuilder;
+use crate::utils::{generate_mock_git_repo, generate_mock_valid_junit_xmls};
+
+fn oidc_upload_command(command_builder: &CommandBuilder, server_host: &str) -> Command {
+    let mut command = command_builder.command();
+    command
+        .env("GITHUB_ACTIONS", "true")
+        .env(
+            ID_TOKEN_REQUEST_URL_ENV,
+            format!("{}{}?api-version=2.0", server_host, GITHUB_ID_TOKEN_PATH),
+        )
+        .env(ID_TOKEN_REQUEST_TOKEN_ENV, "id-token-request-token")
+        .args(["--auth", "oidc"]);
+    command
+}
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_with_oidc_token() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let api_tokens = Arc::new(Mutex::new(Vec::new()));
+    let mut mock_server_builder = MockServerBuilder::new();
+    let handler_api_tokens = api_tokens.clone();
+    mock_server_builder.set_validate_token_org_handler(
+        move |State(state): State<SharedMockServerState>,
+              headers: HeaderMap,
+              Json(request): Json<ValidateTokenOrgRequest>| async move {
+            handler_api_tokens.lock().unwrap().push(
+                headers
+                    .get("x-api-token")
+                    .and_then(|api_token| api_token.to_str().ok())
+                    .map(String::from),
+            );
+            let org_url_slug = request.org_url_slug.clone();
+            state
+                .requests
+                .lock()
+                .unwrap()
+                .push(RequestPayload::ValidateTokenOrg(request));
+            Json(ValidateTokenOrgResponse { org_url_slug })
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = oidc_upload_command(
+        CommandBuilder::upload(temp_dir.path(), state.host.clone()).use_quarantining(false),
+        &state.host,
+    )
+    .assert()
+    .success()
+    .stderr(predicate::str::contains(
+        "Authenticated with the OIDC token of the GitHub Actions job",
+    ));
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(
+        requests[0],
+        RequestPayload::GitHubIdToken(String::from("api-version=2.0&audience=trunk.io"))
+    );
+    assert_matches!(
+        &requests[1],
+        RequestPayload::ExchangeOidcToken(request)
+            if request.oidc_token == MOCK_GITHUB_OIDC_TOKEN && request.org_url_slug == "test-org"
+    );
+    assert!(requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+    // the exchanged token is used in place of --token
+    assert_eq!(
+        api_tokens.lock().unwrap().as_slice(),
+        [Some(String::from(MOCK_EXCHANGED_API_TOKEN))]
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_with_oidc_token_outside_of_github_actions() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = oidc_upload_command(
+        &CommandBuilder::upload(temp_dir.path(), state.host.clone()),
+        &state.host,
+    )
+    .env_remove("GITHUB_ACTIONS")
+    .assert()
+    .code(ErrorCode::InvalidToken.exit_code())
+    .stderr(predicate::str::contains(
+        "OIDC authentication is only supported in GitHub Actions",
+    ));
+
+    assert!(state.requests.lock().unwrap().is_empty());
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_with_oidc_token_without_id_token_permission() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = oidc_upload_command(
+        &CommandBuilder::upload(temp_dir.path(), state.host.clone()),
+        &state.host,
+    )
+    .env_remove(ID_TOKEN_REQUEST_URL_ENV)
+    .env_remove(ID_TOKEN_REQUEST_TOKEN_ENV)
+    .assert()
+    .code(ErrorCode::InvalidToken.exit_code())
+    .stderr(predicate::str::contains(
+        "give the job the `id-token: write` permission",
+    ));
+
+    assert!(state.requests.lock().unwrap().is_empty());
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_with_oidc_token_rejected_by_trunk() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_exchange_oidc_token_handler(|| async { StatusCode::FORBIDDEN });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = oidc_upload_command(
+        &CommandBuilder::upload(temp_dir.path(), state.host.clone()),
+        &state.host,
+    )
+    .assert()
+    .code(ErrorCode::InvalidToken.exit_code())
+    .stderr(predicate::str::contains(
+        "Trunk rejected the OIDC token of the job",
+    ));
+
+    // nothing is uploaded without a token
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_matches!(&requests[0], RequestPayload::GitHubIdToken(_));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
@@ -0,0 +1,99 @@
+use std::{fs, io::BufReader};
+
+use assert_cmd::Command;
+use context::junit::parser::{extra_attrs, JunitParser};
+use predicates::prelude::*;
+use proto::test_context::test_run::TestCaseRunStatus;
+use quick_junit::TestCaseStatus;
+use tempfile::tempdir;
+
+use crate::utils::{generate_internal_bin_from_junits, generate_mock_valid_junit_xmls, CARGO_RUN};
+
+#[test]
+fn bin_to_junit_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let junit_temp_dir = tempdir().unwrap();
+    let mock_junits = generate_mock_valid_junit_xmls(&junit_temp_dir);
+    let internal_bin = generate_internal_bin_from_junits(&temp_dir, &mock_junits);
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["bin-to-junit", "internal.bin", "--output-dir", "out"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Wrote {} test case(s) to out/internal.xml",
+            internal_bin.test_case_runs.len()
+        )));
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+
+    let mut junit_parser = JunitParser::new();
+    junit_parser
+        .parse(BufReader::new(
+            fs::File::open(temp_dir.path().join("out/internal.xml")).unwrap(),
+        ))
+        .unwrap();
+    let reports = junit_parser.into_reports();
+    assert_eq!(reports.len(), 1);
+
+    let mut expected: Vec<(String, TestCaseRunStatus)> = internal_bin
+        .test_case_runs
+        .iter()
+        .map(|test_case_run| (test_case_run.id.clone(), test_case_run.status()))
+        .collect();
+    expected.sort();
+    let mut actual: Vec<(String, TestCaseRunStatus)> = reports[0]
+        .test_suites
+        .iter()
+        .flat_map(|test_suite| &test_suite.test_cases)
+        .map(|test_case| {
+            let status = match test_case.status {
+                TestCaseStatus::Success { .. } => TestCaseRunStatus::Success,
+                TestCaseStatus::NonSuccess { .. } => TestCaseRunStatus::Failure,
+                TestCaseStatus::Skipped { .. } => TestCaseRunStatus::Skipped,
+            };
+            (
+                test_case.extra.get(extra_attrs::ID).unwrap().to_string(),
+                status,
+            )
+        })
+        .collect();
+    actual.sort();
+    // ids and statuses survive, while e.g. system-out is not kept by internal bins
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn bin_to_junit_rejects_invalid_bin() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("garbage.bin"), b"\xff\xff\xff").unwrap();
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["bin-to-junit", "garbage.bin"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to decode internal bin"));
+    println!("{assert}");
+}
+
+#[test]
+fn bin_to_junit_prints_canonical_json() {
+    let temp_dir = tempdir().unwrap();
+    let junit_temp_dir = tempdir().unwrap();
+    let mock_junits = generate_mock_valid_junit_xmls(&junit_temp_dir);
+    let internal_bin = generate_internal_bin_from_junits(&temp_dir, &mock_junits);
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["bin-to-junit", "internal.bin", "--format", "json"])
+        .assert()
+        .success();
+    println!("{assert}");
+
+    let stdout: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(stdout, internal_bin.to_canonical_json());
+    // no junit files are written when printing the bin
+    assert!(!temp_dir.path().join("internal.xml").exists());
+}
@@ -15,6 +15,7 @@ pub struct UploadArgs {
     repo_head_commit_epoch: Option<String>,
     tags: Option<Vec<String>>,
     print_files: Option<bool>,
+    summary_group_by: Option<String>,
     no_upload: Option<bool>,
     team: Option<String>,
     codeowners_path: Option<String>,
@@ -32,6 +33,7 @@ impl UploadArgs {
             repo_head_commit_epoch: None,
             tags: None,
             print_files: None,
+            summary_group_by: None,
             no_upload: None,
             team: None,
             codeowners_path: None,
@@ -100,6 +102,14 @@ impl UploadArgs {
                 vec![]
             }
         }))
+        .chain(
+            self.summary_group_by
+                .clone()
+                .into_iter()
+                .flat_map(|summary_group_by: String| {
+                    vec![String::from("--summary-group-by"), summary_group_by]
+                }),
+        )
         .chain(self.no_upload.into_iter().flat_map(|no_upload: bool| {
             if no_upload {
                 vec![String::from("--repo-root")]
@@ -248,6 +258,22 @@ impl CommandType {
         self
     }
 
+    pub fn summary_group_by(&mut self, new_value: &str) -> &mut Self {
+        match self {
+            CommandType::Upload { upload_args, .. } => {
+                upload_args.summary_group_by = Some(String::from(new_value))
+            }
+            CommandType::Quarantine { upload_args, .. } => {
+                upload_args.summary_group_by = Some(String::from(new_value))
+            }
+            CommandType::Test { upload_args, .. } => {
+                upload_args.summary_group_by = Some(String::from(new_value))
+            }
+            CommandType::Validate { .. } => (),
+        }
+        self
+    }
+
     pub fn repo_root(&mut self, new_value: &str) -> &mut Self {
         match self {
             CommandType::Upload { upload_args, .. } => {
@@ -269,6 +295,8 @@ impl CommandType {
 pub enum PathsState {
     JunitPaths(String),
     BazelBepPath(String),
+    InternalBinPath(String),
+    MultiRepoConfig(String),
 }
 
 impl PathsState {
@@ -276,6 +304,12 @@ impl PathsState {
         match self {
             PathsState::JunitPaths(path) => vec![String::from("--junit-paths"), path.clone()],
             PathsState::BazelBepPath(path) => vec![String::from("--bazel-bep-path"), path.clone()],
+            PathsState::InternalBinPath(path) => {
+                vec![String::from("--internal-bin-path"), path.clone()]
+            }
+            PathsState::MultiRepoConfig(path) => {
+                vec![String::from("--multi-repo-config"), path.clone()]
+            }
         }
     }
 }
@@ -342,6 +376,16 @@ impl<'b> CommandBuilder<'b> {
         self
     }
 
+    pub fn internal_bin_path(&mut self, new_paths: &str) -> &mut Self {
+        self.paths_state = Some(PathsState::InternalBinPath(String::from(new_paths)));
+        self
+    }
+
+    pub fn multi_repo_config(&mut self, new_path: &str) -> &mut Self {
+        self.paths_state = Some(PathsState::MultiRepoConfig(String::from(new_path)));
+        self
+    }
+
     pub fn use_quarantining(&mut self, new_flag: bool) -> &mut Self {
         self.command_type.use_quarantining(new_flag);
         self
@@ -352,13 +396,23 @@ impl<'b> CommandBuilder<'b> {
         self
     }
 
+    pub fn summary_group_by(&mut self, new_value: &str) -> &mut Self {
+        self.command_type.summary_group_by(new_value);
+        self
+    }
+
     pub fn repo_root(&mut self, new_value: &str) -> &mut Self {
         self.command_type.repo_root(new_value);
         self
     }
 
     pub fn command(&self) -> Command {
-        let mut command = Command::new(CARGO_RUN.path());
+        Command::from_std(self.std_command())
+    }
+
+    /// For tests that need to interact with the CLI while it runs, e.g. to signal it
+    pub fn std_command(&self) -> std::process::Command {
+        let mut command = std::process::Command::new(CARGO_RUN.path());
         let args = self.build_args();
         let envs = self.build_envs();
         command.current_dir(self.current_dir).envs(envs).args(args);
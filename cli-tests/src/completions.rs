@@ -0,0 +1,26 @@
+use assert_cmd::Command;
+use test_utils::inputs::get_test_file_path;
+
+use crate::utils::CARGO_RUN;
+
+#[test]
+fn completions_match_golden_files() {
+    for shell in ["bash", "elvish", "fish", "powershell", "zsh"] {
+        let golden_path = format!("test_fixtures/completions/{shell}");
+        let golden = std::fs::read_to_string(get_test_file_path(&golden_path)).unwrap();
+        let assert = Command::new(CARGO_RUN.path())
+            .args(["completions", shell])
+            .assert()
+            .success();
+        let completions = String::from_utf8_lossy(&assert.get_output().stdout);
+        assert!(
+            completions == golden,
+            "completions of {shell} changed, update the golden file with:\ncargo run -- completions {shell} > cli-tests/{golden_path}",
+        );
+    }
+
+    Command::new(CARGO_RUN.path())
+        .args(["completions", "tcsh"])
+        .assert()
+        .failure();
+}
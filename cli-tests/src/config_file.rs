@@ -0,0 +1,110 @@
+use std::fs;
+
+use assert_cmd::Command;
+use assert_matches::assert_matches;
+use constants::{
+    TRUNK_API_CLIENT_RETRY_COUNT_ENV, TRUNK_API_TIMEOUT_SECS_ENV, TRUNK_PUBLIC_API_ADDRESS_ENV,
+};
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{MockServerBuilder, RequestPayload};
+
+use crate::utils::{generate_mock_git_repo, generate_mock_valid_junit_xmls, CARGO_RUN};
+
+const CONFIG_FILE: &str = r#"
+org_url_slug = "file-org"
+junit_paths = ["./*"]
+tags = { team = "platform" }
+api_timeout_secs = 5
+variants = "linux"
+"#;
+
+fn write_config_file(directory: &std::path::Path) {
+    fs::create_dir_all(directory.join(".trunk")).unwrap();
+    fs::write(directory.join(".trunk/flakytests.toml"), CONFIG_FILE).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_with_config_file_defaults() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    write_config_file(temp_dir.path());
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+        .env(TRUNK_API_CLIENT_RETRY_COUNT_ENV, "0")
+        .env("TRUNK_API_TOKEN", "test-token")
+        .args(["upload", "--use-quarantining=false"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Ignoring unknown key `variants` in .trunk/flakytests.toml, did you mean `variant`?",
+        ));
+    println!("{assert}");
+
+    let requests = state.requests.lock().unwrap().clone();
+    let create_bundle_upload_request = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::CreateBundleUpload(request) => Some(request),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(create_bundle_upload_request.org_url_slug, "file-org");
+    assert_matches!(requests.last(), Some(RequestPayload::UpdateBundleUpload(_)));
+}
+
+#[test]
+fn show_config_lists_sources() {
+    let temp_dir = tempdir().unwrap();
+    let config_path = temp_dir.path().join("custom.toml");
+    fs::write(&config_path, CONFIG_FILE).unwrap();
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env(TRUNK_API_TIMEOUT_SECS_ENV, "7")
+        .args([
+            "upload",
+            "--config",
+            &config_path.to_string_lossy(),
+            "--team",
+            "flag-team",
+            "--show-config",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  org_url_slug = \"file-org\" (file)\n",
+        ))
+        .stdout(predicate::str::contains("  team = \"flag-team\" (flag)\n"))
+        .stdout(predicate::str::contains(
+            "  api_timeout_secs = \"7\" (env)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "  use_quarantining = \"true\" (default)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "  codeowners_path = \"\" (unset)\n",
+        ));
+    println!("{assert}");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "upload",
+            "--config",
+            "missing.toml",
+            "--token",
+            "test-token",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to read config file missing.toml",
+        ));
+    println!("{assert}");
+}
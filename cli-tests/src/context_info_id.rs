@@ -0,0 +1,45 @@
+use assert_cmd::Command;
+use context::info_id::gen_info_id_vectors;
+
+use crate::utils::CARGO_RUN;
+
+#[test]
+fn context_info_id_matches_vectors() {
+    let vectors: Vec<_> = gen_info_id_vectors()
+        .into_iter()
+        .filter(|vector| vector.repo_full_name == "github.com/trunk-io/analytics-cli")
+        .collect();
+    assert!(!vectors.is_empty());
+
+    for vector in vectors {
+        let mut args = vec![
+            String::from("context"),
+            String::from("info-id"),
+            format!("--org={}", vector.org_url_slug),
+            String::from("--repo=https://github.com/trunk-io/analytics-cli"),
+            format!("--parent-name={}", vector.parent_name),
+            format!("--name={}", vector.name),
+        ];
+        for (flag, value) in [
+            ("file", &vector.file),
+            ("classname", &vector.classname),
+            ("variant", &vector.variant),
+            ("info-id", &vector.info_id),
+        ] {
+            if let Some(value) = value {
+                args.push(format!("--{}={}", flag, value));
+            }
+        }
+
+        let output = Command::new(CARGO_RUN.path()).args(&args).output().unwrap();
+        assert!(output.status.success(), "{}: {:?}", vector.note, output);
+        assert_eq!(
+            String::from_utf8(output.stdout)
+                .unwrap()
+                .trim_end_matches('\n'),
+            vector.expected_id,
+            "{}",
+            vector.note
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+use crate::utils::CARGO_RUN;
+
+const BASE_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="base">
+    <testsuite name="suite">
+        <testcase classname="a" name="passes" time="1.0" />
+    </testsuite>
+</testsuites>
+"#;
+
+const HEAD_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="head">
+    <testsuite name="suite">
+        <testcase classname="a" name="passes" time="1.0">
+            <failure message="boom" />
+        </testcase>
+    </testsuite>
+</testsuites>
+"#;
+
+#[test]
+fn context_junit_diff_exit_codes() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("base.xml"), BASE_XML).unwrap();
+    fs::write(temp_dir.path().join("head.xml"), HEAD_XML).unwrap();
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["context", "junit", "diff", "base.xml", "base.xml"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No differences found"));
+    println!("{assert}");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["context", "junit", "diff", "base.xml", "head.xml"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("a::passes: success -> failure"));
+    println!("{assert}");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args([
+            "context",
+            "junit",
+            "diff",
+            "base.xml",
+            "head.xml",
+            "--json",
+            "--exit-zero",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""head": "failure""#));
+    println!("{assert}");
+}
+
+#[test]
+fn context_junit_diff_warns_about_duplicate_tests() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(temp_dir.path().join("base.xml"), BASE_XML).unwrap();
+    fs::write(
+        temp_dir.path().join("duplicated.xml"),
+        BASE_XML.replace(
+            "</testsuite>",
+            "    <testcase classname=\"a\" name=\"passes\" time=\"1.0\" />\n    </testsuite>",
+        ),
+    )
+    .unwrap();
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["context", "junit", "diff", "base.xml", "duplicated.xml"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No differences found"))
+        .stderr(predicate::str::contains(
+            "duplicated.xml has more than one test case a::passes, only the first is compared",
+        ));
+    println!("{assert}");
+}
@@ -0,0 +1,76 @@
+use std::fs;
+
+use assert_cmd::Command;
+use junit_mock::JunitMock;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+use crate::utils::CARGO_RUN;
+
+#[test]
+fn context_junit_stats() {
+    let temp_dir = tempdir().unwrap();
+    let mut options = junit_mock::Options::default();
+    options.report.report_random_count = 3;
+    options.test_suite.test_suite_random_count = 2;
+    options.test_case.test_case_random_count = 5;
+    options
+        .test_case
+        .test_case_success_to_skip_to_fail_to_error_percentage = vec![vec![100, 0, 0, 0]];
+    let mut jm = JunitMock::new(options);
+    let reports = jm.generate_reports();
+    jm.write_reports_to_file(temp_dir.path(), reports).unwrap();
+    fs::write(
+        temp_dir.path().join("unparsable.xml"),
+        "<testsuites><testsuite></testcase>",
+    )
+    .unwrap();
+    let glob = temp_dir.path().join("*.xml");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .args(["context", "junit", "stats", glob.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("files           4 (1 unparsable)"))
+        .stdout(predicate::str::contains("test cases      30"))
+        .stdout(predicate::str::contains("  success       30 (100.0%)"))
+        .stdout(predicate::str::contains("with file       100.0%"))
+        .stderr(predicate::str::contains("Could not parse"));
+    println!("{assert}");
+
+    let output = Command::new(CARGO_RUN.path())
+        .args([
+            "context",
+            "junit",
+            "stats",
+            "--json",
+            glob.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["files"].as_array().unwrap().len(), 4);
+    assert_eq!(stats["num_unparsable_files"], 1);
+    assert_eq!(stats["totals"]["num_reports"], 3);
+    assert_eq!(stats["totals"]["num_test_suites"], 6);
+    assert_eq!(stats["totals"]["num_test_cases"], 30);
+    assert_eq!(stats["totals"]["num_success"], 30);
+    assert!(stats["durations"]["p99"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn context_junit_stats_without_files() {
+    let temp_dir = tempdir().unwrap();
+
+    Command::new(CARGO_RUN.path())
+        .args([
+            "context",
+            "junit",
+            "stats",
+            temp_dir.path().join("*.xml").to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No junit files found matching"));
+}
@@ -0,0 +1,91 @@
+use axum::http::StatusCode;
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{MockServerBuilder, RequestPayload};
+use trunk_analytics_cli::datadog_export::{
+    DatadogEventType, DatadogPayload, DD_AGENTLESS_URL_ENV, DD_API_KEY_ENV,
+};
+
+use crate::command_builder::CommandBuilder;
+use crate::utils::{generate_mock_git_repo, generate_mock_valid_junit_xmls};
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_with_datadog_export() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    let export_output = temp_dir.path().join("datadog.json");
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .env(DD_API_KEY_ENV, "dd-api-key")
+        .env(DD_AGENTLESS_URL_ENV, &state.host)
+        .args(["--export", "datadog", "--export-output"])
+        .arg(&export_output)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("test results to Datadog"));
+
+    let exported: DatadogPayload =
+        serde_json::from_slice(&std::fs::read(&export_output).unwrap()).unwrap();
+    assert!(exported
+        .events
+        .iter()
+        .any(|event| event.event_type == DatadogEventType::Test));
+    assert_eq!(
+        exported.events.last().unwrap().event_type,
+        DatadogEventType::TestSessionEnd
+    );
+
+    let requests = state.requests.lock().unwrap().clone();
+    // the upload to Trunk is unchanged
+    assert!(requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+    let submitted = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::DatadogCiTestCycle(api_key, body) => Some((api_key.clone(), body)),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(submitted.0, "dd-api-key");
+    assert_eq!(
+        serde_json::from_str::<DatadogPayload>(submitted.1).unwrap(),
+        exported
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn datadog_export_failure_does_not_fail_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder
+        .set_datadog_ci_test_cycle_handler(|| async { StatusCode::INTERNAL_SERVER_ERROR });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .env(DD_API_KEY_ENV, "dd-api-key")
+        .env(DD_AGENTLESS_URL_ENV, &state.host)
+        .args(["--export", "datadog"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Failed to send the test results to Datadog",
+        ));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
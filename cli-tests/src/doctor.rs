@@ -0,0 +1,126 @@
+use api::message::ValidateTokenOrgResponse;
+use assert_cmd::Command;
+use axum::Json;
+use constants::{TRUNK_API_CLIENT_RETRY_COUNT_ENV, TRUNK_PUBLIC_API_ADDRESS_ENV};
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::MockServerBuilder;
+
+use crate::utils::{
+    generate_mock_codeowners, generate_mock_git_repo, generate_mock_valid_junit_xmls, CARGO_RUN,
+};
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn doctor_checks_mock_repo() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env_remove("TRUNK_API_TOKEN")
+        .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+        .args([
+            "doctor",
+            "--junit-paths",
+            "./*.xml",
+            "--org-url-slug",
+            "test-org",
+            "--token",
+            "test-token",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("FAIL").not())
+        .stdout(predicate::str::contains("trunk-io/analytics-cli"))
+        .stdout(predicate::str::contains("file(s) matched"))
+        .stdout(predicate::str::contains("all matched files parse"))
+        .stdout(predicate::str::contains("CODEOWNERS"))
+        .stdout(predicate::str::contains("valid for test-org"));
+    println!("{assert}");
+
+    // Missing junit files and a token are only warnings.
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env_remove("TRUNK_API_TOKEN")
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("WARN"))
+        .stdout(predicate::str::contains("no --junit-paths given"))
+        .stdout(predicate::str::contains("skipped"));
+    println!("{assert}");
+}
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn doctor_fails_on_unmatched_globs_and_wrong_org() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_validate_token_org_handler(|| async {
+        Json(ValidateTokenOrgResponse {
+            org_url_slug: String::from("other-org"),
+        })
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env_remove("TRUNK_API_TOKEN")
+        .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+        .env(TRUNK_API_CLIENT_RETRY_COUNT_ENV, "0")
+        .args([
+            "doctor",
+            "--junit-paths",
+            "./missing/*.xml",
+            "--org-url-slug",
+            "test-org",
+            "--token",
+            "test-token",
+        ])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("no files match --junit-paths"))
+        .stdout(predicate::str::contains("token belongs to other-org"))
+        .stdout(predicate::str::contains("not found"));
+    println!("{assert}");
+}
+
+#[test]
+fn doctor_classifies_stable_branches() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env_clear()
+        .envs([
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF", "refs/heads/release/1.0"),
+        ])
+        .args([
+            "doctor",
+            "--stable-branches",
+            "main",
+            "--stable-branches",
+            r"re:release/\d{1,2}\.\d+",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("GITHUB_ACTIONS, branch class PB"));
+    println!("{assert}");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .args(["doctor", "--stable-branches", "re:("])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid stable branch pattern"));
+    println!("{assert}");
+}
@@ -0,0 +1,44 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+use crate::utils::CARGO_RUN;
+
+#[test]
+fn env_info_reports_provenance() {
+    let temp_dir = tempdir().unwrap();
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env_clear()
+        .envs([
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_REF", "refs/heads/main"),
+            ("GITHUB_JOB", "test-job"),
+            ("GITHUB_SHA", "abc123"),
+        ])
+        .arg("env-info")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("CI platform: GITHUB_ACTIONS"))
+        .stdout(predicate::str::contains("branch: main (from GITHUB_REF)"))
+        .stdout(predicate::str::contains("Branch class: PB"))
+        .stdout(predicate::str::contains(
+            "Recognized env vars present but unused:\n  GITHUB_SHA",
+        ));
+    println!("{assert}");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env_clear()
+        .envs([("GITHUB_ACTIONS", "true"), ("GITHUB_JOB", "test-job")])
+        .args(["env-info", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""env_vars": [
+        "GITHUB_JOB"
+      ]"#,
+        ));
+    println!("{assert}");
+}
@@ -0,0 +1,79 @@
+use std::{fs, path::Path};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{MockServerBuilder, RequestPayload};
+
+use crate::command_builder::CommandBuilder;
+use crate::utils::{
+    generate_mock_codeowners, generate_mock_git_repo, generate_mock_valid_junit_xmls, CARGO_RUN,
+};
+
+/// Packs an extracted bundle back into a .tar.zstd file, keeping meta.json as the first entry.
+fn repack_bundle(tar_extract_directory: &Path, bundle_path: &Path) {
+    let zstd_encoder = zstd::Encoder::new(fs::File::create(bundle_path).unwrap(), 15).unwrap();
+    let mut tar = tar::Builder::new(zstd_encoder);
+    tar.append_path_with_name(tar_extract_directory.join("meta.json"), "meta.json")
+        .unwrap();
+    tar.append_path_with_name(tar_extract_directory.join("junit/0"), "junit/0")
+        .unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn inspect_verify_bundle() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .assert()
+        .failure();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let bundle_path = temp_dir.path().join("bundle.tar.zstd");
+
+    repack_bundle(tar_extract_directory, &bundle_path);
+    let assert = Command::new(CARGO_RUN.path())
+        .args(["inspect", "--verify", bundle_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("junit/0"))
+        .stdout(predicate::str::contains(
+            "All bundled files match the checksums in meta.json",
+        ));
+    println!("{assert}");
+
+    fs::write(tar_extract_directory.join("junit/0"), "<testsuites />").unwrap();
+    repack_bundle(tar_extract_directory, &bundle_path);
+    let assert = Command::new(CARGO_RUN.path())
+        .args(["inspect", "--verify", bundle_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("checksum mismatch"))
+        .stdout(predicate::str::contains(
+            "1 bundled file(s) do not match the checksums in meta.json",
+        ));
+    println!("{assert}");
+
+    // Without --verify the bundle is only summarized.
+    let assert = Command::new(CARGO_RUN.path())
+        .args(["inspect", bundle_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("checksum mismatch").not());
+    println!("{assert}");
+}
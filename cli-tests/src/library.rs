@@ -0,0 +1,232 @@
+use std::sync::Mutex;
+
+use api::message::{BundleUploadStatus, GetQuarantineConfigResponse, UpdateBundleUploadRequest};
+use assert_matches::assert_matches;
+use tempfile::tempdir;
+use test_utils::{
+    api_client::RecordingApiClient,
+    mock_server::{MockServerBuilder, RequestPayload},
+};
+use trunk_analytics_cli::{
+    phase_timer::UploadPhase,
+    upload::{
+        upload_bundle, upload_bundle_with_api_client, CancellationToken, ProgressSink,
+        QuarantineOutcome, UploadCancelled, UploadConfig, UploadEvent,
+    },
+};
+
+use crate::utils::{
+    generate_mock_git_repo, generate_mock_valid_junit_xmls, write_junit_xml_to_dir,
+};
+
+const FAILING_JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="failing">
+    <testsuite name="suite">
+        <testcase classname="a" name="fails" time="1.0">
+            <failure message="boom" />
+        </testcase>
+    </testsuite>
+</testsuites>
+"#;
+
+#[derive(Default)]
+struct RecordingProgressSink {
+    events: Mutex<Vec<UploadEvent>>,
+}
+
+impl ProgressSink for RecordingProgressSink {
+    fn on_event(&self, event: UploadEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+fn upload_config(repo_root: &std::path::Path, api_address: &str) -> UploadConfig {
+    let mut upload_config = UploadConfig::new("test-token", "test-org");
+    upload_config.api_address = Some(String::from(api_address));
+    upload_config.repo_root = Some(repo_root.to_string_lossy().into_owned());
+    upload_config.junit_paths = vec![format!("{}/*", repo_root.to_string_lossy())];
+    upload_config.use_quarantining = false;
+    upload_config
+}
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn library_upload_bundle_reports_progress() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let progress_sink = RecordingProgressSink::default();
+    let upload_outcome = upload_bundle(
+        upload_config(temp_dir.path(), &state.host),
+        CancellationToken::new(),
+        &progress_sink,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(upload_outcome.exit_code, 0);
+    assert!(upload_outcome.upload_error.is_none());
+    let bundle_upload_id = upload_outcome.bundle_upload_id.unwrap();
+    assert!(upload_outcome
+        .phase_timer
+        .duration(UploadPhase::S3Put)
+        .is_some());
+
+    let events = progress_sink.events.into_inner().unwrap();
+    let started_phases: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            UploadEvent::PhaseStarted(phase) => Some(*phase),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        started_phases,
+        [
+            UploadPhase::JunitScanAndParse,
+            UploadPhase::QuarantineConfigFetch,
+            UploadPhase::CodeownersAssociation,
+            UploadPhase::InternalBinGeneration,
+            UploadPhase::UploadIntent,
+            UploadPhase::TarballCompression,
+            UploadPhase::S3Put,
+        ]
+    );
+    let file_sets = events
+        .iter()
+        .find_map(|event| match event {
+            UploadEvent::FilesCollected(file_sets) => Some(file_sets),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(file_sets.len(), 1);
+    assert!(!file_sets[0].files.is_empty());
+    assert!(events.contains(&UploadEvent::UploadCreated {
+        id: bundle_upload_id.clone()
+    }));
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_matches!(requests.last(), Some(RequestPayload::UpdateBundleUpload(UpdateBundleUploadRequest { id, upload_status })) => {
+        assert_eq!(id, &bundle_upload_id);
+        assert_eq!(upload_status, &BundleUploadStatus::UploadComplete);
+    });
+}
+
+#[tokio::test]
+async fn library_upload_bundle_quarantines_failures() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(FAILING_JUNIT_XML, &temp_dir);
+    let mut upload_config = upload_config(temp_dir.path(), "unused");
+    upload_config.use_quarantining = true;
+
+    let api_client = RecordingApiClient::new();
+    let upload_outcome = upload_bundle_with_api_client(
+        upload_config.clone(),
+        &api_client,
+        CancellationToken::new(),
+        &(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(upload_outcome.exit_code, 1);
+    assert_eq!(
+        upload_outcome.quarantine_outcome,
+        QuarantineOutcome::SomeUnquarantined {
+            quarantined: 0,
+            unquarantined: 1
+        }
+    );
+
+    let failed_test_ids = api_client
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::GetQuarantineBulkTestStatus(request) => Some(
+                request
+                    .test_identifiers
+                    .iter()
+                    .map(|test| test.id.clone())
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        })
+        .unwrap();
+    let api_client =
+        RecordingApiClient::new().with_quarantining_config(GetQuarantineConfigResponse {
+            is_disabled: false,
+            quarantined_tests: failed_test_ids,
+        });
+    let upload_outcome =
+        upload_bundle_with_api_client(upload_config, &api_client, CancellationToken::new(), &())
+            .await
+            .unwrap();
+    assert_eq!(upload_outcome.exit_code, 0);
+    assert_eq!(
+        upload_outcome.quarantine_outcome,
+        QuarantineOutcome::AllQuarantined { count: 1 }
+    );
+}
+
+#[tokio::test]
+async fn library_upload_bundle_cancelled_mid_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let api_client = RecordingApiClient::new().with_stalled_s3_upload();
+
+    let cancellation_token = CancellationToken::new();
+    let (upload_result, ()) = tokio::join!(
+        upload_bundle_with_api_client(
+            upload_config(temp_dir.path(), "unused"),
+            &api_client,
+            cancellation_token.clone(),
+            &(),
+        ),
+        async {
+            api_client.s3_upload_started.notified().await;
+            cancellation_token.cancel();
+        }
+    );
+
+    assert!(upload_result.unwrap_err().is::<UploadCancelled>());
+    let requests = api_client.requests.lock().unwrap().clone();
+    assert_matches!(
+        requests.last(),
+        Some(RequestPayload::UpdateBundleUpload(
+            UpdateBundleUploadRequest {
+                upload_status: BundleUploadStatus::UploadFailed,
+                ..
+            }
+        ))
+    );
+}
+
+#[tokio::test]
+async fn library_upload_bundle_cancelled_before_start() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let api_client = RecordingApiClient::new();
+
+    let cancellation_token = CancellationToken::new();
+    cancellation_token.cancel();
+    let error = upload_bundle_with_api_client(
+        upload_config(temp_dir.path(), "unused"),
+        &api_client,
+        cancellation_token,
+        &(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(error.is::<UploadCancelled>());
+    assert!(api_client.requests.lock().unwrap().is_empty());
+}
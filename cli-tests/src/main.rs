@@ -1,12 +1,42 @@
 #[cfg(test)]
+mod auth;
+#[cfg(test)]
+mod bin_to_junit;
+#[cfg(test)]
 mod command_builder;
 #[cfg(test)]
+mod completions;
+#[cfg(test)]
+mod config_file;
+#[cfg(test)]
+mod context_info_id;
+#[cfg(test)]
+mod context_junit_diff;
+#[cfg(test)]
+mod context_junit_stats;
+#[cfg(test)]
+mod datadog_export;
+#[cfg(test)]
+mod doctor;
+#[cfg(test)]
+mod env_info;
+#[cfg(test)]
+mod inspect;
+#[cfg(test)]
+mod library;
+#[cfg(test)]
+mod preflight;
+#[cfg(test)]
 mod quarantine;
 #[cfg(test)]
+mod status;
+#[cfg(test)]
 mod test;
 #[cfg(test)]
 mod upload;
 #[cfg(test)]
+mod upload_resume;
+#[cfg(test)]
 mod utils;
 #[cfg(test)]
 mod validate;
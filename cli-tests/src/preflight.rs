@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use api::message::{ValidateTokenOrgRequest, ValidateTokenOrgResponse};
+use assert_matches::assert_matches;
+use axum::{extract::State, Json};
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{MockServerBuilder, RequestPayload, SharedMockServerState};
+
+use crate::command_builder::CommandBuilder;
+use crate::utils::{generate_mock_git_repo, generate_mock_valid_junit_xmls};
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn preflight_fails_fast_on_org_mismatch() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_validate_token_org_handler(
+        |State(state): State<SharedMockServerState>,
+         Json(request): Json<ValidateTokenOrgRequest>| async move {
+            state
+                .requests
+                .lock()
+                .unwrap()
+                .push(RequestPayload::ValidateTokenOrg(request));
+            Json(ValidateTokenOrgResponse {
+                org_url_slug: String::from("other-org"),
+            })
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The organization URL slug \"test-org\" does not match the organization of the token, \"other-org\"",
+        ));
+
+    // nothing is parsed or uploaded after the mismatch
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_matches!(&requests[0], RequestPayload::ValidateTokenOrg(_));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preflight_fails_fast_on_org_mismatch_before_running_tests() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_validate_token_org_handler(|| async {
+        Json(ValidateTokenOrgResponse {
+            org_url_slug: String::from("other-org"),
+        })
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::test(
+        temp_dir.path(),
+        state.host.clone(),
+        vec![
+            String::from("bash"),
+            String::from("-c"),
+            String::from("touch ran-tests"),
+        ],
+    )
+    .command()
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains("\"other-org\""));
+
+    assert!(!temp_dir.path().join("ran-tests").exists());
+    assert!(state.requests.lock().unwrap().is_empty());
+
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preflight_passes_on_org_match() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .assert()
+        .success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(
+        requests[0],
+        RequestPayload::ValidateTokenOrg(ValidateTokenOrgRequest {
+            org_url_slug: String::from("test-org"),
+        })
+    );
+    assert!(requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preflight_warns_and_continues_when_unreachable() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_validate_token_org_handler(|| async {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        Json(ValidateTokenOrgResponse {
+            org_url_slug: String::from("other-org"),
+        })
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command();
+    command.args(["--api-timeout-secs", "1"]);
+    let assert = command.assert().success().stderr(predicate::str::contains(
+        "Could not check that the token belongs to organization \"test-org\", continuing",
+    ));
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert!(requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn no_preflight_skips_org_check() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_validate_token_org_handler(|| async {
+        Json(ValidateTokenOrgResponse {
+            org_url_slug: String::from("other-org"),
+        })
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command();
+    command.arg("--no-preflight");
+    let assert = command.assert().success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert!(!requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::ValidateTokenOrg(_))));
+
+    println!("{assert}");
+}
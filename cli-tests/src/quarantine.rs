@@ -1,20 +1,38 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
 
 use api::message::{
     CreateBundleUploadRequest, CreateBundleUploadResponse, GetQuarantineConfigRequest,
     GetQuarantineConfigResponse,
 };
 use axum::{extract::State, Json};
+use bundle::{
+    BundleMeta, BundleMetaQuarantineOutcome, BundleMetaQuarantineOutcomeKind,
+    BundleMetaQuarantineSource, BundleMetaQuarantinedTestSource,
+};
 use lazy_static::lazy_static;
 use predicates::prelude::*;
 use tempfile::tempdir;
-use test_utils::mock_server::{MockServerBuilder, SharedMockServerState};
+use test_utils::mock_server::{MockServerBuilder, RequestPayload, SharedMockServerState};
 
 use crate::{
     command_builder::CommandBuilder,
-    utils::{generate_mock_codeowners, generate_mock_git_repo, generate_mock_valid_junit_xmls},
+    utils::{
+        generate_mock_codeowners, generate_mock_git_repo, generate_mock_valid_junit_xmls,
+        write_junit_xml_to_dir,
+    },
 };
 
+const PASSING_JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="passing">
+    <testsuite name="suite">
+        <testcase classname="a" name="passes" time="1.0" />
+    </testsuite>
+</testsuites>
+"#;
+
 #[derive(Debug, Clone, Copy)]
 enum QuarantineConfigResponse {
     Disabled,
@@ -85,6 +103,7 @@ async fn quarantines_tests_regardless_of_upload() {
                         id_v2: String::from("test-bundle-upload-id-v2"),
                         url: format!("{host}/s3upload"),
                         key: String::from("unused"),
+                        minimum_recommended_cli_version: None,
                     }))
                 }
             };
@@ -182,6 +201,7 @@ async fn do_no_quarantines_tests_when_use_quarantined_disabled() {
                         id_v2: String::from("test-bundle-upload-id-v2"),
                         url: format!("{host}/s3upload"),
                         key: String::from("unused"),
+                        minimum_recommended_cli_version: None,
                     }))
                 }
             };
@@ -225,3 +245,391 @@ async fn do_no_quarantines_tests_when_use_quarantined_disabled() {
     *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
     command.assert().success().stderr(upload_failure.clone());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_quarantine_outcome() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+
+    lazy_static! {
+        static ref QUARANTINE_CONFIG_RESPONSE: Arc<Mutex<QuarantineConfigResponse>> =
+            Arc::new(Mutex::new(QuarantineConfigResponse::None));
+    }
+    mock_server_builder.set_get_quarantining_config_handler(
+        |Json(get_quarantine_bulk_test_status_request): Json<GetQuarantineConfigRequest>| {
+            let mut test_ids = get_quarantine_bulk_test_status_request
+                .test_identifiers
+                .into_iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>();
+            let quarantine_config_response = *QUARANTINE_CONFIG_RESPONSE.lock().unwrap();
+            let quarantined_tests = match quarantine_config_response {
+                QuarantineConfigResponse::Disabled => Vec::new(),
+                QuarantineConfigResponse::None => Vec::new(),
+                QuarantineConfigResponse::Some => test_ids.split_off(1),
+                QuarantineConfigResponse::All => test_ids,
+            };
+            let is_disabled = matches!(
+                quarantine_config_response,
+                QuarantineConfigResponse::Disabled
+            );
+            async move {
+                Json(GetQuarantineConfigResponse {
+                    is_disabled,
+                    quarantined_tests,
+                })
+            }
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let results_file = temp_dir.path().join("results.json");
+    let run = |quarantine_config_response: QuarantineConfigResponse| {
+        *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = quarantine_config_response;
+        let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+            .command()
+            .arg("--results-file")
+            .arg(&results_file)
+            .assert();
+        let results: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&results_file).unwrap()).unwrap();
+        let meta_quarantine_outcome = state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|request| match request {
+                RequestPayload::S3Upload(d) => Some(d.clone()),
+                _ => None,
+            })
+            .map(|tar_extract_directory| {
+                let meta_json =
+                    fs::read_to_string(tar_extract_directory.join("meta.json")).unwrap();
+                serde_json::from_str::<BundleMeta>(&meta_json)
+                    .unwrap()
+                    .quarantine_outcome
+            })
+            .unwrap();
+        (assert, results, meta_quarantine_outcome)
+    };
+
+    // Nothing is quarantined
+    let (assert, results, meta_quarantine_outcome) = run(QuarantineConfigResponse::None);
+    assert.failure().stdout(predicate::str::contains(
+        "were not quarantined, 0 were quarantined",
+    ));
+    assert_eq!(results["exit_code"], 1);
+    assert_eq!(results["bundle_upload_id"], "test-bundle-upload-id");
+    assert_eq!(
+        results["quarantine_outcome"]["outcome"],
+        "SomeUnquarantined"
+    );
+    assert_eq!(results["quarantine_outcome"]["quarantined"], 0);
+    let num_failures = results["quarantine_outcome"]["unquarantined"]
+        .as_u64()
+        .unwrap() as usize;
+    assert!(num_failures > 1);
+    assert_eq!(
+        meta_quarantine_outcome,
+        BundleMetaQuarantineOutcome {
+            outcome: BundleMetaQuarantineOutcomeKind::SomeUnquarantined,
+            num_quarantined: 0,
+            num_unquarantined: num_failures,
+            num_unmatched_quarantined_ids: Some(0),
+        }
+    );
+
+    // All but one failure is quarantined
+    let (assert, results, meta_quarantine_outcome) = run(QuarantineConfigResponse::Some);
+    assert.failure().stdout(predicate::str::contains(format!(
+        "1 test failure(s) were not quarantined, {} were quarantined",
+        num_failures - 1
+    )));
+    assert_eq!(
+        results["quarantine_outcome"],
+        serde_json::json!({
+            "outcome": "SomeUnquarantined",
+            "quarantined": num_failures - 1,
+            "unquarantined": 1,
+        })
+    );
+    assert_eq!(
+        meta_quarantine_outcome.outcome,
+        BundleMetaQuarantineOutcomeKind::SomeUnquarantined
+    );
+
+    // Every failure is quarantined
+    let (assert, results, meta_quarantine_outcome) = run(QuarantineConfigResponse::All);
+    assert.success().stdout(predicate::str::contains(format!(
+        "All {} test failure(s) were quarantined",
+        num_failures
+    )));
+    assert_eq!(results["exit_code"], 0);
+    assert_eq!(
+        results["quarantine_outcome"],
+        serde_json::json!({ "outcome": "AllQuarantined", "count": num_failures })
+    );
+    assert_eq!(
+        meta_quarantine_outcome,
+        BundleMetaQuarantineOutcome {
+            outcome: BundleMetaQuarantineOutcomeKind::AllQuarantined,
+            num_quarantined: num_failures,
+            num_unquarantined: 0,
+            num_unmatched_quarantined_ids: Some(0),
+        }
+    );
+
+    // Quarantining is disabled for the repo
+    let (assert, results, meta_quarantine_outcome) = run(QuarantineConfigResponse::Disabled);
+    assert.stdout(predicate::str::contains(
+        "Quarantining was unavailable, no test failures were quarantined",
+    ));
+    assert_eq!(
+        results["quarantine_outcome"],
+        serde_json::json!({ "outcome": "QuarantiningUnavailable" })
+    );
+    assert_eq!(
+        meta_quarantine_outcome.outcome,
+        BundleMetaQuarantineOutcomeKind::QuarantiningUnavailable
+    );
+
+    // There are no failures to quarantine
+    for entry in fs::read_dir(temp_dir.path()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "xml") {
+            fs::remove_file(path).unwrap();
+        }
+    }
+    write_junit_xml_to_dir(PASSING_JUNIT_XML, &temp_dir);
+    let (assert, results, meta_quarantine_outcome) = run(QuarantineConfigResponse::All);
+    assert
+        .success()
+        .stdout(predicate::str::contains("No test failures"));
+    assert_eq!(
+        results["quarantine_outcome"],
+        serde_json::json!({ "outcome": "NoFailures" })
+    );
+    assert_eq!(
+        meta_quarantine_outcome.outcome,
+        BundleMetaQuarantineOutcomeKind::NoFailures
+    );
+    // the quarantine config is not fetched without failures
+    assert_eq!(meta_quarantine_outcome.num_unmatched_quarantined_ids, None);
+}
+
+const AUDITED_JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="audited">
+    <testsuite name="auth">
+        <testcase id="test-login-succeeds" classname="auth.LoginTest" name="logs in" time="1.0">
+            <failure message="expected 200, got 500" />
+        </testcase>
+        <testcase id="test-logout-succeeds" classname="auth.LogoutTest" name="logs out" time="1.0" />
+    </testsuite>
+</testsuites>
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audits_misspelled_quarantined_test_ids() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(AUDITED_JUNIT_XML, &temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_get_quarantining_config_handler(
+        |_: Json<GetQuarantineConfigRequest>| async {
+            Json(GetQuarantineConfigResponse {
+                is_disabled: false,
+                quarantined_tests: vec![
+                    String::from("test-login-suceeds"),
+                    String::from("test-logout-succeeds"),
+                ],
+            })
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let last_meta_quarantine_outcome = || {
+        state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|request| match request {
+                RequestPayload::S3Upload(d) => Some(d.clone()),
+                _ => None,
+            })
+            .map(|tar_extract_directory| {
+                let meta_json =
+                    fs::read_to_string(tar_extract_directory.join("meta.json")).unwrap();
+                serde_json::from_str::<BundleMeta>(&meta_json)
+                    .unwrap()
+                    .quarantine_outcome
+            })
+            .unwrap()
+    };
+
+    // The misspelled id doesn't quarantine the failure, and the nearest test is suggested
+    let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--quarantine-audit")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "1 of 2 quarantined test ID(s) matched no test case of this upload:",
+        ))
+        .stdout(predicate::str::contains(
+            "test-login-suceeds, did you mean auth -> auth.LoginTest.logs in (id: test-login-succeeds)?",
+        ));
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+    assert_eq!(
+        last_meta_quarantine_outcome().num_unmatched_quarantined_ids,
+        Some(1)
+    );
+
+    // The unmatched count is recorded without printing the audit
+    let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+        .command()
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("matched no test case").not());
+    println!("{assert}");
+    assert_eq!(
+        last_meta_quarantine_outcome().num_unmatched_quarantined_ids,
+        Some(1)
+    );
+
+    // The quarantine config is audited even without failures
+    write_junit_xml_to_dir(PASSING_JUNIT_XML, &temp_dir);
+    let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--quarantine-audit")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "2 of 2 quarantined test ID(s) matched no test case of this upload:",
+        ));
+    println!("{assert}");
+    assert_eq!(
+        last_meta_quarantine_outcome().num_unmatched_quarantined_ids,
+        Some(2)
+    );
+}
+
+const RUNNER_QUARANTINED_JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="runner-quarantined">
+    <testsuite name="auth">
+        <testcase id="test-login-succeeds" classname="auth.LoginTest" name="logs in" time="1.0" quarantined="true">
+            <failure message="expected 200, got 500" />
+        </testcase>
+        <testcase id="test-logout-succeeds" classname="auth.LogoutTest" name="logs out" time="1.0">
+            <failure message="expected 200, got 500" />
+        </testcase>
+    </testsuite>
+</testsuites>
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quarantines_tests_marked_by_the_test_runner() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(RUNNER_QUARANTINED_JUNIT_XML, &temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_get_quarantining_config_handler(
+        |_: Json<GetQuarantineConfigRequest>| async {
+            Json(GetQuarantineConfigResponse {
+                is_disabled: false,
+                quarantined_tests: Vec::new(),
+            })
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let last_meta = || {
+        state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|request| match request {
+                RequestPayload::S3Upload(d) => Some(d.clone()),
+                _ => None,
+            })
+            .map(|tar_extract_directory| {
+                let meta_json =
+                    fs::read_to_string(tar_extract_directory.join("meta.json")).unwrap();
+                serde_json::from_str::<BundleMeta>(&meta_json).unwrap()
+            })
+            .unwrap()
+    };
+
+    // The failure marked by the test runner is quarantined, even though Trunk quarantines none
+    let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--quarantine-audit")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "1 test failure(s) were quarantined:\n    auth -> logs in (id: test-login-succeeds), by the test runner",
+        ));
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+    let meta = last_meta();
+    assert_eq!(
+        meta.quarantine_outcome.outcome,
+        BundleMetaQuarantineOutcomeKind::SomeUnquarantined
+    );
+    assert_eq!(meta.quarantine_outcome.num_quarantined, 1);
+    assert_eq!(meta.base_props.quarantined_tests.len(), 1);
+    assert_eq!(
+        meta.quarantined_test_sources,
+        vec![BundleMetaQuarantinedTestSource {
+            id: String::from("test-login-succeeds"),
+            source: BundleMetaQuarantineSource::Runner,
+        }]
+    );
+
+    // Only failures marked by the test runner are left, so the exit code is overridden
+    write_junit_xml_to_dir(
+        &RUNNER_QUARANTINED_JUNIT_XML.replace(
+            r#"name="logs out" time="1.0">"#,
+            r#"name="logs out" time="1.0" quarantined="true">"#,
+        ),
+        &temp_dir,
+    );
+    let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+        .command()
+        .assert()
+        .success();
+    println!("{assert}");
+    let meta = last_meta();
+    assert_eq!(
+        meta.quarantine_outcome,
+        BundleMetaQuarantineOutcome {
+            outcome: BundleMetaQuarantineOutcomeKind::AllQuarantined,
+            num_quarantined: 2,
+            num_unquarantined: 0,
+            num_unmatched_quarantined_ids: Some(0),
+        }
+    );
+    assert_eq!(meta.quarantined_test_sources.len(), 2);
+
+    // The marks are ignored with --ignore-runner-quarantine-marks
+    let assert = CommandBuilder::quarantine(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--ignore-runner-quarantine-marks")
+        .assert()
+        .failure();
+    println!("{assert}");
+    let meta = last_meta();
+    assert_eq!(meta.quarantine_outcome.num_quarantined, 0);
+    assert!(meta.quarantined_test_sources.is_empty());
+}
@@ -0,0 +1,92 @@
+use assert_cmd::Command;
+use assert_matches::assert_matches;
+use axum::http::StatusCode;
+use constants::{TRUNK_API_CLIENT_RETRY_COUNT_ENV, TRUNK_PUBLIC_API_ADDRESS_ENV};
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{MockServerBuilder, RequestPayload};
+
+use crate::utils::{generate_mock_git_repo, CARGO_RUN};
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn status_lists_recent_uploads() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+        .args([
+            "status",
+            "--org-url-slug",
+            "test-org",
+            "--token",
+            "test-token",
+            "--limit",
+            "5",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "test-bundle-upload-id  2024-01-01T00:00:00Z  UPLOAD_COMPLETE  linux  42 tests, 1 failures",
+        ));
+    println!("{assert}");
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    let request = assert_matches!(&requests[0], RequestPayload::ListBundleUploads(r) => r);
+    assert_eq!(request.org_url_slug, "test-org");
+    assert_eq!(request.limit, 5);
+    assert_eq!(request.repo.owner, "trunk-io");
+    assert_eq!(request.repo.name, "analytics-cli");
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+        .args([
+            "status",
+            "--org-url-slug",
+            "test-org",
+            "--token",
+            "test-token",
+            "--json",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""id": "test-bundle-upload-id""#))
+        .stdout(predicate::str::contains(
+            r#""uploadStatus": "UPLOAD_COMPLETE""#,
+        ));
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn status_reports_unauthorized() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_list_bundle_uploads_handler(|| async { StatusCode::UNAUTHORIZED });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = Command::new(CARGO_RUN.path())
+        .current_dir(temp_dir.path())
+        .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+        .env(TRUNK_API_CLIENT_RETRY_COUNT_ENV, "0")
+        .args([
+            "status",
+            "--org-url-slug",
+            "test-org",
+            "--token",
+            "bad-token",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Your Trunk token may be incorrect",
+        ));
+    println!("{assert}");
+}
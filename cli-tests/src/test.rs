@@ -4,7 +4,10 @@ use assert_matches::assert_matches;
 use bundle::BundleMeta;
 use predicates::prelude::*;
 use tempfile::tempdir;
-use test_utils::mock_server::{MockServerBuilder, RequestPayload};
+use test_utils::{
+    inputs::get_test_file_path,
+    mock_server::{MockServerBuilder, RequestPayload},
+};
 
 use crate::{
     command_builder::CommandBuilder,
@@ -90,9 +93,13 @@ async fn test_command_fails_with_no_junit_files_no_quarantine_successful_upload(
     println!("{assert}");
 
     let requests = state.requests.lock().unwrap().clone();
-    assert_eq!(requests.len(), 4);
+    assert_eq!(requests.len(), 5);
     let mut requests_iter = requests.into_iter();
 
+    assert!(matches!(
+        requests_iter.next().unwrap(),
+        RequestPayload::ValidateTokenOrg(..)
+    ));
     assert!(matches!(
         requests_iter.next().unwrap(),
         RequestPayload::CreateRepo(..)
@@ -163,3 +170,183 @@ async fn test_command_fails_with_upload_not_connected() {
     .failure()
     .code(1);
 }
+
+fn uploaded_test_command(state: &test_utils::mock_server::SharedMockServerState) -> String {
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    bundle_meta.base_props.test_command.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_multiple_commands_stops_at_first_failure() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::test(temp_dir.path(), state.host.clone(), vec![])
+        .use_quarantining(false)
+        .command()
+        .args([
+            "--command",
+            "touch lint",
+            "--command",
+            "exit 3",
+            "--command",
+            "touch integration",
+        ])
+        .assert()
+        .failure()
+        .code(3)
+        .stdout(predicate::str::contains(
+            "test commands:\n  exit 0   touch lint\n  exit 3   exit 3\n",
+        ));
+
+    println!("{assert}");
+
+    assert!(temp_dir.path().join("lint").exists());
+    assert!(!temp_dir.path().join("integration").exists());
+    assert_eq!(
+        uploaded_test_command(&state),
+        "touch lint && exit 3 && touch integration"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_multiple_commands_from_file_continue_on_failure() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+    fs::write(
+        temp_dir.path().join("commands.txt"),
+        "# lint, unit and integration tests\nexit 2\n\nexit 4\ntouch integration\n",
+    )
+    .unwrap();
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::test(temp_dir.path(), state.host.clone(), vec![])
+        .use_quarantining(false)
+        .command()
+        .args([
+            "--commands-file",
+            "commands.txt",
+            "--continue-on-failure",
+            "--allow-empty-test-results",
+            "--results-file",
+            "results.json",
+        ])
+        .assert()
+        .failure()
+        .code(2);
+
+    println!("{assert}");
+
+    let run_results: serde_json::Value =
+        serde_json::from_slice(&fs::read(temp_dir.path().join("results.json")).unwrap()).unwrap();
+    assert_eq!(
+        run_results["test_commands"],
+        serde_json::json!([
+            { "command": "exit 2", "exit_code": 2 },
+            { "command": "exit 4", "exit_code": 4 },
+            { "command": "touch integration", "exit_code": 0 },
+        ])
+    );
+
+    assert!(temp_dir.path().join("integration").exists());
+    assert_eq!(
+        uploaded_test_command(&state),
+        "exit 2; exit 4; touch integration"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_command_records_resource_usage() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::test(
+        temp_dir.path(),
+        state.host.clone(),
+        vec![
+            String::from("bash"),
+            String::from("-c"),
+            String::from("sleep 0.2"),
+        ],
+    )
+    .use_quarantining(false)
+    .command()
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("tests used "));
+
+    println!("{assert}");
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = assert_matches!(
+        requests.iter().find(|request| matches!(request, RequestPayload::S3Upload(_))),
+        Some(RequestPayload::S3Upload(d)) => d
+    );
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let test_command_stats = bundle_meta.test_command_stats.unwrap();
+    assert!(test_command_stats.wall_time_ms >= 200);
+    assert!(test_command_stats.peak_rss_bytes.unwrap() > 0);
+    assert!(test_command_stats.user_cpu_time_ms.is_some());
+    assert!(test_command_stats.system_cpu_time_ms.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_command_converts_the_results_it_wrote() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let converter = format!(
+        "{} {{input}} {{output}}",
+        get_test_file_path("test_fixtures/json_converter.sh")
+    );
+    let assert = CommandBuilder::test(temp_dir.path(), state.host.clone(), vec![])
+        .use_quarantining(false)
+        .command()
+        .args([
+            "--command",
+            "echo '{\"name\": \"adds\", \"status\": \"passed\"}' > math.json",
+            "--converter",
+            converter.as_str(),
+            "--converter-input-paths",
+            "math.json",
+        ])
+        .assert()
+        .success();
+
+    println!("{assert}");
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = assert_matches!(
+        requests.iter().find(|request| matches!(request, RequestPayload::S3Upload(_))),
+        Some(RequestPayload::S3Upload(d)) => d
+    );
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let conversions = bundle_meta.converter.unwrap().conversions;
+    assert_eq!(conversions.len(), 1);
+    assert_eq!(conversions[0].error, None);
+    assert_eq!(bundle_meta.junit_props.num_tests, 1);
+}
@@ -1,29 +1,53 @@
 use std::sync::{Arc, Mutex};
 use std::{fs, io::BufReader};
 
+use api::error_code::ErrorCode;
 use api::message::{
     BundleUploadStatus, CreateBundleUploadRequest, CreateBundleUploadResponse, CreateRepoRequest,
     GetQuarantineConfigRequest, GetQuarantineConfigResponse, UpdateBundleUploadRequest,
+    ValidateTokenOrgRequest,
 };
+use assert_cmd::Command;
 use assert_matches::assert_matches;
-use axum::{extract::State, Json};
-use bundle::{BundleMeta, FileSetType};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{StatusCode, Uri},
+    Json,
+};
+use bundle::{BundleMeta, FileSetType, Sha256Writer};
 use codeowners::CodeOwners;
+use constants::{
+    GITHUB_EXTERNAL_ID_ENV, TRUNK_API_CLIENT_RETRY_COUNT_ENV, TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV,
+    TRUNK_PUBLIC_API_ADDRESS_ENV,
+};
 use context::{
-    bazel_bep::parser::BazelBepParser, junit::parser::JunitParser, repo::RepoUrlParts as Repo,
+    bazel_bep::parser::BazelBepParser,
+    env::parser::{CIPlatform, EnvParser},
+    junit::parser::{extra_attrs, JunitParser, DEFAULT_MAX_TEST_NAME_LEN},
+    repo::RepoUrlParts as Repo,
 };
+use github_actions::{pr_comment::IssueComment, Job, ListJobsResponse};
 use lazy_static::lazy_static;
 use predicates::prelude::*;
+use prost::Message;
+use proto::test_context::test_run::{TestCaseRun, TestCaseRunStatus, TestResult};
 use tempfile::tempdir;
 use test_utils::{
     inputs::get_test_file_path,
+    mock_git_repo::{
+        remove_origin_remote, setup_repo_with_detached_merge_commit,
+        setup_repo_with_dirty_working_tree,
+    },
     mock_server::{MockServerBuilder, RequestPayload, SharedMockServerState},
 };
+use trunk_analytics_cli::{cli_version::DEBUG_CLI_VERSION_ENV, pr_comment::PR_COMMENT_MARKER};
 
 use crate::command_builder::CommandBuilder;
 use crate::utils::{
-    generate_mock_bazel_bep, generate_mock_codeowners, generate_mock_git_repo,
-    generate_mock_valid_junit_xmls,
+    generate_internal_bin_from_junits, generate_mock_bazel_bep, generate_mock_codeowners,
+    generate_mock_git_repo, generate_mock_internal_bin, generate_mock_valid_junit_xmls,
+    write_junit_xml_to_dir, CARGO_RUN,
 };
 
 // NOTE: must be multi threaded to start a mock server
@@ -45,9 +69,16 @@ async fn upload_bundle() {
         .failure();
 
     let requests = state.requests.lock().unwrap().clone();
-    assert_eq!(requests.len(), 5);
+    assert_eq!(requests.len(), 6);
     let mut requests_iter = requests.into_iter();
 
+    assert_eq!(
+        requests_iter.next().unwrap(),
+        RequestPayload::ValidateTokenOrg(ValidateTokenOrgRequest {
+            org_url_slug: String::from("test-org"),
+        })
+    );
+
     let quarantine_request = requests_iter.next().unwrap();
     assert_matches!(quarantine_request, RequestPayload::GetQuarantineBulkTestStatus(req) => {
         assert_eq!(req.repo.host, "github.com");
@@ -108,6 +139,8 @@ async fn upload_bundle() {
     let base_props = bundle_meta.base_props;
     let junit_props = bundle_meta.junit_props;
     let debug_props = bundle_meta.debug_props;
+    let invocation = bundle_meta.invocation;
+    let host_info = bundle_meta.host_info;
 
     assert_eq!(base_props.org, "test-org");
     assert_eq!(
@@ -176,6 +209,14 @@ async fn upload_bundle() {
     );
     assert_eq!(bundled_file.owners, ["@user"]);
     assert_eq!(bundled_file.team, None);
+    let mut sha256_writer = Sha256Writer::new(std::io::sink());
+    std::io::copy(
+        &mut fs::File::open(tar_extract_directory.join(&bundled_file.path)).unwrap(),
+        &mut sha256_writer,
+    )
+    .unwrap();
+    assert_eq!(bundled_file.sha256, Some(sha256_writer.finish().1));
+    assert_eq!(bundle_meta.checksum_algorithm, "sha256");
 
     assert_eq!(
         requests_iter.next().unwrap(),
@@ -191,11 +232,79 @@ async fn upload_bundle() {
             .join(" ")
             .replace("test-token", "***")
     ));
+    assert_eq!(invocation.subcommand, "upload");
+    assert_eq!(invocation.junit_globs, ["./*"]);
+    assert_eq!(
+        invocation.flags.get("use_quarantining"),
+        Some(&String::from("true"))
+    );
+    assert_eq!(
+        invocation.flags.get("print_files"),
+        Some(&String::from("false"))
+    );
+    assert_eq!(invocation.num_tags, 0);
+    assert_eq!(host_info.arch.as_deref(), Some(std::env::consts::ARCH));
+    assert!(host_info.num_logical_cores.is_some());
 
     // HINT: View CLI output with `cargo test -- --nocapture`
     println!("{assert}");
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_meta_never_contains_token() {
+    const TOKEN: &str = "secret-trunk-token-3f9c2a";
+
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    for token_from_env in [true, false] {
+        let mut command = Command::new(CARGO_RUN.path());
+        command
+            .current_dir(temp_dir.path())
+            .env(TRUNK_PUBLIC_API_ADDRESS_ENV, &state.host)
+            .env(TRUNK_API_CLIENT_RETRY_COUNT_ENV, "0")
+            .args([
+                "upload",
+                "--junit-paths",
+                "./*",
+                "--org-url-slug",
+                "test-org",
+                "--use-quarantining=false",
+            ]);
+        if token_from_env {
+            command.env("TRUNK_API_TOKEN", TOKEN);
+        } else {
+            command.args(["--token", TOKEN]);
+        }
+        let assert = command.assert().success();
+
+        let requests = state.requests.lock().unwrap().clone();
+        let tar_extract_directory = requests
+            .iter()
+            .rev()
+            .find_map(|request| match request {
+                RequestPayload::S3Upload(d) => Some(d),
+                _ => None,
+            })
+            .unwrap();
+        let meta_json = fs::read_to_string(tar_extract_directory.join("meta.json")).unwrap();
+        assert!(!meta_json.contains(TOKEN));
+
+        let bundle_meta: BundleMeta = serde_json::from_str(&meta_json).unwrap();
+        assert_eq!(bundle_meta.invocation.subcommand, "upload");
+        assert_eq!(
+            bundle_meta.invocation.flags.get("use_quarantining"),
+            Some(&String::from("false"))
+        );
+
+        // HINT: View CLI output with `cargo test -- --nocapture`
+        println!("{assert}");
+    }
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn upload_bundle_using_bep() {
     let temp_dir = tempdir().unwrap();
@@ -211,9 +320,9 @@ async fn upload_bundle_using_bep() {
         .failure();
 
     let requests = state.requests.lock().unwrap().clone();
-    assert_eq!(requests.len(), 5);
+    assert_eq!(requests.len(), 6);
 
-    let tar_extract_directory = assert_matches!(&requests[3], RequestPayload::S3Upload(d) => d);
+    let tar_extract_directory = assert_matches!(&requests[4], RequestPayload::S3Upload(d) => d);
 
     let junit_file = fs::File::open(tar_extract_directory.join("junit/0")).unwrap();
     let junit_reader = BufReader::new(junit_file);
@@ -228,6 +337,680 @@ async fn upload_bundle_using_bep() {
     assert!(parse_result.errors.is_empty());
     assert_eq!(parse_result.xml_file_counts(), (1, 0));
 
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert!(bundle_meta.excluded_files.is_empty());
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_using_bep_without_raw_test_runner_output() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_bazel_bep(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .bazel_bep_path("./bep.json")
+        .command();
+    command.args(["--include-raw-test-runner-output", "never"]);
+    let assert = command.assert().failure();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = assert_matches!(&requests[4], RequestPayload::S3Upload(d) => d);
+
+    // The junit files are still uploaded, only the BEP events are left out
+    assert!(tar_extract_directory.join("junit/0").exists());
+    assert!(!tar_extract_directory.join("bazel_bep.json").exists());
+
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let bep_bytes = fs::read(temp_dir.path().join("bep.json")).unwrap();
+    assert_eq!(bundle_meta.excluded_files.len(), 1);
+    let excluded_file = &bundle_meta.excluded_files[0];
+    assert_eq!(excluded_file.path, "./bep.json");
+    assert_eq!(excluded_file.size_bytes, bep_bytes.len() as u64);
+    assert_eq!(excluded_file.sha256.len(), 64);
+    assert!(excluded_file
+        .sha256
+        .chars()
+        .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_merging_junit_files() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    // Like a sharded runner that writes a junit file per test case
+    let junit_dir = temp_dir.path().join("junit");
+    fs::create_dir(&junit_dir).unwrap();
+    for i in 0..5000 {
+        let suite_name = if i % 2 == 0 { "suite-a" } else { "suite-b" };
+        fs::write(
+            junit_dir.join(format!("case-{i}.xml")),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="shard-{i}">
+  <testsuite name="{suite_name}" tests="1" failures="0" timestamp="2024-10-01T00:00:00Z">
+    <testcase name="test_{i}" classname="{suite_name}" time="0.01"/>
+  </testsuite>
+</testsuites>
+"#
+            ),
+        )
+        .unwrap();
+    }
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("./junit/*.xml")
+        .command();
+    command.args(["--merge-junit-files", "by-suite"]);
+    let assert = command.assert().success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.junit_props.num_files, 2);
+    assert_eq!(bundle_meta.junit_props.num_original_files, Some(5000));
+    assert_eq!(bundle_meta.junit_props.num_tests, 5000);
+    let bundled_paths: Vec<&str> = bundle_meta.base_props.file_sets[0]
+        .files
+        .iter()
+        .map(|bundled_file| bundled_file.path.as_str())
+        .collect();
+    assert_eq!(bundled_paths, vec!["junit/0", "junit/1"]);
+
+    let mut test_case_names: Vec<String> = Vec::new();
+    for (bundled_path, suite_name) in [("junit/0", "suite-a"), ("junit/1", "suite-b")] {
+        let mut junit_parser = JunitParser::new();
+        junit_parser
+            .parse(BufReader::new(
+                fs::File::open(tar_extract_directory.join(bundled_path)).unwrap(),
+            ))
+            .unwrap();
+        let reports = junit_parser.into_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].name.as_str(), suite_name);
+        // test suites are copied as written, timestamps included
+        assert_eq!(reports[0].test_suites.len(), 2500);
+        for test_suite in &reports[0].test_suites {
+            assert_eq!(test_suite.name.as_str(), suite_name);
+            assert!(test_suite.timestamp.is_some());
+            test_case_names.extend(
+                test_suite
+                    .test_cases
+                    .iter()
+                    .map(|test_case| test_case.name.to_string()),
+            );
+        }
+    }
+    test_case_names.sort();
+    let mut expected_test_case_names: Vec<String> =
+        (0..5000).map(|i| format!("test_{i}")).collect();
+    expected_test_case_names.sort();
+    assert_eq!(test_case_names, expected_test_case_names);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_using_internal_bin() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let internal_bin = generate_mock_internal_bin(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .internal_bin_path("./internal.bin")
+        .command()
+        .assert();
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 6);
+
+    // Quarantining uses the ids of the internal bin
+    let quarantine_request = assert_matches!(
+        &requests[1],
+        RequestPayload::GetQuarantineBulkTestStatus(r) => r
+    );
+    let mut failed_test_ids = internal_bin
+        .test_case_runs
+        .iter()
+        .filter(|test_case_run| test_case_run.status == i32::from(TestCaseRunStatus::Failure))
+        .map(|test_case_run| test_case_run.id.clone())
+        .collect::<Vec<_>>();
+    failed_test_ids.sort();
+    let mut quarantine_test_ids = quarantine_request
+        .test_identifiers
+        .iter()
+        .map(|test| test.id.clone())
+        .collect::<Vec<_>>();
+    quarantine_test_ids.sort();
+    assert_eq!(quarantine_test_ids, failed_test_ids);
+
+    let tar_extract_directory = assert_matches!(&requests[4], RequestPayload::S3Upload(d) => d);
+
+    let bundled_internal_bin = TestResult::decode(
+        fs::read(tar_extract_directory.join("internal.bin"))
+            .unwrap()
+            .as_slice(),
+    )
+    .unwrap();
+    // Each test case run references the junit synthesized from the internal bin in the bundle
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(
+        fs::File::open(tar_extract_directory.join("meta.json")).unwrap(),
+    ))
+    .unwrap();
+    let bundled_paths = bundle_meta
+        .base_props
+        .file_sets
+        .iter()
+        .flat_map(|file_set| file_set.files.iter())
+        .map(|bundled_file| bundled_file.path.clone())
+        .collect::<Vec<_>>();
+    for test_case_run in &bundled_internal_bin.test_case_runs {
+        assert_eq!(test_case_run.source_file, "junit/0");
+        assert!(bundled_paths.contains(&test_case_run.source_file));
+    }
+    let mut expected_internal_bin = internal_bin.clone();
+    for test_case_run in expected_internal_bin.test_case_runs.iter_mut() {
+        test_case_run.source_file = String::from("junit/0");
+    }
+    assert_eq!(bundled_internal_bin, expected_internal_bin);
+
+    let junit_file = fs::File::open(tar_extract_directory.join("junit/0")).unwrap();
+    let mut junit_parser = JunitParser::new();
+    junit_parser.parse(BufReader::new(junit_file)).unwrap();
+    assert!(junit_parser.issues().is_empty());
+    let test_case_ids = junit_parser
+        .into_reports()
+        .iter()
+        .flat_map(|report| report.test_suites.iter())
+        .flat_map(|test_suite| test_suite.test_cases.iter())
+        .filter_map(|test_case| test_case.extra.get("id").map(|id| id.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(test_case_ids.len(), internal_bin.test_case_runs.len());
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_sharded_internal_bin() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let internal_bin = generate_mock_internal_bin(&temp_dir);
+    assert!(internal_bin.test_case_runs.len() > 1);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .internal_bin_path("./internal.bin")
+        .command();
+    command.args(["--internal-bin-max-bytes", "1"]);
+    let assert = command.assert();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = assert_matches!(&requests[4], RequestPayload::S3Upload(d) => d);
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(
+        fs::File::open(tar_extract_directory.join("meta.json")).unwrap(),
+    ))
+    .unwrap();
+    // Every test case run is larger than the max, so each gets a shard
+    assert_eq!(
+        bundle_meta.internal_bin_shards,
+        (0..internal_bin.test_case_runs.len())
+            .map(|i| format!("internal_{i}.bin"))
+            .collect::<Vec<_>>()
+    );
+    assert!(!tar_extract_directory.join("internal.bin").exists());
+
+    let mut sharded_internal_bin = Vec::new();
+    for shard in &bundle_meta.internal_bin_shards {
+        let shard_bytes = fs::read(tar_extract_directory.join(shard)).unwrap();
+        assert_eq!(
+            TestResult::decode(shard_bytes.as_slice())
+                .unwrap()
+                .test_case_runs
+                .len(),
+            1
+        );
+        sharded_internal_bin.extend(shard_bytes);
+    }
+    let mut expected_internal_bin = internal_bin.clone();
+    for test_case_run in expected_internal_bin.test_case_runs.iter_mut() {
+        test_case_run.source_file = String::from("junit/0");
+    }
+    assert_eq!(
+        TestResult::decode(sharded_internal_bin.as_slice()).unwrap(),
+        expected_internal_bin
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_using_internal_bin_with_ci_job_links() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let internal_bin = generate_mock_internal_bin(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_github_list_jobs_handler(|| async {
+        Json(ListJobsResponse {
+            total_count: 1,
+            jobs: vec![Job {
+                id: 102,
+                name: String::from("test (macos-latest)"),
+                status: String::from("in_progress"),
+                runner_name: Some(String::from("runner-2")),
+            }],
+        })
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .internal_bin_path("./internal.bin")
+        .use_quarantining(false)
+        .command()
+        .envs([
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_SERVER_URL", "https://github.com"),
+            ("GITHUB_API_URL", state.host.as_str()),
+            ("GITHUB_TOKEN", "github-token"),
+            ("GITHUB_REPOSITORY", "trunk-io/analytics-cli"),
+            ("GITHUB_RUN_ID", "42"),
+            ("GITHUB_JOB", "test"),
+            ("GITHUB_WORKFLOW", "CI"),
+            ("RUNNER_NAME", "runner-2"),
+            (TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV, "true"),
+        ])
+        .assert();
+
+    let tar_extract_directory = state
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(tar_extract_directory) => Some(tar_extract_directory.clone()),
+            _ => None,
+        })
+        .unwrap();
+    let bundled_internal_bin = TestResult::decode(
+        fs::read(tar_extract_directory.join("internal.bin"))
+            .unwrap()
+            .as_slice(),
+    )
+    .unwrap();
+    assert_eq!(
+        bundled_internal_bin.test_case_runs.len(),
+        internal_bin.test_case_runs.len()
+    );
+    // The job of the matrix, rather than the workflow run
+    for test_case_run in &bundled_internal_bin.test_case_runs {
+        assert_eq!(
+            test_case_run.ci_job_url,
+            "https://github.com/trunk-io/analytics-cli/actions/runs/42/job/102"
+        );
+        assert_eq!(test_case_run.ci_workflow_name, "CI");
+        assert_eq!(test_case_run.ci_external_id, "102");
+    }
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_converter() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let results_dir = temp_dir.path().join("results");
+    fs::create_dir(&results_dir).unwrap();
+    fs::write(
+        results_dir.join("math.json"),
+        "{\"name\": \"adds\", \"status\": \"passed\"}\n{\"name\": \"subtracts\", \"status\": \"failed\"}\n",
+    )
+    .unwrap();
+    fs::write(results_dir.join("broken.json"), "not json\n").unwrap();
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let converter = format!(
+        "{} {{input}} {{output}}",
+        get_test_file_path("test_fixtures/json_converter.sh")
+    );
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command();
+    command.args([
+        "--converter",
+        converter.as_str(),
+        "--converter-input-paths",
+        "results/*.json",
+    ]);
+    let assert = command
+        .assert()
+        .stderr(predicate::str::contains("Leaving"))
+        .stderr(predicate::str::contains("invalid line: not json"));
+
+    let tar_extract_directory = state
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(tar_extract_directory) => Some(tar_extract_directory.clone()),
+            _ => None,
+        })
+        .unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(
+        fs::File::open(tar_extract_directory.join("meta.json")).unwrap(),
+    ))
+    .unwrap();
+
+    let converter_meta = bundle_meta.converter.unwrap();
+    assert_eq!(converter_meta.command, converter);
+    assert_eq!(
+        converter_meta.version,
+        Some(String::from("json-converter 1.2.3"))
+    );
+    let [broken, math] = converter_meta.conversions.as_slice() else {
+        panic!("Expected 2 conversions: {:?}", converter_meta.conversions);
+    };
+    assert!(broken.input_path.ends_with("broken.json"));
+    assert_eq!(broken.output_path, None);
+    assert!(broken
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("invalid line: not json"));
+    assert!(math.input_path.ends_with("math.json"));
+    assert_eq!(math.error, None);
+
+    // Only the converted junit is bundled, and its tests counted
+    let bundled_files: Vec<_> = bundle_meta
+        .base_props
+        .file_sets
+        .iter()
+        .flat_map(|file_set| file_set.files.iter())
+        .collect();
+    assert_eq!(bundled_files.len(), 1);
+    assert_eq!(
+        Some(&bundled_files[0].original_path),
+        math.output_path.as_ref()
+    );
+    let junit = fs::read_to_string(tar_extract_directory.join(&bundled_files[0].path)).unwrap();
+    assert!(junit.contains("name=\"subtracts\""));
+    assert_eq!(bundle_meta.junit_props.num_files, 1);
+    assert_eq!(bundle_meta.junit_props.num_tests, 2);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundles_of_multi_repo_config() {
+    let temp_dir = tempdir().unwrap();
+    for repo in ["service-a", "service-b"] {
+        let repo_root = temp_dir.path().join(repo);
+        fs::create_dir(&repo_root).unwrap();
+        generate_mock_git_repo(&repo_root);
+        fs::create_dir(repo_root.join("results")).unwrap();
+        generate_mock_valid_junit_xmls(repo_root.join("results"));
+    }
+    fs::create_dir(temp_dir.path().join("not-a-repo")).unwrap();
+    fs::write(
+        temp_dir.path().join("multi-repo.toml"),
+        r#"
+            [[repos]]
+            repo_root = "not-a-repo"
+            junit_paths = ["*.xml"]
+
+            [[repos]]
+            repo_root = "service-a"
+            junit_paths = ["results/*.xml"]
+
+            [[repos]]
+            repo_root = "service-b"
+            junit_paths = ["results/*.xml"]
+            org_url_slug = "other-org"
+            variant = "linux"
+        "#,
+    )
+    .unwrap();
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .multi_repo_config("multi-repo.toml")
+        .use_quarantining(false)
+        .command();
+    command.args(["--multi-repo-parallel", "2"]);
+    // the repo that failed doesn't stop the others, but fails the run
+    let assert = command
+        .assert()
+        .code(ErrorCode::GitRepoNotFound.exit_code())
+        .stdout(predicate::str::contains("not-a-repo"))
+        .stdout(predicate::str::contains("Repo"))
+        .stdout(predicate::str::contains("service-a"))
+        .stdout(predicate::str::contains("service-b"));
+
+    let requests = state.requests.lock().unwrap().clone();
+    // the token is checked once per org
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|request| matches!(request, RequestPayload::ValidateTokenOrg(_)))
+            .count(),
+        2
+    );
+    let mut bundle_metas: Vec<BundleMeta> = requests
+        .iter()
+        .filter_map(|request| match request {
+            RequestPayload::S3Upload(tar_extract_directory) => Some(
+                serde_json::from_reader(BufReader::new(
+                    fs::File::open(tar_extract_directory.join("meta.json")).unwrap(),
+                ))
+                .unwrap(),
+            ),
+            _ => None,
+        })
+        .collect();
+    // the repos upload in parallel, so in any order
+    bundle_metas.sort_by(|a, b| {
+        a.base_props
+            .repo
+            .repo_root
+            .cmp(&b.base_props.repo.repo_root)
+    });
+    let [service_a, service_b] = bundle_metas.as_slice() else {
+        panic!("Expected 2 uploads, got {}", bundle_metas.len());
+    };
+
+    for (bundle_meta, repo, org, variant) in [
+        (service_a, "service-a", "test-org", None),
+        (service_b, "service-b", "other-org", Some("linux")),
+    ] {
+        assert!(bundle_meta.base_props.repo.repo_root.ends_with(repo));
+        assert_eq!(bundle_meta.base_props.org, org);
+        assert_eq!(bundle_meta.variant.as_deref(), variant);
+        let bundled_files: Vec<_> = bundle_meta
+            .base_props
+            .file_sets
+            .iter()
+            .flat_map(|file_set| file_set.files.iter())
+            .collect();
+        assert!(!bundled_files.is_empty());
+        for bundled_file in bundled_files {
+            assert!(
+                bundled_file
+                    .original_path
+                    .starts_with(&bundle_meta.base_props.repo.repo_root),
+                "{} is not in {}",
+                bundled_file.original_path,
+                repo
+            );
+        }
+    }
+    // the env vars and host are scanned once for both
+    assert_eq!(service_a.base_props.envs, service_b.base_props.envs);
+    assert_eq!(service_a.host_info, service_b.host_info);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_using_internal_bin_with_suite_hostname_and_package() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let internal_bin = generate_internal_bin_from_junits(
+        &temp_dir,
+        &[get_test_file_path(
+            "../context/test_fixtures/junit_android_sharded.xml",
+        )],
+    );
+    let suite_attrs = |test_result: &TestResult| -> Vec<(String, String)> {
+        test_result
+            .test_case_runs
+            .iter()
+            .map(|test_case_run| {
+                (
+                    test_case_run.hostname.clone(),
+                    test_case_run.package.clone(),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(
+        suite_attrs(&internal_bin),
+        vec![
+            (
+                String::from("emulator-5554"),
+                String::from("com.example.app")
+            ),
+            (
+                String::from("emulator-5554"),
+                String::from("com.example.app")
+            ),
+            (
+                String::from("emulator-5556"),
+                String::from("com.example.app.checkout")
+            ),
+        ]
+    );
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .internal_bin_path("./internal.bin")
+        .command()
+        .assert()
+        // a test case of the fixture failed
+        .failure();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+
+    let bundled_internal_bin = TestResult::decode(
+        fs::read(tar_extract_directory.join("internal.bin"))
+            .unwrap()
+            .as_slice(),
+    )
+    .unwrap();
+    assert_eq!(
+        suite_attrs(&bundled_internal_bin),
+        suite_attrs(&internal_bin)
+    );
+    assert!(bundled_internal_bin
+        .test_case_runs
+        .iter()
+        .all(|test_case_run| test_case_run.source_file == "junit/0"));
+
+    // the junit synthesized from the internal bin keeps them on its test suites
+    let junit_file = fs::File::open(tar_extract_directory.join("junit/0")).unwrap();
+    let mut junit_parser = JunitParser::new();
+    junit_parser.parse(BufReader::new(junit_file)).unwrap();
+    let junit_suite_attrs = junit_parser
+        .into_reports()
+        .iter()
+        .flat_map(|report| report.test_suites.iter())
+        .map(|test_suite| {
+            (
+                test_suite.name.to_string(),
+                test_suite
+                    .extra
+                    .get(extra_attrs::HOSTNAME)
+                    .map(|hostname| hostname.to_string()),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        junit_suite_attrs,
+        vec![
+            (
+                String::from("com.example.app.CheckoutTest"),
+                Some(String::from("emulator-5556"))
+            ),
+            (
+                String::from("com.example.app.LoginTest"),
+                Some(String::from("emulator-5554"))
+            ),
+        ]
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_invalid_internal_bin() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    fs::write(temp_dir.path().join("internal.bin"), b"not a proto").unwrap();
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .internal_bin_path("./internal.bin")
+        .command()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to decode internal bin \"./internal.bin\"",
+        ));
+
+    // only the preflight runs before the internal bin is read
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_matches!(&requests[0], RequestPayload::ValidateTokenOrg(_));
+
     // HINT: View CLI output with `cargo test -- --nocapture`
     println!("{assert}");
 }
@@ -267,7 +1050,7 @@ async fn upload_bundle_success_status_code() {
 
     // No quarantine request
     let requests = state.requests.lock().unwrap().clone();
-    assert_eq!(requests.len(), 4);
+    assert_eq!(requests.len(), 5);
 
     // HINT: View CLI output with `cargo test -- --nocapture`
     println!("{assert}");
@@ -306,37 +1089,204 @@ async fn upload_bundle_success_timestamp_status_code() {
 
     // No quarantine request
     let requests = state.requests.lock().unwrap().clone();
-    assert_eq!(requests.len(), 4);
+    assert_eq!(requests.len(), 5);
 
     // HINT: View CLI output with `cargo test -- --nocapture`
     println!("{assert}");
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn upload_bundle_empty_junit_paths() {
+async fn upload_bundle_gitlab_merge_request_detached_head() {
     let temp_dir = tempdir().unwrap();
-    generate_mock_git_repo(&temp_dir);
+    let source_sha = setup_repo_with_detached_merge_commit(&temp_dir).unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
 
     let state = MockServerBuilder::new().spawn_mock_server().await;
 
     let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
-        .junit_paths("")
         .command()
+        .env("GITLAB_CI", "true")
+        .env("CI_MERGE_REQUEST_IID", "7")
+        .env("CI_MERGE_REQUEST_EVENT_TYPE", "merged_result")
+        .env("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME", "feature/detached")
+        .env("CI_MERGE_REQUEST_SOURCE_BRANCH_SHA", &source_sha)
+        .env("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "main")
         .assert()
-        .failure()
-        .stderr(predicate::str::contains(
-            "error: a value is required for '--junit-paths <JUNIT_PATHS>' but none was supplied",
-        ));
+        .failure();
 
-    // HINT: View CLI output with `cargo test -- --nocapture`
-    println!("{assert}");
-}
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .into_iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
 
-#[tokio::test(flavor = "multi_thread")]
-async fn upload_bundle_no_files_allow_missing_junit_files() {
-    enum Flag {
-        Long,
-        LongWithEquals,
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let reader = BufReader::new(file);
+    let bundle_meta: BundleMeta = serde_json::from_reader(reader).unwrap();
+    let base_props = bundle_meta.base_props;
+
+    assert_eq!(base_props.repo.repo_head_branch, "feature/detached");
+    assert_eq!(base_props.repo.repo_head_sha, source_sha);
+    assert_eq!(
+        base_props.envs.get("CI_MERGE_REQUEST_IID"),
+        Some(&String::from("7"))
+    );
+    assert_eq!(
+        base_props.envs.get("CI_MERGE_REQUEST_TARGET_BRANCH_NAME"),
+        Some(&String::from("main"))
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_jenkins_multibranch_pr() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let jenkins_envs = [
+        ("JENKINS_URL", "https://jenkins.example.com/"),
+        (
+            "BUILD_URL",
+            "https://jenkins.example.com/job/analytics-cli/job/PR-7/3/",
+        ),
+        ("GIT_BRANCH", "PR-7"),
+        ("CHANGE_ID", "7"),
+        ("CHANGE_BRANCH", "feature/jenkins"),
+        ("CHANGE_TARGET", "main"),
+        ("BUILD_NUMBER", "3"),
+        ("NODE_NAME", "linux-agent-1"),
+    ];
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .envs(jenkins_envs)
+        .assert()
+        .failure();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .into_iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let reader = BufReader::new(file);
+    let bundle_meta: BundleMeta = serde_json::from_reader(reader).unwrap();
+    let base_props = bundle_meta.base_props;
+
+    for (key, value) in jenkins_envs {
+        assert_eq!(
+            base_props.envs.get(key),
+            Some(&String::from(value)),
+            "{key} missing from meta.json envs"
+        );
+    }
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_repo_head_author_and_commit_message_overrides() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .args([
+            "--repo-head-author-name",
+            "Spikey",
+            "--repo-head-author-email",
+            "spikey@trunk.io",
+            "--repo-head-commit-message",
+            "Overridden commit message",
+        ])
+        .assert()
+        .failure();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .into_iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let reader = BufReader::new(file);
+    let bundle_meta: BundleMeta = serde_json::from_reader(reader).unwrap();
+    let base_props = bundle_meta.base_props;
+
+    assert_eq!(base_props.repo.repo_head_author_name, "Spikey");
+    assert_eq!(base_props.repo.repo_head_author_email, "spikey@trunk.io");
+    assert_eq!(
+        base_props.repo.repo_head_commit_message,
+        "Overridden commit message"
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_invalid_repo_head_author_email() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .args(["--repo-head-author-email", "spikey"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value 'spikey' for '--repo-head-author-email <REPO_HEAD_AUTHOR_EMAIL>'",
+        ));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_empty_junit_paths() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("")
+        .command()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "error: a value is required for '--junit-paths <JUNIT_PATHS>' but none was supplied",
+        ));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_no_files_allow_missing_junit_files() {
+    enum Flag {
+        Long,
+        LongWithEquals,
         Alias,
         AliasWithEquals,
         Default,
@@ -432,42 +1382,65 @@ async fn upload_bundle_valid_repo_root() {
         .repo_root("../")
         .command()
         .assert()
-        .failure()
+        .code(ErrorCode::GitRepoNotFound.exit_code())
         .stderr(predicate::str::contains(
             "Error: Failed to open git repository at \"../\"",
-        ));
+        ))
+        .stderr(predicate::str::contains("error_code=git_repo_not_found"));
 
     // HINT: View CLI output with `cargo test -- --nocapture`
     println!("{assert}");
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn upload_bundle_when_server_down() {
+async fn upload_bundle_s3_failure_marks_upload_failed() {
     let temp_dir = tempdir().unwrap();
     generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
 
-    let assert = CommandBuilder::upload(temp_dir.path(), String::from("https://localhost:10"))
+    let mut mock_server_builder = MockServerBuilder::new();
+    // Read the body before rejecting it, otherwise the upload may fail with a broken pipe instead
+    mock_server_builder.set_s3_upload_handler(|_: Bytes| async { StatusCode::FORBIDDEN });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
         .command()
         .assert()
-        .success();
+        .failure()
+        .stderr(predicate::str::contains("Failed to upload bundle to S3."));
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_matches!(&requests[2], RequestPayload::CreateBundleUpload(_));
+    assert_eq!(
+        requests.last().unwrap(),
+        &RequestPayload::UpdateBundleUpload(UpdateBundleUploadRequest {
+            id: "test-bundle-upload-id".to_string(),
+            upload_status: BundleUploadStatus::UploadFailed
+        }),
+    );
 
+    // HINT: View CLI output with `cargo test -- --nocapture`
     println!("{assert}");
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn upload_bundle_with_no_junit_files_no_quarantine_successful_upload() {
+async fn upload_bundle_update_status_failure_does_not_affect_exit_code() {
     let temp_dir = tempdir().unwrap();
     generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
 
-    let state = MockServerBuilder::new().spawn_mock_server().await;
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_update_bundle_handler(|| async { StatusCode::FORBIDDEN });
+    let state = mock_server_builder.spawn_mock_server().await;
 
     let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
         .command()
         .assert()
-        .code(0)
         .success()
         .stderr(predicate::str::contains(
-            "No JUnit files found, not quarantining any tests",
+            "Failed to update bundle upload status to UploadComplete",
         ));
 
     // HINT: View CLI output with `cargo test -- --nocapture`
@@ -475,109 +1448,1665 @@ async fn upload_bundle_with_no_junit_files_no_quarantine_successful_upload() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn quarantines_tests_regardless_of_upload() {
+async fn upload_bundle_no_upload_skips_api() {
     let temp_dir = tempdir().unwrap();
     generate_mock_git_repo(&temp_dir);
     generate_mock_valid_junit_xmls(&temp_dir);
-    generate_mock_codeowners(&temp_dir);
 
-    let mut mock_server_builder = MockServerBuilder::new();
+    let state = MockServerBuilder::new().spawn_mock_server().await;
 
-    lazy_static! {
-        static ref QUARANTINE_CONFIG_RESPONSE: Arc<Mutex<QuarantineConfigResponse>> =
-            Arc::new(Mutex::new(QuarantineConfigResponse::None));
-    }
-    #[derive(Debug, Clone, Copy)]
-    enum QuarantineConfigResponse {
-        Disabled,
-        None,
-        Some,
-        All,
-    }
-    mock_server_builder.set_get_quarantining_config_handler(
-        |Json(get_quarantine_bulk_test_status_request): Json<GetQuarantineConfigRequest>| {
-            let mut test_ids = get_quarantine_bulk_test_status_request
-                .test_identifiers
-                .into_iter()
-                .map(|t| t.id)
-                .collect::<Vec<_>>();
-            let quarantine_config_response = *QUARANTINE_CONFIG_RESPONSE.lock().unwrap();
-            let quarantined_tests = match quarantine_config_response {
-                QuarantineConfigResponse::Disabled => Vec::new(),
-                QuarantineConfigResponse::None => Vec::new(),
-                QuarantineConfigResponse::Some => test_ids.split_off(1),
-                QuarantineConfigResponse::All => test_ids,
-            };
-            let is_disabled = matches!(
-                quarantine_config_response,
-                QuarantineConfigResponse::Disabled
-            );
-            async move {
-                Json(GetQuarantineConfigResponse {
-                    is_disabled,
-                    quarantined_tests,
-                })
-            }
-        },
-    );
-    #[derive(Debug, Clone, Copy)]
-    enum CreateBundleResponse {
-        Error,
-        Success,
-    }
-    lazy_static! {
-        static ref CREATE_BUNDLE_RESPONSE: Arc<Mutex<CreateBundleResponse>> =
-            Arc::new(Mutex::new(CreateBundleResponse::Error));
-    }
-    mock_server_builder.set_create_bundle_handler(
-        |State(state): State<SharedMockServerState>, _: Json<CreateBundleUploadRequest>| {
-            let create_bundle_response = *CREATE_BUNDLE_RESPONSE.lock().unwrap();
-            let result = match create_bundle_response {
-                CreateBundleResponse::Error => Err(String::from("Server is down")),
-                CreateBundleResponse::Success => {
-                    let host = &state.host;
-                    Ok(Json(CreateBundleUploadResponse {
-                        id: String::from("test-bundle-upload-id"),
-                        id_v2: String::from("test-bundle-upload-id-v2"),
-                        url: format!("{host}/s3upload"),
-                        key: String::from("unused"),
-                    }))
-                }
-            };
-            async { result }
-        },
-    );
-    let state = mock_server_builder.spawn_mock_server().await;
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--no-upload")
+        .assert()
+        .stderr(predicate::str::contains("Skipping upload."));
 
-    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone()).command();
+    let requests = state.requests.lock().unwrap().clone();
+    assert!(requests.is_empty());
 
-    // First run won't quarantine any tests
-    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::None;
-    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
-    command.assert().failure();
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
 
-    // Second run quarantines all, but 1 test
-    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::Some;
-    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
-    command.assert().failure();
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_no_upload_registers_dry_run() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
 
-    // Third run will not quarantine all tests because of upload failure
-    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::All;
-    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
-    command.assert().failure();
+    let state = MockServerBuilder::new().spawn_mock_server().await;
 
-    // Fourth run will quarantine all tests, and upload them
-    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::All;
-    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Success;
-    command.assert().success();
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args(["--no-upload", "--register-dry-run"])
+        .assert()
+        .stderr(predicate::str::contains("Skipping upload."));
 
-    // Fifth run will run with quarantining disabled, but will fail to upload
-    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::Disabled;
-    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
-    command.assert().failure();
+    let requests = state.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 4);
+    assert_matches!(&requests[1], RequestPayload::CreateRepo(_));
+    assert_matches!(&requests[2], RequestPayload::CreateBundleUpload(_));
+    assert_eq!(
+        requests[3],
+        RequestPayload::UpdateBundleUpload(UpdateBundleUploadRequest {
+            id: "test-bundle-upload-id".to_string(),
+            upload_status: BundleUploadStatus::DryRun
+        }),
+    );
 
-    // Sixth run will run with quarantining disabled, and will succeed with upload
-    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::Disabled;
-    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Success;
-    command.assert().success();
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_skips_non_junit_and_gitignored_files() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    fs::write(
+        temp_dir.path().join("checkstyle-result.xml"),
+        r#"<?xml version="1.0"?><checkstyle version="10.0"><file name="Main.java"/></checkstyle>"#,
+    )
+    .unwrap();
+    fs::create_dir_all(temp_dir.path().join(".idea")).unwrap();
+    generate_mock_valid_junit_xmls(temp_dir.path().join(".idea"));
+    fs::write(temp_dir.path().join(".gitignore"), ".idea/\n").unwrap();
+    let dry_run_output = temp_dir.path().join("dry-run");
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("**/*.xml")
+        .use_quarantining(false)
+        .command()
+        .args(["--no-upload", "--respect-gitignore", "--dry-run-output"])
+        .arg(&dry_run_output)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  Skipped non-junit files: 1"));
+    println!("{assert}");
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dry_run_output.join("summary.json")).unwrap())
+            .unwrap();
+    assert_eq!(summary["files_per_glob"]["**/*.xml"], 1);
+    assert_eq!(summary["skipped_non_junit_files"], 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_explains_files() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let junit_paths = generate_mock_valid_junit_xmls(&temp_dir);
+    fs::rename(&junit_paths[0], temp_dir.path().join("junit.xml")).unwrap();
+    fs::write(
+        temp_dir.path().join("checkstyle-result.xml"),
+        r#"<?xml version="1.0"?><checkstyle version="10.0"><file name="Main.java"/></checkstyle>"#,
+    )
+    .unwrap();
+    fs::create_dir_all(temp_dir.path().join(".idea")).unwrap();
+    fs::copy(
+        temp_dir.path().join("junit.xml"),
+        temp_dir.path().join(".idea/junit.xml"),
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join(".gitignore"), ".idea/\n").unwrap();
+    let expired_path = temp_dir.path().join("expired.xml");
+    fs::copy(temp_dir.path().join("junit.xml"), &expired_path).unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&expired_path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(3 * 60 * 60))
+        .unwrap();
+    fs::create_dir_all(temp_dir.path().join("other")).unwrap();
+    fs::write(temp_dir.path().join("other/notes.txt"), "").unwrap();
+    fs::write(temp_dir.path().join("other/results.junit"), "<testsuites/>").unwrap();
+    let dry_run_output = temp_dir.path().join("dry-run");
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("**/*.xml,other/*")
+        .use_quarantining(false)
+        .command()
+        .args([
+            "--explain-files",
+            "--respect-gitignore",
+            "--junit-max-age",
+            "2h",
+            "--no-upload",
+            "--dry-run-output",
+        ])
+        .arg(&dry_run_output)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Files matched by **/*.xml:"))
+        .stdout(predicate::str::contains(
+            "✖ .idea/junit.xml: ignored by git, with --respect-gitignore",
+        ))
+        .stdout(predicate::str::contains(
+            "✖ expired.xml: last modified longer than --junit-max-age ago",
+        ))
+        .stdout(predicate::str::contains(
+            "✖ checkstyle-result.xml: root element is not testsuites or testsuite",
+        ))
+        .stdout(predicate::str::contains("✔ junit.xml: included"))
+        .stdout(predicate::str::contains("Files matched by other/*:"))
+        .stdout(predicate::str::contains(
+            "✖ other/notes.txt: not a .xml, .junit or .bin file",
+        ))
+        .stdout(predicate::str::contains(
+            "✖ other/results.junit: not a .xml or .bin file",
+        ));
+    println!("{assert}");
+
+    let file_decisions: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(dry_run_output.join("file_decisions.json")).unwrap(),
+    )
+    .unwrap();
+    let mut file_decisions = file_decisions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file_decision| {
+            (
+                file_decision["path"].as_str().unwrap(),
+                file_decision["rule"].as_str().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    file_decisions.sort();
+    assert_eq!(
+        file_decisions,
+        [
+            (".idea/junit.xml", "gitignored"),
+            ("checkstyle-result.xml", "not_junit"),
+            ("expired.xml", "expired"),
+            ("junit.xml", "included"),
+            ("other/notes.txt", "not_allowed"),
+            ("other/results.junit", "unsupported_extension"),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_no_upload_writes_dry_run_output() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    let dry_run_output = temp_dir.path().join("dry-run");
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args(["--no-upload", "--dry-run-output"])
+        .arg(&dry_run_output)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Dry run summary:"))
+        .stdout(predicate::str::contains("    ./*: 1"))
+        .stdout(predicate::str::contains(
+            "    head_branch: refs/heads/trunk/test",
+        ))
+        .stdout(predicate::str::contains("  Bundle size: "));
+    println!("{assert}");
+
+    assert!(dry_run_output.join("bundle.tar.zstd").is_file());
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dry_run_output.join("summary.json")).unwrap())
+            .unwrap();
+    assert_eq!(summary["files_per_glob"]["./*"], 1);
+    assert_eq!(
+        summary["quarantine_outcome"]["outcome"],
+        "QuarantiningUnavailable"
+    );
+    let tests_per_status = summary["tests_per_status"].as_object().unwrap();
+    assert_eq!(
+        tests_per_status.keys().collect::<Vec<_>>(),
+        ["error", "failure", "skipped", "success"]
+    );
+    assert!(summary["bundle_size_bytes"].as_u64().unwrap() > 0);
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args(["--no-upload", "--dry-run-output"])
+        .arg(&dry_run_output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not empty"));
+    println!("{assert}");
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args(["--no-upload", "--force", "--dry-run-output"])
+        .arg(&dry_run_output)
+        .assert()
+        .success();
+    println!("{assert}");
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert!(requests.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_register_dry_run_requires_no_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let assert = CommandBuilder::upload(temp_dir.path(), String::from("https://localhost:10"))
+        .command()
+        .arg("--register-dry-run")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--no-upload"));
+
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_when_server_down() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let assert = CommandBuilder::upload(temp_dir.path(), String::from("https://localhost:10"))
+        .command()
+        .assert()
+        .success();
+
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_no_junit_files_no_quarantine_successful_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .assert()
+        .code(0)
+        .success()
+        .stderr(predicate::str::contains(
+            "No JUnit files found, not quarantining any tests",
+        ));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quarantines_tests_regardless_of_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+    generate_mock_codeowners(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+
+    lazy_static! {
+        static ref QUARANTINE_CONFIG_RESPONSE: Arc<Mutex<QuarantineConfigResponse>> =
+            Arc::new(Mutex::new(QuarantineConfigResponse::None));
+    }
+    #[derive(Debug, Clone, Copy)]
+    enum QuarantineConfigResponse {
+        Disabled,
+        None,
+        Some,
+        All,
+    }
+    mock_server_builder.set_get_quarantining_config_handler(
+        |Json(get_quarantine_bulk_test_status_request): Json<GetQuarantineConfigRequest>| {
+            let mut test_ids = get_quarantine_bulk_test_status_request
+                .test_identifiers
+                .into_iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>();
+            let quarantine_config_response = *QUARANTINE_CONFIG_RESPONSE.lock().unwrap();
+            let quarantined_tests = match quarantine_config_response {
+                QuarantineConfigResponse::Disabled => Vec::new(),
+                QuarantineConfigResponse::None => Vec::new(),
+                QuarantineConfigResponse::Some => test_ids.split_off(1),
+                QuarantineConfigResponse::All => test_ids,
+            };
+            let is_disabled = matches!(
+                quarantine_config_response,
+                QuarantineConfigResponse::Disabled
+            );
+            async move {
+                Json(GetQuarantineConfigResponse {
+                    is_disabled,
+                    quarantined_tests,
+                })
+            }
+        },
+    );
+    #[derive(Debug, Clone, Copy)]
+    enum CreateBundleResponse {
+        Error,
+        Success,
+    }
+    lazy_static! {
+        static ref CREATE_BUNDLE_RESPONSE: Arc<Mutex<CreateBundleResponse>> =
+            Arc::new(Mutex::new(CreateBundleResponse::Error));
+    }
+    mock_server_builder.set_create_bundle_handler(
+        |State(state): State<SharedMockServerState>, _: Json<CreateBundleUploadRequest>| {
+            let create_bundle_response = *CREATE_BUNDLE_RESPONSE.lock().unwrap();
+            let result = match create_bundle_response {
+                CreateBundleResponse::Error => Err(String::from("Server is down")),
+                CreateBundleResponse::Success => {
+                    let host = &state.host;
+                    Ok(Json(CreateBundleUploadResponse {
+                        id: String::from("test-bundle-upload-id"),
+                        id_v2: String::from("test-bundle-upload-id-v2"),
+                        url: format!("{host}/s3upload"),
+                        key: String::from("unused"),
+                        minimum_recommended_cli_version: None,
+                    }))
+                }
+            };
+            async { result }
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone()).command();
+
+    // First run won't quarantine any tests
+    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::None;
+    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
+    command.assert().failure();
+
+    // Second run quarantines all, but 1 test
+    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::Some;
+    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
+    command.assert().failure();
+
+    // Third run will not quarantine all tests because of upload failure
+    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::All;
+    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
+    command.assert().failure();
+
+    // Fourth run will quarantine all tests, and upload them
+    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::All;
+    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Success;
+    command.assert().success();
+
+    // Fifth run will run with quarantining disabled, but will fail to upload
+    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::Disabled;
+    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Error;
+    command.assert().failure();
+
+    // Sixth run will run with quarantining disabled, and will succeed with upload
+    *QUARANTINE_CONFIG_RESPONSE.lock().unwrap() = QuarantineConfigResponse::Disabled;
+    *CREATE_BUNDLE_RESPONSE.lock().unwrap() = CreateBundleResponse::Success;
+    command.assert().success();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_summarizes_failures_by_group() {
+    const JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="suite">
+    <testsuite name="unit" tests="4" failures="3">
+        <testcase name="test_a" classname="a" file="src/a_test.rs">
+            <failure message="boom" />
+        </testcase>
+        <testcase name="test_b" classname="a" file="src/a_test.rs">
+            <failure message="boom" />
+        </testcase>
+        <testcase name="test_c" classname="b" file="lib/b_test.rs">
+            <failure message="boom" />
+        </testcase>
+        <testcase name="test_passes" classname="a" file="src/a_test.rs" />
+    </testsuite>
+</testsuites>
+"#;
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(JUNIT_XML, &temp_dir);
+    fs::write(temp_dir.path().join("CODEOWNERS"), "src/ @team-src\n").unwrap();
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_get_quarantining_config_handler(
+        |Json(get_quarantine_config_request): Json<GetQuarantineConfigRequest>| async move {
+            let quarantined_tests = get_quarantine_config_request
+                .test_identifiers
+                .into_iter()
+                .filter(|test| test.name == "test_c")
+                .map(|test| test.id)
+                .collect();
+            Json(GetQuarantineConfigResponse {
+                is_disabled: false,
+                quarantined_tests,
+            })
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            [
+                "2 failures",
+                "  @team-src: 2 failures",
+                "    src/a_test.rs: 2 failures",
+                "      - test_a",
+                "      - test_b",
+                "Quarantined: 1 failure",
+                "  unowned: 1 failure",
+                "    lib/b_test.rs: 1 failure",
+                "      - test_c",
+            ]
+            .join("\n"),
+        ));
+    println!("{assert}");
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .summary_group_by("suite")
+        .command()
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            ["2 failures", "  unit: 2 failures", "    - test_a"].join("\n"),
+        ));
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_local_flake_detection() {
+    const JUNIT_XML_FAILURE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="suite">
+    <testsuite name="unit" tests="2" failures="1">
+        <testcase name="test_flaky" classname="a" file="src/a_test.rs">
+            <failure message="boom" />
+        </testcase>
+        <testcase name="test_passes" classname="a" file="src/a_test.rs" />
+    </testsuite>
+</testsuites>
+"#;
+    const JUNIT_XML_SUCCESS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="suite">
+    <testsuite name="unit" tests="2" failures="0">
+        <testcase name="test_flaky" classname="a" file="src/a_test.rs" />
+        <testcase name="test_passes" classname="a" file="src/a_test.rs" />
+    </testsuite>
+</testsuites>
+"#;
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let state_dir = tempdir().unwrap();
+    let state_path = state_dir.path().join("flakes.json");
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let upload = |junit_xml: &str| {
+        write_junit_xml_to_dir(junit_xml, &temp_dir);
+        CommandBuilder::upload(temp_dir.path(), state.host.clone())
+            .command()
+            .args(["--local-flake-detection", state_path.to_str().unwrap()])
+            .assert()
+    };
+
+    // a test that broke is not flaky
+    upload(JUNIT_XML_FAILURE).failure();
+    upload(JUNIT_XML_SUCCESS).success();
+    let assert = upload(JUNIT_XML_FAILURE)
+        .success()
+        .stderr(predicate::str::contains(
+            "unit -> test_flaky [LOCALLY QUARANTINED: alternating]",
+        ));
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .filter_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .last()
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert!(bundle_meta.base_props.quarantined_tests.is_empty());
+    assert_eq!(bundle_meta.locally_quarantined_tests.len(), 1);
+    assert_eq!(bundle_meta.locally_quarantined_tests[0].name, "test_flaky");
+    assert_eq!(
+        bundle_meta.locally_quarantined_tests[0].reason,
+        "alternating"
+    );
+
+    let local_flake_state: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+    assert_eq!(local_flake_state["num_runs"], 3);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_posts_pr_comment() {
+    const JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="suite">
+    <testsuite name="unit" tests="3" failures="2">
+        <testcase name="test_a" classname="a" file="src/a_test.rs">
+            <failure message="boom" />
+        </testcase>
+        <testcase name="test_b" classname="a" file="src/a_test.rs">
+            <failure message="boom" />
+        </testcase>
+        <testcase name="test_passes" classname="a" file="src/a_test.rs" />
+    </testsuite>
+</testsuites>
+"#;
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(JUNIT_XML, &temp_dir);
+    fs::write(temp_dir.path().join("CODEOWNERS"), "src/ @team-src\n").unwrap();
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_github_list_issue_comments_handler(
+        |State(state): State<SharedMockServerState>, uri: Uri| async move {
+            let mut requests = state.requests.lock().unwrap();
+            // the comment created by an earlier run, if there was one
+            let comments = requests
+                .iter()
+                .filter_map(|request| match request {
+                    RequestPayload::GitHubCreateIssueComment(_, body) => Some(IssueComment {
+                        id: 1,
+                        body: Some(body.clone()),
+                    }),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            requests.push(RequestPayload::GitHubListIssueComments(String::from(
+                uri.path(),
+            )));
+            Json(comments)
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let github_envs = [
+        ("GITHUB_ACTIONS", "true"),
+        ("GITHUB_API_URL", state.host.as_str()),
+        ("GITHUB_TOKEN", "github-token"),
+        ("GITHUB_REPOSITORY", "trunk-io/analytics-cli"),
+        ("GITHUB_REF", "refs/pull/7/merge"),
+    ];
+    let github_requests = |state: &SharedMockServerState| {
+        state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| {
+                matches!(
+                    request,
+                    RequestPayload::GitHubListIssueComments(..)
+                        | RequestPayload::GitHubCreateIssueComment(..)
+                        | RequestPayload::GitHubUpdateIssueComment(..)
+                )
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .envs(github_envs)
+        .assert();
+    println!("{assert}");
+    let exit_code = assert.get_output().status.code();
+    assert!(github_requests(&state).is_empty());
+
+    for _ in 0..2 {
+        let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+            .use_quarantining(false)
+            .command()
+            .arg("--post-pr-comment")
+            .envs(github_envs)
+            .assert()
+            .code(exit_code.unwrap());
+        println!("{assert}");
+    }
+
+    let github_requests = github_requests(&state);
+    assert_eq!(github_requests.len(), 4);
+    let comment_body = match &github_requests[1] {
+        RequestPayload::GitHubCreateIssueComment(path, body) => {
+            assert_eq!(path, "/repos/trunk-io/analytics-cli/issues/7/comments");
+            body.clone()
+        }
+        request => panic!("expected a comment to be created, got {:?}", request),
+    };
+    assert!(comment_body.starts_with(PR_COMMENT_MARKER));
+    assert!(comment_body.contains("### 2 failures\n- `@team-src`: 2 failures\n"));
+    assert!(comment_body.contains("/blob/"));
+    assert!(comment_body.contains("/src/a_test.rs): 2 failures\n    - test\\_a\n"));
+    assert_eq!(
+        github_requests[3],
+        RequestPayload::GitHubUpdateIssueComment(
+            String::from("/repos/trunk-io/analytics-cli/issues/comments/1"),
+            comment_body
+        )
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_pr_comment_failure_keeps_exit_code() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder
+        .set_github_create_issue_comment_handler(|| async { StatusCode::INTERNAL_SERVER_ERROR });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--post-pr-comment")
+        .envs([
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_API_URL", state.host.as_str()),
+            ("GITHUB_TOKEN", "github-token"),
+            ("GITHUB_REPOSITORY", "trunk-io/analytics-cli"),
+            ("GITHUB_REF", "refs/pull/7/merge"),
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Failed to comment on pull request #7",
+        ));
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_github_external_id_from_actions_api() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_github_list_jobs_handler(|| async {
+        Json(ListJobsResponse {
+            total_count: 2,
+            jobs: vec![
+                Job {
+                    id: 101,
+                    name: String::from("test (ubuntu-latest)"),
+                    status: String::from("in_progress"),
+                    runner_name: Some(String::from("runner-1")),
+                },
+                Job {
+                    id: 102,
+                    name: String::from("test (macos-latest)"),
+                    status: String::from("in_progress"),
+                    runner_name: Some(String::from("runner-2")),
+                },
+            ],
+        })
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let github_envs = [
+        ("GITHUB_ACTIONS", "true"),
+        ("GITHUB_API_URL", state.host.as_str()),
+        ("GITHUB_TOKEN", "github-token"),
+        ("GITHUB_REPOSITORY", "trunk-io/analytics-cli"),
+        ("GITHUB_RUN_ID", "42"),
+        ("GITHUB_JOB", "test"),
+        ("RUNNER_NAME", "runner-2"),
+    ];
+    for use_actions_api in [false, true] {
+        let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+            .use_quarantining(false)
+            .command()
+            .envs(github_envs)
+            .env(
+                TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV,
+                use_actions_api.to_string(),
+            )
+            .assert()
+            .success();
+        println!("{assert}");
+
+        let tar_extract_directory = state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find_map(|request| match request {
+                RequestPayload::S3Upload(tar_extract_directory) => {
+                    Some(tar_extract_directory.clone())
+                }
+                _ => None,
+            })
+            .unwrap();
+        let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+        let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+        assert_eq!(
+            bundle_meta.base_props.envs.get(GITHUB_EXTERNAL_ID_ENV),
+            use_actions_api.then(|| String::from("102")).as_ref()
+        );
+    }
+}
+
+fn junit_xml_with_test_case_ids<T: AsRef<str>>(ids: &[T]) -> String {
+    let test_cases = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            format!(
+                r#"        <testcase name="test_{i}" classname="suite" file="src/test.rs" id="{}" />"#,
+                id.as_ref()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="suite">
+    <testsuite name="unit" tests="{}" failures="0">
+{test_cases}
+    </testsuite>
+</testsuites>
+"#,
+        ids.len()
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_warns_about_test_cases_sharing_an_id() {
+    const SHARED_ID: &str = "7a1f2b3c-0000-4000-8000-000000000000";
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(&junit_xml_with_test_case_ids(&[SHARED_ID; 3]), &temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--duplicate-id-threshold=2")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "3 test cases share the id {SHARED_ID}, e.g. suite.test_0, suite.test_1, suite.test_2"
+        )));
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.duplicate_test_case_ids.len(), 1);
+    assert_eq!(bundle_meta.duplicate_test_case_ids[0].id, SHARED_ID);
+    assert_eq!(bundle_meta.duplicate_test_case_ids[0].count, 3);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_fails_on_test_cases_sharing_an_id() {
+    const SHARED_ID: &str = "7a1f2b3c-0000-4000-8000-000000000000";
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(&junit_xml_with_test_case_ids(&[SHARED_ID; 3]), &temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .args(["--duplicate-id-threshold=2", "--fail-on-duplicate-ids"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "Test cases share ids: {SHARED_ID} (3 test cases)"
+        )));
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert!(!requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_unique_test_case_ids() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let ids = (0..3)
+        .map(|i| format!("7a1f2b3c-0000-4000-8000-00000000000{i}"))
+        .collect::<Vec<_>>();
+    write_junit_xml_to_dir(&junit_xml_with_test_case_ids(&ids), &temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .args(["--duplicate-id-threshold=2", "--fail-on-duplicate-ids"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("share the id").not());
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert!(bundle_meta.duplicate_test_case_ids.is_empty());
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_on_github_enterprise_server() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    remove_origin_remote(&temp_dir).unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .envs([
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_SERVER_URL", "https://github.mycorp.com"),
+            ("GITHUB_API_URL", "https://github.mycorp.com/api/v3"),
+            ("GITHUB_REPOSITORY", "trunk-io/analytics-cli"),
+            ("GITHUB_RUN_ID", "42"),
+            ("GITHUB_REF", "refs/heads/trunk/test"),
+        ])
+        .assert()
+        .success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let base_props = bundle_meta.base_props;
+
+    assert_eq!(
+        base_props.repo.repo,
+        Repo {
+            host: String::from("github.mycorp.com"),
+            owner: String::from("trunk-io"),
+            name: String::from("analytics-cli"),
+        }
+    );
+    assert_eq!(
+        base_props.repo.repo_url,
+        "https://github.mycorp.com/trunk-io/analytics-cli"
+    );
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&base_props.envs);
+    let ci_info = env_parser.into_ci_info_parser().unwrap().info_ci_info();
+    assert_eq!(ci_info.platform, CIPlatform::GitHubActions);
+    assert_eq!(
+        ci_info.job_url,
+        Some(String::from(
+            "https://github.mycorp.com/trunk-io/analytics-cli/actions/runs/42"
+        ))
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_repo_less() {
+    // no git repo at all
+    let temp_dir = tempdir().unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args([
+            "--repo-less",
+            "--repo-url",
+            "https://github.com/trunk-io/analytics-cli.git",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "--repo-less: uploading to github.com/trunk-io/analytics-cli without git metadata",
+        ));
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.repo_metadata_source.as_deref(), Some("none"));
+    assert_eq!(
+        bundle_meta.invocation.flags.get("repo_less"),
+        Some(&String::from("true"))
+    );
+    let repo = bundle_meta.base_props.repo;
+    assert_eq!(
+        repo.repo,
+        Repo {
+            host: String::from("github.com"),
+            owner: String::from("trunk-io"),
+            name: String::from("analytics-cli"),
+        }
+    );
+    assert_eq!(repo.repo_head_sha, "");
+    assert_eq!(repo.repo_head_branch, "");
+    assert_eq!(bundle_meta.working_tree.dirty, None);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_records_dirty_working_tree() {
+    let temp_dir = tempdir().unwrap();
+    setup_repo_with_dirty_working_tree(&temp_dir).unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "repo working tree has 2 modified file(s)",
+        ));
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.working_tree.dirty, Some(true));
+    assert_eq!(bundle_meta.working_tree.num_modified_files, Some(2));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_repo_less_conflicts_with_repo_head_sha() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .args([
+            "--repo-less",
+            "--repo-url",
+            "https://github.com/trunk-io/analytics-cli.git",
+            "--repo-head-sha",
+            "1234567890123456789012345678901234567890",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--repo-less' cannot be used with '--repo-head-sha <REPO_HEAD_SHA>'",
+        ));
+    assert!(state.requests.lock().unwrap().is_empty());
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_env_var_in_junit_paths() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let junit_dir = temp_dir.path().join("reports").join("linux");
+    fs::create_dir_all(&junit_dir).unwrap();
+    generate_mock_valid_junit_xmls(&junit_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("./reports/${MATRIX_NAME}/*,./other/${OTHER_MATRIX_NAME:-linux}/*")
+        .use_quarantining(false)
+        .command()
+        .env("MATRIX_NAME", "linux")
+        .env_remove("OTHER_MATRIX_NAME")
+        .assert()
+        .success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    let file_sets = bundle_meta.base_props.file_sets;
+    assert_eq!(file_sets.len(), 2);
+    assert_eq!(file_sets[0].glob, "./reports/linux/*");
+    assert!(!file_sets[0].files.is_empty());
+    assert_eq!(file_sets[1].glob, "./other/linux/*");
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_sanitizes_junit_files() {
+    let system_out = "x".repeat(8_010);
+    let junit_xml = format!("<testsuites>\n  <testsuite name=\"suite\">\n    <testcase name=\"\x1b[31mtest\x1b[0m\" classname=\"tests\" time=\"0.5\">\n      <failure message=\"boom\">\x00\x1b[1mboom</failure>\n      <system-out>{system_out}</system-out>\n    </testcase>\n  </testsuite>\n</testsuites>\n");
+    let sanitized_junit_xml = junit_xml
+        .replace("\x1b[31m", "")
+        .replace("\x1b[0m", "")
+        .replace(['\0', '\x1b'], "");
+    for flag in [
+        None,
+        Some("--no-sanitize-junit"),
+        Some("--no-truncate-junit-text"),
+    ] {
+        let temp_dir = tempdir().unwrap();
+        generate_mock_git_repo(&temp_dir);
+        fs::write(temp_dir.path().join("junit.xml"), &junit_xml).unwrap();
+
+        let state = MockServerBuilder::new().spawn_mock_server().await;
+
+        let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+            .junit_paths("./junit.xml")
+            .use_quarantining(false)
+            .command();
+        command.args(flag);
+        command.assert().success();
+
+        let requests = state.requests.lock().unwrap().clone();
+        let tar_extract_directory = requests
+            .iter()
+            .find_map(|request| match request {
+                RequestPayload::S3Upload(d) => Some(d),
+                _ => None,
+            })
+            .unwrap();
+        let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+        let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+        let bundled_file = &bundle_meta.base_props.file_sets[0].files[0];
+        let bundled_junit_xml =
+            fs::read_to_string(tar_extract_directory.join(&bundled_file.path)).unwrap();
+
+        match flag {
+            None => {
+                assert_eq!(bundled_file.junit_sanitized, Some(true));
+                assert_eq!(bundled_file.junit_sanitized_size_delta, Some(-21));
+                assert_eq!(
+                    bundled_junit_xml,
+                    sanitized_junit_xml.replace(&system_out, &"x".repeat(8_000))
+                );
+            }
+            Some("--no-sanitize-junit") => {
+                assert_eq!(bundled_file.junit_sanitized, None);
+                assert_eq!(bundled_file.junit_sanitized_size_delta, None);
+                assert_eq!(bundled_junit_xml, junit_xml);
+            }
+            _ => {
+                assert_eq!(bundled_file.junit_sanitized, Some(true));
+                assert_eq!(bundled_file.junit_sanitized_size_delta, Some(-11));
+                assert_eq!(bundled_junit_xml, sanitized_junit_xml);
+            }
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_deduplicates_identical_junit_files() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    let junit_paths = generate_mock_valid_junit_xmls(&temp_dir);
+    assert_eq!(junit_paths.len(), 1);
+    for copy in ["a/one.xml", "a/two.xml", "b/three.xml"] {
+        let copy = temp_dir.path().join(copy);
+        fs::create_dir_all(copy.parent().unwrap()).unwrap();
+        fs::copy(&junit_paths[0], copy).unwrap();
+    }
+    fs::remove_file(&junit_paths[0]).unwrap();
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("./a/*.xml,./b/*.xml")
+        .use_quarantining(false)
+        .command()
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Bundling 2 identical junit files once",
+        ));
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.base_props.version, "2");
+
+    let bundled_files = bundle_meta
+        .base_props
+        .file_sets
+        .iter()
+        .flat_map(|file_set| &file_set.files)
+        .collect::<Vec<_>>();
+    assert_eq!(bundled_files.len(), 3);
+    let original = bundled_files
+        .iter()
+        .find(|bundled_file| bundled_file.dedup_of.is_none())
+        .unwrap();
+    assert!(bundled_files
+        .iter()
+        .filter(|bundled_file| bundled_file.dedup_of.is_some())
+        .all(
+            |bundled_file| bundled_file.dedup_of.as_ref() == Some(&original.path)
+                && bundled_file.sha256 == original.sha256
+        ));
+    let stored_junits = fs::read_dir(tar_extract_directory.join("junit"))
+        .unwrap()
+        .count();
+    assert_eq!(stored_junits, 1);
+
+    assert_eq!(bundle_meta.junit_props.num_files, 1);
+    assert_eq!(bundle_meta.junit_props.num_deduplicated_files, 2);
+    assert_eq!(bundle_meta.junit_props.num_tests, 500);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_with_unset_env_var_in_junit_paths() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .junit_paths("./reports/${MATRIX_NAME}/*")
+        .use_quarantining(false)
+        .command()
+        .env_remove("MATRIX_NAME")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Environment variable MATRIX_NAME in --junit-paths value \"./reports/${MATRIX_NAME}/*\" is not set",
+        ));
+
+    assert!(!state
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_compares_cli_version_to_minimum_recommended() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_minimum_recommended_cli_version("0.8.0");
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let results_file = temp_dir.path().join("results.json");
+    let outdated_warning =
+        "is older than 0.8.0, the minimum version recommended by your organization";
+    for (cli_version, cli_outdated) in [("0.7.9", true), ("0.8.0", false), ("0.8.1", false)] {
+        let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+            .use_quarantining(false)
+            .command()
+            .env(DEBUG_CLI_VERSION_ENV, cli_version)
+            .arg("--results-file")
+            .arg(&results_file)
+            .assert()
+            .success();
+        let assert = if cli_outdated {
+            assert.stderr(predicate::str::contains(format!(
+                "trunk-analytics-cli {cli_version} {outdated_warning}"
+            )))
+        } else {
+            assert.stderr(predicate::str::contains(outdated_warning).not())
+        };
+
+        let results: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&results_file).unwrap()).unwrap();
+        assert_eq!(results["cli_outdated"], cli_outdated, "{cli_version}");
+
+        // HINT: View CLI output with `cargo test -- --nocapture`
+        println!("{assert}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_fails_if_outdated() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = MockServerBuilder::new();
+    mock_server_builder.set_minimum_recommended_cli_version("0.8.0");
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .env(DEBUG_CLI_VERSION_ENV, "0.7.9")
+        .arg("--fail-if-outdated")
+        .assert()
+        .code(ErrorCode::CliOutdated.exit_code())
+        .stderr(predicate::str::contains(
+            "trunk-analytics-cli 0.7.9 is older than 0.8.0, the minimum version recommended by your organization",
+        ))
+        .stderr(predicate::str::contains("error_code=cli_outdated"));
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert!(!requests
+        .iter()
+        .any(|request| matches!(request, RequestPayload::S3Upload(_))));
+    assert_matches!(
+        requests.last(),
+        Some(RequestPayload::UpdateBundleUpload(
+            UpdateBundleUploadRequest {
+                upload_status: BundleUploadStatus::UploadFailed,
+                ..
+            }
+        ))
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_without_minimum_recommended_cli_version() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let results_file = temp_dir.path().join("results.json");
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .env(DEBUG_CLI_VERSION_ENV, "0.7.9")
+        .arg("--fail-if-outdated")
+        .arg("--results-file")
+        .arg(&results_file)
+        .assert()
+        .success();
+
+    let results: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&results_file).unwrap()).unwrap();
+    assert!(results["cli_outdated"].is_null());
+    for phase in ["junit_scan_and_parse", "upload_intent", "s3_put", "total"] {
+        assert!(results["phase_durations_ms"][phase].is_u64(), "{phase}");
+    }
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_error_codes_for_rejected_bundle_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let results_file = temp_dir.path().join("results.json");
+    for (status, error_code) in [
+        (StatusCode::UNAUTHORIZED, ErrorCode::InvalidToken),
+        (StatusCode::NOT_FOUND, ErrorCode::OrgNotFound),
+        (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::UploadFailed),
+    ] {
+        let mut mock_server_builder = MockServerBuilder::new();
+        mock_server_builder.set_create_bundle_handler(
+            move |_: Json<CreateBundleUploadRequest>| async move { status },
+        );
+        let state = mock_server_builder.spawn_mock_server().await;
+
+        let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+            .use_quarantining(false)
+            .command()
+            .arg("--results-file")
+            .arg(&results_file)
+            .assert()
+            .code(error_code.exit_code())
+            .stderr(predicate::str::contains(format!("error_code={error_code}")));
+
+        let results: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&results_file).unwrap()).unwrap();
+        assert_eq!(results["error_code"], error_code.as_str(), "{status}");
+
+        // HINT: View CLI output with `cargo test -- --nocapture`
+        println!("{assert}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_writes_error_code_to_results_file_when_no_test_files() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let results_file = temp_dir.path().join("results.json");
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--allow-empty-test-results=false")
+        .arg("--results-file")
+        .arg(&results_file)
+        .assert()
+        .code(ErrorCode::NoTestFiles.exit_code())
+        .stderr(predicate::str::contains("error_code=no_test_files"));
+
+    let results: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&results_file).unwrap()).unwrap();
+    assert_eq!(
+        results,
+        serde_json::json!({
+            "exit_code": ErrorCode::NoTestFiles.exit_code(),
+            "error_code": "no_test_files",
+        })
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_env_capture_profile_ci_only() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args(["--env-capture-profile", "ci-only"])
+        .envs([
+            ("GITHUB_ACTIONS", "true"),
+            // a known CI env var the CI info is not parsed from
+            ("RUNNER_OS", "Linux"),
+            ("UNRELATED_PLANTED_VAR", "planted"),
+        ])
+        .assert()
+        .success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.env_capture_profile.as_deref(), Some("ci-only"));
+    let envs = bundle_meta.base_props.envs;
+    assert_eq!(envs.get("GITHUB_JOB"), Some(&String::from("test-job")));
+    assert_eq!(envs.get("CI"), Some(&String::from("1")));
+    assert_eq!(envs.get("RUNNER_OS"), None);
+    assert_eq!(envs.get("UNRELATED_PLANTED_VAR"), None);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_env_capture_profile_full_scrubs_token() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .args(["--env-capture-profile", "full"])
+        .env("UNRELATED_PLANTED_VAR", "planted test-token")
+        .env("PLANTED_DEPLOY_PASSWORD", "hunter2")
+        .assert()
+        .success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let file = fs::File::open(tar_extract_directory.join("meta.json")).unwrap();
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(bundle_meta.env_capture_profile.as_deref(), Some("full"));
+    assert_eq!(
+        bundle_meta.base_props.envs.get("UNRELATED_PLANTED_VAR"),
+        Some(&String::from("planted ***"))
+    );
+    assert_eq!(
+        bundle_meta.base_props.envs.get("PLANTED_DEPLOY_PASSWORD"),
+        None
+    );
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_marked_failed_when_terminated_during_upload() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let upload_started = Arc::new(tokio::sync::Notify::new());
+    let mut mock_server_builder = MockServerBuilder::new();
+    let upload_started_handler = upload_started.clone();
+    mock_server_builder.set_s3_upload_handler(move |_: Bytes| async move {
+        upload_started_handler.notify_one();
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        StatusCode::OK
+    });
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .std_command();
+    command
+        .arg("--cancel-grace-period=1s")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let (pid_sender, pid_receiver) = tokio::sync::oneshot::channel();
+    let output = tokio::task::spawn_blocking(move || {
+        let child = command.spawn()?;
+        let _ = pid_sender.send(child.id());
+        child.wait_with_output()
+    });
+    let pid = pid_receiver.await.unwrap().to_string();
+    tokio::time::timeout(
+        std::time::Duration::from_secs(60),
+        upload_started.notified(),
+    )
+    .await
+    .unwrap();
+    let killed = std::process::Command::new("kill")
+        .args(["-TERM", &pid])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = output.await.unwrap().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(143), "{stderr}");
+    assert!(stderr.contains("Exiting after SIGTERM"), "{stderr}");
+
+    let requests = state.requests.lock().unwrap().clone();
+    assert_matches!(
+        requests.last(),
+        Some(RequestPayload::UpdateBundleUpload(
+            UpdateBundleUploadRequest {
+                upload_status: BundleUploadStatus::UploadFailed,
+                ..
+            }
+        ))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_bundle_sanitizes_test_names() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    // both failures are quarantined by the test runner, so that the meta lists them
+    let started_at = chrono::Utc::now() - chrono::TimeDelta::minutes(1);
+    let test_case_runs = [
+        String::from("\u{1b}[31mtest_foo\u{1b}[0m\nfails"),
+        "a".repeat(20_000),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, name)| TestCaseRun {
+        id: format!("internal-bin-test-{i}"),
+        name,
+        classname: String::from("foo"),
+        parent_name: String::from("suite"),
+        status: TestCaseRunStatus::Failure.into(),
+        started_at: Some(started_at.into()),
+        finished_at: Some((started_at + chrono::TimeDelta::seconds(1)).into()),
+        is_quarantined: true,
+        ..Default::default()
+    })
+    .collect();
+    let internal_bin = TestResult {
+        test_case_runs,
+        uploader_metadata: None,
+    };
+    fs::write(
+        temp_dir.path().join("internal.bin"),
+        internal_bin.encode_to_vec(),
+    )
+    .unwrap();
+
+    let state = MockServerBuilder::new().spawn_mock_server().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .internal_bin_path("./internal.bin")
+        .command()
+        .assert()
+        .success();
+
+    let requests = state.requests.lock().unwrap().clone();
+    let tar_extract_directory = requests
+        .iter()
+        .find_map(|request| match request {
+            RequestPayload::S3Upload(d) => Some(d),
+            _ => None,
+        })
+        .unwrap();
+    let expected_names = [
+        "a".repeat(DEFAULT_MAX_TEST_NAME_LEN),
+        String::from("test_foo fails"),
+    ];
+
+    let internal_bin = TestResult::decode(
+        fs::read(tar_extract_directory.join("internal.bin"))
+            .unwrap()
+            .as_slice(),
+    )
+    .unwrap();
+    let mut internal_bin_names = internal_bin
+        .test_case_runs
+        .iter()
+        .map(|test_case_run| test_case_run.name.clone())
+        .collect::<Vec<_>>();
+    internal_bin_names.sort();
+    assert_eq!(internal_bin_names, expected_names);
+
+    let bundle_meta: BundleMeta = serde_json::from_reader(BufReader::new(
+        fs::File::open(tar_extract_directory.join("meta.json")).unwrap(),
+    ))
+    .unwrap();
+    let mut quarantined_test_names = bundle_meta
+        .base_props
+        .quarantined_tests
+        .iter()
+        .map(|test| test.name.clone())
+        .collect::<Vec<_>>();
+    quarantined_test_names.sort();
+    assert_eq!(quarantined_test_names, expected_names);
+
+    // HINT: View CLI output with `cargo test -- --nocapture`
+    println!("{assert}");
 }
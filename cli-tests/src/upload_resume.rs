@@ -0,0 +1,338 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use api::message::{
+    BundleUploadStatus, CreateBundleUploadRequest, CreateBundleUploadResponse,
+    UpdateBundleUploadRequest,
+};
+use axum::{body::Bytes, extract::State, http::StatusCode, response::IntoResponse, Json};
+use bundle::BundleMeta;
+use predicates::prelude::*;
+use tempfile::tempdir;
+use test_utils::mock_server::{
+    s3_upload_handler, MockServerBuilder, RequestPayload, SharedMockServerState,
+};
+use trunk_analytics_cli::upload_journal::{UploadJournal, UPLOAD_JOURNAL_PATH};
+
+use crate::command_builder::CommandBuilder;
+use crate::utils::{
+    generate_mock_git_repo, generate_mock_valid_junit_xmls, write_junit_xml_to_dir,
+};
+
+const FAILING_JUNIT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="failing">
+    <testsuite name="suite">
+        <testcase classname="a" name="fails" time="1.0">
+            <failure message="boom" />
+        </testcase>
+    </testsuite>
+</testsuites>
+"#;
+
+/// A mock server whose first put to S3 fails
+async fn spawn_mock_server_failing_first_put() -> SharedMockServerState {
+    mock_server_builder_failing_first_put()
+        .spawn_mock_server()
+        .await
+}
+
+fn mock_server_builder_failing_first_put() -> MockServerBuilder {
+    let num_puts = Arc::new(AtomicUsize::new(0));
+    let mut mock_server_builder = MockServerBuilder::new();
+    // Read the body before rejecting it, otherwise the upload may fail with a broken pipe instead
+    mock_server_builder.set_s3_upload_handler(
+        move |state: State<SharedMockServerState>, bytes: Bytes| {
+            let num_puts = num_puts.clone();
+            async move {
+                if num_puts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return StatusCode::FORBIDDEN.into_response();
+                }
+                s3_upload_handler(state, bytes).await.into_response()
+            }
+        },
+    );
+    mock_server_builder
+}
+
+/// Removes the junit files, so that only the journaled bundle can be uploaded
+fn remove_junit_files(dir: &std::path::Path) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "xml") {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
+
+fn count_requests<F: Fn(&RequestPayload) -> bool>(state: &SharedMockServerState, f: F) -> usize {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|request| f(request))
+        .count()
+}
+
+// NOTE: must be multi threaded to start a mock server
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_resume_after_failed_put() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = spawn_mock_server_failing_first_put().await;
+
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--resume")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to upload bundle to S3."));
+    println!("{assert}");
+    assert!(temp_dir.path().join(UPLOAD_JOURNAL_PATH).exists());
+
+    remove_junit_files(temp_dir.path());
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--resume")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Resuming upload test-bundle-upload-id",
+        ));
+    println!("{assert}");
+
+    assert_eq!(
+        count_requests(&state, |request| matches!(
+            request,
+            RequestPayload::CreateBundleUpload(_)
+        )),
+        1
+    );
+    assert_eq!(
+        count_requests(&state, |request| matches!(
+            request,
+            RequestPayload::S3Upload(_)
+        )),
+        1
+    );
+    assert_eq!(
+        state.requests.lock().unwrap().last().unwrap(),
+        &RequestPayload::UpdateBundleUpload(UpdateBundleUploadRequest {
+            id: String::from("test-bundle-upload-id"),
+            upload_status: BundleUploadStatus::UploadComplete,
+        }),
+    );
+    assert!(!temp_dir.path().join(UPLOAD_JOURNAL_PATH).exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_resume_with_expired_url_registers_the_upload_again() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let mut mock_server_builder = mock_server_builder_failing_first_put();
+    let num_uploads = Arc::new(AtomicUsize::new(0));
+    mock_server_builder.set_create_bundle_handler(
+        move |State(state): State<SharedMockServerState>,
+              Json(request): Json<CreateBundleUploadRequest>| {
+            let num_uploads = num_uploads.clone();
+            async move {
+                state
+                    .requests
+                    .lock()
+                    .unwrap()
+                    .push(RequestPayload::CreateBundleUpload(request));
+                let upload_number = num_uploads.fetch_add(1, Ordering::SeqCst) + 1;
+                Json(CreateBundleUploadResponse {
+                    id: format!("upload-{upload_number}"),
+                    id_v2: format!("upload-{upload_number}-v2"),
+                    url: format!("{}/s3upload", state.host),
+                    key: String::from("unused"),
+                    minimum_recommended_cli_version: None,
+                })
+            }
+        },
+    );
+    let state = mock_server_builder.spawn_mock_server().await;
+
+    CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--resume")
+        .assert()
+        .failure();
+
+    let journal_path = temp_dir.path().join(UPLOAD_JOURNAL_PATH);
+    let mut journal: UploadJournal =
+        serde_json::from_slice(&std::fs::read(&journal_path).unwrap()).unwrap();
+    journal.upload_url_expires_at = journal.created_at;
+    std::fs::write(&journal_path, serde_json::to_vec(&journal).unwrap()).unwrap();
+
+    remove_junit_files(temp_dir.path());
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--resume")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Resuming upload"))
+        .stderr(predicate::str::contains("expired"));
+    println!("{assert}");
+
+    assert_eq!(
+        count_requests(&state, |request| matches!(
+            request,
+            RequestPayload::CreateBundleUpload(_)
+        )),
+        2
+    );
+    let requests = state.requests.lock().unwrap().clone();
+    let bundle_metas: Vec<BundleMeta> = requests
+        .iter()
+        .filter_map(|request| match request {
+            RequestPayload::S3Upload(tar_extract_directory) => Some(
+                serde_json::from_slice(
+                    &std::fs::read(tar_extract_directory.join("meta.json")).unwrap(),
+                )
+                .unwrap(),
+            ),
+            _ => None,
+        })
+        .collect();
+    // the journaled bundle is uploaded with the ID of the upload registered again
+    assert_eq!(bundle_metas.len(), 1);
+    assert_eq!(bundle_metas[0].base_props.bundle_upload_id, "upload-2");
+    assert_eq!(bundle_metas[0].bundle_upload_id_v2, "upload-2-v2");
+    let update_requests: Vec<_> = requests
+        .iter()
+        .filter_map(|request| match request {
+            RequestPayload::UpdateBundleUpload(request) => Some(request.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        update_requests[update_requests.len() - 2..],
+        [
+            UpdateBundleUploadRequest {
+                id: String::from("upload-1"),
+                upload_status: BundleUploadStatus::UploadFailed,
+            },
+            UpdateBundleUploadRequest {
+                id: String::from("upload-2"),
+                upload_status: BundleUploadStatus::UploadComplete,
+            },
+        ]
+    );
+    assert!(!journal_path.exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_resume_exits_with_the_journaled_test_failures() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    write_junit_xml_to_dir(FAILING_JUNIT_XML, &temp_dir);
+
+    let state = spawn_mock_server_failing_first_put().await;
+
+    CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--resume")
+        .assert()
+        .failure();
+
+    remove_junit_files(temp_dir.path());
+    let assert = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .command()
+        .arg("--resume")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Resuming upload"));
+    println!("{assert}");
+    assert!(!temp_dir.path().join(UPLOAD_JOURNAL_PATH).exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_resume_ignores_the_journal_of_other_junit_paths() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = spawn_mock_server_failing_first_put().await;
+
+    CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .arg("--resume")
+        .assert()
+        .failure();
+    assert!(temp_dir.path().join(UPLOAD_JOURNAL_PATH).exists());
+
+    let mut command = CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command();
+    command.args(["--resume", "--variant", "linux"]);
+    let assert = command
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Not resuming the upload"))
+        .stderr(predicate::str::contains("Resuming upload").not());
+    println!("{assert}");
+    assert_eq!(
+        count_requests(&state, |request| matches!(
+            request,
+            RequestPayload::CreateBundleUpload(_)
+        )),
+        2
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rejects_resume() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = spawn_mock_server_failing_first_put().await;
+
+    let assert = CommandBuilder::test(
+        temp_dir.path(),
+        state.host.clone(),
+        vec![
+            String::from("--resume"),
+            String::from("bash"),
+            String::from("-c"),
+            String::from("exit 0"),
+        ],
+    )
+    .command()
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains(
+        "--resume is only supported by upload",
+    ));
+    println!("{assert}");
+    assert!(state.requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_without_resume_keeps_no_journal() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_git_repo(&temp_dir);
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let state = spawn_mock_server_failing_first_put().await;
+
+    CommandBuilder::upload(temp_dir.path(), state.host.clone())
+        .use_quarantining(false)
+        .command()
+        .assert()
+        .failure();
+    assert!(!temp_dir.path().join(".trunk").exists());
+}
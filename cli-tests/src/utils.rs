@@ -1,6 +1,6 @@
 use std::{
     env, fs,
-    io::Write,
+    io::{BufReader, Write},
     path::{Path, PathBuf},
 };
 
@@ -11,9 +11,16 @@ use bazel_bep::types::build_event_stream::{
     BuildEvent, BuildEventId, File, TestResult,
 };
 use chrono::{TimeDelta, Utc};
+use context::junit::{
+    parser::{extra_attrs, JunitParser},
+    skip_reason::{extract_skip_reason, infer_skip_kind},
+};
 use escargot::{CargoBuild, CargoRun};
 use junit_mock::JunitMock;
 use lazy_static::lazy_static;
+use prost::Message;
+use proto::test_context::test_run;
+use quick_junit::TestCaseStatus;
 use test_utils::mock_git_repo::setup_repo_with_commit;
 
 lazy_static! {
@@ -78,6 +85,94 @@ pub fn generate_mock_bazel_bep<T: AsRef<Path>>(directory: T) {
     file.write_all(outputs_contents.as_bytes()).unwrap();
 }
 
+/// Converts mock junit files into an internal bin named `internal.bin`, giving every test case run
+/// an id of the form `internal-bin-test-{n}`.
+pub fn generate_mock_internal_bin<T: AsRef<Path>>(directory: T) -> test_run::TestResult {
+    let junit_temp_dir = tempfile::tempdir().unwrap();
+    let mock_junits = generate_mock_valid_junit_xmls(&junit_temp_dir);
+    generate_internal_bin_from_junits(directory, &mock_junits)
+}
+
+/// Like [`generate_mock_internal_bin`], for the given junit files.
+pub fn generate_internal_bin_from_junits<T: AsRef<Path>, U: AsRef<Path>>(
+    directory: T,
+    junits: &[U],
+) -> test_run::TestResult {
+    let started_at = Utc::now() - TimeDelta::minutes(1);
+    let test_case_runs = junits
+        .iter()
+        .flat_map(|junit| {
+            let mut junit_parser = JunitParser::new();
+            junit_parser
+                .parse(BufReader::new(fs::File::open(junit).unwrap()))
+                .unwrap();
+            junit_parser.into_reports()
+        })
+        .flat_map(|report| report.test_suites)
+        .flat_map(|test_suite| {
+            let parent_name = test_suite.name.to_string();
+            let suite_attr = |key: &str| {
+                test_suite
+                    .extra
+                    .get(key)
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            };
+            let hostname = suite_attr(extra_attrs::HOSTNAME);
+            let package = suite_attr(extra_attrs::PACKAGE);
+            test_suite.test_cases.into_iter().map(move |test_case| {
+                (
+                    parent_name.clone(),
+                    hostname.clone(),
+                    package.clone(),
+                    test_case,
+                )
+            })
+        })
+        .enumerate()
+        .map(|(i, (parent_name, hostname, package, test_case))| {
+            let status = match test_case.status {
+                TestCaseStatus::Success { .. } => test_run::TestCaseRunStatus::Success,
+                TestCaseStatus::NonSuccess { .. } => test_run::TestCaseRunStatus::Failure,
+                TestCaseStatus::Skipped { .. } => test_run::TestCaseRunStatus::Skipped,
+            };
+            test_run::TestCaseRun {
+                id: format!("internal-bin-test-{i}"),
+                name: test_case.name.to_string(),
+                classname: test_case
+                    .classname
+                    .map(|classname| classname.to_string())
+                    .unwrap_or_default(),
+                file: test_case
+                    .extra
+                    .get("file")
+                    .map(|file| file.to_string())
+                    .unwrap_or_default(),
+                parent_name,
+                status: status.into(),
+                started_at: Some(started_at.into()),
+                finished_at: Some((started_at + TimeDelta::seconds(1)).into()),
+                hostname,
+                package,
+                skip_reason: extract_skip_reason(&test_case.status),
+                skip_kind: infer_skip_kind(&test_case.status).into(),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let internal_bin = test_run::TestResult {
+        test_case_runs,
+        uploader_metadata: None,
+    };
+    fs::write(
+        directory.as_ref().join("internal.bin"),
+        internal_bin.encode_to_vec(),
+    )
+    .unwrap();
+    internal_bin
+}
+
 pub fn generate_mock_invalid_junit_xmls<T: AsRef<Path>>(directory: T) {
     let mut jm_options = junit_mock::Options::default();
     jm_options.test_suite.test_suite_names = Some(vec!["".to_string()]);
@@ -1,3 +1,11 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{TimeDelta, Utc};
+use filetime::FileTime;
 use predicates::prelude::*;
 use tempfile::tempdir;
 
@@ -44,6 +52,40 @@ fn validate_junit_and_bep() {
     println!("{assert}");
 }
 
+#[test]
+fn validate_junit_max_age() {
+    let temp_dir = tempdir().unwrap();
+    let stale_dir = temp_dir.path().join("stale");
+    let fresh_dir = temp_dir.path().join("fresh");
+    fs::create_dir_all(&stale_dir).unwrap();
+    fs::create_dir_all(&fresh_dir).unwrap();
+    let stale_junits = generate_mock_valid_junit_xmls(&stale_dir);
+    generate_mock_valid_junit_xmls(&fresh_dir);
+    generate_mock_codeowners(&temp_dir);
+    let three_hours_ago =
+        FileTime::from_system_time(SystemTime::now() - Duration::from_secs(3 * 60 * 60));
+    for stale_junit in &stale_junits {
+        filetime::set_file_mtime(stale_junit, three_hours_ago).unwrap();
+    }
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .junit_paths("**/*.xml")
+        .command()
+        .arg("--junit-max-age")
+        .arg("2h")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Validating the following 1 files"))
+        .stdout(predicate::str::contains("fresh/"))
+        .stdout(predicate::str::contains("stale/").not())
+        .stdout(predicate::str::contains(format!(
+            "Skipped {} files older than --junit-max-age",
+            stale_junits.len()
+        )));
+
+    println!("{assert}");
+}
+
 #[test]
 fn validate_no_junits() {
     let temp_dir = tempdir().unwrap();
@@ -169,3 +211,545 @@ fn validate_missing_filepath_suboptimal_junits() {
 
     println!("{assert}");
 }
+
+#[test]
+fn validate_sanitized_test_names() {
+    let temp_dir = tempdir().unwrap();
+    let junit_xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="suite" time="1">
+    <testcase classname="foo" name="&#27;[31mtest_foo&#27;[0m&#10;fails" time="0.1" file="foo_test.rs" />
+    <testcase classname="foo" name="{}" time="0.1" file="foo_test.rs" />
+  </testsuite>
+</testsuites>
+"#,
+        "a".repeat(20_000)
+    );
+    write_junit_xml_to_dir(&junit_xml, &temp_dir);
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "OPTIONAL - test case name or classname had control characters or repeated whitespace removed",
+        ))
+        .stdout(predicate::str::contains(
+            "OPTIONAL - test case name was truncated from 20000 bytes",
+        ));
+
+    println!("{assert}");
+}
+
+#[test]
+fn validate_file_inferred_from_classname() {
+    let temp_dir = tempdir().unwrap();
+    let junit_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="src/Button.test.tsx" time="1">
+    <testcase classname="src/Button.test.tsx" name="Button &gt; renders" time="0.1" />
+  </testsuite>
+</testsuites>
+"#;
+    write_junit_xml_to_dir(junit_xml, &temp_dir);
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("missing file or filepath").not());
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .arg("--no-infer-file-from-classname")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("missing file or filepath"));
+    println!("{assert}");
+}
+
+#[test]
+fn validate_junit_attr_map() {
+    let temp_dir = tempdir().unwrap();
+    let junit_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="geometry_tests" time="1">
+    <testcase classname="geometry_tests.global" name="Vectors can be added" source="tests/vector_test.cpp" lineno="12" time="0.1" />
+  </testsuite>
+</testsuites>
+"#;
+    write_junit_xml_to_dir(junit_xml, &temp_dir);
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("missing file or filepath"));
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .arg("--junit-attr-map")
+        .arg("file=source,line=lineno")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("missing file or filepath").not());
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .arg("--junit-attr-map")
+        .arg("name=source")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            r#"cannot map "name", only one of file, filepath, line, id can be mapped"#,
+        ));
+    println!("{assert}");
+}
+
+#[test]
+fn validate_duration_variance() {
+    let temp_dir = tempdir().unwrap();
+    let timestamp = (Utc::now() - TimeDelta::minutes(1)).format("%Y-%m-%dT%H:%M:%S");
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="suite" time="2400" timestamp="{timestamp}">
+    <testcase classname="foo" name="test_foo" time="0.0" timestamp="{timestamp}" file="foo.rs" />
+  </testsuite>
+</testsuites>
+"#
+        ),
+    )
+    .unwrap();
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "test suite suite has test case durations adding up to 0.0s, but its time is 2400.0s",
+        ))
+        .stdout(predicate::str::contains(
+            "Declared test case time covers 0% of wall clock",
+        ));
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--duration-variance-factor", "0.5"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be a number of at least 1"));
+    println!("{assert}");
+}
+
+#[test]
+fn validate_schema() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        junit_xml("suite", Some("foo.rs")),
+    )
+    .unwrap();
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--schema", "xunit2"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "INVALID - [xunit2] report has 1 test cases without a line attribute",
+        ))
+        .stdout(predicate::str::contains("try --schema").not());
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--schema", "junit"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[junit]").not());
+    println!("{assert}");
+
+    let timestamp = (Utc::now() - TimeDelta::minutes(1)).format("%Y-%m-%dT%H:%M:%S");
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="com.example.payments.PaymentGatewayIT" time="1" timestamp="{timestamp}">
+  <testcase name="chargesCard" classname="com.example.payments.PaymentGatewayIT" time="0.8">
+    <flakyFailure message="expected: APPROVED but was: PENDING" type="org.opentest4j.AssertionFailedError" time="0.2">
+      <stackTrace>org.opentest4j.AssertionFailedError: expected: APPROVED but was: PENDING</stackTrace>
+    </flakyFailure>
+  </testcase>
+</testsuite>
+"#
+        ),
+    )
+    .unwrap();
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--schema", "xunit2"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "Most xunit2 rules fail, but the files mostly conform to surefire; try --schema surefire",
+        ));
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--schema", "surefire"])
+        .assert()
+        .success();
+    println!("{assert}");
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--schema", "xunit1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "[possible values: junit, xunit2, surefire]",
+        ));
+    println!("{assert}");
+}
+
+/// A recent junit, which is suboptimal without `file` and invalid with an empty test suite name.
+fn junit_xml(test_suite_name: &str, file: Option<&str>) -> String {
+    let timestamp = (Utc::now() - TimeDelta::minutes(1)).format("%Y-%m-%dT%H:%M:%S");
+    let file = file
+        .map(|file| format!(r#" file="{file}""#))
+        .unwrap_or_default();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="{test_suite_name}" time="1" timestamp="{timestamp}">
+    <testcase classname="foo" name="test_foo" time="0.1" timestamp="{timestamp}"{file} />
+  </testsuite>
+</testsuites>
+"#
+    )
+}
+
+fn assert_validate_strictness<T: AsRef<Path>>(
+    directory: T,
+    strictness: &str,
+    success: bool,
+    summary: &str,
+) {
+    let assert = CommandBuilder::validate(directory.as_ref())
+        .command()
+        .args(["--strictness", strictness])
+        .assert()
+        .stdout(predicate::str::contains(summary));
+    let assert = if success {
+        assert.success()
+    } else {
+        assert.failure()
+    };
+
+    println!("{assert}");
+}
+
+#[test]
+fn validate_strictness_with_only_warnings() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        junit_xml("suite", None),
+    )
+    .unwrap();
+    generate_mock_codeowners(&temp_dir);
+
+    let summary = "0 files not valid, 1 files with validation warnings, 1 codeowners warnings";
+    assert_validate_strictness(&temp_dir, "invalid", true, summary);
+    assert_validate_strictness(&temp_dir, "suboptimal", false, summary);
+    assert_validate_strictness(&temp_dir, "none", true, summary);
+}
+
+#[test]
+fn validate_strictness_with_only_errors() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        junit_xml("", Some("foo_test.rs")),
+    )
+    .unwrap();
+    generate_mock_codeowners(&temp_dir);
+
+    let summary = "1 files not valid, 0 files with validation warnings, 0 codeowners warnings";
+    assert_validate_strictness(&temp_dir, "invalid", false, summary);
+    assert_validate_strictness(&temp_dir, "suboptimal", false, summary);
+    assert_validate_strictness(&temp_dir, "none", true, summary);
+}
+
+#[test]
+fn validate_strictness_with_warnings_and_errors() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        junit_xml("suite", None),
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("junit-1.xml"),
+        junit_xml("", Some("foo_test.rs")),
+    )
+    .unwrap();
+    generate_mock_codeowners(&temp_dir);
+
+    let summary = "1 files not valid, 1 files with validation warnings, 1 codeowners warnings";
+    assert_validate_strictness(&temp_dir, "invalid", false, summary);
+    assert_validate_strictness(&temp_dir, "suboptimal", false, summary);
+    assert_validate_strictness(&temp_dir, "none", true, summary);
+}
+
+#[test]
+fn validate_strictness_missing_codeowners() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("junit-0.xml"),
+        junit_xml("suite", Some("foo_test.rs")),
+    )
+    .unwrap();
+
+    let summary = "0 files not valid, 0 files with validation warnings, 1 codeowners warnings";
+    assert_validate_strictness(&temp_dir, "invalid", true, summary);
+    assert_validate_strictness(&temp_dir, "suboptimal", false, summary);
+    assert_validate_strictness(&temp_dir, "none", true, summary);
+}
+
+fn assert_validate_previous_exit_code<T: AsRef<Path>>(
+    directory: T,
+    previous_exit_code: i32,
+    expected_exit_code: i32,
+    summary: &str,
+) {
+    let assert = CommandBuilder::validate(directory.as_ref())
+        .command()
+        .args(["--previous-exit-code", &previous_exit_code.to_string()])
+        .assert()
+        .code(expected_exit_code)
+        .stdout(predicate::str::contains(summary))
+        .stdout(predicate::str::contains(format!(
+            "Previous command exited with {}",
+            previous_exit_code
+        )));
+
+    println!("{assert}");
+}
+
+#[test]
+fn validate_previous_exit_code() {
+    let valid_dir = tempdir().unwrap();
+    fs::write(
+        valid_dir.path().join("junit-0.xml"),
+        junit_xml("suite", Some("foo_test.rs")),
+    )
+    .unwrap();
+    generate_mock_codeowners(&valid_dir);
+
+    let invalid_dir = tempdir().unwrap();
+    fs::write(
+        invalid_dir.path().join("junit-0.xml"),
+        junit_xml("", Some("foo_test.rs")),
+    )
+    .unwrap();
+    generate_mock_codeowners(&invalid_dir);
+
+    assert_validate_previous_exit_code(
+        &invalid_dir,
+        0,
+        1,
+        "1 files not valid, 0 files with validation warnings, 0 codeowners warnings; validation exit code 1",
+    );
+    assert_validate_previous_exit_code(
+        &valid_dir,
+        2,
+        2,
+        "0 files not valid, 0 files with validation warnings, 0 codeowners warnings; validation exit code 0",
+    );
+    assert_validate_previous_exit_code(
+        &invalid_dir,
+        137,
+        137,
+        "1 files not valid, 0 files with validation warnings, 0 codeowners warnings; validation exit code 1",
+    );
+}
+
+#[test]
+fn validate_test_cases_sharing_an_id() {
+    let temp_dir = tempdir().unwrap();
+    let test_cases = (0..3)
+        .map(|i| {
+            format!(
+                r#"<testcase name="test_{i}" classname="suite" file="src/test.rs" id="shared" />"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+    write_junit_xml_to_dir(
+        &format!(
+            r#"<testsuites><testsuite name="unit" tests="3">{test_cases}</testsuite></testsuites>"#
+        ),
+        &temp_dir,
+    );
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .arg("--duplicate-id-threshold=2")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "INVALID - report has 3 test cases sharing the id shared, which makes them a single test",
+        ));
+
+    println!("{assert}");
+}
+
+#[test]
+fn validate_timings_json() {
+    let temp_dir = tempdir().unwrap();
+    write_junit_xml_to_dir(
+        r#"<testsuites>
+            <testsuite name="suite" time="13">
+                <testcase name="fast" classname="fast" file="a.rs" time="0.01"/>
+                <testcase name="slow" classname="slow" file="a.rs" time="12"/>
+                <testcase name="untimed" classname="untimed" file="a.rs"/>
+            </testsuite>
+        </testsuites>"#,
+        &temp_dir,
+    );
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .arg("--timings=json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""bucket": "ten_seconds_or_more""#,
+        ))
+        .stdout(predicate::str::contains(r#""name": "slow""#));
+
+    println!("{assert}");
+}
+
+#[test]
+fn validate_timings() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .arg("--timings")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Slowest test cases:"))
+        .stdout(predicate::str::contains("Slowest test suites:"))
+        .stdout(predicate::str::contains("<100ms"));
+
+    println!("{assert}");
+}
+
+fn assert_validate_baseline<T: AsRef<Path>>(directory: T, args: &[&str], success: bool) -> String {
+    let assert = CommandBuilder::validate(directory.as_ref())
+        .command()
+        .args(args)
+        .assert();
+    let assert = if success {
+        assert.success()
+    } else {
+        assert.failure()
+    };
+
+    println!("{assert}");
+    String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+}
+
+#[test]
+fn validate_baseline() {
+    let temp_dir = tempdir().unwrap();
+    let baseline_dir = tempdir().unwrap();
+    let baseline_path = baseline_dir.path().join("baseline.json");
+    let baseline = baseline_path.to_str().unwrap();
+    let junit_path = temp_dir.path().join("junit-0.xml");
+
+    fs::write(&junit_path, junit_xml("suite", Some("foo.rs"))).unwrap();
+    let stdout = assert_validate_baseline(
+        &temp_dir,
+        &["--baseline", baseline, "--write-baseline"],
+        true,
+    );
+    assert!(stdout.contains("Wrote validation baseline of 1 test cases"));
+
+    // unchanged, even with a file that wasn't there before
+    fs::write(
+        temp_dir.path().join("junit-1.xml"),
+        junit_xml("other_suite", Some("bar.rs")),
+    )
+    .unwrap();
+    let stdout = assert_validate_baseline(&temp_dir, &["--baseline", baseline], true);
+    assert!(stdout.contains("(1 test cases, now 2)"));
+    assert!(stdout.contains("No metrics changed"));
+    fs::remove_file(temp_dir.path().join("junit-1.xml")).unwrap();
+
+    // regressed
+    fs::write(&junit_path, junit_xml("suite", None)).unwrap();
+    let stdout = assert_validate_baseline(&temp_dir, &["--baseline", baseline], false);
+    assert!(stdout.contains("file coverage"));
+    assert!(stdout.contains("REGRESSED"));
+    assert!(stdout.contains("2 metrics regressed from the validation baseline"));
+    assert_validate_baseline(
+        &temp_dir,
+        &["--baseline", baseline, "--baseline-regressions", "warn"],
+        true,
+    );
+    // the tolerance is in percentage points of the test cases, for coverage and issues alike
+    let stdout = assert_validate_baseline(
+        &temp_dir,
+        &["--baseline", baseline, "--baseline-tolerance", "100"],
+        true,
+    );
+    assert!(stdout.contains("within tolerance"));
+    assert!(!stdout.contains("REGRESSED"));
+    let stdout = assert_validate_baseline(
+        &temp_dir,
+        &["--baseline", baseline, "--baseline-tolerance", "99.9"],
+        false,
+    );
+    assert!(stdout.contains("2 metrics regressed from the validation baseline"));
+
+    // improved
+    assert_validate_baseline(
+        &temp_dir,
+        &["--baseline", baseline, "--write-baseline"],
+        true,
+    );
+    fs::write(&junit_path, junit_xml("suite", Some("foo.rs"))).unwrap();
+    let stdout = assert_validate_baseline(&temp_dir, &["--baseline", baseline], true);
+    assert!(stdout.contains("improved"));
+    assert!(!stdout.contains("REGRESSED"));
+}
+
+#[test]
+fn validate_baseline_missing() {
+    let temp_dir = tempdir().unwrap();
+    generate_mock_valid_junit_xmls(&temp_dir);
+
+    let assert = CommandBuilder::validate(temp_dir.path())
+        .command()
+        .args(["--baseline", "missing.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("create it with --write-baseline"));
+
+    println!("{assert}");
+}
@@ -0,0 +1,37 @@
+#!/bin/sh
+# Converts test results with a JSON object per line, e.g. `{"name": "adds", "status": "passed"}`,
+# to junit. Exits unsuccessfully on any other line.
+set -eu
+
+if [ "${1:-}" = "--version" ]; then
+  echo "json-converter 1.2.3"
+  exit 0
+fi
+
+input="$1"
+output="$2"
+
+field() {
+  printf '%s\n' "$1" | sed -n "s/.*\"$2\": *\"\([^\"]*\)\".*/\1/p"
+}
+
+{
+  echo '<?xml version="1.0" encoding="UTF-8"?>'
+  echo '<testsuites name="json">'
+  echo '<testsuite name="json">'
+  while IFS= read -r line || [ -n "${line}" ]; do
+    [ -z "${line}" ] && continue
+    name="$(field "${line}" name)"
+    status="$(field "${line}" status)"
+    case "${status}" in
+      passed) echo "<testcase name=\"${name}\" classname=\"json\" time=\"0.1\"/>" ;;
+      failed) echo "<testcase name=\"${name}\" classname=\"json\" time=\"0.1\"><failure message=\"failed\"/></testcase>" ;;
+      *)
+        echo "invalid line: ${line}" >&2
+        exit 1
+        ;;
+    esac
+  done <"${input}"
+  echo '</testsuite>'
+  echo '</testsuites>'
+} >"${output}"
@@ -0,0 +1,42 @@
+use api::{
+    client::ApiClient,
+    error_code::{ErrorCode, WithErrorCode},
+    message::ExchangeOidcTokenRequest,
+};
+use clap::ValueEnum;
+use github_actions::{
+    oidc::{IdTokenRequest, TRUNK_OIDC_AUDIENCE},
+    DEFAULT_API_TIMEOUT,
+};
+
+/// How the CLI authenticates with Trunk
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AuthMode {
+    /// With the organization token of --token
+    #[default]
+    Token,
+    /// With a short-lived token exchanged for the OIDC token of the GitHub Actions job, which
+    /// needs the `id-token: write` permission
+    Oidc,
+}
+
+/// Requests the OIDC token of the GitHub Actions job the CLI runs in, and exchanges it with Trunk
+/// for a short-lived API token of `org_url_slug`.
+pub async fn exchange_github_oidc_token(
+    api_client: &ApiClient,
+    org_url_slug: &str,
+) -> anyhow::Result<String> {
+    let id_token = IdTokenRequest::from_env()
+        .error_code(ErrorCode::InvalidToken)?
+        .fetch_id_token(TRUNK_OIDC_AUDIENCE, DEFAULT_API_TIMEOUT)
+        .await
+        .error_code(ErrorCode::InvalidToken)?;
+    let exchange_oidc_token_response = api_client
+        .exchange_oidc_token(&ExchangeOidcTokenRequest {
+            oidc_token: id_token,
+            org_url_slug: String::from(org_url_slug),
+        })
+        .await?;
+    log::info!("Authenticated with the OIDC token of the GitHub Actions job");
+    Ok(exchange_oidc_token_response.api_token)
+}
@@ -0,0 +1,112 @@
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::Path,
+};
+
+use clap::{Args, ValueEnum};
+use constants::EXIT_SUCCESS;
+use context::junit::internal_bin::{bin_to_junit, read_internal_bin};
+
+#[derive(Args, Clone, Debug)]
+pub struct BinToJunitArgs {
+    #[arg(help = "Path to the internal bin, a serialized TestResult.")]
+    bin_path: String,
+    #[arg(
+        long,
+        default_value = ".",
+        help = "Directory to write the junit files to. Created if it does not exist."
+    )]
+    output_dir: String,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = BinFormat::Junit,
+        help = "Write junit files, or print the internal bin to stdout as canonical proto JSON or Rust debug output instead."
+    )]
+    format: BinFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BinFormat {
+    /// Junit XML files in the output directory
+    #[default]
+    Junit,
+    /// Canonical proto3 JSON, stable for scripts to parse
+    Json,
+    /// Rust debug output, not stable
+    Debug,
+}
+
+impl Display for BinFormat {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            BinFormat::Junit => write!(f, "junit"),
+            BinFormat::Json => write!(f, "json"),
+            BinFormat::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+pub async fn run_bin_to_junit(
+    BinToJunitArgs {
+        bin_path,
+        output_dir,
+        format,
+    }: BinToJunitArgs,
+) -> anyhow::Result<i32> {
+    let test_result = read_internal_bin(&bin_path)?;
+    match format {
+        BinFormat::Junit => {}
+        BinFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&test_result.to_canonical_json())?
+            );
+            return Ok(EXIT_SUCCESS);
+        }
+        BinFormat::Debug => {
+            println!("{:#?}", test_result);
+            return Ok(EXIT_SUCCESS);
+        }
+    }
+
+    let reports = bin_to_junit(&test_result);
+    if reports.is_empty() {
+        println!("No test case runs in {}, no junit files written", bin_path);
+        return Ok(EXIT_SUCCESS);
+    }
+
+    fs::create_dir_all(&output_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create output directory {}: {}", output_dir, e))?;
+    let file_stem = Path::new(&bin_path)
+        .file_stem()
+        .map(|file_stem| file_stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("junit"));
+    let num_reports = reports.len();
+    for (i, report) in reports.into_iter().enumerate() {
+        let file_name = if num_reports == 1 {
+            format!("{}.xml", file_stem)
+        } else {
+            format!("{}-{}.xml", file_stem, i)
+        };
+        let junit_path = Path::new(&output_dir).join(file_name);
+        let junit_file = fs::File::create(&junit_path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create junit file {}: {}",
+                junit_path.display(),
+                e
+            )
+        })?;
+        report.serialize(junit_file).map_err(|e| {
+            anyhow::anyhow!("Failed to write junit file {}: {}", junit_path.display(), e)
+        })?;
+        println!(
+            "Wrote {} test case(s) to {}",
+            report.tests,
+            junit_path.display()
+        );
+    }
+
+    Ok(EXIT_SUCCESS)
+}
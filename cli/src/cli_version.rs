@@ -0,0 +1,114 @@
+//! Compares the version of the CLI to the minimum version recommended by the org.
+//!
+//! Trunk returns the minimum when a bundle upload is created. Older CLIs leave out meta that newer
+//! backend features rely on, so those features silently degrade for them.
+
+use std::fmt;
+
+use api::error_code::{ErrorCode, WithErrorCode};
+use colored::Colorize;
+use semver::Version;
+
+/// Overrides the version of the CLI that is compared, since every build from source is 0.0.0.
+pub const DEBUG_CLI_VERSION_ENV: &str = "DEBUG_CLI_VERSION";
+
+const RELEASES_URL: &str = "https://github.com/trunk-io/analytics-cli/releases";
+
+pub fn cli_version() -> String {
+    std::env::var(DEBUG_CLI_VERSION_ENV)
+        .ok()
+        .filter(|cli_version| !cli_version.is_empty())
+        .unwrap_or_else(|| String::from(env!("CARGO_PKG_VERSION")))
+}
+
+/// Returned with `--fail-if-outdated` when the CLI is older than the minimum recommended version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliOutdated {
+    pub cli_version: String,
+    pub minimum_recommended_cli_version: String,
+}
+
+impl fmt::Display for CliOutdated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trunk-analytics-cli {} is older than {}, the minimum version recommended by your organization",
+            self.cli_version, self.minimum_recommended_cli_version
+        )
+    }
+}
+
+impl std::error::Error for CliOutdated {}
+
+/// Returns whether `cli_version` is older than `minimum_recommended_cli_version`, or `None` if
+/// they can't be compared, e.g. for a development build.
+pub fn is_outdated(cli_version: &str, minimum_recommended_cli_version: &str) -> Option<bool> {
+    let cli_version = Version::parse(cli_version.trim_start_matches('v')).ok()?;
+    if cli_version == Version::new(0, 0, 0) {
+        return None;
+    }
+    let minimum_recommended_cli_version =
+        Version::parse(minimum_recommended_cli_version.trim_start_matches('v')).ok()?;
+    Some(cli_version < minimum_recommended_cli_version)
+}
+
+/// Warns if the CLI is older than `minimum_recommended_cli_version`, and fails instead if
+/// `fail_if_outdated`. Returns whether it is outdated, if that is known.
+pub fn check_cli_version(
+    minimum_recommended_cli_version: Option<&str>,
+    fail_if_outdated: bool,
+) -> anyhow::Result<Option<bool>> {
+    let Some(minimum_recommended_cli_version) = minimum_recommended_cli_version else {
+        return Ok(None);
+    };
+    let cli_version = cli_version();
+    let outdated = is_outdated(&cli_version, minimum_recommended_cli_version);
+    match outdated {
+        None => log::debug!(
+            "Could not compare CLI version {} to the minimum recommended version {}",
+            cli_version,
+            minimum_recommended_cli_version
+        ),
+        Some(false) => (),
+        Some(true) => {
+            let cli_outdated = CliOutdated {
+                cli_version,
+                minimum_recommended_cli_version: String::from(minimum_recommended_cli_version),
+            };
+            if fail_if_outdated {
+                return Err(cli_outdated).error_code(ErrorCode::CliOutdated);
+            }
+            log::warn!(
+                "{}",
+                format!(
+                    "{}. Features that rely on newer bundle meta may not work. Upgrade to the latest release from {}",
+                    cli_outdated, RELEASES_URL
+                )
+                .yellow()
+                .bold()
+            );
+        }
+    }
+    Ok(outdated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_semver() {
+        assert_eq!(is_outdated("0.7.1", "0.8.0"), Some(true));
+        assert_eq!(is_outdated("0.7.1", "0.7.10"), Some(true));
+        assert_eq!(is_outdated("0.7.10", "0.7.9"), Some(false));
+        assert_eq!(is_outdated("0.8.0", "v0.8.0"), Some(false));
+        assert_eq!(is_outdated("1.0.0-beta.1", "1.0.0"), Some(true));
+    }
+
+    #[test]
+    fn does_not_compare_development_builds_or_invalid_versions() {
+        assert_eq!(is_outdated("0.0.0", "0.8.0"), None);
+        assert_eq!(is_outdated("0.7.1", "latest"), None);
+        assert_eq!(is_outdated("0.7.1", "0.8"), None);
+    }
+}
@@ -0,0 +1,26 @@
+use clap::{Args, Command};
+use clap_complete::Shell;
+use constants::EXIT_SUCCESS;
+
+/// Name of the installed binary, which is what shells complete. The `bin_name` of the CLI is what
+/// users type through the `trunk` launcher, and contains a space.
+pub const COMPLETIONS_BIN_NAME: &str = "trunk-analytics-cli";
+
+#[derive(Args, Clone, Debug)]
+pub struct CompletionsArgs {
+    #[arg(value_enum, help = "Shell to generate the completion script for.")]
+    pub shell: Shell,
+}
+
+pub async fn run_completions(
+    CompletionsArgs { shell }: CompletionsArgs,
+    mut command: Command,
+) -> anyhow::Result<i32> {
+    clap_complete::generate(
+        shell,
+        &mut command,
+        COMPLETIONS_BIN_NAME,
+        &mut std::io::stdout(),
+    );
+    Ok(EXIT_SUCCESS)
+}
@@ -0,0 +1,464 @@
+//! Defaults for CLI options from a repo-local config file, `.trunk/flakytests.toml` unless
+//! `--config` says otherwise. For example:
+//!
+//! ```toml
+//! org_url_slug = "my-org"
+//! junit_paths = ["target/**/junit.xml"]
+//! use_quarantining = true
+//! tags = { team = "platform" }
+//! junit_attr_map = { file = "source", line = "lineno" }
+//! ```
+//!
+//! Values from the file become the defaults of the matching args, so flags and env vars always
+//! win over the file. Globs in `junit_paths` may use `${VAR}` like `--junit-paths`, and are
+//! interpolated when they are expanded.
+
+use std::{collections::BTreeMap, ffi::OsString, fmt::Write, path::Path};
+
+use anyhow::Context;
+use clap::{
+    builder::Resettable, parser::ValueSource, ArgMatches, Args, Command, FromArgMatches, Parser,
+};
+
+pub const CONFIG_FILE_PATH: &str = ".trunk/flakytests.toml";
+
+/// Keys of the config file, which are also the ids of the args they provide defaults for.
+const CONFIG_KEYS: &[&str] = &[
+    "org_url_slug",
+    "junit_paths",
+    "tags",
+    "team",
+    "variant",
+    "codeowners_path",
+    "use_quarantining",
+    "allow_empty_test_results",
+    "strictness",
+    "stable_branches",
+    "summary_group_by",
+    "post_pr_comment",
+    "api_timeout_secs",
+    "s3_timeout_secs",
+    "connect_timeout_secs",
+    "include_raw_test_runner_output",
+    "junit_attr_map",
+    "merge_junit_files",
+    "env_capture_profile",
+];
+/// Args that are required unless `junit_paths` is present, which it is once the file has it.
+const JUNIT_PATHS_ALTERNATIVES: &[&str] =
+    &["bazel_bep_path", "xcresult_path", "internal_bin_paths"];
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct ConfigFileArgs {
+    #[arg(
+        long,
+        global = true,
+        help = "Path to the config file providing defaults for options. Defaults to .trunk/flakytests.toml."
+    )]
+    pub config: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Print the configuration with the source of each value, then exit."
+    )]
+    pub show_config: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub path: String,
+    values: BTreeMap<String, Vec<String>>,
+}
+
+impl ConfigFile {
+    /// Loads the config file at `path`, or at [`CONFIG_FILE_PATH`] if there is one.
+    pub fn load(path: Option<&str>) -> anyhow::Result<Option<Self>> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(CONFIG_FILE_PATH).is_file() => CONFIG_FILE_PATH,
+            None => return Ok(None),
+        };
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path))?;
+        Self::parse(path, &contents).map(Some)
+    }
+
+    pub fn parse<T: AsRef<str>>(path: T, contents: &str) -> anyhow::Result<Self> {
+        let path = String::from(path.as_ref());
+        let table = contents
+            .parse::<toml::Table>()
+            .with_context(|| format!("Failed to parse config file {}", path))?;
+
+        let mut values = BTreeMap::new();
+        for (key, value) in table {
+            if !CONFIG_KEYS.contains(&key.as_str()) {
+                match nearest_config_key(&key) {
+                    Some(nearest_key) => log::warn!(
+                        "Ignoring unknown key `{}` in {}, did you mean `{}`?",
+                        key,
+                        path,
+                        nearest_key
+                    ),
+                    None => log::warn!("Ignoring unknown key `{}` in {}", key, path),
+                }
+                continue;
+            }
+            let key_values = config_values(&key, value)
+                .with_context(|| format!("Invalid value for `{}` in {}", key, path))?;
+            values.insert(key, key_values);
+        }
+
+        Ok(Self { path, values })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&[String]> {
+        self.values.get(key).map(Vec::as_slice)
+    }
+
+    /// Makes the values of the file the defaults of every subcommand arg with a matching id. Such
+    /// args are no longer required, since the file already provides them.
+    pub fn apply(&self, command: Command) -> Command {
+        let subcommand_names: Vec<String> = command
+            .get_subcommands()
+            .map(|subcommand| String::from(subcommand.get_name()))
+            .collect();
+        subcommand_names
+            .into_iter()
+            .fold(command, |command, subcommand_name| {
+                command.mut_subcommand(subcommand_name, |subcommand| {
+                    self.apply_to_subcommand(subcommand)
+                })
+            })
+    }
+
+    fn apply_to_subcommand(&self, mut subcommand: Command) -> Command {
+        for (key, values) in &self.values {
+            if !has_arg(&subcommand, key) {
+                continue;
+            }
+            subcommand = subcommand.mut_arg(key, |arg| {
+                arg.default_values(values)
+                    .required(false)
+                    .required_unless_present(Resettable::Reset)
+            });
+            if key == "junit_paths" {
+                for alternative in JUNIT_PATHS_ALTERNATIVES {
+                    if has_arg(&subcommand, alternative) {
+                        subcommand = subcommand.mut_arg(alternative, |arg| {
+                            arg.required_unless_present(Resettable::Reset)
+                        });
+                    }
+                }
+            }
+        }
+        subcommand
+    }
+}
+
+pub enum ParsedArgs<T> {
+    Parsed(Box<T>),
+    /// `--show-config` was given, with the configuration to print
+    ShowConfig(String),
+}
+
+/// Parses `args` into `T`, using the config file for the defaults of its subcommands.
+pub fn parse_args<T, I>(args: I) -> anyhow::Result<ParsedArgs<T>>
+where
+    T: Parser,
+    I: IntoIterator<Item = OsString>,
+{
+    let args: Vec<OsString> = args.into_iter().collect();
+    // errors, e.g. missing required args, are reported once the config file fills in the defaults
+    let config_file_args = ConfigFileArgs::augment_args(T::command())
+        .ignore_errors(true)
+        .try_get_matches_from(&args)
+        .ok()
+        .and_then(|matches| ConfigFileArgs::from_arg_matches(&matches).ok())
+        .unwrap_or_default();
+    let config_file = ConfigFile::load(config_file_args.config.as_deref())?;
+
+    let mut command = ConfigFileArgs::augment_args(T::command());
+    if let Some(config_file) = &config_file {
+        command = config_file.apply(command);
+    }
+
+    if config_file_args.show_config {
+        let matches = command.ignore_errors(true).get_matches_from(&args);
+        return Ok(ParsedArgs::ShowConfig(show_config(
+            config_file.as_ref(),
+            &matches,
+        )));
+    }
+
+    let matches = command.clone().get_matches_from(&args);
+    match T::from_arg_matches(&matches) {
+        Ok(parsed) => Ok(ParsedArgs::Parsed(Box::new(parsed))),
+        Err(e) => e.format(&mut command).exit(),
+    }
+}
+
+/// Lists the effective value of each config key the subcommand has, along with where it came from.
+pub fn show_config(config_file: Option<&ConfigFile>, matches: &ArgMatches) -> String {
+    let mut shown = match config_file {
+        Some(config_file) => format!("Config file: {}\n", config_file.path),
+        None => format!("Config file: none found at {}\n", CONFIG_FILE_PATH),
+    };
+    let Some((subcommand_name, subcommand_matches)) = matches.subcommand() else {
+        return shown;
+    };
+
+    let _ = writeln!(shown, "Configuration of `{}`:", subcommand_name);
+    for key in CONFIG_KEYS {
+        let Ok(raw_values) = subcommand_matches.try_get_raw(key) else {
+            // not an arg of this subcommand
+            continue;
+        };
+        let values: Vec<_> = raw_values
+            .into_iter()
+            .flatten()
+            .map(|raw_value| raw_value.to_string_lossy())
+            .collect();
+        // values may contain commas, e.g. regexes of stable branches, so lists are not joined
+        let value = match values.as_slice() {
+            [] => String::from("\"\""),
+            [value] => format!("{:?}", value),
+            values => format!("{:?}", values),
+        };
+        let source = match subcommand_matches.value_source(key) {
+            Some(ValueSource::CommandLine) => "flag",
+            Some(ValueSource::EnvVariable) => "env",
+            Some(ValueSource::DefaultValue)
+                if config_file.is_some_and(|config_file| config_file.get(key).is_some()) =>
+            {
+                "file"
+            }
+            Some(ValueSource::DefaultValue) => "default",
+            _ => "unset",
+        };
+        let _ = writeln!(shown, "  {} = {} ({})", key, value, source);
+    }
+    shown
+}
+
+fn has_arg(command: &Command, id: &str) -> bool {
+    command.get_arguments().any(|arg| arg.get_id() == id)
+}
+
+/// Strings are passed as is, lists become multiple values and tables become `key=value` pairs,
+/// which is how `tags` are given as flags.
+fn config_values(key: &str, value: toml::Value) -> anyhow::Result<Vec<String>> {
+    match value {
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(|value| config_value(key, value))
+            .collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(name, value)| {
+                config_value(key, value).map(|value| format!("{}={}", name, value))
+            })
+            .collect(),
+        value => config_value(key, value).map(|value| vec![value]),
+    }
+}
+
+fn config_value(key: &str, value: toml::Value) -> anyhow::Result<String> {
+    match value {
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        value => Err(anyhow::anyhow!(
+            "expected a string, integer or boolean for `{}` but got a {}",
+            key,
+            value.type_str()
+        )),
+    }
+}
+
+fn nearest_config_key(key: &str) -> Option<&'static str> {
+    let key = key.replace('-', "_");
+    CONFIG_KEYS
+        .iter()
+        .map(|config_key| (edit_distance(&key, config_key), *config_key))
+        .filter(|(distance, config_key)| *distance <= (config_key.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, config_key)| config_key)
+}
+
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            row.push(substitution.min(previous_row[j + 1] + 1).min(row[j] + 1));
+        }
+        previous_row = row;
+    }
+    previous_row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Subcommand};
+
+    use super::*;
+    use crate::{upload_command::UploadArgs, validate_command::ValidateArgs};
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[command(subcommand)]
+        command: TestCommands,
+    }
+
+    #[derive(Debug, Subcommand)]
+    enum TestCommands {
+        Upload(Box<UploadArgs>),
+        Validate(ValidateArgs),
+    }
+
+    const CONFIG_FILE: &str = r#"
+        org_url_slug = "file-org"
+        junit_paths = ["file/*.xml", "more/*.xml"]
+        use_quarantining = false
+        tags = { team = "platform" }
+        summary_group_by = "file"
+        api_timeout_secs = 5
+        stable_branches = ["main", "re:release/\\d{1,2}\\.\\d+"]
+        variant = "linux"
+    "#;
+
+    fn parse_with_config_file(args: &[&str]) -> Result<ArgMatches, clap::Error> {
+        let config_file = ConfigFile::parse("flakytests.toml", CONFIG_FILE).unwrap();
+        config_file
+            .apply(TestCli::command())
+            .try_get_matches_from(["trunk-analytics-cli"].iter().chain(args))
+    }
+
+    #[test]
+    fn parses_config_values() {
+        let config_file = ConfigFile::parse("flakytests.toml", CONFIG_FILE).unwrap();
+        assert_eq!(
+            config_file.get("org_url_slug"),
+            Some(&[String::from("file-org")][..])
+        );
+        assert_eq!(
+            config_file.get("junit_paths"),
+            Some(&[String::from("file/*.xml"), String::from("more/*.xml")][..])
+        );
+        assert_eq!(
+            config_file.get("use_quarantining"),
+            Some(&[String::from("false")][..])
+        );
+        assert_eq!(
+            config_file.get("tags"),
+            Some(&[String::from("team=platform")][..])
+        );
+        assert_eq!(
+            config_file.get("api_timeout_secs"),
+            Some(&[String::from("5")][..])
+        );
+
+        let with_unknown_key = ConfigFile::parse("flakytests.toml", "org_slug = \"org\"").unwrap();
+        assert_eq!(with_unknown_key.get("org_slug"), None);
+        assert!(ConfigFile::parse("flakytests.toml", "team = [[\"a\"]]").is_err());
+        assert!(ConfigFile::parse("flakytests.toml", "team = ").is_err());
+    }
+
+    #[test]
+    fn suggests_nearest_config_key() {
+        assert_eq!(nearest_config_key("org_slug"), Some("org_url_slug"));
+        assert_eq!(nearest_config_key("junit-paths"), Some("junit_paths"));
+        assert_eq!(
+            nearest_config_key("use_quarantine"),
+            Some("use_quarantining")
+        );
+        assert_eq!(nearest_config_key("stable_branch"), Some("stable_branches"));
+        assert_eq!(nearest_config_key("variants"), Some("variant"));
+        assert_eq!(nearest_config_key("quarantine"), None);
+    }
+
+    #[test]
+    fn config_file_provides_defaults() {
+        let matches = parse_with_config_file(&["upload", "--token", "token"]).unwrap();
+        let TestCommands::Upload(upload_args) =
+            TestCli::from_arg_matches(&matches).unwrap().command
+        else {
+            panic!("expected upload");
+        };
+        assert_eq!(upload_args.org_url_slug, "file-org");
+        assert_eq!(upload_args.junit_paths, ["file/*.xml", "more/*.xml"]);
+        assert!(!upload_args.use_quarantining);
+        assert_eq!(upload_args.tags, ["team=platform"]);
+        assert_eq!(upload_args.api_timeout_secs, Some(5));
+        assert_eq!(
+            upload_args.stable_branches,
+            ["main", r"re:release/\d{1,2}\.\d+"]
+        );
+        assert_eq!(upload_args.variant.as_deref(), Some("linux"));
+        // not in the config file
+        assert!(upload_args.allow_empty_test_results);
+
+        // keys without a matching arg are left alone
+        let matches = parse_with_config_file(&["validate"]).unwrap();
+        assert!(TestCli::from_arg_matches(&matches).is_ok());
+    }
+
+    #[test]
+    fn flags_win_over_config_file() {
+        let matches = parse_with_config_file(&[
+            "upload",
+            "--token",
+            "token",
+            "--org-url-slug",
+            "flag-org",
+            "--junit-paths",
+            "flag/*.xml",
+            "--use-quarantining",
+        ])
+        .unwrap();
+        let TestCommands::Upload(upload_args) =
+            TestCli::from_arg_matches(&matches).unwrap().command
+        else {
+            panic!("expected upload");
+        };
+        assert_eq!(upload_args.org_url_slug, "flag-org");
+        assert_eq!(upload_args.junit_paths, ["flag/*.xml"]);
+        assert!(upload_args.use_quarantining);
+        assert_eq!(upload_args.tags, ["team=platform"]);
+
+        let shown = show_config(
+            Some(&ConfigFile::parse("flakytests.toml", CONFIG_FILE).unwrap()),
+            &matches,
+        );
+        assert!(shown.contains("  org_url_slug = \"flag-org\" (flag)\n"));
+        assert!(shown.contains("  tags = \"team=platform\" (file)\n"));
+        assert!(shown.contains("  allow_empty_test_results = \"true\" (default)\n"));
+        assert!(shown.contains("  team = \"\" (unset)\n"));
+        assert!(shown.contains(
+            "  stable_branches = [\"main\", \"re:release/\\\\d{1,2}\\\\.\\\\d+\"] (file)\n"
+        ));
+        assert!(shown.contains("  junit_paths = \"flag/*.xml\" (flag)\n"));
+    }
+
+    #[test]
+    fn required_args_are_still_required_without_config_file() {
+        assert!(TestCli::command()
+            .try_get_matches_from(["trunk-analytics-cli", "upload", "--token", "token"])
+            .is_err());
+        let config_file = ConfigFile::parse("flakytests.toml", "org_url_slug = \"org\"").unwrap();
+        assert!(config_file
+            .apply(TestCli::command())
+            .try_get_matches_from(["trunk-analytics-cli", "upload", "--token", "token"])
+            .is_err());
+    }
+
+    #[test]
+    fn computes_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("team", "team"), 0);
+        assert_eq!(edit_distance("tema", "team"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
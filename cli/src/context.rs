@@ -1,111 +1,405 @@
 #[cfg(target_os = "macos")]
 use std::io::Write;
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     env,
-    io::BufReader,
+    fs::File,
+    io::Read,
     path::Path,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use api::{client::ApiClient, message::CreateBundleUploadResponse};
+use api::{
+    client::ApiClientTrait,
+    error_code::{ErrorCode, WithErrorCode},
+    message::CreateBundleUploadResponse,
+};
 use bundle::{
-    parse_custom_tags, BundleMeta, BundleMetaBaseProps, BundleMetaDebugProps, BundleMetaJunitProps,
-    FileSet, FileSetBuilder, QuarantineBulkTestStatus, META_VERSION,
+    parse_custom_tags, BundleMeta, BundleMetaBaseProps, BundleMetaDebugProps,
+    BundleMetaDuplicateTestCaseId, BundleMetaExcludedFile, BundleMetaHostInfo,
+    BundleMetaInvocation, BundleMetaJunitProps, BundleMetaQuarantineOutcome, BundleMetaWorkingTree,
+    FileSet, FileSetBuilder, QuarantineBulkTestStatus, QuarantineOutcome, CHECKSUM_ALGORITHM,
+    META_VERSION, REPO_METADATA_SOURCE_NONE,
+};
+use codeowners::associate_codeowners;
+use colored::Colorize;
+use constants::{
+    ENVS_CI_ONLY_ALLOWLIST, ENVS_DENYLIST_SUBSTRINGS, ENVS_TO_GET, GITHUB_EXTERNAL_ID_ENV,
 };
-use constants::ENVS_TO_GET;
 #[cfg(target_os = "macos")]
 use context::repo::RepoUrlParts;
 use context::{
     bazel_bep::parser::{BazelBepParser, BepParseResult},
-    junit::{junit_path::JunitReportFileWithStatus, parser::JunitParser},
-    repo::BundleRepo,
+    env::{
+        parser::{EnvParser, StableBranches},
+        EnvVars,
+    },
+    junit::{
+        internal_bin::{
+            internal_bin_to_report, merge_internal_bins, read_internal_bin,
+            sanitize_test_case_run_names, set_ci_job_links,
+        },
+        junit_path::{JunitPathWithVariant, JunitReportFileWithStatus},
+        parser::{JunitParser, DEFAULT_MAX_TEST_NAME_LEN},
+        validator::{find_duplicate_test_case_ids, DuplicateTestCaseId},
+    },
+    repo::{
+        self,
+        validator::{RepoValidationIssue, RepoValidationIssueSubOptimal},
+        BundleRepo,
+    },
 };
+use proto::test_context::test_run::TestResult;
+use quick_junit::Report;
 use tempfile::TempDir;
 #[cfg(target_os = "macos")]
 use xcresult::XCResult;
+use xcresult::XCResultSummary;
 
 use crate::{
     context_quarantine::{gather_quarantine_context, FailedTestsExtractor, QuarantineContext},
+    converter::{convert_files, Converter, DEFAULT_CONVERTER_TIMEOUT},
+    env_interpolation::{interpolate_env_vars, interpolate_env_vars_in_all},
+    host_info::{gather_host_info, host_info_summary},
     print::print_bep_results,
+    quarantine_audit::QuarantineAudit,
+    summary::TestFailure,
     test_command::TestRunResult,
-    upload_command::UploadArgs,
+    upload::{
+        EnvCaptureProfile, MergeJunitFiles, ProgressSink, RawTestRunnerOutputInclusion,
+        UploadConfig, UploadEvent, RAW_TEST_RUNNER_OUTPUT_AUTO_MAX_BYTES,
+    },
 };
 
 pub struct PreTestContext {
     pub meta: BundleMeta,
     pub junit_path_wrappers: Vec<JunitReportFileWithStatus>,
     pub bep_result: Option<BepParseResult>,
+    pub internal_bin: Option<TestResult>,
+    /// Where the junit synthesized from `internal_bin` was written to
+    pub internal_bin_junit_path: Option<String>,
+    pub xcresult_summary: Option<XCResultSummary>,
     pub junit_path_wrappers_temp_dir: Option<TempDir>,
+    /// Run by [`convert_test_runner_output`] once the tests ran, as they write its input files
+    pub pending_conversion: Option<PendingConversion>,
+}
+
+pub struct PendingConversion {
+    converter: Converter,
+    input_paths: Vec<String>,
+    variant: Option<String>,
+}
+
+const SCRUBBED_TOKEN: &str = "***";
+
+fn scrub_token<T: AsRef<str>>(value: T, token: &str) -> String {
+    if token.is_empty() {
+        return String::from(value.as_ref());
+    }
+    value.as_ref().replace(token, SCRUBBED_TOKEN)
 }
 
 // This function is used to gather debug properties for the bundle meta.
 // It will trigger EXC_BAD_ACCESS on arm64-darwin builds when compiled under cdylib
 pub fn gather_debug_props(token: String) -> BundleMetaDebugProps {
     BundleMetaDebugProps {
-        command_line: env::args()
-            .collect::<Vec<String>>()
-            .join(" ")
-            .replace(&token, "***"),
+        command_line: scrub_token(env::args().collect::<Vec<String>>().join(" "), &token),
+    }
+}
+
+/// Records the subcommand and the flags it was run with. Values that could hold secrets or personal
+/// information, like the token or commit author, are never included, only whether they were set.
+fn gather_invocation(upload_config: &UploadConfig) -> BundleMetaInvocation {
+    let token = upload_config.token.as_str();
+    let flags = [
+        ("print_files", upload_config.print_files),
+        ("no_upload", upload_config.no_upload),
+        ("register_dry_run", upload_config.register_dry_run),
+        ("use_quarantining", upload_config.use_quarantining),
+        ("repo_less", upload_config.repo_less),
+        (
+            "allow_empty_test_results",
+            upload_config.allow_empty_test_results,
+        ),
+        (
+            "no_infer_file_from_classname",
+            upload_config.no_infer_file_from_classname,
+        ),
+    ]
+    .into_iter()
+    .map(|(flag, value)| (String::from(flag), value.to_string()))
+    .collect();
+    let overrides = [
+        ("bazel_bep_path", upload_config.bazel_bep_path.is_some()),
+        ("repo_root", upload_config.repo_root.is_some()),
+        ("repo_url", upload_config.repo_url.is_some()),
+        ("repo_head_sha", upload_config.repo_head_sha.is_some()),
+        ("repo_head_branch", upload_config.repo_head_branch.is_some()),
+        (
+            "repo_head_commit_epoch",
+            upload_config.repo_head_commit_epoch.is_some(),
+        ),
+        (
+            "repo_head_author_name",
+            upload_config.repo_head_author_name.is_some(),
+        ),
+        (
+            "repo_head_author_email",
+            upload_config.repo_head_author_email.is_some(),
+        ),
+        (
+            "repo_head_commit_message",
+            upload_config.repo_head_commit_message.is_some(),
+        ),
+        ("team", upload_config.team.is_some()),
+        ("variant", upload_config.variant.is_some()),
+        (
+            "local_flake_detection",
+            upload_config.local_flake_detection.is_some(),
+        ),
+        ("codeowners_path", upload_config.codeowners_path.is_some()),
+        ("converter", upload_config.converter.is_some()),
+        ("api_timeout_secs", upload_config.api_timeout_secs.is_some()),
+        ("s3_timeout_secs", upload_config.s3_timeout_secs.is_some()),
+        (
+            "connect_timeout_secs",
+            upload_config.connect_timeout_secs.is_some(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, is_set)| *is_set)
+    .map(|(name, _)| String::from(name))
+    .collect();
+
+    BundleMetaInvocation {
+        // there are no global options, so the subcommand is always the first argument
+        subcommand: env::args()
+            .nth(1)
+            .map(|subcommand| scrub_token(subcommand, token))
+            .unwrap_or_default(),
+        flags,
+        overrides,
+        junit_globs: upload_config
+            .junit_paths
+            .iter()
+            .chain(
+                upload_config
+                    .junit_paths_with_variant
+                    .iter()
+                    .map(|junit_path_with_variant| &junit_path_with_variant.junit_path),
+            )
+            .map(|junit_path| scrub_token(junit_path, token))
+            .collect(),
+        num_internal_bin_paths: upload_config.internal_bin_paths.len(),
+        num_tags: upload_config.tags.len(),
+        repo_url_overridden_mismatch: false,
+    }
+}
+
+/// The env vars and host the CLI runs on, which are the same for every bundle of a multi-repo
+/// upload, so they are only gathered once.
+#[derive(Debug, Clone)]
+pub struct EnvScan {
+    pub envs: HashMap<String, String>,
+    pub host_info: BundleMetaHostInfo,
+}
+
+impl EnvScan {
+    pub fn gather(upload_config: &UploadConfig) -> Self {
+        let host_info = gather_host_info();
+        log::info!("Host: {}", host_info_summary(&host_info));
+        Self {
+            envs: gather_envs(
+                upload_config.env_capture_profile,
+                &upload_config.stable_branches,
+                &upload_config.token,
+            ),
+            host_info,
+        }
     }
 }
 
 pub fn gather_pre_test_context(
-    upload_args: UploadArgs,
+    upload_config: UploadConfig,
+    debug_props: BundleMetaDebugProps,
+) -> anyhow::Result<PreTestContext> {
+    let env_scan = EnvScan::gather(&upload_config);
+    gather_pre_test_context_with_env_scan(upload_config, debug_props, env_scan)
+}
+
+/// The repo and head commit the test results of `upload_config` are for
+pub fn gather_repo(upload_config: &UploadConfig) -> anyhow::Result<BundleRepo> {
+    if upload_config.repo_less {
+        return BundleRepo::repo_less(
+            upload_config.repo_root.clone(),
+            upload_config.repo_url.clone().unwrap_or_default(),
+        );
+    }
+    BundleRepo::new(
+        upload_config.repo_root.clone(),
+        upload_config.repo_url.clone(),
+        upload_config.repo_head_sha.clone(),
+        upload_config.repo_head_branch.clone(),
+        upload_config.repo_head_commit_epoch.clone(),
+        upload_config.repo_head_author_name.clone(),
+        upload_config.repo_head_author_email.clone(),
+        upload_config.repo_head_commit_message.clone(),
+        &std::env::vars().collect(),
+    )
+    .error_code(ErrorCode::GitRepoNotFound)
+}
+
+pub fn gather_pre_test_context_with_env_scan(
+    upload_config: UploadConfig,
     debug_props: BundleMetaDebugProps,
+    EnvScan { envs, host_info }: EnvScan,
 ) -> anyhow::Result<PreTestContext> {
-    let UploadArgs {
+    let mut invocation = gather_invocation(&upload_config);
+    let repo = gather_repo(&upload_config)?;
+    let repo_metadata_source = if upload_config.repo_less {
+        warn_repo_less(&repo);
+        Some(REPO_METADATA_SOURCE_NONE.to_string())
+    } else {
+        warn_if_dirty_working_tree(&repo);
+        invocation.repo_url_overridden_mismatch = warn_if_repo_url_mismatch(&repo);
+        None
+    };
+    let UploadConfig {
         junit_paths,
+        junit_paths_with_variant,
         #[cfg(target_os = "macos")]
         xcresult_path,
         bazel_bep_path,
+        internal_bin_paths,
         org_url_slug,
-        repo_root,
-        repo_url,
-        repo_head_sha,
-        repo_head_branch,
-        repo_head_commit_epoch,
         tags,
+        variant,
         #[cfg(target_os = "macos")]
         allow_empty_test_results,
+        include_raw_test_runner_output,
+        env_capture_profile,
+        converter,
+        converter_input_paths,
+        converter_timeout,
         ..
-    } = upload_args;
-
-    let repo = BundleRepo::new(
-        repo_root,
-        repo_url,
-        repo_head_sha,
-        repo_head_branch,
-        repo_head_commit_epoch,
+    } = upload_config;
+    let junit_paths = interpolate_env_vars_in_all(junit_paths, "--junit-paths")?;
+    let converter_input_paths =
+        interpolate_env_vars_in_all(converter_input_paths, "--converter-input-paths")?;
+    let junit_paths_with_variant = junit_paths_with_variant
+        .into_iter()
+        .map(|junit_path_with_variant| {
+            Ok(JunitPathWithVariant {
+                junit_path: interpolate_env_vars(
+                    junit_path_with_variant.junit_path,
+                    "--junit-paths-with-variant",
+                )?,
+                ..junit_path_with_variant
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let bazel_bep_path = bazel_bep_path
+        .map(|bazel_bep_path| interpolate_env_vars(bazel_bep_path, "--bazel-bep-path"))
+        .transpose()?;
+    #[cfg(target_os = "macos")]
+    let xcresult_path = xcresult_path
+        .map(|xcresult_path| interpolate_env_vars(xcresult_path, "--xcresult-path"))
+        .transpose()?;
+
+    let excluded_bep_file = bazel_bep_path
+        .as_ref()
+        .map(|bazel_bep_path| {
+            exclude_raw_test_runner_output(
+                bazel_bep_path,
+                include_raw_test_runner_output,
+                RAW_TEST_RUNNER_OUTPUT_AUTO_MAX_BYTES,
+            )
+        })
+        .transpose()?
+        .flatten();
+
+    let CoalescedJunitPathWrappers {
+        mut junit_path_wrappers,
+        mut bep_result,
+        xcresult_summary,
+        mut junit_path_wrappers_temp_dir,
+    } = coalesce_junit_path_wrappers(
+        junit_paths,
+        junit_paths_with_variant,
+        bazel_bep_path,
+        #[cfg(target_os = "macos")]
+        xcresult_path,
+        #[cfg(target_os = "macos")]
+        &repo.repo,
+        #[cfg(target_os = "macos")]
+        &org_url_slug,
+        #[cfg(target_os = "macos")]
+        allow_empty_test_results,
     )?;
 
-    let (junit_path_wrappers, bep_result, junit_path_wrappers_temp_dir) =
-        coalesce_junit_path_wrappers(
-            junit_paths,
-            bazel_bep_path,
-            #[cfg(target_os = "macos")]
-            xcresult_path,
-            #[cfg(target_os = "macos")]
-            &repo.repo,
-            #[cfg(target_os = "macos")]
-            &org_url_slug,
-            #[cfg(target_os = "macos")]
-            allow_empty_test_results,
-        )?;
-
-    let envs: HashMap<String, String> = ENVS_TO_GET
-        .iter()
-        .filter_map(|&env_var| {
-            env::var(env_var)
-                .map(|env_var_value| (env_var.to_string(), env_var_value))
-                .ok()
+    let excluded_files = excluded_bep_file
+        .map(|excluded_bep_file| {
+            log::info!(
+                "Leaving the BEP file {} ({} bytes) out of the bundle",
+                excluded_bep_file.path,
+                excluded_bep_file.size_bytes
+            );
+            bep_result = None;
+            vec![excluded_bep_file]
         })
-        .collect();
+        .unwrap_or_default();
+
+    let pending_conversion = converter
+        .map(|converter| {
+            anyhow::Ok(PendingConversion {
+                converter: Converter::new(
+                    converter,
+                    converter_timeout.unwrap_or(DEFAULT_CONVERTER_TIMEOUT),
+                )?,
+                input_paths: converter_input_paths,
+                variant: variant.clone(),
+            })
+        })
+        .transpose()?;
+
+    let internal_bin = gather_internal_bin(internal_bin_paths)?;
+    let mut internal_bin_junit_path = None;
+    if let Some(internal_bin) = internal_bin.as_ref() {
+        let temp_dir = match junit_path_wrappers_temp_dir.take() {
+            Some(temp_dir) => temp_dir,
+            None => tempfile::tempdir()?,
+        };
+        let internal_bin_junit = write_internal_bin_junit(&temp_dir, internal_bin)?;
+        internal_bin_junit_path = Some(internal_bin_junit.junit_path.clone());
+        junit_path_wrappers.push(internal_bin_junit);
+        junit_path_wrappers_temp_dir = Some(temp_dir);
+    }
+    let (variant, variants) = apply_variant(&mut junit_path_wrappers, variant);
 
     let meta = BundleMeta {
         junit_props: BundleMetaJunitProps::default(),
         debug_props,
         bundle_upload_id_v2: String::with_capacity(0),
+        invocation,
+        host_info,
+        excluded_files,
+        checksum_algorithm: CHECKSUM_ALGORITHM.to_string(),
+        quarantine_outcome: BundleMetaQuarantineOutcome::default(),
+        duplicate_test_case_ids: Vec::with_capacity(0),
+        skipped_files: Vec::with_capacity(0),
+        variant,
+        variants,
+        repo_metadata_source,
+        locally_quarantined_tests: Vec::with_capacity(0),
+        env_capture_profile: Some(env_capture_profile.as_str().to_string()),
+        test_command_stats: None,
+        quarantined_test_sources: Vec::with_capacity(0),
+        internal_bin_shards: Vec::with_capacity(0),
+        converter: None,
+        working_tree: BundleMetaWorkingTree {
+            dirty: repo.dirty,
+            num_modified_files: repo.num_modified_files,
+        },
         base_props: BundleMetaBaseProps {
             version: META_VERSION.to_string(),
             org: org_url_slug,
@@ -132,28 +426,419 @@ pub fn gather_pre_test_context(
         meta,
         junit_path_wrappers,
         bep_result,
+        internal_bin,
+        internal_bin_junit_path,
+        xcresult_summary,
         junit_path_wrappers_temp_dir,
+        pending_conversion,
     })
 }
 
+/// Converts the converter input files to junit files, which are bundled with the other ones under
+/// the variant the other ones default to.
+pub fn convert_test_runner_output(
+    meta: &mut BundleMeta,
+    junit_path_wrappers: &mut Vec<JunitReportFileWithStatus>,
+    junit_path_wrappers_temp_dir: &mut Option<TempDir>,
+    PendingConversion {
+        converter,
+        input_paths,
+        variant,
+    }: PendingConversion,
+) -> anyhow::Result<()> {
+    let temp_dir = match junit_path_wrappers_temp_dir.take() {
+        Some(temp_dir) => temp_dir,
+        None => tempfile::tempdir()?,
+    };
+    let converted = convert_files(
+        &converter,
+        &input_paths,
+        &meta.base_props.repo.repo_root,
+        temp_dir.path(),
+    );
+    *junit_path_wrappers_temp_dir = Some(temp_dir);
+    let (converted_junit_path_wrappers, converter) = converted?;
+    if !converted_junit_path_wrappers.is_empty() && !meta.variants.is_empty() {
+        if let Some(variant) = variant.as_ref() {
+            if !meta.variants.contains(variant) {
+                meta.variants.push(variant.clone());
+                meta.variants.sort();
+            }
+        }
+    }
+    junit_path_wrappers.extend(converted_junit_path_wrappers.into_iter().map(
+        |junit_path_wrapper| JunitReportFileWithStatus {
+            variant: variant.clone(),
+            ..junit_path_wrapper
+        },
+    ));
+    meta.converter = Some(converter);
+    Ok(())
+}
+
+/// Returns the record of the raw test runner output at `path` to keep in the bundle meta if it is
+/// left out of the bundle, or `None` if it is embedded.
+fn exclude_raw_test_runner_output<T: AsRef<Path>>(
+    path: T,
+    include_raw_test_runner_output: RawTestRunnerOutputInclusion,
+    auto_max_bytes: u64,
+) -> anyhow::Result<Option<BundleMetaExcludedFile>> {
+    let path = path.as_ref();
+    let size_bytes = std::fs::metadata(path)?.len();
+    if include_raw_test_runner_output.includes(size_bytes, auto_max_bytes) {
+        return Ok(None);
+    }
+    Ok(Some(BundleMetaExcludedFile {
+        path: path.to_string_lossy().into_owned(),
+        size_bytes,
+        sha256: sha256_file(path)?,
+    }))
+}
+
+fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = openssl::sha::Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let bytes_read = file.read(&mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buf[..bytes_read]);
+    }
+    Ok(hex::encode(hasher.finish()))
+}
+
+fn gather_internal_bin(internal_bin_paths: Vec<String>) -> anyhow::Result<Option<TestResult>> {
+    if internal_bin_paths.is_empty() {
+        return Ok(None);
+    }
+    let internal_bins = internal_bin_paths
+        .iter()
+        .map(read_internal_bin)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut internal_bin = merge_internal_bins(internal_bins);
+    log::info!(
+        "Read {} test case runs from {} internal bin files",
+        internal_bin.test_case_runs.len(),
+        internal_bin_paths.len()
+    );
+    let num_sanitized = sanitize_test_case_run_names(&mut internal_bin, DEFAULT_MAX_TEST_NAME_LEN);
+    if num_sanitized > 0 {
+        log::info!(
+            "Sanitized or truncated the names of {} test case runs of the internal bin",
+            num_sanitized
+        );
+    }
+    Ok(Some(internal_bin))
+}
+
+/// Gives the junit files without a variant of their own the global `variant`. Returns the variant
+/// of the bundle meta if it is the only one, and the variants of the file sets otherwise.
+fn apply_variant(
+    junit_path_wrappers: &mut [JunitReportFileWithStatus],
+    variant: Option<String>,
+) -> (Option<String>, Vec<String>) {
+    let has_own_variants = junit_path_wrappers
+        .iter()
+        .any(|junit_path_wrapper| junit_path_wrapper.variant.is_some());
+    for junit_path_wrapper in junit_path_wrappers.iter_mut() {
+        if junit_path_wrapper.variant.is_none() {
+            junit_path_wrapper.variant.clone_from(&variant);
+        }
+    }
+    if !has_own_variants {
+        return (variant, Vec::new());
+    }
+    let variants: BTreeSet<String> = junit_path_wrappers
+        .iter()
+        .filter_map(|junit_path_wrapper| junit_path_wrapper.variant.clone())
+        .collect();
+    (None, variants.into_iter().collect())
+}
+
+/// Quarantining and the file sets of the bundle are driven by junit, so a junit file is
+/// synthesized from the internal bin with its test case ids kept as-is.
+fn write_internal_bin_junit(
+    junit_temp_dir: &TempDir,
+    internal_bin: &TestResult,
+) -> anyhow::Result<JunitReportFileWithStatus> {
+    let junit_temp_path = junit_temp_dir.path().join("internal_bin_junit.xml");
+    let junit_temp = std::fs::File::create(&junit_temp_path)?;
+    internal_bin_to_report(internal_bin)
+        .serialize(junit_temp)
+        .map_err(|e| anyhow::anyhow!("Failed to write junit file: {}", e))?;
+    let junit_path = junit_temp_path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Failed to convert junit temp path to string."))?;
+    Ok(JunitReportFileWithStatus::from(String::from(junit_path)))
+}
+
+/// Records on each test case run of the internal bin the bundle path of the junit synthesized from
+/// it, e.g. `junit/3`, unless the internal bin already has one.
+pub fn set_internal_bin_source_file(
+    internal_bin: &mut TestResult,
+    internal_bin_junit_path: &str,
+    file_sets: &[FileSet],
+) {
+    let Some(bundled_file) = file_sets
+        .iter()
+        .flat_map(|file_set| file_set.files.iter())
+        .find(|bundled_file| bundled_file.original_path == internal_bin_junit_path)
+    else {
+        return;
+    };
+    for test_case_run in internal_bin
+        .test_case_runs
+        .iter_mut()
+        .filter(|test_case_run| test_case_run.source_file.is_empty())
+    {
+        test_case_run.source_file.clone_from(&bundled_file.path);
+    }
+}
+
+/// Links each test case run of the internal bin to the CI job of the upload, as parsed from the env
+/// vars of the meta, including the GitHub Actions job id when it was found.
+pub fn set_internal_bin_ci_job_links(
+    internal_bin: &mut TestResult,
+    envs: &EnvVars,
+    stable_branches: &StableBranches,
+) {
+    let mut env_parser = EnvParser::with_stable_branches(stable_branches.clone());
+    env_parser.parse(envs);
+    let Some(ci_info) = env_parser
+        .into_ci_info_parser()
+        .map(|ci_info_parser| ci_info_parser.info_ci_info())
+    else {
+        return;
+    };
+    set_ci_job_links(
+        internal_bin,
+        &ci_info,
+        envs.get(GITHUB_EXTERNAL_ID_ENV).map(String::as_str),
+    );
+}
+
+/// Uploads from a dirty working tree are still allowed, but their results may not match the head
+/// commit they are attributed to.
+fn warn_if_dirty_working_tree(repo: &BundleRepo) {
+    if repo.dirty != Some(true) {
+        return;
+    }
+    for issue in repo::validator::validate(repo).issues() {
+        if let RepoValidationIssue::SubOptimal(
+            issue @ RepoValidationIssueSubOptimal::RepoWorkingTreeDirty(..),
+        ) = issue
+        {
+            log::warn!("{}", issue.to_string().yellow());
+        }
+    }
+}
+
+/// The env vars recorded in the bundle meta, as chosen by `env_capture_profile`
+fn gather_envs(
+    env_capture_profile: EnvCaptureProfile,
+    stable_branches: &StableBranches,
+    token: &str,
+) -> HashMap<String, String> {
+    let env_vars: EnvVars = env::vars().collect();
+    let is_denied = |env_var: &str| {
+        let env_var = env_var.to_uppercase();
+        ENVS_DENYLIST_SUBSTRINGS
+            .iter()
+            .any(|denied| env_var.contains(denied))
+    };
+    let captured: BTreeSet<String> = match env_capture_profile {
+        EnvCaptureProfile::CiOnly => {
+            let mut env_parser = EnvParser::with_stable_branches(stable_branches.clone());
+            env_parser.parse(&env_vars);
+            let mut captured: BTreeSet<String> = ENVS_CI_ONLY_ALLOWLIST
+                .iter()
+                .map(|env_var| env_var.to_string())
+                .collect();
+            if let Some(ci_info_parser) = env_parser.ci_info_parser() {
+                captured.extend(ci_info_parser.provenance().env_vars().cloned());
+            }
+            captured
+                .into_iter()
+                .filter(|env_var| !is_denied(env_var))
+                .collect()
+        }
+        EnvCaptureProfile::Standard => ENVS_TO_GET
+            .iter()
+            .filter(|env_var| !is_denied(env_var))
+            .map(|env_var| env_var.to_string())
+            .collect(),
+        EnvCaptureProfile::Full => env_vars
+            .keys()
+            .filter(|env_var| !is_denied(env_var))
+            .cloned()
+            .collect(),
+    };
+    captured
+        .into_iter()
+        .filter_map(|env_var| {
+            let value = env_vars.get(&env_var)?;
+            let value = match env_capture_profile {
+                EnvCaptureProfile::Full => scrub_token(value, token),
+                EnvCaptureProfile::CiOnly | EnvCaptureProfile::Standard => value.clone(),
+            };
+            Some((env_var, value))
+        })
+        .collect()
+}
+
+/// Repo-less uploads are accepted without a sha or branch, so the results can't be tied to a
+/// commit. Says so instead of silently uploading the empty fields, and tags Sentry events to tell
+/// them apart.
+fn warn_repo_less(repo: &BundleRepo) {
+    sentry::configure_scope(|scope| {
+        scope.set_tag("repo_metadata_source", REPO_METADATA_SOURCE_NONE);
+    });
+    log::warn!(
+        "{}",
+        format!(
+            "--repo-less: uploading to {} without git metadata. Test results are not attributed to a commit or branch.",
+            repo.repo.repo_full_name(),
+        )
+        .yellow()
+    );
+}
+
+/// A `--repo-url` of a fork while the checkout's origin is the upstream repo, or vice versa, sends
+/// the results to another Trunk repo than expected. Returns whether they differ.
+fn warn_if_repo_url_mismatch(repo: &BundleRepo) -> bool {
+    let Some(git_remote) = repo.git_remote_mismatch() else {
+        return false;
+    };
+    log::warn!(
+        "{}",
+        format!(
+            "--repo-url {} ({}) does not match the git remote {} ({}). Test results will be uploaded to {}.",
+            repo.repo_url,
+            repo.repo.repo_full_name(),
+            repo.git_remote_url.as_deref().unwrap_or_default(),
+            git_remote.repo_full_name(),
+            repo.repo.repo_full_name()
+        )
+        .yellow()
+        .bold()
+    );
+    true
+}
+
+/// Owners of each failed test's file, and whether it was quarantined, for the summary of failures.
+pub fn gather_test_failures(
+    meta: &BundleMeta,
+    failed_tests_extractor: &FailedTestsExtractor,
+) -> Vec<TestFailure> {
+    let codeowners = meta
+        .base_props
+        .codeowners
+        .as_ref()
+        .and_then(|codeowners| codeowners.owners.as_ref());
+    let repo_root = Path::new(&meta.base_props.repo.repo_root);
+    failed_tests_extractor
+        .failed_tests()
+        .iter()
+        .map(|test| {
+            let owners = codeowners
+                .zip(test.file.as_ref())
+                .map(|(codeowners, file)| {
+                    let file = Path::new(file);
+                    associate_codeowners(codeowners, file.strip_prefix(repo_root).unwrap_or(file))
+                })
+                .unwrap_or_default();
+            let quarantined = meta
+                .base_props
+                .quarantined_tests
+                .iter()
+                .any(|quarantined_test| quarantined_test.id == test.id);
+            TestFailure::new(
+                test.id.clone(),
+                test.name.clone(),
+                test.parent_name.clone(),
+                test.file.clone(),
+                owners,
+                quarantined,
+            )
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn gather_post_test_context<U: AsRef<Path>>(
     meta: &mut BundleMeta,
     junit_path_wrappers: Vec<JunitReportFileWithStatus>,
     team: &Option<String>,
     codeowners_path: &Option<U>,
     allow_empty_test_results: bool,
+    duplicate_id_threshold: usize,
     test_run_result: &Option<TestRunResult>,
-) -> anyhow::Result<FileSetBuilder> {
+    merge_junit_files: MergeJunitFiles,
+    respect_gitignore: bool,
+    junit_max_age: Option<Duration>,
+    junit_parser: &dyn Fn() -> JunitParser,
+    progress_sink: &dyn ProgressSink,
+) -> anyhow::Result<(FileSetBuilder, Vec<Report>, HashMap<String, usize>)> {
     let mut file_set_builder = FileSetBuilder::build_file_sets(
         &meta.base_props.repo.repo_root,
         &junit_path_wrappers,
         team,
         codeowners_path,
         test_run_result.as_ref().map(|r| r.exec_start),
+        respect_gitignore,
+        junit_max_age,
     )?;
+    // before failing for a lack of files, which the decisions explain
+    progress_sink.on_event(UploadEvent::FilesDecided(
+        file_set_builder.file_decisions().to_vec(),
+    ));
+    if file_set_builder.num_expired_files() > 0 {
+        log::info!(
+            "Skipped {} files older than {}",
+            file_set_builder.num_expired_files(),
+            humantime::format_duration(junit_max_age.unwrap_or_default())
+        );
+    }
+    if file_set_builder.num_gitignored_files() > 0 {
+        log::info!(
+            "Skipped {} files ignored by git",
+            file_set_builder.num_gitignored_files()
+        );
+    }
+    if file_set_builder.num_skipped_non_junit_files() > 0 {
+        log::info!(
+            "Skipped {} files that are not junit",
+            file_set_builder.num_skipped_non_junit_files()
+        );
+    }
 
     if !allow_empty_test_results && file_set_builder.no_files_found() {
-        return Err(anyhow::anyhow!("No JUnit files found to upload."));
+        return Err(anyhow::anyhow!("No JUnit files found to upload."))
+            .error_code(ErrorCode::NoTestFiles);
+    }
+
+    let num_original_files = match merge_junit_files.grouping() {
+        Some(grouping) => {
+            let num_original_files = file_set_builder.merge_junit_files(grouping)?;
+            log::info!(
+                "Merged {} files into {}",
+                num_original_files,
+                file_set_builder.count()
+            );
+            Some(num_original_files)
+        }
+        None => None,
+    };
+
+    file_set_builder.dedup_identical_files();
+    if file_set_builder.num_deduplicated_files() > 0 {
+        log::info!(
+            "Bundling {} identical junit files once, saving {} bytes",
+            file_set_builder.num_deduplicated_files(),
+            file_set_builder.num_deduplicated_bytes()
+        );
     }
 
     log::info!("Total files pack and upload: {}", file_set_builder.count());
@@ -167,32 +852,67 @@ pub fn gather_post_test_context<U: AsRef<Path>>(
         );
     }
 
+    let (reports, num_tests_by_path) = parse_reports(file_set_builder.file_sets(), junit_parser);
+    let duplicate_test_case_ids = find_duplicate_test_case_ids(&reports, duplicate_id_threshold);
+    for duplicate_test_case_id in &duplicate_test_case_ids {
+        log::warn!(
+            "{}",
+            format!(
+                "{} test cases share the id {}, e.g. {}. They will be treated as a single test; check how the id attribute is set.",
+                duplicate_test_case_id.count,
+                duplicate_test_case_id.id,
+                duplicate_test_case_id.example_names.join(", ")
+            )
+            .yellow()
+        );
+    }
+
     meta.junit_props = BundleMetaJunitProps {
-        num_files: file_set_builder.count(),
-        num_tests: parse_num_tests(file_set_builder.file_sets()),
+        num_files: file_set_builder.count() - file_set_builder.num_deduplicated_files(),
+        num_tests: reports.iter().map(|report| report.tests).sum(),
+        num_original_files,
+        num_skipped_non_junit_files: file_set_builder.num_skipped_non_junit_files(),
+        num_deduplicated_files: file_set_builder.num_deduplicated_files(),
     };
+    meta.duplicate_test_case_ids = duplicate_test_case_ids
+        .into_iter()
+        .map(|DuplicateTestCaseId { id, count, .. }| BundleMetaDuplicateTestCaseId { id, count })
+        .collect();
     meta.base_props.file_sets = file_set_builder.file_sets().to_vec();
     meta.base_props.codeowners = file_set_builder.take_codeowners();
     meta.base_props.test_command = test_run_result.as_ref().map(|r| r.command.clone());
+    meta.test_command_stats = test_run_result
+        .as_ref()
+        .map(|r| r.test_command_stats.clone());
+
+    Ok((file_set_builder, reports, num_tests_by_path))
+}
 
-    Ok(file_set_builder)
+/// The junit files to upload, and the test runner outputs they were generated from.
+struct CoalescedJunitPathWrappers {
+    junit_path_wrappers: Vec<JunitReportFileWithStatus>,
+    bep_result: Option<BepParseResult>,
+    xcresult_summary: Option<XCResultSummary>,
+    junit_path_wrappers_temp_dir: Option<TempDir>,
 }
 
 fn coalesce_junit_path_wrappers(
     junit_paths: Vec<String>,
+    junit_paths_with_variant: Vec<JunitPathWithVariant>,
     bazel_bep_path: Option<String>,
     #[cfg(target_os = "macos")] xcresult_path: Option<String>,
     #[cfg(target_os = "macos")] repo: &RepoUrlParts,
     #[cfg(target_os = "macos")] org_url_slug: &str,
     #[cfg(target_os = "macos")] allow_empty_test_results: bool,
-) -> anyhow::Result<(
-    Vec<JunitReportFileWithStatus>,
-    Option<BepParseResult>,
-    Option<TempDir>,
-)> {
+) -> anyhow::Result<CoalescedJunitPathWrappers> {
     let mut junit_path_wrappers = junit_paths
         .into_iter()
         .map(JunitReportFileWithStatus::from)
+        .chain(
+            junit_paths_with_variant
+                .into_iter()
+                .map(JunitReportFileWithStatus::from),
+        )
         .collect();
 
     let mut bep_result: Option<BepParseResult> = None;
@@ -205,11 +925,14 @@ fn coalesce_junit_path_wrappers(
     }
 
     let mut _junit_path_wrappers_temp_dir = None;
+    let mut _xcresult_summary = None;
     #[cfg(target_os = "macos")]
     {
         let temp_dir = tempfile::tempdir()?;
-        let temp_paths = handle_xcresult(&temp_dir, xcresult_path, repo, org_url_slug)?;
+        let (temp_paths, xcresult_summary) =
+            handle_xcresult(&temp_dir, xcresult_path, repo, org_url_slug)?;
         _junit_path_wrappers_temp_dir = Some(temp_dir);
+        _xcresult_summary = xcresult_summary;
         junit_path_wrappers = [junit_path_wrappers.as_slice(), temp_paths.as_slice()].concat();
         if junit_path_wrappers.is_empty() {
             if allow_empty_test_results {
@@ -217,31 +940,30 @@ fn coalesce_junit_path_wrappers(
             } else {
                 return Err(anyhow::anyhow!(
                     "No tests found in the provided XCResult path."
-                ));
+                ))
+                .error_code(ErrorCode::NoTestFiles);
             }
         }
     }
 
-    Ok((
+    Ok(CoalescedJunitPathWrappers {
         junit_path_wrappers,
         bep_result,
-        _junit_path_wrappers_temp_dir,
-    ))
+        xcresult_summary: _xcresult_summary,
+        junit_path_wrappers_temp_dir: _junit_path_wrappers_temp_dir,
+    })
 }
 
 pub async fn gather_exit_code_and_quarantined_tests_context(
     meta: &mut BundleMeta,
     use_quarantining: bool,
-    api_client: &ApiClient,
+    api_client: &dyn ApiClientTrait,
     file_set_builder: &FileSetBuilder,
+    failed_tests_extractor: &FailedTestsExtractor,
     test_run_result: &Option<TestRunResult>,
-) -> i32 {
+    quarantine_audit: bool,
+) -> (i32, QuarantineOutcome, Option<QuarantineAudit>) {
     // Run the quarantine step and update the exit code.
-    let failed_tests_extractor = FailedTestsExtractor::new(
-        &meta.base_props.repo.repo,
-        &meta.base_props.org,
-        file_set_builder.file_sets(),
-    );
     let QuarantineContext {
         exit_code,
         quarantine_status:
@@ -249,16 +971,29 @@ pub async fn gather_exit_code_and_quarantined_tests_context(
                 quarantine_results: quarantined_tests,
                 ..
             },
+        outcome,
+        audit,
+        num_unmatched_quarantined_ids,
+        quarantine_sources,
     } = if !use_quarantining {
+        let outcome = if failed_tests_extractor.failed_tests().is_empty() {
+            QuarantineOutcome::NoFailures
+        } else {
+            QuarantineOutcome::QuarantiningUnavailable
+        };
         // use the exit code of the test run result if exists
         if let Some(test_run_result) = test_run_result {
             QuarantineContext {
                 exit_code: test_run_result.exit_code,
+                outcome,
                 ..Default::default()
             }
         } else {
             // default to success if no test run result (i.e. `upload`)
-            QuarantineContext::default()
+            QuarantineContext {
+                outcome,
+                ..Default::default()
+            }
         }
     } else {
         gather_quarantine_context(
@@ -269,27 +1004,35 @@ pub async fn gather_exit_code_and_quarantined_tests_context(
                 test_identifiers: failed_tests_extractor.failed_tests().to_vec(),
             },
             file_set_builder,
-            Some(failed_tests_extractor),
+            failed_tests_extractor,
             test_run_result.as_ref().map(|t| t.exit_code),
+            quarantine_audit,
         )
         .await
     };
 
     meta.base_props.quarantined_tests = quarantined_tests;
+    meta.quarantined_test_sources = quarantine_sources;
+    meta.quarantine_outcome = outcome.into();
+    meta.quarantine_outcome.num_unmatched_quarantined_ids = num_unmatched_quarantined_ids;
 
-    exit_code
+    (exit_code, outcome, audit)
+}
+
+pub fn bundle_upload_request(meta: &BundleMeta) -> api::message::CreateBundleUploadRequest {
+    api::message::CreateBundleUploadRequest {
+        repo: meta.base_props.repo.repo.clone(),
+        org_url_slug: meta.base_props.org.clone(),
+        client_version: format!("trunk-analytics-cli {}", meta.base_props.cli_version),
+    }
 }
 
 pub async fn gather_upload_id_context(
     meta: &mut BundleMeta,
-    api_client: &ApiClient,
+    api_client: &dyn ApiClientTrait,
 ) -> anyhow::Result<CreateBundleUploadResponse> {
     let upload = api_client
-        .create_bundle_upload(&api::message::CreateBundleUploadRequest {
-            repo: meta.base_props.repo.repo.clone(),
-            org_url_slug: meta.base_props.org.clone(),
-            client_version: format!("trunk-analytics-cli {}", meta.base_props.cli_version),
-        })
+        .create_bundle_upload(&bundle_upload_request(meta))
         .await?;
     meta.base_props.bundle_upload_id.clone_from(&upload.id);
     meta.bundle_upload_id_v2.clone_from(&upload.id_v2);
@@ -302,11 +1045,13 @@ fn handle_xcresult(
     xcresult_path: Option<String>,
     repo: &RepoUrlParts,
     org_url_slug: &str,
-) -> Result<Vec<JunitReportFileWithStatus>, anyhow::Error> {
+) -> Result<(Vec<JunitReportFileWithStatus>, Option<XCResultSummary>), anyhow::Error> {
     let mut temp_paths = Vec::new();
+    let mut xcresult_summary = None;
     if let Some(xcresult_path) = xcresult_path {
-        let xcresult = XCResult::new(xcresult_path, repo, org_url_slug);
-        let junits = xcresult?
+        let xcresult = XCResult::new(xcresult_path, repo, org_url_slug)?;
+        xcresult_summary = Some(xcresult.summary());
+        let junits = xcresult
             .generate_junits()
             .map_err(|e| anyhow::anyhow!("Failed to generate junit files from xcresult: {}", e))?;
         for (i, junit) in junits.iter().enumerate() {
@@ -324,6 +1069,7 @@ fn handle_xcresult(
                 temp_paths.push(JunitReportFileWithStatus {
                     junit_path: junit_temp_path_string.to_string(),
                     status: None,
+                    variant: None,
                 });
             } else {
                 return Err(anyhow::anyhow!(
@@ -332,29 +1078,22 @@ fn handle_xcresult(
             }
         }
     }
-    Ok(temp_paths)
+    Ok((temp_paths, xcresult_summary))
 }
 
-fn parse_num_tests(file_sets: &[FileSet]) -> usize {
-    file_sets
+/// Parses the bundled files, returning their reports and the number of tests of each by its `path`
+fn parse_reports(
+    file_sets: &[FileSet],
+    junit_parser: &dyn Fn() -> JunitParser,
+) -> (Vec<Report>, HashMap<String, usize>) {
+    let mut num_tests_by_path = HashMap::new();
+    let reports = file_sets
         .iter()
         .flat_map(|file_set| &file_set.files)
+        .filter(|bundled_file| bundled_file.dedup_of.is_none())
         .filter_map(|bundled_file| {
-            let path = std::path::Path::new(&bundled_file.original_path);
-            let file = std::fs::File::open(path);
-            if let Err(ref e) = file {
-                log::warn!(
-                    "Could not open file {}: {}",
-                    bundled_file.get_print_path(),
-                    e
-                );
-            }
-            file.ok().map(|f| (f, bundled_file))
-        })
-        .filter_map(|(file, bundled_file)| {
-            let file_buf_reader = BufReader::new(file);
-            let mut junit_parser = JunitParser::new();
-            if let Err(e) = junit_parser.parse(file_buf_reader) {
+            let mut junit_parser = junit_parser();
+            if let Err(e) = junit_parser.parse_file(bundled_file.read_path()) {
                 log::warn!(
                     "Encountered error while parsing file {}: {}",
                     bundled_file.get_print_path(),
@@ -362,8 +1101,90 @@ fn parse_num_tests(file_sets: &[FileSet]) -> usize {
                 );
                 return None;
             }
-            Some(junit_parser)
+            let reports = junit_parser.into_reports();
+            num_tests_by_path.insert(
+                bundled_file.path.clone(),
+                reports.iter().map(|report| report.tests).sum(),
+            );
+            Some(reports)
         })
-        .flat_map(|junit_parser| junit_parser.into_reports())
-        .fold(0, |num_tests, report| num_tests + report.tests)
+        .flatten()
+        .collect();
+    (reports, num_tests_by_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_raw_test_runner_output_over_threshold() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bep_path = temp_dir.path().join("bep.json");
+        std::fs::write(&bep_path, "hello\n").unwrap();
+
+        assert_eq!(
+            exclude_raw_test_runner_output(&bep_path, RawTestRunnerOutputInclusion::Auto, 6)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            exclude_raw_test_runner_output(&bep_path, RawTestRunnerOutputInclusion::Always, 5)
+                .unwrap(),
+            None
+        );
+
+        let excluded_file = BundleMetaExcludedFile {
+            path: bep_path.to_string_lossy().into_owned(),
+            size_bytes: 6,
+            sha256: String::from(
+                "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03",
+            ),
+        };
+        assert_eq!(
+            exclude_raw_test_runner_output(&bep_path, RawTestRunnerOutputInclusion::Auto, 5)
+                .unwrap(),
+            Some(excluded_file.clone())
+        );
+        assert_eq!(
+            exclude_raw_test_runner_output(&bep_path, RawTestRunnerOutputInclusion::Never, 6)
+                .unwrap(),
+            Some(excluded_file)
+        );
+    }
+
+    #[test]
+    fn applies_global_variant_to_junit_paths_without_one() {
+        let junit_path_wrappers = || {
+            vec![
+                JunitReportFileWithStatus::from(String::from("shared/*.xml")),
+                JunitReportFileWithStatus::from(JunitPathWithVariant {
+                    junit_path: String::from("ios/*.xml"),
+                    variant: String::from("ios"),
+                }),
+            ]
+        };
+
+        let mut single_variant = vec![JunitReportFileWithStatus::from(String::from("*.xml"))];
+        assert_eq!(
+            apply_variant(&mut single_variant, Some(String::from("linux"))),
+            (Some(String::from("linux")), Vec::new())
+        );
+        assert_eq!(single_variant[0].variant.as_deref(), Some("linux"));
+
+        let mut own_variants = junit_path_wrappers();
+        assert_eq!(
+            apply_variant(&mut own_variants, Some(String::from("android"))),
+            (None, vec![String::from("android"), String::from("ios")])
+        );
+        assert_eq!(own_variants[0].variant.as_deref(), Some("android"));
+        assert_eq!(own_variants[1].variant.as_deref(), Some("ios"));
+
+        let mut without_global_variant = junit_path_wrappers();
+        assert_eq!(
+            apply_variant(&mut without_global_variant, None),
+            (None, vec![String::from("ios")])
+        );
+        assert_eq!(without_global_variant[0].variant, None);
+    }
 }
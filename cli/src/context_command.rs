@@ -0,0 +1,396 @@
+use std::{fs::File, io::BufReader, time::Duration};
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use constants::{EXIT_FAILURE, EXIT_SUCCESS};
+use context::{
+    info_id::gen_info_id,
+    junit::{
+        diff::{diff_reports, JunitDiff, JunitDiffTestKey},
+        parser::JunitParser,
+        stats::{JunitCounts, JunitStats},
+    },
+    repo::RepoUrlParts,
+};
+use quick_junit::Report;
+
+#[derive(Args, Clone, Debug)]
+pub struct ContextArgs {
+    #[command(subcommand)]
+    pub command: ContextCommands,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum ContextCommands {
+    /// Print the ID Trunk gives a test case, to check other implementations against
+    InfoId(InfoIdArgs),
+    /// Commands for junit files
+    Junit(JunitArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct JunitArgs {
+    #[command(subcommand)]
+    pub command: JunitCommands,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum JunitCommands {
+    /// Compare the test results of two junit files
+    Diff(JunitDiffArgs),
+    /// Print aggregate statistics of junit files, to profile the test output of a repo
+    Stats(JunitStatsArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct JunitDiffArgs {
+    #[arg(help = "Path to the base junit file.")]
+    pub base_path: String,
+    #[arg(help = "Path to the junit file to compare against the base.")]
+    pub head_path: String,
+    #[arg(
+        long,
+        default_value = "1000",
+        help = "Only report duration changes larger than this many milliseconds."
+    )]
+    pub duration_threshold_ms: u64,
+    #[arg(long, help = "Print the diff as JSON.")]
+    pub json: bool,
+    #[arg(long, help = "Exit successfully even when differences are found.")]
+    pub exit_zero: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct JunitStatsArgs {
+    #[arg(required = true, help = "Glob paths to junit files.")]
+    pub globs: Vec<String>,
+    #[arg(
+        long,
+        help = "Print the statistics of each file and their totals as JSON instead of a table."
+    )]
+    pub json: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct InfoIdArgs {
+    #[arg(long, help = "Organization url slug.")]
+    pub org: String,
+    #[arg(
+        long,
+        help = "URL of the repository, e.g. https://github.com/owner/name, or its full name, e.g. github.com/owner/name."
+    )]
+    pub repo: String,
+    #[arg(long, help = "Name of the test case.")]
+    pub name: String,
+    #[arg(
+        long,
+        default_value = "",
+        help = "Name of the test suite of the test case."
+    )]
+    pub parent_name: String,
+    #[arg(long, help = "File of the test case.")]
+    pub file: Option<String>,
+    #[arg(long, help = "Classname of the test case.")]
+    pub classname: Option<String>,
+    #[arg(long, help = "Variant the test case ran in.")]
+    pub variant: Option<String>,
+    #[arg(
+        long,
+        help = "ID provided by the test runner, which is used as is when it is not empty."
+    )]
+    pub info_id: Option<String>,
+}
+
+pub async fn run_context(ContextArgs { command }: ContextArgs) -> anyhow::Result<i32> {
+    match command {
+        ContextCommands::InfoId(info_id_args) => run_info_id(info_id_args),
+        ContextCommands::Junit(JunitArgs {
+            command: JunitCommands::Diff(junit_diff_args),
+        }) => run_junit_diff(junit_diff_args),
+        ContextCommands::Junit(JunitArgs {
+            command: JunitCommands::Stats(junit_stats_args),
+        }) => run_junit_stats(junit_stats_args),
+    }
+}
+
+fn run_info_id(
+    InfoIdArgs {
+        org,
+        repo,
+        name,
+        parent_name,
+        file,
+        classname,
+        variant,
+        info_id,
+    }: InfoIdArgs,
+) -> anyhow::Result<i32> {
+    let repo_full_name = repo_full_name(&repo)?;
+    println!(
+        "{}",
+        gen_info_id(
+            &org,
+            &repo_full_name,
+            file.as_deref(),
+            classname.as_deref(),
+            &parent_name,
+            &name,
+            info_id.as_deref(),
+            variant.as_deref(),
+        )
+    );
+    Ok(EXIT_SUCCESS)
+}
+
+fn run_junit_diff(
+    JunitDiffArgs {
+        base_path,
+        head_path,
+        duration_threshold_ms,
+        json,
+        exit_zero,
+    }: JunitDiffArgs,
+) -> anyhow::Result<i32> {
+    let base_reports = parse_junit_file(&base_path)?;
+    let head_reports = parse_junit_file(&head_path)?;
+    let diff = diff_reports(
+        &base_reports,
+        &head_reports,
+        Duration::from_millis(duration_threshold_ms),
+    );
+
+    warn_duplicates(&base_path, &diff.base_duplicates);
+    warn_duplicates(&head_path, &diff.head_duplicates);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print_junit_diff(&diff);
+    }
+
+    if diff.is_empty() || exit_zero {
+        Ok(EXIT_SUCCESS)
+    } else {
+        Ok(EXIT_FAILURE)
+    }
+}
+
+fn parse_junit_file(path: &str) -> anyhow::Result<Vec<Report>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open junit file {}: {}", path, e))?;
+    let mut junit_parser = JunitParser::new();
+    junit_parser
+        .parse(BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("Failed to parse junit file {}: {}", path, e))?;
+    Ok(junit_parser.into_reports())
+}
+
+fn warn_duplicates(path: &str, duplicates: &[JunitDiffTestKey]) {
+    for test in duplicates {
+        log::warn!(
+            "{}",
+            format!(
+                "{} has more than one test case {}, only the first is compared",
+                path, test
+            )
+            .yellow()
+        );
+    }
+}
+
+fn print_junit_diff(diff: &JunitDiff) {
+    if diff.is_empty() {
+        println!("No differences found");
+        return;
+    }
+
+    if !diff.added.is_empty() {
+        println!("Added tests ({}):", diff.added.len());
+        for test in &diff.added {
+            println!("  {} {}", "+".green(), test);
+        }
+    }
+    if !diff.removed.is_empty() {
+        println!("Removed tests ({}):", diff.removed.len());
+        for test in &diff.removed {
+            println!("  {} {}", "-".red(), test);
+        }
+    }
+    if !diff.status_changes.is_empty() {
+        println!("Status changes ({}):", diff.status_changes.len());
+        for change in &diff.status_changes {
+            println!("  {}: {} -> {}", change.test, change.base, change.head);
+        }
+    }
+    if !diff.duration_changes.is_empty() {
+        println!("Duration changes ({}):", diff.duration_changes.len());
+        for change in &diff.duration_changes {
+            println!(
+                "  {}: {}ms -> {}ms",
+                change.test, change.base_ms, change.head_ms
+            );
+        }
+    }
+    if !diff.attribute_changes.is_empty() {
+        println!("Attribute changes ({}):", diff.attribute_changes.len());
+        for change in &diff.attribute_changes {
+            println!(
+                "  {} [{}]: {} -> {}",
+                change.test,
+                change.attribute,
+                change.base.as_deref().unwrap_or("<none>"),
+                change.head.as_deref().unwrap_or("<none>")
+            );
+        }
+    }
+}
+
+/// Files are parsed one at a time, so that thousands of them can be profiled without holding their
+/// reports in memory.
+fn run_junit_stats(JunitStatsArgs { globs, json }: JunitStatsArgs) -> anyhow::Result<i32> {
+    let mut stats = JunitStats::default();
+    for pattern in &globs {
+        let paths =
+            glob::glob(pattern).map_err(|e| anyhow::anyhow!("Invalid glob {}: {}", pattern, e))?;
+        for path in paths {
+            let path = match path {
+                Ok(path) if path.is_file() => path,
+                Ok(_) => continue,
+                Err(e) => {
+                    log::warn!("Could not read {}: {}", e.path().display(), e.error());
+                    continue;
+                }
+            };
+            let display_path = path.to_string_lossy().into_owned();
+            match File::open(&path) {
+                Ok(file) => stats.add_file(display_path, BufReader::new(file)),
+                Err(e) => stats.add_unparsable_file(display_path, e.to_string()),
+            }
+        }
+    }
+    stats.finish();
+    if stats.files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No junit files found matching {}",
+            globs.join(", ")
+        ));
+    }
+
+    for file_stats in &stats.files {
+        if let Some(error) = &file_stats.error {
+            log::warn!(
+                "{}",
+                format!("Could not parse {}: {}", file_stats.path, error).yellow()
+            );
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print_junit_stats(&stats);
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+fn print_junit_stats(stats: &JunitStats) {
+    let JunitCounts {
+        num_reports,
+        num_test_suites,
+        num_test_cases,
+        num_success,
+        num_failure,
+        num_error,
+        num_skipped,
+        num_parse_issues_suboptimal,
+        num_parse_issues_invalid,
+        ..
+    } = stats.totals;
+    let of_test_cases = |count: usize| {
+        if num_test_cases == 0 {
+            format!("{count}")
+        } else {
+            format!(
+                "{count} ({:.1}%)",
+                count as f64 * 100.0 / num_test_cases as f64
+            )
+        }
+    };
+    let rows = [
+        (
+            "files",
+            format!(
+                "{} ({} unparsable)",
+                stats.files.len(),
+                stats.num_unparsable_files
+            ),
+        ),
+        ("reports", num_reports.to_string()),
+        ("test suites", num_test_suites.to_string()),
+        ("test cases", num_test_cases.to_string()),
+        ("  success", of_test_cases(num_success)),
+        ("  failure", of_test_cases(num_failure)),
+        ("  error", of_test_cases(num_error)),
+        ("  skipped", of_test_cases(num_skipped)),
+        (
+            "durations",
+            stats
+                .durations
+                .map(|durations| {
+                    format!(
+                        "p50 {:.3}s, p90 {:.3}s, p99 {:.3}s",
+                        durations.p50, durations.p90, durations.p99
+                    )
+                })
+                .unwrap_or_else(|| String::from("none")),
+        ),
+        ("with file", format!("{:.1}%", stats.totals.file_coverage())),
+        (
+            "with timestamp",
+            format!("{:.1}%", stats.totals.timestamp_coverage()),
+        ),
+        (
+            "with duration",
+            format!("{:.1}%", stats.totals.time_coverage()),
+        ),
+        (
+            "parse issues",
+            format!(
+                "{} suboptimal, {} invalid",
+                num_parse_issues_suboptimal, num_parse_issues_invalid
+            ),
+        ),
+    ];
+    for (name, value) in rows {
+        println!("{:<16}{}", name, value);
+    }
+}
+
+/// `host/owner/name` of a repo URL, or of a full name, which is missing the scheme of a URL.
+fn repo_full_name(repo: &str) -> anyhow::Result<String> {
+    RepoUrlParts::from_url(repo)
+        .or_else(|_| RepoUrlParts::from_url(&format!("https://{}", repo)))
+        .map(|repo_url_parts| repo_url_parts.repo_full_name())
+        .map_err(|e| anyhow::anyhow!("Failed to parse --repo {}: {}", repo, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repo_urls_and_full_names() {
+        for repo in [
+            "https://github.com/trunk-io/analytics-cli",
+            "https://github.com/trunk-io/analytics-cli.git",
+            "git@github.com:trunk-io/analytics-cli.git",
+            "github.com/trunk-io/analytics-cli",
+        ] {
+            assert_eq!(
+                repo_full_name(repo).unwrap(),
+                "github.com/trunk-io/analytics-cli",
+                "{}",
+                repo
+            );
+        }
+        assert!(repo_full_name("not a repo").is_err());
+    }
+}
@@ -1,18 +1,38 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use api::client::ApiClient;
-use bundle::{FileSet, FileSetBuilder, QuarantineBulkTestStatus, Test};
+use api::client::ApiClientTrait;
+use bundle::{
+    BundleMetaQuarantineSource, BundleMetaQuarantinedTestSource, FileSet, FileSetBuilder,
+    QuarantineBulkTestStatus, QuarantineOutcome, Test,
+};
 use constants::{EXIT_FAILURE, EXIT_SUCCESS};
 use context::{
-    junit::{junit_path::JunitReportStatus, parser::JunitParser},
+    info_id::gen_info_id,
+    junit::{
+        junit_path::JunitReportStatus,
+        parser::{extra_attrs, file_or_inferred, JunitParser},
+    },
     repo::RepoUrlParts,
 };
 use quick_junit::TestCaseStatus;
 
+use crate::quarantine_audit::{
+    count_unmatched_quarantined_ids, QuarantineAudit, QuarantinedFailure,
+};
+
 #[derive(Debug, Default, Clone)]
 pub struct QuarantineContext {
     pub exit_code: i32,
     pub quarantine_status: QuarantineBulkTestStatus,
+    pub outcome: QuarantineOutcome,
+    /// Quarantined test IDs that matched no test of the upload, if the quarantine config was
+    /// fetched to audit it
+    pub audit: Option<QuarantineAudit>,
+    /// Number of quarantined test IDs that matched no test of the upload, if the quarantine config
+    /// was fetched
+    pub num_unmatched_quarantined_ids: Option<usize>,
+    /// Who quarantined each of the quarantined failures
+    pub quarantine_sources: Vec<BundleMetaQuarantinedTestSource>,
 }
 
 fn convert_case_to_test<T: AsRef<str>>(
@@ -21,59 +41,74 @@ fn convert_case_to_test<T: AsRef<str>>(
     parent_name: String,
     case: &quick_junit::TestCase,
     suite: &quick_junit::TestSuite,
+    variant: Option<&str>,
 ) -> Test {
     let name = String::from(case.name.as_str());
     let xml_string_to_string = |s: &quick_junit::XmlString| String::from(s.as_str());
     let class_name = case.classname.as_ref().map(xml_string_to_string);
-    let file = case.extra.get("file").map(xml_string_to_string);
+    let file = case.extra.get(extra_attrs::FILE).map(xml_string_to_string);
     let timestamp_millis = case
         .timestamp
         .or(suite.timestamp)
         .map(|t| t.timestamp_millis());
-    let mut test = Test {
+    let id = gen_info_id(
+        org_slug.as_ref(),
+        &repo.repo_full_name(),
+        file.as_deref(),
+        class_name.as_deref(),
+        &parent_name,
+        &name,
+        case.extra.get("id").map(|id| id.as_str()),
+        variant,
+    );
+    Test {
         name,
         parent_name,
         class_name,
-        file,
-        id: String::with_capacity(0),
+        // the inferred file is only for codeowners and display, it's not part of the ID
+        file: file_or_inferred(case).map(xml_string_to_string),
+        id,
         timestamp_millis,
-    };
-    if let Some(id) = case.extra.get("id").map(xml_string_to_string) {
-        test.id = id;
-    } else {
-        test.set_id(org_slug, repo);
     }
-    test
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct FailedTestsExtractor {
     failed_tests: Vec<Test>,
+    /// Tests that failed and then passed on a later retry, which are not in `failed_tests`
+    recovered_tests: Vec<Test>,
+    /// Every test of the upload, of which quarantined test IDs that match none are audited
+    parsed_tests: Vec<Test>,
+    /// Tests with a test case that the test runner marked as quarantined
+    runner_quarantined_ids: HashSet<String>,
 }
 
 impl FailedTestsExtractor {
-    pub fn new<T: AsRef<str>>(repo: &RepoUrlParts, org_slug: T, file_sets: &[FileSet]) -> Self {
+    /// Each junit file is parsed by a parser made with `junit_parser`, which should parse them like
+    /// those of the bundle meta so that failures get the same test IDs
+    pub fn new<T: AsRef<str>, P: Fn() -> JunitParser>(
+        repo: &RepoUrlParts,
+        org_slug: T,
+        file_sets: &[FileSet],
+        junit_parser: P,
+    ) -> Self {
         let mut failures: HashMap<String, Test> = HashMap::new();
         let mut successes: HashMap<String, i64> = HashMap::new();
+        let mut parsed_tests: BTreeMap<String, Test> = BTreeMap::new();
+        let mut runner_quarantined_ids: HashSet<String> = HashSet::new();
 
         for file_set in file_sets {
-            if let Some(resolved_status) = &file_set.resolved_status {
-                // TODO(TRUNK-13911): We should populate the status for all junits, regardless of the presence of a test runner status.
-                if resolved_status != &JunitReportStatus::Failed {
-                    continue;
-                }
-            }
-            for file in &file_set.files {
-                let file = match std::fs::File::open(&file.original_path) {
-                    Ok(file) => file,
-                    Err(e) => {
-                        log::warn!("Error opening file: {}", e);
-                        continue;
-                    }
-                };
-                let reader = std::io::BufReader::new(file);
-                let mut junitxml = JunitParser::new();
-                match junitxml.parse(reader) {
+            // TODO(TRUNK-13911): We should populate the status for all junits, regardless of the presence of a test runner status.
+            let has_failures = file_set
+                .resolved_status
+                .as_ref()
+                .map_or(true, |resolved_status| {
+                    resolved_status == &JunitReportStatus::Failed
+                });
+            // copies of a file are parsed once
+            for file in file_set.files.iter().filter(|file| file.dedup_of.is_none()) {
+                let mut junitxml = junit_parser();
+                match junitxml.parse_file(file.read_path()) {
                     Ok(junitxml) => junitxml,
                     Err(e) => {
                         log::warn!("Error parsing junitxml: {}", e);
@@ -90,7 +125,21 @@ impl FailedTestsExtractor {
                                 parent_name.clone(),
                                 case,
                                 suite,
+                                file_set.variant.as_deref(),
                             );
+                            parsed_tests
+                                .entry(test.id.clone())
+                                .or_insert_with(|| test.clone());
+                            if case
+                                .extra
+                                .get(extra_attrs::QUARANTINED)
+                                .is_some_and(|quarantined| quarantined.as_str() == "true")
+                            {
+                                runner_quarantined_ids.insert(test.id.clone());
+                            }
+                            if !has_failures {
+                                continue;
+                            }
                             match &case.status {
                                 TestCaseStatus::Skipped { .. } => {
                                     continue;
@@ -123,26 +172,48 @@ impl FailedTestsExtractor {
             }
         }
 
-        let failed_tests = failures
-            .into_iter()
-            .filter_map(|(id, test)| {
-                // Tests with the same id and a later timestamp should override their previous status.
-                if let Some(existing_timestamp) = successes.get(&id) {
-                    if *existing_timestamp > test.timestamp_millis.unwrap_or(0) {
-                        return None;
-                    }
-                }
-                Some(test)
+        // Tests with the same id and a later timestamp should override their previous status.
+        let (recovered_tests, failed_tests) = failures.into_values().partition(|test| {
+            successes.get(&test.id).is_some_and(|success_timestamp| {
+                *success_timestamp > test.timestamp_millis.unwrap_or(0)
             })
-            .collect();
+        });
 
-        Self { failed_tests }
+        Self {
+            failed_tests,
+            recovered_tests,
+            parsed_tests: parsed_tests.into_values().collect(),
+            runner_quarantined_ids,
+        }
+    }
+
+    /// Without runner quarantine marks, only the tests of the quarantine config are quarantined
+    pub fn with_runner_quarantine_marks(self, runner_quarantine_marks: bool) -> Self {
+        if runner_quarantine_marks {
+            return self;
+        }
+        Self {
+            runner_quarantined_ids: HashSet::new(),
+            ..self
+        }
     }
 
     pub fn failed_tests(&self) -> &[Test] {
         &self.failed_tests
     }
 
+    pub fn recovered_tests(&self) -> &[Test] {
+        &self.recovered_tests
+    }
+
+    pub fn parsed_tests(&self) -> &[Test] {
+        &self.parsed_tests
+    }
+
+    pub fn runner_quarantined_ids(&self) -> &HashSet<String> {
+        &self.runner_quarantined_ids
+    }
+
     pub fn exit_code(&self) -> i32 {
         if self.failed_tests.is_empty() {
             EXIT_SUCCESS
@@ -153,20 +224,13 @@ impl FailedTestsExtractor {
 }
 
 pub async fn gather_quarantine_context(
-    api_client: &ApiClient,
+    api_client: &dyn ApiClientTrait,
     request: &api::message::GetQuarantineConfigRequest,
     file_set_builder: &FileSetBuilder,
-    failed_tests_extractor: Option<FailedTestsExtractor>,
+    failed_tests_extractor: &FailedTestsExtractor,
     test_run_exit_code: Option<i32>,
+    quarantine_audit: bool,
 ) -> QuarantineContext {
-    let failed_tests_extractor = failed_tests_extractor.unwrap_or_else(|| {
-        FailedTestsExtractor::new(
-            &request.repo,
-            &request.org_url_slug,
-            file_set_builder.file_sets(),
-        )
-    });
-
     let mut exit_code = test_run_exit_code.unwrap_or(EXIT_SUCCESS);
 
     if file_set_builder.no_files_found() {
@@ -177,12 +241,34 @@ pub async fn gather_quarantine_context(
         };
     }
 
-    let quarantine_config = if !failed_tests_extractor.failed_tests().is_empty() {
+    let mut quarantining_unavailable = false;
+    let mut audit = None;
+    let mut num_unmatched_quarantined_ids = None;
+    // the config is fetched without failures only to audit it
+    let quarantine_config = if !failed_tests_extractor.failed_tests().is_empty() || quarantine_audit
+    {
         log::info!("Checking if failed tests can be quarantined");
         let result = api_client.get_quarantining_config(request).await;
 
-        if let Err(ref err) = result {
-            log::error!("{}", err);
+        match result {
+            Ok(ref quarantine_config) if !quarantine_config.is_disabled => {
+                num_unmatched_quarantined_ids = Some(count_unmatched_quarantined_ids(
+                    &quarantine_config.quarantined_tests,
+                    failed_tests_extractor.parsed_tests(),
+                ));
+                // suggesting the nearest test of each unmatched ID compares it with every test
+                if quarantine_audit {
+                    audit = Some(QuarantineAudit::new(
+                        &quarantine_config.quarantined_tests,
+                        failed_tests_extractor.parsed_tests(),
+                    ));
+                }
+            }
+            Ok(_) => (),
+            Err(ref err) => {
+                log::error!("{}", err);
+                quarantining_unavailable = true;
+            }
         }
 
         result.unwrap_or_default()
@@ -197,41 +283,52 @@ pub async fn gather_quarantine_context(
         return QuarantineContext {
             exit_code,
             quarantine_status: QuarantineBulkTestStatus::default(),
+            outcome: QuarantineOutcome::QuarantiningUnavailable,
+            audit,
+            num_unmatched_quarantined_ids,
+            quarantine_sources: Vec::new(),
         };
     } else {
         // quarantining is enabled, continue with quarantine process and update exit code
         exit_code = test_run_exit_code.unwrap_or_else(|| failed_tests_extractor.exit_code());
     }
 
-    // quarantine the failed tests
+    // quarantine the failed tests, whether Trunk or the test runner quarantined them
     let mut quarantine_results = QuarantineBulkTestStatus::default();
+    let mut quarantine_sources = Vec::new();
     let quarantined = &quarantine_config.quarantined_tests;
+    let runner_quarantined = failed_tests_extractor.runner_quarantined_ids();
 
     let total_failures = failed_tests_extractor.failed_tests().len();
-    quarantine_results.quarantine_results = failed_tests_extractor
-        .failed_tests()
-        .iter()
-        .cloned()
-        .filter_map(|failure| {
-            let quarantine_failure = quarantined.contains(&failure.id);
-            log::info!(
-                "{} -> {}{}(id: {})",
-                failure.parent_name,
-                failure.name,
-                if quarantine_failure {
-                    " [QUARANTINED] "
-                } else {
-                    " "
-                },
-                failure.id
-            );
-            if quarantine_failure {
-                Some(failure)
-            } else {
-                None
-            }
-        })
-        .collect();
+    for failure in failed_tests_extractor.failed_tests() {
+        let source = match (
+            quarantined.contains(&failure.id),
+            runner_quarantined.contains(&failure.id),
+        ) {
+            (true, true) => Some(BundleMetaQuarantineSource::ServerAndRunner),
+            (true, false) => Some(BundleMetaQuarantineSource::Server),
+            (false, true) => Some(BundleMetaQuarantineSource::Runner),
+            (false, false) => None,
+        };
+        log::info!(
+            "{} -> {}{}(id: {})",
+            failure.parent_name,
+            failure.name,
+            match source {
+                Some(BundleMetaQuarantineSource::Runner) => " [QUARANTINED by the test runner] ",
+                Some(_) => " [QUARANTINED] ",
+                None => " ",
+            },
+            failure.id
+        );
+        if let Some(source) = source {
+            quarantine_results.quarantine_results.push(failure.clone());
+            quarantine_sources.push(BundleMetaQuarantinedTestSource {
+                id: failure.id.clone(),
+                source,
+            });
+        }
+    }
     quarantine_results.group_is_quarantined =
         quarantine_results.quarantine_results.len() == total_failures;
 
@@ -250,9 +347,43 @@ pub async fn gather_quarantine_context(
         exit_code
     };
 
+    let num_quarantined = quarantine_results.quarantine_results.len();
+    // failures the test runner quarantined are quarantined even when the config can't be fetched
+    let outcome = if total_failures == 0 {
+        QuarantineOutcome::NoFailures
+    } else if quarantine_results.group_is_quarantined {
+        QuarantineOutcome::AllQuarantined {
+            count: num_quarantined,
+        }
+    } else if quarantining_unavailable {
+        QuarantineOutcome::QuarantiningUnavailable
+    } else {
+        QuarantineOutcome::SomeUnquarantined {
+            quarantined: num_quarantined,
+            unquarantined: total_failures - num_quarantined,
+        }
+    };
+
+    let audit = audit.map(|audit| {
+        audit.with_quarantined_failures(
+            quarantine_results
+                .quarantine_results
+                .iter()
+                .zip(&quarantine_sources)
+                .map(|(test, quarantine_source)| {
+                    QuarantinedFailure::new(test, quarantine_source.source)
+                })
+                .collect(),
+        )
+    });
+
     QuarantineContext {
         exit_code,
         quarantine_status: quarantine_results,
+        outcome,
+        audit,
+        num_unmatched_quarantined_ids,
+        quarantine_sources,
     }
 }
 
@@ -292,13 +423,18 @@ mod tests {
             ],
             glob: String::from("**/*.xml"),
             resolved_status: None,
+            variant: None,
         }];
 
-        let retried_failures =
-            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets)
-                .failed_tests()
-                .to_vec();
-        assert!(retried_failures.is_empty());
+        let failed_tests_extractor = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        );
+        assert!(failed_tests_extractor.failed_tests().is_empty());
+        assert_eq!(failed_tests_extractor.recovered_tests().len(), 1);
+        assert_eq!(failed_tests_extractor.recovered_tests()[0].name, "Hello");
     }
 
     #[tokio::test(start_paused = true)]
@@ -317,12 +453,17 @@ mod tests {
             ],
             glob: String::from("**/*.xml"),
             resolved_status: None,
+            variant: None,
         }];
 
-        let retried_failures =
-            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets)
-                .failed_tests()
-                .to_vec();
+        let retried_failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
         assert!(retried_failures.is_empty());
     }
 
@@ -342,12 +483,17 @@ mod tests {
             ],
             glob: String::from("**/*.xml"),
             resolved_status: None,
+            variant: None,
         }];
 
-        let mut multi_failures =
-            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets)
-                .failed_tests()
-                .to_vec();
+        let mut multi_failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
         multi_failures.sort_by(|a, b| a.name.cmp(&b.name));
         assert_eq!(multi_failures.len(), 2);
         assert_eq!(multi_failures[0].name, "Goodbye");
@@ -378,12 +524,17 @@ mod tests {
             ],
             glob: String::from("**/*.xml"),
             resolved_status: None,
+            variant: None,
         }];
 
-        let some_failures =
-            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets)
-                .failed_tests()
-                .to_vec();
+        let some_failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
         assert_eq!(some_failures.len(), 1);
         assert_eq!(some_failures[0].name, "Goodbye");
     }
@@ -399,6 +550,7 @@ mod tests {
                 }],
                 glob: String::from("1/*.xml"),
                 resolved_status: Some(JunitReportStatus::Passed),
+                variant: None,
             },
             FileSet {
                 file_set_type: FileSetType::Junit,
@@ -408,6 +560,7 @@ mod tests {
                 }],
                 glob: String::from("2/*.xml"),
                 resolved_status: Some(JunitReportStatus::Flaky),
+                variant: None,
             },
             FileSet {
                 file_set_type: FileSetType::Junit,
@@ -417,15 +570,149 @@ mod tests {
                 }],
                 glob: String::from("3/*.xml"),
                 resolved_status: Some(JunitReportStatus::Failed),
+                variant: None,
             },
         ];
 
-        let mut multi_failures =
-            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets)
-                .failed_tests()
-                .to_vec();
+        let mut multi_failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
         multi_failures.sort_by(|a, b| a.name.cmp(&b.name));
         assert_eq!(multi_failures.len(), 1);
         assert_eq!(multi_failures[0].name, "Hello");
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_extract_failed_tests_of_variants() {
+        let file_set = |glob: &str, variant: &str| FileSet {
+            file_set_type: FileSetType::Junit,
+            files: vec![BundledFile {
+                original_path: get_test_file_path(JUNIT0_FAIL),
+                ..BundledFile::default()
+            }],
+            glob: String::from(glob),
+            resolved_status: None,
+            variant: Some(String::from(variant)),
+        };
+        let file_sets = vec![
+            file_set("ios/*.xml", "ios"),
+            file_set("android/*.xml", "android"),
+        ];
+
+        let variant_failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
+        assert_eq!(variant_failures.len(), 2);
+        assert_eq!(variant_failures[0].name, "Hello");
+        assert_eq!(variant_failures[1].name, "Hello");
+        assert_ne!(variant_failures[0].id, variant_failures[1].id);
+
+        let failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &[FileSet {
+                variant: None,
+                ..file_set("*.xml", "")
+            }],
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
+        assert_eq!(failures.len(), 1);
+        assert!(variant_failures
+            .iter()
+            .all(|variant_failure| variant_failure.id != failures[0].id));
+    }
+
+    #[test]
+    fn test_extract_failed_tests_with_inferred_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let junit_path = temp_dir.path().join("vitest.xml");
+        std::fs::write(
+            &junit_path,
+            r#"<testsuites>
+                <testsuite name="src/Button.test.tsx">
+                    <testcase classname="src/Button.test.tsx" name="renders">
+                        <failure/>
+                    </testcase>
+                </testsuite>
+            </testsuites>"#,
+        )
+        .unwrap();
+        let file_sets = vec![FileSet {
+            file_set_type: FileSetType::Junit,
+            files: vec![BundledFile {
+                original_path: junit_path.to_string_lossy().to_string(),
+                ..BundledFile::default()
+            }],
+            glob: String::from("*.xml"),
+            resolved_status: None,
+            variant: None,
+        }];
+
+        let failures = FailedTestsExtractor::new(
+            &RepoUrlParts::default(),
+            ORG_SLUG,
+            &file_sets,
+            JunitParser::new,
+        )
+        .failed_tests()
+        .to_vec();
+        assert_eq!(failures[0].file.as_deref(), Some("src/Button.test.tsx"));
+
+        let uninferred_failures =
+            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets, || {
+                JunitParser::new().with_infer_file_from_classname(false)
+            })
+            .failed_tests()
+            .to_vec();
+        assert_eq!(uninferred_failures[0].file, None);
+        // inferring the file doesn't change the ID of the test
+        assert_eq!(uninferred_failures[0].id, failures[0].id);
+    }
+
+    #[test]
+    fn test_extract_failed_tests_relative_to_the_repo_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let junit_path = temp_dir.path().join("junit.xml");
+        std::fs::write(
+            &junit_path,
+            r#"<testsuites>
+                <testsuite name="auth">
+                    <testcase file="C:\repo\src\login_test.rs" name="logs in">
+                        <failure/>
+                    </testcase>
+                </testsuite>
+            </testsuites>"#,
+        )
+        .unwrap();
+        let file_sets = vec![FileSet {
+            file_set_type: FileSetType::Junit,
+            files: vec![BundledFile {
+                original_path: junit_path.to_string_lossy().to_string(),
+                ..BundledFile::default()
+            }],
+            glob: String::from("*.xml"),
+            resolved_status: None,
+            variant: None,
+        }];
+
+        let failures =
+            FailedTestsExtractor::new(&RepoUrlParts::default(), ORG_SLUG, &file_sets, || {
+                JunitParser::with_repo_root("C:\\repo")
+            })
+            .failed_tests()
+            .to_vec();
+        assert_eq!(failures[0].file.as_deref(), Some("src/login_test.rs"));
+    }
 }
@@ -0,0 +1,339 @@
+//! Converters turn test result files of formats the CLI doesn't parse, like those of an in-house
+//! test framework, into junit, by running a command for each of them. The junit they write is
+//! then parsed, validated and bundled like any other junit file.
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use bundle::{BundleMetaConversion, BundleMetaConverter};
+use context::junit::junit_path::JunitReportFileWithStatus;
+
+use crate::test_command::shell_command;
+
+pub const DEFAULT_CONVERTER_TIMEOUT: Duration = Duration::from_secs(60);
+pub const CONVERTER_INPUT_PLACEHOLDER: &str = "{input}";
+pub const CONVERTER_OUTPUT_PLACEHOLDER: &str = "{output}";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A command converting the file at `{input}` to junit at `{output}`, run with the shell, e.g.
+/// `my-converter {input} {output}`
+#[derive(Debug, Clone)]
+pub struct Converter {
+    command: String,
+    timeout: Duration,
+}
+
+impl Converter {
+    pub fn new(command: String, timeout: Duration) -> anyhow::Result<Self> {
+        for placeholder in [CONVERTER_INPUT_PLACEHOLDER, CONVERTER_OUTPUT_PLACEHOLDER] {
+            if !command.contains(placeholder) {
+                return Err(anyhow::anyhow!(
+                    "The converter command {:?} has no {} placeholder",
+                    command,
+                    placeholder
+                ));
+            }
+        }
+        Ok(Self { command, timeout })
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The first line the program of the command, its first word, prints for `--version`
+    pub fn probe_version(&self) -> Option<String> {
+        let program = self.command.split_whitespace().next()?;
+        match run_with_timeout(&format!("{} --version", program), self.timeout) {
+            Ok(stdout) => stdout
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(String::from),
+            Err(e) => {
+                log::debug!(
+                    "Failed to get the version of converter {}: {:#}",
+                    program,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Runs the command for `input`, which has to write junit to `output` and exit successfully
+    /// within the timeout.
+    pub fn convert<T: AsRef<Path>, U: AsRef<Path>>(
+        &self,
+        input: T,
+        output: U,
+    ) -> anyhow::Result<()> {
+        let command = self
+            .command
+            .replace(
+                CONVERTER_INPUT_PLACEHOLDER,
+                &shell_quote(&input.as_ref().to_string_lossy()),
+            )
+            .replace(
+                CONVERTER_OUTPUT_PLACEHOLDER,
+                &shell_quote(&output.as_ref().to_string_lossy()),
+            );
+        run_with_timeout(&command, self.timeout)?;
+        if !output.as_ref().is_file() {
+            return Err(anyhow::anyhow!(
+                "the converter did not write {:?}",
+                output.as_ref()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Converts each file matched by `input_globs`, relative to `repo_root` unless absolute, to a junit
+/// file in `output_dir`. A file that fails to convert is warned about and left out, while the
+/// conversion is still recorded in the returned meta.
+pub fn convert_files<T: AsRef<str>>(
+    converter: &Converter,
+    input_globs: &[T],
+    repo_root: &str,
+    output_dir: &Path,
+) -> anyhow::Result<(Vec<JunitReportFileWithStatus>, BundleMetaConverter)> {
+    let mut input_paths = Vec::new();
+    for input_glob in input_globs {
+        let input_glob = PathBuf::from(input_glob.as_ref());
+        let input_glob = if input_glob.is_absolute() {
+            input_glob
+        } else {
+            Path::new(repo_root).join(input_glob)
+        };
+        let mut paths: Vec<PathBuf> = glob::glob(&input_glob.to_string_lossy())
+            .with_context(|| format!("Invalid converter input glob {:?}", input_glob))?
+            .filter_map(|entry| entry.ok().filter(|path| path.is_file()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            if !input_paths.contains(&path) {
+                input_paths.push(path);
+            }
+        }
+    }
+    if input_paths.is_empty() {
+        log::warn!("No files matched the converter input globs");
+    }
+
+    let mut junit_path_wrappers = Vec::with_capacity(input_paths.len());
+    let mut conversions = Vec::with_capacity(input_paths.len());
+    for (i, input_path) in input_paths.into_iter().enumerate() {
+        let output_path = output_dir.join(format!("converted_{}.xml", i));
+        let start = Instant::now();
+        let converted = converter.convert(&input_path, &output_path);
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let input_path = input_path.to_string_lossy().into_owned();
+        match converted {
+            Ok(()) => {
+                let output_path = output_path.to_string_lossy().into_owned();
+                log::info!("Converted {} to junit in {}ms", input_path, duration_ms);
+                junit_path_wrappers.push(JunitReportFileWithStatus::from(output_path.clone()));
+                conversions.push(BundleMetaConversion {
+                    input_path,
+                    output_path: Some(output_path),
+                    duration_ms,
+                    error: None,
+                });
+            }
+            Err(e) => {
+                log::warn!("Leaving {} out of the bundle: {:#}", input_path, e);
+                conversions.push(BundleMetaConversion {
+                    input_path,
+                    output_path: None,
+                    duration_ms,
+                    error: Some(format!("{:#}", e)),
+                });
+            }
+        }
+    }
+
+    Ok((
+        junit_path_wrappers,
+        BundleMetaConverter {
+            command: converter.command().to_string(),
+            version: converter.probe_version(),
+            conversions,
+        },
+    ))
+}
+
+/// Runs `command` with the shell, returning its stdout. Fails with its stderr when it exits
+/// unsuccessfully, and kills it when it runs longer than `timeout`.
+fn run_with_timeout(command: &str, timeout: Duration) -> anyhow::Result<String> {
+    let [program, args @ ..] = shell_command(command);
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {:?}", command))?;
+    // Read in the background, so that a chatty command doesn't block on a full pipe
+    let stdout = read_to_string_in_background(child.stdout.take());
+    let stderr = read_to_string_in_background(child.stderr.take());
+
+    let start = Instant::now();
+    let exit_status = loop {
+        if let Some(exit_status) = child.try_wait()? {
+            break exit_status;
+        }
+        if start.elapsed() >= timeout {
+            // Whatever the command started may still hold the pipes, so they aren't waited for
+            if let Err(e) = child.kill() {
+                log::debug!("Failed to kill {:?}: {}", command, e);
+            }
+            let _ = child.wait();
+            return Err(anyhow::anyhow!(
+                "timed out after {}",
+                humantime::format_duration(timeout)
+            ));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !exit_status.success() {
+        let stderr = stderr.trim();
+        return Err(if stderr.is_empty() {
+            anyhow::anyhow!("{}", exit_status)
+        } else {
+            anyhow::anyhow!("{}: {}", exit_status, stderr)
+        });
+    }
+    Ok(stdout)
+}
+
+fn read_to_string_in_background<R: Read + Send + 'static>(reader: Option<R>) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut reader) = reader {
+            let _ = reader.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+#[cfg(not(windows))]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value)
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_files_and_records_failures() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_dir = temp_dir.path().join("results dir");
+        std::fs::create_dir(&input_dir).unwrap();
+        std::fs::write(input_dir.join("a.txt"), "<testsuites/>").unwrap();
+        std::fs::write(input_dir.join("b's.txt"), "<testsuites/>").unwrap();
+        std::fs::write(input_dir.join("c.txt"), "broken").unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+
+        let converter = Converter::new(
+            String::from(
+                "grep -q testsuites {input} || { echo \"not a result\" >&2; exit 3; }; cp {input} {output}",
+            ),
+            DEFAULT_CONVERTER_TIMEOUT,
+        )
+        .unwrap();
+        let (junit_path_wrappers, converter_meta) = convert_files(
+            &converter,
+            &["results dir/*.txt", "results dir/a.txt"],
+            &temp_dir.path().to_string_lossy(),
+            output_dir.path(),
+        )
+        .unwrap();
+
+        assert_eq!(junit_path_wrappers.len(), 2);
+        for junit_path_wrapper in &junit_path_wrappers {
+            assert_eq!(
+                std::fs::read_to_string(&junit_path_wrapper.junit_path).unwrap(),
+                "<testsuites/>"
+            );
+        }
+        assert_eq!(converter_meta.command, converter.command());
+        assert_eq!(
+            converter_meta
+                .conversions
+                .iter()
+                .map(|conversion| (
+                    Path::new(&conversion.input_path)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned(),
+                    conversion.output_path.is_some(),
+                    conversion.error.clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("a.txt"), true, None),
+                (String::from("b's.txt"), true, None),
+                (
+                    String::from("c.txt"),
+                    false,
+                    Some(String::from("exit status: 3: not a result"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn times_out() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let input_path = temp_dir.path().join("input.txt");
+        std::fs::write(&input_path, "").unwrap();
+        let converter = Converter::new(
+            String::from("sleep 5; touch {output} {input}"),
+            Duration::from_millis(100),
+        )
+        .unwrap();
+
+        let start = Instant::now();
+        let error = converter
+            .convert(&input_path, temp_dir.path().join("output.xml"))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "timed out after 100ms");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn probes_version_and_requires_placeholders() {
+        let converter = Converter::new(
+            String::from("echo {input} {output}"),
+            DEFAULT_CONVERTER_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(converter.probe_version(), Some(String::from("--version")));
+        let converter = Converter::new(
+            String::from("false {input} {output}"),
+            DEFAULT_CONVERTER_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(converter.probe_version(), None);
+
+        assert!(Converter::new(String::from("cat {input}"), DEFAULT_CONVERTER_TIMEOUT).is_err());
+    }
+}
@@ -0,0 +1,702 @@
+//! Exports the test results of an upload as a Datadog CI Visibility test cycle payload, for orgs
+//! that keep feeding Datadog while they move to Trunk, without parsing the junit files twice.
+//!
+//! The payload is what the agentless mode of the Datadog tracers sends to the `citestcycle`
+//! intake: a session, a module and a suite end event per test suite, and a test event per test
+//! case, with the CI and git metadata of the run.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use context::{
+    env::{parser::CIInfo, parser::CIPlatform, parser::EnvParser, parser::StableBranches, EnvVars},
+    junit::parser::extra_attrs,
+    meta::MetaContext,
+    repo::BundleRepo,
+};
+use quick_junit::{Report, TestCase, TestCaseStatus, TestSuite};
+use serde::{Deserialize, Serialize};
+
+pub const DD_API_KEY_ENV: &str = "DD_API_KEY";
+pub const DD_SITE_ENV: &str = "DD_SITE";
+pub const DD_SERVICE_ENV: &str = "DD_SERVICE";
+pub const DD_ENV_ENV: &str = "DD_ENV";
+/// Replaces the intake of `DD_SITE`, like it does for the Datadog tracers
+pub const DD_AGENTLESS_URL_ENV: &str = "DD_CIVISIBILITY_AGENTLESS_URL";
+
+const DEFAULT_DD_SITE: &str = "datadoghq.com";
+const INTAKE_PATH: &str = "/api/v2/citestcycle";
+const PAYLOAD_VERSION: u32 = 1;
+const EVENT_VERSION: u32 = 1;
+const TEST_EVENT_VERSION: u32 = 2;
+
+/// Where test results are exported to besides Trunk
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExportFormat {
+    /// The Datadog CI Visibility test cycle payload
+    Datadog,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatadogPayload {
+    pub version: u32,
+    pub metadata: BTreeMap<String, BTreeMap<String, String>>,
+    pub events: Vec<DatadogEvent>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatadogEvent {
+    #[serde(rename = "type")]
+    pub event_type: DatadogEventType,
+    pub version: u32,
+    pub content: DatadogEventContent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatadogEventType {
+    Test,
+    TestSuiteEnd,
+    TestModuleEnd,
+    TestSessionEnd,
+}
+
+impl DatadogEventType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DatadogEventType::Test => "test",
+            DatadogEventType::TestSuiteEnd => "test_suite_end",
+            DatadogEventType::TestModuleEnd => "test_module_end",
+            DatadogEventType::TestSessionEnd => "test_session_end",
+        }
+    }
+}
+
+/// Only test events are spans with trace and span ids, the end events are tied to their tests by
+/// the session, module and suite ids.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatadogEventContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<u64>,
+    pub test_session_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_module_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_suite_id: Option<u64>,
+    pub name: String,
+    pub resource: String,
+    pub service: String,
+    #[serde(rename = "type")]
+    pub span_type: String,
+    /// Nanoseconds since the epoch
+    pub start: i64,
+    /// Nanoseconds
+    pub duration: i64,
+    pub error: u8,
+    pub meta: BTreeMap<String, String>,
+    pub metrics: BTreeMap<String, f64>,
+}
+
+/// Everything about the run that goes into the payload besides the test results.
+#[derive(Debug, Clone)]
+pub struct DatadogExport {
+    pub service: String,
+    pub env: Option<String>,
+    pub ci_info: CIInfo,
+    pub repo: BundleRepo,
+    pub cli_version: String,
+    /// Makes the ids of the events unique to the run, while the same results give the same ids
+    pub seed: String,
+    /// When tests without a timestamp are assumed to have started
+    pub now: DateTime<Utc>,
+}
+
+impl DatadogExport {
+    /// Reads the service and env from `DD_SERVICE` and `DD_ENV`, and the CI info from the env.
+    /// The service defaults to the name of the repo.
+    pub fn from_env(
+        env_vars: &EnvVars,
+        repo: &BundleRepo,
+        stable_branches: &StableBranches,
+        cli_version: String,
+    ) -> Self {
+        let mut env_parser = EnvParser::with_stable_branches(stable_branches.clone());
+        env_parser.parse(env_vars);
+        let ci_info = env_parser
+            .into_ci_info_parser()
+            .map(|ci_info_parser| ci_info_parser.info_ci_info())
+            .unwrap_or_else(|| CIInfo::new(CIPlatform::Unknown));
+        let now = Utc::now();
+        Self {
+            service: env_vars
+                .get(DD_SERVICE_ENV)
+                .filter(|service| !service.is_empty())
+                .cloned()
+                .unwrap_or_else(|| repo.repo.name.clone()),
+            env: env_vars
+                .get(DD_ENV_ENV)
+                .filter(|env| !env.is_empty())
+                .cloned(),
+            ci_info: MetaContext::new(&ci_info, repo).ci_info,
+            repo: repo.clone(),
+            cli_version,
+            seed: format!(
+                "{}:{}",
+                repo.repo_head_sha,
+                now.timestamp_nanos_opt().unwrap_or_default()
+            ),
+            now,
+        }
+    }
+
+    pub fn payload(&self, reports: &[Report]) -> DatadogPayload {
+        let session_id = self.id(&["session"]);
+        let module_id = self.id(&["module"]);
+        let mut events = Vec::new();
+        let mut session_span = SpanTimes::default();
+        let mut session_status = Status::Skip;
+
+        for (report_index, report) in reports.iter().enumerate() {
+            for (suite_index, test_suite) in report.test_suites.iter().enumerate() {
+                let suite_key = [report_index.to_string(), suite_index.to_string()];
+                let suite_id = self.id(&["suite", &suite_key[0], &suite_key[1]]);
+                let suite_start = test_suite
+                    .timestamp
+                    .map(|timestamp| timestamp.with_timezone(&Utc))
+                    .or_else(|| report.timestamp.map(|t| t.with_timezone(&Utc)))
+                    .unwrap_or(self.now);
+                let mut suite_span = SpanTimes::default();
+                let mut suite_status = Status::Skip;
+
+                for (case_index, test_case) in test_suite.test_cases.iter().enumerate() {
+                    let start = test_case
+                        .timestamp
+                        .map(|timestamp| timestamp.with_timezone(&Utc))
+                        .unwrap_or(suite_start);
+                    let duration = test_case.time.unwrap_or_default();
+                    suite_span.add(start, duration);
+                    let status = Status::of(&test_case.status);
+                    suite_status = suite_status.combine(status);
+
+                    let trace_id = self.id(&[
+                        "test",
+                        &suite_key[0],
+                        &suite_key[1],
+                        &case_index.to_string(),
+                    ]);
+                    let mut content = self.content(
+                        DatadogEventType::Test,
+                        session_id,
+                        Some(test_suite),
+                        start,
+                        duration,
+                        status,
+                    );
+                    content.trace_id = Some(trace_id);
+                    content.span_id = Some(trace_id);
+                    content.parent_id = Some(0);
+                    content.test_module_id = Some(module_id);
+                    content.test_suite_id = Some(suite_id);
+                    content.name = String::from("junit.test");
+                    content.resource =
+                        format!("{}.{}", test_suite.name.as_str(), test_case.name.as_str());
+                    set_test_case_meta(&mut content, test_case);
+                    events.push(DatadogEvent {
+                        event_type: DatadogEventType::Test,
+                        version: TEST_EVENT_VERSION,
+                        content,
+                    });
+                }
+
+                let (start, duration) = match test_suite.time {
+                    Some(time) => (suite_start, time),
+                    None => suite_span.start_and_duration(suite_start),
+                };
+                session_span.add(start, duration);
+                session_status = session_status.combine(suite_status);
+                let mut content = self.content(
+                    DatadogEventType::TestSuiteEnd,
+                    session_id,
+                    Some(test_suite),
+                    start,
+                    duration,
+                    suite_status,
+                );
+                content.test_module_id = Some(module_id);
+                content.test_suite_id = Some(suite_id);
+                events.push(DatadogEvent {
+                    event_type: DatadogEventType::TestSuiteEnd,
+                    version: EVENT_VERSION,
+                    content,
+                });
+            }
+        }
+
+        let (start, duration) = session_span.start_and_duration(self.now);
+        for event_type in [
+            DatadogEventType::TestModuleEnd,
+            DatadogEventType::TestSessionEnd,
+        ] {
+            let mut content = self.content(
+                event_type,
+                session_id,
+                None,
+                start,
+                duration,
+                session_status,
+            );
+            if event_type == DatadogEventType::TestModuleEnd {
+                content.test_module_id = Some(module_id);
+                content
+                    .meta
+                    .insert(String::from("test.module"), self.service.clone());
+            }
+            events.push(DatadogEvent {
+                event_type,
+                version: EVENT_VERSION,
+                content,
+            });
+        }
+
+        DatadogPayload {
+            version: PAYLOAD_VERSION,
+            metadata: BTreeMap::from([(
+                String::from("*"),
+                BTreeMap::from([
+                    (String::from("language"), String::from("junit")),
+                    (
+                        String::from("library_version"),
+                        format!("trunk-analytics-cli/{}", self.cli_version),
+                    ),
+                ]),
+            )]),
+            events,
+        }
+    }
+
+    /// The content every event shares, with the CI and git metadata of the run. Events of the
+    /// whole session have no `test_suite`.
+    fn content(
+        &self,
+        event_type: DatadogEventType,
+        session_id: u64,
+        test_suite: Option<&TestSuite>,
+        start: DateTime<Utc>,
+        duration: Duration,
+        status: Status,
+    ) -> DatadogEventContent {
+        let mut meta = self.run_meta();
+        meta.insert(String::from("test.status"), String::from(status.as_str()));
+        let resource = match test_suite {
+            Some(test_suite) => {
+                meta.insert(
+                    String::from("test.suite"),
+                    String::from(test_suite.name.as_str()),
+                );
+                String::from(test_suite.name.as_str())
+            }
+            None => self.service.clone(),
+        };
+        DatadogEventContent {
+            trace_id: None,
+            span_id: None,
+            parent_id: None,
+            test_session_id: session_id,
+            test_module_id: None,
+            test_suite_id: None,
+            name: format!("junit.{}", event_type.as_str()),
+            resource,
+            service: self.service.clone(),
+            span_type: String::from(event_type.as_str()),
+            start: start.timestamp_nanos_opt().unwrap_or_default(),
+            duration: i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX),
+            error: u8::from(status == Status::Fail),
+            meta,
+            metrics: BTreeMap::new(),
+        }
+    }
+
+    fn run_meta(&self) -> BTreeMap<String, String> {
+        let ci_info = &self.ci_info;
+        let repo = &self.repo;
+        let mut meta = BTreeMap::new();
+        let mut insert = |key: &str, value: Option<&str>| {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                meta.insert(String::from(key), String::from(value));
+            }
+        };
+        insert("env", self.env.as_deref());
+        insert("test.type", Some("test"));
+        insert("ci.provider.name", ci_provider_name(ci_info.platform));
+        insert("ci.job.url", ci_info.job_url.as_deref());
+        insert("ci.job.name", ci_info.job.as_deref());
+        insert("ci.pipeline.name", ci_info.workflow.as_deref());
+        insert("git.repository_url", Some(&repo.repo_url));
+        insert("git.commit.sha", Some(&repo.repo_head_sha));
+        insert("git.branch", ci_info.branch.as_deref());
+        insert("git.commit.message", ci_info.commit_message.as_deref());
+        insert("git.commit.author.name", ci_info.author_name.as_deref());
+        insert("git.commit.author.email", ci_info.author_email.as_deref());
+        insert(
+            "git.commit.committer.name",
+            ci_info.committer_name.as_deref(),
+        );
+        insert(
+            "git.commit.committer.email",
+            ci_info.committer_email.as_deref(),
+        );
+        insert(
+            "git.pull_request.base_branch",
+            ci_info.target_branch.as_deref(),
+        );
+        meta
+    }
+
+    /// A nonzero id that fits in an i64, which is what Datadog expects of span ids
+    fn id(&self, key: &[&str]) -> u64 {
+        let mut hasher = openssl::sha::Sha256::new();
+        hasher.update(self.seed.as_bytes());
+        for part in key {
+            hasher.update(b"/");
+            hasher.update(part.as_bytes());
+        }
+        let digest = hasher.finish();
+        let mut id_bytes = [0; 8];
+        id_bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(id_bytes) >> 1).max(1)
+    }
+}
+
+fn set_test_case_meta(content: &mut DatadogEventContent, test_case: &TestCase) {
+    content.meta.insert(
+        String::from("test.name"),
+        String::from(test_case.name.as_str()),
+    );
+    if let Some(file) = test_case
+        .extra
+        .get(extra_attrs::FILE)
+        .or_else(|| test_case.extra.get(extra_attrs::FILEPATH))
+        .or_else(|| {
+            test_case
+                .extra
+                .get(extra_attrs::FILE_INFERRED_FROM_CLASSNAME)
+        })
+    {
+        content.meta.insert(
+            String::from("test.source.file"),
+            String::from(file.as_str()),
+        );
+    }
+    if let Some(line) = test_case
+        .extra
+        .get(extra_attrs::LINE)
+        .and_then(|line| line.parse::<f64>().ok())
+    {
+        content
+            .metrics
+            .insert(String::from("test.source.start"), line);
+    }
+    if let TestCaseStatus::NonSuccess {
+        message,
+        ty,
+        description,
+        ..
+    } = &test_case.status
+    {
+        for (key, value) in [
+            ("error.message", message),
+            ("error.type", ty),
+            ("error.stack", description),
+        ] {
+            if let Some(value) = value {
+                content
+                    .meta
+                    .insert(String::from(key), String::from(value.as_str()));
+            }
+        }
+    }
+}
+
+/// The names the Datadog tracers give CI providers
+fn ci_provider_name(platform: CIPlatform) -> Option<&'static str> {
+    match platform {
+        CIPlatform::GitHubActions => Some("github"),
+        CIPlatform::JenkinsPipeline | CIPlatform::Jenkins => Some("jenkins"),
+        CIPlatform::CircleCI => Some("circleci"),
+        CIPlatform::Buildkite => Some("buildkite"),
+        CIPlatform::Semaphore => Some("semaphore"),
+        CIPlatform::TravisCI => Some("travisci"),
+        CIPlatform::Webappio => Some("webappio"),
+        CIPlatform::AWSCodeBuild => Some("awscodepipeline"),
+        CIPlatform::BitbucketPipelines => Some("bitbucket"),
+        CIPlatform::AzurePipelines => Some("azurepipelines"),
+        CIPlatform::GitLabCI => Some("gitlab"),
+        CIPlatform::Drone => Some("drone"),
+        CIPlatform::Custom | CIPlatform::Unknown => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl Status {
+    fn of(status: &TestCaseStatus) -> Self {
+        match status {
+            TestCaseStatus::Success { .. } => Status::Pass,
+            TestCaseStatus::NonSuccess { .. } => Status::Fail,
+            TestCaseStatus::Skipped { .. } => Status::Skip,
+        }
+    }
+
+    /// The status of a suite or session with tests of both statuses
+    fn combine(self, other: Self) -> Self {
+        match (self, other) {
+            (Status::Fail, _) | (_, Status::Fail) => Status::Fail,
+            (Status::Pass, _) | (_, Status::Pass) => Status::Pass,
+            (Status::Skip, Status::Skip) => Status::Skip,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Fail => "fail",
+            Status::Skip => "skip",
+        }
+    }
+}
+
+/// The earliest start and latest end of the spans of a suite or session
+#[derive(Debug, Default)]
+struct SpanTimes {
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+impl SpanTimes {
+    fn add(&mut self, start: DateTime<Utc>, duration: Duration) {
+        let end = start + chrono::Duration::from_std(duration).unwrap_or_default();
+        self.start = Some(self.start.map_or(start, |s| s.min(start)));
+        self.end = Some(self.end.map_or(end, |e| e.max(end)));
+    }
+
+    fn start_and_duration(&self, default_start: DateTime<Utc>) -> (DateTime<Utc>, Duration) {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => (start, (end - start).to_std().unwrap_or_default()),
+            _ => (default_start, Duration::ZERO),
+        }
+    }
+}
+
+/// The intake of `DD_CIVISIBILITY_AGENTLESS_URL`, or else of `DD_SITE`
+pub fn intake_url(env_vars: &EnvVars) -> String {
+    let non_empty = |key: &str| env_vars.get(key).filter(|value| !value.is_empty());
+    match non_empty(DD_AGENTLESS_URL_ENV) {
+        Some(agentless_url) => format!("{}{}", agentless_url.trim_end_matches('/'), INTAKE_PATH),
+        None => format!(
+            "https://citestcycle-intake.{}{}",
+            non_empty(DD_SITE_ENV).map_or(DEFAULT_DD_SITE, String::as_str),
+            INTAKE_PATH
+        ),
+    }
+}
+
+pub async fn submit(
+    payload: &DatadogPayload,
+    intake_url: &str,
+    api_key: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()?
+        .post(intake_url)
+        .header("dd-api-key", api_key)
+        .json(payload)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Writes the payload to `export_output` and submits it to Datadog when `DD_API_KEY` is set. Never
+/// fails, since the export must not change the outcome of the upload to Trunk.
+pub async fn export_to_datadog(
+    reports: &[Report],
+    repo: &BundleRepo,
+    stable_branches: &StableBranches,
+    export_output: Option<&str>,
+    timeout: Duration,
+) {
+    let env_vars: EnvVars = std::env::vars().collect();
+    let api_key = env_vars
+        .get(DD_API_KEY_ENV)
+        .filter(|api_key| !api_key.is_empty());
+    if export_output.is_none() && api_key.is_none() {
+        log::warn!(
+            "Not exporting to Datadog, set --export-output or {}",
+            DD_API_KEY_ENV
+        );
+        return;
+    }
+
+    let payload = DatadogExport::from_env(
+        &env_vars,
+        repo,
+        stable_branches,
+        crate::cli_version::cli_version(),
+    )
+    .payload(reports);
+    if let Some(export_output) = export_output {
+        let written = serde_json::to_vec_pretty(&payload)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(export_output, json)?));
+        match written {
+            Ok(()) => log::info!("Wrote the Datadog export to {}", export_output),
+            Err(e) => log::warn!(
+                "Failed to write the Datadog export to {}: {}",
+                export_output,
+                e
+            ),
+        }
+    }
+    if let Some(api_key) = api_key {
+        let intake_url = intake_url(&env_vars);
+        match submit(&payload, &intake_url, api_key, timeout).await {
+            Ok(()) => log::info!(
+                "Sent {} test results to Datadog",
+                payload
+                    .events
+                    .iter()
+                    .filter(|event| event.event_type == DatadogEventType::Test)
+                    .count()
+            ),
+            Err(e) => log::warn!("Failed to send the test results to Datadog: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use context::{junit::parser::JunitParser, repo::RepoUrlParts};
+
+    use super::*;
+
+    fn export() -> DatadogExport {
+        let mut ci_info = CIInfo::new(CIPlatform::GitHubActions);
+        ci_info.job_url = Some(String::from(
+            "https://github.com/example/app/actions/runs/1",
+        ));
+        ci_info.job = Some(String::from("test"));
+        ci_info.workflow = Some(String::from("CI"));
+        ci_info.branch = Some(String::from("main"));
+        ci_info.author_name = Some(String::from("Jane Doe"));
+        ci_info.author_email = Some(String::from("jane@example.com"));
+        ci_info.commit_message = Some(String::from("Fix the widget"));
+        DatadogExport {
+            service: String::from("app"),
+            env: Some(String::from("ci")),
+            ci_info,
+            repo: BundleRepo {
+                repo: RepoUrlParts {
+                    host: String::from("github.com"),
+                    owner: String::from("example"),
+                    name: String::from("app"),
+                },
+                repo_url: String::from("https://github.com/example/app"),
+                repo_head_sha: String::from("abc123"),
+                ..Default::default()
+            },
+            cli_version: String::from("0.0.0"),
+            seed: String::from("seed"),
+            now: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+        }
+    }
+
+    fn parse(xml: &str) -> Vec<Report> {
+        let mut junit_parser = JunitParser::new();
+        junit_parser.parse(xml.as_bytes()).unwrap();
+        junit_parser.into_reports()
+    }
+
+    #[test]
+    fn payload_matches_golden_file() {
+        let payload = export().payload(&parse(include_str!(
+            "../test_fixtures/datadog_export/junit.xml"
+        )));
+        let golden: DatadogPayload =
+            serde_json::from_str(include_str!("../test_fixtures/datadog_export/payload.json"))
+                .unwrap();
+        assert_eq!(
+            payload,
+            golden,
+            "update the golden file with:\n{}",
+            serde_json::to_string_pretty(&payload).unwrap()
+        );
+    }
+
+    #[test]
+    fn ids_are_unique_per_run_and_stable_for_a_seed() {
+        let reports = parse(include_str!("../test_fixtures/datadog_export/junit.xml"));
+        let test_ids = |export: &DatadogExport| {
+            export
+                .payload(&reports)
+                .events
+                .into_iter()
+                .filter_map(|event| event.content.span_id)
+                .collect::<Vec<_>>()
+        };
+
+        let ids = test_ids(&export());
+        assert_eq!(ids, test_ids(&export()));
+        let mut unique_ids = ids.clone();
+        unique_ids.sort();
+        unique_ids.dedup();
+        assert_eq!(unique_ids.len(), ids.len());
+        assert!(ids.iter().all(|id| *id > 0 && *id <= i64::MAX as u64));
+
+        let other_run = DatadogExport {
+            seed: String::from("other seed"),
+            ..export()
+        };
+        assert!(test_ids(&other_run)
+            .iter()
+            .all(|other_id| !ids.contains(other_id)));
+    }
+
+    #[test]
+    fn intake_url_follows_site_and_agentless_url() {
+        assert_eq!(
+            intake_url(&EnvVars::default()),
+            "https://citestcycle-intake.datadoghq.com/api/v2/citestcycle"
+        );
+        assert_eq!(
+            intake_url(&EnvVars::from_iter([(
+                String::from(DD_SITE_ENV),
+                String::from("datadoghq.eu")
+            )])),
+            "https://citestcycle-intake.datadoghq.eu/api/v2/citestcycle"
+        );
+        assert_eq!(
+            intake_url(&EnvVars::from_iter([
+                (String::from(DD_SITE_ENV), String::from("datadoghq.eu")),
+                (
+                    String::from(DD_AGENTLESS_URL_ENV),
+                    String::from("http://localhost:8126/")
+                )
+            ])),
+            "http://localhost:8126/api/v2/citestcycle"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use std::{env, fmt, fs::File, io::BufReader};
+
+use api::{client::ApiClient, message::ValidateTokenOrgRequest};
+use bundle::FileSetBuilder;
+use clap::Args;
+use codeowners::CodeOwners;
+use colored::{ColoredString, Colorize};
+use constants::{EXIT_FAILURE, EXIT_SUCCESS};
+use context::{
+    env::{
+        parser::{CIPlatform, EnvParser, StableBranches},
+        EnvVars,
+    },
+    junit::{junit_path::JunitReportFileWithStatus, parser::JunitParser},
+    repo::BundleRepo,
+};
+
+use crate::{
+    env_info_command::parse_stable_branch, env_interpolation::interpolate_env_vars_in_all,
+};
+
+#[derive(Args, Clone, Debug)]
+pub struct DoctorArgs {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        help = "Comma-separated list of glob paths to junit files.",
+    )]
+    pub junit_paths: Vec<String>,
+    #[arg(long, help = "Organization url slug.")]
+    pub org_url_slug: Option<String>,
+    #[arg(
+        long,
+        env = "TRUNK_API_TOKEN",
+        hide_env_values = true,
+        help = "Organization token. Defaults to TRUNK_API_TOKEN env var."
+    )]
+    pub token: Option<String>,
+    #[arg(long, help = "Path to repository root. Defaults to current directory.")]
+    pub repo_root: Option<String>,
+    #[arg(long, help = "Value to override URL of repository.")]
+    pub repo_url: Option<String>,
+    #[arg(long, help = "Value to override CODEOWNERS file or directory path.")]
+    pub codeowners_path: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_stable_branch,
+        help = "Stable branch, which is classified as protected. Repeat for more than one. Prefix with `re:` to match a regex. Defaults to main and master."
+    )]
+    pub stable_branches: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoctorCheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl DoctorCheckStatus {
+    fn colored(&self) -> ColoredString {
+        match self {
+            DoctorCheckStatus::Pass => self.to_string().green(),
+            DoctorCheckStatus::Warn => self.to_string().yellow(),
+            DoctorCheckStatus::Fail => self.to_string().red(),
+        }
+    }
+}
+
+impl fmt::Display for DoctorCheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DoctorCheckStatus::Pass => write!(f, "PASS"),
+            DoctorCheckStatus::Warn => write!(f, "WARN"),
+            DoctorCheckStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub status: DoctorCheckStatus,
+    pub detail: String,
+    /// What to change to make the check pass, shown for warnings and failures
+    pub hint: Option<&'static str>,
+}
+
+impl DoctorCheck {
+    fn pass<T: Into<String>>(name: &'static str, detail: T) -> Self {
+        Self {
+            name,
+            status: DoctorCheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn<T: Into<String>>(name: &'static str, detail: T, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: DoctorCheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+
+    fn fail<T: Into<String>>(name: &'static str, detail: T, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: DoctorCheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+/// Runs the checks of an upload without gathering a bundle or uploading anything, so that a new
+/// repo can be set up without trial and error. Only failed checks cause a non-zero exit code.
+pub async fn run_doctor(
+    DoctorArgs {
+        junit_paths,
+        org_url_slug,
+        token,
+        repo_root,
+        repo_url,
+        codeowners_path,
+        stable_branches,
+    }: DoctorArgs,
+) -> anyhow::Result<i32> {
+    let mut checks = Vec::new();
+
+    let repo = BundleRepo::new(
+        repo_root.clone(),
+        repo_url,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        &std::env::vars().collect(),
+    );
+    let repo_root = match &repo {
+        Ok(repo) => {
+            checks.push(DoctorCheck::pass(
+                "git repo",
+                format!(
+                    "{}/{}/{} on {}",
+                    repo.repo.host, repo.repo.owner, repo.repo.name, repo.repo_head_branch
+                ),
+            ));
+            repo.repo_root.clone()
+        }
+        Err(e) => {
+            checks.push(DoctorCheck::fail(
+                "git repo",
+                e.to_string(),
+                "Run from inside a git clone, or pass --repo-root and --repo-url.",
+            ));
+            repo_root.unwrap_or_else(|| {
+                env::current_dir()
+                    .ok()
+                    .and_then(|p| p.to_str().map(String::from))
+                    .unwrap_or_default()
+            })
+        }
+    };
+
+    checks.extend(check_junit_files(&repo_root, junit_paths, &codeowners_path));
+    checks.push(check_codeowners(&repo_root, &codeowners_path));
+    checks.push(check_ci_platform(&StableBranches::new(&stable_branches)?));
+    checks.push(check_token(org_url_slug, token).await);
+
+    print_checks(&checks);
+
+    if checks
+        .iter()
+        .any(|check| check.status == DoctorCheckStatus::Fail)
+    {
+        Ok(EXIT_FAILURE)
+    } else {
+        Ok(EXIT_SUCCESS)
+    }
+}
+
+fn check_junit_files(
+    repo_root: &str,
+    junit_paths: Vec<String>,
+    codeowners_path: &Option<String>,
+) -> Vec<DoctorCheck> {
+    if junit_paths.is_empty() {
+        return vec![DoctorCheck::warn(
+            "junit paths",
+            "no --junit-paths given",
+            "Pass the same --junit-paths as to upload to check that they match files.",
+        )];
+    }
+
+    let junit_paths = match interpolate_env_vars_in_all(junit_paths, "--junit-paths") {
+        Ok(junit_paths) => junit_paths,
+        Err(e) => {
+            return vec![DoctorCheck::fail(
+                "junit paths",
+                e.to_string(),
+                "Set the environment variables that --junit-paths use.",
+            )]
+        }
+    };
+    let junit_paths = junit_paths
+        .into_iter()
+        .map(JunitReportFileWithStatus::from)
+        .collect::<Vec<_>>();
+    let file_set_builder = match FileSetBuilder::build_file_sets(
+        repo_root,
+        &junit_paths,
+        &None,
+        codeowners_path,
+        None,
+        false,
+        None,
+    ) {
+        Ok(file_set_builder) => file_set_builder,
+        Err(e) => {
+            return vec![DoctorCheck::fail(
+                "junit paths",
+                e.to_string(),
+                "Check that --junit-paths are valid globs.",
+            )]
+        }
+    };
+    if file_set_builder.no_files_found() {
+        return vec![DoctorCheck::fail(
+            "junit paths",
+            "no files match --junit-paths",
+            "Run the tests first, and check that the globs are relative to the repo root.",
+        )];
+    }
+
+    let junit_files_check = DoctorCheck::pass(
+        "junit paths",
+        format!("{} file(s) matched", file_set_builder.count()),
+    );
+
+    let unparsable_files = file_set_builder
+        .file_sets()
+        .iter()
+        .flat_map(|file_set| &file_set.files)
+        .filter(|bundled_file| {
+            let mut junit_parser = JunitParser::new();
+            File::open(bundled_file.read_path())
+                .map_err(anyhow::Error::from)
+                .and_then(|file| junit_parser.parse(BufReader::new(file)))
+                .is_err()
+        })
+        .map(|bundled_file| bundled_file.get_print_path().to_string())
+        .collect::<Vec<_>>();
+    let junit_parse_check = if unparsable_files.is_empty() {
+        DoctorCheck::pass("junit parsing", "all matched files parse")
+    } else {
+        DoctorCheck::fail(
+            "junit parsing",
+            format!("could not parse {}", unparsable_files.join(", ")),
+            "Run `validate` with the same --junit-paths for details.",
+        )
+    };
+
+    vec![junit_files_check, junit_parse_check]
+}
+
+fn check_codeowners(repo_root: &str, codeowners_path: &Option<String>) -> DoctorCheck {
+    match CodeOwners::find_file(repo_root, codeowners_path) {
+        Some(CodeOwners {
+            path,
+            owners: Some(_),
+        }) => DoctorCheck::pass("CODEOWNERS", format!("found {:?}", path)),
+        Some(CodeOwners { path, owners: None }) => DoctorCheck::fail(
+            "CODEOWNERS",
+            format!("could not parse {:?}", path),
+            "Fix the syntax of the CODEOWNERS file, or pass --codeowners-path.",
+        ),
+        None => DoctorCheck::warn(
+            "CODEOWNERS",
+            "not found",
+            "Add a CODEOWNERS file, or pass --codeowners-path, to see owners of flaky tests.",
+        ),
+    }
+}
+
+fn check_ci_platform(stable_branches: &StableBranches) -> DoctorCheck {
+    let env_vars: EnvVars = env::vars().collect();
+    let mut env_parser = EnvParser::with_stable_branches(stable_branches.clone());
+    env_parser.parse(&env_vars);
+    let ci_info = env_parser
+        .into_ci_info_parser()
+        .map(|ci_info_parser| ci_info_parser.info_ci_info());
+    let platform = ci_info
+        .as_ref()
+        .map(|ci_info| ci_info.platform)
+        .unwrap_or(CIPlatform::Unknown);
+    if platform == CIPlatform::Unknown {
+        DoctorCheck::warn(
+            "CI platform",
+            "not detected",
+            "Expected when running locally. In CI, run `env-info` to see which variables are read.",
+        )
+    } else {
+        let branch_class = ci_info
+            .and_then(|ci_info| ci_info.branch_class)
+            .map(|branch_class| format!(", branch class {}", branch_class.to_string()))
+            .unwrap_or_default();
+        DoctorCheck::pass(
+            "CI platform",
+            format!("{}{}", platform.to_string(), branch_class),
+        )
+    }
+}
+
+async fn check_token(org_url_slug: Option<String>, token: Option<String>) -> DoctorCheck {
+    let (org_url_slug, token) = match (org_url_slug, token) {
+        (Some(org_url_slug), Some(token)) => (org_url_slug, token),
+        _ => {
+            return DoctorCheck::warn(
+                "API token",
+                "skipped, --org-url-slug and --token are needed",
+                "Pass --org-url-slug and --token, or set TRUNK_API_TOKEN.",
+            )
+        }
+    };
+
+    let api_client = match ApiClient::new(&token) {
+        Ok(api_client) => api_client,
+        Err(e) => {
+            return DoctorCheck::fail("API token", e.to_string(), "Check the value of --token.")
+        }
+    };
+    match api_client
+        .validate_token_org(&ValidateTokenOrgRequest {
+            org_url_slug: org_url_slug.clone(),
+        })
+        .await
+    {
+        Ok(response) if response.org_url_slug == org_url_slug => {
+            DoctorCheck::pass("API token", format!("valid for {}", org_url_slug))
+        }
+        Ok(response) => DoctorCheck::fail(
+            "API token",
+            format!("token belongs to {}", response.org_url_slug),
+            "Set --org-url-slug to the organization of the token, or use that organization's token.",
+        ),
+        Err(e) => DoctorCheck::fail(
+            "API token",
+            format!("{:#}", e),
+            "Check the token and that Trunk's API can be reached from this machine.",
+        ),
+    }
+}
+
+fn print_checks(checks: &[DoctorCheck]) {
+    let name_width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or_default();
+    for check in checks {
+        println!(
+            "{}  {:<width$}  {}",
+            check.status.colored(),
+            check.name,
+            check.detail,
+            width = name_width
+        );
+        if let Some(hint) = check.hint {
+            println!("      {:<width$}  {}", "", hint, width = name_width);
+        }
+    }
+}
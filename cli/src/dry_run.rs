@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use bundle::{BundleMeta, FileDecision, QuarantineOutcome};
+use context::junit::parser::JunitParser;
+use quick_junit::{NonSuccessKind, TestCaseStatus};
+
+pub const DRY_RUN_BUNDLE_FILE_NAME: &str = "bundle.tar.zstd";
+pub const DRY_RUN_SUMMARY_FILE_NAME: &str = "summary.json";
+pub const DRY_RUN_FILE_DECISIONS_FILE_NAME: &str = "file_decisions.json";
+
+/// Creates the output directory of a dry run. An existing directory must be empty, unless `force`
+/// is set, so that the outputs of two runs are never mixed.
+pub fn prepare_dry_run_output_dir<T: AsRef<Path>>(dir: T, force: bool) -> anyhow::Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir).map_err(|e| {
+        anyhow::anyhow!("Failed to create dry run output directory {:?}: {}", dir, e)
+    })?;
+    if !force && std::fs::read_dir(dir)?.next().is_some() {
+        return Err(anyhow::anyhow!(
+            "Dry run output directory {:?} is not empty, pass --force to write to it anyway",
+            dir
+        ));
+    }
+    Ok(())
+}
+
+/// Writes why each file matched by the junit globs was bundled or not next to the dry run bundle.
+pub fn write_file_decisions<T: AsRef<Path>>(
+    dir: T,
+    file_decisions: &[FileDecision],
+) -> anyhow::Result<PathBuf> {
+    let path = dir.as_ref().join(DRY_RUN_FILE_DECISIONS_FILE_NAME);
+    std::fs::write(&path, serde_json::to_string_pretty(file_decisions)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write file decisions {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+/// What a dry run would have uploaded. Everything is sorted so that the summaries of two runs can
+/// be diffed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRunSummary {
+    pub files_per_glob: BTreeMap<String, usize>,
+    /// Files matched by the globs that were skipped for not being junit
+    pub skipped_non_junit_files: usize,
+    pub tests_per_status: BTreeMap<&'static str, usize>,
+    pub repo: BTreeMap<&'static str, String>,
+    pub quarantine_outcome: QuarantineOutcome,
+    /// `parent name/name` of each quarantined test
+    pub quarantined_tests: Vec<String>,
+    pub bundle_size_bytes: u64,
+}
+
+impl DryRunSummary {
+    pub fn new(meta: &BundleMeta, bundle_size_bytes: u64) -> Self {
+        let base_props = &meta.base_props;
+
+        let mut files_per_glob = BTreeMap::new();
+        for file_set in &base_props.file_sets {
+            *files_per_glob.entry(file_set.glob.clone()).or_default() += file_set.files.len();
+        }
+
+        let mut tests_per_status: BTreeMap<&'static str, usize> =
+            ["success", "failure", "error", "skipped"]
+                .into_iter()
+                .map(|status| (status, 0))
+                .collect();
+        for bundled_file in base_props
+            .file_sets
+            .iter()
+            .flat_map(|file_set| &file_set.files)
+            .filter(|bundled_file| {
+                bundled_file.path.starts_with("junit/") && bundled_file.dedup_of.is_none()
+            })
+        {
+            let mut junit_parser = JunitParser::new();
+            let parsed = File::open(bundled_file.read_path())
+                .map_err(anyhow::Error::from)
+                .and_then(|file| junit_parser.parse(BufReader::new(file)));
+            if let Err(e) = parsed {
+                log::warn!(
+                    "Could not count the tests of {}: {}",
+                    bundled_file.get_print_path(),
+                    e
+                );
+                continue;
+            }
+            for test_case in junit_parser
+                .into_reports()
+                .iter()
+                .flat_map(|report| &report.test_suites)
+                .flat_map(|test_suite| &test_suite.test_cases)
+            {
+                *tests_per_status
+                    .entry(status_name(&test_case.status))
+                    .or_default() += 1;
+            }
+        }
+
+        let bundle_repo = &base_props.repo;
+        let repo = BTreeMap::from([
+            ("host", bundle_repo.repo.host.clone()),
+            ("owner", bundle_repo.repo.owner.clone()),
+            ("name", bundle_repo.repo.name.clone()),
+            ("url", bundle_repo.repo_url.clone()),
+            ("head_sha", bundle_repo.repo_head_sha.clone()),
+            ("head_branch", bundle_repo.repo_head_branch.clone()),
+            (
+                "head_commit_epoch",
+                bundle_repo.repo_head_commit_epoch.to_string(),
+            ),
+            (
+                "head_author_name",
+                bundle_repo.repo_head_author_name.clone(),
+            ),
+            (
+                "head_author_email",
+                bundle_repo.repo_head_author_email.clone(),
+            ),
+        ]);
+
+        let mut quarantined_tests = base_props
+            .quarantined_tests
+            .iter()
+            .map(|test| format!("{}/{}", test.parent_name, test.name))
+            .collect::<Vec<_>>();
+        quarantined_tests.sort();
+
+        Self {
+            files_per_glob,
+            skipped_non_junit_files: meta.junit_props.num_skipped_non_junit_files,
+            tests_per_status,
+            repo,
+            quarantine_outcome: meta.quarantine_outcome.clone().into(),
+            quarantined_tests,
+            bundle_size_bytes,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "files_per_glob": self.files_per_glob,
+            "skipped_non_junit_files": self.skipped_non_junit_files,
+            "tests_per_status": self.tests_per_status,
+            "repo": self.repo,
+            "quarantine_outcome": self.quarantine_outcome,
+            "quarantined_tests": self.quarantined_tests,
+            "bundle_size_bytes": self.bundle_size_bytes,
+        })
+    }
+
+    /// Writes the summary as `summary.json` into the output directory of a dry run.
+    pub fn write_to_dir<T: AsRef<Path>>(&self, dir: T) -> anyhow::Result<PathBuf> {
+        let path = dir.as_ref().join(DRY_RUN_SUMMARY_FILE_NAME);
+        std::fs::write(&path, serde_json::to_string_pretty(&self.to_json())?)
+            .map_err(|e| anyhow::anyhow!("Failed to write dry run summary {:?}: {}", path, e))?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for DryRunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Dry run summary:")?;
+        writeln!(f, "  Files per glob:")?;
+        for (glob, count) in &self.files_per_glob {
+            writeln!(f, "    {}: {}", glob, count)?;
+        }
+        if self.skipped_non_junit_files > 0 {
+            writeln!(
+                f,
+                "  Skipped non-junit files: {}",
+                self.skipped_non_junit_files
+            )?;
+        }
+        writeln!(f, "  Tests per status:")?;
+        for (status, count) in &self.tests_per_status {
+            writeln!(f, "    {}: {}", status, count)?;
+        }
+        writeln!(f, "  Repo:")?;
+        for (field, value) in &self.repo {
+            writeln!(f, "    {}: {}", field, value)?;
+        }
+        writeln!(f, "  Quarantine: {}", self.quarantine_outcome)?;
+        for test in &self.quarantined_tests {
+            writeln!(f, "    quarantined: {}", test)?;
+        }
+        write!(f, "  Bundle size: {} bytes", self.bundle_size_bytes)
+    }
+}
+
+fn status_name(status: &TestCaseStatus) -> &'static str {
+    match status {
+        TestCaseStatus::Success { .. } => "success",
+        TestCaseStatus::NonSuccess {
+            kind: NonSuccessKind::Failure,
+            ..
+        } => "failure",
+        TestCaseStatus::NonSuccess {
+            kind: NonSuccessKind::Error,
+            ..
+        } => "error",
+        TestCaseStatus::Skipped { .. } => "skipped",
+    }
+}
@@ -0,0 +1,134 @@
+use std::env;
+
+use clap::Args;
+use constants::{ENVS_TO_GET, EXIT_SUCCESS};
+use context::env::{
+    parser::{CIInfo, CIInfoProvenance, CIPlatform, EnvParser, StableBranches},
+    EnvVars,
+};
+
+#[derive(Args, Clone, Debug)]
+pub struct EnvInfoArgs {
+    #[arg(
+        long,
+        value_parser = parse_stable_branch,
+        help = "Stable branch. Repeat for more than one. Prefix with `re:` to match a regex."
+    )]
+    stable_branches: Vec<String>,
+    #[arg(long, help = "Print the environment info as JSON.")]
+    json: bool,
+}
+
+pub(crate) fn parse_stable_branch(stable_branch: &str) -> Result<String, String> {
+    StableBranches::new(&[stable_branch])
+        .map(|_| String::from(stable_branch))
+        .map_err(|e| e.to_string())
+}
+
+pub async fn run_env_info(
+    EnvInfoArgs {
+        stable_branches,
+        json,
+    }: EnvInfoArgs,
+) -> anyhow::Result<i32> {
+    let env_vars: EnvVars = env::vars().collect();
+    let mut env_parser = EnvParser::with_stable_branches(StableBranches::new(&stable_branches)?);
+    env_parser.parse(&env_vars);
+    let ci_info_parser = env_parser
+        .into_ci_info_parser()
+        .ok_or_else(|| anyhow::anyhow!("Failed to parse CI info from environment."))?;
+    let provenance = ci_info_parser.provenance().clone();
+    let ci_info = ci_info_parser.info_ci_info();
+    let unused_env_vars = unused_env_vars(&env_vars, &ci_info, &provenance);
+
+    if json {
+        let fields = ci_info_fields(&ci_info)
+            .into_iter()
+            .map(|(field, value)| {
+                (
+                    field.to_string(),
+                    serde_json::json!({
+                        "value": value,
+                        "env_vars": provenance.get(field).cloned().unwrap_or_default(),
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        let output = serde_json::json!({
+            "platform": ci_info.platform.to_string(),
+            "fields": fields,
+            "branch_class": ci_info.branch_class.map(|c| c.to_string()),
+            "unused_env_vars": unused_env_vars,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("CI platform: {}", ci_info.platform.to_string());
+        println!("CI info:");
+        for (field, value) in ci_info_fields(&ci_info) {
+            let source = provenance
+                .get(field)
+                .map(|env_vars| format!(" (from {})", env_vars.join(", ")))
+                .unwrap_or_default();
+            println!(
+                "  {}: {}{}",
+                field,
+                value.unwrap_or_else(|| String::from("<unset>")),
+                source
+            );
+        }
+        println!(
+            "Branch class: {}",
+            ci_info
+                .branch_class
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| String::from("<unset>"))
+        );
+        if !unused_env_vars.is_empty() {
+            println!("Recognized env vars present but unused:");
+            for env_var in &unused_env_vars {
+                println!("  {}", env_var);
+            }
+        }
+    }
+
+    Ok(EXIT_SUCCESS)
+}
+
+fn ci_info_fields(ci_info: &CIInfo) -> Vec<(&'static str, Option<String>)> {
+    vec![
+        ("job_url", ci_info.job_url.clone()),
+        ("branch", ci_info.branch.clone()),
+        ("pr_number", ci_info.pr_number.map(|n| n.to_string())),
+        ("target_branch", ci_info.target_branch.clone()),
+        (
+            "merge_request_event_type",
+            ci_info
+                .merge_request_event_type
+                .map(|t| Into::<&str>::into(t).to_string()),
+        ),
+        ("actor", ci_info.actor.clone()),
+        ("committer_name", ci_info.committer_name.clone()),
+        ("committer_email", ci_info.committer_email.clone()),
+        ("author_name", ci_info.author_name.clone()),
+        ("author_email", ci_info.author_email.clone()),
+        ("commit_message", ci_info.commit_message.clone()),
+        ("title", ci_info.title.clone()),
+        ("workflow", ci_info.workflow.clone()),
+        ("job", ci_info.job.clone()),
+    ]
+}
+
+fn unused_env_vars(
+    env_vars: &EnvVars,
+    ci_info: &CIInfo,
+    provenance: &CIInfoProvenance,
+) -> Vec<String> {
+    let platform_env_var: &str = ci_info.platform.into();
+    ENVS_TO_GET
+        .iter()
+        .filter(|env_var| env_vars.get(**env_var).is_some_and(|v| !v.is_empty()))
+        .filter(|env_var| ci_info.platform == CIPlatform::Unknown || **env_var != platform_env_var)
+        .filter(|env_var| !provenance.env_vars().any(|v| v == **env_var))
+        .map(|env_var| env_var.to_string())
+        .collect()
+}
@@ -0,0 +1,170 @@
+//! Interpolation of environment variables in paths, so that `--junit-paths` and friends can name
+//! directories like `reports/${MATRIX_NAME}/junit.xml` without relying on the quoting of a shell.
+//!
+//! `${VAR}` is replaced with the value of `VAR`, and `${VAR:-default}` with `default` if `VAR` is
+//! unset or empty. `$$` is a literal `$`, and any other `$` is left as is.
+
+/// Interpolates the environment variables in `value` of `flag`, which names the flag in errors.
+pub fn interpolate_env_vars<T: AsRef<str>>(value: T, flag: &str) -> anyhow::Result<String> {
+    interpolate(value.as_ref(), flag, |name| std::env::var(name).ok())
+}
+
+pub fn interpolate_env_vars_in_all(values: Vec<String>, flag: &str) -> anyhow::Result<Vec<String>> {
+    values
+        .into_iter()
+        .map(|value| interpolate_env_vars(value, flag))
+        .collect()
+}
+
+fn interpolate<F: Fn(&str) -> Option<String>>(
+    value: &str,
+    flag: &str,
+    lookup: F,
+) -> anyhow::Result<String> {
+    let mut interpolated = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(dollar) = rest.find('$') {
+        interpolated.push_str(&rest[..dollar]);
+        rest = &rest[dollar..];
+        if let Some(after_escape) = rest.strip_prefix("$$") {
+            interpolated.push('$');
+            rest = after_escape;
+            continue;
+        }
+        let Some(after_brace) = rest.strip_prefix("${") else {
+            interpolated.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let Some(close) = after_brace.find('}') else {
+            return Err(anyhow::anyhow!(
+                "Unterminated `${{` in {} value {:?}. Write `$$` for a literal `$`.",
+                flag,
+                value
+            ));
+        };
+        let expression = &after_brace[..close];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if !is_env_var_name(name) {
+            return Err(anyhow::anyhow!(
+                "Invalid environment variable name {:?} in {} value {:?}",
+                name,
+                flag,
+                value
+            ));
+        }
+        match (lookup(name).filter(|var| !var.is_empty()), default) {
+            (Some(var), _) => interpolated.push_str(&var),
+            (None, Some(default)) => interpolated.push_str(default),
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Environment variable {} in {} value {:?} is not set. Set it, or give it a default with `${{{}:-default}}`.",
+                    name,
+                    flag,
+                    value,
+                    name
+                ));
+            }
+        }
+        rest = &after_brace[close + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "MATRIX_NAME" => Some(String::from("linux")),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolates_env_vars() {
+        assert_eq!(
+            interpolate("reports/${MATRIX_NAME}/junit.xml", "--junit-paths", lookup).unwrap(),
+            "reports/linux/junit.xml"
+        );
+        assert_eq!(
+            interpolate("${MATRIX_NAME}-${MATRIX_NAME}", "--junit-paths", lookup).unwrap(),
+            "linux-linux"
+        );
+        assert_eq!(
+            interpolate("reports/**/*.xml", "--junit-paths", lookup).unwrap(),
+            "reports/**/*.xml"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_default() {
+        assert_eq!(
+            interpolate("reports/${UNSET:-all}/*.xml", "--junit-paths", lookup).unwrap(),
+            "reports/all/*.xml"
+        );
+        assert_eq!(
+            interpolate("reports/${EMPTY:-all}/*.xml", "--junit-paths", lookup).unwrap(),
+            "reports/all/*.xml"
+        );
+        assert_eq!(
+            interpolate("reports/${MATRIX_NAME:-all}/*.xml", "--junit-paths", lookup).unwrap(),
+            "reports/linux/*.xml"
+        );
+        assert_eq!(
+            interpolate("reports/${UNSET:-}*.xml", "--junit-paths", lookup).unwrap(),
+            "reports/*.xml"
+        );
+    }
+
+    #[test]
+    fn escapes_dollar() {
+        assert_eq!(
+            interpolate("reports/$${MATRIX_NAME}/$$x", "--junit-paths", lookup).unwrap(),
+            "reports/${MATRIX_NAME}/$x"
+        );
+        assert_eq!(
+            interpolate("reports/$MATRIX_NAME/$", "--junit-paths", lookup).unwrap(),
+            "reports/$MATRIX_NAME/$"
+        );
+    }
+
+    #[test]
+    fn names_unset_env_var_and_flag() {
+        let error = interpolate("reports/${UNSET}/*.xml", "--bazel-bep-path", lookup)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with(
+            r#"Environment variable UNSET in --bazel-bep-path value "reports/${UNSET}/*.xml" is not set"#
+        ));
+        assert!(interpolate("reports/${EMPTY}/*.xml", "--junit-paths", lookup).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_interpolation() {
+        assert!(
+            interpolate("reports/${MATRIX_NAME/*.xml", "--junit-paths", lookup)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Unterminated `${` in --junit-paths")
+        );
+        assert!(interpolate("reports/${}/*.xml", "--junit-paths", lookup)
+            .unwrap_err()
+            .to_string()
+            .starts_with(r#"Invalid environment variable name "" in --junit-paths"#));
+    }
+}
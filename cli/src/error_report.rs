@@ -0,0 +1,41 @@
+//! How a fatal error ends a run: its exit code, and what gets logged and sent to Sentry.
+
+use std::time::Duration;
+
+use api::error_code::ErrorCode;
+
+/// Reports `e` and returns the exit code it ends the run with. Not reaching Trunk's server isn't
+/// allowed to fail the job, so a refused connection only warns and exits successfully.
+pub fn handle_error(e: &anyhow::Error) -> i32 {
+    match (*(e.root_cause())).downcast_ref::<std::io::Error>() {
+        Some(io_error) if io_error.kind() == std::io::ErrorKind::ConnectionRefused => {
+            log::warn!("Could not connect to trunk's server: {:?}", e);
+            exitcode::OK
+        }
+        _ => report_error(e),
+    }
+}
+
+/// Logs a fatal error with its code and sends it to Sentry, fingerprinted by the code so that
+/// unrelated failures with similar messages don't group together. Returns the exit code.
+pub fn report_error(e: &anyhow::Error) -> i32 {
+    let error_code = ErrorCode::of(e);
+    log::error!("Error: {:?}", e);
+    log::error!("error_code={}", error_code);
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("error_code", error_code);
+            scope.set_fingerprint(Some(&["{{ default }}", error_code.as_str()]));
+        },
+        || sentry::capture_message(&format!("{:#}", e), sentry::Level::Error),
+    );
+    flush_sentry();
+    error_code.exit_code()
+}
+
+/// `std::process::exit` skips dropping the guard, which would otherwise flush the events.
+pub fn flush_sentry() {
+    if let Some(client) = sentry::Hub::current().client() {
+        client.flush(Some(Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,134 @@
+use std::time::Instant;
+
+use bundle::BundleMetaHostInfo;
+use sysinfo::{MemoryRefreshKind, System};
+
+const BYTES_PER_GIB: f64 = (1024 * 1024 * 1024) as f64;
+#[cfg(target_os = "linux")]
+const CONTAINER_MARKER_FILES: &[&str] = &["/.dockerenv", "/run/.containerenv"];
+#[cfg(any(target_os = "linux", test))]
+const CONTAINER_CGROUP_MARKERS: &[&str] = &["docker", "kubepods", "containerd", "lxc", "libpod"];
+#[cfg(target_os = "linux")]
+const PROC_1_CGROUP: &str = "/proc/1/cgroup";
+
+/// Only reads what is cheap to read, i.e. never refreshes the process or CPU tables, so that
+/// gathering host info doesn't noticeably slow down uploads.
+pub fn gather_host_info() -> BundleMetaHostInfo {
+    let start = Instant::now();
+
+    let mut system = System::new();
+    system.refresh_memory_specifics(MemoryRefreshKind::new().with_ram());
+    let host_info = BundleMetaHostInfo {
+        os_name: System::name(),
+        os_version: System::os_version(),
+        arch: Some(String::from(std::env::consts::ARCH)),
+        num_logical_cores: std::thread::available_parallelism()
+            .ok()
+            .map(|num_logical_cores| num_logical_cores.get()),
+        total_memory_bytes: Some(system.total_memory()).filter(|&total_memory| total_memory > 0),
+        in_container: detect_container(),
+    };
+
+    log::debug!("Gathered host info in {}ms", start.elapsed().as_millis());
+    host_info
+}
+
+/// e.g. `Ubuntu 22.04 (x86_64), 4 cores, 15.6 GiB memory, in container`
+pub fn host_info_summary(host_info: &BundleMetaHostInfo) -> String {
+    let os = match (&host_info.os_name, &host_info.os_version) {
+        (Some(os_name), Some(os_version)) => format!("{} {}", os_name, os_version),
+        (Some(os_name), None) => os_name.clone(),
+        _ => String::from("unknown OS"),
+    };
+    let mut summary = match &host_info.arch {
+        Some(arch) => format!("{} ({})", os, arch),
+        None => os,
+    };
+    if let Some(num_logical_cores) = host_info.num_logical_cores {
+        summary.push_str(&format!(", {} cores", num_logical_cores));
+    }
+    if let Some(total_memory_bytes) = host_info.total_memory_bytes {
+        summary.push_str(&format!(
+            ", {:.1} GiB memory",
+            total_memory_bytes as f64 / BYTES_PER_GIB
+        ));
+    }
+    if host_info.in_container == Some(true) {
+        summary.push_str(", in container");
+    }
+    summary
+}
+
+#[cfg(target_os = "linux")]
+fn detect_container() -> Option<bool> {
+    if CONTAINER_MARKER_FILES
+        .iter()
+        .any(|marker_file| std::path::Path::new(marker_file).exists())
+    {
+        return Some(true);
+    }
+    std::fs::read_to_string(PROC_1_CGROUP)
+        .ok()
+        .map(|cgroup| is_container_cgroup(&cgroup))
+}
+
+/// Containers are only detected on Linux, where they run natively.
+#[cfg(not(target_os = "linux"))]
+fn detect_container() -> Option<bool> {
+    None
+}
+
+/// Under cgroup v2, the cgroup of a containerized process is usually just `0::/`, so this only
+/// detects containers under cgroup v1 and is backed by the marker files.
+#[cfg(any(target_os = "linux", test))]
+fn is_container_cgroup(cgroup: &str) -> bool {
+    cgroup.lines().any(|line| {
+        CONTAINER_CGROUP_MARKERS
+            .iter()
+            .any(|marker| line.contains(marker))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_host_info() {
+        let host_info = BundleMetaHostInfo {
+            os_name: Some(String::from("Ubuntu")),
+            os_version: Some(String::from("22.04")),
+            arch: Some(String::from("x86_64")),
+            num_logical_cores: Some(4),
+            total_memory_bytes: Some(16 * 1024 * 1024 * 1024),
+            in_container: Some(true),
+        };
+        assert_eq!(
+            host_info_summary(&host_info),
+            "Ubuntu 22.04 (x86_64), 4 cores, 16.0 GiB memory, in container"
+        );
+        assert_eq!(
+            host_info_summary(&BundleMetaHostInfo::default()),
+            "unknown OS"
+        );
+    }
+
+    #[test]
+    fn detects_container_cgroups() {
+        assert!(is_container_cgroup(
+            "12:memory:/docker/3f9c2a\n11:cpu:/docker/3f9c2a"
+        ));
+        assert!(is_container_cgroup(
+            "1:name=systemd:/kubepods/besteffort/pod1234"
+        ));
+        assert!(!is_container_cgroup("0::/init.scope"));
+        assert!(!is_container_cgroup("12:memory:/user.slice"));
+    }
+
+    #[test]
+    fn gathers_host_info() {
+        let host_info = gather_host_info();
+        assert_eq!(host_info.arch.as_deref(), Some(std::env::consts::ARCH));
+        assert!(host_info.num_logical_cores.unwrap_or(1) > 0);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fs::File, io::BufReader};
+
+use bundle::read_tarball_checksums;
+use clap::Args;
+use colored::Colorize;
+use constants::{EXIT_FAILURE, EXIT_SUCCESS};
+
+#[derive(Args, Clone, Debug)]
+pub struct InspectArgs {
+    #[arg(help = "Path to the bundle, a .tar.zstd file created by the CLI.")]
+    bundle_path: String,
+    #[arg(
+        long,
+        help = "Recompute the checksum of each bundled file and report files that do not match meta.json."
+    )]
+    verify: bool,
+}
+
+pub async fn run_inspect(
+    InspectArgs {
+        bundle_path,
+        verify,
+    }: InspectArgs,
+) -> anyhow::Result<i32> {
+    let file = File::open(&bundle_path)
+        .map_err(|e| anyhow::anyhow!("Failed to open bundle {}: {}", bundle_path, e))?;
+    let (meta, checksums) = read_tarball_checksums(BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("Failed to read bundle {}: {}", bundle_path, e))?;
+    let base_props = meta.base_props();
+
+    println!("Bundle: {}", bundle_path);
+    println!("  Organization: {}", base_props.org);
+    println!("  CLI version: {}", base_props.cli_version);
+
+    let mut num_mismatches = 0;
+    for file_set in &base_props.file_sets {
+        for bundled_file in &file_set.files {
+            if let Some(dedup_of) = &bundled_file.dedup_of {
+                println!(
+                    "  {} ({}) copy of {}",
+                    bundled_file.path,
+                    bundled_file.get_print_path(),
+                    dedup_of
+                );
+                continue;
+            }
+            let expected = bundled_file.sha256.as_deref().unwrap_or("none");
+            println!(
+                "  {} ({}) sha256: {}",
+                bundled_file.path,
+                bundled_file.get_print_path(),
+                expected
+            );
+            if let Some(size_delta) = bundled_file.junit_sanitized_size_delta {
+                println!("    sanitized while bundling ({:+} bytes)", size_delta);
+            }
+            if !verify {
+                continue;
+            }
+            match (
+                bundled_file.sha256.as_ref(),
+                checksums.get(&bundled_file.path),
+            ) {
+                (_, None) => {
+                    num_mismatches += 1;
+                    println!("    {} missing from the bundle", "✖".red());
+                }
+                (None, Some(_)) => {
+                    println!("    {} no checksum recorded, skipping", "⚠".yellow());
+                }
+                (Some(expected), Some(actual)) if expected == actual => {
+                    println!("    {} checksum matches", "✔".green());
+                }
+                (Some(_), Some(actual)) => {
+                    num_mismatches += 1;
+                    println!("    {} checksum mismatch, found {}", "✖".red(), actual);
+                }
+            }
+        }
+    }
+
+    if !verify {
+        return Ok(EXIT_SUCCESS);
+    }
+
+    if num_mismatches > 0 {
+        println!(
+            "{} bundled file(s) do not match the checksums in meta.json",
+            num_mismatches
+        );
+        Ok(EXIT_FAILURE)
+    } else {
+        println!("All bundled files match the checksums in meta.json");
+        Ok(EXIT_SUCCESS)
+    }
+}
@@ -1,7 +1,35 @@
+pub mod auth;
+pub mod bin_to_junit_command;
+pub mod cli_version;
+pub mod completions_command;
+pub mod config_file;
 pub mod context;
+pub mod context_command;
 pub mod context_quarantine;
+pub mod converter;
+pub mod datadog_export;
+pub mod doctor_command;
+pub mod dry_run;
+pub mod env_info_command;
+pub mod env_interpolation;
+pub mod error_report;
+pub mod host_info;
+pub mod inspect_command;
+pub mod local_flake_detection;
+pub mod multi_repo;
+pub mod phase_timer;
+pub mod pr_comment;
+pub mod preflight;
 pub mod print;
+pub mod quarantine_audit;
 pub mod quarantine_command;
+pub mod shutdown;
+pub mod status_command;
+pub mod summary;
 pub mod test_command;
+pub mod test_command_stats;
+pub mod upload;
 pub mod upload_command;
+pub mod upload_journal;
+pub mod validate_baseline;
 pub mod validate_command;
@@ -0,0 +1,407 @@
+//! Experimental quarantining of flaky tests from their history in a local state file, for teams
+//! that run offline and upload later. It is kept apart from the quarantining by Trunk: locally
+//! quarantined tests are recorded on their own in the bundle meta and never reported to Trunk as
+//! quarantined.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use bundle::{BundleMeta, BundleMetaLocallyQuarantinedTest, Test};
+use colored::Colorize;
+use constants::EXIT_SUCCESS;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::context_quarantine::FailedTestsExtractor;
+
+/// Number of runs kept in the history of each test. Tests that were part of none of them are
+/// forgotten.
+pub const LOCAL_FLAKE_HISTORY_LEN: usize = 10;
+const LOCAL_FLAKE_STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalRunStatus {
+    Pass,
+    Fail,
+    /// Failed, then passed on a retry within the same run
+    Recovered,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalTestHistory {
+    pub name: String,
+    pub parent_name: String,
+    /// Number of the last run the test was part of
+    pub last_run: u64,
+    /// Statuses of the last runs the test was part of, oldest first
+    pub runs: Vec<LocalRunStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlakyPattern {
+    /// Failed and passed on a retry within a single run
+    RecoveredOnRetry,
+    /// Went from failing to passing or back more than once across runs
+    Alternating,
+}
+
+impl FlakyPattern {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlakyPattern::RecoveredOnRetry => "recovered_on_retry",
+            FlakyPattern::Alternating => "alternating",
+        }
+    }
+}
+
+impl LocalTestHistory {
+    pub fn flaky_pattern(&self) -> Option<FlakyPattern> {
+        if self.runs.contains(&LocalRunStatus::Recovered) {
+            return Some(FlakyPattern::RecoveredOnRetry);
+        }
+        // a single change is a test that broke or got fixed
+        let num_changes = self
+            .runs
+            .windows(2)
+            .filter(|runs| runs[0] != runs[1])
+            .count();
+        (num_changes > 1).then_some(FlakyPattern::Alternating)
+    }
+}
+
+/// Contents of the state file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalFlakeState {
+    pub version: u32,
+    pub num_runs: u64,
+    /// History of each test, by ID
+    pub tests: BTreeMap<String, LocalTestHistory>,
+}
+
+impl Default for LocalFlakeState {
+    fn default() -> Self {
+        Self {
+            version: LOCAL_FLAKE_STATE_VERSION,
+            num_runs: 0,
+            tests: BTreeMap::new(),
+        }
+    }
+}
+
+impl LocalFlakeState {
+    pub fn record_run<'a, I: IntoIterator<Item = (&'a Test, LocalRunStatus)>>(&mut self, run: I) {
+        self.num_runs += 1;
+        for (test, status) in run {
+            let history = self.tests.entry(test.id.clone()).or_default();
+            history.name.clone_from(&test.name);
+            history.parent_name.clone_from(&test.parent_name);
+            history.last_run = self.num_runs;
+            history.runs.push(status);
+            let num_expired_runs = history.runs.len().saturating_sub(LOCAL_FLAKE_HISTORY_LEN);
+            history.runs.drain(..num_expired_runs);
+        }
+        let num_runs = self.num_runs;
+        self.tests
+            .retain(|_, history| history.last_run + LOCAL_FLAKE_HISTORY_LEN as u64 > num_runs);
+    }
+}
+
+/// Records this run in the state file at `state_path`, and returns the failed tests Trunk did not
+/// quarantine whose history matches a flaky pattern.
+pub fn detect_local_flakes<T: AsRef<Path>>(
+    state_path: T,
+    failed_tests_extractor: &FailedTestsExtractor,
+    quarantined_tests: &[Test],
+) -> anyhow::Result<Vec<BundleMetaLocallyQuarantinedTest>> {
+    let quarantined_ids: HashSet<&str> = quarantined_tests
+        .iter()
+        .map(|test| test.id.as_str())
+        .collect();
+    let mut locally_quarantined_tests = Vec::new();
+    update_state(state_path.as_ref(), |state| {
+        state.record_run(run_statuses(failed_tests_extractor));
+        locally_quarantined_tests = failed_tests_extractor
+            .failed_tests()
+            .iter()
+            .filter(|test| !quarantined_ids.contains(test.id.as_str()))
+            .filter_map(|test| {
+                let flaky_pattern = state.tests.get(&test.id)?.flaky_pattern()?;
+                Some(BundleMetaLocallyQuarantinedTest {
+                    id: test.id.clone(),
+                    name: test.name.clone(),
+                    parent_name: test.parent_name.clone(),
+                    reason: String::from(flaky_pattern.as_str()),
+                })
+            })
+            .collect();
+    })?;
+    Ok(locally_quarantined_tests)
+}
+
+/// Runs [`detect_local_flakes`] and records its tests in `meta`. Returns `exit_code`, or success
+/// if every failure Trunk did not quarantine is locally quarantined. Problems with the state file
+/// are logged, as the local flake detection is never a reason to fail.
+pub fn apply_local_flake_detection(
+    state_path: &str,
+    meta: &mut BundleMeta,
+    failed_tests_extractor: &FailedTestsExtractor,
+    exit_code: i32,
+) -> i32 {
+    let quarantined_tests = &meta.base_props.quarantined_tests;
+    let locally_quarantined_tests =
+        match detect_local_flakes(state_path, failed_tests_extractor, quarantined_tests) {
+            Ok(locally_quarantined_tests) => locally_quarantined_tests,
+            Err(e) => {
+                log::warn!(
+                    "{}",
+                    format!("Local flake detection with {} failed: {:#}", state_path, e).yellow()
+                );
+                return exit_code;
+            }
+        };
+    for test in &locally_quarantined_tests {
+        log::info!(
+            "{} -> {} [LOCALLY QUARANTINED: {}] (id: {})",
+            test.parent_name,
+            test.name,
+            test.reason,
+            test.id
+        );
+    }
+    let num_unquarantined = failed_tests_extractor
+        .failed_tests()
+        .iter()
+        .filter(|test| {
+            !quarantined_tests
+                .iter()
+                .any(|quarantined_test| quarantined_test.id == test.id)
+        })
+        .count();
+    let exit_code = if exit_code != EXIT_SUCCESS
+        && num_unquarantined > 0
+        && locally_quarantined_tests.len() == num_unquarantined
+    {
+        log::warn!(
+            "{}",
+            "All test failures not quarantined by Trunk are flaky by their local history, overriding exit code to be exit_success. They are not quarantined by Trunk."
+                .yellow()
+        );
+        EXIT_SUCCESS
+    } else {
+        exit_code
+    };
+    meta.locally_quarantined_tests = locally_quarantined_tests;
+    exit_code
+}
+
+fn run_statuses(
+    failed_tests_extractor: &FailedTestsExtractor,
+) -> impl Iterator<Item = (&Test, LocalRunStatus)> {
+    let failed_ids: HashSet<&str> = failed_tests_extractor
+        .failed_tests()
+        .iter()
+        .map(|test| test.id.as_str())
+        .collect();
+    let recovered_ids: HashSet<&str> = failed_tests_extractor
+        .recovered_tests()
+        .iter()
+        .map(|test| test.id.as_str())
+        .collect();
+    failed_tests_extractor
+        .parsed_tests()
+        .iter()
+        .map(move |test| {
+            let status = if failed_ids.contains(test.id.as_str()) {
+                LocalRunStatus::Fail
+            } else if recovered_ids.contains(test.id.as_str()) {
+                LocalRunStatus::Recovered
+            } else {
+                LocalRunStatus::Pass
+            };
+            (test, status)
+        })
+}
+
+/// Concurrent runs with the same state file take turns through an advisory lock on a file next
+/// to it, so that none of their runs is lost.
+fn update_state<F: FnOnce(&mut LocalFlakeState)>(state_path: &Path, f: F) -> anyhow::Result<()> {
+    let mut lock_path = state_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+    lock_file.lock_exclusive()?;
+
+    let mut state = read_state(state_path)?;
+    f(&mut state);
+    write_state(state_path, &state)?;
+
+    lock_file.unlock()?;
+    Ok(())
+}
+
+/// A state file that can't be parsed is moved aside to start over, instead of failing every run.
+fn read_state(state_path: &Path) -> anyhow::Result<LocalFlakeState> {
+    let contents = match fs::read(state_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(LocalFlakeState::default()),
+        Err(e) => return Err(e.into()),
+    };
+    match serde_json::from_slice::<LocalFlakeState>(&contents) {
+        Ok(state) if state.version == LOCAL_FLAKE_STATE_VERSION => Ok(state),
+        Ok(state) => {
+            let corrupt_path = move_aside(state_path)?;
+            log::warn!(
+                "Local flake detection state file {} is of version {}, starting over. It was moved to {}.",
+                state_path.display(),
+                state.version,
+                corrupt_path.display()
+            );
+            Ok(LocalFlakeState::default())
+        }
+        Err(e) => {
+            let corrupt_path = move_aside(state_path)?;
+            log::warn!(
+                "Local flake detection state file {} is corrupt ({}), starting over. It was moved to {}.",
+                state_path.display(),
+                e,
+                corrupt_path.display()
+            );
+            Ok(LocalFlakeState::default())
+        }
+    }
+}
+
+fn move_aside(state_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut corrupt_path = state_path.as_os_str().to_owned();
+    corrupt_path.push(".corrupt");
+    let corrupt_path = PathBuf::from(corrupt_path);
+    fs::rename(state_path, &corrupt_path)?;
+    Ok(corrupt_path)
+}
+
+/// Replaces the state file with a new one, so that an interrupted run can't leave it half written.
+fn write_state(state_path: &Path, state: &LocalFlakeState) -> anyhow::Result<()> {
+    let dir = state_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut temp_file, state)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(state_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(id: &str) -> Test {
+        Test {
+            name: format!("name_{id}"),
+            parent_name: String::from("suite"),
+            class_name: None,
+            file: None,
+            id: String::from(id),
+            timestamp_millis: None,
+        }
+    }
+
+    fn history(runs: &[LocalRunStatus]) -> LocalTestHistory {
+        LocalTestHistory {
+            runs: runs.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_flaky_patterns() {
+        use LocalRunStatus::*;
+
+        assert_eq!(history(&[]).flaky_pattern(), None);
+        assert_eq!(history(&[Pass, Pass, Fail]).flaky_pattern(), None);
+        assert_eq!(history(&[Fail, Fail, Pass]).flaky_pattern(), None);
+        assert_eq!(
+            history(&[Fail, Pass, Fail]).flaky_pattern(),
+            Some(FlakyPattern::Alternating)
+        );
+        assert_eq!(
+            history(&[Pass, Fail, Fail, Pass, Fail]).flaky_pattern(),
+            Some(FlakyPattern::Alternating)
+        );
+        assert_eq!(
+            history(&[Pass, Recovered, Pass]).flaky_pattern(),
+            Some(FlakyPattern::RecoveredOnRetry)
+        );
+    }
+
+    #[test]
+    fn bounds_history_and_forgets_old_tests() {
+        let mut state = LocalFlakeState::default();
+        let (a, b) = (test("a"), test("b"));
+        state.record_run([(&a, LocalRunStatus::Fail), (&b, LocalRunStatus::Pass)]);
+        for _ in 0..LOCAL_FLAKE_HISTORY_LEN - 1 {
+            state.record_run([(&a, LocalRunStatus::Pass)]);
+        }
+        assert_eq!(state.tests["a"].runs.len(), LOCAL_FLAKE_HISTORY_LEN);
+        assert_eq!(state.tests["a"].runs[0], LocalRunStatus::Fail);
+        assert!(state.tests.contains_key("b"));
+
+        state.record_run([(&a, LocalRunStatus::Pass)]);
+        assert_eq!(state.tests["a"].runs.len(), LOCAL_FLAKE_HISTORY_LEN);
+        assert!(!state.tests["a"].runs.contains(&LocalRunStatus::Fail));
+        assert!(!state.tests.contains_key("b"));
+        assert_eq!(state.num_runs, LOCAL_FLAKE_HISTORY_LEN as u64 + 1);
+    }
+
+    #[test]
+    fn updates_state_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state_path = temp_dir.path().join("flakes.json");
+        let a = test("a");
+
+        update_state(&state_path, |state| {
+            state.record_run([(&a, LocalRunStatus::Fail)])
+        })
+        .unwrap();
+        update_state(&state_path, |state| {
+            state.record_run([(&a, LocalRunStatus::Pass)])
+        })
+        .unwrap();
+
+        let state = read_state(&state_path).unwrap();
+        assert_eq!(state.num_runs, 2);
+        assert_eq!(
+            state.tests["a"].runs,
+            vec![LocalRunStatus::Fail, LocalRunStatus::Pass]
+        );
+        assert_eq!(state.tests["a"].name, "name_a");
+    }
+
+    #[test]
+    fn recovers_from_corrupt_state_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state_path = temp_dir.path().join("flakes.json");
+        fs::write(&state_path, r#"{"version": 1, "num_runs": 3, "tes"#).unwrap();
+
+        update_state(&state_path, |state| {
+            assert_eq!(*state, LocalFlakeState::default());
+            state.record_run([(&test("a"), LocalRunStatus::Pass)]);
+        })
+        .unwrap();
+
+        assert_eq!(read_state(&state_path).unwrap().num_runs, 1);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("flakes.json.corrupt")).unwrap(),
+            r#"{"version": 1, "num_runs": 3, "tes"#
+        );
+    }
+}
@@ -1,10 +1,22 @@
 use std::{env, io::Write};
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use constants::SENTRY_DSN;
 use trunk_analytics_cli::{
+    bin_to_junit_command::{run_bin_to_junit, BinToJunitArgs},
+    completions_command::{run_completions, CompletionsArgs},
+    config_file::{parse_args, ParsedArgs},
+    context_command::{run_context, ContextArgs},
+    doctor_command::{run_doctor, DoctorArgs},
+    env_info_command::{run_env_info, EnvInfoArgs},
+    error_report::{flush_sentry, handle_error},
+    inspect_command::{run_inspect, InspectArgs},
+    multi_repo::run_multi_repo_upload,
     quarantine_command::{run_quarantine, QuarantineArgs},
+    shutdown::{self, Signal},
+    status_command::{run_status, StatusArgs},
     test_command::{run_test, TestArgs},
+    upload::UploadCancelled,
     upload_command::{run_upload, UploadArgs, UploadRunResult},
     validate_command::{run_validate, ValidateArgs},
 };
@@ -23,8 +35,22 @@ struct Cli {
 
 #[derive(Debug, Subcommand)]
 enum Commands {
+    /// Convert an internal bin of test results to junit XML files
+    BinToJunit(BinToJunitArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+    /// Compute what the CLI derives from test results, for ad-hoc checks
+    Context(ContextArgs),
+    /// Check that the repo, junit files, CODEOWNERS, CI and token are set up for uploads
+    Doctor(DoctorArgs),
+    /// Show what the CLI detects from the current environment
+    EnvInfo(EnvInfoArgs),
+    /// Show the contents of a bundle created by the CLI
+    Inspect(InspectArgs),
     /// Quarantine flaky tests and upload data to Trunk Flaky Tests
     Quarantine(QuarantineArgs),
+    /// Show the most recent uploads to Trunk Flaky Tests for this repo
+    Status(StatusArgs),
     /// Run a test command and upload data to Trunk Flaky Tests
     Test(TestArgs),
     /// Upload data to Trunk Flaky Tests
@@ -51,23 +77,36 @@ fn main() -> anyhow::Result<()> {
         .build()?
         .block_on(async {
             setup_logger()?;
-            let cli = Cli::parse();
-            match run(cli).await {
+            let cli = match parse_args::<Cli, _>(env::args_os())? {
+                ParsedArgs::Parsed(cli) => *cli,
+                ParsedArgs::ShowConfig(config) => {
+                    print!("{}", config);
+                    std::process::exit(exitcode::OK);
+                }
+            };
+            let result = run(cli).await;
+            if let Some(signal) = shutdown::received_signal() {
+                std::process::exit(exit_after_signal(signal, result));
+            }
+            match result {
                 Ok(exit_code) => std::process::exit(exit_code),
-                Err(e) => match (*(e.root_cause())).downcast_ref::<std::io::Error>() {
-                    Some(io_error) if io_error.kind() == std::io::ErrorKind::ConnectionRefused => {
-                        log::warn!("Could not connect to trunk's server: {:?}", e);
-                        std::process::exit(exitcode::OK);
-                    }
-                    _ => {
-                        log::error!("Error: {:?}", e);
-                        std::process::exit(exitcode::SOFTWARE);
-                    }
-                },
+                Err(e) => std::process::exit(handle_error(&e)),
             }
         })
 }
 
+/// Wraps up a run cut short by a signal, whose exit code tells CI it was cancelled rather than
+/// failed. Returns the exit code.
+fn exit_after_signal(signal: Signal, result: anyhow::Result<i32>) -> i32 {
+    match result {
+        Err(e) if !e.is::<UploadCancelled>() => log::error!("Error: {:?}", e),
+        _ => (),
+    }
+    log::warn!("Exiting after {}", signal);
+    flush_sentry();
+    signal.exit_code()
+}
+
 async fn run(cli: Cli) -> anyhow::Result<i32> {
     log::info!(
         "Starting trunk flakytests {} (git={}) rustc={}",
@@ -75,13 +114,35 @@ async fn run(cli: Cli) -> anyhow::Result<i32> {
         env!("VERGEN_GIT_SHA"),
         env!("VERGEN_RUSTC_SEMVER")
     );
+    if let Commands::Quarantine(_) | Commands::Test(_) | Commands::Upload(_) = cli.command {
+        shutdown::handle_signals()?;
+    }
     match cli.command {
-        Commands::Quarantine(quarantine_args) => run_quarantine(quarantine_args).await,
-        Commands::Upload(upload_args) => {
+        Commands::BinToJunit(bin_to_junit_args) => run_bin_to_junit(bin_to_junit_args).await,
+        Commands::Completions(completions_args) => {
+            run_completions(completions_args, Cli::command()).await
+        }
+        Commands::Context(context_args) => run_context(context_args).await,
+        Commands::Doctor(doctor_args) => run_doctor(doctor_args).await,
+        Commands::EnvInfo(env_info_args) => run_env_info(env_info_args).await,
+        Commands::Inspect(inspect_args) => run_inspect(inspect_args).await,
+        Commands::Quarantine(mut quarantine_args) => {
+            quarantine_args.authenticate().await?;
+            let api_client = quarantine_args.api_client()?;
+            run_quarantine(quarantine_args, &api_client).await
+        }
+        Commands::Status(status_args) => run_status(status_args).await,
+        Commands::Upload(mut upload_args) => {
+            upload_args.authenticate().await?;
+            let api_client = upload_args.api_client()?;
+            if let Some(multi_repo_config) = upload_args.multi_repo_config.clone() {
+                return run_multi_repo_upload(&multi_repo_config, upload_args, &api_client).await;
+            }
             let UploadRunResult {
                 exit_code,
                 upload_bundle_error,
-            } = run_upload(upload_args, None, None).await?;
+                ..
+            } = run_upload(upload_args, &api_client, None, None).await?;
             if let Some(upload_bundle_error) = upload_bundle_error {
                 return Err(upload_bundle_error);
             }
@@ -0,0 +1,509 @@
+//! Uploads for meta-repos, which check out several repos side by side and run the tests of all of
+//! them in one job. A `--multi-repo-config` file lists the repos, e.g.
+//!
+//! ```toml
+//! [[repos]]
+//! repo_root = "service-a"
+//! junit_paths = ["target/junit/*.xml"]
+//!
+//! [[repos]]
+//! repo_root = "service-b"
+//! junit_paths = ["test-results/**/*.xml"]
+//! org_url_slug = "other-org"
+//! variant = "linux"
+//! ```
+//!
+//! Each repo gets a bundle of its own, while authentication and the scan of the env vars and host
+//! happen once for all of them.
+
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use api::client::ApiClientTrait;
+use colored::Colorize;
+use constants::GITHUB_EXTERNAL_ID_ENV;
+use futures::{stream, StreamExt};
+use github_actions::{extract_github_external_id, ExternalIdOptions};
+use serde::Deserialize;
+use toml::Spanned;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    context::{gather_debug_props, gather_pre_test_context_with_env_scan, EnvScan},
+    error_report::handle_error,
+    preflight::run_preflight,
+    upload::UploadConfig,
+    upload_command::{run_upload, UploadArgs, UploadRunResult},
+};
+
+/// A repo of a multi-repo upload, whose `repo_root` is resolved against the directory of the
+/// config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiRepoEntry {
+    pub repo_root: String,
+    pub junit_paths: Vec<String>,
+    pub org_url_slug: Option<String>,
+    pub variant: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiRepoConfig {
+    pub path: String,
+    pub entries: Vec<MultiRepoEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultiRepoConfigFile {
+    #[serde(default)]
+    repos: Vec<MultiRepoEntryFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultiRepoEntryFile {
+    repo_root: Spanned<String>,
+    junit_paths: Spanned<Vec<String>>,
+    org_url_slug: Option<Spanned<String>>,
+    variant: Option<Spanned<String>>,
+}
+
+impl MultiRepoConfig {
+    pub fn load<T: AsRef<str>>(path: T) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read multi-repo config {}", path))?;
+        Self::parse(path, &contents)
+    }
+
+    /// Fails with every problem of the file, each at the line and column of the value it is about.
+    pub fn parse<T: AsRef<str>>(path: T, contents: &str) -> anyhow::Result<Self> {
+        let path = String::from(path.as_ref());
+        let config_file: MultiRepoConfigFile = toml::from_str(contents)
+            .with_context(|| format!("Failed to parse multi-repo config {}", path))?;
+        if config_file.repos.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The multi-repo config {} has no [[repos]]",
+                path
+            ));
+        }
+
+        let config_dir = Path::new(&path).parent().unwrap_or(Path::new(""));
+        let mut problems = Vec::new();
+        let mut problem = |span: Range<usize>, i: usize, key: &str, message: String| {
+            problems.push(format!(
+                "{}: repos[{}].{} {}",
+                location(&path, contents, span),
+                i,
+                key,
+                message
+            ));
+        };
+        let mut canonical_repo_roots: Vec<PathBuf> = Vec::new();
+        let mut entries = Vec::with_capacity(config_file.repos.len());
+        for (i, entry) in config_file.repos.into_iter().enumerate() {
+            let repo_root_span = entry.repo_root.span();
+            let repo_root = config_dir.join(entry.repo_root.into_inner());
+            match repo_root.canonicalize() {
+                Ok(canonical_repo_root) if canonical_repo_root.is_dir() => {
+                    if let Some(other) = canonical_repo_roots
+                        .iter()
+                        .position(|other| *other == canonical_repo_root)
+                    {
+                        problem(
+                            repo_root_span,
+                            i,
+                            "repo_root",
+                            format!("is also the repo root of repos[{}]", other),
+                        );
+                    }
+                    canonical_repo_roots.push(canonical_repo_root);
+                }
+                _ => problem(
+                    repo_root_span,
+                    i,
+                    "repo_root",
+                    format!("{:?} is not a directory", repo_root),
+                ),
+            }
+
+            let junit_paths_span = entry.junit_paths.span();
+            let junit_paths = entry.junit_paths.into_inner();
+            if junit_paths.is_empty() {
+                problem(
+                    junit_paths_span,
+                    i,
+                    "junit_paths",
+                    String::from("has no globs"),
+                );
+            } else if junit_paths.iter().any(|junit_path| junit_path.is_empty()) {
+                problem(
+                    junit_paths_span,
+                    i,
+                    "junit_paths",
+                    String::from("has an empty glob"),
+                );
+            }
+
+            let mut non_empty = |key: &str, value: Option<Spanned<String>>| {
+                let value = value?;
+                if value.get_ref().is_empty() {
+                    problem(value.span(), i, key, String::from("is empty"));
+                }
+                Some(value.into_inner())
+            };
+            let org_url_slug = non_empty("org_url_slug", entry.org_url_slug);
+            let variant = non_empty("variant", entry.variant);
+
+            entries.push(MultiRepoEntry {
+                repo_root: repo_root.to_string_lossy().into_owned(),
+                junit_paths,
+                org_url_slug,
+                variant,
+            });
+        }
+
+        if !problems.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid multi-repo config:\n  {}",
+                problems.join("\n  ")
+            ));
+        }
+        Ok(Self { path, entries })
+    }
+}
+
+/// `path:line:column` of the start of `span`, counted from 1 like editors do
+fn location(path: &str, contents: &str, span: Range<usize>) -> String {
+    let before = contents.get(..span.start).unwrap_or(contents);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    format!("{}:{}:{}", path, line, column)
+}
+
+/// How the upload of a repo of a multi-repo upload went
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RepoResult {
+    repo_root: String,
+    org_url_slug: String,
+    exit_code: i32,
+    /// The quarantine outcome of the repo, or why it failed
+    result: String,
+    failed: bool,
+}
+
+impl RepoResult {
+    fn failed(entry: &MultiRepoEntry, org_url_slug: &str, error: &anyhow::Error) -> Self {
+        log::error!("Failed to upload the test results of {}", entry.repo_root);
+        Self {
+            repo_root: entry.repo_root.clone(),
+            org_url_slug: String::from(org_url_slug),
+            exit_code: handle_error(error),
+            result: format!("{:#}", error),
+            failed: true,
+        }
+    }
+}
+
+/// Uploads a bundle for each repo of the multi-repo config at `path`, `--multi-repo-parallel` at a
+/// time, with the options of `upload_args`. A repo that fails to upload doesn't stop the others.
+/// Returns the highest exit code of the repos.
+pub async fn run_multi_repo_upload(
+    path: &str,
+    upload_args: UploadArgs,
+    api_client: &dyn ApiClientTrait,
+) -> anyhow::Result<i32> {
+    let multi_repo_config = MultiRepoConfig::load(path)?;
+    let parallel = upload_args
+        .multi_repo_parallel
+        .map(usize::from)
+        .unwrap_or(1);
+
+    let mut env_scan = EnvScan::gather(&UploadConfig::from(upload_args.clone()));
+    if let Some(external_id) = extract_github_external_id(&ExternalIdOptions::from_env()).await {
+        env_scan
+            .envs
+            .insert(String::from(GITHUB_EXTERNAL_ID_ENV), external_id);
+    }
+    let debug_props = gather_debug_props(upload_args.token.clone());
+
+    // checked once per org, rather than for every repo
+    let mut preflight_errors = BTreeMap::new();
+    if !upload_args.no_preflight {
+        for entry in &multi_repo_config.entries {
+            let org_url_slug = org_url_slug(entry, &upload_args);
+            if preflight_errors.contains_key(org_url_slug) {
+                continue;
+            }
+            let mut upload_config = UploadConfig::from(upload_args.clone());
+            upload_config.org_url_slug = String::from(org_url_slug);
+            preflight_errors.insert(
+                String::from(org_url_slug),
+                run_preflight(&upload_config, api_client).await.err(),
+            );
+        }
+    }
+
+    let repo_results: Vec<RepoResult> = stream::iter(&multi_repo_config.entries)
+        .map(|entry| {
+            let upload_args = &upload_args;
+            let env_scan = &env_scan;
+            let debug_props = &debug_props;
+            let preflight_errors = &preflight_errors;
+            async move {
+                let org_url_slug = org_url_slug(entry, upload_args);
+                if let Some(Some(preflight_error)) = preflight_errors.get(org_url_slug) {
+                    return RepoResult::failed(entry, org_url_slug, preflight_error);
+                }
+                log::info!("Uploading the test results of {}", entry.repo_root);
+                let mut upload_args = upload_args.clone();
+                upload_args.repo_root = Some(entry.repo_root.clone());
+                upload_args.junit_paths = entry.junit_paths.clone();
+                upload_args.org_url_slug = String::from(org_url_slug);
+                if let Some(variant) = entry.variant.as_ref() {
+                    upload_args.variant = Some(variant.clone());
+                }
+                let upload_run_result = match gather_pre_test_context_with_env_scan(
+                    upload_args.clone().into(),
+                    debug_props.clone(),
+                    env_scan.clone(),
+                ) {
+                    Ok(pre_test_context) => {
+                        run_upload(upload_args, api_client, Some(pre_test_context), None).await
+                    }
+                    Err(e) => Err(e),
+                };
+                match upload_run_result {
+                    Ok(UploadRunResult {
+                        exit_code,
+                        quarantine_outcome,
+                        upload_bundle_error: None,
+                        ..
+                    }) => RepoResult {
+                        repo_root: entry.repo_root.clone(),
+                        org_url_slug: String::from(org_url_slug),
+                        exit_code,
+                        result: quarantine_outcome.to_string(),
+                        failed: false,
+                    },
+                    Ok(UploadRunResult {
+                        upload_bundle_error: Some(e),
+                        ..
+                    })
+                    | Err(e) => RepoResult::failed(entry, org_url_slug, &e),
+                }
+            }
+        })
+        .buffered(parallel)
+        .collect()
+        .await;
+
+    println!("{}", repo_results_table(&repo_results));
+    Ok(repo_results
+        .iter()
+        .map(|repo_result| repo_result.exit_code)
+        .max()
+        .unwrap_or_default())
+}
+
+fn org_url_slug<'a>(entry: &'a MultiRepoEntry, upload_args: &'a UploadArgs) -> &'a str {
+    entry
+        .org_url_slug
+        .as_deref()
+        .unwrap_or(&upload_args.org_url_slug)
+}
+
+/// A line per repo, with columns aligned by the width names take in a terminal
+fn repo_results_table(repo_results: &[RepoResult]) -> String {
+    let header = ["Repo", "Org", "Exit code", "Result"];
+    let rows: Vec<[String; 4]> = repo_results
+        .iter()
+        .map(|repo_result| {
+            [
+                repo_result.repo_root.clone(),
+                repo_result.org_url_slug.clone(),
+                repo_result.exit_code.to_string(),
+                // only the first line of an error fits in a table
+                repo_result
+                    .result
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..header.len() - 1)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].width())
+                .chain([header[column].width()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |cells: [&str; 4]| {
+        let mut line = String::new();
+        for (cell, width) in cells.iter().zip(&widths) {
+            line.push_str(cell);
+            line.push_str(&" ".repeat(width - cell.width() + 2));
+        }
+        line.push_str(cells[3]);
+        line
+    };
+
+    let mut lines = vec![format!("  {}", line(header)).bold().to_string()];
+    for (repo_result, row) in repo_results.iter().zip(&rows) {
+        let row = line([&row[0], &row[1], &row[2], &row[3]]);
+        lines.push(if repo_result.failed || repo_result.exit_code != 0 {
+            format!("{} {}", "✖".red(), row.red())
+        } else {
+            format!("{} {}", "✔".green(), row.green())
+        });
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries_relative_to_config_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp_dir.path().join("service-a")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("service-b")).unwrap();
+        let path = temp_dir.path().join("multi-repo.toml");
+
+        let multi_repo_config = MultiRepoConfig::parse(
+            path.to_string_lossy(),
+            r#"
+                [[repos]]
+                repo_root = "service-a"
+                junit_paths = ["target/*.xml"]
+
+                [[repos]]
+                repo_root = "service-b"
+                junit_paths = ["a/*.xml", "b/*.xml"]
+                org_url_slug = "other-org"
+                variant = "linux"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            multi_repo_config.entries,
+            vec![
+                MultiRepoEntry {
+                    repo_root: temp_dir
+                        .path()
+                        .join("service-a")
+                        .to_string_lossy()
+                        .into_owned(),
+                    junit_paths: vec![String::from("target/*.xml")],
+                    org_url_slug: None,
+                    variant: None,
+                },
+                MultiRepoEntry {
+                    repo_root: temp_dir
+                        .path()
+                        .join("service-b")
+                        .to_string_lossy()
+                        .into_owned(),
+                    junit_paths: vec![String::from("a/*.xml"), String::from("b/*.xml")],
+                    org_url_slug: Some(String::from("other-org")),
+                    variant: Some(String::from("linux")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_problems_at_their_location() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp_dir.path().join("service-a")).unwrap();
+        let path = temp_dir.path().join("multi-repo.toml");
+        let path = path.to_string_lossy();
+
+        let error = MultiRepoConfig::parse(
+            &path,
+            [
+                "[[repos]]",
+                "repo_root = \"service-a\"",
+                "junit_paths = []",
+                "",
+                "[[repos]]",
+                "repo_root = \"service-a\"",
+                "junit_paths = [\"*.xml\"]",
+                "variant = \"\"",
+                "",
+                "[[repos]]",
+                "repo_root = \"missing\"",
+                "junit_paths = [\"*.xml\"]",
+            ]
+            .join("\n")
+            .as_str(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            [
+                String::from("Invalid multi-repo config:"),
+                format!("  {path}:3:15: repos[0].junit_paths has no globs"),
+                format!("  {path}:6:13: repos[1].repo_root is also the repo root of repos[0]"),
+                format!("  {path}:8:11: repos[1].variant is empty"),
+                format!(
+                    "  {path}:11:13: repos[2].repo_root {:?} is not a directory",
+                    temp_dir.path().join("missing")
+                ),
+            ]
+            .join("\n")
+        );
+
+        let error = MultiRepoConfig::parse(
+            &path,
+            "[[repos]]\nrepo_root = \"service-a\"\njunit_path = [\"*.xml\"]\n",
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("line 3, column 1"));
+        assert!(MultiRepoConfig::parse(&path, "").is_err());
+    }
+
+    #[test]
+    fn aligns_repo_results_table() {
+        let table = repo_results_table(&[
+            RepoResult {
+                repo_root: String::from("service-a"),
+                org_url_slug: String::from("org"),
+                exit_code: 0,
+                result: String::from("No failures"),
+                failed: false,
+            },
+            RepoResult {
+                repo_root: String::from("服务-b"),
+                org_url_slug: String::from("other-org"),
+                exit_code: 69,
+                result: String::from("Failed to upload\ncaused by"),
+                failed: true,
+            },
+        ]);
+        assert_eq!(
+            console::strip_ansi_codes(&table),
+            [
+                "  Repo       Org        Exit code  Result",
+                "✔ service-a  org        0          No failures",
+                "✖ 服务-b     other-org  69         Failed to upload",
+            ]
+            .join("\n")
+        );
+    }
+}
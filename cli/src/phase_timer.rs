@@ -0,0 +1,146 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadPhase {
+    JunitScanAndParse,
+    QuarantineConfigFetch,
+    CodeownersAssociation,
+    InternalBinGeneration,
+    UploadIntent,
+    TarballCompression,
+    S3Put,
+}
+
+impl fmt::Display for UploadPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UploadPhase::JunitScanAndParse => "junit scan and parse",
+            UploadPhase::QuarantineConfigFetch => "quarantine config fetch",
+            UploadPhase::CodeownersAssociation => "codeowners association",
+            UploadPhase::InternalBinGeneration => "internal bin generation",
+            UploadPhase::UploadIntent => "upload intent",
+            UploadPhase::TarballCompression => "tarball compression",
+            UploadPhase::S3Put => "s3 put",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl UploadPhase {
+    /// The key of the phase in the results file
+    pub fn key(&self) -> &'static str {
+        match self {
+            UploadPhase::JunitScanAndParse => "junit_scan_and_parse",
+            UploadPhase::QuarantineConfigFetch => "quarantine_config_fetch",
+            UploadPhase::CodeownersAssociation => "codeowners_association",
+            UploadPhase::InternalBinGeneration => "internal_bin_generation",
+            UploadPhase::UploadIntent => "upload_intent",
+            UploadPhase::TarballCompression => "tarball_compression",
+            UploadPhase::S3Put => "s3_put",
+        }
+    }
+}
+
+/// Records how long each phase of an upload takes, so slow uploads can be attributed to parsing
+/// or to the network.
+#[derive(Debug, Clone)]
+pub struct PhaseTimer {
+    started_at: Instant,
+    phase_durations: Vec<(UploadPhase, Duration)>,
+}
+
+impl Default for PhaseTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhaseTimer {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            phase_durations: Vec::new(),
+        }
+    }
+
+    /// Records the time elapsed since `phase_start` for `phase`. Recording the same phase more
+    /// than once accumulates its duration.
+    pub fn record(&mut self, phase: UploadPhase, phase_start: Instant) {
+        let elapsed = phase_start.elapsed();
+        if let Some((_, duration)) = self.phase_durations.iter_mut().find(|(p, _)| *p == phase) {
+            *duration += elapsed;
+        } else {
+            self.phase_durations.push((phase, elapsed));
+        }
+    }
+
+    pub fn duration(&self, phase: UploadPhase) -> Option<Duration> {
+        self.phase_durations
+            .iter()
+            .find(|(p, _)| *p == phase)
+            .map(|(_, duration)| *duration)
+    }
+
+    pub fn phase_durations(&self) -> &[(UploadPhase, Duration)] {
+        &self.phase_durations
+    }
+
+    pub fn total(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// One line with the duration of each phase and the total, e.g.
+    /// `junit scan and parse 12ms, s3 put 80ms, total 95ms`.
+    pub fn summary(&self) -> String {
+        self.phase_durations
+            .iter()
+            .map(|(phase, duration)| format!("{} {}ms", phase, duration.as_millis()))
+            .chain(std::iter::once(format!(
+                "total {}ms",
+                self.total().as_millis()
+            )))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The duration of each phase and the total in milliseconds, keyed by [`UploadPhase::key`].
+    pub fn durations_ms(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.phase_durations
+            .iter()
+            .map(|(phase, duration)| (phase.key(), duration))
+            .chain(std::iter::once(("total", &self.total())))
+            .map(|(key, duration)| (key.to_string(), (duration.as_millis() as u64).into()))
+            .collect()
+    }
+
+    pub fn log_summary(&self) {
+        log::info!("Upload phases: {}", self.summary());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_accumulates_repeated_phases() {
+        let mut phase_timer = PhaseTimer::new();
+        let phase_start = Instant::now();
+        std::thread::sleep(Duration::from_millis(5));
+        phase_timer.record(UploadPhase::S3Put, phase_start);
+        let first = phase_timer.duration(UploadPhase::S3Put).unwrap();
+        phase_timer.record(UploadPhase::S3Put, Instant::now());
+
+        assert_eq!(phase_timer.phase_durations().len(), 1);
+        assert!(phase_timer.duration(UploadPhase::S3Put).unwrap() >= first);
+        assert!(phase_timer.duration(UploadPhase::UploadIntent).is_none());
+        assert!(phase_timer.total() >= first);
+        assert!(phase_timer.summary().starts_with("s3 put "));
+        assert!(phase_timer.summary().contains(", total "));
+        let durations_ms = phase_timer.durations_ms();
+        assert_eq!(durations_ms.keys().collect::<Vec<_>>(), ["s3_put", "total"]);
+    }
+}
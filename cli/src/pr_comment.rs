@@ -0,0 +1,83 @@
+use std::env;
+
+use context::{
+    env::{
+        parser::{CIPlatform, EnvParser, StableBranches},
+        EnvVars,
+    },
+    repo::BundleRepo,
+};
+use github_actions::{
+    pr_comment::{PrCommentAction, PrCommenter},
+    DEFAULT_API_TIMEOUT,
+};
+
+use crate::summary::{FailureSummary, MarkdownRenderer};
+
+/// Hidden in the comment, to update the comment of an earlier run instead of adding another
+pub const PR_COMMENT_MARKER: &str = "<!-- trunk-analytics-cli:failure-summary -->";
+
+/// Comments the failure summary on the pull request of a GitHub Actions run, for repos without
+/// the Trunk GitHub app. Failing to comment is only logged, since it must not change the exit
+/// code of the run.
+pub async fn post_pr_comment(
+    failure_summary: &FailureSummary,
+    repo: &BundleRepo,
+    stable_branches: &StableBranches,
+) {
+    let env_vars: EnvVars = env::vars().collect();
+    let mut env_parser = EnvParser::with_stable_branches(stable_branches.clone());
+    env_parser.parse(&env_vars);
+    let ci_info = env_parser
+        .into_ci_info_parser()
+        .map(|ci_info_parser| ci_info_parser.info_ci_info());
+    let Some(ci_info) = ci_info.filter(|ci_info| ci_info.platform == CIPlatform::GitHubActions)
+    else {
+        log::warn!("Not commenting on the pull request, it is only supported in GitHub Actions");
+        return;
+    };
+    let Some(pr_number) = ci_info.pr_number else {
+        log::warn!("Not commenting on the pull request, the run is not for a pull request");
+        return;
+    };
+    let pr_commenter = match PrCommenter::from_env(pr_number) {
+        Ok(pr_commenter) => pr_commenter,
+        Err(missing_env_vars) => {
+            log::warn!(
+                "Not commenting on pull request #{}, missing {}",
+                pr_number,
+                missing_env_vars.join(", ")
+            );
+            return;
+        }
+    };
+
+    let body = MarkdownRenderer {
+        blob_url: blob_url(repo),
+    }
+    .render(failure_summary);
+    match pr_commenter
+        .upsert(PR_COMMENT_MARKER, &body, DEFAULT_API_TIMEOUT)
+        .await
+    {
+        Ok(PrCommentAction::Created(..)) => {
+            log::info!("Commented the test summary on pull request #{}", pr_number)
+        }
+        Ok(PrCommentAction::Updated(..)) => log::info!(
+            "Updated the test summary comment on pull request #{}",
+            pr_number
+        ),
+        Err(e) => log::warn!("Failed to comment on pull request #{}: {}", pr_number, e),
+    }
+}
+
+/// Where the files of the head commit are shown, to link failures to them
+fn blob_url(repo: &BundleRepo) -> Option<String> {
+    if repo.repo.host.is_empty() || repo.repo_head_sha.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "https://{}/{}/{}/blob/{}",
+        repo.repo.host, repo.repo.owner, repo.repo.name, repo.repo_head_sha
+    ))
+}
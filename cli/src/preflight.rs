@@ -0,0 +1,58 @@
+use api::{
+    client::ApiClientTrait,
+    error_code::{ErrorCode, WithErrorCode},
+    message::ValidateTokenOrgRequest,
+};
+
+use crate::upload::UploadConfig;
+
+/// Checks that the token belongs to the org of `--org-url-slug` before any test results are
+/// gathered, since a wrong slug otherwise only fails once the bundle upload is created.
+///
+/// Only a mismatch is an error. If the org of the token can't be looked up, e.g. because Trunk
+/// services can't be reached, the upload goes ahead as if there was no preflight.
+pub async fn run_preflight(
+    upload_config: &UploadConfig,
+    api_client: &dyn ApiClientTrait,
+) -> anyhow::Result<()> {
+    if upload_config.no_upload && !upload_config.register_dry_run {
+        return Ok(());
+    }
+
+    let response = match api_client
+        .validate_token_org(&ValidateTokenOrgRequest {
+            org_url_slug: upload_config.org_url_slug.clone(),
+        })
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!(
+                "Could not check that the token belongs to organization {:?}, continuing: {:?}",
+                upload_config.org_url_slug,
+                e
+            );
+            return Ok(());
+        }
+    };
+
+    check_token_org(&upload_config.org_url_slug, &response.org_url_slug)
+}
+
+fn check_token_org(org_url_slug: &str, token_org_url_slug: &str) -> anyhow::Result<()> {
+    if org_url_slug == token_org_url_slug {
+        return Ok(());
+    }
+    Err::<(), _>(anyhow::anyhow!(
+        concat!(
+            "The organization URL slug {:?} does not match the organization of the token, {:?}. ",
+            "Set --org-url-slug to {:?}, or use the token of {:?} ",
+            "(Settings -> Manage Organization -> Organization API Token -> View)."
+        ),
+        org_url_slug,
+        token_org_url_slug,
+        token_org_url_slug,
+        org_url_slug,
+    ))
+    .error_code(ErrorCode::OrgNotFound)
+}
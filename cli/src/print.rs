@@ -1,3 +1,5 @@
+use bundle::FileDecision;
+use colored::Colorize;
 use context::bazel_bep::parser::BepParseResult;
 
 pub fn print_bep_results(bep_result: &BepParseResult) {
@@ -12,3 +14,20 @@ pub fn print_bep_results(bep_result: &BepParseResult) {
         cached_xml_count
     );
 }
+
+/// Why each file matched by the junit globs was uploaded or not, grouped by glob
+pub fn print_file_decisions(file_decisions: &[FileDecision]) {
+    let mut glob = None;
+    for file_decision in file_decisions {
+        if glob != Some(&file_decision.glob) {
+            glob = Some(&file_decision.glob);
+            println!("Files matched by {}:", file_decision.glob);
+        }
+        let mark = if file_decision.rule.is_included() {
+            "✔".green()
+        } else {
+            "✖".red()
+        };
+        println!("  {} {}: {}", mark, file_decision.path, file_decision.rule);
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashSet, fmt};
+
+use bundle::{BundleMetaQuarantineSource, Test};
+
+use crate::config_file::edit_distance;
+
+/// A test case of the upload, as suggested for a quarantined test ID that matched none
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NearestTest {
+    pub id: String,
+    pub name: String,
+    pub class_name: Option<String>,
+    pub parent_name: String,
+}
+
+impl From<&Test> for NearestTest {
+    fn from(test: &Test) -> Self {
+        Self {
+            id: test.id.clone(),
+            name: test.name.clone(),
+            class_name: test.class_name.clone(),
+            parent_name: test.parent_name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for NearestTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> ", self.parent_name)?;
+        if let Some(class_name) = &self.class_name {
+            write!(f, "{}.", class_name)?;
+        }
+        write!(f, "{} (id: {})", self.name, self.id)
+    }
+}
+
+/// A test ID quarantined in Trunk that no test case of the upload has
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnmatchedQuarantinedTest {
+    pub id: String,
+    /// The test case of the upload closest to `id`, which it may be a misspelling of
+    pub nearest_test: Option<NearestTest>,
+}
+
+/// A failed test of the upload that was quarantined, by Trunk or the test runner
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QuarantinedFailure {
+    pub id: String,
+    pub name: String,
+    pub parent_name: String,
+    pub source: BundleMetaQuarantineSource,
+}
+
+impl QuarantinedFailure {
+    pub fn new(test: &Test, source: BundleMetaQuarantineSource) -> Self {
+        Self {
+            id: test.id.clone(),
+            name: test.name.clone(),
+            parent_name: test.parent_name.clone(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for QuarantinedFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self.source {
+            BundleMetaQuarantineSource::Server => "Trunk",
+            BundleMetaQuarantineSource::Runner => "the test runner",
+            BundleMetaQuarantineSource::ServerAndRunner => "Trunk and the test runner",
+        };
+        write!(
+            f,
+            "{} -> {} (id: {}), by {}",
+            self.parent_name, self.name, self.id, source
+        )
+    }
+}
+
+/// The quarantined test IDs returned by Trunk that matched no test case of the upload, which are
+/// likely misspelled or of tests that were renamed or removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QuarantineAudit {
+    pub num_quarantined: usize,
+    pub unmatched: Vec<UnmatchedQuarantinedTest>,
+    /// The failures of the upload that were quarantined, and who quarantined them
+    pub quarantined_failures: Vec<QuarantinedFailure>,
+}
+
+/// Number of distinct quarantined test IDs that matched no parsed test, without looking for the
+/// nearest test of each like [`QuarantineAudit::new`]
+pub fn count_unmatched_quarantined_ids(quarantined_ids: &[String], parsed_tests: &[Test]) -> usize {
+    let parsed_ids: HashSet<&str> = parsed_tests.iter().map(|test| test.id.as_str()).collect();
+    quarantined_ids
+        .iter()
+        .map(String::as_str)
+        .filter(|id| !parsed_ids.contains(id))
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+impl QuarantineAudit {
+    pub fn new(quarantined_ids: &[String], parsed_tests: &[Test]) -> Self {
+        let parsed_ids: HashSet<&str> = parsed_tests.iter().map(|test| test.id.as_str()).collect();
+        let mut unmatched: Vec<UnmatchedQuarantinedTest> = quarantined_ids
+            .iter()
+            .filter(|id| !parsed_ids.contains(id.as_str()))
+            .map(|id| UnmatchedQuarantinedTest {
+                id: id.clone(),
+                nearest_test: nearest_test(id, parsed_tests).map(NearestTest::from),
+            })
+            .collect();
+        unmatched.sort_by(|a, b| a.id.cmp(&b.id));
+        unmatched.dedup_by(|a, b| a.id == b.id);
+        Self {
+            num_quarantined: quarantined_ids.len(),
+            unmatched,
+            quarantined_failures: Vec::new(),
+        }
+    }
+
+    pub fn with_quarantined_failures(self, quarantined_failures: Vec<QuarantinedFailure>) -> Self {
+        Self {
+            quarantined_failures,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for QuarantineAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.unmatched.is_empty() {
+            write!(
+                f,
+                "All {} quarantined test ID(s) matched a test case of this upload",
+                self.num_quarantined
+            )?;
+        } else {
+            write!(
+                f,
+                "{} of {} quarantined test ID(s) matched no test case of this upload:",
+                self.unmatched.len(),
+                self.num_quarantined
+            )?;
+            for unmatched in &self.unmatched {
+                write!(f, "\n    {}", unmatched.id)?;
+                if let Some(nearest_test) = &unmatched.nearest_test {
+                    write!(f, ", did you mean {}?", nearest_test)?;
+                }
+            }
+        }
+        if !self.quarantined_failures.is_empty() {
+            write!(
+                f,
+                "\n{} test failure(s) were quarantined:",
+                self.quarantined_failures.len()
+            )?;
+            for quarantined_failure in &self.quarantined_failures {
+                write!(f, "\n    {}", quarantined_failure)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The parsed test whose ID, name or qualified name is closest to `id`, if it is close enough to
+/// be a misspelling of it
+fn nearest_test<'a>(id: &str, parsed_tests: &'a [Test]) -> Option<&'a Test> {
+    let max_distance = (id.chars().count() / 3).max(2);
+    parsed_tests
+        .iter()
+        .map(|test| {
+            let qualified_name = test
+                .class_name
+                .as_ref()
+                .map(|class_name| format!("{}.{}", class_name, test.name));
+            let distance = [Some(&test.id), Some(&test.name), qualified_name.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|candidate| edit_distance(id, candidate))
+                .min()
+                .unwrap_or(usize::MAX);
+            (distance, test)
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, test)| test)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(id: &str, class_name: &str, name: &str) -> Test {
+        Test {
+            name: String::from(name),
+            parent_name: String::from("suite"),
+            class_name: Some(String::from(class_name)),
+            file: None,
+            id: String::from(id),
+            timestamp_millis: None,
+        }
+    }
+
+    #[test]
+    fn suggests_nearest_parsed_test() {
+        let parsed_tests = vec![
+            test("test-login-succeeds", "auth.LoginTest", "logs in"),
+            test(
+                "1b4e28ba-2fa1-11d2-883f-0016d3cca427",
+                "auth.LogoutTest",
+                "logs out",
+            ),
+        ];
+        let quarantined_ids = [
+            String::from("test-login-succeeds"),
+            String::from("test-login-suceeds"),
+            String::from("1b4e28ba-2fa1-11d2-883f-0016d3cca42"),
+            String::from("auth.LogoutTest.logs-out"),
+            String::from("0e1ab3c4-9f5d-4c7e-8a2b-6d3f1e0c9b7a"),
+        ];
+        let audit = QuarantineAudit::new(&quarantined_ids, &parsed_tests);
+        assert_eq!(audit.num_quarantined, 5);
+        assert_eq!(
+            count_unmatched_quarantined_ids(&quarantined_ids, &parsed_tests),
+            audit.unmatched.len()
+        );
+        let suggestions: Vec<(&str, Option<&str>)> = audit
+            .unmatched
+            .iter()
+            .map(|unmatched| {
+                (
+                    unmatched.id.as_str(),
+                    unmatched
+                        .nearest_test
+                        .as_ref()
+                        .map(|nearest_test| nearest_test.id.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                ("0e1ab3c4-9f5d-4c7e-8a2b-6d3f1e0c9b7a", None),
+                (
+                    "1b4e28ba-2fa1-11d2-883f-0016d3cca42",
+                    Some("1b4e28ba-2fa1-11d2-883f-0016d3cca427")
+                ),
+                (
+                    "auth.LogoutTest.logs-out",
+                    Some("1b4e28ba-2fa1-11d2-883f-0016d3cca427")
+                ),
+                ("test-login-suceeds", Some("test-login-succeeds")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unmatched_ids() {
+        let parsed_tests = vec![test("test-login-succeeds", "auth.LoginTest", "logs in")];
+        assert_eq!(
+            QuarantineAudit::new(&[String::from("test-login-succeeds")], &parsed_tests).to_string(),
+            "All 1 quarantined test ID(s) matched a test case of this upload"
+        );
+        assert_eq!(
+            QuarantineAudit::new(&[String::from("test-login-suceeds")], &parsed_tests)
+                .to_string(),
+            "1 of 1 quarantined test ID(s) matched no test case of this upload:\n    test-login-suceeds, did you mean suite -> auth.LoginTest.logs in (id: test-login-succeeds)?"
+        );
+    }
+
+    #[test]
+    fn reports_who_quarantined_failures() {
+        let parsed_tests = vec![test("test-login-succeeds", "auth.LoginTest", "logs in")];
+        let audit = QuarantineAudit::new(&[String::from("test-login-succeeds")], &parsed_tests)
+            .with_quarantined_failures(vec![
+                QuarantinedFailure::new(&parsed_tests[0], BundleMetaQuarantineSource::Server),
+                QuarantinedFailure::new(
+                    &test("test-logout", "auth.LogoutTest", "logs out"),
+                    BundleMetaQuarantineSource::Runner,
+                ),
+            ]);
+        assert_eq!(
+            audit.to_string(),
+            "All 1 quarantined test ID(s) matched a test case of this upload\n2 test failure(s) were quarantined:\n    suite -> logs in (id: test-login-succeeds), by Trunk\n    suite -> logs out (id: test-logout), by the test runner"
+        );
+    }
+}
@@ -1,6 +1,10 @@
+use api::client::{ApiClient, ApiClientTrait};
 use clap::Args;
 
-use crate::upload_command::{run_upload, UploadArgs, UploadRunResult};
+use crate::{
+    multi_repo::run_multi_repo_upload,
+    upload_command::{run_upload, UploadArgs, UploadRunResult},
+};
 
 #[derive(Args, Clone, Debug)]
 pub struct QuarantineArgs {
@@ -8,13 +12,30 @@ pub struct QuarantineArgs {
     upload_args: UploadArgs,
 }
 
+impl QuarantineArgs {
+    pub fn api_client(&self) -> anyhow::Result<ApiClient> {
+        self.upload_args.api_client()
+    }
+
+    pub async fn authenticate(&mut self) -> anyhow::Result<()> {
+        self.upload_args.authenticate().await
+    }
+}
+
 // This is an alias to `run_upload`, but does not exit on upload failure
-pub async fn run_quarantine(QuarantineArgs { upload_args }: QuarantineArgs) -> anyhow::Result<i32> {
-    let upload_run_result = run_upload(upload_args, None, None).await;
+pub async fn run_quarantine(
+    QuarantineArgs { upload_args }: QuarantineArgs,
+    api_client: &dyn ApiClientTrait,
+) -> anyhow::Result<i32> {
+    if let Some(multi_repo_config) = upload_args.multi_repo_config.clone() {
+        return run_multi_repo_upload(&multi_repo_config, upload_args, api_client).await;
+    }
+    let upload_run_result = run_upload(upload_args, api_client, None, None).await;
     upload_run_result.map(
         |UploadRunResult {
              exit_code,
              upload_bundle_error,
+             ..
          }| {
             if let Some(e) = upload_bundle_error {
                 log::error!("Error uploading test results: {:?}", e);
@@ -0,0 +1,169 @@
+//! Termination of the CLI by CI, e.g. GitHub Actions sends SIGINT and then SIGTERM to the steps of
+//! a cancelled job before killing them.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU32, AtomicU8, Ordering},
+        LazyLock,
+    },
+};
+
+use tokio_util::sync::CancellationToken;
+
+static SHUTDOWN: LazyLock<Shutdown> = LazyLock::new(|| Shutdown {
+    cancellation_token: CancellationToken::new(),
+    signal: AtomicU8::new(NO_SIGNAL),
+    test_command_pid: AtomicU32::new(NO_TEST_COMMAND),
+});
+
+const NO_SIGNAL: u8 = 0;
+const NO_TEST_COMMAND: u32 = 0;
+
+struct Shutdown {
+    cancellation_token: CancellationToken,
+    signal: AtomicU8,
+    test_command_pid: AtomicU32,
+}
+
+/// A signal asking the CLI to terminate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Signal {
+    Interrupt = 1,
+    Terminate = 2,
+}
+
+impl Signal {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Signal::Interrupt),
+            2 => Some(Signal::Terminate),
+            _ => None,
+        }
+    }
+
+    /// 128 plus the signal number, like a shell reports for a process killed by it, so that CI can
+    /// tell a cancelled run from a failed one
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Signal::Interrupt => 130,
+            Signal::Terminate => 143,
+        }
+    }
+
+    #[cfg(unix)]
+    fn number(&self) -> libc::c_int {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signal::Interrupt => write!(f, "SIGINT"),
+            Signal::Terminate => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+/// Cancelled once the CLI receives a signal, after the running test command got it. Never
+/// cancelled unless [`handle_signals`] was called.
+pub fn cancellation_token() -> CancellationToken {
+    SHUTDOWN.cancellation_token.clone()
+}
+
+/// The first signal the CLI received, if any
+pub fn received_signal() -> Option<Signal> {
+    Signal::from_u8(SHUTDOWN.signal.load(Ordering::SeqCst))
+}
+
+/// Handles SIGINT and SIGTERM, which no longer terminate the CLI, by forwarding them to the
+/// running test command and cancelling [`cancellation_token`]. The CLI is expected to check
+/// [`received_signal`] and exit once it has wrapped up. Receiving a signal again while no test
+/// command is running exits right away.
+///
+/// Must be called from within a tokio runtime.
+pub fn handle_signals() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigint = signal(SignalKind::interrupt())?;
+        let mut sigterm = signal(SignalKind::terminate())?;
+        tokio::spawn(async move {
+            loop {
+                let signal = tokio::select! {
+                    Some(()) = sigint.recv() => Signal::Interrupt,
+                    Some(()) = sigterm.recv() => Signal::Terminate,
+                    else => return,
+                };
+                on_signal(signal);
+            }
+        });
+    }
+    #[cfg(not(unix))]
+    tokio::spawn(async move {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            on_signal(Signal::Interrupt);
+        }
+    });
+    Ok(())
+}
+
+fn on_signal(signal: Signal) {
+    let first_signal = SHUTDOWN
+        .signal
+        .compare_exchange(NO_SIGNAL, signal as u8, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok();
+    let forwarded = forward_to_test_command(signal);
+    if first_signal {
+        log::warn!("Received {}, wrapping up", signal);
+        SHUTDOWN.cancellation_token.cancel();
+    } else if !forwarded {
+        log::warn!("Received {} again, exiting now", signal);
+        std::process::exit(signal.exit_code());
+    }
+}
+
+/// Returns whether a test command was running.
+fn forward_to_test_command(signal: Signal) -> bool {
+    let pid = SHUTDOWN.test_command_pid.load(Ordering::SeqCst);
+    if pid == NO_TEST_COMMAND {
+        return false;
+    }
+    log::info!("Forwarding {} to the test command", signal);
+    #[cfg(unix)]
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, signal.number()) } == -1 {
+        log::warn!(
+            "Failed to forward {} to the test command: {}",
+            signal,
+            std::io::Error::last_os_error()
+        );
+    }
+    // on Windows, Ctrl+C already reaches every process attached to the console
+    true
+}
+
+/// Forwards the signals the CLI receives to the test command with `pid` until dropped, right after
+/// it exits.
+pub struct ForwardSignals(());
+
+impl ForwardSignals {
+    pub fn to(pid: u32) -> Self {
+        SHUTDOWN.test_command_pid.store(pid, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for ForwardSignals {
+    fn drop(&mut self) {
+        SHUTDOWN
+            .test_command_pid
+            .store(NO_TEST_COMMAND, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,98 @@
+use api::{
+    client::ApiClient,
+    message::{BundleUploadSummary, ListBundleUploadsRequest},
+};
+use clap::Args;
+use constants::EXIT_SUCCESS;
+use context::repo::BundleRepo;
+
+#[derive(Args, Clone, Debug)]
+pub struct StatusArgs {
+    #[arg(long, help = "Organization url slug.")]
+    pub org_url_slug: String,
+    #[arg(
+        long,
+        required = true,
+        env = "TRUNK_API_TOKEN",
+        help = "Organization token. Defaults to TRUNK_API_TOKEN env var."
+    )]
+    pub token: String,
+    #[arg(long, help = "Path to repository root. Defaults to current directory.")]
+    pub repo_root: Option<String>,
+    #[arg(long, help = "Value to override URL of repository.")]
+    pub repo_url: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u16).range(1..=100),
+        help = "Number of recent uploads to show."
+    )]
+    pub limit: u16,
+    #[arg(long, help = "Print the recent uploads as JSON.")]
+    pub json: bool,
+}
+
+pub async fn run_status(
+    StatusArgs {
+        org_url_slug,
+        token,
+        repo_root,
+        repo_url,
+        limit,
+        json,
+    }: StatusArgs,
+) -> anyhow::Result<i32> {
+    let repo = BundleRepo::new(
+        repo_root,
+        repo_url,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        &std::env::vars().collect(),
+    )?;
+    let api_client = ApiClient::new(&token)?;
+
+    let response = api_client
+        .list_recent_uploads(&ListBundleUploadsRequest {
+            repo: repo.repo,
+            org_url_slug,
+            limit: limit.into(),
+        })
+        .await?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&response.bundle_uploads)?
+        );
+    } else if response.bundle_uploads.is_empty() {
+        println!("No recent uploads found.");
+    } else {
+        for bundle_upload in &response.bundle_uploads {
+            println!("{}", format_bundle_upload(bundle_upload));
+        }
+    }
+
+    Ok(EXIT_SUCCESS)
+}
+
+fn format_bundle_upload(bundle_upload: &BundleUploadSummary) -> String {
+    let test_counts = match (bundle_upload.num_tests, bundle_upload.num_failures) {
+        (Some(num_tests), Some(num_failures)) => {
+            format!("{} tests, {} failures", num_tests, num_failures)
+        }
+        (Some(num_tests), None) => format!("{} tests", num_tests),
+        _ => String::from("tests pending"),
+    };
+    format!(
+        "{}  {}  {}  {}  {}",
+        bundle_upload.id,
+        bundle_upload.created_at,
+        bundle_upload.upload_status,
+        bundle_upload.variant.as_deref().unwrap_or("<no variant>"),
+        test_counts
+    )
+}
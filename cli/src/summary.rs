@@ -0,0 +1,792 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use clap::ValueEnum;
+use colored::Colorize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const MAX_EXAMPLES: usize = 3;
+const UNOWNED: &str = "unowned";
+const UNKNOWN_FILE: &str = "unknown file";
+/// Columns to fit the summary to when the width of the terminal can't be detected
+pub const DEFAULT_WIDTH: usize = 100;
+/// Narrower terminals get each field on a line of its own, rather than indented
+const MIN_INDENTED_WIDTH: usize = 40;
+
+/// A failed test, as shown in the summary at the end of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TestFailure {
+    pub id: String,
+    pub name: String,
+    pub parent_name: String,
+    pub file: Option<String>,
+    /// Codeowners of the file of the test, the first of which is its primary owner
+    pub owners: Vec<String>,
+    pub quarantined: bool,
+}
+
+impl TestFailure {
+    pub fn new(
+        id: String,
+        name: String,
+        parent_name: String,
+        file: Option<String>,
+        owners: Vec<String>,
+        quarantined: bool,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            parent_name,
+            file,
+            owners,
+            quarantined,
+        }
+    }
+
+    fn primary_owner(&self) -> &str {
+        self.owners.first().map(String::as_str).unwrap_or(UNOWNED)
+    }
+
+    fn file(&self) -> &str {
+        self.file.as_deref().unwrap_or(UNKNOWN_FILE)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SummaryGroupBy {
+    /// Group by primary codeowner, then by file
+    #[default]
+    Owner,
+    File,
+    Suite,
+    /// List every failure
+    None,
+}
+
+impl Display for SummaryGroupBy {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            SummaryGroupBy::Owner => write!(f, "owner"),
+            SummaryGroupBy::File => write!(f, "file"),
+            SummaryGroupBy::Suite => write!(f, "suite"),
+            SummaryGroupBy::None => write!(f, "none"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureGroupKind {
+    Owner,
+    File,
+    Suite,
+}
+
+impl Display for FailureGroupKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            FailureGroupKind::Owner => write!(f, "owner"),
+            FailureGroupKind::File => write!(f, "file"),
+            FailureGroupKind::Suite => write!(f, "suite"),
+        }
+    }
+}
+
+/// Failures sharing an owner, file or suite, with a few of their names as examples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureGroup {
+    pub kind: FailureGroupKind,
+    pub name: String,
+    pub num_failures: usize,
+    pub examples: Vec<String>,
+    /// Failures of an owner grouped by file
+    pub subgroups: Vec<FailureGroup>,
+    /// Whether `name` is the path of a file, which can be linked to
+    pub is_file: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureSection {
+    pub num_failures: usize,
+    pub groups: Vec<FailureGroup>,
+    /// Every failure, when they are not grouped
+    pub ungrouped: Vec<String>,
+}
+
+/// Failures laid out for display, with quarantined failures apart from those that fail the run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailureSummary {
+    pub unquarantined: FailureSection,
+    pub quarantined: FailureSection,
+}
+
+impl FailureSummary {
+    pub fn new(test_failures: &[TestFailure], group_by: SummaryGroupBy) -> Self {
+        let (quarantined, unquarantined): (Vec<_>, Vec<_>) = test_failures
+            .iter()
+            .partition(|test_failure| test_failure.quarantined);
+        Self {
+            unquarantined: FailureSection::new(&unquarantined, group_by),
+            quarantined: FailureSection::new(&quarantined, group_by),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unquarantined.num_failures == 0 && self.quarantined.num_failures == 0
+    }
+}
+
+impl FailureSection {
+    fn new(test_failures: &[&TestFailure], group_by: SummaryGroupBy) -> Self {
+        let num_failures = test_failures.len();
+        // failures are in no particular order, so sort them to choose examples consistently
+        let mut test_failures = test_failures.to_vec();
+        test_failures.sort_by(|a, b| a.name.cmp(&b.name));
+        let test_failures = test_failures.as_slice();
+        match group_by {
+            SummaryGroupBy::Owner => Self {
+                num_failures,
+                groups: group_failures(test_failures, TestFailure::primary_owner)
+                    .into_iter()
+                    .map(|(owner, test_failures)| FailureGroup {
+                        kind: FailureGroupKind::Owner,
+                        name: String::from(owner),
+                        num_failures: test_failures.len(),
+                        examples: Vec::new(),
+                        subgroups: file_groups(&test_failures),
+                        is_file: false,
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            SummaryGroupBy::File => Self {
+                num_failures,
+                groups: file_groups(test_failures),
+                ..Default::default()
+            },
+            SummaryGroupBy::Suite => Self {
+                num_failures,
+                groups: group_failures(test_failures, |test_failure| {
+                    test_failure.parent_name.as_str()
+                })
+                .into_iter()
+                .map(|(suite, test_failures)| {
+                    leaf_group(FailureGroupKind::Suite, suite, &test_failures)
+                })
+                .collect(),
+                ..Default::default()
+            },
+            SummaryGroupBy::None => Self {
+                num_failures,
+                ungrouped: test_failures
+                    .iter()
+                    .map(|test_failure| test_failure.name.clone())
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Groups with the most failures come first, and ties are broken by name.
+fn group_failures<'a, F: Fn(&'a TestFailure) -> &'a str>(
+    test_failures: &[&'a TestFailure],
+    key: F,
+) -> Vec<(&'a str, Vec<&'a TestFailure>)> {
+    let mut groups: BTreeMap<&str, Vec<&TestFailure>> = BTreeMap::new();
+    for &test_failure in test_failures {
+        groups
+            .entry(key(test_failure))
+            .or_default()
+            .push(test_failure);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by_key(|(_, test_failures)| std::cmp::Reverse(test_failures.len()));
+    groups
+}
+
+fn file_groups(test_failures: &[&TestFailure]) -> Vec<FailureGroup> {
+    group_failures(test_failures, TestFailure::file)
+        .into_iter()
+        .map(|(file, test_failures)| FailureGroup {
+            is_file: test_failures
+                .iter()
+                .any(|test_failure| test_failure.file.is_some()),
+            ..leaf_group(FailureGroupKind::File, file, &test_failures)
+        })
+        .collect()
+}
+
+fn leaf_group(kind: FailureGroupKind, name: &str, test_failures: &[&TestFailure]) -> FailureGroup {
+    FailureGroup {
+        kind,
+        name: String::from(name),
+        num_failures: test_failures.len(),
+        examples: test_failures
+            .iter()
+            .take(MAX_EXAMPLES)
+            .map(|test_failure| test_failure.name.clone())
+            .collect(),
+        subgroups: Vec::new(),
+        is_file: false,
+    }
+}
+
+/// Renders with colors and Unicode bullets for terminals, or as plain text for everything else,
+/// e.g. CI logs. Names are truncated to fit the lines to a width in columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryRenderer {
+    Fancy,
+    Plain,
+}
+
+impl SummaryRenderer {
+    pub fn for_stdout() -> Self {
+        if console::Term::stdout().is_term() {
+            SummaryRenderer::Fancy
+        } else {
+            SummaryRenderer::Plain
+        }
+    }
+
+    pub fn render(&self, failure_summary: &FailureSummary, width: usize) -> String {
+        let mut lines = Vec::new();
+        if failure_summary.unquarantined.num_failures > 0 {
+            let header = failures_count(failure_summary.unquarantined.num_failures);
+            lines.push(match self {
+                SummaryRenderer::Fancy => header.red().bold().to_string(),
+                SummaryRenderer::Plain => header,
+            });
+            self.render_section(&failure_summary.unquarantined, true, width, &mut lines);
+        }
+        if failure_summary.quarantined.num_failures > 0 {
+            let mut quarantined_lines = vec![format!(
+                "Quarantined: {}",
+                failures_count(failure_summary.quarantined.num_failures)
+            )];
+            // nested styles would end the dimming early
+            self.render_section(
+                &failure_summary.quarantined,
+                false,
+                width,
+                &mut quarantined_lines,
+            );
+            lines.extend(quarantined_lines.into_iter().map(|line| match self {
+                SummaryRenderer::Fancy => line.dimmed().to_string(),
+                SummaryRenderer::Plain => line,
+            }));
+        }
+        lines.join("\n")
+    }
+
+    fn render_section(
+        &self,
+        failure_section: &FailureSection,
+        emphasize: bool,
+        width: usize,
+        lines: &mut Vec<String>,
+    ) {
+        for failure_group in &failure_section.groups {
+            self.render_group(failure_group, 1, emphasize, width, lines);
+        }
+        for name in &failure_section.ungrouped {
+            lines.push(self.render_test(name, 1, width));
+        }
+    }
+
+    fn render_group(
+        &self,
+        failure_group: &FailureGroup,
+        depth: usize,
+        emphasize: bool,
+        width: usize,
+        lines: &mut Vec<String>,
+    ) {
+        let emphasize = emphasize && *self == SummaryRenderer::Fancy;
+        if is_stacked(width) {
+            lines.push(self.fit(
+                &format!("{}: ", failure_group.kind),
+                &failure_group.name,
+                "",
+                width,
+                emphasize,
+            ));
+            lines.push(format!("failures: {}", failure_group.num_failures));
+        } else {
+            lines.push(self.fit(
+                &indent(depth),
+                &failure_group.name,
+                &format!(": {}", failures_count(failure_group.num_failures)),
+                width,
+                emphasize,
+            ));
+        }
+        for subgroup in &failure_group.subgroups {
+            self.render_group(subgroup, depth + 1, emphasize, width, lines);
+        }
+        for example in &failure_group.examples {
+            lines.push(self.render_test(example, depth + 1, width));
+        }
+        let num_more = failure_group.num_failures - failure_group.examples.len();
+        if failure_group.subgroups.is_empty() && num_more > 0 {
+            lines.push(if is_stacked(width) {
+                format!("more: {}", num_more)
+            } else {
+                format!("{}... and {} more", indent(depth + 1), num_more)
+            });
+        }
+    }
+
+    fn render_test(&self, name: &str, depth: usize, width: usize) -> String {
+        let prefix = if is_stacked(width) {
+            String::from("test: ")
+        } else {
+            format!("{}{} ", indent(depth), self.bullet())
+        };
+        self.fit(&prefix, name, "", width, false)
+    }
+
+    /// `text` between `prefix` and `suffix`, cleaned of escape codes and line breaks and truncated
+    /// so that the line is at most `width` columns.
+    fn fit(&self, prefix: &str, text: &str, suffix: &str, width: usize, bold: bool) -> String {
+        let text = truncate(
+            &sanitize(text),
+            width.saturating_sub(prefix.width() + suffix.width()),
+            self.ellipsis(),
+        );
+        if bold {
+            format!("{}{}{}", prefix, text.bold(), suffix)
+        } else {
+            format!("{}{}{}", prefix, text, suffix)
+        }
+    }
+
+    fn bullet(&self) -> &str {
+        match self {
+            SummaryRenderer::Fancy => "•",
+            SummaryRenderer::Plain => "-",
+        }
+    }
+
+    fn ellipsis(&self) -> &str {
+        match self {
+            SummaryRenderer::Fancy => "…",
+            SummaryRenderer::Plain => "...",
+        }
+    }
+}
+
+/// The width in columns of the terminal of stdout, or [`DEFAULT_WIDTH`] when it isn't one.
+pub fn terminal_width() -> usize {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| usize::from(columns))
+        .filter(|columns| *columns > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+fn is_stacked(width: usize) -> bool {
+    width < MIN_INDENTED_WIDTH
+}
+
+/// Test names can contain anything, including the colors of the test framework that reported
+/// them, which would throw off their width.
+fn sanitize(text: &str) -> String {
+    console::strip_ansi_codes(text)
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Cuts `text` to at most `width` columns, ending with `ellipsis` when it had to be cut. Wide
+/// characters, e.g. CJK or emoji, take 2 columns.
+fn truncate(text: &str, width: usize, ellipsis: &str) -> String {
+    if text.width() <= width {
+        return String::from(text);
+    }
+    let width = width.saturating_sub(ellipsis.width());
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or_default();
+        if truncated_width + c_width > width {
+            break;
+        }
+        truncated.push(c);
+        truncated_width += c_width;
+    }
+    truncated.push_str(ellipsis);
+    truncated
+}
+
+/// Renders as GitHub-flavored markdown for pull request comments, linking files to `blob_url`,
+/// e.g. `https://github.com/owner/name/blob/<sha>`. Owners are code spans, so that commenting
+/// doesn't notify them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownRenderer {
+    pub blob_url: Option<String>,
+}
+
+impl MarkdownRenderer {
+    pub fn render(&self, failure_summary: &FailureSummary) -> String {
+        let mut lines = Vec::new();
+        if failure_summary.is_empty() {
+            lines.push(String::from("### No test failures"));
+        }
+        if failure_summary.unquarantined.num_failures > 0 {
+            lines.push(format!(
+                "### {}",
+                failures_count(failure_summary.unquarantined.num_failures)
+            ));
+            self.render_section(&failure_summary.unquarantined, &mut lines);
+        }
+        if failure_summary.quarantined.num_failures > 0 {
+            lines.push(String::new());
+            lines.push(format!(
+                "<details><summary>Quarantined: {}</summary>",
+                failures_count(failure_summary.quarantined.num_failures)
+            ));
+            lines.push(String::new());
+            self.render_section(&failure_summary.quarantined, &mut lines);
+            lines.push(String::new());
+            lines.push(String::from("</details>"));
+        }
+        lines.join("\n")
+    }
+
+    fn render_section(&self, failure_section: &FailureSection, lines: &mut Vec<String>) {
+        for failure_group in &failure_section.groups {
+            self.render_group(failure_group, 0, lines);
+        }
+        for name in &failure_section.ungrouped {
+            lines.push(format!("- {}", escape_markdown(name)));
+        }
+    }
+
+    fn render_group(&self, failure_group: &FailureGroup, depth: usize, lines: &mut Vec<String>) {
+        let name = match (&self.blob_url, failure_group.is_file) {
+            (Some(blob_url), true) => format!(
+                "[{}]({}/{})",
+                code_span(&failure_group.name),
+                blob_url.trim_end_matches('/'),
+                url_path(failure_group.name.trim_start_matches("./"))
+            ),
+            _ => code_span(&failure_group.name),
+        };
+        lines.push(format!(
+            "{}- {}: {}",
+            indent(depth),
+            name,
+            failures_count(failure_group.num_failures)
+        ));
+        for subgroup in &failure_group.subgroups {
+            self.render_group(subgroup, depth + 1, lines);
+        }
+        for example in &failure_group.examples {
+            lines.push(format!(
+                "{}- {}",
+                indent(depth + 1),
+                escape_markdown(example)
+            ));
+        }
+        let num_more = failure_group.num_failures - failure_group.examples.len();
+        if failure_group.subgroups.is_empty() && num_more > 0 {
+            lines.push(format!("{}- ... and {} more", indent(depth + 1), num_more));
+        }
+    }
+}
+
+/// Test names can contain anything, so characters that markdown or HTML would interpret are
+/// escaped.
+fn escape_markdown(text: &str) -> String {
+    text.chars().fold(String::new(), |mut escaped, c| {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '|' | '~' | '@' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+        escaped
+    })
+}
+
+/// Encodes the characters of a path that would end a markdown link early.
+fn url_path(path: &str) -> String {
+    path.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// A code span delimited by more backticks than `text` has in a row.
+fn code_span(text: &str) -> String {
+    let text = text.replace(['\n', '\r'], " ");
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let delimiter = "`".repeat(longest_run + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{delimiter} {text} {delimiter}")
+    } else {
+        format!("{delimiter}{text}{delimiter}")
+    }
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+fn failures_count(num_failures: usize) -> String {
+    if num_failures == 1 {
+        String::from("1 failure")
+    } else {
+        format!("{} failures", num_failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_failure(
+        name: &str,
+        file: Option<&str>,
+        owner: Option<&str>,
+        quarantined: bool,
+    ) -> TestFailure {
+        TestFailure::new(
+            format!("id-{name}"),
+            String::from(name),
+            String::from("suite"),
+            file.map(String::from),
+            owner.map(String::from).into_iter().collect(),
+            quarantined,
+        )
+    }
+
+    fn test_failures() -> Vec<TestFailure> {
+        vec![
+            test_failure("test_a", Some("src/a_test.rs"), Some("@team-a"), false),
+            test_failure("test_b", Some("src/a_test.rs"), Some("@team-a"), false),
+            test_failure("test_c", Some("src/b_test.rs"), Some("@team-a"), false),
+            test_failure("test_d", Some("src/a_test.rs"), Some("@team-a"), false),
+            test_failure("test_e", Some("src/a_test.rs"), Some("@team-a"), false),
+            test_failure("test_f", None, None, false),
+            test_failure("test_g", Some("src/c_test.rs"), Some("@team-b"), true),
+        ]
+    }
+
+    #[test]
+    fn groups_by_owner_then_file() {
+        let failure_summary = FailureSummary::new(&test_failures(), SummaryGroupBy::Owner);
+        assert_eq!(
+            SummaryRenderer::Plain.render(&failure_summary, DEFAULT_WIDTH),
+            [
+                "6 failures",
+                "  @team-a: 5 failures",
+                "    src/a_test.rs: 4 failures",
+                "      - test_a",
+                "      - test_b",
+                "      - test_d",
+                "      ... and 1 more",
+                "    src/b_test.rs: 1 failure",
+                "      - test_c",
+                "  unowned: 1 failure",
+                "    unknown file: 1 failure",
+                "      - test_f",
+                "Quarantined: 1 failure",
+                "  @team-b: 1 failure",
+                "    src/c_test.rs: 1 failure",
+                "      - test_g",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn groups_by_file_suite_or_not_at_all() {
+        let failure_summary = FailureSummary::new(&test_failures(), SummaryGroupBy::File);
+        let file_names: Vec<_> = failure_summary
+            .unquarantined
+            .groups
+            .iter()
+            .map(|failure_group| (failure_group.name.as_str(), failure_group.num_failures))
+            .collect();
+        assert_eq!(
+            file_names,
+            [
+                ("src/a_test.rs", 4),
+                ("src/b_test.rs", 1),
+                ("unknown file", 1)
+            ]
+        );
+
+        let failure_summary = FailureSummary::new(&test_failures(), SummaryGroupBy::Suite);
+        assert_eq!(failure_summary.unquarantined.groups.len(), 1);
+        assert_eq!(failure_summary.unquarantined.groups[0].examples.len(), 3);
+
+        let failure_summary = FailureSummary::new(&test_failures(), SummaryGroupBy::None);
+        assert!(failure_summary.unquarantined.groups.is_empty());
+        assert_eq!(failure_summary.unquarantined.ungrouped.len(), 6);
+        assert_eq!(failure_summary.quarantined.ungrouped, ["test_g"]);
+    }
+
+    #[test]
+    fn renders_markdown_with_file_links() {
+        let mut test_failures = test_failures();
+        test_failures.push(test_failure(
+            "test_<h>_@me",
+            Some("src/`b`_test.rs"),
+            Some("@team-a"),
+            false,
+        ));
+        let failure_summary = FailureSummary::new(&test_failures, SummaryGroupBy::Owner);
+        let markdown_renderer = MarkdownRenderer {
+            blob_url: Some(String::from(
+                "https://github.com/trunk-io/analytics-cli/blob/abc123",
+            )),
+        };
+        assert_eq!(
+            markdown_renderer.render(&failure_summary),
+            [
+                "### 7 failures",
+                "- `@team-a`: 6 failures",
+                "  - [`src/a_test.rs`](https://github.com/trunk-io/analytics-cli/blob/abc123/src/a_test.rs): 4 failures",
+                "    - test\\_a",
+                "    - test\\_b",
+                "    - test\\_d",
+                "    - ... and 1 more",
+                "  - [``src/`b`_test.rs``](https://github.com/trunk-io/analytics-cli/blob/abc123/src/`b`_test.rs): 1 failure",
+                "    - test\\_&lt;h&gt;\\_\\@me",
+                "  - [`src/b_test.rs`](https://github.com/trunk-io/analytics-cli/blob/abc123/src/b_test.rs): 1 failure",
+                "    - test\\_c",
+                "- `unowned`: 1 failure",
+                "  - `unknown file`: 1 failure",
+                "    - test\\_f",
+                "",
+                "<details><summary>Quarantined: 1 failure</summary>",
+                "",
+                "- `@team-b`: 1 failure",
+                "  - [`src/c_test.rs`](https://github.com/trunk-io/analytics-cli/blob/abc123/src/c_test.rs): 1 failure",
+                "    - test\\_g",
+                "",
+                "</details>",
+            ]
+            .join("\n")
+        );
+
+        let failure_summary = FailureSummary::new(&[], SummaryGroupBy::Owner);
+        assert_eq!(
+            MarkdownRenderer::default().render(&failure_summary),
+            "### No test failures"
+        );
+    }
+
+    fn wide_test_failures() -> Vec<TestFailure> {
+        vec![
+            test_failure(
+                "测试".repeat(30).as_str(),
+                Some("src/数学_test.rs"),
+                Some("@チーム"),
+                false,
+            ),
+            test_failure(
+                "test_🚀_launch",
+                Some("src/数学_test.rs"),
+                Some("@チーム"),
+                false,
+            ),
+            test_failure(
+                "a".repeat(500).as_str(),
+                Some("src/long_test.rs"),
+                None,
+                false,
+            ),
+            test_failure("\u{1b}[31mtest_red\u{1b}[0m\nsecond line", None, None, true),
+        ]
+    }
+
+    fn assert_fits(rendered: &str, width: usize) {
+        for line in rendered.lines() {
+            assert!(line.width() <= width, "{:?} is wider than {}", line, width);
+        }
+    }
+
+    #[test]
+    fn truncates_wide_names_to_fit() {
+        let failure_summary = FailureSummary::new(&wide_test_failures(), SummaryGroupBy::Owner);
+        let rendered = SummaryRenderer::Plain.render(&failure_summary, 60);
+        assert_eq!(
+            rendered,
+            [
+                String::from("3 failures"),
+                String::from("  @チーム: 2 failures"),
+                String::from("    src/数学_test.rs: 2 failures"),
+                String::from("      - test_🚀_launch"),
+                format!("      - {}...", "测试".repeat(12)),
+                String::from("  unowned: 1 failure"),
+                String::from("    src/long_test.rs: 1 failure"),
+                format!("      - {}...", "a".repeat(49)),
+                String::from("Quarantined: 1 failure"),
+                String::from("  unowned: 1 failure"),
+                String::from("    unknown file: 1 failure"),
+                String::from("      - test_red second line"),
+            ]
+            .join("\n")
+        );
+        assert_fits(&rendered, 60);
+
+        let rendered = SummaryRenderer::Fancy.render(&failure_summary, 60);
+        assert_fits(&console::strip_ansi_codes(&rendered), 60);
+        assert!(rendered.contains(&format!("      • {}…", "a".repeat(51))));
+    }
+
+    #[test]
+    fn stacks_fields_on_narrow_terminals() {
+        let failure_summary = FailureSummary::new(&wide_test_failures(), SummaryGroupBy::Owner);
+        let rendered = SummaryRenderer::Plain.render(&failure_summary, 30);
+        assert_eq!(
+            rendered,
+            [
+                String::from("3 failures"),
+                String::from("owner: @チーム"),
+                String::from("failures: 2"),
+                String::from("file: src/数学_test.rs"),
+                String::from("failures: 2"),
+                String::from("test: test_🚀_launch"),
+                format!("test: {}...", "测试".repeat(5)),
+                String::from("owner: unowned"),
+                String::from("failures: 1"),
+                String::from("file: src/long_test.rs"),
+                String::from("failures: 1"),
+                format!("test: {}...", "a".repeat(21)),
+                String::from("Quarantined: 1 failure"),
+                String::from("owner: unowned"),
+                String::from("failures: 1"),
+                String::from("file: unknown file"),
+                String::from("failures: 1"),
+                String::from("test: test_red second line"),
+            ]
+            .join("\n")
+        );
+        assert_fits(&rendered, 30);
+    }
+
+    #[test]
+    fn renders_nothing_without_failures() {
+        let failure_summary = FailureSummary::new(&[], SummaryGroupBy::Owner);
+        assert!(failure_summary.is_empty());
+        assert_eq!(
+            SummaryRenderer::Plain.render(&failure_summary, DEFAULT_WIDTH),
+            ""
+        );
+    }
+}
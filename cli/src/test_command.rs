@@ -1,13 +1,21 @@
 use std::{
+    fs,
+    path::Path,
     process::{Command, Stdio},
     time::SystemTime,
 };
 
+use bundle::BundleMetaTestCommandStats;
 use clap::Args;
-use constants::EXIT_FAILURE;
+use constants::{EXIT_FAILURE, EXIT_SUCCESS};
+use serde::Serialize;
 
 use crate::{
     context::{gather_debug_props, gather_pre_test_context},
+    preflight::run_preflight,
+    shutdown::{self, ForwardSignals},
+    test_command_stats::{add_test_command_stats, wait_with_stats},
+    upload::UploadCancelled,
     upload_command::{run_upload, UploadArgs, UploadRunResult},
 };
 
@@ -16,42 +24,111 @@ pub struct TestArgs {
     #[command(flatten)]
     upload_args: UploadArgs,
     #[arg(
-        required = true,
+        required_unless_present_any = ["commands", "commands_file"],
+        conflicts_with_all = ["commands", "commands_file"],
         allow_hyphen_values = true,
         trailing_var_arg = true,
         help = "Test command to invoke."
     )]
     command: Vec<String>,
+    #[arg(
+        long = "command",
+        conflicts_with = "commands_file",
+        help = "Test command to invoke with the shell. Repeat to run multiple commands in order."
+    )]
+    commands: Vec<String>,
+    #[arg(
+        long,
+        help = "Path to a file of test commands to invoke with the shell, one per line."
+    )]
+    commands_file: Option<String>,
+    #[arg(
+        long,
+        help = "Keep running the remaining test commands after one fails."
+    )]
+    continue_on_failure: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct TestRunResult {
+    /// All commands that were run, joined the way a shell would have run them
     pub command: String,
     pub exec_start: SystemTime,
+    /// The exit code of the first command that failed, if any
+    pub exit_code: i32,
+    pub command_run_results: Vec<CommandRunResult>,
+    /// Resources used by all commands that were run
+    pub test_command_stats: BundleMetaTestCommandStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandRunResult {
+    pub command: String,
     pub exit_code: i32,
 }
 
 pub async fn run_test(
     TestArgs {
-        upload_args,
+        mut upload_args,
         command,
+        commands,
+        commands_file,
+        continue_on_failure,
     }: TestArgs,
 ) -> anyhow::Result<i32> {
+    if upload_args.multi_repo_config.is_some() {
+        return Err(anyhow::anyhow!(
+            "--multi-repo-config is only supported by upload and quarantine"
+        ));
+    }
+    // a journaled bundle holds the results of earlier tests, not those of the tests run now
+    if upload_args.resume {
+        return Err(anyhow::anyhow!("--resume is only supported by upload"));
+    }
+    // an invalid token fails the upload after the tests run, rather than the tests
+    let api_client = match upload_args.authenticate().await {
+        Ok(()) => upload_args.api_client(),
+        Err(e) => Err(e),
+    };
+    if let (false, Ok(api_client)) = (upload_args.no_preflight, &api_client) {
+        run_preflight(&upload_args.clone().into(), api_client).await?;
+    }
     let token = upload_args.token.clone();
-    let pre_test_context = gather_pre_test_context(upload_args.clone(), gather_debug_props(token))?;
+    let pre_test_context =
+        gather_pre_test_context(upload_args.clone().into(), gather_debug_props(token))?;
 
-    log::info!("running command: {:?}", command);
-    let test_run_result = run_test_command(&command).await?;
+    let test_run_result = if !command.is_empty() {
+        log::info!("running command: {:?}", command);
+        run_test_command(&command).await?
+    } else {
+        let commands = match commands_file {
+            Some(commands_file) => read_commands_file(commands_file)?,
+            None => commands,
+        };
+        run_test_commands(&commands, continue_on_failure).await?
+    };
     let test_run_result_exit_code = test_run_result.exit_code;
+    report_test_command_stats(&test_run_result.test_command_stats);
 
-    let upload_run_result =
-        run_upload(upload_args, Some(pre_test_context), Some(test_run_result)).await;
+    let upload_run_result = match api_client {
+        Ok(api_client) => {
+            run_upload(
+                upload_args,
+                &api_client,
+                Some(pre_test_context),
+                Some(test_run_result),
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
 
     upload_run_result
         .and_then(
             |UploadRunResult {
                  exit_code,
                  upload_bundle_error,
+                 ..
              }| {
                 if let Some(e) = upload_bundle_error {
                     return Err(e);
@@ -60,14 +137,135 @@ pub async fn run_test(
             },
         )
         .or_else(|e| {
-            log::error!("Error uploading test results: {:?}", e);
+            if e.is::<UploadCancelled>() {
+                log::warn!("Not uploading test results: {}", e);
+            } else {
+                log::error!("Error uploading test results: {:?}", e);
+            }
             Ok(test_run_result_exit_code)
         })
 }
 
+/// Attaches the resources used by the tests to the telemetry of the run, to correlate flaky tests
+/// with resource pressure.
+fn report_test_command_stats(test_command_stats: &BundleMetaTestCommandStats) {
+    let Ok(sentry::protocol::Value::Object(test_command_stats)) =
+        serde_json::to_value(test_command_stats)
+    else {
+        return;
+    };
+    sentry::configure_scope(|scope| {
+        scope.set_context(
+            "test_command_stats",
+            sentry::protocol::Context::Other(test_command_stats.into_iter().collect()),
+        );
+    });
+}
+
+/// Skips blank lines and `#` comments.
+fn read_commands_file<T: AsRef<Path>>(commands_file: T) -> anyhow::Result<Vec<String>> {
+    let commands_file = commands_file.as_ref();
+    let commands: Vec<String> = fs::read_to_string(commands_file)
+        .map_err(|e| anyhow::anyhow!("Failed to read commands file {:?}: {}", commands_file, e))?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+    if commands.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No test commands found in {:?}",
+            commands_file
+        ));
+    }
+    Ok(commands)
+}
+
+/// Runs each command with the shell in order, stopping at the first one that fails unless
+/// `continue_on_failure` is set. Test results are collected once all of them are done.
+pub async fn run_test_commands<T: AsRef<str>>(
+    commands: &[T],
+    continue_on_failure: bool,
+) -> anyhow::Result<TestRunResult> {
+    let exec_start = SystemTime::now();
+    let mut exit_code = EXIT_SUCCESS;
+    let mut command_run_results = Vec::with_capacity(commands.len());
+    let mut test_command_stats = BundleMetaTestCommandStats::default();
+    for command in commands {
+        if let Some(signal) = shutdown::received_signal() {
+            log::info!("Not running the remaining commands after {}", signal);
+            break;
+        }
+        let command = command.as_ref();
+        log::info!("running command: {:?}", command);
+        let command_run_result = run_test_command(&shell_command(command)).await?;
+        add_test_command_stats(
+            &mut test_command_stats,
+            &command_run_result.test_command_stats,
+        );
+        command_run_results.push(CommandRunResult {
+            command: String::from(command),
+            exit_code: command_run_result.exit_code,
+        });
+        if command_run_result.exit_code == EXIT_SUCCESS {
+            continue;
+        }
+        if exit_code == EXIT_SUCCESS {
+            exit_code = command_run_result.exit_code;
+        }
+        if !continue_on_failure {
+            break;
+        }
+    }
+    if command_run_results.len() < commands.len() && shutdown::received_signal().is_none() {
+        log::info!(
+            "Skipped {} commands after a command failed",
+            commands.len() - command_run_results.len()
+        );
+    }
+
+    Ok(TestRunResult {
+        command: commands
+            .iter()
+            .map(|command| command.as_ref())
+            .collect::<Vec<_>>()
+            .join(if continue_on_failure { "; " } else { " && " }),
+        exec_start,
+        exit_code,
+        command_run_results,
+        test_command_stats,
+    })
+}
+
+/// The exit code of each of the test commands, when more than one was run
+pub fn command_run_results_summary(command_run_results: &[CommandRunResult]) -> Option<String> {
+    if command_run_results.len() < 2 {
+        return None;
+    }
+    let mut summary = String::from("test commands:");
+    for command_run_result in command_run_results {
+        summary.push_str(&format!(
+            "\n  exit {:<3} {}",
+            command_run_result.exit_code, command_run_result.command
+        ));
+    }
+    Some(summary)
+}
+
+/// The program and arguments running `command` with the shell of the platform
+#[cfg(windows)]
+pub fn shell_command(command: &str) -> [&str; 3] {
+    ["cmd", "/C", command]
+}
+
+#[cfg(not(windows))]
+pub fn shell_command(command: &str) -> [&str; 3] {
+    ["sh", "-c", command]
+}
+
 pub async fn run_test_command<T: AsRef<str>>(command: &[T]) -> anyhow::Result<TestRunResult> {
     let exec_start = SystemTime::now();
-    let mut child = Command::new(command.first().map(|s| s.as_ref()).unwrap_or_default())
+    let child = Command::new(command.first().map(|s| s.as_ref()).unwrap_or_default())
         .args(
             command
                 .iter()
@@ -78,8 +276,10 @@ pub async fn run_test_command<T: AsRef<str>>(command: &[T]) -> anyhow::Result<Te
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .spawn()?;
-    let exit_code = child
-        .wait()
+    let forward_signals = ForwardSignals::to(child.id());
+    let (exit_status, test_command_stats) = wait_with_stats(child);
+    drop(forward_signals);
+    let exit_code = exit_status
         .map_or_else(
             |e| {
                 log::error!("Error waiting for execution: {}", e);
@@ -90,13 +290,19 @@ pub async fn run_test_command<T: AsRef<str>>(command: &[T]) -> anyhow::Result<Te
         .unwrap_or(EXIT_FAILURE);
     log::info!("Command exit code: {}", exit_code);
 
+    let command = command
+        .iter()
+        .map(|s| s.as_ref())
+        .collect::<Vec<_>>()
+        .join(" ");
     Ok(TestRunResult {
         exit_code,
         exec_start,
-        command: command
-            .iter()
-            .map(|s| s.as_ref())
-            .collect::<Vec<_>>()
-            .join(" "),
+        command_run_results: vec![CommandRunResult {
+            command: command.clone(),
+            exit_code,
+        }],
+        command,
+        test_command_stats,
     })
 }
@@ -0,0 +1,209 @@
+use std::{
+    io,
+    process::{Child, ExitStatus},
+    time::{Duration, Instant},
+};
+
+use bundle::BundleMetaTestCommandStats;
+
+const BYTES_PER_MIB: f64 = (1024 * 1024) as f64;
+const BYTES_PER_GIB: f64 = (1024 * 1024 * 1024) as f64;
+/// `ru_maxrss` is in bytes on macOS, and in kilobytes on the other unixes
+#[cfg(all(unix, target_os = "macos"))]
+const MAXRSS_UNIT_BYTES: u64 = 1;
+#[cfg(all(unix, not(target_os = "macos")))]
+const MAXRSS_UNIT_BYTES: u64 = 1024;
+
+/// Waits for the test command like `Child::wait`, also collecting the resources it used. Only the
+/// direct child is measured, along with the subprocesses it waited for on platforms that account
+/// them to it.
+pub fn wait_with_stats(child: Child) -> (io::Result<ExitStatus>, BundleMetaTestCommandStats) {
+    let started_at = Instant::now();
+    let (exit_status, mut test_command_stats) = wait_with_usage(child);
+    test_command_stats.wall_time_ms = started_at.elapsed().as_millis() as u64;
+    (exit_status, test_command_stats)
+}
+
+/// Reaps the child with `wait4`, which reports its usage without ptrace or elevated privileges.
+#[cfg(unix)]
+fn wait_with_usage(child: Child) -> (io::Result<ExitStatus>, BundleMetaTestCommandStats) {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pid = child.id() as libc::pid_t;
+    let mut status: libc::c_int = 0;
+    // SAFETY: rusage is plain old data, for which all zeroes is valid
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: both pointers are to live locals, and the child has not been reaped yet since
+        // `Child::wait` is never called on it
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } != -1 {
+            break;
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return (Err(e), BundleMetaTestCommandStats::default());
+        }
+    }
+
+    let test_command_stats = BundleMetaTestCommandStats {
+        peak_rss_bytes: Some(rusage.ru_maxrss as u64 * MAXRSS_UNIT_BYTES),
+        user_cpu_time_ms: Some(timeval_ms(rusage.ru_utime)),
+        system_cpu_time_ms: Some(timeval_ms(rusage.ru_stime)),
+        ..Default::default()
+    };
+    (Ok(ExitStatus::from_raw(status)), test_command_stats)
+}
+
+#[cfg(unix)]
+fn timeval_ms(timeval: libc::timeval) -> u64 {
+    timeval.tv_sec as u64 * 1000 + timeval.tv_usec as u64 / 1000
+}
+
+/// Usage isn't collected on Windows yet, so only the wall clock is recorded.
+#[cfg(not(unix))]
+fn wait_with_usage(mut child: Child) -> (io::Result<ExitStatus>, BundleMetaTestCommandStats) {
+    (child.wait(), BundleMetaTestCommandStats::default())
+}
+
+/// Sums the stats of commands run one after the other, keeping the highest peak RSS.
+pub fn add_test_command_stats(
+    total: &mut BundleMetaTestCommandStats,
+    test_command_stats: &BundleMetaTestCommandStats,
+) {
+    fn add(total: Option<u64>, value: Option<u64>) -> Option<u64> {
+        match (total, value) {
+            (Some(total), Some(value)) => Some(total + value),
+            (total, value) => total.or(value),
+        }
+    }
+
+    total.wall_time_ms += test_command_stats.wall_time_ms;
+    total.peak_rss_bytes = total.peak_rss_bytes.max(test_command_stats.peak_rss_bytes);
+    total.user_cpu_time_ms = add(total.user_cpu_time_ms, test_command_stats.user_cpu_time_ms);
+    total.system_cpu_time_ms = add(
+        total.system_cpu_time_ms,
+        test_command_stats.system_cpu_time_ms,
+    );
+}
+
+/// e.g. `tests used 3.2 GiB peak RSS, 41 min CPU over 12 min wall`
+pub fn test_command_stats_summary(test_command_stats: &BundleMetaTestCommandStats) -> String {
+    let wall_time = format_duration(Duration::from_millis(test_command_stats.wall_time_ms));
+    let cpu_time_ms = match (
+        test_command_stats.user_cpu_time_ms,
+        test_command_stats.system_cpu_time_ms,
+    ) {
+        (None, None) => None,
+        (user_cpu_time_ms, system_cpu_time_ms) => {
+            Some(user_cpu_time_ms.unwrap_or_default() + system_cpu_time_ms.unwrap_or_default())
+        }
+    };
+    let mut usage = Vec::new();
+    if let Some(peak_rss_bytes) = test_command_stats.peak_rss_bytes {
+        usage.push(format!("{} peak RSS", format_bytes(peak_rss_bytes)));
+    }
+    if let Some(cpu_time_ms) = cpu_time_ms {
+        usage.push(format!(
+            "{} CPU",
+            format_duration(Duration::from_millis(cpu_time_ms))
+        ));
+    }
+    if usage.is_empty() {
+        format!("tests ran for {} wall", wall_time)
+    } else {
+        format!("tests used {} over {} wall", usage.join(", "), wall_time)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= BYTES_PER_GIB {
+        format!("{:.1} GiB", bytes / BYTES_PER_GIB)
+    } else {
+        format!("{:.1} MiB", bytes / BYTES_PER_MIB)
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration >= Duration::from_secs(60) {
+        format!("{} min", (duration.as_secs_f64() / 60.0).round())
+    } else {
+        format!("{:.1} s", duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_usage() {
+        let test_command_stats = BundleMetaTestCommandStats {
+            wall_time_ms: 12 * 60 * 1000,
+            peak_rss_bytes: Some(3_435_973_837),
+            user_cpu_time_ms: Some(38 * 60 * 1000),
+            system_cpu_time_ms: Some(3 * 60 * 1000),
+        };
+        assert_eq!(
+            test_command_stats_summary(&test_command_stats),
+            "tests used 3.2 GiB peak RSS, 41 min CPU over 12 min wall"
+        );
+    }
+
+    #[test]
+    fn summarizes_wall_clock_only() {
+        let test_command_stats = BundleMetaTestCommandStats {
+            wall_time_ms: 1500,
+            ..Default::default()
+        };
+        assert_eq!(
+            test_command_stats_summary(&test_command_stats),
+            "tests ran for 1.5 s wall"
+        );
+    }
+
+    #[test]
+    fn adds_commands_run_in_order() {
+        let mut total = BundleMetaTestCommandStats::default();
+        add_test_command_stats(
+            &mut total,
+            &BundleMetaTestCommandStats {
+                wall_time_ms: 100,
+                peak_rss_bytes: Some(200),
+                user_cpu_time_ms: Some(10),
+                system_cpu_time_ms: Some(1),
+            },
+        );
+        add_test_command_stats(
+            &mut total,
+            &BundleMetaTestCommandStats {
+                wall_time_ms: 50,
+                peak_rss_bytes: Some(100),
+                user_cpu_time_ms: Some(5),
+                system_cpu_time_ms: Some(2),
+            },
+        );
+        assert_eq!(
+            total,
+            BundleMetaTestCommandStats {
+                wall_time_ms: 150,
+                peak_rss_bytes: Some(200),
+                user_cpu_time_ms: Some(15),
+                system_cpu_time_ms: Some(3),
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn collects_usage_of_the_child() {
+        let child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let (exit_status, test_command_stats) = wait_with_stats(child);
+        assert_eq!(exit_status.unwrap().code(), Some(3));
+        assert!(test_command_stats.peak_rss_bytes.unwrap() > 0);
+        assert!(test_command_stats.user_cpu_time_ms.is_some());
+    }
+}
@@ -0,0 +1,1178 @@
+//! Uploads test results to Trunk Flaky Tests from another Rust program, instead of shelling out to
+//! the `trunk-analytics-cli` binary.
+//!
+//! # Stability
+//!
+//! [`upload_bundle`], [`upload_bundle_with_api_client`] and the types they take and return are the
+//! only supported library API of this crate. Everything else is an implementation detail of the binary and may change in any release.
+//!
+//! Fields and variants may be added to [`UploadConfig`], [`UploadOutcome`] and [`UploadEvent`] in
+//! any release, which is why they are `#[non_exhaustive]`: create an [`UploadConfig`] with
+//! [`UploadConfig::new`] and set its fields, and match [`UploadEvent`]s with a wildcard arm.
+//! Removing a field or variant, or changing what it means, is called out in the release notes.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+pub use api::client::ApiClientTrait;
+use api::{
+    client::{ApiClient, ApiClientTimeouts},
+    error_code::{ErrorCode, WithErrorCode},
+    message::{BundleUploadStatus, CreateBundleUploadResponse},
+    upload_progress::UploadProgress,
+};
+use bundle::{rewrite_tarball_meta, BundleMeta, BundlerUtil, FileSet};
+pub use bundle::{BundleMetaSkippedFile, FileDecision, QuarantineOutcome};
+use chrono::Utc;
+use clap::ValueEnum;
+use constants::{EXIT_SUCCESS, GITHUB_EXTERNAL_ID_ENV};
+pub use context::junit::junit_path::JunitPathWithVariant;
+use context::{
+    bazel_bep::parser::BepParseResult,
+    env::parser::StableBranches,
+    junit::{
+        internal_bin::shard_internal_bin, merge::JunitMergeGrouping, parser::JunitParser,
+        validator::DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD,
+    },
+    repo::BundleRepo,
+};
+use github_actions::{extract_github_external_id, ExternalIdOptions};
+use prost::Message;
+use proto::test_context::test_run::TestResult;
+use tempfile::TempDir;
+pub use tokio_util::sync::CancellationToken;
+use xcresult::XCResultSummary;
+
+pub use crate::cli_version::CliOutdated;
+pub use crate::datadog_export::ExportFormat;
+pub use crate::quarantine_audit::{
+    NearestTest, QuarantineAudit, QuarantinedFailure, UnmatchedQuarantinedTest,
+};
+use crate::{
+    cli_version::check_cli_version,
+    context::{
+        bundle_upload_request, convert_test_runner_output, gather_debug_props,
+        gather_exit_code_and_quarantined_tests_context, gather_post_test_context,
+        gather_pre_test_context, gather_repo, gather_test_failures, gather_upload_id_context,
+        set_internal_bin_ci_job_links, set_internal_bin_source_file, PreTestContext,
+    },
+    context_quarantine::FailedTestsExtractor,
+    datadog_export::export_to_datadog,
+    dry_run::{
+        prepare_dry_run_output_dir, write_file_decisions, DryRunSummary, DRY_RUN_BUNDLE_FILE_NAME,
+    },
+    local_flake_detection::apply_local_flake_detection,
+    phase_timer::{PhaseTimer, UploadPhase},
+    summary::TestFailure,
+    test_command::TestRunResult,
+    upload_journal::{UploadJournal, UploadJournalKey, UploadJournalState},
+};
+
+/// What to upload, and where. Unlike the CLI, nothing is read from arguments, so every option is
+/// set here.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct UploadConfig {
+    /// Glob paths to junit files
+    pub junit_paths: Vec<String>,
+    /// Glob paths to junit files whose tests belong to a variant other than `variant`
+    pub junit_paths_with_variant: Vec<JunitPathWithVariant>,
+    /// Path to a bazel build event protocol JSON file, which takes the place of `junit_paths`
+    pub bazel_bep_path: Option<String>,
+    /// Paths to internal bin files of serialized test results
+    pub internal_bin_paths: Vec<String>,
+    #[cfg(target_os = "macos")]
+    pub xcresult_path: Option<String>,
+    pub org_url_slug: String,
+    pub token: String,
+    /// Address of Trunk services. Defaults to `TRUNK_PUBLIC_API_ADDRESS`, then to production.
+    pub api_address: Option<String>,
+    /// Path to the repository root. Defaults to the current directory.
+    pub repo_root: Option<String>,
+    pub repo_url: Option<String>,
+    pub repo_head_sha: Option<String>,
+    pub repo_head_branch: Option<String>,
+    /// Commit epoch of the repository head, in seconds or as an ISO-8601 datetime
+    pub repo_head_commit_epoch: Option<String>,
+    pub repo_head_author_name: Option<String>,
+    pub repo_head_author_email: Option<String>,
+    pub repo_head_commit_message: Option<String>,
+    /// Builds the repo from `repo_url` alone instead of reading git, leaving out the head sha,
+    /// branch, commit and author
+    pub repo_less: bool,
+    /// Custom `tag=value` pairs
+    pub tags: Vec<String>,
+    /// Prints the files to upload
+    pub print_files: bool,
+    /// Bundles the test results without uploading them
+    pub no_upload: bool,
+    /// Registers a skipped upload with Trunk as a dry run
+    pub register_dry_run: bool,
+    /// Directory to write the bundle and `summary.json` of a dry run to. Without it, the bundle
+    /// of a dry run is written to a temp dir that is removed.
+    pub dry_run_output: Option<String>,
+    /// Write to `dry_run_output` even if it is not empty
+    pub force_dry_run_output: bool,
+    pub team: Option<String>,
+    /// Variant of the tests, e.g. the platform they ran on, which gives them IDs of their own
+    pub variant: Option<String>,
+    /// CODEOWNERS file or directory path. Defaults to the standard locations in the repository.
+    pub codeowners_path: Option<String>,
+    /// Branches classified as protected when the CI info is parsed from the env
+    pub stable_branches: StableBranches,
+    pub use_quarantining: bool,
+    /// Do not fail if no test results are found
+    pub allow_empty_test_results: bool,
+    pub api_timeout_secs: Option<u64>,
+    pub s3_timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    /// Whether the BEP events are embedded in the bundle
+    pub include_raw_test_runner_output: RawTestRunnerOutputInclusion,
+    /// Fail instead of warning when more than `duplicate_id_threshold` test cases share an `id`
+    pub fail_on_duplicate_ids: bool,
+    /// Number of test cases that may share an `id` before it is reported. Defaults to
+    /// [`DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD`].
+    pub duplicate_id_threshold: Option<usize>,
+    /// Fail instead of warning when the CLI is older than the minimum version recommended by the
+    /// org
+    pub fail_if_outdated: bool,
+    /// Fetch the quarantine config even without failures, to audit it against the uploaded tests
+    pub quarantine_audit: bool,
+    /// Whether junit files are merged before bundling, for runners that write one per test case
+    pub merge_junit_files: MergeJunitFiles,
+    /// Leave out the files matched by the junit globs that are ignored by git
+    pub respect_gitignore: bool,
+    /// Leave out the files matched by the junit globs that were last modified longer ago
+    pub junit_max_age: Option<Duration>,
+    /// State file of the experimental local flake detection, which treats failed tests with a
+    /// flaky history as quarantined without reporting them to Trunk as such
+    pub local_flake_detection: Option<String>,
+    /// Which env vars are captured into the bundle
+    pub env_capture_profile: EnvCaptureProfile,
+    /// Bundle the junit files as they are instead of sanitizing them
+    pub no_sanitize_junit: bool,
+    /// Keep the texts of the junit files that are longer than Trunk keeps when sanitizing them
+    pub no_truncate_junit_text: bool,
+    /// How much of the bundle, in percent, has to be uploaded for a cancelled upload to be
+    /// finished rather than aborted. Defaults to [`DEFAULT_FINISH_UPLOAD_ON_CANCEL_PERCENT`].
+    pub finish_upload_on_cancel_percent: Option<u8>,
+    /// How long a cancelled upload may take to finish. Defaults to
+    /// [`DEFAULT_CANCEL_GRACE_PERIOD`].
+    pub cancel_grace_period: Option<Duration>,
+    /// Where the parsed test results are also exported to, after the upload. Failing to export
+    /// never fails the upload.
+    pub export: Option<ExportFormat>,
+    /// File the export is written to
+    pub export_output: Option<String>,
+    /// Keep the bundle and a journal of its upload in the `.trunk` directory of the repo until it
+    /// is put, and put the bundle of a journaled upload of the same test results instead of
+    /// bundling them again. See [`crate::upload_journal`].
+    pub resume: bool,
+    /// Only quarantine the failed tests of the quarantine config, not those the test runner marked
+    /// as quarantined
+    pub ignore_runner_quarantine_marks: bool,
+    /// Memory-map large junit files to parse them instead of reading them through a buffer
+    pub mmap_junit_files: bool,
+    /// Do not use path-like test case classnames as the test file when file and filepath are
+    /// missing, see [`JunitParser::with_infer_file_from_classname`]
+    pub no_infer_file_from_classname: bool,
+    /// Largest encoded size of the internal bin, above which it is sharded across several files of
+    /// the bundle. Defaults to [`DEFAULT_INTERNAL_BIN_MAX_BYTES`].
+    pub internal_bin_max_bytes: Option<u64>,
+    /// Command converting each file matched by `converter_input_paths` to junit, see
+    /// [`crate::converter::Converter`]
+    pub converter: Option<String>,
+    /// Globs of the test result files the converter is run on
+    pub converter_input_paths: Vec<String>,
+    /// How long the converter may take per file. Defaults to
+    /// [`crate::converter::DEFAULT_CONVERTER_TIMEOUT`].
+    pub converter_timeout: Option<Duration>,
+}
+
+pub const DEFAULT_FINISH_UPLOAD_ON_CANCEL_PERCENT: u8 = 80;
+pub const DEFAULT_CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+/// How often the progress of putting a bundle to S3 is logged and reported
+pub const S3_PUT_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+pub const DEFAULT_INTERNAL_BIN_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Largest raw test runner output that is embedded in the bundle with
+/// [`RawTestRunnerOutputInclusion::Auto`]
+pub const RAW_TEST_RUNNER_OUTPUT_AUTO_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Whether the raw output of the test runner, like a BEP file, is embedded in the bundle. Outputs
+/// that are left out are still recorded in the bundle meta with their size and hash.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RawTestRunnerOutputInclusion {
+    /// Only when it is at most 50MB
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl RawTestRunnerOutputInclusion {
+    pub fn includes(&self, size_bytes: u64, auto_max_bytes: u64) -> bool {
+        match self {
+            RawTestRunnerOutputInclusion::Auto => size_bytes <= auto_max_bytes,
+            RawTestRunnerOutputInclusion::Always => true,
+            RawTestRunnerOutputInclusion::Never => false,
+        }
+    }
+}
+
+/// How the junit files of a file set are merged before bundling. Merged files are written in place
+/// of the originals, and split again when they exceed 10MB.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MergeJunitFiles {
+    /// Keep the files as they are
+    #[default]
+    Off,
+    /// A file per test suite name
+    BySuite,
+    /// A single file
+    All,
+}
+
+impl MergeJunitFiles {
+    pub fn grouping(&self) -> Option<JunitMergeGrouping> {
+        match self {
+            MergeJunitFiles::Off => None,
+            MergeJunitFiles::BySuite => Some(JunitMergeGrouping::BySuite),
+            MergeJunitFiles::All => Some(JunitMergeGrouping::All),
+        }
+    }
+}
+
+/// Which env vars are captured into the bundle meta
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EnvCaptureProfile {
+    /// Only the env vars the CI info was parsed from, and `CI`
+    CiOnly,
+    /// The known CI env vars
+    #[default]
+    Standard,
+    /// Every env var but the ones whose name looks like a secret, with the token scrubbed, for
+    /// debugging
+    Full,
+}
+
+impl EnvCaptureProfile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnvCaptureProfile::CiOnly => "ci-only",
+            EnvCaptureProfile::Standard => "standard",
+            EnvCaptureProfile::Full => "full",
+        }
+    }
+}
+
+impl UploadConfig {
+    /// A config with the same defaults as the CLI, i.e. quarantining on and empty test results
+    /// allowed.
+    pub fn new<T: Into<String>, U: Into<String>>(token: T, org_url_slug: U) -> Self {
+        Self {
+            token: token.into(),
+            org_url_slug: org_url_slug.into(),
+            use_quarantining: true,
+            allow_empty_test_results: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn api_client_timeouts(&self) -> ApiClientTimeouts {
+        let default = ApiClientTimeouts::default();
+        ApiClientTimeouts {
+            api: self
+                .api_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.api),
+            s3_read: self
+                .s3_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.s3_read),
+            connect: self
+                .connect_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(default.connect),
+        }
+    }
+
+    /// Client for the Trunk services at `api_address`, or `TRUNK_PUBLIC_API_ADDRESS` if unset.
+    pub fn api_client(&self) -> anyhow::Result<ApiClient> {
+        let api_client = ApiClient::new_with_timeouts(&self.token, self.api_client_timeouts())?;
+        Ok(match self.api_address.as_ref() {
+            Some(api_address) => api_client.with_host(api_address),
+            None => api_client,
+        })
+    }
+
+    /// The parser of the junit files of the repo at `repo_root`, for the bundle meta as well as for
+    /// quarantining so that both derive the same test IDs
+    pub(crate) fn junit_parser(&self, repo_root: &str) -> JunitParser {
+        JunitParser::with_repo_root(repo_root)
+            .with_infer_file_from_classname(!self.no_infer_file_from_classname)
+            .with_mmap(self.mmap_junit_files)
+    }
+}
+
+/// Progress of an upload, reported to a [`ProgressSink`] as it happens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UploadEvent {
+    PhaseStarted(UploadPhase),
+    PhaseFinished {
+        phase: UploadPhase,
+        duration: Duration,
+    },
+    /// Why each file matched by the junit globs is bundled or not, once they have been scanned
+    FilesDecided(Vec<FileDecision>),
+    /// The files that will be bundled, once test results have been scanned
+    FilesCollected(Vec<FileSet>),
+    /// Every failed test, once quarantining has run
+    TestFailures(Vec<TestFailure>),
+    /// The files left out of the bundle because they could not be read, once it is made
+    FilesSkipped(Vec<BundleMetaSkippedFile>),
+    /// A bundle upload was registered with Trunk
+    UploadCreated {
+        id: String,
+    },
+    /// Bytes of the bundle put to S3 so far, every [`S3_PUT_PROGRESS_INTERVAL`] while it is put
+    S3PutProgress {
+        bytes_sent: u64,
+        total_bytes: u64,
+    },
+}
+
+/// Receives the progress of an upload. Events are reported from the task running the upload, so
+/// implementations should return quickly.
+pub trait ProgressSink: Send + Sync {
+    fn on_event(&self, event: UploadEvent);
+}
+
+/// Ignores all progress.
+impl ProgressSink for () {
+    fn on_event(&self, _event: UploadEvent) {}
+}
+
+/// The error, wrapped in an [`anyhow::Error`], of an upload cancelled through its
+/// [`CancellationToken`]. Check for it with `error.is::<UploadCancelled>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadCancelled;
+
+impl fmt::Display for UploadCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Upload was cancelled")
+    }
+}
+
+impl std::error::Error for UploadCancelled {}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct UploadOutcome {
+    /// Exit code of the tests after quarantining, which is known even if the upload failed
+    pub exit_code: i32,
+    /// Whether failures were quarantined, which explains `exit_code`
+    pub quarantine_outcome: QuarantineOutcome,
+    /// Quarantined test IDs that matched no test case of the upload, if the quarantine config was
+    /// fetched
+    pub quarantine_audit: Option<QuarantineAudit>,
+    /// Id of the bundle upload registered with Trunk, unless the upload was skipped
+    pub bundle_upload_id: Option<String>,
+    /// Why the bundle could not be uploaded, if it couldn't
+    pub upload_error: Option<anyhow::Error>,
+    pub phase_timer: PhaseTimer,
+    /// What would have been uploaded, for a dry run
+    pub dry_run_summary: Option<DryRunSummary>,
+    /// Whether the CLI is older than the minimum version recommended by the org, if Trunk
+    /// recommends one and the versions could be compared
+    pub cli_outdated: Option<bool>,
+    /// The repo and head commit the results are for
+    pub repo: BundleRepo,
+    /// Whether the bundle of a journaled upload was uploaded instead, see
+    /// [`UploadConfig::resume`]. The test results are then not collected again, and `exit_code`
+    /// and `quarantine_outcome` are those of the run that journaled the upload.
+    pub resumed: bool,
+}
+
+/// Collects test results, quarantines failed tests and uploads the bundle to Trunk.
+///
+/// Cancellation is checked between phases and while the bundle is being uploaded, and returns an
+/// [`UploadCancelled`] error. A bundle that is mostly uploaded when the upload is cancelled is
+/// given a grace period to finish instead, see [`UploadConfig::finish_upload_on_cancel_percent`].
+/// An upload cancelled after it was registered with Trunk is marked as failed.
+pub async fn upload_bundle(
+    config: UploadConfig,
+    cancellation_token: CancellationToken,
+    progress_sink: &dyn ProgressSink,
+) -> anyhow::Result<UploadOutcome> {
+    let api_client = config.api_client()?;
+    upload(
+        config,
+        &api_client,
+        None,
+        None,
+        &cancellation_token,
+        progress_sink,
+    )
+    .await
+}
+
+/// Like [`upload_bundle`], but makes its calls to Trunk services and S3 through `api_client`
+/// instead of over the network, so that the `token` and `api_address` of `config` are unused.
+pub async fn upload_bundle_with_api_client(
+    config: UploadConfig,
+    api_client: &dyn ApiClientTrait,
+    cancellation_token: CancellationToken,
+    progress_sink: &dyn ProgressSink,
+) -> anyhow::Result<UploadOutcome> {
+    upload(
+        config,
+        api_client,
+        None,
+        None,
+        &cancellation_token,
+        progress_sink,
+    )
+    .await
+}
+
+pub(crate) async fn upload(
+    config: UploadConfig,
+    api_client: &dyn ApiClientTrait,
+    pre_test_context: Option<PreTestContext>,
+    test_run_result: Option<TestRunResult>,
+    cancellation_token: &CancellationToken,
+    progress_sink: &dyn ProgressSink,
+) -> anyhow::Result<UploadOutcome> {
+    let mut phases = UploadPhases {
+        phase_timer: PhaseTimer::new(),
+        cancellation_token,
+        progress_sink,
+    };
+    if let (true, Some(dry_run_output)) = (config.no_upload, config.dry_run_output.as_ref()) {
+        prepare_dry_run_output_dir(dry_run_output, config.force_dry_run_output)?;
+    }
+
+    phases.check_cancelled()?;
+    // checked before the test results are collected, which a resumed upload doesn't need
+    if config.resume && !config.no_upload && test_run_result.is_none() {
+        let repo = match pre_test_context.as_ref() {
+            Some(pre_test_context) => pre_test_context.meta.base_props.repo.clone(),
+            None => gather_repo(&config)?,
+        };
+        if let Some(journal) = UploadJournal::read_resumable(
+            &repo.repo_root,
+            &UploadJournalKey::new(&repo, &config),
+            Utc::now().timestamp(),
+        ) {
+            return resume_upload(journal, repo, api_client, &config, phases).await;
+        }
+    }
+    let PreTestContext {
+        mut meta,
+        mut junit_path_wrappers,
+        bep_result,
+        mut internal_bin,
+        internal_bin_junit_path,
+        xcresult_summary,
+        // directory is removed on drop
+        mut junit_path_wrappers_temp_dir,
+        pending_conversion,
+    } = if let Some(pre_test_context) = pre_test_context {
+        pre_test_context
+    } else {
+        gather_pre_test_context(config.clone(), gather_debug_props(config.token.clone()))?
+    };
+    // the bundles of a multi-repo upload share the one looked up for all of them
+    if !meta.base_props.envs.contains_key(GITHUB_EXTERNAL_ID_ENV) {
+        if let Some(external_id) = extract_github_external_id(&ExternalIdOptions::from_env()).await
+        {
+            meta.base_props
+                .envs
+                .insert(String::from(GITHUB_EXTERNAL_ID_ENV), external_id);
+        }
+    }
+
+    let phase_start = phases.start(UploadPhase::JunitScanAndParse)?;
+    if let Some(pending_conversion) = pending_conversion {
+        convert_test_runner_output(
+            &mut meta,
+            &mut junit_path_wrappers,
+            &mut junit_path_wrappers_temp_dir,
+            pending_conversion,
+        )?;
+    }
+    let repo_root = meta.base_props.repo.repo_root.clone();
+    let (file_set_builder, reports, num_tests_by_path) = gather_post_test_context(
+        &mut meta,
+        junit_path_wrappers,
+        &config.team,
+        &config.codeowners_path,
+        config.allow_empty_test_results,
+        config
+            .duplicate_id_threshold
+            .unwrap_or(DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD),
+        &test_run_result,
+        config.merge_junit_files,
+        config.respect_gitignore,
+        config.junit_max_age,
+        &|| config.junit_parser(&repo_root),
+        progress_sink,
+    )?;
+    if let (true, Some(dry_run_output)) = (config.no_upload, config.dry_run_output.as_ref()) {
+        let file_decisions_path =
+            write_file_decisions(dry_run_output, file_set_builder.file_decisions())?;
+        log::info!("Wrote file decisions to {:?}", file_decisions_path);
+    }
+    if config.fail_on_duplicate_ids && !meta.duplicate_test_case_ids.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Test cases share ids: {}",
+            meta.duplicate_test_case_ids
+                .iter()
+                .map(|duplicate| format!("{} ({} test cases)", duplicate.id, duplicate.count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    phases.finish(UploadPhase::JunitScanAndParse, phase_start);
+    progress_sink.on_event(UploadEvent::FilesCollected(
+        meta.base_props.file_sets.clone(),
+    ));
+
+    let phase_start = phases.start(UploadPhase::QuarantineConfigFetch)?;
+    let failed_tests_extractor = FailedTestsExtractor::new(
+        &meta.base_props.repo.repo,
+        &meta.base_props.org,
+        file_set_builder.file_sets(),
+        || config.junit_parser(&repo_root),
+    )
+    .with_runner_quarantine_marks(!config.ignore_runner_quarantine_marks);
+    let (exit_code, quarantine_outcome, quarantine_audit) =
+        gather_exit_code_and_quarantined_tests_context(
+            &mut meta,
+            config.use_quarantining,
+            api_client,
+            &file_set_builder,
+            &failed_tests_extractor,
+            &test_run_result,
+            config.quarantine_audit,
+        )
+        .await;
+    let exit_code = match config.local_flake_detection.as_ref() {
+        Some(state_path) => {
+            apply_local_flake_detection(state_path, &mut meta, &failed_tests_extractor, exit_code)
+        }
+        None => exit_code,
+    };
+    phases.finish(UploadPhase::QuarantineConfigFetch, phase_start);
+
+    let phase_start = phases.start(UploadPhase::CodeownersAssociation)?;
+    let test_failures = gather_test_failures(&meta, &failed_tests_extractor);
+    phases.finish(UploadPhase::CodeownersAssociation, phase_start);
+    progress_sink.on_event(UploadEvent::TestFailures(test_failures));
+
+    let phase_start = phases.start(UploadPhase::InternalBinGeneration)?;
+    if let (Some(internal_bin), Some(internal_bin_junit_path)) =
+        (internal_bin.as_mut(), internal_bin_junit_path.as_ref())
+    {
+        set_internal_bin_source_file(
+            internal_bin,
+            internal_bin_junit_path,
+            &meta.base_props.file_sets,
+        );
+    }
+    if let Some(internal_bin) = internal_bin.as_mut() {
+        set_internal_bin_ci_job_links(internal_bin, &meta.base_props.envs, &config.stable_branches);
+    }
+    let bundle_extras = BundleExtras::new(
+        bep_result,
+        internal_bin,
+        xcresult_summary,
+        num_tests_by_path,
+        config
+            .internal_bin_max_bytes
+            .unwrap_or(DEFAULT_INTERNAL_BIN_MAX_BYTES),
+    )?;
+    phases.finish(UploadPhase::InternalBinGeneration, phase_start);
+
+    // only kept around for the export
+    let reports = config.export.map(|_| reports);
+    let repo = meta.base_props.repo.clone();
+    let upload_bundle_result = upload_tarball(
+        meta,
+        api_client,
+        bundle_extras,
+        &config,
+        exit_code,
+        quarantine_outcome,
+        &mut phases,
+    )
+    .await;
+    phases.phase_timer.log_summary();
+
+    if let (Some(ExportFormat::Datadog), Some(reports)) = (config.export, reports.as_ref()) {
+        export_to_datadog(
+            reports,
+            &repo,
+            &config.stable_branches,
+            config.export_output.as_deref(),
+            config.api_client_timeouts().api,
+        )
+        .await;
+    }
+
+    let ((bundle_upload_id, dry_run_summary, cli_outdated), upload_error) =
+        match upload_bundle_result {
+            Ok(upload_result) => (upload_result, None),
+            Err(e) if e.is::<UploadCancelled>() || ErrorCode::of(&e) == ErrorCode::CliOutdated => {
+                return Err(e)
+            }
+            Err(e) => ((None, None, None), Some(e)),
+        };
+    Ok(UploadOutcome {
+        exit_code,
+        quarantine_outcome,
+        quarantine_audit,
+        bundle_upload_id,
+        upload_error,
+        phase_timer: phases.phase_timer,
+        dry_run_summary,
+        cli_outdated,
+        repo,
+        resumed: false,
+    })
+}
+
+/// Times each phase of an upload, reports it to the progress sink, and checks for cancellation
+/// before it starts.
+struct UploadPhases<'a> {
+    phase_timer: PhaseTimer,
+    cancellation_token: &'a CancellationToken,
+    progress_sink: &'a dyn ProgressSink,
+}
+
+impl UploadPhases<'_> {
+    fn check_cancelled(&self) -> anyhow::Result<()> {
+        if self.cancellation_token.is_cancelled() {
+            return Err(UploadCancelled.into());
+        }
+        Ok(())
+    }
+
+    fn start(&self, phase: UploadPhase) -> anyhow::Result<Instant> {
+        self.check_cancelled()?;
+        self.progress_sink
+            .on_event(UploadEvent::PhaseStarted(phase));
+        Ok(Instant::now())
+    }
+
+    fn finish(&mut self, phase: UploadPhase, phase_start: Instant) {
+        self.phase_timer.record(phase, phase_start);
+        self.progress_sink.on_event(UploadEvent::PhaseFinished {
+            phase,
+            duration: phase_start.elapsed(),
+        });
+    }
+}
+
+/// Everything bundled alongside the meta and the test result files.
+struct BundleExtras {
+    bep_result: Option<BepParseResult>,
+    internal_bin_shards: Vec<Vec<u8>>,
+    xcresult_summary: Option<Vec<u8>>,
+    num_tests_by_path: HashMap<String, usize>,
+}
+
+impl BundleExtras {
+    /// The internal bin is sharded when it encodes to more than `internal_bin_max_bytes`
+    fn new(
+        bep_result: Option<BepParseResult>,
+        internal_bin: Option<TestResult>,
+        xcresult_summary: Option<XCResultSummary>,
+        num_tests_by_path: HashMap<String, usize>,
+        internal_bin_max_bytes: u64,
+    ) -> anyhow::Result<Self> {
+        let internal_bin_max_bytes = usize::try_from(internal_bin_max_bytes).unwrap_or(usize::MAX);
+        let internal_bin_shards: Vec<Vec<u8>> = internal_bin
+            .map(|internal_bin| shard_internal_bin(internal_bin, internal_bin_max_bytes))
+            .unwrap_or_default()
+            .iter()
+            .map(Message::encode_to_vec)
+            .collect();
+        if internal_bin_shards.len() > 1 {
+            log::info!(
+                "Sharded the internal bin into {} files of at most {} bytes",
+                internal_bin_shards.len(),
+                internal_bin_max_bytes
+            );
+        }
+        Ok(Self {
+            bep_result,
+            internal_bin_shards,
+            xcresult_summary: xcresult_summary
+                .map(|xcresult_summary| serde_json::to_vec(&xcresult_summary))
+                .transpose()?,
+            num_tests_by_path,
+        })
+    }
+
+    fn into_bundler(self, meta: BundleMeta, config: &UploadConfig) -> BundlerUtil {
+        BundlerUtil::new(meta, self.bep_result)
+            .with_internal_bin_shards(self.internal_bin_shards)
+            .with_xcresult_summary(self.xcresult_summary)
+            .with_sanitize_junit(!config.no_sanitize_junit)
+            .with_truncate_junit_text(!config.no_truncate_junit_text)
+            .with_num_tests_by_path(self.num_tests_by_path)
+    }
+}
+
+/// The tarball of a bundle and, for a dry run, the summary of what would have been uploaded
+struct Tarball {
+    path: PathBuf,
+    // directory is removed on drop
+    _temp_dir: Option<TempDir>,
+    dry_run_summary: Option<DryRunSummary>,
+}
+
+/// The tarball of a dry run is kept in `dry_run_output` when it is set, next to its summary. The
+/// tarball of an upload that can be resumed is kept in the `.trunk` directory of the repo.
+fn make_tarball(
+    meta: BundleMeta,
+    bundle_extras: BundleExtras,
+    config: &UploadConfig,
+    progress_sink: &dyn ProgressSink,
+) -> anyhow::Result<Tarball> {
+    let dry_run_output = config
+        .dry_run_output
+        .as_ref()
+        .filter(|_| config.no_upload)
+        .map(Path::new);
+    let journal_tarball_path = (config.resume && !config.no_upload)
+        .then(|| UploadJournal::tarball_path(&meta.base_props.repo.repo_root));
+    let mut bundler = bundle_extras.into_bundler(meta, config);
+    let (path, temp_dir) = if let Some(dry_run_output) = dry_run_output {
+        let path = dry_run_output.join(DRY_RUN_BUNDLE_FILE_NAME);
+        bundler.make_tarball(&path)?;
+        (path, None)
+    } else if let Some(path) = journal_tarball_path {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        bundler.make_tarball(&path)?;
+        (path, None)
+    } else {
+        let (path, temp_dir) = bundler.make_tarball_in_temp_dir()?;
+        (path, Some(temp_dir))
+    };
+
+    let meta = bundler.meta();
+    if !meta.skipped_files.is_empty() {
+        progress_sink.on_event(UploadEvent::FilesSkipped(meta.skipped_files.clone()));
+    }
+
+    let dry_run_summary = config
+        .no_upload
+        .then(|| -> anyhow::Result<DryRunSummary> {
+            let dry_run_summary = DryRunSummary::new(meta, std::fs::metadata(&path)?.len());
+            if let Some(dry_run_output) = dry_run_output {
+                let summary_path = dry_run_summary.write_to_dir(dry_run_output)?;
+                log::info!("Wrote dry run summary to {:?}", summary_path);
+            }
+            Ok(dry_run_summary)
+        })
+        .transpose()?;
+
+    Ok(Tarball {
+        path,
+        _temp_dir: temp_dir,
+        dry_run_summary,
+    })
+}
+
+async fn upload_tarball(
+    mut meta: BundleMeta,
+    api_client: &dyn ApiClientTrait,
+    bundle_extras: BundleExtras,
+    config: &UploadConfig,
+    exit_code: i32,
+    quarantine_outcome: QuarantineOutcome,
+    phases: &mut UploadPhases<'_>,
+) -> anyhow::Result<(Option<String>, Option<DryRunSummary>, Option<bool>)> {
+    if config.no_upload && !config.register_dry_run {
+        phases.check_cancelled()?;
+        let tarball = make_tarball(meta, bundle_extras, config, phases.progress_sink)?;
+        log::info!("Flushed tarball to {:?}", tarball.path);
+        log::info!("Skipping upload.");
+        return Ok((None, tarball.dry_run_summary, None));
+    }
+
+    let phase_start = phases.start(UploadPhase::UploadIntent)?;
+    api_client
+        .create_repo(&api::message::CreateRepoRequest {
+            repo: meta.base_props.repo.repo.clone(),
+            org_url_slug: meta.base_props.org.clone(),
+            remote_urls: vec![meta.base_props.repo.repo_url.clone()],
+        })
+        .await
+        .error_code(ErrorCode::UploadFailed)?;
+
+    let upload = gather_upload_id_context(&mut meta, api_client)
+        .await
+        .error_code(ErrorCode::UploadFailed)?;
+    let cli_outdated = match check_cli_version(
+        upload.minimum_recommended_cli_version.as_deref(),
+        config.fail_if_outdated,
+    ) {
+        Ok(cli_outdated) => cli_outdated,
+        Err(e) => {
+            update_bundle_upload_status(api_client, &upload.id, BundleUploadStatus::UploadFailed)
+                .await;
+            return Err(e);
+        }
+    };
+    phases.finish(UploadPhase::UploadIntent, phase_start);
+    phases.progress_sink.on_event(UploadEvent::UploadCreated {
+        id: upload.id.clone(),
+    });
+
+    let repo_root = meta.base_props.repo.repo_root.clone();
+    let journal = (config.resume && !config.no_upload).then_some((exit_code, quarantine_outcome));
+    let dry_run_summary = match put_tarball(
+        meta,
+        api_client,
+        bundle_extras,
+        &upload,
+        config,
+        journal,
+        phases,
+    )
+    .await
+    {
+        Ok((upload_status, dry_run_summary)) => {
+            update_bundle_upload_status(api_client, &upload.id, upload_status).await;
+            if journal.is_some() {
+                UploadJournal::remove(&repo_root);
+            }
+            dry_run_summary
+        }
+        Err(e) => {
+            update_bundle_upload_status(api_client, &upload.id, BundleUploadStatus::UploadFailed)
+                .await;
+            return Err(e);
+        }
+    };
+
+    if config.no_upload {
+        log::info!("Skipping upload.");
+    } else if exit_code == EXIT_SUCCESS {
+        log::info!("Done");
+    } else {
+        log::info!(
+            "Upload successful; returning unsuccessful exit code of test run: {}",
+            exit_code
+        )
+    }
+
+    Ok((Some(upload.id), dry_run_summary, cli_outdated))
+}
+
+/// Returns the status to record for the registered upload once the bundle is put, or not. With
+/// the exit code and quarantine outcome of the tests in `journal`, the upload is journaled until
+/// the bundle is put, so that it can be resumed.
+async fn put_tarball(
+    meta: BundleMeta,
+    api_client: &dyn ApiClientTrait,
+    bundle_extras: BundleExtras,
+    upload: &CreateBundleUploadResponse,
+    config: &UploadConfig,
+    journal: Option<(i32, QuarantineOutcome)>,
+    phases: &mut UploadPhases<'_>,
+) -> anyhow::Result<(BundleUploadStatus, Option<DryRunSummary>)> {
+    let repo = meta.base_props.repo.clone();
+    let upload_request = bundle_upload_request(&meta);
+    let phase_start = phases.start(UploadPhase::TarballCompression)?;
+    let tarball = make_tarball(meta, bundle_extras, config, phases.progress_sink)?;
+    phases.finish(UploadPhase::TarballCompression, phase_start);
+    log::info!("Flushed tarball to {:?}", tarball.path);
+
+    if config.no_upload {
+        return Ok((BundleUploadStatus::DryRun, tarball.dry_run_summary));
+    }
+
+    let journal = journal
+        .map(
+            |(exit_code, quarantine_outcome)| -> anyhow::Result<UploadJournal> {
+                let journal = UploadJournal::new(
+                    UploadJournalKey::new(&repo, config),
+                    &tarball.path,
+                    &upload_request,
+                    upload,
+                    exit_code,
+                    quarantine_outcome,
+                    Utc::now().timestamp(),
+                )?;
+                journal.write(&repo.repo_root)?;
+                log::info!(
+                    "Journaled the upload to {:?}, resume it with --resume if it fails",
+                    UploadJournal::path(&repo.repo_root)
+                );
+                Ok(journal)
+            },
+        )
+        .transpose()?;
+
+    put_bundle(api_client, &upload.url, &tarball.path, config, phases).await?;
+    if let Some(mut journal) = journal {
+        journal.state = UploadJournalState::Put;
+        journal.write(&repo.repo_root)?;
+    }
+    Ok((BundleUploadStatus::UploadComplete, None))
+}
+
+/// Puts the tarball at `tarball_path` to the presigned `url`, finishing it within the grace period
+/// when it is mostly put by the time the upload is cancelled.
+async fn put_bundle(
+    api_client: &dyn ApiClientTrait,
+    url: &str,
+    tarball_path: &Path,
+    config: &UploadConfig,
+    phases: &mut UploadPhases<'_>,
+) -> anyhow::Result<()> {
+    let phase_start = phases.start(UploadPhase::S3Put)?;
+    let upload_progress = UploadProgress::default();
+    // dropping the request aborts it
+    let put_bundle = api_client.put_bundle_to_s3_with_progress(url, tarball_path, &upload_progress);
+    tokio::pin!(put_bundle);
+    let put_or_cancel = async {
+        tokio::select! {
+            put_bundle_result = &mut put_bundle => put_bundle_result,
+            _ = phases.cancellation_token.cancelled() => {
+                let percent = upload_progress.percent().unwrap_or_default();
+                let finish_percent = config
+                    .finish_upload_on_cancel_percent
+                    .unwrap_or(DEFAULT_FINISH_UPLOAD_ON_CANCEL_PERCENT);
+                let grace_period = config
+                    .cancel_grace_period
+                    .unwrap_or(DEFAULT_CANCEL_GRACE_PERIOD);
+                if percent < u64::from(finish_percent) {
+                    log::warn!("Aborting the upload, {}% of the bundle was uploaded", percent);
+                    return Err(UploadCancelled.into());
+                }
+                log::warn!(
+                    "Finishing the upload within {:?}, {}% of the bundle was uploaded",
+                    grace_period,
+                    percent
+                );
+                tokio::time::timeout(grace_period, &mut put_bundle)
+                    .await
+                    .map_err(|_| UploadCancelled)?
+            }
+        }
+    };
+    let put_bundle_result = match tokio::select! {
+        put_bundle_result = put_or_cancel => put_bundle_result,
+        never = report_put_progress(&upload_progress, phases.progress_sink) => match never {},
+    } {
+        Err(e) if e.is::<UploadCancelled>() => return Err(e),
+        put_bundle_result => put_bundle_result.error_code(ErrorCode::UploadFailed),
+    };
+    phases.finish(UploadPhase::S3Put, phase_start);
+
+    put_bundle_result
+}
+
+/// Logs and reports the progress of putting a bundle every [`S3_PUT_PROGRESS_INTERVAL`], so a long
+/// upload is not mistaken for a hung one. Never finishes, it is dropped once the put is done.
+async fn report_put_progress(
+    upload_progress: &UploadProgress,
+    progress_sink: &dyn ProgressSink,
+) -> std::convert::Infallible {
+    let mut interval = tokio::time::interval_at(
+        tokio::time::Instant::now() + S3_PUT_PROGRESS_INTERVAL,
+        S3_PUT_PROGRESS_INTERVAL,
+    );
+    loop {
+        interval.tick().await;
+        if let Some(summary) = upload_progress.summary() {
+            log::info!("Uploading bundle to S3, {}", summary);
+        }
+        progress_sink.on_event(UploadEvent::S3PutProgress {
+            bytes_sent: upload_progress.bytes_sent(),
+            total_bytes: upload_progress.total_bytes(),
+        });
+    }
+}
+
+/// Puts the tarball of a journaled upload, unless it was already put, and updates the status of
+/// the upload. An upload whose presigned URL expired is registered again, its tarball given the ID
+/// of the new upload, and the expired one marked as failed. The journal is kept when the put fails,
+/// so that it can be resumed again.
+///
+/// The resumed upload exits with the exit code of the tests of the run that journaled it.
+async fn resume_upload(
+    mut journal: UploadJournal,
+    repo: BundleRepo,
+    api_client: &dyn ApiClientTrait,
+    config: &UploadConfig,
+    mut phases: UploadPhases<'_>,
+) -> anyhow::Result<UploadOutcome> {
+    log::info!(
+        "Resuming upload {} of the bundle journaled at {:?}",
+        journal.upload_id,
+        UploadJournal::path(&repo.repo_root)
+    );
+    if journal.state == UploadJournalState::Bundled
+        && journal.upload_url_expired(Utc::now().timestamp())
+    {
+        let phase_start = phases.start(UploadPhase::UploadIntent)?;
+        let upload = api_client
+            .create_bundle_upload(&journal.upload_request)
+            .await
+            .error_code(ErrorCode::UploadFailed)?;
+        log::info!(
+            "The upload URL of {} expired, uploading the journaled bundle as {}",
+            journal.upload_id,
+            upload.id
+        );
+        update_bundle_upload_status(
+            api_client,
+            &journal.upload_id,
+            BundleUploadStatus::UploadFailed,
+        )
+        .await;
+        rewrite_tarball_meta(&journal.tarball_path, |meta| {
+            meta.base_props.bundle_upload_id.clone_from(&upload.id);
+            meta.bundle_upload_id_v2.clone_from(&upload.id_v2);
+        })?;
+        journal = journal.with_upload(&upload, Utc::now().timestamp())?;
+        journal.write(&repo.repo_root)?;
+        phases.finish(UploadPhase::UploadIntent, phase_start);
+    }
+    let put_result = match journal.state {
+        UploadJournalState::Bundled => {
+            put_bundle(
+                api_client,
+                &journal.upload_url,
+                &journal.tarball_path,
+                config,
+                &mut phases,
+            )
+            .await
+        }
+        UploadJournalState::Put => Ok(()),
+    };
+    let upload_error = match put_result {
+        Ok(()) => {
+            journal.state = UploadJournalState::Put;
+            journal.write(&repo.repo_root)?;
+            update_bundle_upload_status(
+                api_client,
+                &journal.upload_id,
+                BundleUploadStatus::UploadComplete,
+            )
+            .await;
+            UploadJournal::remove(&repo.repo_root);
+            None
+        }
+        Err(e) if e.is::<UploadCancelled>() => return Err(e),
+        Err(e) => {
+            update_bundle_upload_status(
+                api_client,
+                &journal.upload_id,
+                BundleUploadStatus::UploadFailed,
+            )
+            .await;
+            Some(e)
+        }
+    };
+    phases.phase_timer.log_summary();
+
+    Ok(UploadOutcome {
+        exit_code: journal.exit_code,
+        quarantine_outcome: journal.quarantine_outcome,
+        quarantine_audit: None,
+        bundle_upload_id: Some(journal.upload_id),
+        upload_error,
+        phase_timer: phases.phase_timer,
+        dry_run_summary: None,
+        cli_outdated: None,
+        repo,
+        resumed: true,
+    })
+}
+
+/// Best-effort, so that a failure to record the status of an upload never affects the exit code.
+async fn update_bundle_upload_status(
+    api_client: &dyn ApiClientTrait,
+    id: &str,
+    upload_status: BundleUploadStatus,
+) {
+    if let Err(e) = api_client
+        .update_bundle_upload(&api::message::UpdateBundleUploadRequest {
+            id: String::from(id),
+            upload_status: upload_status.clone(),
+        })
+        .await
+    {
+        log::warn!("{}", e)
+    } else {
+        log::debug!("Updated bundle upload status to {}", upload_status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingProgressSink {
+        events: Mutex<Vec<UploadEvent>>,
+    }
+
+    impl ProgressSink for RecordingProgressSink {
+        fn on_event(&self, event: UploadEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_put_progress_periodically() {
+        let upload_progress = UploadProgress::default();
+        let bundle = [0u8; 100];
+        upload_progress.reset(bundle.len() as u64);
+        let mut buf = Vec::new();
+        upload_progress
+            .reader(&bundle[..40])
+            .read_to_end(&mut buf)
+            .await
+            .unwrap();
+        let progress_sink = RecordingProgressSink::default();
+
+        let _ = tokio::time::timeout(
+            S3_PUT_PROGRESS_INTERVAL * 2 + Duration::from_secs(1),
+            report_put_progress(&upload_progress, &progress_sink),
+        )
+        .await;
+
+        let progress = UploadEvent::S3PutProgress {
+            bytes_sent: 40,
+            total_bytes: 100,
+        };
+        assert_eq!(
+            progress_sink.events.into_inner().unwrap(),
+            [progress.clone(), progress]
+        );
+    }
+}
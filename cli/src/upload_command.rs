@@ -1,16 +1,33 @@
-use api::{client::ApiClient, message::BundleUploadStatus};
-use bundle::{BundleMeta, BundlerUtil};
+use std::{num::NonZeroUsize, sync::Mutex, time::Duration};
+
+use api::{
+    client::{ApiClient, ApiClientTrait},
+    error_code::ErrorCode,
+};
 use clap::{ArgAction, Args};
-use constants::EXIT_SUCCESS;
-use context::bazel_bep::parser::BepParseResult;
+use colored::Colorize;
+use constants::{
+    TRUNK_API_TIMEOUT_SECS_ENV, TRUNK_CONNECT_TIMEOUT_SECS_ENV, TRUNK_S3_TIMEOUT_SECS_ENV,
+};
+use context::{env::parser::StableBranches, junit::junit_path::JunitPathWithVariant};
 
 use crate::{
-    context::{
-        gather_debug_props, gather_exit_code_and_quarantined_tests_context,
-        gather_post_test_context, gather_pre_test_context, gather_upload_id_context,
-        PreTestContext,
+    auth::{exchange_github_oidc_token, AuthMode},
+    context::PreTestContext,
+    env_info_command::parse_stable_branch,
+    phase_timer::PhaseTimer,
+    pr_comment::post_pr_comment,
+    preflight::run_preflight,
+    print::print_file_decisions,
+    shutdown,
+    summary::{terminal_width, FailureSummary, SummaryGroupBy, SummaryRenderer, TestFailure},
+    test_command::{command_run_results_summary, TestRunResult},
+    test_command_stats::test_command_stats_summary,
+    upload::{
+        upload, BundleMetaSkippedFile, EnvCaptureProfile, ExportFormat, MergeJunitFiles,
+        ProgressSink, QuarantineAudit, QuarantineOutcome, RawTestRunnerOutputInclusion,
+        UploadConfig, UploadEvent, UploadOutcome,
     },
-    test_command::TestRunResult,
 };
 
 #[cfg(target_os = "macos")]
@@ -22,23 +39,36 @@ const JUNIT_GLOB_REQUIRED_UNLESS_PRESENT_ARG: &str = "junit_paths";
 pub struct UploadArgs {
     #[arg(
         long,
-        required_unless_present_any = [JUNIT_GLOB_REQUIRED_UNLESS_PRESENT_ARG, "bazel_bep_path"],
+        required_unless_present_any = [JUNIT_GLOB_REQUIRED_UNLESS_PRESENT_ARG, "bazel_bep_path", "internal_bin_paths", "junit_paths_with_variant", "converter_input_paths", "multi_repo_config"],
         conflicts_with = "bazel_bep_path",
         value_delimiter = ',',
         value_parser = clap::builder::NonEmptyStringValueParser::new(),
-        help = "Comma-separated list of glob paths to junit files."
+        help = "Comma-separated list of glob paths to junit files. ${VAR} and ${VAR:-default} are replaced with environment variables, and $$ with $."
     )]
     pub junit_paths: Vec<String>,
     #[arg(
         long,
-        required_unless_present_any = [JUNIT_GLOB_REQUIRED_UNLESS_PRESENT_ARG, "junit_paths"],
+        conflicts_with = "bazel_bep_path",
+        help = "Glob path to junit files and the variant of their tests, as <glob>=<variant>, e.g. ios/**/*.xml=ios. May be repeated, and takes the place of --variant for these files."
+    )]
+    pub junit_paths_with_variant: Vec<JunitPathWithVariant>,
+    #[arg(
+        long,
+        required_unless_present_any = [JUNIT_GLOB_REQUIRED_UNLESS_PRESENT_ARG, "junit_paths", "internal_bin_paths", "junit_paths_with_variant", "converter_input_paths", "multi_repo_config"],
         help = "Path to bazel build event protocol JSON file."
     )]
     pub bazel_bep_path: Option<String>,
+    #[arg(
+        long = "internal-bin-path",
+        value_delimiter = ',',
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        help = "Comma-separated list of paths to internal bin files of serialized test results."
+    )]
+    pub internal_bin_paths: Vec<String>,
     #[cfg(target_os = "macos")]
     #[arg(long,
-        required_unless_present_any = ["junit_paths", "bazel_bep_path"],
-        conflicts_with_all = ["junit_paths", "bazel_bep_path"],
+        required_unless_present_any = ["junit_paths", "bazel_bep_path", "internal_bin_paths", "junit_paths_with_variant", "converter_input_paths", "multi_repo_config"],
+        conflicts_with_all = ["junit_paths", "bazel_bep_path", "junit_paths_with_variant"],
         required = false, help = "Path of xcresult directory"
     )]
     pub xcresult_path: Option<String>,
@@ -46,11 +76,20 @@ pub struct UploadArgs {
     pub org_url_slug: String,
     #[arg(
         long,
-        required = true,
+        required = false,
+        required_unless_present = "auth",
+        default_value_if("auth", "oidc", ""),
         env = "TRUNK_API_TOKEN",
-        help = "Organization token. Defaults to TRUNK_API_TOKEN env var."
+        help = "Organization token. Defaults to TRUNK_API_TOKEN env var. Not needed with --auth oidc."
     )]
     pub token: String,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = AuthMode::Token,
+        help = "How to authenticate with Trunk. `oidc` exchanges the OIDC token of the GitHub Actions job for a short-lived token, which needs the `id-token: write` permission."
+    )]
+    pub auth: AuthMode,
     #[arg(long, help = "Path to repository root. Defaults to current directory.")]
     pub repo_root: Option<String>,
     #[arg(long, help = "Value to override URL of repository.")]
@@ -59,8 +98,36 @@ pub struct UploadArgs {
     pub repo_head_sha: Option<String>,
     #[arg(long, help = "Value to override branch of repository head.")]
     pub repo_head_branch: Option<String>,
-    #[arg(long, help = "Value to override commit epoch of repository head.")]
+    #[arg(
+        long,
+        value_parser = parse_repo_head_commit_epoch,
+        help = "Value to override commit epoch of repository head, in seconds or as an ISO-8601 datetime."
+    )]
     pub repo_head_commit_epoch: Option<String>,
+    #[arg(long, help = "Value to override author name of repository head.")]
+    pub repo_head_author_name: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_repo_head_author_email,
+        help = "Value to override author email of repository head."
+    )]
+    pub repo_head_author_email: Option<String>,
+    #[arg(long, help = "Value to override commit message of repository head.")]
+    pub repo_head_commit_message: Option<String>,
+    #[arg(
+        long,
+        requires = "repo_url",
+        conflicts_with_all = [
+            "repo_head_sha",
+            "repo_head_branch",
+            "repo_head_commit_epoch",
+            "repo_head_author_name",
+            "repo_head_author_email",
+            "repo_head_commit_message",
+        ],
+        help = "Upload without any git metadata, for tests run outside of a checkout. Requires --repo-url. The results are not attributed to a commit or branch."
+    )]
+    pub repo_less: bool,
     #[arg(
         long,
         value_delimiter = ',',
@@ -69,16 +136,62 @@ pub struct UploadArgs {
     pub tags: Vec<String>,
     #[arg(long, help = "Print files which will be uploaded to stdout.")]
     pub print_files: bool,
+    #[arg(
+        long,
+        help = "Print every file matched by the junit globs with the rule that decided whether it is uploaded."
+    )]
+    pub explain_files: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SummaryGroupBy::Owner,
+        help = "How to group failed tests in the summary at the end of the run."
+    )]
+    pub summary_group_by: SummaryGroupBy,
+    #[arg(
+        long,
+        help = "Comment the failure summary on the pull request when running in GitHub Actions, using GITHUB_TOKEN. Updates the comment of earlier runs. For repos without the Trunk GitHub app."
+    )]
+    pub post_pr_comment: bool,
     #[arg(
         long,
         alias = "dry-run",
         help = "Run metrics CLI without uploading to API."
     )]
     pub no_upload: bool,
+    #[arg(
+        long,
+        requires = "no_upload",
+        help = "Register the skipped upload with Trunk as a dry run."
+    )]
+    pub register_dry_run: bool,
+    #[arg(
+        long,
+        requires = "no_upload",
+        help = "Directory to write the bundle and a summary.json of the dry run to. It is created if missing and must be empty."
+    )]
+    pub dry_run_output: Option<String>,
+    #[arg(
+        long,
+        requires = "dry_run_output",
+        help = "Write to --dry-run-output even if it is not empty."
+    )]
+    pub force: bool,
     #[arg(long, help = "Value to tag team owner of upload.")]
     pub team: Option<String>,
+    #[arg(
+        long,
+        help = "Variant of the tests of the upload, e.g. the platform they ran on. Tests of different variants are tracked separately."
+    )]
+    pub variant: Option<String>,
     #[arg(long, help = "Value to override CODEOWNERS file or directory path.")]
     pub codeowners_path: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_stable_branch,
+        help = "Stable branch, which is classified as protected. Repeat for more than one. Prefix with `re:` to match a regex. Defaults to main and master."
+    )]
+    pub stable_branches: Vec<String>,
     #[arg(
         long,
         help = "Run commands with the quarantining step.",
@@ -102,6 +215,200 @@ pub struct UploadArgs {
         default_missing_value = "true",
     )]
     pub allow_empty_test_results: bool,
+    #[arg(
+        long,
+        env = TRUNK_API_TIMEOUT_SECS_ENV,
+        help = "Timeout in seconds for requests to Trunk services. Defaults to 30."
+    )]
+    pub api_timeout_secs: Option<u64>,
+    #[arg(
+        long,
+        env = TRUNK_S3_TIMEOUT_SECS_ENV,
+        help = "Read timeout in seconds while uploading the bundle. Defaults to 60."
+    )]
+    pub s3_timeout_secs: Option<u64>,
+    #[arg(
+        long,
+        env = TRUNK_CONNECT_TIMEOUT_SECS_ENV,
+        help = "Timeout in seconds for establishing connections. Defaults to 10."
+    )]
+    pub connect_timeout_secs: Option<u64>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = RawTestRunnerOutputInclusion::Auto,
+        help = "Whether to embed the BEP file in the bundle. With auto, only files of at most 50MB are embedded; the path, size and sha256 of files left out are recorded instead."
+    )]
+    pub include_raw_test_runner_output: RawTestRunnerOutputInclusion,
+    #[arg(
+        long,
+        help = "Skip checking that the token belongs to the organization of --org-url-slug before gathering test results."
+    )]
+    pub no_preflight: bool,
+    #[arg(
+        long,
+        help = "Write the exit code, error code, bundle upload id, quarantine outcome and upload phase durations of the run, and the exit code of each test command, as JSON to this file."
+    )]
+    pub results_file: Option<String>,
+    #[arg(
+        long,
+        help = "Fail instead of warning when more than --duplicate-id-threshold test cases share an id."
+    )]
+    pub fail_on_duplicate_ids: bool,
+    #[arg(
+        long,
+        help = "Number of test cases that may share an id before it is reported. Defaults to 50."
+    )]
+    pub duplicate_id_threshold: Option<usize>,
+    #[arg(
+        long,
+        help = "Fail instead of warning when the CLI is older than the minimum version recommended by your organization."
+    )]
+    pub fail_if_outdated: bool,
+    #[arg(
+        long,
+        help = "Print the quarantined test IDs that matched no test case of this upload, with the nearest test case as a suggestion."
+    )]
+    pub quarantine_audit: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = MergeJunitFiles::Off,
+        help = "Merge the junit files of each glob before bundling, e.g. when the test runner writes one per test case. Merged files are split again when they exceed 10MB."
+    )]
+    pub merge_junit_files: MergeJunitFiles,
+    #[arg(
+        long,
+        help = "Leave out the files matched by the junit globs that are ignored by the .gitignore files or .git/info/exclude of the repo."
+    )]
+    pub respect_gitignore: bool,
+    #[arg(
+        long,
+        value_parser = humantime::parse_duration,
+        help = "Leave out the files matched by the junit globs that were last modified longer ago than this, e.g. 2h, to skip stale results of previous runs. Ages are relative to the start of the test command when there is one."
+    )]
+    pub junit_max_age: Option<Duration>,
+    #[arg(
+        long,
+        value_name = "STATE_FILE",
+        help = "Experimental: keep the pass/fail history of the last runs of each test in this JSON file, and treat failed tests whose history is flaky as quarantined. They are only quarantined locally, never by Trunk."
+    )]
+    pub local_flake_detection: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = EnvCaptureProfile::Standard,
+        help = "Which env vars are captured into the bundle: only the ones the CI info was read from (ci-only), the known CI env vars (standard), or every env var for debugging (full). Env vars whose name looks like a secret are never captured."
+    )]
+    pub env_capture_profile: EnvCaptureProfile,
+    #[arg(
+        long,
+        help = "Bundle the junit files exactly as they are, instead of transcoding them to UTF-8, removing the characters that are not allowed in XML and truncating texts and attribute values longer than the 8,000 bytes Trunk keeps."
+    )]
+    pub no_sanitize_junit: bool,
+    #[arg(
+        long,
+        conflicts_with = "no_sanitize_junit",
+        help = "Keep the texts and attribute values of the junit files that are longer than the 8,000 bytes Trunk keeps when sanitizing them."
+    )]
+    pub no_truncate_junit_text: bool,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        help = "When the CLI receives SIGINT or SIGTERM while uploading the bundle, finish the upload if at least this percent of it was sent, and abort it otherwise. Defaults to 80."
+    )]
+    pub finish_upload_on_cancel_percent: Option<u8>,
+    #[arg(
+        long,
+        value_parser = humantime::parse_duration,
+        help = "How long an upload that is finished after SIGINT or SIGTERM may take, e.g. 3s. Defaults to 5s."
+    )]
+    pub cancel_grace_period: Option<Duration>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Also export the parsed test results, e.g. to Datadog CI Visibility with datadog, which writes them to --export-output and sends them to Datadog when DD_API_KEY is set. The export never changes the exit code."
+    )]
+    pub export: Option<ExportFormat>,
+    #[arg(long, requires = "export", help = "File to write the export to.")]
+    pub export_output: Option<String>,
+    #[arg(
+        long,
+        help = "Keep the bundle and a journal of its upload in .trunk/ until it is uploaded, and when a previous run with --resume for the same commit, org, junit paths and variant failed to upload its bundle, upload that one instead of collecting the test results again. A resumed upload exits with the exit code of the tests of the run that journaled it. Not supported by test."
+    )]
+    pub resume: bool,
+    #[arg(
+        long,
+        help = "Only quarantine the failed tests of the quarantine config of Trunk, ignoring test cases that the test runner marked as quarantined with quarantined=\"true\" in the junit or is_quarantined in the internal bin."
+    )]
+    pub ignore_runner_quarantine_marks: bool,
+    #[arg(
+        long,
+        help = "Memory-map junit files of 16MB or more to parse them, instead of reading them through a buffer, on 64-bit platforms. Set TRUNK_NO_MMAP=1 to turn it off, e.g. for files on network filesystems."
+    )]
+    pub mmap_junit_files: bool,
+    #[arg(
+        long,
+        help = "Do not use path-like test case classnames as the test file when file and filepath are missing, for the uploaded test results as well as quarantining."
+    )]
+    pub no_infer_file_from_classname: bool,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Largest size in bytes of the internal bin of the bundle. The test case runs of a larger one are split in order across internal_0.bin, internal_1.bin etc. of at most this size each. Defaults to 268435456 (256MB)."
+    )]
+    pub internal_bin_max_bytes: Option<u64>,
+    #[arg(
+        long,
+        requires = "converter_input_paths",
+        help = "Command converting a test result file of a custom format to junit, run with the shell for each file matched by --converter-input-paths, e.g. \"my-converter {input} {output}\". {input} is replaced with the path of the file and {output} with where to write the junit XML. Files that fail to convert are left out of the bundle. The first line its first word prints for --version is recorded as its version."
+    )]
+    pub converter: Option<String>,
+    #[arg(
+        long,
+        requires = "converter",
+        value_delimiter = ',',
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        help = "Comma-separated list of glob paths to the test result files to run --converter on. ${VAR} and ${VAR:-default} are replaced with environment variables, and $$ with $."
+    )]
+    pub converter_input_paths: Vec<String>,
+    #[arg(
+        long,
+        requires = "converter",
+        value_parser = humantime::parse_duration,
+        help = "How long --converter may take per file before it is killed and the file left out, e.g. 30s. Defaults to 60s."
+    )]
+    pub converter_timeout: Option<Duration>,
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "junit_paths",
+            "junit_paths_with_variant",
+            "bazel_bep_path",
+            "internal_bin_paths",
+            "converter",
+            "repo_root",
+            "repo_url",
+            "repo_head_sha",
+            "repo_head_branch",
+            "repo_head_commit_epoch",
+            "repo_head_author_name",
+            "repo_head_author_email",
+            "repo_head_commit_message",
+            "repo_less",
+            "post_pr_comment",
+            "dry_run_output",
+            "results_file",
+        ],
+        help = "Path to a TOML file of [[repos]], each with a repo_root and junit_paths and optionally an org_url_slug and variant, e.g. for a meta-repo that checks out several repos side by side. A bundle is uploaded for each repo, with the other options applying to all of them, and the exit code is the highest of the repos. Relative repo roots are relative to the file, and junit paths to the repo root."
+    )]
+    pub multi_repo_config: Option<String>,
+    #[arg(
+        long,
+        requires = "multi_repo_config",
+        help = "How many repos of --multi-repo-config to upload at once. Defaults to 1."
+    )]
+    pub multi_repo_parallel: Option<NonZeroUsize>,
 }
 
 impl UploadArgs {
@@ -120,144 +427,385 @@ impl UploadArgs {
             ..Default::default()
         }
     }
+
+    pub fn api_client(&self) -> anyhow::Result<ApiClient> {
+        UploadConfig::from(self.clone()).api_client()
+    }
+
+    /// With `--auth oidc`, replaces the token with one exchanged for the OIDC token of the GitHub
+    /// Actions job. Must be called before [`UploadArgs::api_client`].
+    pub async fn authenticate(&mut self) -> anyhow::Result<()> {
+        if self.auth != AuthMode::Oidc {
+            return Ok(());
+        }
+        let upload_config = UploadConfig::from(self.clone());
+        let api_client = ApiClient::new_for_token_exchange(upload_config.api_client_timeouts())?;
+        let api_client = match upload_config.api_address.as_ref() {
+            Some(api_address) => api_client.with_host(api_address),
+            None => api_client,
+        };
+        self.token = exchange_github_oidc_token(&api_client, &self.org_url_slug).await?;
+        Ok(())
+    }
+}
+
+impl From<UploadArgs> for UploadConfig {
+    fn from(
+        UploadArgs {
+            junit_paths,
+            junit_paths_with_variant,
+            bazel_bep_path,
+            internal_bin_paths,
+            #[cfg(target_os = "macos")]
+            xcresult_path,
+            org_url_slug,
+            token,
+            auth: _,
+            repo_root,
+            repo_url,
+            repo_head_sha,
+            repo_head_branch,
+            repo_head_commit_epoch,
+            repo_head_author_name,
+            repo_head_author_email,
+            repo_head_commit_message,
+            repo_less,
+            tags,
+            print_files,
+            explain_files: _,
+            summary_group_by: _,
+            post_pr_comment: _,
+            no_upload,
+            register_dry_run,
+            dry_run_output,
+            force,
+            team,
+            variant,
+            codeowners_path,
+            stable_branches,
+            use_quarantining,
+            allow_empty_test_results,
+            api_timeout_secs,
+            s3_timeout_secs,
+            connect_timeout_secs,
+            include_raw_test_runner_output,
+            no_preflight: _,
+            results_file: _,
+            fail_on_duplicate_ids,
+            duplicate_id_threshold,
+            fail_if_outdated,
+            quarantine_audit,
+            merge_junit_files,
+            respect_gitignore,
+            junit_max_age,
+            local_flake_detection,
+            env_capture_profile,
+            no_sanitize_junit,
+            no_truncate_junit_text,
+            finish_upload_on_cancel_percent,
+            cancel_grace_period,
+            export,
+            export_output,
+            resume,
+            ignore_runner_quarantine_marks,
+            mmap_junit_files,
+            no_infer_file_from_classname,
+            internal_bin_max_bytes,
+            converter,
+            converter_input_paths,
+            converter_timeout,
+            multi_repo_config: _,
+            multi_repo_parallel: _,
+        }: UploadArgs,
+    ) -> Self {
+        let mut upload_config = UploadConfig::new(token, org_url_slug);
+        upload_config.junit_paths = junit_paths;
+        upload_config.junit_paths_with_variant = junit_paths_with_variant;
+        upload_config.bazel_bep_path = bazel_bep_path;
+        upload_config.internal_bin_paths = internal_bin_paths;
+        #[cfg(target_os = "macos")]
+        {
+            upload_config.xcresult_path = xcresult_path;
+        }
+        upload_config.repo_root = repo_root;
+        upload_config.repo_url = repo_url;
+        upload_config.repo_head_sha = repo_head_sha;
+        upload_config.repo_head_branch = repo_head_branch;
+        upload_config.repo_head_commit_epoch = repo_head_commit_epoch;
+        upload_config.repo_head_author_name = repo_head_author_name;
+        upload_config.repo_head_author_email = repo_head_author_email;
+        upload_config.repo_head_commit_message = repo_head_commit_message;
+        upload_config.repo_less = repo_less;
+        upload_config.tags = tags;
+        upload_config.print_files = print_files;
+        upload_config.no_upload = no_upload;
+        upload_config.register_dry_run = register_dry_run;
+        upload_config.dry_run_output = dry_run_output;
+        upload_config.force_dry_run_output = force;
+        upload_config.team = team;
+        upload_config.variant = variant;
+        upload_config.codeowners_path = codeowners_path;
+        // each entry was already checked by `parse_stable_branch`
+        upload_config.stable_branches = StableBranches::new(&stable_branches).unwrap_or_default();
+        upload_config.use_quarantining = use_quarantining;
+        upload_config.allow_empty_test_results = allow_empty_test_results;
+        upload_config.api_timeout_secs = api_timeout_secs;
+        upload_config.s3_timeout_secs = s3_timeout_secs;
+        upload_config.connect_timeout_secs = connect_timeout_secs;
+        upload_config.include_raw_test_runner_output = include_raw_test_runner_output;
+        upload_config.fail_on_duplicate_ids = fail_on_duplicate_ids;
+        upload_config.duplicate_id_threshold = duplicate_id_threshold;
+        upload_config.fail_if_outdated = fail_if_outdated;
+        upload_config.quarantine_audit = quarantine_audit;
+        upload_config.merge_junit_files = merge_junit_files;
+        upload_config.respect_gitignore = respect_gitignore;
+        upload_config.junit_max_age = junit_max_age;
+        upload_config.local_flake_detection = local_flake_detection;
+        upload_config.env_capture_profile = env_capture_profile;
+        upload_config.no_sanitize_junit = no_sanitize_junit;
+        upload_config.no_truncate_junit_text = no_truncate_junit_text;
+        upload_config.finish_upload_on_cancel_percent = finish_upload_on_cancel_percent;
+        upload_config.cancel_grace_period = cancel_grace_period;
+        upload_config.export = export;
+        upload_config.export_output = export_output;
+        upload_config.resume = resume;
+        upload_config.ignore_runner_quarantine_marks = ignore_runner_quarantine_marks;
+        upload_config.mmap_junit_files = mmap_junit_files;
+        upload_config.no_infer_file_from_classname = no_infer_file_from_classname;
+        upload_config.internal_bin_max_bytes = internal_bin_max_bytes;
+        upload_config.converter = converter;
+        upload_config.converter_input_paths = converter_input_paths;
+        upload_config.converter_timeout = converter_timeout;
+        upload_config
+    }
+}
+
+fn parse_repo_head_author_email(email: &str) -> Result<String, String> {
+    if email.is_empty() || email.contains('@') {
+        Ok(String::from(email))
+    } else {
+        Err(String::from("email must contain '@'"))
+    }
+}
+
+fn parse_repo_head_commit_epoch(commit_epoch: &str) -> Result<String, String> {
+    context::repo::parse_commit_epoch(commit_epoch)
+        .map(|epoch| epoch.to_string())
+        .map_err(|e| e.to_string())
 }
 
 pub struct UploadRunResult {
     pub exit_code: i32,
+    /// Whether failures were quarantined, which explains `exit_code`
+    pub quarantine_outcome: QuarantineOutcome,
     pub upload_bundle_error: Option<anyhow::Error>,
+    pub phase_timer: PhaseTimer,
+}
+
+/// Prints the files to upload when asked to, and keeps the failed tests and skipped files for the
+/// summary at the end of the run. Phases are only logged, by the upload itself.
+struct CliProgressSink {
+    print_files: bool,
+    explain_files: bool,
+    test_failures: Mutex<Vec<TestFailure>>,
+    skipped_files: Mutex<Vec<BundleMetaSkippedFile>>,
+}
+
+impl ProgressSink for CliProgressSink {
+    fn on_event(&self, event: UploadEvent) {
+        match event {
+            UploadEvent::FilesDecided(file_decisions) if self.explain_files => {
+                print_file_decisions(&file_decisions);
+            }
+            UploadEvent::FilesCollected(file_sets) if self.print_files => {
+                println!("Files to upload:");
+                for file_set in &file_sets {
+                    println!(
+                        "  File set ({:?}): {}",
+                        file_set.file_set_type, file_set.glob
+                    );
+                    for file in &file_set.files {
+                        println!("    {}", file.original_path);
+                    }
+                }
+            }
+            UploadEvent::TestFailures(test_failures) => {
+                *self.test_failures.lock().unwrap() = test_failures;
+            }
+            UploadEvent::FilesSkipped(skipped_files) => {
+                *self.skipped_files.lock().unwrap() = skipped_files;
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The files left out of the bundle, which are missing from the uploaded results
+fn print_skipped_files(skipped_files: &[BundleMetaSkippedFile]) {
+    if skipped_files.is_empty() {
+        return;
+    }
+    println!(
+        "{}",
+        format!(
+            "⚠ Left {} file(s) out of the bundle because they could not be read:",
+            skipped_files.len()
+        )
+        .yellow()
+    );
+    for skipped_file in skipped_files {
+        println!(
+            "    {}: {}",
+            skipped_file.original_path, skipped_file.reason
+        );
+    }
+}
+
+/// Quarantined test IDs that matched no test case, which are likely misspelled
+fn print_quarantine_audit(quarantine_audit: &QuarantineAudit) {
+    if quarantine_audit.unmatched.is_empty() {
+        println!("{} {}", "✔".green(), quarantine_audit.to_string().green());
+    } else {
+        println!("{} {}", "⚠".yellow(), quarantine_audit.to_string().yellow());
+    }
+}
+
+/// The last line of the run, which tells whether it is green because of quarantining
+fn print_quarantine_outcome(quarantine_outcome: &QuarantineOutcome) {
+    let line = quarantine_outcome.to_string().bold();
+    match quarantine_outcome {
+        QuarantineOutcome::NoFailures => println!("{} {}", "✔".green(), line.green()),
+        QuarantineOutcome::AllQuarantined { .. } => println!("{} {}", "⚠".yellow(), line.yellow()),
+        QuarantineOutcome::SomeUnquarantined { .. }
+        | QuarantineOutcome::QuarantiningUnavailable => {
+            println!("{} {}", "✖".red(), line.red())
+        }
+    }
 }
 
 pub async fn run_upload(
     upload_args: UploadArgs,
+    api_client: &dyn ApiClientTrait,
     pre_test_context: Option<PreTestContext>,
     test_run_result: Option<TestRunResult>,
 ) -> anyhow::Result<UploadRunResult> {
-    let api_client = ApiClient::new(&upload_args.token)?;
-
-    let PreTestContext {
-        mut meta,
-        junit_path_wrappers,
-        bep_result,
-        // directory is removed on drop
-        junit_path_wrappers_temp_dir: _junit_path_wrappers_temp_dir,
-    } = if let Some(pre_test_context) = pre_test_context {
-        pre_test_context
-    } else {
-        gather_pre_test_context(upload_args.clone(), gather_debug_props(upload_args.token))?
+    let summary_group_by = upload_args.summary_group_by;
+    let comment_on_pr = upload_args.post_pr_comment;
+    let progress_sink = CliProgressSink {
+        print_files: upload_args.print_files,
+        explain_files: upload_args.explain_files,
+        test_failures: Mutex::new(Vec::new()),
+        skipped_files: Mutex::new(Vec::new()),
     };
-
-    let file_set_builder = gather_post_test_context(
-        &mut meta,
-        junit_path_wrappers,
-        &upload_args.team,
-        &upload_args.codeowners_path,
-        upload_args.allow_empty_test_results,
-        &test_run_result,
-    )?;
-
-    if upload_args.print_files {
-        println!("Files to upload:");
-        for file_set in &meta.base_props.file_sets {
-            println!(
-                "  File set ({:?}): {}",
-                file_set.file_set_type, file_set.glob
-            );
-            for file in &file_set.files {
-                println!("    {}", file.original_path);
-            }
+    let no_preflight = upload_args.no_preflight;
+    let results_file = upload_args.results_file.clone();
+    let audit_quarantine = upload_args.quarantine_audit;
+    let test_command_stats = test_run_result
+        .as_ref()
+        .map(|test_run_result| test_run_result.test_command_stats.clone());
+    let command_run_results = test_run_result
+        .as_ref()
+        .map(|test_run_result| test_run_result.command_run_results.clone());
+    let upload_config: UploadConfig = upload_args.into();
+    let stable_branches = upload_config.stable_branches.clone();
+    let upload_result = async {
+        // the test command checks before running tests, and gathers the pre-test context after
+        if pre_test_context.is_none() && !no_preflight {
+            run_preflight(&upload_config, api_client).await?;
         }
+        upload(
+            upload_config,
+            api_client,
+            pre_test_context,
+            test_run_result,
+            &shutdown::cancellation_token(),
+            &progress_sink,
+        )
+        .await
     }
-
-    let exit_code = gather_exit_code_and_quarantined_tests_context(
-        &mut meta,
-        upload_args.use_quarantining,
-        &api_client,
-        &file_set_builder,
-        &test_run_result,
-    )
-    .await;
-
-    let upload_bundle_result = upload_bundle(
-        meta,
-        &api_client,
-        bep_result,
-        upload_args.no_upload,
-        exit_code,
-    )
     .await;
-
-    Ok(UploadRunResult {
+    let UploadOutcome {
         exit_code,
-        upload_bundle_error: upload_bundle_result.err(),
-    })
-}
-
-async fn upload_bundle(
-    mut meta: BundleMeta,
-    api_client: &ApiClient,
-    bep_result: Option<BepParseResult>,
-    no_upload: bool,
-    exit_code: i32,
-) -> anyhow::Result<()> {
-    api_client
-        .create_repo(&api::message::CreateRepoRequest {
-            repo: meta.base_props.repo.repo.clone(),
-            org_url_slug: meta.base_props.org.clone(),
-            remote_urls: vec![meta.base_props.repo.repo_url.clone()],
-        })
-        .await?;
-
-    let upload = gather_upload_id_context(&mut meta, api_client).await?;
-
-    let (
-        bundle_temp_file,
-        // directory is removed on drop
-        _bundle_temp_dir,
-    ) = BundlerUtil::new(meta, bep_result).make_tarball_in_temp_dir()?;
-    log::info!("Flushed temporary tarball to {:?}", bundle_temp_file);
-
-    if no_upload {
-        if let Err(e) = api_client
-            .update_bundle_upload(&api::message::UpdateBundleUploadRequest {
-                id: upload.id.clone(),
-                upload_status: BundleUploadStatus::DryRun,
-            })
-            .await
-        {
-            log::warn!("{}", e);
-        } else {
-            log::debug!("Updated bundle upload status to DRY_RUN");
+        quarantine_outcome,
+        quarantine_audit,
+        bundle_upload_id,
+        upload_error,
+        phase_timer,
+        dry_run_summary,
+        cli_outdated,
+        repo,
+        ..
+    } = match upload_result {
+        Ok(upload_outcome) => upload_outcome,
+        Err(e) => {
+            if let Some(results_file) = results_file {
+                let error_code = ErrorCode::of(&e);
+                let run_results = serde_json::json!({
+                    "exit_code": error_code.exit_code(),
+                    "error_code": error_code.as_str(),
+                });
+                // the error the run failed with matters more than failing to write the file
+                if let Err(write_error) = write_results_file(&results_file, &run_results) {
+                    log::warn!("{}", write_error);
+                }
+            }
+            return Err(e);
         }
-        log::info!("Skipping upload.");
-    } else {
-        api_client
-            .put_bundle_to_s3(&upload.url, &bundle_temp_file)
-            .await?;
+    };
 
-        if let Err(e) = api_client
-            .update_bundle_upload(&api::message::UpdateBundleUploadRequest {
-                id: upload.id.clone(),
-                upload_status: BundleUploadStatus::UploadComplete,
-            })
-            .await
-        {
-            log::warn!("{}", e)
-        } else {
-            log::debug!(
-                "Updated bundle upload status to {:#?}",
-                BundleUploadStatus::UploadComplete
-            )
+    let test_failures = progress_sink.test_failures.into_inner().unwrap();
+    let failure_summary = FailureSummary::new(&test_failures, summary_group_by);
+    if !failure_summary.is_empty() {
+        println!(
+            "{}",
+            SummaryRenderer::for_stdout().render(&failure_summary, terminal_width())
+        );
+    }
+    if comment_on_pr {
+        post_pr_comment(&failure_summary, &repo, &stable_branches).await;
+    }
+    if audit_quarantine {
+        if let Some(quarantine_audit) = &quarantine_audit {
+            print_quarantine_audit(quarantine_audit);
         }
+    }
+    print_skipped_files(&progress_sink.skipped_files.into_inner().unwrap());
+    if let Some(dry_run_summary) = &dry_run_summary {
+        println!("{}", dry_run_summary);
+    }
+    if let Some(test_command_stats) = &test_command_stats {
+        println!("{}", test_command_stats_summary(test_command_stats));
+    }
+    if let Some(summary) = command_run_results
+        .as_deref()
+        .and_then(command_run_results_summary)
+    {
+        println!("{}", summary);
+    }
+    print_quarantine_outcome(&quarantine_outcome);
 
-        if exit_code == EXIT_SUCCESS {
-            log::info!("Done");
-        } else {
-            log::info!(
-                "Upload successful; returning unsuccessful exit code of test run: {}",
-                exit_code
-            )
-        }
+    if let Some(results_file) = results_file {
+        let run_results = serde_json::json!({
+            "exit_code": exit_code,
+            "bundle_upload_id": bundle_upload_id,
+            "quarantine_outcome": quarantine_outcome,
+            "cli_outdated": cli_outdated,
+            "error_code": upload_error.as_ref().map(|e| ErrorCode::of(e).as_str()),
+            "phase_durations_ms": phase_timer.durations_ms(),
+            "test_commands": command_run_results,
+        });
+        write_results_file(&results_file, &run_results)?;
     }
 
-    Ok(())
+    Ok(UploadRunResult {
+        exit_code,
+        quarantine_outcome,
+        upload_bundle_error: upload_error,
+        phase_timer,
+    })
+}
+
+fn write_results_file(results_file: &str, run_results: &serde_json::Value) -> anyhow::Result<()> {
+    std::fs::write(results_file, serde_json::to_string_pretty(run_results)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write results file {}: {}", results_file, e))
 }
@@ -0,0 +1,456 @@
+//! A journal of a bundle upload kept with `--resume`, so that a CLI invoked again after putting
+//! the bundle to S3 failed puts the same tarball instead of bundling the test results again.
+//! Journals are only resumed by an upload of the same test results, see [`UploadJournalKey`],
+//! while the tarball is unchanged. A journaled upload whose presigned URL expired is registered
+//! again, and its tarball given the ID of the new upload.
+
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use api::message::{CreateBundleUploadRequest, CreateBundleUploadResponse};
+use bundle::QuarantineOutcome;
+use chrono::NaiveDateTime;
+use context::repo::BundleRepo;
+use serde::{Deserialize, Serialize};
+
+use crate::upload::UploadConfig;
+
+/// Relative to the repo root
+pub const UPLOAD_JOURNAL_PATH: &str = ".trunk/upload-journal.json";
+/// Relative to the repo root. The tarball of a journaled upload is kept here until it is put.
+pub const UPLOAD_JOURNAL_TARBALL_PATH: &str = ".trunk/upload-bundle.tar.zstd";
+/// Journals older than this are ignored and removed
+pub const UPLOAD_JOURNAL_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long a presigned URL that doesn't say when it expires is assumed to be valid
+const DEFAULT_PRESIGNED_URL_TTL: Duration = Duration::from_secs(15 * 60);
+/// Time left for the put to finish before the presigned URL expires
+const PRESIGNED_URL_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+const UPLOAD_JOURNAL_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadJournalState {
+    /// The tarball was made, but not put
+    Bundled,
+    /// The tarball was put, but the status of the upload may not have been updated
+    Put,
+}
+
+/// What the test results of a journaled upload are. Other jobs or shards of the same commit upload
+/// other test results, so they don't resume the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadJournalKey {
+    pub repo_url: String,
+    pub repo_head_sha: String,
+    pub org_url_slug: String,
+    /// The junit globs, followed by those of `--junit-paths-with-variant` as `<glob>=<variant>`
+    pub junit_paths: Vec<String>,
+    pub variant: Option<String>,
+}
+
+impl UploadJournalKey {
+    pub fn new(repo: &BundleRepo, config: &UploadConfig) -> Self {
+        Self {
+            repo_url: repo.repo_url.clone(),
+            repo_head_sha: repo.repo_head_sha.clone(),
+            org_url_slug: config.org_url_slug.clone(),
+            junit_paths: config
+                .junit_paths
+                .iter()
+                .cloned()
+                .chain(
+                    config
+                        .junit_paths_with_variant
+                        .iter()
+                        .map(ToString::to_string),
+                )
+                .collect(),
+            variant: config.variant.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadJournal {
+    pub version: u32,
+    /// Seconds since the epoch
+    pub created_at: i64,
+    #[serde(flatten)]
+    pub key: UploadJournalKey,
+    pub tarball_path: PathBuf,
+    pub tarball_sha256: String,
+    /// How the upload was registered, to register it again when its presigned URL expired
+    pub upload_request: CreateBundleUploadRequest,
+    pub upload_id: String,
+    pub upload_url: String,
+    /// Seconds since the epoch
+    pub upload_url_expires_at: i64,
+    /// Exit code of the tests after quarantining, which the resumed upload exits with
+    pub exit_code: i32,
+    pub quarantine_outcome: QuarantineOutcome,
+    pub state: UploadJournalState,
+}
+
+impl UploadJournal {
+    pub fn new(
+        key: UploadJournalKey,
+        tarball_path: &Path,
+        upload_request: &CreateBundleUploadRequest,
+        upload: &CreateBundleUploadResponse,
+        exit_code: i32,
+        quarantine_outcome: QuarantineOutcome,
+        now: i64,
+    ) -> anyhow::Result<Self> {
+        Self {
+            version: UPLOAD_JOURNAL_VERSION,
+            created_at: now,
+            key,
+            tarball_path: tarball_path.to_path_buf(),
+            tarball_sha256: String::with_capacity(0),
+            upload_request: upload_request.clone(),
+            upload_id: String::with_capacity(0),
+            upload_url: String::with_capacity(0),
+            upload_url_expires_at: 0,
+            exit_code,
+            quarantine_outcome,
+            state: UploadJournalState::Bundled,
+        }
+        .with_upload(upload, now)
+    }
+
+    /// The journal of the tarball put to the presigned URL of `upload` instead. The tarball must
+    /// have been given the ID of `upload`, which changes its checksum.
+    pub fn with_upload(
+        self,
+        upload: &CreateBundleUploadResponse,
+        now: i64,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            tarball_sha256: file_sha256(&self.tarball_path)?,
+            upload_id: upload.id.clone(),
+            upload_url: upload.url.clone(),
+            upload_url_expires_at: presigned_url_expires_at(&upload.url)
+                .unwrap_or(now + DEFAULT_PRESIGNED_URL_TTL.as_secs() as i64),
+            ..self
+        })
+    }
+
+    /// Whether the presigned URL expires before a put started at `now` can finish
+    pub fn upload_url_expired(&self, now: i64) -> bool {
+        now + PRESIGNED_URL_EXPIRY_MARGIN.as_secs() as i64 > self.upload_url_expires_at
+    }
+
+    pub fn path(repo_root: &str) -> PathBuf {
+        Path::new(repo_root).join(UPLOAD_JOURNAL_PATH)
+    }
+
+    pub fn tarball_path(repo_root: &str) -> PathBuf {
+        Path::new(repo_root).join(UPLOAD_JOURNAL_TARBALL_PATH)
+    }
+
+    pub fn write(&self, repo_root: &str) -> anyhow::Result<()> {
+        let journal_path = Self::path(repo_root);
+        let dir = journal_path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+        let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer_pretty(&mut temp_file, self)?;
+        temp_file.as_file().sync_all()?;
+        temp_file.persist(journal_path)?;
+        Ok(())
+    }
+
+    /// The journal of an upload of the repo at `repo_root` that can be resumed at `now` by an upload
+    /// with `key`. A journal that can't be is removed with its tarball.
+    pub fn read_resumable(repo_root: &str, key: &UploadJournalKey, now: i64) -> Option<Self> {
+        let journal_path = Self::path(repo_root);
+        let contents = match fs::read(&journal_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!(
+                    "Failed to read upload journal {}: {}",
+                    journal_path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        let journal = serde_json::from_slice::<Self>(&contents)
+            .map_err(|e| format!("it is corrupt ({})", e))
+            .and_then(|journal| match journal.why_not_resumable(key, now) {
+                Some(reason) => Err(reason),
+                None => Ok(journal),
+            });
+        match journal {
+            Ok(journal) => Some(journal),
+            Err(reason) => {
+                log::info!(
+                    "Not resuming the upload of journal {}, {}",
+                    journal_path.display(),
+                    reason
+                );
+                Self::remove(repo_root);
+                None
+            }
+        }
+    }
+
+    fn why_not_resumable(&self, key: &UploadJournalKey, now: i64) -> Option<String> {
+        if self.version != UPLOAD_JOURNAL_VERSION {
+            return Some(format!("it is of version {}", self.version));
+        }
+        if now - self.created_at > UPLOAD_JOURNAL_TTL.as_secs() as i64 {
+            return Some(String::from("it is too old"));
+        }
+        if self.key.repo_url != key.repo_url || self.key.repo_head_sha != key.repo_head_sha {
+            return Some(format!(
+                "it is for {} at {}",
+                self.key.repo_url, self.key.repo_head_sha
+            ));
+        }
+        if self.key != *key {
+            return Some(format!(
+                "it is for the test results of {} in org {}{}",
+                self.key.junit_paths.join(", "),
+                self.key.org_url_slug,
+                self.key
+                    .variant
+                    .as_ref()
+                    .map(|variant| format!(" of variant {}", variant))
+                    .unwrap_or_default()
+            ));
+        }
+        if self.state == UploadJournalState::Put {
+            return None;
+        }
+        match file_sha256(&self.tarball_path) {
+            Ok(sha256) if sha256 == self.tarball_sha256 => None,
+            Ok(_) => Some(String::from("its tarball changed")),
+            Err(e) => Some(format!("its tarball can't be read ({})", e)),
+        }
+    }
+
+    /// Removes the journal and its tarball, if they exist
+    pub fn remove(repo_root: &str) {
+        for path in [Self::path(repo_root), Self::tarball_path(repo_root)] {
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != ErrorKind::NotFound {
+                    log::warn!("Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+fn file_sha256(path: &Path) -> anyhow::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = openssl::sha::Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+    Ok(hex::encode(hasher.finish()))
+}
+
+/// When a presigned S3 URL expires, from its `X-Amz-Date` and `X-Amz-Expires` parameters
+fn presigned_url_expires_at(url: &str) -> Option<i64> {
+    let (_, query) = url.split_once('?')?;
+    let mut signed_at = None;
+    let mut expires_in = None;
+    for param in query.split('&') {
+        match param.split_once('=') {
+            Some(("X-Amz-Date", value)) => {
+                signed_at = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok()
+            }
+            Some(("X-Amz-Expires", value)) => expires_in = value.parse::<i64>().ok(),
+            _ => (),
+        }
+    }
+    Some(signed_at?.and_utc().timestamp() + expires_in?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn repo(repo_root: &Path, repo_head_sha: &str) -> BundleRepo {
+        BundleRepo {
+            repo_root: repo_root.to_string_lossy().to_string(),
+            repo_url: String::from("https://github.com/trunk-io/analytics-cli"),
+            repo_head_sha: String::from(repo_head_sha),
+            ..Default::default()
+        }
+    }
+
+    fn key(repo: &BundleRepo) -> UploadJournalKey {
+        let mut upload_config = UploadConfig::new("token", "org");
+        upload_config.junit_paths = vec![String::from("junit/*.xml")];
+        UploadJournalKey::new(repo, &upload_config)
+    }
+
+    fn upload(url: &str) -> CreateBundleUploadResponse {
+        CreateBundleUploadResponse {
+            id: String::from("upload-id"),
+            id_v2: String::from("upload-id-v2"),
+            url: String::from(url),
+            key: String::from("key"),
+            minimum_recommended_cli_version: None,
+        }
+    }
+
+    fn write_journal(repo: &BundleRepo, url: &str) -> UploadJournal {
+        let tarball_path = UploadJournal::tarball_path(&repo.repo_root);
+        fs::create_dir_all(tarball_path.parent().unwrap()).unwrap();
+        fs::write(&tarball_path, "tarball").unwrap();
+        let upload_request = CreateBundleUploadRequest {
+            repo: repo.repo.clone(),
+            org_url_slug: String::from("org"),
+            client_version: String::from("trunk-analytics-cli cargo=0.0.0"),
+        };
+        let journal = UploadJournal::new(
+            key(repo),
+            &tarball_path,
+            &upload_request,
+            &upload(url),
+            1,
+            QuarantineOutcome::SomeUnquarantined {
+                quarantined: 0,
+                unquarantined: 1,
+            },
+            NOW,
+        )
+        .unwrap();
+        journal.write(&repo.repo_root).unwrap();
+        journal
+    }
+
+    #[test]
+    fn presigned_url_expiry() {
+        assert_eq!(
+            presigned_url_expires_at(
+                "https://bucket.s3.amazonaws.com/key?X-Amz-Date=20231114T221320Z&X-Amz-Expires=600&X-Amz-Signature=abc"
+            ),
+            Some(NOW + 600)
+        );
+        assert_eq!(presigned_url_expires_at("http://localhost/s3upload"), None);
+    }
+
+    #[test]
+    fn resumable_for_the_same_commit_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = repo(temp_dir.path(), "abc");
+        let journal = write_journal(&repo, "http://localhost/s3upload");
+        assert_eq!(journal.upload_url_expires_at, NOW + 15 * 60);
+
+        assert_eq!(
+            UploadJournal::read_resumable(&repo.repo_root, &key(&repo), NOW + 60),
+            Some(journal)
+        );
+
+        let other_repo = BundleRepo {
+            repo_head_sha: String::from("def"),
+            ..repo.clone()
+        };
+        assert_eq!(
+            UploadJournal::read_resumable(&repo.repo_root, &key(&other_repo), NOW + 60),
+            None
+        );
+        assert!(!UploadJournal::path(&repo.repo_root).exists());
+        assert!(!UploadJournal::tarball_path(&repo.repo_root).exists());
+    }
+
+    #[test]
+    fn resumable_for_the_same_test_results_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = repo(temp_dir.path(), "abc");
+
+        for other_key in [
+            UploadJournalKey {
+                org_url_slug: String::from("other-org"),
+                ..key(&repo)
+            },
+            UploadJournalKey {
+                junit_paths: vec![String::from("shard-2/*.xml")],
+                ..key(&repo)
+            },
+            UploadJournalKey {
+                variant: Some(String::from("linux")),
+                ..key(&repo)
+            },
+        ] {
+            write_journal(&repo, "http://localhost/s3upload");
+            assert_eq!(
+                UploadJournal::read_resumable(&repo.repo_root, &other_key, NOW + 60),
+                None
+            );
+            assert!(!UploadJournal::path(&repo.repo_root).exists());
+        }
+    }
+
+    #[test]
+    fn resumable_with_an_expired_upload_url() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = repo(temp_dir.path(), "abc");
+
+        write_journal(&repo, "http://localhost/s3upload");
+        let journal =
+            UploadJournal::read_resumable(&repo.repo_root, &key(&repo), NOW + 15 * 60).unwrap();
+        assert!(journal.upload_url_expired(NOW + 15 * 60));
+
+        fs::write(&journal.tarball_path, "tarball of the new upload").unwrap();
+        let journal = journal
+            .with_upload(
+                &upload(
+                    "https://bucket.s3.amazonaws.com/key?X-Amz-Date=20231114T222320Z&X-Amz-Expires=600",
+                ),
+                NOW + 15 * 60,
+            )
+            .unwrap();
+        assert_eq!(journal.upload_url_expires_at, NOW + 20 * 60);
+        assert!(!journal.upload_url_expired(NOW + 15 * 60));
+        journal.write(&repo.repo_root).unwrap();
+        assert_eq!(
+            UploadJournal::read_resumable(&repo.repo_root, &key(&repo), NOW + 15 * 60),
+            Some(journal)
+        );
+    }
+
+    #[test]
+    fn not_resumable_when_too_old_or_changed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = repo(temp_dir.path(), "abc");
+
+        write_journal(&repo, "http://localhost/s3upload");
+        fs::write(UploadJournal::tarball_path(&repo.repo_root), "changed").unwrap();
+        assert_eq!(
+            UploadJournal::read_resumable(&repo.repo_root, &key(&repo), NOW + 60),
+            None
+        );
+
+        let mut journal = write_journal(&repo, "http://localhost/s3upload");
+        journal.state = UploadJournalState::Put;
+        journal.write(&repo.repo_root).unwrap();
+        assert_eq!(
+            UploadJournal::read_resumable(&repo.repo_root, &key(&repo), NOW + 60 * 60),
+            Some(journal)
+        );
+        assert_eq!(
+            UploadJournal::read_resumable(
+                &repo.repo_root,
+                &key(&repo),
+                NOW + UPLOAD_JOURNAL_TTL.as_secs() as i64 + 1
+            ),
+            None
+        );
+    }
+}
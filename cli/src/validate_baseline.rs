@@ -0,0 +1,429 @@
+//! Validation metrics of the junit files recorded in a baseline file, so that a later run of
+//! validate can fail when they got worse, e.g. after a test runner upgrade stops writing
+//! timestamps. Only the aggregate metrics are compared: files come and go between runs, so the
+//! metrics of each file are recorded for reference only.
+//!
+//! Every metric is compared as a percentage of the test cases, so that a run with more or fewer
+//! tests than the baseline compares fairly: the attribute coverage, and the issues of each rule
+//! per 100 test cases. The tolerance is in percentage points of either.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use context::junit::{parser::extra_attrs, validator::JunitReportValidation};
+use quick_junit::Report;
+use serde::{Deserialize, Serialize};
+
+pub const VALIDATE_BASELINE_SCHEMA_VERSION: u32 = 1;
+/// In percentage points, see [`compare_to_baseline`]
+pub const DEFAULT_BASELINE_TOLERANCE: f64 = 0.0;
+
+pub mod attributes {
+    pub const FILE: &str = "file";
+    pub const LINE: &str = "line";
+    pub const ID: &str = "id";
+    pub const TIME: &str = "time";
+    pub const TIMESTAMP: &str = "timestamp";
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BaselineRegressions {
+    /// Exit with a non-zero exit code when a metric regressed
+    #[default]
+    Fail,
+    /// Only print the regressed metrics
+    Warn,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaselineOptions {
+    pub path: PathBuf,
+    /// Write the baseline instead of comparing to it
+    pub write: bool,
+    /// Percentage points a metric may get worse by before it regressed
+    pub tolerance: f64,
+    pub regressions: BaselineRegressions,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidateBaseline {
+    pub schema_version: u32,
+    pub aggregate: ValidationMetrics,
+    pub files: BTreeMap<String, ValidationMetrics>,
+}
+
+impl ValidateBaseline {
+    pub fn new(files: BTreeMap<String, ValidationMetrics>) -> Self {
+        Self {
+            schema_version: VALIDATE_BASELINE_SCHEMA_VERSION,
+            aggregate: ValidationMetrics::aggregate(files.values()),
+            files,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationMetrics {
+    pub num_test_cases: usize,
+    /// Number of validation issues of each rule, by the name of the rule
+    pub issue_counts: BTreeMap<String, usize>,
+    /// Percentage of test cases that have each attribute, their own or their parents'
+    pub attribute_coverage: BTreeMap<String, f64>,
+}
+
+impl ValidationMetrics {
+    pub fn new(report: &Report, report_validation: &JunitReportValidation) -> Self {
+        let mut issue_counts = BTreeMap::new();
+        for issue in report_validation.all_issues() {
+            *issue_counts.entry(String::from(issue.rule())).or_default() += 1;
+        }
+
+        let mut num_test_cases = 0;
+        let mut attribute_counts: BTreeMap<&str, usize> = [
+            attributes::FILE,
+            attributes::LINE,
+            attributes::ID,
+            attributes::TIME,
+            attributes::TIMESTAMP,
+        ]
+        .into_iter()
+        .map(|attribute| (attribute, 0))
+        .collect();
+        let mut count = |attribute: &str, has_attribute: bool| {
+            if let Some(attribute_count) = attribute_counts.get_mut(attribute) {
+                *attribute_count += usize::from(has_attribute);
+            }
+        };
+        for test_suite in &report.test_suites {
+            for test_case in &test_suite.test_cases {
+                num_test_cases += 1;
+                count(
+                    attributes::FILE,
+                    test_case.extra.contains_key(extra_attrs::FILE)
+                        || test_case.extra.contains_key(extra_attrs::FILEPATH),
+                );
+                count(
+                    attributes::LINE,
+                    test_case.extra.contains_key(extra_attrs::LINE),
+                );
+                count(
+                    attributes::ID,
+                    test_case.extra.contains_key(extra_attrs::ID),
+                );
+                count(
+                    attributes::TIME,
+                    test_case.time.or(test_suite.time).or(report.time).is_some(),
+                );
+                count(
+                    attributes::TIMESTAMP,
+                    test_case
+                        .timestamp
+                        .or(test_suite.timestamp)
+                        .or(report.timestamp)
+                        .is_some(),
+                );
+            }
+        }
+
+        Self {
+            num_test_cases,
+            issue_counts,
+            attribute_coverage: attribute_counts
+                .into_iter()
+                .map(|(attribute, count)| {
+                    (
+                        String::from(attribute),
+                        percentage(count as f64, num_test_cases),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Sums the issue counts and weighs the attribute coverage of each file by its test cases
+    pub fn aggregate<'a, T: IntoIterator<Item = &'a ValidationMetrics>>(metrics: T) -> Self {
+        let mut num_test_cases = 0;
+        let mut issue_counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut attribute_counts: BTreeMap<String, f64> = BTreeMap::new();
+        for file_metrics in metrics {
+            num_test_cases += file_metrics.num_test_cases;
+            for (rule, count) in &file_metrics.issue_counts {
+                *issue_counts.entry(rule.clone()).or_default() += count;
+            }
+            for (attribute, coverage) in &file_metrics.attribute_coverage {
+                *attribute_counts.entry(attribute.clone()).or_default() +=
+                    coverage * file_metrics.num_test_cases as f64 / 100.0;
+            }
+        }
+        Self {
+            num_test_cases,
+            issue_counts,
+            attribute_coverage: attribute_counts
+                .into_iter()
+                .map(|(attribute, count)| (attribute, percentage(count, num_test_cases)))
+                .collect(),
+        }
+    }
+}
+
+fn percentage(count: f64, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    count * 100.0 / total as f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaselineMetricKind {
+    /// Percentage of test cases with an attribute, which is better when higher
+    AttributeCoverage,
+    /// Issues of a rule per 100 test cases, which is better when lower
+    IssueRate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaselineDeltaStatus {
+    Improved,
+    /// Worse, but by no more than the tolerance
+    WithinTolerance,
+    Regressed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaselineDelta {
+    pub metric: String,
+    pub kind: BaselineMetricKind,
+    pub baseline: f64,
+    pub current: f64,
+    pub status: BaselineDeltaStatus,
+}
+
+impl BaselineDelta {
+    fn new(
+        metric: String,
+        kind: BaselineMetricKind,
+        baseline: f64,
+        current: f64,
+        tolerance: f64,
+    ) -> Self {
+        let worsening = match kind {
+            BaselineMetricKind::AttributeCoverage => baseline - current,
+            BaselineMetricKind::IssueRate => current - baseline,
+        };
+        let status = if worsening < 0.0 {
+            BaselineDeltaStatus::Improved
+        } else if worsening <= tolerance {
+            BaselineDeltaStatus::WithinTolerance
+        } else {
+            BaselineDeltaStatus::Regressed
+        };
+        Self {
+            metric,
+            kind,
+            baseline,
+            current,
+            status,
+        }
+    }
+}
+
+/// The aggregate metrics that changed from the baseline, with the issue counts of each rule as
+/// rates per 100 test cases. `tolerance` is how many percentage points worse a metric may get
+/// before it regressed, e.g. with 5, attribute coverage may drop from 80% to 75% and a rule may go
+/// from 10 to 15 issues per 100 test cases.
+pub fn compare_to_baseline(
+    baseline: &ValidationMetrics,
+    current: &ValidationMetrics,
+    tolerance: f64,
+) -> Vec<BaselineDelta> {
+    let mut deltas = Vec::new();
+
+    let attributes = baseline
+        .attribute_coverage
+        .keys()
+        .chain(current.attribute_coverage.keys())
+        .collect::<BTreeSet<_>>();
+    for attribute in attributes {
+        let baseline_coverage = baseline.attribute_coverage.get(attribute).copied();
+        let current_coverage = current.attribute_coverage.get(attribute).copied();
+        // coverage of an attribute that only one of them knows of can't be compared
+        if let (Some(baseline_coverage), Some(current_coverage)) =
+            (baseline_coverage, current_coverage)
+        {
+            deltas.push(BaselineDelta::new(
+                format!("{} coverage", attribute),
+                BaselineMetricKind::AttributeCoverage,
+                baseline_coverage,
+                current_coverage,
+                tolerance,
+            ));
+        }
+    }
+
+    let rules = baseline
+        .issue_counts
+        .keys()
+        .chain(current.issue_counts.keys())
+        .collect::<BTreeSet<_>>();
+    let issue_rate = |metrics: &ValidationMetrics, rule: &str| {
+        let count = metrics.issue_counts.get(rule).copied().unwrap_or_default();
+        percentage(count as f64, metrics.num_test_cases)
+    };
+    for rule in rules {
+        deltas.push(BaselineDelta::new(
+            format!("{} issues", rule),
+            BaselineMetricKind::IssueRate,
+            issue_rate(baseline, rule),
+            issue_rate(current, rule),
+            tolerance,
+        ));
+    }
+
+    deltas.retain(|delta| delta.baseline != delta.current);
+    deltas
+}
+
+pub fn read_baseline(baseline_path: &Path) -> anyhow::Result<ValidateBaseline> {
+    let contents = fs::read(baseline_path).with_context(|| {
+        format!(
+            "Failed to read validation baseline {}, create it with --write-baseline",
+            baseline_path.display()
+        )
+    })?;
+    let baseline: ValidateBaseline = serde_json::from_slice(&contents).with_context(|| {
+        format!(
+            "Failed to parse validation baseline {}",
+            baseline_path.display()
+        )
+    })?;
+    if baseline.schema_version != VALIDATE_BASELINE_SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Validation baseline {} is of schema version {}, but this version of the CLI reads {}. Write it again with --write-baseline.",
+            baseline_path.display(),
+            baseline.schema_version,
+            VALIDATE_BASELINE_SCHEMA_VERSION
+        ));
+    }
+    Ok(baseline)
+}
+
+pub fn write_baseline(baseline_path: &Path, baseline: &ValidateBaseline) -> anyhow::Result<()> {
+    let dir = baseline_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut temp_file, baseline)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(baseline_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(
+        num_test_cases: usize,
+        issue_counts: &[(&str, usize)],
+        attribute_coverage: &[(&str, f64)],
+    ) -> ValidationMetrics {
+        ValidationMetrics {
+            num_test_cases,
+            issue_counts: issue_counts
+                .iter()
+                .map(|(rule, count)| (String::from(*rule), *count))
+                .collect(),
+            attribute_coverage: attribute_coverage
+                .iter()
+                .map(|(attribute, coverage)| (String::from(*attribute), *coverage))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn aggregate_weighs_coverage_by_test_cases() {
+        let aggregate = ValidationMetrics::aggregate(&[
+            metrics(3, &[("TestCaseNoTimestamp", 3)], &[("timestamp", 0.0)]),
+            metrics(1, &[], &[("timestamp", 100.0)]),
+        ]);
+        assert_eq!(
+            aggregate,
+            metrics(4, &[("TestCaseNoTimestamp", 3)], &[("timestamp", 25.0)])
+        );
+    }
+
+    #[test]
+    fn compare_to_baseline_with_tolerance() {
+        let baseline = metrics(
+            10,
+            &[("TestCaseNoTimestamp", 10), ("TestCaseNoTimeDuration", 2)],
+            &[("file", 80.0), ("timestamp", 50.0)],
+        );
+        // twice the test cases, so twice the issues are the same rate
+        let current = metrics(
+            20,
+            &[("TestCaseNoTimestamp", 21), ("TestCaseInvalidId", 4)],
+            &[("file", 75.0), ("timestamp", 60.0)],
+        );
+
+        let deltas = compare_to_baseline(&baseline, &current, 5.0);
+        let statuses: Vec<_> = deltas
+            .iter()
+            .map(|delta| (delta.metric.as_str(), delta.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("file coverage", BaselineDeltaStatus::WithinTolerance),
+                ("timestamp coverage", BaselineDeltaStatus::Improved),
+                ("TestCaseInvalidId issues", BaselineDeltaStatus::Regressed),
+                (
+                    "TestCaseNoTimeDuration issues",
+                    BaselineDeltaStatus::Improved
+                ),
+                (
+                    "TestCaseNoTimestamp issues",
+                    BaselineDeltaStatus::WithinTolerance
+                ),
+            ]
+        );
+
+        let deltas = compare_to_baseline(&baseline, &current, DEFAULT_BASELINE_TOLERANCE);
+        assert_eq!(
+            deltas
+                .iter()
+                .filter(|delta| delta.status == BaselineDeltaStatus::Regressed)
+                .count(),
+            3
+        );
+        assert!(compare_to_baseline(&baseline, &baseline, DEFAULT_BASELINE_TOLERANCE).is_empty());
+    }
+
+    #[test]
+    fn read_baseline_rejects_other_schema_versions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let baseline_path = temp_dir.path().join("baseline.json");
+        let baseline = ValidateBaseline::new(BTreeMap::from([(
+            String::from("junit.xml"),
+            metrics(1, &[], &[("file", 100.0)]),
+        )]));
+        write_baseline(&baseline_path, &baseline).unwrap();
+        assert_eq!(read_baseline(&baseline_path).unwrap(), baseline);
+
+        write_baseline(
+            &baseline_path,
+            &ValidateBaseline {
+                schema_version: VALIDATE_BASELINE_SCHEMA_VERSION + 1,
+                ..baseline
+            },
+        )
+        .unwrap();
+        assert!(read_baseline(&baseline_path).is_err());
+    }
+}
@@ -1,7 +1,13 @@
-use std::{collections::BTreeMap, io::BufReader};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    io::BufReader,
+    path::PathBuf,
+    time::Duration,
+};
 
 use bundle::{FileSet, FileSetBuilder};
-use clap::{arg, Args};
+use clap::{arg, builder::TypedValueParser, Args, ValueEnum};
 use codeowners::CodeOwners;
 use colored::{ColoredString, Colorize};
 use console::Emoji;
@@ -9,18 +15,30 @@ use constants::{EXIT_FAILURE, EXIT_SUCCESS};
 use context::{
     bazel_bep::parser::BazelBepParser,
     junit::{
+        attr_map::{JunitAttrMap, JunitAttrMapping},
         junit_path::JunitReportFileWithStatus,
         parser::{JunitParseIssue, JunitParseIssueLevel, JunitParser},
+        schema::{closest_schema, JunitSchema},
+        timings::{DurationBucket, DurationBucketCount, JunitTimings},
         validator::{
-            validate as validate_report, JunitReportValidation, JunitReportValidationFlatIssue,
-            JunitReportValidationIssueSubOptimal, JunitValidationIssue, JunitValidationIssueType,
-            JunitValidationLevel,
+            validate_with_options, JunitReportValidation, JunitReportValidationIssueSubOptimal,
+            JunitTestCaseValidationIssueSubOptimal, JunitValidationIssue, JunitValidationIssueType,
+            JunitValidationLevel, JunitValidationOptions, DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD,
+            DEFAULT_DURATION_VARIANCE_FACTOR,
         },
     },
 };
 use quick_junit::Report;
 
-use crate::print::print_bep_results;
+use crate::{
+    env_interpolation::{interpolate_env_vars, interpolate_env_vars_in_all},
+    print::{print_bep_results, print_file_decisions},
+    validate_baseline::{
+        compare_to_baseline, read_baseline, write_baseline, BaselineDelta, BaselineDeltaStatus,
+        BaselineOptions, BaselineRegressions, ValidateBaseline, ValidationMetrics,
+        DEFAULT_BASELINE_TOLERANCE,
+    },
+};
 
 #[derive(Args, Clone, Debug)]
 pub struct ValidateArgs {
@@ -43,6 +61,183 @@ pub struct ValidateArgs {
     show_warnings: bool,
     #[arg(long, help = "Value to override CODEOWNERS file or directory path.")]
     pub codeowners_path: Option<String>,
+    #[arg(
+        long,
+        help = "Do not use path-like test case classnames as the test file when file and filepath are missing."
+    )]
+    pub no_infer_file_from_classname: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ValidateStrictness::Invalid,
+        help = "Lowest level of validation issues that causes a non-zero exit code."
+    )]
+    pub strictness: ValidateStrictness,
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        help = "Exit code of a command run before validate, e.g. the tests. Exit with it instead when it's non-zero, regardless of validation."
+    )]
+    pub previous_exit_code: Option<i32>,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD,
+        help = "Number of test cases that may share an id before it is an invalid issue."
+    )]
+    pub duplicate_id_threshold: usize,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_DURATION_VARIANCE_FACTOR,
+        value_parser = parse_duration_variance_factor,
+        help = "How many times less or more than the wall clock time of their test suite test case durations may add up to before it is a validation warning."
+    )]
+    pub duration_variance_factor: f64,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma-separated list of attribute=junit_attribute pairs, e.g. file=source,line=lineno, to read file, filepath, line or id from another junit attribute when it is missing."
+    )]
+    pub junit_attr_map: Vec<JunitAttrMapping>,
+    #[arg(
+        long,
+        default_value_t = JunitSchema::Junit,
+        value_parser = clap::builder::PossibleValuesParser::new(["junit", "xunit2", "surefire"])
+            .map(|schema| schema.parse::<JunitSchema>().unwrap()),
+        help = "Junit dialect to check conformance to on top of plain junit: xunit2 requires file and line on test cases and timestamps on test suites, surefire validates rerun elements."
+    )]
+    pub schema: JunitSchema,
+    #[arg(
+        long,
+        value_parser = humantime::parse_duration,
+        help = "Leave out the files matched by the junit globs that were last modified longer ago than this, e.g. 2h, like upload does."
+    )]
+    pub junit_max_age: Option<Duration>,
+    #[arg(
+        long,
+        help = "Print every file matched by the junit globs with the rule that decided whether it is validated."
+    )]
+    pub explain_files: bool,
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        help = "Print the slowest test cases and test suites and a histogram of test case durations after validating, as text or with --timings=json as JSON."
+    )]
+    pub timings: Option<TimingsFormat>,
+    #[arg(
+        long,
+        help = "Path to a validation baseline JSON file to compare the attribute coverage and issue counts of the junit files to, to catch them getting worse over time."
+    )]
+    pub baseline: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "baseline",
+        help = "Write the attribute coverage and issue counts of the junit files to --baseline instead of comparing to it."
+    )]
+    pub write_baseline: bool,
+    #[arg(
+        long,
+        requires = "baseline",
+        default_value_t = DEFAULT_BASELINE_TOLERANCE,
+        value_parser = parse_baseline_tolerance,
+        help = "How many percentage points worse than in --baseline a metric may get before it regressed. Metrics are percentages of the test cases: the attribute coverage, and the issues of each rule per 100 test cases."
+    )]
+    pub baseline_tolerance: f64,
+    #[arg(
+        long,
+        value_enum,
+        requires = "baseline",
+        default_value_t = BaselineRegressions::Fail,
+        help = "Whether metrics that regressed from --baseline cause a non-zero exit code."
+    )]
+    pub baseline_regressions: BaselineRegressions,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingsFormat {
+    Text,
+    Json,
+}
+
+fn parse_baseline_tolerance(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(tolerance) if tolerance.is_finite() && tolerance >= 0.0 => Ok(tolerance),
+        Ok(_) => Err(String::from("must be a non-negative number")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_duration_variance_factor(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(factor) if factor.is_finite() && factor >= 1.0 => Ok(factor),
+        Ok(_) => Err(String::from("must be a number of at least 1")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidateStrictness {
+    /// Fail only on invalid issues
+    #[default]
+    Invalid,
+    /// Fail on invalid and suboptimal issues, including a missing CODEOWNERS file
+    Suboptimal,
+    /// Never fail because of validation issues
+    None,
+}
+
+impl Display for ValidateStrictness {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ValidateStrictness::Invalid => write!(f, "invalid"),
+            ValidateStrictness::Suboptimal => write!(f, "suboptimal"),
+            ValidateStrictness::None => write!(f, "none"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValidateRunResult {
+    pub strictness: ValidateStrictness,
+    pub num_reports: usize,
+    pub num_invalid_reports: usize,
+    pub num_suboptimal_reports: usize,
+    pub num_suboptimal_codeowners_issues: usize,
+    pub num_baseline_regressions: usize,
+    pub baseline_regressions: BaselineRegressions,
+    pub previous_exit_code: Option<i32>,
+}
+
+impl ValidateRunResult {
+    /// The previous command's exit code when it failed, so that a failing test run isn't hidden by
+    /// a successful validation. Otherwise the validation's exit code.
+    pub fn exit_code(&self) -> i32 {
+        match self.previous_exit_code {
+            Some(previous_exit_code) if previous_exit_code != EXIT_SUCCESS => previous_exit_code,
+            _ => self.validation_exit_code(),
+        }
+    }
+
+    pub fn validation_exit_code(&self) -> i32 {
+        let has_failing_issues = match self.strictness {
+            ValidateStrictness::Invalid => self.num_invalid_reports > 0,
+            ValidateStrictness::Suboptimal => {
+                self.num_invalid_reports > 0
+                    || self.num_suboptimal_reports > 0
+                    || self.num_suboptimal_codeowners_issues > 0
+            }
+            ValidateStrictness::None => false,
+        };
+        let has_failing_baseline_regressions = self.num_baseline_regressions > 0
+            && self.baseline_regressions == BaselineRegressions::Fail;
+        if has_failing_issues || has_failing_baseline_regressions {
+            EXIT_FAILURE
+        } else {
+            EXIT_SUCCESS
+        }
+    }
 }
 
 pub async fn run_validate(validate_args: ValidateArgs) -> anyhow::Result<i32> {
@@ -51,21 +246,63 @@ pub async fn run_validate(validate_args: ValidateArgs) -> anyhow::Result<i32> {
         bazel_bep_path,
         show_warnings: _,
         codeowners_path,
+        no_infer_file_from_classname,
+        strictness,
+        previous_exit_code,
+        duplicate_id_threshold,
+        duration_variance_factor,
+        junit_attr_map,
+        schema,
+        junit_max_age,
+        explain_files,
+        timings,
+        baseline,
+        write_baseline,
+        baseline_tolerance,
+        baseline_regressions,
     } = validate_args;
 
     let junit_file_paths = match bazel_bep_path {
         Some(bazel_bep_path) => {
+            let bazel_bep_path = interpolate_env_vars(bazel_bep_path, "--bazel-bep-path")?;
             let mut parser = BazelBepParser::new(bazel_bep_path);
             let bep_result = parser.parse()?;
             print_bep_results(&bep_result);
             bep_result.uncached_xml_files()
         }
-        None => junit_paths
+        None => interpolate_env_vars_in_all(junit_paths, "--junit-paths")?
             .into_iter()
             .map(JunitReportFileWithStatus::from)
             .collect(),
     };
-    validate(junit_file_paths, codeowners_path).await
+    let validate_run_result = validate(
+        junit_file_paths,
+        codeowners_path,
+        !no_infer_file_from_classname,
+        JunitAttrMap::from(junit_attr_map),
+        strictness,
+        JunitValidationOptions {
+            duplicate_id_threshold,
+            duration_variance_factor,
+            schema,
+        },
+        junit_max_age,
+        explain_files,
+        timings,
+        baseline.map(|path| BaselineOptions {
+            path,
+            write: write_baseline,
+            tolerance: baseline_tolerance,
+            regressions: baseline_regressions,
+        }),
+    )
+    .await?;
+    let validate_run_result = ValidateRunResult {
+        previous_exit_code,
+        ..validate_run_result
+    };
+    print_strictness_summary(&validate_run_result);
+    Ok(validate_run_result.exit_code())
 }
 
 type JunitFileToReportAndParseIssues =
@@ -77,7 +314,15 @@ type JunitFileToValidation = BTreeMap<String, JunitReportValidation>;
 async fn validate(
     junit_paths: Vec<JunitReportFileWithStatus>,
     codeowners_path: Option<String>,
-) -> anyhow::Result<i32> {
+    infer_file_from_classname: bool,
+    junit_attr_map: JunitAttrMap,
+    strictness: ValidateStrictness,
+    validation_options: JunitValidationOptions,
+    junit_max_age: Option<Duration>,
+    explain_files: bool,
+    timings_format: Option<TimingsFormat>,
+    baseline_options: Option<BaselineOptions>,
+) -> anyhow::Result<ValidateRunResult> {
     // scan files
     let current_dir = std::env::current_dir()
         .ok()
@@ -89,14 +334,24 @@ async fn validate(
         &None,
         &Option::<&str>::None,
         None,
+        false,
+        junit_max_age,
     )?;
+    if explain_files {
+        print_file_decisions(file_set_builder.file_decisions());
+    }
     if file_set_builder.no_files_found() {
         return Err(anyhow::anyhow!("No JUnit files found to validate."));
     }
     print_matched_files(&file_set_builder);
 
     // parse
-    let parse_results = parse_file_sets(file_set_builder.file_sets());
+    let parse_results = parse_file_sets(
+        &current_dir,
+        file_set_builder.file_sets(),
+        infer_file_from_classname,
+        &junit_attr_map,
+    );
     let num_reports = parse_results.len();
     let (parsed_reports, parse_issues) = parse_results.into_iter().fold(
         (JunitFileToReport::new(), JunitFileToParseIssues::new()),
@@ -121,38 +376,80 @@ async fn validate(
     let (num_unparsable_reports, num_suboptimally_parsable_reports) =
         print_parse_issues(&parse_issues);
 
+    // durations are gathered before validation takes the reports
+    let timings = timings_format.map(|_| JunitTimings::from_reports(parsed_reports.values()));
+
     // validate
+    let mut file_metrics = BTreeMap::new();
     let report_validations: JunitFileToValidation = parsed_reports
         .into_iter()
-        .map(|(file, report)| (file, validate_report(&report)))
+        .map(|(file, report)| {
+            let report_validation = validate_with_options(&report, &validation_options);
+            if baseline_options.is_some() {
+                file_metrics.insert(
+                    file.clone(),
+                    ValidationMetrics::new(&report, &report_validation),
+                );
+            }
+            (file, report_validation)
+        })
         .collect();
     // print validation results
     let (mut num_invalid_reports, mut num_suboptimal_reports) =
-        print_validation_issues(&report_validations);
+        print_validation_issues(&report_validations, validation_options.schema);
 
     // print summary
     num_invalid_reports += num_unparsable_reports;
     num_suboptimal_reports += num_suboptimally_parsable_reports;
-    let exit = if num_invalid_reports == 0 {
+    if num_invalid_reports == 0 {
         print_summary_success(num_reports, num_suboptimal_reports);
-        EXIT_SUCCESS
     } else {
         print_summary_failure(num_reports, num_invalid_reports, num_suboptimal_reports);
-        EXIT_FAILURE
-    };
+    }
+    print_closest_schema(validation_options.schema, &report_validations);
 
     let codeowners = CodeOwners::find_file(&current_dir, &codeowners_path);
 
-    print_codeowners_validation(codeowners, &report_validations);
+    let num_suboptimal_codeowners_issues =
+        print_codeowners_validation(codeowners, &report_validations);
 
-    Ok(exit)
+    if let Some(timings) = timings {
+        match timings_format {
+            Some(TimingsFormat::Json) => println!("{}", serde_json::to_string_pretty(&timings)?),
+            _ => print_timings(&timings),
+        }
+    }
+
+    let (num_baseline_regressions, baseline_regressions) = match baseline_options {
+        Some(baseline_options) => (
+            check_baseline(&baseline_options, ValidateBaseline::new(file_metrics))?,
+            baseline_options.regressions,
+        ),
+        None => (0, BaselineRegressions::default()),
+    };
+
+    Ok(ValidateRunResult {
+        strictness,
+        num_reports,
+        num_invalid_reports,
+        num_suboptimal_reports,
+        num_suboptimal_codeowners_issues,
+        num_baseline_regressions,
+        baseline_regressions,
+        previous_exit_code: None,
+    })
 }
 
-fn parse_file_sets(file_sets: &[FileSet]) -> JunitFileToReportAndParseIssues {
+fn parse_file_sets(
+    repo_root: &str,
+    file_sets: &[FileSet],
+    infer_file_from_classname: bool,
+    junit_attr_map: &JunitAttrMap,
+) -> JunitFileToReportAndParseIssues {
     file_sets.iter().flat_map(|file_set| &file_set.files).fold(
         JunitFileToReportAndParseIssues::new(),
         |mut parse_results, bundled_file| -> JunitFileToReportAndParseIssues {
-            let path = std::path::Path::new(&bundled_file.original_path);
+            let path = std::path::Path::new(bundled_file.read_path());
             let file = match std::fs::File::open(path) {
                 Ok(file) => file,
                 Err(e) => {
@@ -165,7 +462,9 @@ fn parse_file_sets(file_sets: &[FileSet]) -> JunitFileToReportAndParseIssues {
             };
 
             let file_buf_reader = BufReader::new(file);
-            let mut junit_parser = JunitParser::new();
+            let mut junit_parser = JunitParser::with_repo_root(repo_root)
+                .with_infer_file_from_classname(infer_file_from_classname)
+                .with_attr_map(junit_attr_map.clone());
             if let Err(e) = junit_parser.parse(file_buf_reader) {
                 parse_results.insert(
                     bundled_file.get_print_path().to_string(),
@@ -205,6 +504,12 @@ fn print_matched_files(file_set_builder: &FileSetBuilder) {
             println!("    {}", file.get_print_path());
         }
     }
+    if file_set_builder.num_expired_files() > 0 {
+        println!(
+            "  Skipped {} files older than --junit-max-age",
+            file_set_builder.num_expired_files()
+        );
+    }
 }
 
 fn print_parse_issues(parse_issues: &JunitFileToParseIssues) -> (usize, usize) {
@@ -328,7 +633,189 @@ fn print_summary_success(num_reports: usize, num_suboptimal_reports: usize) {
     );
 }
 
-fn print_validation_issues(report_validations: &JunitFileToValidation) -> (usize, usize) {
+fn print_closest_schema(schema: JunitSchema, report_validations: &JunitFileToValidation) {
+    let rule_checks = report_validations
+        .values()
+        .flat_map(|report_validation| &report_validation.schema_rule_checks);
+    if let Some(closest_schema) = closest_schema(schema, rule_checks) {
+        println!(
+            "{}",
+            format!(
+                "Most {} rules fail, but the files mostly conform to {}; try --schema {}",
+                schema, closest_schema, closest_schema
+            )
+            .yellow()
+        );
+    }
+}
+
+fn print_timings(timings: &JunitTimings) {
+    println!("\nSlowest test cases:");
+    for test_case in &timings.slowest_test_cases {
+        println!(
+            "  {:>10.3}s  {} ({})",
+            test_case.duration_secs, test_case.name, test_case.test_suite
+        );
+    }
+    println!("\nSlowest test suites:");
+    for test_suite in &timings.slowest_test_suites {
+        println!(
+            "  {:>10.3}s  {} ({} test cases)",
+            test_suite.duration_secs, test_suite.name, test_suite.num_test_cases
+        );
+    }
+    println!("\nTest case durations:");
+    for DurationBucketCount { bucket, count } in &timings.histogram {
+        println!("  {:<10} {}", bucket.to_string(), count);
+    }
+    let num_unknown = timings.count(DurationBucket::Unknown);
+    if num_unknown > 0 {
+        println!(
+            "{}",
+            format!("{} test cases have no duration", num_unknown).yellow()
+        );
+    }
+}
+
+/// Writes the baseline, or compares to it and returns the number of metrics that regressed.
+fn check_baseline(
+    baseline_options: &BaselineOptions,
+    current: ValidateBaseline,
+) -> anyhow::Result<usize> {
+    if baseline_options.write {
+        write_baseline(&baseline_options.path, &current)?;
+        println!(
+            "\nWrote validation baseline of {} test cases to {}",
+            current.aggregate.num_test_cases,
+            baseline_options.path.display()
+        );
+        return Ok(0);
+    }
+
+    let baseline = read_baseline(&baseline_options.path)?;
+    let deltas = compare_to_baseline(
+        &baseline.aggregate,
+        &current.aggregate,
+        baseline_options.tolerance,
+    );
+    println!(
+        "\nComparing to validation baseline {} ({} test cases, now {}):",
+        baseline_options.path.display(),
+        baseline.aggregate.num_test_cases,
+        current.aggregate.num_test_cases
+    );
+    if deltas.is_empty() {
+        println!("  No metrics changed{}", Emoji(" ✅", ""));
+        return Ok(0);
+    }
+    print_baseline_deltas(&deltas);
+
+    let num_baseline_regressions = deltas
+        .iter()
+        .filter(|delta| delta.status == BaselineDeltaStatus::Regressed)
+        .count();
+    if num_baseline_regressions > 0 {
+        println!(
+            "{}",
+            format!(
+                "{} metrics regressed beyond the tolerance of {} percentage points",
+                num_baseline_regressions, baseline_options.tolerance
+            )
+            .red()
+        );
+    }
+    Ok(num_baseline_regressions)
+}
+
+fn print_baseline_deltas(deltas: &[BaselineDelta]) {
+    let metric_width = deltas
+        .iter()
+        .map(|delta| delta.metric.len())
+        .max()
+        .unwrap_or_default();
+    println!(
+        "  {:<metric_width$}  {:>9}  {:>9}  {:>9}",
+        "metric", "baseline", "current", "delta"
+    );
+    for delta in deltas {
+        let status = match delta.status {
+            BaselineDeltaStatus::Improved => "improved".green(),
+            BaselineDeltaStatus::WithinTolerance => "within tolerance".yellow(),
+            BaselineDeltaStatus::Regressed => "REGRESSED".red(),
+        };
+        // both kinds of metrics are percentages of the test cases
+        println!(
+            "  {:<metric_width$}  {:>8.1}%  {:>8.1}%  {:>+9.1}  {}",
+            delta.metric,
+            delta.baseline,
+            delta.current,
+            delta.current - delta.baseline,
+            status
+        );
+    }
+}
+
+fn print_strictness_summary(validate_run_result: &ValidateRunResult) {
+    let ValidateRunResult {
+        strictness,
+        num_invalid_reports,
+        num_suboptimal_reports,
+        num_suboptimal_codeowners_issues,
+        ..
+    } = validate_run_result;
+    let exit_code = validate_run_result.exit_code();
+    let validation_exit_code = validate_run_result.validation_exit_code();
+    println!(
+        "\nStrictness {}: {} files not valid, {} files with validation warnings, {} codeowners warnings; {} {}{}",
+        strictness.to_string().bold(),
+        num_invalid_reports.to_string().red(),
+        num_suboptimal_reports.to_string().yellow(),
+        num_suboptimal_codeowners_issues.to_string().yellow(),
+        if validate_run_result.previous_exit_code.is_some() {
+            "validation exit code"
+        } else {
+            "exiting with"
+        },
+        validation_exit_code,
+        if validation_exit_code == EXIT_SUCCESS {
+            Emoji(" ✅", "")
+        } else {
+            Emoji(" ❌", "")
+        },
+    );
+    if validate_run_result.num_baseline_regressions > 0 {
+        println!(
+            "{} metrics regressed from the validation baseline{}",
+            validate_run_result
+                .num_baseline_regressions
+                .to_string()
+                .red(),
+            match validate_run_result.baseline_regressions {
+                BaselineRegressions::Fail => "",
+                BaselineRegressions::Warn => " (not failing with --baseline-regressions warn)",
+            }
+        );
+    }
+    if let Some(previous_exit_code) = validate_run_result.previous_exit_code {
+        if previous_exit_code == EXIT_SUCCESS {
+            println!(
+                "Previous command exited with {}; exiting with validation exit code {}",
+                previous_exit_code, exit_code
+            );
+        } else {
+            println!(
+                "Previous command exited with {}; exiting with it {}",
+                previous_exit_code,
+                Emoji("❌", "")
+            );
+        }
+    }
+}
+
+fn print_validation_issues(
+    report_validations: &JunitFileToValidation,
+    schema: JunitSchema,
+) -> (usize, usize) {
     let mut num_invalid_reports: usize = 0;
     let mut num_suboptimal_reports: usize = 0;
     for (i, (file, report_validation)) in report_validations.iter().enumerate() {
@@ -340,7 +827,6 @@ fn print_validation_issues(report_validations: &JunitFileToValidation) -> (usize
         let num_test_cases = report_validation.test_cases().len();
         let num_validation_errors = report_validation.num_invalid_issues();
         let num_validation_warnings = report_validation.num_suboptimal_issues();
-        let all_issues: Vec<JunitReportValidationFlatIssue> = report_validation.all_issues_flat();
 
         let num_validation_errors_str = if num_validation_errors > 0 {
             num_validation_errors.to_string().red()
@@ -364,11 +850,28 @@ fn print_validation_issues(report_validations: &JunitFileToValidation) -> (usize
             num_validation_warnings_str,
         );
 
-        for issue in all_issues {
+        for issue in report_validation.all_issues() {
+            // Which dialect's rules produced an issue only matters when validating one
+            let schema_str = if schema == JunitSchema::Junit {
+                String::new()
+            } else {
+                format!("[{}] ", issue.schema())
+            };
             println!(
-                "  {} - {}",
-                print_validation_level(issue.level),
-                issue.error_message,
+                "  {} - {}{}",
+                print_validation_level(JunitValidationLevel::from(issue)),
+                schema_str,
+                issue.to_string(),
+            );
+        }
+
+        if let Some(duration_coverage_ratio) = report_validation
+            .duration_coverage
+            .and_then(|duration_coverage| duration_coverage.ratio())
+        {
+            println!(
+                "  Declared test case time covers {:.0}% of wall clock",
+                duration_coverage_ratio * 100.0
             );
         }
 
@@ -391,11 +894,13 @@ fn print_validation_level(level: JunitValidationLevel) -> ColoredString {
     }
 }
 
+/// Returns the number of suboptimal codeowners issues.
 fn print_codeowners_validation(
     codeowners: Option<CodeOwners>,
     report_validations: &JunitFileToValidation,
-) {
+) -> usize {
     println!("\nChecking for codeowners file...");
+    let mut num_suboptimal_codeowners_issues: usize = 0;
     match codeowners {
         Some(owners) => {
             println!(
@@ -417,15 +922,41 @@ fn print_codeowners_validation(
                 });
 
             if has_test_cases_without_matching_codeowners_paths {
+                num_suboptimal_codeowners_issues += 1;
                 println!(
                     "    {} - CODEOWNERS found but test cases are missing filepaths. We will not be able to correlate flaky tests with owners.",
                     print_validation_level(JunitValidationLevel::SubOptimal)
                 );
             }
+
+            let has_test_cases_with_paths_outside_repo = report_validations
+                .iter()
+                .flat_map(|(_, report_validation)| report_validation.test_cases())
+                .flat_map(|test_case_validation| test_case_validation.issues())
+                .any(|issue| {
+                    matches!(
+                        issue,
+                        JunitValidationIssue::SubOptimal(
+                            JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathOutsideRepo(..)
+                        )
+                    )
+                });
+
+            if has_test_cases_with_paths_outside_repo {
+                num_suboptimal_codeowners_issues += 1;
+                println!(
+                    "    {} - CODEOWNERS found but some test case filepaths are outside of the repo. We will not be able to correlate those tests with owners.",
+                    print_validation_level(JunitValidationLevel::SubOptimal)
+                );
+            }
+        }
+        None => {
+            num_suboptimal_codeowners_issues += 1;
+            println!(
+                "  {} - No codeowners file found.",
+                print_validation_level(JunitValidationLevel::SubOptimal)
+            );
         }
-        None => println!(
-            "  {} - No codeowners file found.",
-            print_validation_level(JunitValidationLevel::SubOptimal)
-        ),
     }
+    num_suboptimal_codeowners_issues
 }
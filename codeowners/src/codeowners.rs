@@ -146,16 +146,18 @@ impl BindingsOwners {
     }
 }
 
-fn associate_codeowners<T: AsRef<Path>>(owners: &Owners, file: T) -> Vec<String> {
+pub fn associate_codeowners<T: AsRef<Path>>(owners: &Owners, file: T) -> Vec<String> {
+    // CODEOWNERS patterns always use `/`, so match Windows paths with their separators converted
+    let file = file.as_ref().to_string_lossy().replace('\\', "/");
     match owners {
         Owners::GitHubOwners(gho) => gho
-            .of(file)
+            .of(&file)
             .unwrap_or_default()
             .iter()
             .map(ToString::to_string)
             .collect(),
         Owners::GitLabOwners(glo) => glo
-            .of(file)
+            .of(&file)
             .unwrap_or_default()
             .iter()
             .map(ToString::to_string)
@@ -253,4 +255,19 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    pub async fn test_association_with_windows_paths() {
+        let codeowners = CodeOwners::parse(b"src/Foo.Tests/ @dotnet-team".to_vec());
+        let owners = Arc::new(codeowners.owners.unwrap());
+
+        let owners = crate::associate_codeowners_multithreaded(vec![
+            (owners.clone(), String::from(r"src\Foo.Tests\BarTests.cs")),
+            (owners, String::from("src/Foo.Tests/BarTests.cs")),
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(owners, vec![vec!["@dotnet-team"], vec!["@dotnet-team"]]);
+    }
 }
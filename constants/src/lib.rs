@@ -21,6 +21,12 @@ pub const CODEOWNERS_LOCATIONS: &[&str] = &[".github", ".bitbucket", ".", "docs"
 pub const DEFAULT_ORIGIN: &str = "https://api.trunk.io";
 pub const TRUNK_PUBLIC_API_ADDRESS_ENV: &str = "TRUNK_PUBLIC_API_ADDRESS";
 pub const TRUNK_API_CLIENT_RETRY_COUNT_ENV: &str = "TRUNK_API_CLIENT_RETRY_COUNT";
+pub const TRUNK_API_TIMEOUT_SECS_ENV: &str = "TRUNK_API_TIMEOUT_SECS";
+pub const TRUNK_S3_TIMEOUT_SECS_ENV: &str = "TRUNK_S3_TIMEOUT_SECS";
+pub const TRUNK_CONNECT_TIMEOUT_SECS_ENV: &str = "TRUNK_CONNECT_TIMEOUT_SECS";
+pub const TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV: &str = "TRUNK_GITHUB_ACTIONS_API_LOOKUP";
+/// Not read from the environment, but recorded in the bundle's envs alongside the GitHub env vars
+pub const GITHUB_EXTERNAL_ID_ENV: &str = "GITHUB_EXTERNAL_ID";
 pub const ENVS_TO_GET: &[&str] = &[
     "CI",
     "GIT_BRANCH",
@@ -35,6 +41,7 @@ pub const ENVS_TO_GET: &[&str] = &[
     "GITHUB_ACTIONS",
     "GITHUB_ACTION_REPOSITORY",
     "GITHUB_ACTOR",
+    "GITHUB_API_URL",
     "GITHUB_BASE_REF",
     "GITHUB_EVENT_NAME",
     "GITHUB_HEAD_REF",
@@ -60,6 +67,8 @@ pub const ENVS_TO_GET: &[&str] = &[
     "CI_COMMIT_REF_NAME",
     "CI_COMMIT_MESSAGE",
     "CI_MERGE_REQUEST_SOURCE_BRANCH_NAME",
+    "CI_MERGE_REQUEST_SOURCE_BRANCH_SHA",
+    "CI_MERGE_REQUEST_TARGET_BRANCH_NAME",
     "CI_EXTERNAL_PULL_REQUEST_SOURCE_BRANCH_NAME",
     "CI_COMMIT_SHA",
     "CI_MERGE_REQUEST_EVENT_TYPE",
@@ -71,6 +80,7 @@ pub const ENVS_TO_GET: &[&str] = &[
     "CI_JOB_STAGE",
     // Jenkins
     "BUILD_ID",
+    "BUILD_NUMBER",
     "JENKINS_URL",
     "EXECUTOR_NUMBER",
     "JOB_NAME",
@@ -355,6 +365,19 @@ pub const ENVS_TO_GET: &[&str] = &[
     "DRONE_TARGET_BRANCH",
     "DRONE_WORKSPACE",
 ];
+/// Env vars captured with the `ci-only` env capture profile in addition to the ones the CI info was
+/// parsed from
+pub const ENVS_CI_ONLY_ALLOWLIST: &[&str] = &["CI"];
+/// Env vars whose name contains one of these, case-insensitively, are never captured
+pub const ENVS_DENYLIST_SUBSTRINGS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
 
 pub const SENTRY_DSN: &str =
     "https://4814eaf1df0e8a1e3303bb7e2f89095a@o681886.ingest.us.sentry.io/4507772986982400";
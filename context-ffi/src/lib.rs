@@ -0,0 +1,204 @@
+//! C ABI for the parsing functions of the Python and JS bindings, for consumers that can only
+//! load a shared library.
+//!
+//! Results are returned as JSON in the same shape as the binding types, e.g.
+//! [`BindingsParseResult`], so that consumers can share schemas with the Python and JS bindings.
+//! Every buffer returned through an `out_*` pointer, including the error messages of
+//! unsuccessful calls, is nul-terminated and must be released with [`context_free`].
+//!
+//! The C header is generated by cbindgen into the `OUT_DIR` of the build script, e.g.
+//! `target/release/build/context-ffi-*/out/context_ffi.h`.
+//!
+//! Panics are reported as [`ContextStatus::Panic`] only when they unwind. The `release` profile of
+//! the workspace sets `panic = "abort"`, so a panic in a library built with it aborts the process
+//! that loaded it. The `dev` and `release-with-debug` profiles unwind.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    io::BufReader,
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use bundle::{parse_meta as parse_meta_impl, Test};
+use context::{
+    junit::{bindings::BindingsParseResult, parser::JunitParser},
+    repo::RepoUrlParts,
+};
+use serde::Serialize;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextStatus {
+    Ok = 0,
+    /// The input could not be parsed, the output is the error message
+    Error = 1,
+    /// A required pointer was null or a string was not valid UTF-8
+    InvalidArgument = 2,
+    /// The call panicked, the output is the panic message if it had one
+    Panic = 3,
+}
+
+/// Parses a junit xml like `junit_parse` of the bindings, outputting the `BindingsParseResult`
+/// as JSON.
+///
+/// # Safety
+///
+/// `xml_ptr` must point to `xml_len` readable bytes, and `out_json_ptr` and `out_json_len` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn context_junit_parse(
+    xml_ptr: *const u8,
+    xml_len: usize,
+    out_json_ptr: *mut *mut c_char,
+    out_json_len: *mut usize,
+) -> ContextStatus {
+    call(out_json_ptr, out_json_len, || {
+        let xml = bytes_arg(xml_ptr, xml_len)?;
+        let mut junit_parser = JunitParser::new();
+        junit_parser
+            .parse(BufReader::new(xml))
+            .map_err(|e| CallError::Error(e.to_string()))?;
+        to_json(&BindingsParseResult::from(junit_parser))
+    })
+}
+
+/// Generates the ID of a test case like the CLI does when uploading, outputting it as a string.
+/// `file`, `classname` and `variant` may be null.
+///
+/// # Safety
+///
+/// Every non-null string must be nul-terminated, and `out_id_ptr` and `out_id_len` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn context_gen_info_id(
+    org_url_slug: *const c_char,
+    repo_url: *const c_char,
+    file: *const c_char,
+    classname: *const c_char,
+    parent_name: *const c_char,
+    name: *const c_char,
+    variant: *const c_char,
+    out_id_ptr: *mut *mut c_char,
+    out_id_len: *mut usize,
+) -> ContextStatus {
+    call(out_id_ptr, out_id_len, || {
+        let org_url_slug = str_arg(org_url_slug)?.ok_or(CallError::InvalidArgument)?;
+        let repo_url = str_arg(repo_url)?.ok_or(CallError::InvalidArgument)?;
+        let repo = RepoUrlParts::from_url(repo_url).map_err(|e| CallError::Error(e.to_string()))?;
+        let test = Test::new(
+            str_arg(name)?.ok_or(CallError::InvalidArgument)?.into(),
+            str_arg(parent_name)?
+                .ok_or(CallError::InvalidArgument)?
+                .into(),
+            str_arg(classname)?.map(String::from),
+            str_arg(file)?.map(String::from),
+            org_url_slug,
+            &repo,
+            None,
+            str_arg(variant)?,
+        );
+        Ok(test.id)
+    })
+}
+
+/// Parses the `meta.json` of a bundle like `parse_meta` of the bindings, outputting the
+/// `VersionedBundle` as JSON.
+///
+/// # Safety
+///
+/// `meta_ptr` must point to `meta_len` readable bytes, and `out_json_ptr` and `out_json_len` must
+/// be writable.
+#[no_mangle]
+pub unsafe extern "C" fn context_parse_meta(
+    meta_ptr: *const u8,
+    meta_len: usize,
+    out_json_ptr: *mut *mut c_char,
+    out_json_len: *mut usize,
+) -> ContextStatus {
+    call(out_json_ptr, out_json_len, || {
+        let meta_bytes = bytes_arg(meta_ptr, meta_len)?;
+        let versioned_bundle =
+            parse_meta_impl(meta_bytes.to_vec()).map_err(|e| CallError::Error(e.to_string()))?;
+        to_json(&versioned_bundle)
+    })
+}
+
+/// Releases a buffer returned by any of the other functions. Does nothing if `ptr` is null.
+///
+/// # Safety
+///
+/// `ptr` must be null or a buffer returned by this library that was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn context_free(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        drop(CString::from_raw(ptr));
+    }
+}
+
+enum CallError {
+    Error(String),
+    InvalidArgument,
+}
+
+/// Runs `f`, writing its output or error message to `out_ptr` and `out_len`.
+unsafe fn call<F: FnOnce() -> Result<String, CallError>>(
+    out_ptr: *mut *mut c_char,
+    out_len: *mut usize,
+    f: F,
+) -> ContextStatus {
+    if out_ptr.is_null() || out_len.is_null() {
+        return ContextStatus::InvalidArgument;
+    }
+    *out_ptr = ptr::null_mut();
+    *out_len = 0;
+
+    let (status, out) = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(out)) => (ContextStatus::Ok, out),
+        Ok(Err(CallError::Error(message))) => (ContextStatus::Error, message),
+        Ok(Err(CallError::InvalidArgument)) => return ContextStatus::InvalidArgument,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            (ContextStatus::Panic, message)
+        }
+    };
+
+    // Neither JSON nor the messages have interior nul bytes, but don't rely on it
+    let out = CString::new(out).unwrap_or_else(|e| {
+        let nul_position = e.nul_position();
+        let mut out = e.into_vec();
+        out.truncate(nul_position);
+        CString::new(out).unwrap_or_default()
+    });
+    *out_len = out.as_bytes().len();
+    *out_ptr = out.into_raw();
+    status
+}
+
+unsafe fn bytes_arg<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], CallError> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(CallError::InvalidArgument);
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<Option<&'a str>, CallError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|_| CallError::InvalidArgument)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, CallError> {
+    serde_json::to_string(value).map_err(|e| CallError::Error(e.to_string()))
+}
@@ -0,0 +1,185 @@
+use std::{
+    ffi::{c_char, CStr, CString},
+    path::PathBuf,
+    ptr,
+};
+
+use bundle::Test;
+use context::repo::RepoUrlParts;
+use context_ffi::ContextStatus;
+use libloading::{Library, Symbol};
+
+type ParseBytesFn =
+    unsafe extern "C" fn(*const u8, usize, *mut *mut c_char, *mut usize) -> ContextStatus;
+type GenInfoIdFn = unsafe extern "C" fn(
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *mut *mut c_char,
+    *mut usize,
+) -> ContextStatus;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// Loads the cdylib that cargo builds into the `deps` directory next to this test binary.
+fn load_library() -> Library {
+    let deps_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .map(PathBuf::from)
+        .unwrap();
+    let library_path = deps_dir.join(libloading::library_filename("context_ffi"));
+    unsafe { Library::new(&library_path) }
+        .unwrap_or_else(|e| panic!("failed to load {}: {}", library_path.display(), e))
+}
+
+fn take_output(library: &Library, out_ptr: *mut c_char, out_len: usize) -> String {
+    assert!(!out_ptr.is_null());
+    let output = unsafe { CStr::from_ptr(out_ptr) }
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(output.len(), out_len);
+    unsafe {
+        let context_free: Symbol<FreeFn> = library.get(b"context_free").unwrap();
+        context_free(out_ptr);
+    }
+    output
+}
+
+fn parse_bytes(library: &Library, symbol: &[u8], bytes: &[u8]) -> (ContextStatus, String) {
+    let mut out_ptr = ptr::null_mut();
+    let mut out_len = 0;
+    let status = unsafe {
+        let parse_bytes: Symbol<ParseBytesFn> = library.get(symbol).unwrap();
+        parse_bytes(bytes.as_ptr(), bytes.len(), &mut out_ptr, &mut out_len)
+    };
+    (status, take_output(library, out_ptr, out_len))
+}
+
+#[test]
+fn test_junit_parse() {
+    let library = load_library();
+    let xml = std::fs::read(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../context/test_fixtures/junit_utf8.xml"),
+    )
+    .unwrap();
+
+    let (status, json) = parse_bytes(&library, b"context_junit_parse", &xml);
+    assert_eq!(status, ContextStatus::Ok, "{}", json);
+
+    let parse_result: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(parse_result["issues"], serde_json::json!([]));
+    let report = &parse_result["report"];
+    assert_eq!(report["name"], "Kaffeemaschine");
+    assert_eq!(report["tests"], 2);
+    let test_cases = report["test_suites"][0]["test_cases"].as_array().unwrap();
+    assert_eq!(test_cases.len(), 2);
+    assert_eq!(test_cases[1]["name"], "zahle_5_€");
+    assert_eq!(test_cases[1]["status"]["status"], "NonSuccess");
+    assert_eq!(
+        test_cases[1]["status"]["non_success"]["message"],
+        "erwartet 5 €, erhalten 4 €"
+    );
+}
+
+#[test]
+fn test_junit_parse_null_xml() {
+    let library = load_library();
+
+    let mut out_ptr = ptr::null_mut();
+    let mut out_len = 0;
+    let status = unsafe {
+        let context_junit_parse: Symbol<ParseBytesFn> =
+            library.get(b"context_junit_parse").unwrap();
+        context_junit_parse(ptr::null(), 1, &mut out_ptr, &mut out_len)
+    };
+    assert_eq!(status, ContextStatus::InvalidArgument);
+    assert!(out_ptr.is_null());
+}
+
+#[test]
+fn test_parse_meta_error() {
+    let library = load_library();
+
+    let (status, message) = parse_bytes(&library, b"context_parse_meta", b"{}");
+    assert_eq!(status, ContextStatus::Error);
+    assert!(!message.is_empty());
+}
+
+#[test]
+fn test_gen_info_id() {
+    let library = load_library();
+    let org_url_slug = CString::new("trunk").unwrap();
+    let repo_url = CString::new("https://github.com/trunk-io/analytics-cli.git").unwrap();
+    let parent_name = CString::new("HelloTest").unwrap();
+    let name = CString::new("Hello").unwrap();
+    let variant = CString::new("linux").unwrap();
+
+    let mut out_ptr = ptr::null_mut();
+    let mut out_len = 0;
+    let status = unsafe {
+        let context_gen_info_id: Symbol<GenInfoIdFn> = library.get(b"context_gen_info_id").unwrap();
+        context_gen_info_id(
+            org_url_slug.as_ptr(),
+            repo_url.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            parent_name.as_ptr(),
+            name.as_ptr(),
+            variant.as_ptr(),
+            &mut out_ptr,
+            &mut out_len,
+        )
+    };
+    let id = take_output(&library, out_ptr, out_len);
+    assert_eq!(status, ContextStatus::Ok, "{}", id);
+
+    let expected = Test::new(
+        "Hello".into(),
+        "HelloTest".into(),
+        None,
+        None,
+        "trunk",
+        &RepoUrlParts::from_url("https://github.com/trunk-io/analytics-cli.git").unwrap(),
+        None,
+        Some("linux"),
+    );
+    assert_eq!(id, expected.id);
+
+    let status = unsafe {
+        let context_gen_info_id: Symbol<GenInfoIdFn> = library.get(b"context_gen_info_id").unwrap();
+        context_gen_info_id(
+            org_url_slug.as_ptr(),
+            repo_url.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            parent_name.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            &mut out_ptr,
+            &mut out_len,
+        )
+    };
+    assert_eq!(status, ContextStatus::InvalidArgument);
+    assert!(out_ptr.is_null());
+}
+
+#[test]
+fn test_header_declares_exported_functions() {
+    let header = include_str!(concat!(env!("OUT_DIR"), "/context_ffi.h"));
+    for function in [
+        "context_junit_parse",
+        "context_gen_info_id",
+        "context_parse_meta",
+        "context_free",
+    ] {
+        assert!(
+            header.contains(&format!(" {function}(")),
+            "{function} is not declared in the header"
+        );
+    }
+}
@@ -1,14 +1,19 @@
 use std::{collections::HashMap, io::BufReader};
 
 use bundle::{parse_meta_from_tarball as parse_tarball, VersionedBundle};
-use context::{env, junit, repo};
+use context::{env, info_id, junit, meta, repo};
 use futures::{future::Either, io::BufReader as BufReaderAsync, stream::TryStreamExt};
 use js_sys::Uint8Array;
+use prost::Message;
+use proto::test_context::test_run::TestResult;
 use wasm_bindgen::prelude::*;
 use wasm_streams::{readable::sys, readable::ReadableStream};
 
 #[wasm_bindgen]
-pub fn env_parse(env_vars: js_sys::Object) -> Option<env::parser::CIInfo> {
+pub fn env_parse(
+    env_vars: js_sys::Object,
+    stable_branches: Option<Vec<String>>,
+) -> Result<Option<env::parser::CIInfo>, JsError> {
     let env_vars: HashMap<String, String> = js_sys::Object::entries(&env_vars)
         .iter()
         .filter_map(|entry| {
@@ -22,12 +27,14 @@ pub fn env_parse(env_vars: js_sys::Object) -> Option<env::parser::CIInfo> {
             }
         })
         .collect();
-    let mut env_parser = env::parser::EnvParser::new();
+    let stable_branches = env::parser::StableBranches::new(&stable_branches.unwrap_or_default())
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let mut env_parser = env::parser::EnvParser::with_stable_branches(stable_branches);
     env_parser.parse(&env_vars);
 
-    env_parser
+    Ok(env_parser
         .into_ci_info_parser()
-        .map(|ci_info_parser| ci_info_parser.info_ci_info())
+        .map(|ci_info_parser| ci_info_parser.info_ci_info()))
 }
 
 #[wasm_bindgen]
@@ -35,8 +42,16 @@ pub fn parse_branch_class(
     value: &str,
     pr_number: Option<usize>,
     gitlab_merge_request_event_type: Option<env::parser::GitLabMergeRequestEventType>,
-) -> env::parser::BranchClass {
-    env::parser::BranchClass::from((value, pr_number, gitlab_merge_request_event_type))
+    stable_branches: Option<Vec<String>>,
+) -> Result<env::parser::BranchClass, JsError> {
+    let stable_branches = env::parser::StableBranches::new(&stable_branches.unwrap_or_default())
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(env::parser::BranchClass::from((
+        value,
+        pr_number,
+        gitlab_merge_request_event_type,
+        &stable_branches,
+    )))
 }
 
 #[wasm_bindgen]
@@ -45,25 +60,135 @@ pub fn env_validate(ci_info: &env::parser::CIInfo) -> env::validator::EnvValidat
 }
 
 #[wasm_bindgen]
-pub fn junit_parse(xml: Vec<u8>) -> Result<junit::bindings::BindingsParseResult, JsError> {
-    let mut junit_parser = junit::parser::JunitParser::new();
+pub fn junit_parse(
+    xml: Vec<u8>,
+    repo_root: Option<String>,
+) -> Result<junit::bindings::BindingsParseResult, JsError> {
+    parse_junit(&xml, repo_root).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn parse_junit(
+    xml: &[u8],
+    repo_root: Option<String>,
+) -> anyhow::Result<junit::bindings::BindingsParseResult> {
+    let mut stream_parser = JunitStreamParser::new(repo_root);
+    stream_parser.push_chunk(xml)?;
+    stream_parser.finish_parse()
+}
+
+/// Parses junit XML pushed in chunks, e.g. as read from a file too large to be held in memory,
+/// keeping only what is left of the last chunk after its complete markup. Finishes to the same
+/// result as [`junit_parse`] on all of the XML.
+#[wasm_bindgen]
+pub struct JunitStreamParser(junit::stream::JunitStreamParser);
+
+#[wasm_bindgen]
+impl JunitStreamParser {
+    #[wasm_bindgen(constructor)]
+    pub fn new(repo_root: Option<String>) -> Self {
+        let junit_parser = match repo_root {
+            Some(repo_root) => junit::parser::JunitParser::with_repo_root(repo_root),
+            None => junit::parser::JunitParser::new(),
+        };
+        Self(junit::stream::JunitStreamParser::new(junit_parser))
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), JsError> {
+        self.push_chunk(chunk)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    pub fn finish(self) -> Result<junit::bindings::BindingsParseResult, JsError> {
+        self.finish_parse()
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+impl JunitStreamParser {
+    fn push_chunk(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        self.0.push(chunk)
+    }
+
+    fn finish_parse(self) -> anyhow::Result<junit::bindings::BindingsParseResult> {
+        let junit_parser = self.0.finish()?;
+        let issues_flat = junit_parser.issues_flat();
+        let mut parsed_reports = junit_parser.into_reports();
+
+        let report = if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop())
+        {
+            Some(junit::bindings::BindingsReport::from(parsed_report))
+        } else {
+            None
+        };
+
+        Ok(junit::bindings::BindingsParseResult {
+            report,
+            issues: issues_flat,
+        })
+    }
+}
+
+/// Parses and validates in one call, without converting the report to JS and back in between.
+#[wasm_bindgen]
+pub fn junit_parse_and_validate(
+    xml: Vec<u8>,
+    repo_root: Option<String>,
+) -> Result<junit::bindings::BindingsParseAndValidateResult, JsError> {
+    let mut junit_parser = match repo_root {
+        Some(repo_root) => junit::parser::JunitParser::with_repo_root(repo_root),
+        None => junit::parser::JunitParser::new(),
+    };
     junit_parser
         .parse(BufReader::new(&xml[..]))
         .map_err(|e| JsError::new(&e.to_string()))?;
 
-    let issues_flat = junit_parser.issues_flat();
-    let mut parsed_reports = junit_parser.into_reports();
+    Ok(junit::bindings::BindingsParseAndValidateResult::from(
+        junit_parser,
+    ))
+}
+
+/// The result of one of the inputs of a `*_many` function, which fails on its own instead of
+/// throwing for the whole batch.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct JunitParseManyResult {
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub parse_result: Option<junit::bindings::BindingsParseResult>,
+}
 
-    let report = if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop()) {
-        Some(junit::bindings::BindingsReport::from(parsed_report))
-    } else {
-        None
-    };
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct BinParseManyResult {
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub reports: Option<Vec<junit::bindings::BindingsReport>>,
+}
 
-    Ok(junit::bindings::BindingsParseResult {
-        report,
-        issues: issues_flat,
-    })
+/// Parses each of `xmls` like [`junit_parse`] in a single call, returning their results in order.
+#[wasm_bindgen]
+pub fn junit_parse_many(
+    xmls: Vec<Uint8Array>,
+    repo_root: Option<String>,
+) -> Vec<JunitParseManyResult> {
+    let mut buf = Vec::new();
+    xmls.iter()
+        .map(|xml| {
+            copy_to_buf(xml, &mut buf);
+            match parse_junit(&buf, repo_root.clone()) {
+                Ok(parse_result) => JunitParseManyResult {
+                    success: true,
+                    error_message: None,
+                    parse_result: Some(parse_result),
+                },
+                Err(e) => JunitParseManyResult {
+                    success: false,
+                    error_message: Some(e.to_string()),
+                    parse_result: None,
+                },
+            }
+        })
+        .collect()
 }
 
 #[wasm_bindgen]
@@ -75,11 +200,118 @@ pub fn junit_validate(
     ))
 }
 
+/// Parses and validates in one call like the CLI does, for callers that only need the validation.
+/// With `repo_root`, file paths inside it are made relative while parsing, so only test case files
+/// outside of the repo are reported as such, which is the one validation that depends on it.
+/// Returns `undefined` unless the xml has exactly one report.
+#[wasm_bindgen]
+pub fn junit_validate_xml(
+    xml: Vec<u8>,
+    repo_root: Option<String>,
+) -> Result<Option<junit::bindings::BindingsJunitReportValidation>, JsError> {
+    Ok(junit_parse_and_validate(xml, repo_root)?.validation)
+}
+
+/// The ID given to a test case, see [`info_id`] for how it is derived.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn gen_info_id(
+    org_url_slug: &str,
+    repo_full_name: &str,
+    file: Option<String>,
+    classname: Option<String>,
+    parent_name: &str,
+    name: &str,
+    info_id: Option<String>,
+    variant: Option<String>,
+) -> String {
+    info_id::gen_info_id(
+        org_url_slug,
+        repo_full_name,
+        file.as_deref(),
+        classname.as_deref(),
+        parent_name,
+        name,
+        info_id.as_deref(),
+        variant.as_deref(),
+    )
+}
+
+#[wasm_bindgen]
+pub fn gen_info_id_vectors() -> Vec<info_id::InfoIdVector> {
+    info_id::gen_info_id_vectors()
+}
+
+/// Validates each of `reports` like [`junit_validate`] in a single call, returning their
+/// validations in order.
+#[wasm_bindgen]
+pub fn junit_validate_many(
+    reports: Vec<junit::bindings::BindingsReport>,
+) -> Vec<junit::bindings::BindingsJunitReportValidation> {
+    reports
+        .into_iter()
+        .map(|report| {
+            junit::bindings::BindingsJunitReportValidation::from(junit::validator::validate(
+                &report.into(),
+            ))
+        })
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn bin_parse(bin: Vec<u8>) -> Result<Vec<junit::bindings::BindingsReport>, JsError> {
+    parse_bin(&bin).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn parse_bin(bin: &[u8]) -> Result<Vec<junit::bindings::BindingsReport>, prost::DecodeError> {
+    let test_result = TestResult::decode(bin)?;
+    Ok(vec![junit::bindings::BindingsReport::from(test_result)])
+}
+
+/// Parses each of `bins` like [`bin_parse`] in a single call, returning their results in order.
+#[wasm_bindgen]
+pub fn bin_parse_many(bins: Vec<Uint8Array>) -> Vec<BinParseManyResult> {
+    let mut buf = Vec::new();
+    bins.iter()
+        .map(|bin| {
+            copy_to_buf(bin, &mut buf);
+            match parse_bin(&buf) {
+                Ok(reports) => BinParseManyResult {
+                    success: true,
+                    error_message: None,
+                    reports: Some(reports),
+                },
+                Err(e) => BinParseManyResult {
+                    success: false,
+                    error_message: Some(e.to_string()),
+                    reports: None,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Inputs are copied out of JS memory one at a time into the same buffer, so that a batch only
+/// needs as much memory as its largest input.
+fn copy_to_buf(array: &Uint8Array, buf: &mut Vec<u8>) {
+    buf.clear();
+    buf.resize(array.length() as usize, 0);
+    array.copy_to(buf);
+}
+
 #[wasm_bindgen]
 pub fn repo_validate(bundle_repo: repo::BundleRepo) -> repo::validator::RepoValidation {
     repo::validator::validate(&bundle_repo)
 }
 
+#[wasm_bindgen]
+pub fn meta_validate(
+    ci_info: &env::parser::CIInfo,
+    bundle_repo: &repo::BundleRepo,
+) -> meta::validator::MetaValidation {
+    meta::validator::validate(&meta::MetaContext::new(ci_info, bundle_repo))
+}
+
 #[wasm_bindgen()]
 pub async fn parse_meta_from_tarball(
     input: sys::ReadableStream,
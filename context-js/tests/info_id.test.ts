@@ -0,0 +1,71 @@
+import { createHash } from "crypto";
+import { describe, expect, it } from "vitest";
+
+import {
+  InfoIdVector,
+  gen_info_id,
+  gen_info_id_vectors,
+} from "../pkg/context_js";
+
+const NAMESPACE_URL = "6ba7b811-9dad-11d1-80b4-00c04fd430c8";
+
+const uuidV5 = (namespace: string, name: string): string => {
+  const hash = createHash("sha1")
+    .update(Buffer.from(namespace.replace(/-/g, ""), "hex"))
+    .update(Buffer.from(name, "utf8"))
+    .digest();
+  const bytes = hash.subarray(0, 16);
+  bytes[6] = (bytes[6] & 0x0f) | 0x50;
+  bytes[8] = (bytes[8] & 0x3f) | 0x80;
+  const hex = bytes.toString("hex");
+  return [
+    hex.slice(0, 8),
+    hex.slice(8, 12),
+    hex.slice(12, 16),
+    hex.slice(16, 20),
+    hex.slice(20),
+  ].join("-");
+};
+
+const referenceInfoId = (vector: InfoIdVector): string => {
+  if (vector.info_id) {
+    return vector.info_id;
+  }
+  const parts = [
+    vector.org_url_slug,
+    vector.repo_full_name,
+    vector.file ?? "",
+    vector.classname ?? "",
+    vector.parent_name,
+    vector.name,
+    "JUNIT_TESTCASE",
+  ];
+  if (vector.variant) {
+    parts.push(vector.variant);
+  }
+  return uuidV5(NAMESPACE_URL, parts.join("#"));
+};
+
+describe("context-js", () => {
+  it("generates the info IDs of the test vectors", () => {
+    expect.hasAssertions();
+
+    const vectors = gen_info_id_vectors();
+    expect(vectors.length).toBeGreaterThan(0);
+
+    vectors.forEach((vector) => {
+      const infoId = gen_info_id(
+        vector.org_url_slug,
+        vector.repo_full_name,
+        vector.file,
+        vector.classname,
+        vector.parent_name,
+        vector.name,
+        vector.info_id,
+        vector.variant,
+      );
+      expect(infoId, vector.note).toBe(vector.expected_id);
+      expect(referenceInfoId(vector), vector.note).toBe(vector.expected_id);
+    });
+  });
+});
@@ -8,16 +8,25 @@ import {
   EnvValidationLevel,
   JunitValidationLevel,
   JunitValidationType,
+  MetaValidationLevel,
   RepoUrlParts,
   RepoValidationLevel,
   env_parse,
   env_validate,
+  bin_parse,
+  bin_parse_many,
   junit_parse,
+  junit_parse_and_validate,
+  junit_parse_many,
   junit_validate,
+  junit_validate_many,
+  junit_validate_xml,
+  meta_validate,
   repo_validate,
   parse_branch_class,
   BranchClass,
   GitLabMergeRequestEventType,
+  JunitStreamParser,
 } from "../pkg/context_js";
 
 // eslint-disable-next-line vitest/require-hook
@@ -57,6 +66,18 @@ describe("context-js", () => {
       "CI info committer name too short",
       "CI info title too short",
     ]);
+    expect(
+      envValidation.issues_flat().map(({ level, field }) => [level, field]),
+    ).toStrictEqual(
+      [
+        "author_email",
+        "author_name",
+        "commit_message",
+        "committer_email",
+        "committer_name",
+        "title",
+      ].map((field) => [EnvValidationLevel.SubOptimal, field]),
+    );
   });
 
   it("parses and validates junit files", () => {
@@ -128,6 +149,180 @@ describe("context-js", () => {
     expect(junitReportValidation.max_level()).toBe(JunitValidationLevel.Valid);
   });
 
+  it("parses and validates junit files in batches like one at a time", () => {
+    expect.hasAssertions();
+
+    const validTimestamp = dayjs.utc().toISOString();
+    const staleTimestamp = dayjs.utc().subtract(30, "hour").toISOString();
+    const junitXml = (timestamp: string) => `
+      <testsuites name="my-test-run" tests="1" failures="1" errors="0">
+        <testsuite name="my-test-suite" tests="1" disabled="0" errors="0" failures="1" timestamp="${timestamp}">
+          <testcase name="failure-case" file="test.py" classname="MyClass" timestamp="${timestamp}" time="1">
+            <failure/>
+          </testcase>
+        </testsuite>
+      </testsuites>
+    `;
+    const xmls = [
+      Buffer.from(junitXml(validTimestamp), "utf-8"),
+      Buffer.from("<testsuites><testsuite", "utf-8"),
+      Buffer.from(junitXml(staleTimestamp), "utf-8"),
+    ];
+
+    const parseResults = junit_parse_many(xmls);
+
+    expect(
+      parseResults.map(({ success, error_message }) => [
+        success,
+        error_message === undefined,
+      ]),
+    ).toStrictEqual([
+      [true, true],
+      [false, false],
+      [true, true],
+    ]);
+    expect(() => junit_parse(xmls[1])).toThrow(parseResults[1].error_message);
+    const summarizeParseResult = (
+      parseResult: ReturnType<typeof junit_parse>,
+    ) => ({
+      report: parseResult.report?.name,
+      tests: parseResult.report?.tests,
+      issues: parseResult.issues.map(({ error_message }) => error_message),
+    });
+    expect(
+      [parseResults[0], parseResults[2]].map(({ parse_result }) =>
+        // eslint-disable-next-line vitest/no-conditional-in-test
+        parse_result ? summarizeParseResult(parse_result) : undefined,
+      ),
+    ).toStrictEqual(
+      [xmls[0], xmls[2]].map((xml) => summarizeParseResult(junit_parse(xml))),
+    );
+
+    const reports = [parseResults[0], parseResults[2]].map(
+      ({ parse_result }) => parse_result?.report,
+    );
+    // eslint-disable-next-line vitest/no-conditional-in-test
+    if (!reports[0] || !reports[1]) throw Error("report is undefined");
+    const validations = junit_validate_many([reports[0], reports[1]]);
+
+    expect(
+      validations.map((validation) => validation.max_level()),
+    ).toStrictEqual([JunitValidationLevel.Valid, JunitValidationLevel.SubOptimal]);
+    expect(
+      validations.map((validation) => validation.num_suboptimal_issues()),
+    ).toStrictEqual(
+      [reports[0], reports[1]].map((report) =>
+        junit_validate(report).num_suboptimal_issues(),
+      ),
+    );
+  });
+
+  it("parses and validates junit files in one call like in two", () => {
+    expect.hasAssertions();
+
+    const staleTimestamp = dayjs.utc().subtract(30, "hour").toISOString();
+    const xmls = [
+      `
+      <testsuites name="my-test-run" tests="1" failures="1" errors="0">
+        <testsuite name="my-test-suite" tests="1" disabled="0" errors="0" failures="1" timestamp="${staleTimestamp}">
+          <testcase name="failure-case" file="test.py" classname="MyClass" timestamp="${staleTimestamp}" time="1">
+            <failure/>
+          </testcase>
+          <testcase name="no-file" classname="MyClass"/>
+        </testsuite>
+      </testsuites>
+    `,
+      `<testsuite name="suite"><testcase name="" classname="MyClass"/></testsuite>`,
+      `<testsuites name="a"/><testsuites name="b"/>`,
+    ].map((xml) => Buffer.from(xml, "utf-8"));
+
+    const summarizeValidation = (
+      validation: ReturnType<typeof junit_validate> | undefined,
+    ) => ({
+      max_level: validation?.max_level(),
+      issues: validation
+        ?.all_issues_owned()
+        .map(({ level, error_type, error_message }) => [
+          level,
+          error_type,
+          error_message,
+        ]),
+    });
+
+    expect(
+      xmls.map((xml) => {
+        const result = junit_parse_and_validate(xml);
+        return {
+          tests: result.report?.tests,
+          issues: result.issues.map(({ error_message }) => error_message),
+          validation: summarizeValidation(result.validation),
+        };
+      }),
+    ).toStrictEqual(
+      xmls.map((xml) => {
+        const parseResult = junit_parse(xml);
+        return {
+          tests: parseResult.report?.tests,
+          issues: parseResult.issues.map(({ error_message }) => error_message),
+          validation: summarizeValidation(
+            // eslint-disable-next-line vitest/no-conditional-in-test
+            parseResult.report ? junit_validate(parseResult.report) : undefined,
+          ),
+        };
+      }),
+    );
+    expect(junit_parse_and_validate(xmls[2]).validation).toBeUndefined();
+  });
+
+  it("validates junit xml with the file paths of the repo root", () => {
+    expect.hasAssertions();
+
+    const xml = Buffer.from(
+      `
+      <testsuites name="run" tests="1">
+        <testsuite name="suite" tests="1" timestamp="2020-01-01T00:00:00Z">
+          <testcase name="case" file="C:\\repo\\tests\\case.test.ts" classname="MyClass" time="1"/>
+        </testsuite>
+      </testsuites>
+    `,
+      "utf-8",
+    );
+    const outsideRepoIssues = (
+      validation: ReturnType<typeof junit_validate_xml>,
+    ) =>
+      validation
+        ?.all_issues_owned()
+        .map(({ error_message }) => error_message)
+        .filter((error_message) => error_message.includes("outside of the repo"));
+
+    // without the root, the path can't be told apart from one outside of the repo
+    expect(outsideRepoIssues(junit_validate_xml(xml))).toStrictEqual([
+      "test case file or filepath is an absolute path outside of the repo",
+    ]);
+    expect(outsideRepoIssues(junit_validate_xml(xml, "C:\\repo"))).toStrictEqual(
+      [],
+    );
+    expect(
+      junit_validate_xml(Buffer.from("<testsuites/><testsuites/>", "utf-8")),
+    ).toBeUndefined();
+  });
+
+  it("parses internal bins in batches with per-item errors", () => {
+    expect.hasAssertions();
+
+    const bins = [new Uint8Array(), new Uint8Array([0xff, 0xff, 0xff])];
+
+    const parseResults = bin_parse_many(bins);
+
+    expect(
+      parseResults.map(({ success, reports }) => [success, reports?.length]),
+    ).toStrictEqual([
+      [true, bin_parse(bins[0]).length],
+      [false, undefined],
+    ]);
+    expect(() => bin_parse(bins[1])).toThrow(parseResults[1].error_message);
+  });
+
   it("validates repos", () => {
     expect.hasAssertions();
 
@@ -151,6 +346,121 @@ describe("context-js", () => {
     expect(repoValidation.max_level()).toBe(RepoValidationLevel.Valid);
   });
 
+  it("parses junit pushed in chunks like all at once", () => {
+    expect.hasAssertions();
+
+    const testCases = Array.from(
+      { length: 2000 },
+      (_, i) =>
+        `<testcase name="test ${i} – ✓" classname="Café" file="test.py" time="0.${i}"><failure message="a &gt; b"><![CDATA[<not a="tag"> ]]]]><![CDATA[>]]></failure></testcase>`,
+    ).join("\n");
+    const junitXml = `<?xml version="1.0" encoding="UTF-8"?>
+      <!-- generated -> by a test -->
+      <testsuites name="my-test-run 🚀">
+        <testsuite name="my-test-suite > 1">
+          ${testCases}
+        </testsuite>
+      </testsuites>
+    `;
+    const summarizeParseResult = (
+      parseResult: ReturnType<typeof junit_parse>,
+    ) => ({
+      report: parseResult.report?.name,
+      tests: parseResult.report?.tests,
+      testSuites: parseResult.report?.test_suites.map((testSuite) => [
+        testSuite.name,
+        testSuite.test_cases.map(({ name, time }) => [name, time]),
+      ]),
+      issues: parseResult.issues.map(({ error_message }) => error_message),
+    });
+    const parseInChunks = (xml: Buffer, chunkLength: number) => {
+      const streamParser = new JunitStreamParser();
+      for (let start = 0; start < xml.length; start += chunkLength) {
+        streamParser.push(xml.subarray(start, start + chunkLength));
+      }
+      return streamParser.finish();
+    };
+
+    for (const xml of [
+      Buffer.from(junitXml, "utf-8"),
+      Buffer.concat([
+        Buffer.from([0xff, 0xfe]),
+        Buffer.from(junitXml, "utf16le"),
+      ]),
+    ]) {
+      expect(xml.length).toBeGreaterThan(64 * 1024);
+
+      const parseResult = summarizeParseResult(junit_parse(xml));
+      for (const chunkLength of [7, 64 * 1024]) {
+        expect(
+          summarizeParseResult(parseInChunks(xml, chunkLength)),
+        ).toStrictEqual(parseResult);
+      }
+    }
+
+    const danglingXml = Buffer.from(
+      '<testsuites><testsuite name="a"><testcase name="b">',
+      "utf-8",
+    );
+
+    expect(summarizeParseResult(parseInChunks(danglingXml, 5))).toStrictEqual(
+      summarizeParseResult(junit_parse(danglingXml)),
+    );
+
+    const invalidXml = Buffer.from(
+      "<testsuites><testsuite></testcase></testsuites>",
+      "utf-8",
+    );
+
+    expect(() => parseInChunks(invalidXml, 5)).toThrow(
+      "expected `</testsuite>`, but `</testcase>` was found",
+    );
+  });
+
+  it("flattens repo and meta validation issues", () => {
+    expect.hasAssertions();
+
+    const repo = new RepoUrlParts("github", "trunk-io", "analytics-cli");
+    const bundleRepo = new BundleRepo(
+      repo,
+      ".",
+      "https://github.com/trunk-io/analytics-cli",
+      "",
+      "",
+      "",
+      BigInt(dayjs.utc().unix()),
+      "commit",
+      "Spikey",
+      "spikey@trunk.io",
+    );
+    const ciInfo = env_parse({ GITHUB_ACTIONS: "true", GITHUB_REF: "" });
+    // NOTE: Need to narrow type here
+    // eslint-disable-next-line vitest/no-conditional-in-test
+    if (!ciInfo) throw Error("ciInfo is undefined");
+
+    const metaValidation = meta_validate(ciInfo, bundleRepo);
+    const repoValidation = repo_validate(bundleRepo);
+
+    expect(
+      repoValidation
+        .issues_flat()
+        .map(({ level, field, error_message }) => [level, field, error_message]),
+    ).toStrictEqual([
+      [
+        RepoValidationLevel.SubOptimal,
+        "repo_head_branch",
+        "repo branch name too short",
+      ],
+      [RepoValidationLevel.Invalid, "repo_head_sha", "repo sha too short"],
+    ]);
+    expect(
+      metaValidation
+        .issues_flat()
+        .filter(({ level }) => level === MetaValidationLevel.Invalid)
+        .map(({ field, error_message }) => [field, error_message]),
+    ).toStrictEqual([["branch", "CI info branch name too short"]]);
+  });
+
   it("validates branch class", () => {
     expect.hasAssertions();
 
@@ -178,4 +488,33 @@ describe("context-js", () => {
 
     expect(parse_branch_class("")).toBe(BranchClass.None);
   });
+
+  it("validates stable branch patterns", () => {
+    expect.hasAssertions();
+
+    const stableBranches = ["main", "re:release/.*", "re:hotfix/.*"];
+
+    expect(
+      parse_branch_class(
+        "release/2024.11",
+        undefined,
+        undefined,
+        stableBranches,
+      ),
+    ).toBe(BranchClass.ProtectedBranch);
+    expect(
+      parse_branch_class("hotfix/urgent", undefined, undefined, stableBranches),
+    ).toBe(BranchClass.ProtectedBranch);
+    expect(
+      parse_branch_class(
+        "feature/release/x",
+        undefined,
+        undefined,
+        stableBranches,
+      ),
+    ).toBe(BranchClass.None);
+    expect(() =>
+      parse_branch_class("main", undefined, undefined, ["re:("]),
+    ).toThrow("invalid stable branch pattern");
+  });
 });
@@ -32,6 +32,8 @@ const generateBundleMeta = (): TestBundleMeta => ({
           path: "0.xml",
           owners: ["owner"],
           team: "team",
+          sha256:
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
         },
       ],
       glob: "**/*.xml",
@@ -113,6 +115,257 @@ describe("context-js", () => {
         bundle_upload_id_v2: "SOME ID",
       },
     ],
+    [
+      "V0_6_4",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: ["repo_head_sha"],
+          junit_globs: ["**/junit.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 1,
+        },
+      },
+    ],
+    [
+      "V0_6_5",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: [],
+          junit_globs: ["**/junit.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Darwin",
+          os_version: "14.5",
+          arch: "aarch64",
+          num_logical_cores: 10,
+          total_memory_bytes: 17179869184,
+          in_container: false,
+        },
+      },
+    ],
+    [
+      "V0_6_6",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: ["bazel_bep_path"],
+          junit_globs: [],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Ubuntu",
+          os_version: "22.04",
+          arch: "x86_64",
+          num_logical_cores: 4,
+          total_memory_bytes: 16777216000,
+          in_container: true,
+        },
+        excluded_files: [
+          {
+            path: "bep.json",
+            size_bytes: 536870912,
+            sha256:
+              "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03",
+          },
+        ],
+      },
+    ],
+    [
+      "V0_6_7",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: [],
+          junit_globs: ["**/*.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Ubuntu",
+          os_version: "22.04",
+          arch: "x86_64",
+          num_logical_cores: 4,
+          total_memory_bytes: 16777216000,
+          in_container: true,
+        },
+        excluded_files: [],
+        checksum_algorithm: "sha256",
+      },
+    ],
+    [
+      "V0_6_8",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli test --token=*** -- pytest",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "test",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: [],
+          junit_globs: ["**/*.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Ubuntu",
+          os_version: "22.04",
+          arch: "x86_64",
+          num_logical_cores: 4,
+          total_memory_bytes: 16777216000,
+          in_container: true,
+        },
+        excluded_files: [],
+        checksum_algorithm: "sha256",
+        quarantine_outcome: {
+          outcome: "SomeUnquarantined",
+          num_quarantined: 2,
+          num_unquarantined: 1,
+        },
+      },
+    ],
+    [
+      "V0_6_9",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: [],
+          junit_globs: ["**/*.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Ubuntu",
+          os_version: "22.04",
+          arch: "x86_64",
+          num_logical_cores: 4,
+          total_memory_bytes: 16777216000,
+          in_container: true,
+        },
+        excluded_files: [],
+        checksum_algorithm: "sha256",
+        quarantine_outcome: {
+          outcome: "NoFailures",
+          num_quarantined: 0,
+          num_unquarantined: 0,
+        },
+        duplicate_test_case_ids: [
+          { id: "7a1f2b3c-0000-4000-8000-000000000000", count: 120 },
+        ],
+      },
+    ],
+    [
+      "V0_6_10",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: [],
+          junit_globs: ["**/*.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Ubuntu",
+          os_version: "22.04",
+          arch: "x86_64",
+          num_logical_cores: 4,
+          total_memory_bytes: 16777216000,
+          in_container: true,
+        },
+        excluded_files: [],
+        checksum_algorithm: "sha256",
+        quarantine_outcome: {
+          outcome: "NoFailures",
+          num_quarantined: 0,
+          num_unquarantined: 0,
+        },
+        duplicate_test_case_ids: [],
+        skipped_files: [
+          {
+            original_path: "/home/runner/work/trunk/test/junit-1.xml",
+            reason: 'failed to open "/home/runner/work/trunk/test/junit-1.xml"',
+          },
+        ],
+      },
+    ],
+    [
+      "V0_6_11",
+      {
+        num_tests: faker.number.int(100),
+        num_files: faker.number.int(100),
+        command_line: "trunk-analytics-cli upload --token=***",
+        bundle_upload_id_v2: "SOME ID",
+        invocation: {
+          subcommand: "upload",
+          flags: { use_quarantining: "true", no_upload: "false" },
+          overrides: [],
+          junit_globs: ["**/*.xml"],
+          num_internal_bin_paths: 0,
+          num_tags: 0,
+        },
+        host_info: {
+          os_name: "Ubuntu",
+          os_version: "22.04",
+          arch: "x86_64",
+          num_logical_cores: 4,
+          total_memory_bytes: 16777216000,
+          in_container: true,
+        },
+        excluded_files: [],
+        checksum_algorithm: "sha256",
+        quarantine_outcome: {
+          outcome: "NoFailures",
+          num_quarantined: 0,
+          num_unquarantined: 0,
+        },
+        duplicate_test_case_ids: [],
+        skipped_files: [
+          {
+            original_path: "/home/runner/work/trunk/test/junit-1.xml",
+            reason: 'failed to open "/home/runner/work/trunk/test/junit-1.xml"',
+          },
+        ],
+        working_tree: {
+          dirty: true,
+          num_modified_files: 2,
+        },
+      },
+    ],
   ];
 
   it.each(versionTests)(
@@ -166,4 +419,28 @@ describe("context-js", () => {
       async () => await parse_meta_from_tarball(readableStream),
     ).rejects.toThrow("missing field `version`");
   });
+
+  it("rejects a meta.json that is a link", async () => {
+    expect.hasAssertions();
+
+    const tmpDir = await fs.mkdtemp(
+      path.resolve(os.tmpdir(), "bundle-upload-extract-"),
+    );
+    await fs.symlink("/etc/passwd", path.resolve(tmpDir, "meta.json"));
+    const tarPath = path.resolve(tmpDir, `bundle.tar`);
+    await tar.create({ cwd: tmpDir, file: tarPath }, ["meta.json"]);
+    const compressedBuffer = await compress(await fs.readFile(tarPath));
+    await fs.rm(tmpDir, { recursive: true, force: true });
+
+    const readableStream = new ReadableStream({
+      start(controller) {
+        controller.enqueue(compressedBuffer);
+        controller.close();
+      },
+    });
+
+    await expect(
+      async () => await parse_meta_from_tarball(readableStream),
+    ).rejects.toThrow('bundle entry "meta.json" is a link');
+  });
 });
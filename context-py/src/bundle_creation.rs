@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bundle::{
+    parse_custom_tags, BundleMeta, BundleMetaBaseProps, BundleMetaDebugProps, BundleMetaHostInfo,
+    BundleMetaInvocation, BundleMetaJunitProps, BundleMetaQuarantineOutcome, BundleMetaWorkingTree,
+    BundledFile, BundlerUtil, FileSet, CHECKSUM_ALGORITHM, META_VERSION,
+};
+use context::{junit::bindings::BindingsReport, repo::BundleRepo};
+use prost::Message;
+use proto::test_context::test_run::TestResult;
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+};
+use quick_junit::Report;
+
+const REQUIRED_META_FIELDS: [&str; 4] = ["org", "repo", "cli_version", "bundle_upload_id"];
+const OPTIONAL_META_FIELDS: [&str; 8] = [
+    "bundle_upload_id_v2",
+    "tags",
+    "envs",
+    "upload_time_epoch",
+    "test_command",
+    "os_info",
+    "variant",
+    "command_line",
+];
+
+/// Builds the latest bundle meta from the fields given to `create_bundle`, failing with all of
+/// the missing required fields at once. `tags` are `key=value` strings, as given to the CLI.
+pub fn bundle_meta_from_fields(
+    py: Python<'_>,
+    mut meta_fields: HashMap<String, PyObject>,
+) -> PyResult<BundleMeta> {
+    let missing_fields: Vec<&str> = REQUIRED_META_FIELDS
+        .into_iter()
+        .filter(|field| !meta_fields.contains_key(*field))
+        .collect();
+    if !missing_fields.is_empty() {
+        return Err(PyValueError::new_err(format!(
+            "Missing required meta fields: {}",
+            missing_fields.join(", ")
+        )));
+    }
+    let mut unknown_fields: Vec<&str> = meta_fields
+        .keys()
+        .map(String::as_str)
+        .filter(|field| {
+            !REQUIRED_META_FIELDS.contains(field) && !OPTIONAL_META_FIELDS.contains(field)
+        })
+        .collect();
+    if !unknown_fields.is_empty() {
+        unknown_fields.sort_unstable();
+        return Err(PyValueError::new_err(format!(
+            "Unknown meta fields: {}",
+            unknown_fields.join(", ")
+        )));
+    }
+
+    let org: String = required_field(py, &mut meta_fields, "org")?;
+    let repo: BundleRepo = required_field(py, &mut meta_fields, "repo")?;
+    let cli_version: String = required_field(py, &mut meta_fields, "cli_version")?;
+    let bundle_upload_id: String = required_field(py, &mut meta_fields, "bundle_upload_id")?;
+    let bundle_upload_id_v2: Option<String> =
+        optional_field(py, &mut meta_fields, "bundle_upload_id_v2")?;
+    let tags: Option<Vec<String>> = optional_field(py, &mut meta_fields, "tags")?;
+    let envs: Option<HashMap<String, String>> = optional_field(py, &mut meta_fields, "envs")?;
+    let upload_time_epoch: Option<u64> = optional_field(py, &mut meta_fields, "upload_time_epoch")?;
+    let test_command: Option<String> = optional_field(py, &mut meta_fields, "test_command")?;
+    let os_info: Option<String> = optional_field(py, &mut meta_fields, "os_info")?;
+    let variant: Option<String> = optional_field(py, &mut meta_fields, "variant")?;
+    let command_line: Option<String> = optional_field(py, &mut meta_fields, "command_line")?;
+
+    let tags = parse_custom_tags(&tags.unwrap_or_default())
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    let upload_time_epoch = match upload_time_epoch {
+        Some(upload_time_epoch) => upload_time_epoch,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?
+            .as_secs(),
+    };
+
+    Ok(BundleMeta {
+        junit_props: BundleMetaJunitProps::default(),
+        debug_props: BundleMetaDebugProps {
+            command_line: command_line.unwrap_or_default(),
+        },
+        bundle_upload_id_v2: bundle_upload_id_v2.unwrap_or_default(),
+        invocation: BundleMetaInvocation::default(),
+        host_info: BundleMetaHostInfo::default(),
+        excluded_files: Vec::with_capacity(0),
+        checksum_algorithm: CHECKSUM_ALGORITHM.to_string(),
+        quarantine_outcome: BundleMetaQuarantineOutcome::default(),
+        duplicate_test_case_ids: Vec::with_capacity(0),
+        skipped_files: Vec::with_capacity(0),
+        variant,
+        variants: Vec::with_capacity(0),
+        repo_metadata_source: None,
+        locally_quarantined_tests: Vec::with_capacity(0),
+        env_capture_profile: None,
+        test_command_stats: None,
+        quarantined_test_sources: Vec::with_capacity(0),
+        internal_bin_shards: Vec::with_capacity(0),
+        converter: None,
+        working_tree: BundleMetaWorkingTree {
+            dirty: repo.dirty,
+            num_modified_files: repo.num_modified_files,
+        },
+        base_props: BundleMetaBaseProps {
+            version: META_VERSION.to_string(),
+            cli_version,
+            org,
+            repo,
+            bundle_upload_id,
+            tags,
+            file_sets: Vec::with_capacity(0),
+            envs: envs.unwrap_or_default(),
+            upload_time_epoch,
+            test_command,
+            os_info,
+            quarantined_tests: Vec::with_capacity(0),
+            codeowners: None,
+        },
+    })
+}
+
+fn required_field<'py, T: FromPyObject<'py>>(
+    py: Python<'py>,
+    meta_fields: &mut HashMap<String, PyObject>,
+    field: &str,
+) -> PyResult<T> {
+    optional_field(py, meta_fields, field)?
+        .ok_or_else(|| PyValueError::new_err(format!("Missing required meta fields: {}", field)))
+}
+
+/// A field set to `None` is treated as missing.
+fn optional_field<'py, T: FromPyObject<'py>>(
+    py: Python<'py>,
+    meta_fields: &mut HashMap<String, PyObject>,
+    field: &str,
+) -> PyResult<Option<T>> {
+    let Some(value) = meta_fields.remove(field) else {
+        return Ok(None);
+    };
+    value
+        .into_bound(py)
+        .extract::<Option<T>>()
+        .map_err(|err| PyTypeError::new_err(format!("Invalid meta field {}: {}", field, err)))
+}
+
+/// Writes the bundle the CLI would upload for junit files of `reports`: each report is bundled as
+/// a junit file, e.g. `junit/0`, and their test case runs as the internal bin.
+pub fn write_bundle(
+    mut meta: BundleMeta,
+    reports: Vec<BindingsReport>,
+    output_path: &str,
+) -> anyhow::Result<()> {
+    let junit_temp_dir = tempfile::tempdir()?;
+    let mut bundled_files = Vec::with_capacity(reports.len());
+    let mut test_result = TestResult::default();
+    for (i, report) in reports.into_iter().enumerate() {
+        let junit_temp_path = junit_temp_dir.path().join(format!("{}.xml", i));
+        let junit_report: Report = report.clone().into();
+        junit_report
+            .serialize(File::create(&junit_temp_path)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write junit file: {}", e))?;
+        meta.junit_props.num_tests += junit_report.tests;
+
+        let bundled_file = BundledFile {
+            original_path: junit_temp_path.to_string_lossy().into_owned(),
+            path: format!("junit/{}", i),
+            ..Default::default()
+        };
+        for mut test_case_run in report.into_test_case_runs() {
+            if test_case_run.source_file.is_empty() {
+                test_case_run.source_file.clone_from(&bundled_file.path);
+            }
+            test_result.test_case_runs.push(test_case_run);
+        }
+        bundled_files.push(bundled_file);
+    }
+    meta.junit_props.num_files = bundled_files.len();
+    if !bundled_files.is_empty() {
+        meta.base_props.file_sets = vec![FileSet::new(
+            bundled_files,
+            String::new(),
+            None,
+            meta.variant.clone(),
+        )];
+    }
+
+    BundlerUtil::new(meta, None)
+        .with_internal_bin(Some(test_result.encode_to_vec()))
+        .make_tarball(&PathBuf::from(output_path))
+}
@@ -1,32 +1,45 @@
 use std::{collections::HashMap, io::BufReader, sync::Arc};
 
 use bundle::{
-    parse_meta as parse_meta_impl, parse_meta_from_tarball as parse_meta_from_tarball_impl,
-    BindingsVersionedBundle,
+    parse_internal_bin_from_tarball as parse_internal_bin_from_tarball_impl,
+    parse_meta as parse_meta_impl,
+    parse_meta_and_internal_bin_from_tarball as parse_meta_and_internal_bin_from_tarball_impl,
+    parse_meta_from_tarball as parse_meta_from_tarball_impl, BindingsVersionedBundle,
 };
 use codeowners::{
     associate_codeowners_multithreaded as associate_codeowners, BindingsOwners, CodeOwners, Owners,
 };
-use context::{env, junit, meta, repo};
+use context::{env, info_id, junit, meta, repo};
 use prost::Message;
-use pyo3::{exceptions::PyTypeError, prelude::*};
+use pyo3::{
+    exceptions::{PyOSError, PyTypeError, PyValueError},
+    prelude::*,
+};
 use pyo3_stub_gen::{define_stub_info_gatherer, derive::gen_stub_pyfunction};
 
+mod bundle_creation;
 mod py_bytes_read;
 
 use py_bytes_read::PyBytesReader;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
 
 define_stub_info_gatherer!(stub_info);
 
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn env_parse(env_vars: HashMap<String, String>) -> Option<env::parser::CIInfo> {
-    let mut env_parser = env::parser::EnvParser::new();
+#[pyo3(signature = (env_vars, stable_branches=Vec::new()))]
+fn env_parse(
+    env_vars: HashMap<String, String>,
+    stable_branches: Vec<String>,
+) -> PyResult<Option<env::parser::CIInfo>> {
+    let stable_branches = env::parser::StableBranches::new(&stable_branches)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let mut env_parser = env::parser::EnvParser::with_stable_branches(stable_branches);
     env_parser.parse(&env_vars);
 
-    env_parser
+    Ok(env_parser
         .into_ci_info_parser()
-        .map(|ci_info_parser| ci_info_parser.info_ci_info())
+        .map(|ci_info_parser| ci_info_parser.info_ci_info()))
 }
 
 #[gen_stub_pyfunction]
@@ -55,26 +68,63 @@ fn ci_platform_to_string(ci_platform: env::parser::CIPlatform) -> String {
     String::from(ci_platform.to_string())
 }
 
+/// Releases the GIL while parsing.
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn junit_parse(xml: Vec<u8>) -> PyResult<junit::bindings::BindingsParseResult> {
-    let mut junit_parser = junit::parser::JunitParser::new();
-    if let Err(e) = junit_parser.parse(BufReader::new(&xml[..])) {
-        return Err(PyTypeError::new_err(e.to_string()));
-    }
-
-    let issues_flat = junit_parser.issues_flat();
-    let mut parsed_reports = junit_parser.into_reports();
-
-    let report = if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop()) {
-        Some(junit::bindings::BindingsReport::from(parsed_report))
-    } else {
-        None
-    };
+#[pyo3(signature = (xml, repo_root=None))]
+fn junit_parse(
+    py: Python<'_>,
+    xml: Vec<u8>,
+    repo_root: Option<String>,
+) -> PyResult<junit::bindings::BindingsParseResult> {
+    py.allow_threads(|| {
+        let mut junit_parser = match repo_root {
+            Some(repo_root) => junit::parser::JunitParser::with_repo_root(repo_root),
+            None => junit::parser::JunitParser::new(),
+        };
+        if let Err(e) = junit_parser.parse(BufReader::new(&xml[..])) {
+            return Err(PyTypeError::new_err(e.to_string()));
+        }
+
+        let issues_flat = junit_parser.issues_flat();
+        let mut parsed_reports = junit_parser.into_reports();
+
+        let report = if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop())
+        {
+            Some(junit::bindings::BindingsReport::from(parsed_report))
+        } else {
+            None
+        };
+
+        Ok(junit::bindings::BindingsParseResult {
+            report,
+            issues: issues_flat,
+        })
+    })
+}
 
-    Ok(junit::bindings::BindingsParseResult {
-        report,
-        issues: issues_flat,
+/// Parses and validates in one call, without converting the report to Python and back in between.
+/// Releases the GIL while parsing and validating.
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (xml, repo_root=None))]
+fn junit_parse_and_validate(
+    py: Python<'_>,
+    xml: Vec<u8>,
+    repo_root: Option<String>,
+) -> PyResult<junit::bindings::BindingsParseAndValidateResult> {
+    py.allow_threads(|| {
+        let mut junit_parser = match repo_root {
+            Some(repo_root) => junit::parser::JunitParser::with_repo_root(repo_root),
+            None => junit::parser::JunitParser::new(),
+        };
+        if let Err(e) = junit_parser.parse(BufReader::new(&xml[..])) {
+            return Err(PyTypeError::new_err(e.to_string()));
+        }
+
+        Ok(junit::bindings::BindingsParseAndValidateResult::from(
+            junit_parser,
+        ))
     })
 }
 
@@ -98,6 +148,21 @@ fn bin_parse(bin: Vec<u8>) -> PyResult<Vec<junit::bindings::BindingsReport>> {
     Ok(vec![junit::bindings::BindingsReport::from(test_result)])
 }
 
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn bin_to_junit_xml(bin: Vec<u8>) -> PyResult<Vec<String>> {
+    let test_result = proto::test_context::test_run::TestResult::decode(bin.as_slice())
+        .map_err(|err| PyTypeError::new_err(err.to_string()))?;
+    junit::internal_bin::bin_to_junit(&test_result)
+        .into_iter()
+        .map(|report| {
+            report
+                .to_string()
+                .map_err(|err| PyTypeError::new_err(err.to_string()))
+        })
+        .collect()
+}
+
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn junit_validate(
@@ -106,6 +171,45 @@ fn junit_validate(
     junit::bindings::BindingsJunitReportValidation::from(junit::validator::validate(&report.into()))
 }
 
+/// Parses and validates in one call like the CLI does, for callers that only need the validation.
+/// With `repo_root`, file paths inside it are made relative while parsing, so only test case files
+/// outside of the repo are reported as such, which is the one validation that depends on it.
+/// Returns `None` unless the xml has exactly one report. Releases the GIL.
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (xml, repo_root=None))]
+fn junit_validate_xml(
+    py: Python<'_>,
+    xml: Vec<u8>,
+    repo_root: Option<String>,
+) -> PyResult<Option<junit::bindings::BindingsJunitReportValidation>> {
+    Ok(junit_parse_and_validate(py, xml, repo_root)?.validation)
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn junit_report_status_from_attempts(
+    attempts: Vec<junit::junit_path::JunitReportAttemptStatus>,
+) -> Option<junit::junit_path::JunitReportStatus> {
+    junit::junit_path::JunitReportStatus::from_attempt_statuses(&attempts)
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn junit_report_status_from_string(
+    status: String,
+) -> PyResult<junit::junit_path::JunitReportStatus> {
+    status
+        .parse::<junit::junit_path::JunitReportStatus>()
+        .map_err(|err| PyTypeError::new_err(err.to_string()))
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn junit_report_status_to_string(status: junit::junit_path::JunitReportStatus) -> String {
+    status.to_string()
+}
+
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn junit_validation_level_to_string(
@@ -144,6 +248,7 @@ fn repo_validation_level_to_string(
     repo_validation_level.to_string()
 }
 
+/// Releases the GIL while decompressing and parsing, re-acquiring it only to read from `reader`.
 #[gen_stub_pyfunction]
 #[pyfunction]
 pub fn parse_meta_from_tarball(
@@ -154,12 +259,111 @@ pub fn parse_meta_from_tarball(
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()?;
-    let versioned_bundle = rt
-        .block_on(parse_meta_from_tarball_impl(py_bytes_reader))
+    let versioned_bundle = py
+        .allow_threads(|| rt.block_on(parse_meta_from_tarball_impl(py_bytes_reader)))
+        .map_err(|err| PyTypeError::new_err(err.to_string()))?;
+    Ok(BindingsVersionedBundle(versioned_bundle))
+}
+
+/// Releases the GIL while decompressing and parsing, re-acquiring it only to read from `reader`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+pub fn parse_internal_bin_from_tarball(
+    py: Python<'_>,
+    reader: PyObject,
+) -> PyResult<Vec<junit::bindings::BindingsReport>> {
+    let py_bytes_reader = PyBytesReader::new(reader.into_bound(py))?;
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let internal_bin = py
+        .allow_threads(|| rt.block_on(parse_internal_bin_from_tarball_impl(py_bytes_reader)))
         .map_err(|err| PyTypeError::new_err(err.to_string()))?;
+    bin_parse(internal_bin)
+}
+
+/// Like `parse_meta_from_tarball`, but reads the tarball at `path` in Rust, without the GIL.
+#[gen_stub_pyfunction]
+#[pyfunction]
+pub fn parse_meta_from_tarball_path(
+    py: Python<'_>,
+    path: String,
+) -> PyResult<BindingsVersionedBundle> {
+    let versioned_bundle =
+        py.allow_threads(|| block_on_tarball_path(&path, parse_meta_from_tarball_impl))?;
     Ok(BindingsVersionedBundle(versioned_bundle))
 }
 
+/// Like `parse_internal_bin_from_tarball`, but reads the tarball at `path` in Rust, without the
+/// GIL.
+#[gen_stub_pyfunction]
+#[pyfunction]
+pub fn parse_internal_bin_from_tarball_path(
+    py: Python<'_>,
+    path: String,
+) -> PyResult<Vec<junit::bindings::BindingsReport>> {
+    let internal_bin =
+        py.allow_threads(|| block_on_tarball_path(&path, parse_internal_bin_from_tarball_impl))?;
+    bin_parse(internal_bin)
+}
+
+/// Reads both the meta and the internal bin of the tarball at `path` in one pass, without the GIL.
+/// The internal bin is `None` for bundles without one.
+#[gen_stub_pyfunction]
+#[pyfunction]
+pub fn parse_meta_and_internal_bin_from_tarball_path(
+    py: Python<'_>,
+    path: String,
+) -> PyResult<(
+    BindingsVersionedBundle,
+    Option<Vec<junit::bindings::BindingsReport>>,
+)> {
+    let (versioned_bundle, internal_bin) = py.allow_threads(|| {
+        block_on_tarball_path(&path, parse_meta_and_internal_bin_from_tarball_impl)
+    })?;
+    Ok((
+        BindingsVersionedBundle(versioned_bundle),
+        internal_bin.map(bin_parse).transpose()?,
+    ))
+}
+
+type TarballPathReader = Compat<tokio::io::BufReader<tokio::fs::File>>;
+
+/// Streams the tarball at `path` into `parse` with tokio's fs, naming `path` in any error.
+fn block_on_tarball_path<T, F, Fut>(path: &str, parse: F) -> PyResult<T>
+where
+    F: FnOnce(TarballPathReader) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<T>>,
+{
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(async {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|err| PyOSError::new_err(format!("Failed to open {}: {}", path, err)))?;
+        parse(tokio::io::BufReader::new(file).compat())
+            .await
+            .map_err(|err| PyTypeError::new_err(format!("Failed to parse {}: {}", path, err)))
+    })
+}
+
+/// Bundles `reports` into the zstd tarball at `output_path`, as the CLI would bundle junit files
+/// of them, with the latest bundle meta built from `meta_fields`. `org`, `repo`, `cli_version` and
+/// `bundle_upload_id` are required. Releases the GIL while bundling.
+#[gen_stub_pyfunction]
+#[pyfunction]
+pub fn create_bundle(
+    py: Python<'_>,
+    meta_fields: HashMap<String, PyObject>,
+    reports: Vec<junit::bindings::BindingsReport>,
+    output_path: String,
+) -> PyResult<()> {
+    let meta = bundle_creation::bundle_meta_from_fields(py, meta_fields)?;
+    py.allow_threads(|| bundle_creation::write_bundle(meta, reports, &output_path))
+        .map_err(|err| PyOSError::new_err(format!("Failed to create {}: {:#}", output_path, err)))
+}
+
 #[gen_stub_pyfunction]
 #[pyfunction]
 pub fn parse_meta(meta_bytes: Vec<u8>) -> PyResult<BindingsVersionedBundle> {
@@ -194,9 +398,11 @@ fn codeowners_parse(codeowners_bytes: Vec<u8>) -> PyResult<BindingsOwners> {
     }
 }
 
+/// Releases the GIL while parsing.
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn parse_many_codeowners_n_threads(
+    py: Python<'_>,
     to_parse: Vec<Option<Vec<u8>>>,
     num_threads: usize,
 ) -> PyResult<Vec<Option<BindingsOwners>>> {
@@ -204,18 +410,20 @@ fn parse_many_codeowners_n_threads(
         .worker_threads(num_threads)
         .enable_all()
         .build()?;
-    parse_many_codeowners_multithreaded_impl(rt, to_parse)
+    py.allow_threads(|| parse_many_codeowners_multithreaded_impl(rt, to_parse))
 }
 
+/// Releases the GIL while parsing.
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn parse_many_codeowners_multithreaded(
+    py: Python<'_>,
     to_parse: Vec<Option<Vec<u8>>>,
 ) -> PyResult<Vec<Option<BindingsOwners>>> {
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
-    parse_many_codeowners_multithreaded_impl(rt, to_parse)
+    py.allow_threads(|| parse_many_codeowners_multithreaded_impl(rt, to_parse))
 }
 
 fn parse_many_codeowners_multithreaded_impl(
@@ -241,9 +449,11 @@ fn parse_many_codeowners_multithreaded_impl(
     Ok(results)
 }
 
+/// Releases the GIL while associating.
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn associate_codeowners_n_threads(
+    py: Python<'_>,
     codeowners_matchers: HashMap<String, Option<BindingsOwners>>,
     to_associate: Vec<(String, Option<String>)>,
     num_threads: usize,
@@ -252,19 +462,25 @@ fn associate_codeowners_n_threads(
         .worker_threads(num_threads)
         .enable_all()
         .build()?;
-    associate_codeowners_multithreaded_impl(rt, codeowners_matchers, to_associate)
+    py.allow_threads(|| {
+        associate_codeowners_multithreaded_impl(rt, codeowners_matchers, to_associate)
+    })
 }
 
+/// Releases the GIL while associating.
 #[gen_stub_pyfunction]
 #[pyfunction]
 fn associate_codeowners_multithreaded(
+    py: Python<'_>,
     codeowners_matchers: HashMap<String, Option<BindingsOwners>>,
     to_associate: Vec<(String, Option<String>)>,
 ) -> PyResult<Vec<Vec<String>>> {
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
-    associate_codeowners_multithreaded_impl(rt, codeowners_matchers, to_associate)
+    py.allow_threads(|| {
+        associate_codeowners_multithreaded_impl(rt, codeowners_matchers, to_associate)
+    })
 }
 
 fn associate_codeowners_multithreaded_impl(
@@ -323,11 +539,45 @@ fn associate_codeowners_multithreaded_impl(
     Ok(results)
 }
 
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (org_url_slug, repo_full_name, file, classname, parent_name, name, info_id, variant))]
+#[allow(clippy::too_many_arguments)]
+fn gen_info_id(
+    org_url_slug: &str,
+    repo_full_name: &str,
+    file: Option<&str>,
+    classname: Option<&str>,
+    parent_name: &str,
+    name: &str,
+    info_id: Option<&str>,
+    variant: Option<&str>,
+) -> String {
+    info_id::gen_info_id(
+        org_url_slug,
+        repo_full_name,
+        file,
+        classname,
+        parent_name,
+        name,
+        info_id,
+        variant,
+    )
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn gen_info_id_vectors() -> Vec<info_id::InfoIdVector> {
+    info_id::gen_info_id_vectors()
+}
+
 #[pymodule]
 fn context_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<env::parser::CIInfo>()?;
     m.add_class::<env::parser::CIPlatform>()?;
     m.add_class::<env::parser::BranchClass>()?;
+    m.add_class::<env::validator::EnvValidation>()?;
+    m.add_class::<env::validator::EnvValidationFlatIssue>()?;
     m.add_class::<env::validator::EnvValidationLevel>()?;
     m.add_function(wrap_pyfunction!(env_parse, m)?)?;
     m.add_function(wrap_pyfunction!(env_validate, m)?)?;
@@ -336,35 +586,70 @@ fn context_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(ci_platform_to_string, m)?)?;
 
     m.add_class::<junit::bindings::BindingsParseResult>()?;
+    m.add_class::<junit::bindings::BindingsParseAndValidateResult>()?;
     m.add_class::<junit::bindings::BindingsReport>()?;
     m.add_class::<junit::bindings::BindingsTestSuite>()?;
     m.add_class::<junit::bindings::BindingsTestCase>()?;
     m.add_class::<junit::bindings::BindingsTestRerun>()?;
     m.add_class::<junit::bindings::BindingsTestCaseStatusStatus>()?;
     m.add_class::<junit::bindings::BindingsNonSuccessKind>()?;
+    m.add_class::<junit::bindings::BindingsSkipKind>()?;
     m.add_class::<junit::bindings::BindingsJunitReportValidation>()?;
     m.add_class::<junit::parser::JunitParseFlatIssue>()?;
     m.add_class::<junit::parser::JunitParseIssueLevel>()?;
     m.add_class::<junit::validator::JunitReportValidationFlatIssue>()?;
     m.add_class::<junit::validator::JunitValidationLevel>()?;
     m.add_class::<junit::validator::JunitValidationType>()?;
+    m.add_class::<junit::junit_path::JunitReportStatus>()?;
+    m.add_class::<junit::junit_path::JunitReportAttemptStatus>()?;
     m.add_function(wrap_pyfunction!(junit_parse, m)?)?;
+    m.add_function(wrap_pyfunction!(junit_parse_and_validate, m)?)?;
     m.add_function(wrap_pyfunction!(bin_parse, m)?)?;
+    m.add_function(wrap_pyfunction!(bin_to_junit_xml, m)?)?;
     m.add_function(wrap_pyfunction!(junit_parse_issue_level_to_string, m)?)?;
     m.add_function(wrap_pyfunction!(junit_validate, m)?)?;
+    m.add_function(wrap_pyfunction!(junit_validate_xml, m)?)?;
+    m.add_function(wrap_pyfunction!(junit_report_status_from_attempts, m)?)?;
+    m.add_function(wrap_pyfunction!(junit_report_status_from_string, m)?)?;
+    m.add_function(wrap_pyfunction!(junit_report_status_to_string, m)?)?;
     m.add_function(wrap_pyfunction!(junit_validation_level_to_string, m)?)?;
     m.add_function(wrap_pyfunction!(junit_validation_type_to_string, m)?)?;
 
     m.add_class::<repo::BundleRepo>()?;
     m.add_class::<repo::RepoUrlParts>()?;
+    m.add_class::<repo::validator::RepoValidation>()?;
+    m.add_class::<repo::validator::RepoValidationFlatIssue>()?;
     m.add_class::<repo::validator::RepoValidationLevel>()?;
     m.add_function(wrap_pyfunction!(repo_validate, m)?)?;
     m.add_function(wrap_pyfunction!(repo_validation_level_to_string, m)?)?;
 
+    m.add_class::<info_id::InfoIdVector>()?;
+    m.add_function(wrap_pyfunction!(gen_info_id, m)?)?;
+    m.add_function(wrap_pyfunction!(gen_info_id_vectors, m)?)?;
+
     m.add_class::<meta::bindings::BindingsMetaContext>()?;
     m.add_class::<meta::validator::MetaValidation>()?;
+    m.add_class::<meta::validator::MetaValidationFlatIssue>()?;
     m.add_class::<meta::validator::MetaValidationLevel>()?;
+    m.add_class::<bundle::BundleMetaQuarantineOutcomeKind>()?;
+    m.add_class::<bundle::BundleMetaDuplicateTestCaseId>()?;
+    m.add_class::<bundle::BundleMetaSkippedFile>()?;
+    m.add_class::<bundle::BundleMetaLocallyQuarantinedTest>()?;
+    m.add_class::<bundle::BundleMetaTestCommandStats>()?;
+    m.add_class::<bundle::BundleMetaQuarantineSource>()?;
+    m.add_class::<bundle::BundleMetaQuarantinedTestSource>()?;
+    m.add_class::<bundle::BundleMetaConverter>()?;
+    m.add_class::<bundle::BundleMetaConversion>()?;
+    m.add_class::<bundle::BundleMetaWorkingTree>()?;
     m.add_function(wrap_pyfunction!(parse_meta_from_tarball, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_internal_bin_from_tarball, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_meta_from_tarball_path, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_internal_bin_from_tarball_path, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        parse_meta_and_internal_bin_from_tarball_path,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(create_bundle, m)?)?;
     m.add_function(wrap_pyfunction!(parse_meta, m)?)?;
     m.add_function(wrap_pyfunction!(meta_validate, m)?)?;
     m.add_function(wrap_pyfunction!(meta_validation_level_to_string, m)?)?;
@@ -7,17 +7,20 @@ use std::{
 use futures_io::{AsyncBufRead, AsyncRead};
 use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
 
-pub struct PyBytesReader<'py> {
-    inner: Bound<'py, PyAny>,
+/// Reads from a Python file-like object without holding the GIL, so that it can be used inside
+/// `Python::allow_threads`. The GIL is re-acquired only for each `read` call.
+pub struct PyBytesReader {
+    inner: Py<PyAny>,
     content_length: usize,
     content_length_read: usize,
     inner_buffer: Vec<u8>,
+    inner_buffer_consumed: usize,
 }
 
-impl<'py> PyBytesReader<'py> {
+impl PyBytesReader {
     const DEFAULT_CHUNK_SIZE: usize = 1024;
 
-    pub fn new(py_bytes_reader: Bound<'py, PyAny>) -> PyResult<Self> {
+    pub fn new(py_bytes_reader: Bound<'_, PyAny>) -> PyResult<Self> {
         let content_length_attr = py_bytes_reader.getattr("_content_length")?;
         let content_length = content_length_attr.extract::<usize>().or_else(|_| {
             // NOTE: The stubs for `_content_length` indicate it is supposed to be an `int`, but in
@@ -28,55 +31,76 @@ impl<'py> PyBytesReader<'py> {
             })
         })?;
         Ok(Self {
-            inner: py_bytes_reader,
+            inner: py_bytes_reader.unbind(),
             content_length,
             content_length_read: 0,
             inner_buffer: Vec::with_capacity(0),
+            inner_buffer_consumed: 0,
         })
     }
 
     pub fn content_length_remaining(&self) -> usize {
         self.content_length - self.content_length_read
     }
+
+    /// Reads at most `amt` bytes, which may be fewer than requested, e.g. at the end of the
+    /// stream.
+    fn read(&mut self, amt: usize) -> io::Result<Vec<u8>> {
+        let amt = cmp::min(amt, self.content_length_remaining());
+        let bytes = Python::with_gil(|py| -> io::Result<Vec<u8>> {
+            let read = self.inner.bind(py).call_method1("read", (amt,))?;
+            let bytes = read
+                .downcast::<PyBytes>()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?
+                .as_bytes();
+            Ok(Vec::from(&bytes[..cmp::min(amt, bytes.len())]))
+        })?;
+        self.content_length_read += bytes.len();
+        Ok(bytes)
+    }
+
+    fn buffered(&self) -> &[u8] {
+        &self.inner_buffer[self.inner_buffer_consumed..]
+    }
 }
 
-impl AsyncRead for PyBytesReader<'_> {
+impl AsyncRead for PyBytesReader {
     fn poll_read(
         self: Pin<&mut Self>,
         _cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
         let self_mut = self.get_mut();
-        let amt = cmp::min(buf.len(), self_mut.content_length_remaining());
-        let read = self_mut.inner.call_method1("read", (amt,))?;
-        let bytes = read
-            .downcast::<PyBytes>()
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?
-            .as_bytes();
-        buf[..amt].copy_from_slice(&bytes[..amt]);
-        self_mut.content_length_read += amt;
-        Poll::Ready(Ok(amt))
+        // Bytes already buffered by `poll_fill_buf` come before anything still in `inner`
+        if !self_mut.buffered().is_empty() {
+            let amt = cmp::min(buf.len(), self_mut.buffered().len());
+            buf[..amt].copy_from_slice(&self_mut.buffered()[..amt]);
+            self_mut.inner_buffer_consumed += amt;
+            return Poll::Ready(Ok(amt));
+        }
+        let bytes = self_mut.read(buf.len())?;
+        buf[..bytes.len()].copy_from_slice(&bytes);
+        Poll::Ready(Ok(bytes.len()))
     }
 }
 
-impl AsyncBufRead for PyBytesReader<'_> {
+impl AsyncBufRead for PyBytesReader {
     fn poll_fill_buf(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
         let self_mut = self.get_mut();
-        let amt = cmp::min(
-            PyBytesReader::DEFAULT_CHUNK_SIZE,
-            self_mut.content_length_remaining(),
-        );
-        let read = self_mut.inner.call_method1("read", (amt,))?;
-        let bytes = read
-            .downcast::<PyBytes>()
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?
-            .as_bytes();
-        self_mut.inner_buffer = Vec::from(bytes);
-        Poll::Ready(Ok(&self_mut.inner_buffer))
+        // Only read more once the previous chunk has been consumed, otherwise its remaining bytes
+        // would be dropped
+        if self_mut.buffered().is_empty() {
+            self_mut.inner_buffer = self_mut.read(PyBytesReader::DEFAULT_CHUNK_SIZE)?;
+            self_mut.inner_buffer_consumed = 0;
+        }
+        Poll::Ready(Ok(self_mut.buffered()))
     }
 
     fn consume(self: Pin<&mut Self>, amt: usize) {
         let self_mut = self.get_mut();
-        self_mut.content_length_read += amt;
+        self_mut.inner_buffer_consumed = cmp::min(
+            self_mut.inner_buffer_consumed + amt,
+            self_mut.inner_buffer.len(),
+        );
     }
 }
@@ -0,0 +1,149 @@
+def test_create_bundle_parses_back():
+    import tempfile
+
+    from context_py import (
+        BindingsReport,
+        BindingsTestCaseStatusStatus,
+        BundleRepo,
+        RepoUrlParts,
+        create_bundle,
+        junit_parse,
+        parse_meta_and_internal_bin_from_tarball_path,
+    )
+
+    junit_xmls = [
+        """
+        <testsuites name="unit" tests="2" failures="1">
+          <testsuite name="math" tests="2" failures="1" hostname="emulator-5554">
+            <testcase name="adds" classname="MathTest" id="1a2b" file="math_test.py" line="3" timestamp="2024-10-01T12:00:00.5Z" time="0.25"/>
+            <testcase name="divides" classname="MathTest" file="math_test.py" timestamp="2024-10-01T12:00:01Z" time="1.5">
+              <failure message="ZeroDivisionError">Traceback</failure>
+            </testcase>
+          </testsuite>
+        </testsuites>
+        """,
+        """
+        <testsuites name="integration" tests="1">
+          <testsuite name="api" tests="1">
+            <testcase name="skips" classname="ApiTest" timestamp="2024-10-01T12:00:02Z" time="0">
+              <skipped message="not on CI"/>
+            </testcase>
+          </testsuite>
+        </testsuites>
+        """,
+    ]
+    reports: list[BindingsReport] = []
+    for junit_xml in junit_xmls:
+        report = junit_parse(str.encode(junit_xml)).report
+        assert report is not None
+        reports.append(report)
+
+    repo = RepoUrlParts(host="github.com", owner="trunk-io", name="analytics-cli")
+    bundle_repo = BundleRepo(
+        repo,
+        ".",
+        "https://github.com/trunk-io/analytics-cli",
+        "74518d470d8cfeb41408a85cf6097bb7f09ad902",
+        "74518d4",
+        "main",
+        1720652103,
+        "commit",
+        "Spikey",
+        "spikey@trunk.io",
+    )
+
+    with tempfile.TemporaryDirectory() as tempdir:
+        tarball_path = f"{tempdir}/bundle.tar.zstd"
+        create_bundle(
+            {
+                "org": "trunk",
+                "repo": bundle_repo,
+                "cli_version": "context-py",
+                "bundle_upload_id": "59c8ddd9-0a00-4b56-9eea-ef0d60ebcb79",
+                "tags": ["team=infra"],
+                "envs": {"CI": "true"},
+                "upload_time_epoch": 1721095230,
+                "variant": "linux",
+            },
+            reports,
+            tarball_path,
+        )
+        versioned_bundle, bin_reports = parse_meta_and_internal_bin_from_tarball_path(
+            tarball_path
+        )
+
+    bundle_meta = versioned_bundle.get_v0_6_11()
+    assert bundle_meta is not None
+    # the repo was given without a working tree status
+    assert bundle_meta.working_tree.dirty is None
+    base_props = bundle_meta.base_props
+    assert base_props.org == "trunk"
+    assert base_props.bundle_upload_id == "59c8ddd9-0a00-4b56-9eea-ef0d60ebcb79"
+    assert base_props.repo.repo_url == "https://github.com/trunk-io/analytics-cli"
+    assert base_props.repo.repo_head_sha == "74518d470d8cfeb41408a85cf6097bb7f09ad902"
+    assert [(tag.key, tag.value) for tag in base_props.tags] == [("team", "infra")]
+    assert base_props.envs == {"CI": "true"}
+    assert base_props.upload_time_epoch == 1721095230
+    assert bundle_meta.variant == "linux"
+    assert bundle_meta.junit_props.num_files == 2
+    assert bundle_meta.junit_props.num_tests == 3
+    assert len(base_props.file_sets) == 1
+    assert [f.path for f in base_props.file_sets[0].files] == ["junit/0", "junit/1"]
+    assert all(f.sha256 for f in base_props.file_sets[0].files)
+
+    assert bin_reports is not None and len(bin_reports) == 1
+    bin_test_cases = {
+        test_case.name: (test_suite.name, test_case)
+        for test_suite in bin_reports[0].test_suites
+        for test_case in test_suite.test_cases
+    }
+    for source_file, report in zip(["junit/0", "junit/1"], reports):
+        for test_suite in report.test_suites:
+            for test_case in test_suite.test_cases:
+                parent_name, bin_test_case = bin_test_cases.pop(test_case.name)
+                assert parent_name == test_suite.name
+                assert bin_test_case.classname == test_case.classname
+                assert bin_test_case.status.status == test_case.status.status
+                assert bin_test_case.timestamp == test_case.timestamp
+                assert bin_test_case.time == test_case.time
+                assert bin_test_case.source_file == source_file
+                bin_extra = bin_test_case.py_extra()
+                extra = test_case.py_extra()
+                assert bin_extra.get("id", "") == extra.get("id", "")
+                assert bin_extra.get("file", "") == extra.get("file", "")
+                assert bin_extra["line"] == extra.get("line", "0")
+                assert bin_extra.get("hostname") == test_suite.hostname
+    assert bin_test_cases == {}
+
+    divides = next(
+        case
+        for suite in bin_reports[0].test_suites
+        for case in suite.test_cases
+        if case.name == "divides"
+    )
+    assert divides.status.status == BindingsTestCaseStatusStatus.NonSuccess
+    assert divides.status.non_success is not None
+    assert divides.status.non_success.message == "ZeroDivisionError"
+
+
+def test_create_bundle_missing_meta_fields():
+    import re
+    import tempfile
+
+    import pytest
+    from context_py import create_bundle
+
+    with tempfile.TemporaryDirectory() as tempdir:
+        tarball_path = f"{tempdir}/bundle.tar.zstd"
+        with pytest.raises(
+            ValueError,
+            match=re.escape(
+                "Missing required meta fields: org, repo, cli_version, bundle_upload_id"
+            ),
+        ):
+            create_bundle({"tags": []}, [], tarball_path)
+        with pytest.raises(
+            ValueError,
+            match=re.escape("Missing required meta fields: repo, bundle_upload_id"),
+        ):
+            create_bundle({"org": "trunk", "cli_version": "context-py"}, [], tarball_path)
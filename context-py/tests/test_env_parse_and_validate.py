@@ -1,3 +1,6 @@
+import pytest
+
+
 def test_env_parse_and_validate():
     from context_py import CIPlatform, EnvValidationLevel, env_parse, env_validate
 
@@ -27,3 +30,57 @@ def test_env_parse_and_validate():
         "CI info committer name too short",
         "CI info title too short",
     ], "\n" + "\n".join([issue.error_message for issue in env_validation.issues_flat()])
+    assert [
+        (issue.level, issue.field) for issue in env_validation.issues_flat()
+    ] == [
+        (EnvValidationLevel.SubOptimal, field)
+        for field in [
+            "author_email",
+            "author_name",
+            "commit_message",
+            "committer_email",
+            "committer_name",
+            "title",
+        ]
+    ]
+
+
+def test_env_parse_stable_branch_patterns():
+    from context_py import BranchClass, env_parse
+
+    env_vars = {
+        "GITHUB_ACTIONS": "true",
+        "GITHUB_REF": "refs/heads/release/2024.11",
+    }
+
+    ci_info = env_parse(env_vars, ["main", "re:release/.*"])
+    assert ci_info is not None
+    assert ci_info.branch == "release/2024.11"
+    assert ci_info.branch_class == BranchClass.ProtectedBranch
+
+    ci_info = env_parse(env_vars)
+    assert ci_info is not None
+    assert ci_info.branch_class != BranchClass.ProtectedBranch
+
+    with pytest.raises(ValueError, match="invalid stable branch pattern"):
+        env_parse(env_vars, ["re:("])
+
+
+def test_env_parse_jenkins():
+    from context_py import CIPlatform, ci_platform_to_string, env_parse
+
+    env_vars = {
+        "JENKINS_URL": "https://jenkins.example.com/",
+        "BUILD_URL": "https://jenkins.example.com/job/analytics-cli/42/",
+        "GIT_BRANCH": "origin/main",
+    }
+
+    ci_info = env_parse(env_vars)
+    assert ci_info is not None
+    assert ci_info.platform == CIPlatform.Jenkins
+    assert ci_platform_to_string(ci_info.platform) == "JENKINS_URL"
+
+    ci_info = env_parse({**env_vars, "BUILD_ID": "42"})
+    assert ci_info is not None
+    assert ci_info.platform == CIPlatform.JenkinsPipeline
+    assert ci_platform_to_string(ci_info.platform) == "BUILD_ID"
@@ -0,0 +1,38 @@
+def reference_info_id(vector):
+    import uuid
+
+    if vector.info_id:
+        return vector.info_id
+    parts = [
+        vector.org_url_slug,
+        vector.repo_full_name,
+        vector.file or "",
+        vector.classname or "",
+        vector.parent_name,
+        vector.name,
+        "JUNIT_TESTCASE",
+    ]
+    if vector.variant:
+        parts.append(vector.variant)
+    return str(uuid.uuid5(uuid.NAMESPACE_URL, "#".join(parts)))
+
+
+def test_gen_info_id_vectors():
+    from context_py import gen_info_id, gen_info_id_vectors
+
+    vectors = gen_info_id_vectors()
+    assert len(vectors) > 0
+
+    for vector in vectors:
+        info_id = gen_info_id(
+            vector.org_url_slug,
+            vector.repo_full_name,
+            vector.file,
+            vector.classname,
+            vector.parent_name,
+            vector.name,
+            vector.info_id,
+            vector.variant,
+        )
+        assert info_id == vector.expected_id, vector.note
+        assert reference_info_id(vector) == vector.expected_id, vector.note
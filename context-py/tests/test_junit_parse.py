@@ -14,7 +14,7 @@ def test_junit_parse_valid():
     valid_timestamp = datetime.now().astimezone(timezone.utc).isoformat()
     valid_junit_xml = f"""
     <testsuites name="my-test-run" tests="1" failures="1" errors="0">
-      <testsuite name="my-test-suite" tests="1" disabled="0" errors="0" failures="1" timestamp="{valid_timestamp}">
+      <testsuite name="my-test-suite" tests="1" disabled="0" errors="0" failures="1" timestamp="{valid_timestamp}" hostname="emulator-5554" package="com.example.app">
         <testcase name="failure-case" file="test.py" classname="MyClass" timestamp="{valid_timestamp}" time="1">
           <failure message="AssertionError: assert 'testdata' in '# estdata'">
             FAILURE BODY
@@ -46,6 +46,9 @@ def test_junit_parse_valid():
         == datetime.fromisoformat(valid_timestamp).timestamp() * MICROSECONDS_PER_SECOND
     )
 
+    assert test_suite.hostname == "emulator-5554"
+    assert test_suite.package == "com.example.app"
+
     assert len(test_suite.test_cases) == 1
     test_case = test_suite.test_cases[0]
 
@@ -59,6 +62,28 @@ def test_junit_parse_valid():
     assert test_case.status.non_success.description == "FAILURE BODY"
 
 
+def test_junit_parse_windows_file_paths():
+    from context_py import junit_parse
+
+    windows_junit_xml = r"""
+    <testsuites>
+      <testsuite name="Foo.Tests.dll" tests="2">
+        <testcase classname="Foo.Tests.BarTests" name="Adds_two_numbers" time="0.01" file="D:\a\foo\foo\src\Foo.Tests\BarTests.cs" />
+        <testcase classname="Foo.Tests.BarTests" name="Subtracts_two_numbers" time="0.01" file="src\Foo.Tests\BarTests.cs" />
+      </testsuite>
+    </testsuites>
+    """
+
+    parse_result = junit_parse(str.encode(windows_junit_xml), r"D:\a\foo\foo")
+    assert parse_result.report is not None
+
+    test_cases = parse_result.report.test_suites[0].test_cases
+    assert [test_case.py_extra()["file"] for test_case in test_cases] == [
+        "src/Foo.Tests/BarTests.cs",
+        "src/Foo.Tests/BarTests.cs",
+    ]
+
+
 def test_junit_parse_no_reports():
     from context_py import JunitParseIssueLevel, junit_parse
 
@@ -121,9 +146,188 @@ def test_junit_parse_nested_testsuites():
     test_suite = report.test_suites[0]
     assert (
         test_suite.name
-        == "/home/runner/work/flake-farm/flake-farm/php/phpunit/phpunit.xml"
+        == "/home/runner/work/flake-farm/flake-farm/php/phpunit/phpunit.xml > Project Test Suite"
     )
 
     assert len(test_suite.test_cases) == 2
     for test_case in test_suite.test_cases:
         assert test_case.status.status == BindingsTestCaseStatusStatus.Success
+
+
+def test_junit_parse_skip_kinds():
+    from context_py import BindingsSkipKind, BindingsTestCaseStatusStatus, junit_parse
+
+    skipped_junit_xml = """
+    <testsuites>
+      <testsuite name="tests.test_api" tests="3" skipped="2">
+        <testcase name="test_legacy_endpoint" classname="tests.test_api" time="0">
+          <skipped type="pytest.skip" message="unconditional skip">tests/test_api.py:10: unconditional skip</skipped>
+        </testcase>
+        <testcase name="test_windows_paths" classname="tests.test_api" time="0">
+          <skipped type="pytest.skip" message="requires windows" />
+        </testcase>
+        <testcase name="test_passes" classname="tests.test_api" time="0" />
+      </testsuite>
+    </testsuites>
+    """
+
+    parse_result = junit_parse(str.encode(skipped_junit_xml))
+    assert len(parse_result.issues) == 0
+    report = parse_result.report
+    assert report is not None
+
+    disabled, skipped, passed = report.test_suites[0].test_cases
+    assert disabled.status.status == BindingsTestCaseStatusStatus.Skipped
+    assert disabled.skip_reason == "unconditional skip"
+    assert disabled.skip_kind == BindingsSkipKind.Disabled
+    assert skipped.skip_reason == "requires windows"
+    assert skipped.skip_kind == BindingsSkipKind.Other
+    assert passed.skip_reason is None
+    assert passed.skip_kind is None
+
+
+def test_junit_parse_releases_gil():
+    import os
+    import time
+    from concurrent.futures import ThreadPoolExecutor
+
+    import pytest
+
+    from context_py import junit_parse
+
+    if (os.cpu_count() or 1) < 2:
+        pytest.skip("requires at least 2 CPUs")
+
+    test_cases = "\n".join(
+        f'<testcase name="test_{i}" classname="MyClass" file="test.py" time="0.1"/>'
+        for i in range(200_000)
+    )
+    large_junit_xml = str.encode(
+        f"""
+    <testsuites>
+      <testsuite name="my-test-suite" tests="200000">
+        {test_cases}
+      </testsuite>
+    </testsuites>
+    """
+    )
+
+    start = time.perf_counter()
+    junit_parse(large_junit_xml)
+    single_parse_time = time.perf_counter() - start
+
+    with ThreadPoolExecutor(max_workers=2) as executor:
+        start = time.perf_counter()
+        futures = [executor.submit(junit_parse, large_junit_xml) for _ in range(2)]
+        for future in futures:
+            assert future.result().report is not None
+        concurrent_parse_time = time.perf_counter() - start
+
+    # Parses holding the GIL would run back to back, taking ~2x as long
+    assert concurrent_parse_time < single_parse_time * 1.6
+
+
+def test_bin_to_junit_xml_round_trip():
+    from context_py import (
+        BindingsTestCaseStatusStatus,
+        bin_parse,
+        bin_to_junit_xml,
+        junit_parse,
+    )
+
+    def length_delimited(field_number: int, value: bytes) -> bytes:
+        assert len(value) < 128
+        return bytes([field_number << 3 | 2, len(value)]) + value
+
+    def varint(field_number: int, value: int) -> bytes:
+        assert value < 128
+        return bytes([field_number << 3, value])
+
+    def test_case_run(id: str, status: int, attempt_number: int = 0) -> bytes:
+        return length_delimited(
+            1,
+            length_delimited(1, id.encode())
+            + length_delimited(2, f"test_{id}".encode())
+            + length_delimited(3, b"MyClass")
+            + length_delimited(5, b"my-test-suite")
+            + varint(7, status)
+            + varint(8, attempt_number),
+        )
+
+    SUCCESS, FAILURE, SKIPPED = 1, 2, 3
+    bin = (
+        test_case_run("1", FAILURE)
+        + test_case_run("1", SUCCESS, attempt_number=1)
+        + test_case_run("2", FAILURE)
+        + test_case_run("3", SKIPPED)
+    )
+
+    assert bin_to_junit_xml(b"") == []
+    junit_xmls = bin_to_junit_xml(bin)
+    assert len(junit_xmls) == 1
+    # bin_parse keeps every attempt, while the junit folds attempts of an id into one test case
+    assert sum(len(s.test_cases) for s in bin_parse(bin)[0].test_suites) == 4
+
+    report = junit_parse(str.encode(junit_xmls[0])).report
+    assert report is not None
+    assert report.tests == 3
+    test_cases = report.test_suites[0].test_cases
+    assert [test_case.extra["id"] for test_case in test_cases] == ["1", "2", "3"]
+    assert [test_case.status.status for test_case in test_cases] == [
+        BindingsTestCaseStatusStatus.Success,
+        BindingsTestCaseStatusStatus.NonSuccess,
+        BindingsTestCaseStatusStatus.Skipped,
+    ]
+    assert test_cases[0].status.success is not None
+    assert len(test_cases[0].status.success.flaky_runs) == 1
+    # internal bins don't keep system-out
+    assert test_cases[0].system_out is None
+
+
+def test_bin_parse_source_file():
+    from context_py import bin_parse
+
+    def length_delimited(tag: bytes, value: bytes) -> bytes:
+        assert len(value) < 128
+        return tag + bytes([len(value)]) + value
+
+    # field 1 of TestResult, holding fields 1, 5 and 16 (`source_file`) of a TestCaseRun
+    bin = length_delimited(
+        b"\x0a",
+        length_delimited(b"\x0a", b"1")
+        + length_delimited(b"\x2a", b"my-test-suite")
+        + length_delimited(b"\x82\x01", b"junit/3"),
+    )
+
+    test_cases = bin_parse(bin)[0].test_suites[0].test_cases
+    assert [test_case.source_file for test_case in test_cases] == ["junit/3"]
+
+
+def test_bin_parse_ci_job_links():
+    from context_py import bin_parse
+
+    def length_delimited(tag: bytes, value: bytes) -> bytes:
+        assert len(value) < 128
+        return tag + bytes([len(value)]) + value
+
+    # field 1 of TestResult, holding fields 1, 5, 18 (`ci_job_url`), 19 (`ci_workflow_name`) and
+    # 20 (`ci_external_id`) of a TestCaseRun
+    bin = length_delimited(
+        b"\x0a",
+        length_delimited(b"\x0a", b"1")
+        + length_delimited(b"\x2a", b"my-test-suite")
+        + length_delimited(b"\x92\x01", b"https://github.com/trunk-io/runs/42/job/102")
+        + length_delimited(b"\x9a\x01", b"CI")
+        + length_delimited(b"\xa2\x01", b"102"),
+    )
+
+    test_case = bin_parse(bin)[0].test_suites[0].test_cases[0]
+    assert test_case.ci_job_url == "https://github.com/trunk-io/runs/42/job/102"
+    assert test_case.ci_workflow_name == "CI"
+    assert test_case.ci_external_id == "102"
+
+    bin = length_delimited(b"\x0a", length_delimited(b"\x0a", b"1"))
+    test_case = bin_parse(bin)[0].test_suites[0].test_cases[0]
+    assert test_case.ci_job_url is None
+    assert test_case.ci_workflow_name is None
+    assert test_case.ci_external_id is None
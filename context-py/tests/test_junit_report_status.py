@@ -0,0 +1,54 @@
+import pytest
+
+
+@pytest.mark.parametrize(
+    "attempts,expected",
+    [
+        ([], None),
+        (["Skipped", "Skipped"], None),
+        (["Passed"], "Passed"),
+        (["Failed"], "Failed"),
+        (["Passed", "Skipped"], "Passed"),
+        (["Failed", "Failed", "Failed"], "Failed"),
+        (["Failed", "Passed"], "Flaky"),
+        (["Failed", "Skipped", "Passed"], "Flaky"),
+        (["Passed", "Failed"], "Failed"),
+    ],
+)
+def test_junit_report_status_from_attempts(attempts, expected):
+    from context_py import (
+        JunitReportAttemptStatus,
+        JunitReportStatus,
+        junit_report_status_from_attempts,
+    )
+
+    status = junit_report_status_from_attempts(
+        [getattr(JunitReportAttemptStatus, attempt) for attempt in attempts]
+    )
+
+    if expected is None:
+        assert status is None
+    else:
+        assert status == getattr(JunitReportStatus, expected)
+
+
+def test_junit_report_status_string_round_trip():
+    from context_py import (
+        JunitReportStatus,
+        junit_report_status_from_string,
+        junit_report_status_to_string,
+    )
+
+    for status in [
+        JunitReportStatus.Passed,
+        JunitReportStatus.Failed,
+        JunitReportStatus.Flaky,
+    ]:
+        assert (
+            junit_report_status_from_string(junit_report_status_to_string(status))
+            == status
+        )
+    assert junit_report_status_from_string("flaky") == JunitReportStatus.Flaky
+
+    with pytest.raises(TypeError):
+        junit_report_status_from_string("Skipped")
@@ -99,3 +99,89 @@ def test_junit_validate_suboptimal():
     assert (
         junit_validation_level_to_string(report_level_issues[0].level) == "SUBOPTIMAL"
     )
+
+
+def test_junit_parse_and_validate_matches_two_step():
+    from context_py import (
+        BindingsParseAndValidateResult,
+        junit_parse,
+        junit_parse_and_validate,
+        junit_validate,
+    )
+
+    xmls = [
+        """
+        <testsuites name="run" tests="1" failures="1">
+          <testsuite name="suite" tests="1" failures="1" timestamp="2020-01-01T00:00:00Z">
+            <testcase name="case" file="test.py" classname="MyClass" time="1">
+              <failure message="boom"/>
+            </testcase>
+            <testcase name="no-file" classname="MyClass"/>
+          </testsuite>
+        </testsuites>
+        """,
+        """<testsuite name="suite"><testcase name="" classname="MyClass"/></testsuite>""",
+        """<testsuites name="a"/><testsuites name="b"/>""",
+    ]
+
+    def flat(issues):
+        return [(issue.level, issue.error_message) for issue in issues]
+
+    for xml in xmls:
+        combined: BindingsParseAndValidateResult = junit_parse_and_validate(
+            str.encode(xml)
+        )
+        parse_result = junit_parse(str.encode(xml))
+
+        assert flat(combined.issues) == flat(parse_result.issues)
+        if parse_result.report is None:
+            assert combined.report is None
+            assert combined.validation is None
+            continue
+
+        assert combined.report is not None
+        assert combined.report.tests == parse_result.report.tests
+        validation = junit_validate(parse_result.report)
+        assert combined.validation is not None
+        assert combined.validation.max_level() == validation.max_level()
+        assert [
+            (issue.level, issue.error_type, issue.error_message)
+            for issue in combined.validation.all_issues_owned()
+        ] == [
+            (issue.level, issue.error_type, issue.error_message)
+            for issue in validation.all_issues_owned()
+        ]
+
+
+def test_junit_validate_xml_with_repo_root():
+    from context_py import junit_validate_xml
+
+    xml = str.encode(
+        """
+        <testsuites name="run" tests="1">
+          <testsuite name="suite" tests="1" timestamp="2020-01-01T00:00:00Z">
+            <testcase name="case" file="C:\\repo\\tests\\test_case.py" classname="MyClass" time="1"/>
+          </testsuite>
+        </testsuites>
+        """
+    )
+
+    def outside_repo_issues(validation):
+        return [
+            issue.error_message
+            for issue in validation.all_issues_owned()
+            if "outside of the repo" in issue.error_message
+        ]
+
+    # without the root, the path can't be told apart from one outside of the repo
+    validation = junit_validate_xml(xml)
+    assert validation is not None
+    assert outside_repo_issues(validation) == [
+        "test case file or filepath is an absolute path outside of the repo"
+    ]
+
+    validation = junit_validate_xml(xml, "C:\\repo")
+    assert validation is not None
+    assert outside_repo_issues(validation) == []
+
+    assert junit_validate_xml(str.encode("<testsuites/><testsuites/>")) is None
@@ -97,6 +97,10 @@ def test_no_branch_supplied():
     assert meta_validation.max_level() == MetaValidationLevel.Invalid, "\n" + "\n".join(
         [issue.error_message for issue in meta_validation.issues_flat()]
     )
+    assert [
+        (issue.level, issue.field, issue.error_message)
+        for issue in meta_validation.issues_flat()
+    ] == [(MetaValidationLevel.Invalid, "branch", "CI info branch name too short")]
 
 
 def ci_info_and_bundle_repo():
@@ -1,15 +1,9 @@
-def test_parse_meta_from_tarball():
-    import io
-    import json
-    import tarfile
-    import tempfile
+import typing as PT
 
-    import zstandard as zstd
-    from botocore.response import StreamingBody
-    from context_py import parse_meta_from_tarball
 
-    # trunk-ignore(pyright/reportUnknownVariableType)
-    expected_meta = {
+def _meta(**overrides: PT.Any) -> PT.Dict[str, PT.Any]:
+    """The meta of CLI 0.5.29 with `overrides`"""
+    meta: PT.Dict[str, PT.Any] = {
         "version": "1",
         "bundle_upload_id": "59c8ddd9-0a00-4b56-9eea-ef0d60ebcb79",
         "cli_version": "cargo=0.5.11 git=7e5824fa365c63a2d4b38020762be17f4edd6425 rustc=1.80.0-nightly",
@@ -48,7 +42,29 @@ def test_parse_meta_from_tarball():
                 "glob": "junit.xml",
             }
         ],
-        "envs": {
+        "envs": {},
+        "upload_time_epoch": 1721095230,
+        "test_command": None,
+        "os_info": "linux",
+        "group_is_quarantined": None,
+        "quarantined_tests": [],
+    }
+    meta.update(overrides)
+    return meta
+
+
+def test_parse_meta_from_tarball():
+    import io
+    import json
+    import tarfile
+    import tempfile
+
+    import zstandard as zstd
+    from botocore.response import StreamingBody
+    from context_py import parse_meta_from_tarball
+
+    expected_meta = _meta(
+        envs={
             "GITHUB_ACTION_REPOSITORY": "",
             "GITHUB_SERVER_URL": "https://github.com",
             "GITHUB_REPOSITORY": "trunk/test",
@@ -68,12 +84,7 @@ def test_parse_meta_from_tarball():
             "GITHUB_ACTIONS": "true",
             "GITHUB_RUN_ID": "9949497745",
         },
-        "upload_time_epoch": 1721095230,
-        "test_command": None,
-        "os_info": "linux",
-        "group_is_quarantined": None,
-        "quarantined_tests": [],
-    }
+    )
 
     encoded_meta = json.dumps(expected_meta).encode()
 
@@ -105,3 +116,168 @@ def test_parse_meta_from_tarball():
 
     bundle_meta = versioned_bundle.get_v0_5_29()
     assert bundle_meta.base_props.bundle_upload_id == expected_meta["bundle_upload_id"]
+
+
+def test_parse_from_tarball_path_matches_reader_and_is_faster():
+    import io
+    import json
+    import re
+    import tarfile
+    import tempfile
+    import time
+
+    import pytest
+    import zstandard as zstd
+    from botocore.response import StreamingBody
+    from context_py import (
+        parse_internal_bin_from_tarball,
+        parse_internal_bin_from_tarball_path,
+        parse_meta_and_internal_bin_from_tarball_path,
+        parse_meta_from_tarball,
+        parse_meta_from_tarball_path,
+    )
+
+    meta = _meta(
+        file_sets=[
+            {
+                "file_set_type": "Junit",
+                "files": [
+                    {
+                        "original_path": f"/home/runner/work/trunk/test/file{i}.xml",
+                        "path": f"junit/{i}",
+                        "last_modified_epoch_ns": 1721095230341044019,
+                        "owners": [],
+                        "team": "",
+                    }
+                    for i in range(20000)
+                ],
+                "glob": "**/*.xml",
+            }
+        ],
+        envs={"CI": "true"},
+    )
+
+    def read_with_reader(tarball_path: str, parse):  # type: ignore
+        with open(tarball_path, "rb") as f:
+            tarball = f.read()
+        return parse(StreamingBody(io.BytesIO(tarball), len(tarball)))
+
+    def fastest(parse) -> float:  # type: ignore
+        durations = []
+        for _ in range(3):
+            start = time.perf_counter()
+            parse()
+            durations.append(time.perf_counter() - start)
+        return min(durations)
+
+    with tempfile.TemporaryDirectory() as tempdir:
+        meta_file_path = f"{tempdir}/meta.json"
+        with open(meta_file_path, "wb") as f:
+            f.write(json.dumps(meta).encode())
+        junk_file_path = f"{tempdir}/junk"
+        with open(junk_file_path, "wb") as f:
+            f.write(b"<testsuites />" * 1000)
+        # an empty `TestResult` encodes to no bytes
+        internal_bin_path = f"{tempdir}/internal.bin"
+        open(internal_bin_path, "wb").close()
+
+        tar_path = f"{tempdir}/bundle.tar"
+        with tarfile.open(tar_path, "w") as tar:
+            tar.add(meta_file_path, "meta.json")
+            for i in range(1000):
+                tar.add(junk_file_path, f"junit/{i}")
+            tar.add(internal_bin_path, "internal.bin")
+        tarball_path = f"{tempdir}/bundle.tar.zstd"
+        with open(tar_path, "rb") as f:
+            with open(tarball_path, "wb") as out:
+                out.write(zstd.ZstdCompressor(level=6).compress(f.read()))
+
+        reader_meta = read_with_reader(tarball_path, parse_meta_from_tarball)
+        path_meta = parse_meta_from_tarball_path(tarball_path)
+        reader_bin = read_with_reader(tarball_path, parse_internal_bin_from_tarball)
+        path_bin = parse_internal_bin_from_tarball_path(tarball_path)
+        combined_meta, combined_bin = parse_meta_and_internal_bin_from_tarball_path(
+            tarball_path
+        )
+
+        for versioned_bundle in [reader_meta, path_meta, combined_meta]:
+            bundle_meta = versioned_bundle.get_v0_5_29()
+            assert bundle_meta.base_props.bundle_upload_id == meta["bundle_upload_id"]
+            assert [f.path for f in bundle_meta.base_props.file_sets[0].files] == [
+                f["path"] for f in meta["file_sets"][0]["files"]
+            ]
+        assert combined_bin is not None
+        for reports in [reader_bin, path_bin, combined_bin]:
+            assert [(r.name, r.tests) for r in reports] == [
+                (r.name, r.tests) for r in reader_bin
+            ]
+
+        assert fastest(
+            lambda: parse_internal_bin_from_tarball_path(tarball_path)
+        ) < fastest(
+            lambda: read_with_reader(tarball_path, parse_internal_bin_from_tarball)
+        )
+
+        missing_path = f"{tempdir}/missing.tar.zstd"
+        with pytest.raises(OSError, match=re.escape(missing_path)):
+            parse_meta_from_tarball_path(missing_path)
+        with pytest.raises(TypeError, match=re.escape(tar_path)):
+            parse_meta_from_tarball_path(tar_path)
+
+
+def test_parse_from_tarball_rejects_unsafe_entries():
+    import io
+    import json
+    import re
+    import tarfile
+    import tempfile
+
+    import pytest
+    import zstandard as zstd
+    from botocore.response import StreamingBody
+    from context_py import (
+        parse_internal_bin_from_tarball,
+        parse_meta_and_internal_bin_from_tarball_path,
+        parse_meta_from_tarball,
+    )
+
+    meta = json.dumps(_meta(file_sets=[], envs={"CI": "true"})).encode()
+
+    def crafted_bundle(tempdir: str, entry: tarfile.TarInfo) -> str:
+        tar_path = f"{tempdir}/bundle.tar"
+        with tarfile.open(tar_path, "w") as tar:
+            meta_info = tarfile.TarInfo("meta.json")
+            meta_info.size = len(meta)
+            tar.addfile(meta_info, io.BytesIO(meta))
+            tar.addfile(entry, io.BytesIO(b"root:x:0:0"))
+        tarball_path = f"{tempdir}/bundle.tar.zstd"
+        with open(tar_path, "rb") as f:
+            with open(tarball_path, "wb") as out:
+                out.write(zstd.ZstdCompressor(level=6).compress(f.read()))
+        return tarball_path
+
+    traversal = tarfile.TarInfo("../../etc/passwd")
+    traversal.size = len(b"root:x:0:0")
+    absolute = tarfile.TarInfo("/etc/passwd")
+    absolute.size = len(b"root:x:0:0")
+    symlink = tarfile.TarInfo("internal.bin")
+    symlink.type = tarfile.SYMTYPE
+    symlink.linkname = "/etc/passwd"
+
+    for entry, message in [
+        (traversal, 'bundle entry "../../etc/passwd" has a `..` component'),
+        (absolute, 'bundle entry "/etc/passwd" has an absolute path'),
+        (symlink, 'bundle entry "internal.bin" is a link'),
+    ]:
+        with tempfile.TemporaryDirectory() as tempdir:
+            tarball_path = crafted_bundle(tempdir, entry)
+            with pytest.raises(TypeError, match=re.escape(message)):
+                parse_meta_and_internal_bin_from_tarball_path(tarball_path)
+            with open(tarball_path, "rb") as f:
+                tarball = f.read()
+            with pytest.raises(TypeError, match=re.escape(message)):
+                parse_internal_bin_from_tarball(
+                    StreamingBody(io.BytesIO(tarball), len(tarball))
+                )
+            # only the first entry is read for the meta
+            parse_meta_from_tarball(StreamingBody(io.BytesIO(tarball), len(tarball)))
@@ -1,3 +1,6 @@
+import typing as PT
+
+
 def test_parse_meta_valid():
     import json
     import typing as PT
@@ -60,6 +63,321 @@ def test_parse_meta_valid():
     assert bundle_meta.base_props.bundle_upload_id == valid_meta["bundle_upload_id"]
 
 
+def _meta(version: str, **overrides: PT.Any) -> PT.Dict[str, PT.Any]:
+    """The meta of CLI `version`, with the fields added by every version up to it"""
+    meta: PT.Dict[str, PT.Any] = {
+        "version": "1",
+        "bundle_upload_id": "59c8ddd9-0a00-4b56-9eea-ef0d60ebcb79",
+        "bundle_upload_id_v2": "59c8ddd9-0a00-4b56-9eea-ef0d60ebcb79",
+        "cli_version": f"cargo={version} git=7e5824fa365c63a2d4b38020762be17f4edd6425 rustc=1.80.0-nightly",
+        "org": "trunk",
+        "repo": {
+            "repo": {"host": "github.com", "owner": "trunk", "name": "test"},
+            "repo_root": "/home/runner/work/trunk/test",
+            "repo_url": "https://github.com/trunk/test",
+            "repo_head_sha": "74518d470d8cfeb41408a85cf6097bb7f09ad902",
+            "repo_head_branch": "refs/heads/main",
+            "repo_head_commit_epoch": 1720652103,
+            "repo_head_commit_message": "ci: add .deepsource.toml",
+            "repo_head_author_name": "deepsource-io[bot]",
+            "repo_head_author_email": "42547082+deepsource-io[bot]@users.noreply.github.com",
+        },
+        "tags": [],
+        "file_sets": [],
+        "envs": {},
+        "upload_time_epoch": 1721095230,
+        "test_command": None,
+        "os_info": "linux",
+        "quarantined_tests": [],
+        "codeowners": None,
+        "num_files": 0,
+        "num_tests": 0,
+        "command_line": "trunk-analytics-cli upload --token ***",
+        "invocation": {
+            "subcommand": "upload",
+            "flags": {"use_quarantining": "false", "no_upload": "false"},
+            "overrides": ["repo_head_sha"],
+            "junit_globs": ["**/junit.xml"],
+            "num_internal_bin_paths": 0,
+            "num_tags": 2,
+        },
+    }
+    for fields_version, fields in [
+        ("0.6.4", {}),
+        (
+            "0.6.5",
+            {
+                "host_info": {
+                    "os_name": "Ubuntu",
+                    "os_version": "22.04",
+                    "arch": "x86_64",
+                    "num_logical_cores": 4,
+                    "total_memory_bytes": 16777216000,
+                    "in_container": None,
+                },
+            },
+        ),
+        ("0.6.6", {"excluded_files": []}),
+        (
+            "0.6.7",
+            {
+                "file_sets": [
+                    {
+                        "file_set_type": "Junit",
+                        "files": [
+                            {
+                                "original_path": "/home/runner/work/trunk/test/junit.xml",
+                                "original_path_rel": "junit.xml",
+                                "path": "junit/0",
+                                "owners": [],
+                                "team": None,
+                                "sha256": "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                            }
+                        ],
+                        "glob": "**/junit.xml",
+                        "resolved_status": None,
+                    }
+                ],
+                "num_files": 1,
+                "checksum_algorithm": "sha256",
+            },
+        ),
+        (
+            "0.6.8",
+            {
+                "quarantine_outcome": {
+                    "outcome": "AllQuarantined",
+                    "num_quarantined": 2,
+                    "num_unquarantined": 0,
+                },
+            },
+        ),
+        ("0.6.9", {"duplicate_test_case_ids": []}),
+        ("0.6.10", {"skipped_files": []}),
+        ("0.6.11", {"working_tree": {"dirty": None, "num_modified_files": None}}),
+    ]:
+        meta.update(fields)
+        if fields_version == version:
+            break
+    else:
+        raise ValueError(f"unknown meta version {version}")
+    meta.update(overrides)
+    return meta
+
+
+def test_parse_meta_with_invocation():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta("0.6.4")
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_4()
+    assert bundle_meta is not None
+    assert bundle_meta.invocation.subcommand == "upload"
+    assert bundle_meta.invocation.flags["use_quarantining"] == "false"
+    assert bundle_meta.invocation.overrides == ["repo_head_sha"]
+    assert bundle_meta.invocation.junit_globs == ["**/junit.xml"]
+    assert bundle_meta.invocation.num_tags == 2
+
+    bundle_meta = versioned_bundle.get_v0_6_3()
+    assert bundle_meta is not None
+    assert bundle_meta.bundle_upload_id_v2 == valid_meta["bundle_upload_id_v2"]
+
+
+def test_parse_meta_with_host_info():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta("0.6.5")
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_5()
+    assert bundle_meta is not None
+    assert bundle_meta.host_info.os_name == "Ubuntu"
+    assert bundle_meta.host_info.os_version == "22.04"
+    assert bundle_meta.host_info.arch == "x86_64"
+    assert bundle_meta.host_info.num_logical_cores == 4
+    assert bundle_meta.host_info.total_memory_bytes == 16777216000
+    assert bundle_meta.host_info.in_container is None
+
+    bundle_meta = versioned_bundle.get_v0_6_4()
+    assert bundle_meta is not None
+    assert bundle_meta.invocation.subcommand == "upload"
+
+
+def test_parse_meta_with_excluded_files():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta(
+        "0.6.6",
+        excluded_files=[
+            {
+                "path": "bazel-bep.json",
+                "size_bytes": 536870912,
+                "sha256": "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03",
+            }
+        ],
+    )
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_6()
+    assert bundle_meta is not None
+    assert len(bundle_meta.excluded_files) == 1
+    assert bundle_meta.excluded_files[0].path == "bazel-bep.json"
+    assert bundle_meta.excluded_files[0].size_bytes == 536870912
+    assert (
+        bundle_meta.excluded_files[0].sha256
+        == "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+    )
+
+    bundle_meta = versioned_bundle.get_v0_6_5()
+    assert bundle_meta is not None
+    assert bundle_meta.host_info.os_name == "Ubuntu"
+
+
+def test_parse_meta_with_file_checksums():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta("0.6.7")
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_7()
+    assert bundle_meta is not None
+    assert bundle_meta.checksum_algorithm == "sha256"
+    bundled_file = bundle_meta.base_props.file_sets[0].files[0]
+    assert (
+        bundled_file.sha256
+        == "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    )
+
+    bundle_meta = versioned_bundle.get_v0_6_6()
+    assert bundle_meta is not None
+    assert bundle_meta.base_props.file_sets[0].files[0].sha256 is not None
+
+
+def test_parse_meta_with_quarantine_outcome():
+    import json
+
+    from context_py import BundleMetaQuarantineOutcomeKind, parse_meta
+
+    valid_meta = _meta("0.6.8")
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_8()
+    assert bundle_meta is not None
+    assert (
+        bundle_meta.quarantine_outcome.outcome
+        == BundleMetaQuarantineOutcomeKind.AllQuarantined
+    )
+    assert bundle_meta.quarantine_outcome.num_quarantined == 2
+    assert bundle_meta.quarantine_outcome.num_unquarantined == 0
+    # bundles of CLIs that did not audit the quarantine config don't have it
+    assert bundle_meta.quarantine_outcome.num_unmatched_quarantined_ids is None
+
+    bundle_meta = versioned_bundle.get_v0_6_7()
+    assert bundle_meta is not None
+    assert bundle_meta.checksum_algorithm == "sha256"
+
+
+def test_parse_meta_with_duplicate_test_case_ids():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta(
+        "0.6.9",
+        duplicate_test_case_ids=[
+            {"id": "7a1f2b3c-0000-4000-8000-000000000000", "count": 120},
+        ],
+    )
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_9()
+    assert bundle_meta is not None
+    assert len(bundle_meta.duplicate_test_case_ids) == 1
+    duplicate_test_case_id = bundle_meta.duplicate_test_case_ids[0]
+    assert duplicate_test_case_id.id == "7a1f2b3c-0000-4000-8000-000000000000"
+    assert duplicate_test_case_id.count == 120
+
+    bundle_meta = versioned_bundle.get_v0_6_8()
+    assert bundle_meta is not None
+    assert bundle_meta.quarantine_outcome.num_quarantined == 2
+
+
+def test_parse_meta_with_skipped_files():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta(
+        "0.6.10",
+        skipped_files=[
+            {
+                "original_path": "/home/runner/work/trunk/test/junit-1.xml",
+                "reason": 'failed to open "/home/runner/work/trunk/test/junit-1.xml"',
+            },
+        ],
+    )
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_10()
+    assert bundle_meta is not None
+    assert len(bundle_meta.skipped_files) == 1
+    skipped_file = bundle_meta.skipped_files[0]
+    assert skipped_file.original_path == "/home/runner/work/trunk/test/junit-1.xml"
+    assert skipped_file.reason.startswith("failed to open")
+    assert bundle_meta.junit_props.num_files == 1
+    # bundles of CLIs that did not merge junit files don't have it
+    assert bundle_meta.junit_props.num_original_files is None
+
+    bundle_meta = versioned_bundle.get_v0_6_9()
+    assert bundle_meta is not None
+    assert bundle_meta.duplicate_test_case_ids == []
+
+
+def test_parse_meta_with_working_tree():
+    import json
+
+    from context_py import parse_meta
+
+    valid_meta = _meta(
+        "0.6.11", working_tree={"dirty": True, "num_modified_files": 3}
+    )
+
+    encoded_meta = json.dumps(valid_meta).encode()
+    versioned_bundle = parse_meta(encoded_meta)
+
+    bundle_meta = versioned_bundle.get_v0_6_11()
+    assert bundle_meta is not None
+    assert bundle_meta.working_tree.dirty is True
+    assert bundle_meta.working_tree.num_modified_files == 3
+
+    bundle_meta = versioned_bundle.get_v0_6_10()
+    assert bundle_meta is not None
+    assert bundle_meta.skipped_files == []
+
+
 def test_parse_meta_invalid():
     import json
 
@@ -23,3 +23,71 @@ def test_repo_validate():
     assert repo_validation.max_level() == RepoValidationLevel.Valid, "\n" + "\n".join(
         [issue.error_message for issue in repo_validation.issues_flat()]
     )
+
+
+def test_repo_validate_issues_flat():
+    import math
+    import time
+
+    from context_py import (
+        BundleRepo,
+        RepoUrlParts,
+        RepoValidationFlatIssue,
+        RepoValidationLevel,
+        repo_validate,
+    )
+
+    repo = RepoUrlParts(host="github", owner="trunk-io", name="analytics-cli")
+    bundle_repo = BundleRepo(
+        repo,
+        ".",
+        "https://github.com/trunk-io/analytics-cli",
+        "",
+        "",
+        "main",
+        math.floor(time.time()),
+        "commit",
+        "Spikey",
+        "spikey@trunk.io",
+    )
+
+    repo_validation = repo_validate(bundle_repo)
+
+    assert repo_validation.max_level() == RepoValidationLevel.Invalid
+    issues: list[RepoValidationFlatIssue] = repo_validation.issues_flat()
+    assert [(issue.level, issue.field, issue.error_message) for issue in issues] == [
+        (RepoValidationLevel.Invalid, "repo_head_sha", "repo sha too short")
+    ]
+
+
+def test_repo_validate_dirty_working_tree():
+    import math
+    import time
+
+    from context_py import BundleRepo, RepoUrlParts, RepoValidationLevel, repo_validate
+
+    repo = RepoUrlParts(host="github", owner="trunk-io", name="analytics-cli")
+    bundle_repo = BundleRepo(
+        repo,
+        ".",
+        "https://github.com/trunk-io/analytics-cli",
+        "abc",
+        "abc",
+        "main",
+        math.floor(time.time()),
+        "commit",
+        "Spikey",
+        "spikey@trunk.io",
+        dirty=True,
+        num_modified_files=3,
+    )
+
+    repo_validation = repo_validate(bundle_repo)
+
+    assert repo_validation.max_level() == RepoValidationLevel.SubOptimal
+    assert [(issue.field, issue.error_message) for issue in repo_validation.issues_flat()] == [
+        (
+            "dirty",
+            "repo working tree has 3 modified file(s), test results may not match the head commit",
+        )
+    ]
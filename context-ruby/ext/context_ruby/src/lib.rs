@@ -1,30 +1,129 @@
 use std::collections::HashMap;
 
-use context::{env, repo};
+use bundle::{
+    parse_meta as parse_meta_impl, parse_meta_from_tarball as parse_meta_from_tarball_impl,
+    BindingsVersionedBundle,
+};
+use context::{env, info_id, meta, repo};
+use futures::{executor::block_on, io::Cursor};
+use magnus::RString;
 use test_report::report;
 
-pub fn env_parse(env_vars: magnus::RHash) -> Option<env::parser::CIInfo> {
+mod ruby_io_read;
+
+use ruby_io_read::RubyIoReader;
+
+pub fn env_parse(
+    ruby: &magnus::Ruby,
+    env_vars: magnus::RHash,
+    stable_branches: Vec<String>,
+) -> Result<Option<env::parser::CIInfo>, magnus::Error> {
     let env_vars: HashMap<String, String> = env_vars.to_hash_map().unwrap_or_default();
-    let mut env_parser = env::parser::EnvParser::new();
+    let stable_branches = env::parser::StableBranches::new(&stable_branches)
+        .map_err(|e| magnus::Error::new(ruby.exception_arg_error(), e.to_string()))?;
+    let mut env_parser = env::parser::EnvParser::with_stable_branches(stable_branches);
     env_parser.parse(&env_vars);
 
-    env_parser
+    Ok(env_parser
         .into_ci_info_parser()
-        .map(|ci_info_parser| ci_info_parser.info_ci_info())
+        .map(|ci_info_parser| ci_info_parser.info_ci_info()))
 }
 
 pub fn env_validate(ci_info: &env::parser::CIInfo) -> env::validator::EnvValidation {
     env::validator::validate(ci_info)
 }
 
-pub fn repo_validate(bundle_repo: repo::BundleRepo) -> repo::validator::RepoValidation {
-    repo::validator::validate(&bundle_repo)
+pub fn repo_validate(bundle_repo: &repo::BundleRepo) -> repo::validator::RepoValidation {
+    repo::validator::validate(bundle_repo)
+}
+
+pub fn meta_validate(
+    ci_info: &env::parser::CIInfo,
+    bundle_repo: &repo::BundleRepo,
+) -> meta::validator::MetaValidation {
+    meta::validator::validate(&meta::MetaContext::new(ci_info, bundle_repo))
+}
+
+/// Reads the meta.json of a bundle tarball from `input`, which is either an IO-like object or a
+/// String of the bytes of the tarball.
+pub fn parse_meta_from_tarball(
+    ruby: &magnus::Ruby,
+    input: magnus::Value,
+) -> Result<BindingsVersionedBundle, magnus::Error> {
+    let versioned_bundle = match RString::from_value(input) {
+        // SAFETY: `tarball_bytes` is an argument of this method, so the caller keeps it alive. The
+        // slice is copied into the `Vec` before the tarball is parsed, and no Ruby code that could
+        // mutate or resize the string runs while the slice is borrowed.
+        Some(tarball_bytes) => block_on(parse_meta_from_tarball_impl(Cursor::new(Vec::from(
+            unsafe { tarball_bytes.as_slice() },
+        )))),
+        None => block_on(parse_meta_from_tarball_impl(RubyIoReader::new(input))),
+    }
+    .map_err(|err| magnus::Error::new(ruby.exception_runtime_error(), err.to_string()))?;
+    Ok(BindingsVersionedBundle(versioned_bundle))
+}
+
+pub fn parse_meta(
+    ruby: &magnus::Ruby,
+    meta_bytes: RString,
+) -> Result<BindingsVersionedBundle, magnus::Error> {
+    // SAFETY: `meta_bytes` is an argument of this method, so the caller keeps it alive. Only the
+    // copy in the `Vec` is parsed, so no Ruby code runs while the slice is borrowed.
+    let versioned_bundle = parse_meta_impl(Vec::from(unsafe { meta_bytes.as_slice() }))
+        .map_err(|err| magnus::Error::new(ruby.exception_runtime_error(), err.to_string()))?;
+    Ok(BindingsVersionedBundle(versioned_bundle))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn gen_info_id(
+    org_url_slug: String,
+    repo_full_name: String,
+    file: Option<String>,
+    classname: Option<String>,
+    parent_name: String,
+    name: String,
+    info_id: Option<String>,
+    variant: Option<String>,
+) -> String {
+    info_id::gen_info_id(
+        &org_url_slug,
+        &repo_full_name,
+        file.as_deref(),
+        classname.as_deref(),
+        &parent_name,
+        &name,
+        info_id.as_deref(),
+        variant.as_deref(),
+    )
+}
+
+pub fn gen_info_id_vectors() -> Vec<info_id::InfoIdVector> {
+    info_id::gen_info_id_vectors()
 }
 
 #[magnus::init]
 fn init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
     env::parser::ruby_init(ruby)?;
+    env::validator::ruby_init(ruby)?;
+    repo::validator::ruby_init(ruby)?;
+    meta::validator::ruby_init(ruby)?;
     report::ruby_init(ruby)?;
-    ruby.define_global_function("env_parse", magnus::function!(env_parse, 1));
+    info_id::ruby_init(ruby)?;
+    repo::ruby_init(ruby)?;
+    bundle::ruby_init(ruby)?;
+    ruby.define_global_function("env_parse", magnus::function!(env_parse, 2));
+    ruby.define_global_function("env_validate", magnus::function!(env_validate, 1));
+    ruby.define_global_function("repo_validate", magnus::function!(repo_validate, 1));
+    ruby.define_global_function("meta_validate", magnus::function!(meta_validate, 2));
+    ruby.define_global_function(
+        "parse_meta_from_tarball",
+        magnus::function!(parse_meta_from_tarball, 1),
+    );
+    ruby.define_global_function("parse_meta", magnus::function!(parse_meta, 1));
+    ruby.define_global_function("gen_info_id", magnus::function!(gen_info_id, 8));
+    ruby.define_global_function(
+        "gen_info_id_vectors",
+        magnus::function!(gen_info_id_vectors, 0),
+    );
     Ok(())
 }
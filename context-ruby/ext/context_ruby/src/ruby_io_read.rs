@@ -0,0 +1,78 @@
+use std::{
+    cmp, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_io::{AsyncBufRead, AsyncRead};
+use magnus::{value::ReprValue, RString, Value};
+
+/// Reads from a Ruby IO-like object, i.e. anything with a `read(length)` method that returns a
+/// String of at most `length` bytes, or `nil` at the end of the stream.
+///
+/// `inner` is only kept alive by the caller holding on to it, so a reader must not outlive the
+/// call of the function it was created in.
+pub struct RubyIoReader {
+    inner: Value,
+    inner_buffer: Vec<u8>,
+    inner_buffer_position: usize,
+}
+
+impl RubyIoReader {
+    const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+    pub fn new(ruby_io: Value) -> Self {
+        Self {
+            inner: ruby_io,
+            inner_buffer: Vec::with_capacity(0),
+            inner_buffer_position: 0,
+        }
+    }
+
+    fn read(&self, amt: usize) -> io::Result<Vec<u8>> {
+        let read: Option<RString> = self
+            .inner
+            .funcall("read", (amt,))
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        // SAFETY: `read` is the String just returned by the IO, only referenced from this stack
+        // frame, which Ruby's GC scans, so it is not collected while it is borrowed. It is copied
+        // before the next call into Ruby, which is the only code that could mutate it.
+        Ok(read
+            .map(|read| Vec::from(unsafe { read.as_slice() }))
+            .unwrap_or_default())
+    }
+
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.inner_buffer_position >= self.inner_buffer.len() {
+            self.inner_buffer = self.read(RubyIoReader::DEFAULT_CHUNK_SIZE)?;
+            self.inner_buffer_position = 0;
+        }
+        Ok(&self.inner_buffer[self.inner_buffer_position..])
+    }
+}
+
+impl AsyncRead for RubyIoReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let self_mut = self.get_mut();
+        let available = self_mut.fill_buf()?;
+        let amt = cmp::min(buf.len(), available.len());
+        buf[..amt].copy_from_slice(&available[..amt]);
+        self_mut.inner_buffer_position += amt;
+        Poll::Ready(Ok(amt))
+    }
+}
+
+impl AsyncBufRead for RubyIoReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        Poll::Ready(self.get_mut().fill_buf())
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let self_mut = self.get_mut();
+        self_mut.inner_buffer_position += amt;
+    }
+}
@@ -0,0 +1,52 @@
+# frozen_string_literal: true
+
+require 'digest'
+require_relative '../lib/context_ruby'
+require_relative '../spec/spec_helper'
+
+NAMESPACE_URL = '6ba7b811-9dad-11d1-80b4-00c04fd430c8'
+
+def uuid_v5(namespace, name)
+  bytes = Digest::SHA1.digest([namespace.delete('-')].pack('H*') + name.b).bytes[0, 16]
+  bytes[6] = (bytes[6] & 0x0f) | 0x50
+  bytes[8] = (bytes[8] & 0x3f) | 0x80
+  hex = bytes.pack('C*').unpack1('H*')
+  [hex[0, 8], hex[8, 4], hex[12, 4], hex[16, 4], hex[20, 12]].join('-')
+end
+
+def reference_info_id(vector)
+  return vector.info_id unless vector.info_id.nil? || vector.info_id.empty?
+
+  parts = [
+    vector.org_url_slug,
+    vector.repo_full_name,
+    vector.file || '',
+    vector.classname || '',
+    vector.parent_name,
+    vector.name,
+    'JUNIT_TESTCASE'
+  ]
+  parts << vector.variant unless vector.variant.nil? || vector.variant.empty?
+  uuid_v5(NAMESPACE_URL, parts.join('#'))
+end
+
+describe 'context_ruby' do
+  it 'should generate the info IDs of the test vectors' do
+    vectors = gen_info_id_vectors
+    expect(vectors).not_to be_empty
+    vectors.each do |vector|
+      info_id = gen_info_id(
+        vector.org_url_slug,
+        vector.repo_full_name,
+        vector.file,
+        vector.classname,
+        vector.parent_name,
+        vector.name,
+        vector.info_id,
+        vector.variant
+      )
+      expect(info_id).to eq(vector.expected_id), vector.note
+      expect(reference_info_id(vector)).to eq(vector.expected_id), vector.note
+    end
+  end
+end
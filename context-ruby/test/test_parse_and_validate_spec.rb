@@ -15,7 +15,7 @@ describe 'context_ruby' do
       'GITHUB_WORKFLOW' => 'test-workflow',
       'GITHUB_JOB' => 'test-job'
     }
-    parsed = env_parse(env_vars)
+    parsed = env_parse(env_vars, [])
     expect(parsed.platform.to_s).to eq('GITHUB_ACTIONS')
     expect(parsed.job_url).to eq('https://github.com/analytics-cli/actions/runs/12345')
     expect(parsed.branch).to eq('abc')
@@ -24,6 +24,11 @@ describe 'context_ruby' do
     expect(parsed.job).to eq('test-job')
   end
 
+  it 'should reject invalid stable branch patterns' do
+    env_vars = { 'GITHUB_ACTIONS' => 'true' }
+    expect { env_parse(env_vars, ['re:(']) }.to raise_error(ArgumentError)
+  end
+
   it 'should be able to make a new CIInfo' do
     ci = CIInfo.new(1)
     expect(ci.platform.to_s).to eq('BUILD_ID')
@@ -37,4 +42,23 @@ describe 'context_ruby' do
   it 'should error on invalid CIInfo' do
     expect { CIInfo.new(100) }.to raise_error(TypeError)
   end
+
+  it 'should flatten env validation issues' do
+    env_vars = {
+      'GITHUB_ACTIONS' => 'true',
+      'GITHUB_REF' => 'abc',
+      'GITHUB_ACTOR' => 'Spikey',
+      'GITHUB_REPOSITORY' => 'analytics-cli',
+      'GITHUB_RUN_ID' => '12345'
+    }
+    env_validation = env_validate(env_parse(env_vars, []))
+    expect(env_validation.max_level.to_s).to eq('SUBOPTIMAL')
+    issues = env_validation.issues_flat.map { |issue| [issue.level.to_s, issue.field] }
+    expect(issues).to eq(
+      %w[author_email author_name commit_message committer_email committer_name title].map do |field|
+        ['SUBOPTIMAL', field]
+      end
+    )
+    expect(env_validation.issues_flat.first.error_message).to eq('CI info author email too short')
+  end
 end
@@ -0,0 +1,81 @@
+# frozen_string_literal: true
+
+require 'json'
+require_relative '../lib/context_ruby'
+require_relative '../spec/spec_helper'
+
+# Generated with `trunk-analytics-cli upload --dry-run --dry-run-output out --org-url-slug fixture-org
+# --token fixture-token --junit-paths "junit/*.xml" --variant linux`
+FIXTURE_BUNDLE_PATH = File.join(__dir__, 'fixtures', 'bundle.tar.zstd')
+
+def expect_fixture_bundle(versioned_bundle)
+  expect(versioned_bundle.schema).to eq('V0_6_10')
+  expect(versioned_bundle.org).to eq('fixture-org')
+  expect(versioned_bundle.variant).to eq('linux')
+  expect(versioned_bundle.envs).to be_a(Hash)
+
+  repo = versioned_bundle.repo
+  expect(repo.repo.host).to eq('github.com')
+  expect(repo.repo.owner).to eq('trunk-io')
+  expect(repo.repo.name).to eq('analytics-cli')
+  expect(repo.repo_head_author_name).to eq('Spikey')
+
+  file_sets = versioned_bundle.file_sets
+  expect(file_sets.length).to eq(1)
+  expect(file_sets[0].glob).to eq('junit/*.xml')
+  expect(file_sets[0].variant).to eq('linux')
+  expect(file_sets[0].files.map(&:original_path_rel)).to eq(['junit/junit.xml'])
+  expect(file_sets[0].files.map(&:path)).to eq(['junit/0'])
+end
+
+# trunk-ignore(rubocop/Metrics/BlockLength)
+describe 'context_ruby' do
+  it 'should parse the meta of a bundle from an IO' do
+    File.open(FIXTURE_BUNDLE_PATH, 'rb') do |file|
+      expect_fixture_bundle(parse_meta_from_tarball(file))
+    end
+  end
+
+  it 'should parse the meta of a bundle from a String' do
+    expect_fixture_bundle(parse_meta_from_tarball(File.binread(FIXTURE_BUNDLE_PATH)))
+  end
+
+  it 'should parse a meta.json' do
+    meta_json = {
+      'version' => '1',
+      'cli_version' => 'cargo=0.5.11 git=7e5824fa365c63a2d4b38020762be17f4edd6425 rustc=1.80.0-nightly',
+      'org' => 'test-org',
+      'repo' => {
+        'repo' => { 'host' => 'github.com', 'owner' => 'trunk-io', 'name' => 'analytics-cli' },
+        'repo_root' => '',
+        'repo_url' => '',
+        'repo_head_sha' => '',
+        'repo_head_branch' => '',
+        'repo_head_commit_epoch' => 0,
+        'repo_head_commit_message' => '',
+        'repo_head_author_name' => '',
+        'repo_head_author_email' => ''
+      },
+      'bundle_upload_id' => '',
+      'tags' => [],
+      'file_sets' => [],
+      'envs' => { 'CI' => 'true' },
+      'upload_time_epoch' => 0,
+      'test_command' => nil,
+      'os_info' => nil,
+      'quarantined_tests' => [],
+      'codeowners' => nil
+    }.to_json
+
+    versioned_bundle = parse_meta(meta_json)
+    expect(versioned_bundle.schema).to eq('V0_5_29')
+    expect(versioned_bundle.org).to eq('test-org')
+    expect(versioned_bundle.variant).to be_nil
+    expect(versioned_bundle.envs).to eq({ 'CI' => 'true' })
+  end
+
+  it 'should raise on an invalid bundle' do
+    expect { parse_meta_from_tarball('not a bundle') }.to raise_error(RuntimeError)
+    expect { parse_meta('{}') }.to raise_error(RuntimeError)
+  end
+end
@@ -1,10 +1,10 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::Ok;
-use bazel_bep::types::build_event_stream::{
-    build_event::Payload, build_event_id::Id, file::File::Uri, BuildEvent,
+use bazel_bep::{
+    stream::BepReader,
+    types::build_event_stream::{file::File::Uri, BuildEvent},
 };
-use serde_json::Deserializer;
 
 use crate::junit::junit_path::{JunitReportFileWithStatus, JunitReportStatus};
 
@@ -53,6 +53,7 @@ impl BepParseResult {
                         .map(|f| JunitReportFileWithStatus {
                             junit_path: f.clone(),
                             status: r.summary_status.clone(),
+                            variant: None,
                         })
                         .collect::<Vec<JunitReportFileWithStatus>>(),
                 )
@@ -82,88 +83,64 @@ impl BazelBepParser {
         let file = std::fs::File::open(&self.bazel_bep_path)?;
         let reader = std::io::BufReader::new(file);
 
-        let (errors, test_results, summary_statuses, bep_test_events) =
-            Deserializer::from_reader(reader)
-                .into_iter::<BuildEvent>()
-                .fold(
-                    (
-                        Vec::<String>::new(),
-                        Vec::<TestResult>::new(),
-                        HashMap::<String, JunitReportStatus>::new(),
-                        Vec::<BuildEvent>::new(),
-                    ),
-                    |(mut errors, mut test_results, mut summary_statuses, mut bep_test_events),
-                     parse_event| {
-                        match parse_event {
-                            Result::Err(ref err) => {
-                                errors.push(format!("Error parsing build event: {}", err));
-                            }
-                            Result::Ok(build_event) => {
-                                let payload = &build_event.payload;
-                                let id = build_event.id.clone().and_then(|id| id.id);
-                                match (payload, id) {
-                                    (
-                                        Some(Payload::TestSummary(test_summary)),
-                                        Some(Id::TestSummary(id)),
-                                    ) => {
-                                        if let Result::Ok(status) = JunitReportStatus::try_from(
-                                            test_summary.overall_status(),
-                                        ) {
-                                            summary_statuses.insert(id.label.clone(), status);
-                                        }
-                                        bep_test_events.push(build_event);
-                                    }
-                                    (
-                                        Some(Payload::TestResult(test_result)),
-                                        Some(Id::TestResult(id)),
-                                    ) => {
-                                        let xml_files = test_result
-                                            .test_action_output
-                                            .iter()
-                                            .filter_map(|action_output| {
-                                                if action_output.name.ends_with(".xml") {
-                                                    action_output.file.clone().and_then(|f| {
-                                                        if let Uri(uri) = f {
-                                                            Some(
-                                                                uri.strip_prefix(FILE_URI_PREFIX)
-                                                                    .unwrap_or(&uri)
-                                                                    .to_string(),
-                                                            )
-                                                        } else {
-                                                            None
-                                                        }
-                                                    })
-                                                } else {
-                                                    None
-                                                }
-                                            })
-                                            .collect();
-
-                                        let cached = if let Some(execution_info) =
-                                            &test_result.execution_info
-                                        {
-                                            execution_info.cached_remotely
-                                                || test_result.cached_locally
-                                        } else {
-                                            test_result.cached_locally
-                                        };
+        let mut errors = Vec::<String>::new();
+        let mut test_results = Vec::<TestResult>::new();
+        let mut summary_statuses = HashMap::<String, JunitReportStatus>::new();
+        let mut bep_test_events = Vec::<BuildEvent>::new();
+        for parse_event in BepReader::new(reader) {
+            let build_event = match parse_event {
+                Result::Err(err) => {
+                    errors.push(format!("Error parsing build event: {}", err));
+                    continue;
+                }
+                Result::Ok(build_event) => build_event,
+            };
 
-                                        test_results.push(TestResult {
-                                            label: id.label.clone(),
-                                            cached,
-                                            xml_files,
-                                            summary_status: None,
-                                        });
-                                        bep_test_events.push(build_event);
-                                    }
-                                    _ => {}
+            if let Some((id, test_summary)) = build_event.as_test_summary() {
+                if let Result::Ok(status) =
+                    JunitReportStatus::try_from(test_summary.overall_status())
+                {
+                    summary_statuses.insert(id.label.clone(), status);
+                }
+                bep_test_events.push(build_event);
+            } else if let Some((id, test_result)) = build_event.as_test_result() {
+                let xml_files = test_result
+                    .test_action_output
+                    .iter()
+                    .filter_map(|action_output| {
+                        if action_output.name.ends_with(".xml") {
+                            action_output.file.clone().and_then(|f| {
+                                if let Uri(uri) = f {
+                                    Some(
+                                        uri.strip_prefix(FILE_URI_PREFIX)
+                                            .unwrap_or(&uri)
+                                            .to_string(),
+                                    )
+                                } else {
+                                    None
                                 }
-                            }
+                            })
+                        } else {
+                            None
                         }
+                    })
+                    .collect();
 
-                        (errors, test_results, summary_statuses, bep_test_events)
-                    },
-                );
+                let cached = if let Some(execution_info) = &test_result.execution_info {
+                    execution_info.cached_remotely || test_result.cached_locally
+                } else {
+                    test_result.cached_locally
+                };
+
+                test_results.push(TestResult {
+                    label: id.label.clone(),
+                    cached,
+                    xml_files,
+                    summary_status: None,
+                });
+                bep_test_events.push(build_event);
+            }
+        }
 
         Ok(BepParseResult {
             bep_test_events,
@@ -189,6 +166,8 @@ mod tests {
     const EMPTY_EXAMPLE: &str = "test_fixtures/bep_empty";
     const PARTIAL_EXAMPLE: &str = "test_fixtures/bep_partially_valid";
     const FLAKY_SUMMARY_EXAMPLE: &str = "test_fixtures/bep_flaky_summary";
+    const BINARY_FLAKY_SUMMARY_EXAMPLE: &str = "test_fixtures/bep_binary_flaky_summary";
+    const CORRUPTED_MIDDLE_EVENT_EXAMPLE: &str = "test_fixtures/bep_corrupted_middle_event";
 
     #[test]
     fn test_parse_simple_bep() {
@@ -201,7 +180,8 @@ mod tests {
             parse_result.uncached_xml_files(),
             vec![JunitReportFileWithStatus {
                 junit_path: "/tmp/hello_test/test.xml".to_string(),
-                status: None
+                status: None,
+                variant: None
             }]
         );
         assert_eq!(parse_result.xml_file_counts(), (1, 0));
@@ -232,18 +212,20 @@ mod tests {
             vec![
                 JunitReportFileWithStatus {
                     junit_path: "/tmp/hello_test/test.xml".to_string(),
-                    status: Some(JunitReportStatus::Passed)
+                    status: Some(JunitReportStatus::Passed),
+                    variant: None
                 },
                 JunitReportFileWithStatus {
                     junit_path: "/tmp/client_test/test.xml".to_string(),
-                    status: Some(JunitReportStatus::Passed)
+                    status: Some(JunitReportStatus::Passed),
+                    variant: None
                 }
             ]
         );
         assert_eq!(parse_result.xml_file_counts(), (3, 1));
         assert_eq!(
             *parse_result.errors,
-            vec!["Error parsing build event: EOF while parsing a value at line 108 column 0"]
+            vec!["Error parsing build event: EOF while parsing a value at line 107 column 18"]
         );
     }
 
@@ -258,22 +240,79 @@ mod tests {
             vec![
                 JunitReportFileWithStatus {
                     junit_path: "/tmp/hello_test/test_attempts/attempt_1.xml".to_string(),
-                    status: Some(JunitReportStatus::Flaky)
+                    status: Some(JunitReportStatus::Flaky),
+                    variant: None
                 },
                 JunitReportFileWithStatus {
                     junit_path: "/tmp/hello_test/test_attempts/attempt_2.xml".to_string(),
-                    status: Some(JunitReportStatus::Flaky)
+                    status: Some(JunitReportStatus::Flaky),
+                    variant: None
                 },
                 JunitReportFileWithStatus {
                     junit_path: "/tmp/hello_test/test.xml".to_string(),
-                    status: Some(JunitReportStatus::Flaky)
+                    status: Some(JunitReportStatus::Flaky),
+                    variant: None
                 },
                 JunitReportFileWithStatus {
                     junit_path: "/tmp/client_test/test.xml".to_string(),
-                    status: Some(JunitReportStatus::Failed)
+                    status: Some(JunitReportStatus::Failed),
+                    variant: None
                 }
             ]
         );
         assert_eq!(parse_result.xml_file_counts(), (4, 0));
     }
+
+    #[test]
+    fn test_parse_binary_bep() {
+        let json_parse_result = BazelBepParser::new(get_test_file_path(FLAKY_SUMMARY_EXAMPLE))
+            .parse()
+            .unwrap();
+        let input_file = get_test_file_path(BINARY_FLAKY_SUMMARY_EXAMPLE);
+        let mut parser = BazelBepParser::new(input_file);
+        let parse_result = parser.parse().unwrap();
+
+        assert_eq!(
+            parse_result.uncached_xml_files(),
+            json_parse_result.uncached_xml_files()
+        );
+        assert_eq!(parse_result.xml_file_counts(), (4, 0));
+        assert_eq!(
+            parse_result.bep_test_events,
+            json_parse_result.bep_test_events
+        );
+        assert!(parse_result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_corrupted_middle_event_bep() {
+        let input_file = get_test_file_path(CORRUPTED_MIDDLE_EVENT_EXAMPLE);
+        let mut parser = BazelBepParser::new(input_file);
+        let parse_result = parser.parse().unwrap();
+
+        assert_eq!(
+            parse_result.uncached_xml_files(),
+            vec![
+                JunitReportFileWithStatus {
+                    junit_path: "/tmp/hello_test/test_attempts/attempt_1.xml".to_string(),
+                    status: Some(JunitReportStatus::Flaky),
+                    variant: None
+                },
+                JunitReportFileWithStatus {
+                    junit_path: "/tmp/hello_test/test.xml".to_string(),
+                    status: Some(JunitReportStatus::Flaky),
+                    variant: None
+                },
+                JunitReportFileWithStatus {
+                    junit_path: "/tmp/client_test/test.xml".to_string(),
+                    status: Some(JunitReportStatus::Failed),
+                    variant: None
+                }
+            ]
+        );
+        assert_eq!(
+            *parse_result.errors,
+            vec!["Error parsing build event: EOF while parsing an object at line 24 column 80"]
+        );
+    }
 }
@@ -1,9 +1,12 @@
+use std::collections::BTreeMap;
+
 #[cfg(feature = "ruby")]
 use magnus::{value::ReprValue, Module, Object};
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
 use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyclass_enum};
+use regex::Regex;
 use thiserror::Error;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -17,6 +20,8 @@ mod ci_platform_env_key {
     pub const GITHUB_ACTIONS: &str = "GITHUB_ACTIONS";
     /// https://www.jenkins.io/doc/book/pipeline/jenkinsfile/#using-environment-variables
     pub const JENKINS_PIPELINE: &str = "BUILD_ID";
+    /// https://www.jenkins.io/doc/book/pipeline/jenkinsfile/#using-environment-variables
+    pub const JENKINS: &str = "JENKINS_URL";
     /// https://circleci.com/docs/variables/#built-in-environment-variables
     pub const CIRCLECI: &str = "CIRCLECI";
     /// https://buildkite.com/docs/pipelines/environment-variables#buildkite-environment-variables
@@ -67,6 +72,8 @@ pub enum CIPlatform {
     GitLabCI,
     Drone,
     Custom,
+    /// Jenkins builds other than pipelines, e.g. freestyle jobs
+    Jenkins,
     Unknown,
 }
 
@@ -86,6 +93,7 @@ impl Into<&str> for CIPlatform {
             CIPlatform::GitLabCI => ci_platform_env_key::GITLAB_CI,
             CIPlatform::Drone => ci_platform_env_key::DRONE,
             CIPlatform::Custom => ci_platform_env_key::CUSTOM,
+            CIPlatform::Jenkins => ci_platform_env_key::JENKINS,
             CIPlatform::Unknown => "UNKNOWN",
         }
     }
@@ -122,6 +130,7 @@ impl magnus::TryConvert for CIPlatform {
             10 => Ok(CIPlatform::GitLabCI),
             11 => Ok(CIPlatform::Drone),
             12 => Ok(CIPlatform::Custom),
+            13 => Ok(CIPlatform::Jenkins),
             _ => Err(magnus::Error::new(
                 magnus::Ruby::get_with(val).exception_type_error(),
                 format!("invalid CIPlatform: {}", val),
@@ -146,6 +155,7 @@ impl From<&str> for CIPlatform {
             ci_platform_env_key::GITLAB_CI => CIPlatform::GitLabCI,
             ci_platform_env_key::DRONE => CIPlatform::Drone,
             ci_platform_env_key::CUSTOM => CIPlatform::Custom,
+            ci_platform_env_key::JENKINS => CIPlatform::Jenkins,
             _ => CIPlatform::Unknown,
         }
     }
@@ -155,9 +165,11 @@ impl From<&EnvVars> for CIPlatform {
     fn from(value: &EnvVars) -> Self {
         let mut ci_platform = CIPlatform::Unknown;
         for (key, ..) in value.iter() {
-            ci_platform = CIPlatform::from(key.as_str());
-            if ci_platform != CIPlatform::Unknown {
-                break;
+            match CIPlatform::from(key.as_str()) {
+                CIPlatform::Unknown => (),
+                // pipelines set `JENKINS_URL` too, so keep looking for their `BUILD_ID`
+                CIPlatform::Jenkins => ci_platform = CIPlatform::Jenkins,
+                platform => return platform,
             }
         }
         ci_platform
@@ -172,10 +184,69 @@ pub enum CIInfoParseError {
 
 const MAX_BRANCH_NAME_SIZE: usize = 1000;
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StableBranchesError {
+    #[error("invalid stable branch pattern {0:?}: {1}")]
+    InvalidPattern(String, String),
+}
+
+/// Branches treated as protected when classifying a branch. Entries prefixed with `re:` are
+/// regex patterns that must match the whole cleaned branch name, e.g. `re:release/.*`.
+#[derive(Debug, Clone)]
+pub struct StableBranches {
+    names: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl StableBranches {
+    const PATTERN_PREFIX: &'static str = "re:";
+    const DEFAULT_NAMES: &'static [&'static str] = &["master", "main"];
+
+    /// Falls back to the default stable branches when `stable_branches` is empty.
+    pub fn new<T: AsRef<str>>(stable_branches: &[T]) -> Result<Self, StableBranchesError> {
+        if stable_branches.is_empty() {
+            return Ok(Self::default());
+        }
+        let mut names = Vec::new();
+        let mut patterns = Vec::new();
+        for stable_branch in stable_branches {
+            let stable_branch = stable_branch.as_ref();
+            if let Some(pattern) = stable_branch.strip_prefix(Self::PATTERN_PREFIX) {
+                let regex = Regex::new(&format!("^(?:{pattern})$")).map_err(|e| {
+                    StableBranchesError::InvalidPattern(String::from(pattern), e.to_string())
+                })?;
+                patterns.push(regex);
+            } else {
+                names.push(String::from(stable_branch));
+            }
+        }
+        Ok(Self { names, patterns })
+    }
+
+    pub fn matches(&self, branch: &str) -> bool {
+        self.names.iter().any(|name| name == branch)
+            || self.patterns.iter().any(|pattern| pattern.is_match(branch))
+    }
+}
+
+impl Default for StableBranches {
+    fn default() -> Self {
+        Self {
+            names: Self::DEFAULT_NAMES
+                .iter()
+                .map(|n| String::from(*n))
+                .collect(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CIInfoParser<'a> {
     errors: Vec<CIInfoParseError>,
     ci_info: CIInfo,
+    provenance: CIInfoProvenance,
+    stable_branches: StableBranches,
     env_vars: &'a EnvVars,
 }
 
@@ -184,6 +255,8 @@ impl<'a> CIInfoParser<'a> {
         Self {
             errors: Vec::new(),
             ci_info: CIInfo::new(platform),
+            provenance: CIInfoProvenance::default(),
+            stable_branches: StableBranches::default(),
             env_vars,
         }
     }
@@ -196,10 +269,14 @@ impl<'a> CIInfoParser<'a> {
         self.ci_info
     }
 
+    pub fn provenance(&self) -> &CIInfoProvenance {
+        &self.provenance
+    }
+
     pub fn parse(&mut self) {
         match self.ci_info.platform {
             CIPlatform::GitHubActions => self.parse_github_actions(),
-            CIPlatform::JenkinsPipeline => self.parse_jenkins_pipeline(),
+            CIPlatform::JenkinsPipeline | CIPlatform::Jenkins => self.parse_jenkins(),
             CIPlatform::Buildkite => self.parse_buildkite(),
             CIPlatform::Semaphore => self.parse_semaphore(),
             CIPlatform::GitLabCI => self.parse_gitlab_ci(),
@@ -224,47 +301,45 @@ impl<'a> CIInfoParser<'a> {
         if let Some(branch) = &mut self.ci_info.branch {
             *branch = clean_branch(branch);
         }
+        if let Some(target_branch) = &mut self.ci_info.target_branch {
+            *target_branch = clean_branch(target_branch);
+        }
     }
 
     fn parse_branch_class(&mut self) {
         if let Some(branch) = &self.ci_info.branch {
-            let mut merge_request_event_type: Option<GitLabMergeRequestEventType> = None;
-            if let Some(env_event_type) = self.get_env_var("CI_MERGE_REQUEST_EVENT_TYPE") {
-                match GitLabMergeRequestEventType::try_from(env_event_type.as_str()) {
-                    Ok(event_type) => {
-                        merge_request_event_type = Some(event_type);
-                    }
-                    Err(err) => {
-                        self.errors.push(err);
-                    }
-                }
+            if self.ci_info.merge_request_event_type.is_some() {
+                self.provenance
+                    .record("branch_class", "CI_MERGE_REQUEST_EVENT_TYPE");
             }
 
             self.ci_info.branch_class = Some(BranchClass::from((
                 branch.as_str(),
                 self.ci_info.pr_number,
-                merge_request_event_type,
+                self.ci_info.merge_request_event_type,
+                &self.stable_branches,
             )));
         }
     }
 
     fn parse_custom_info(&mut self) {
-        self.ci_info.job_url = self.get_env_var("JOB_URL");
-        self.ci_info.workflow = self.get_env_var("JOB_NAME");
-        self.ci_info.job = self.get_env_var("JOB_NAME");
+        self.ci_info.job_url = self.get_env_var_for("job_url", "JOB_URL");
+        self.ci_info.workflow = self.get_env_var_for("workflow", "JOB_NAME");
+        self.ci_info.job = self.get_env_var_for("job", "JOB_NAME");
 
-        self.ci_info.actor = self.get_env_var("AUTHOR_EMAIL");
-        self.ci_info.committer_email = self.get_env_var("AUTHOR_EMAIL");
-        self.ci_info.author_email = self.get_env_var("AUTHOR_EMAIL");
+        self.ci_info.actor = self.get_env_var_for("actor", "AUTHOR_EMAIL");
+        self.ci_info.committer_email = self.get_env_var_for("committer_email", "AUTHOR_EMAIL");
+        self.ci_info.author_email = self.get_env_var_for("author_email", "AUTHOR_EMAIL");
 
-        self.ci_info.committer_name = self.get_env_var("AUTHOR_NAME");
-        self.ci_info.author_name = self.get_env_var("AUTHOR_NAME");
+        self.ci_info.committer_name = self.get_env_var_for("committer_name", "AUTHOR_NAME");
+        self.ci_info.author_name = self.get_env_var_for("author_name", "AUTHOR_NAME");
 
-        self.ci_info.branch = self.get_env_var("COMMIT_BRANCH");
-        self.ci_info.commit_message = self.get_env_var("COMMIT_MESSAGE");
+        self.ci_info.branch = self.get_env_var_for("branch", "COMMIT_BRANCH");
+        self.ci_info.commit_message = self.get_env_var_for("commit_message", "COMMIT_MESSAGE");
 
-        self.ci_info.pr_number = Self::parse_pr_number(self.get_env_var("PR_NUMBER"));
-        self.ci_info.title = self.get_env_var("PR_TITLE");
+        self.ci_info.pr_number =
+            Self::parse_pr_number(self.get_env_var_for("pr_number", "PR_NUMBER"));
+        self.ci_info.title = self.get_env_var_for("title", "PR_TITLE");
     }
 
     fn parse_github_actions(&mut self) {
@@ -276,51 +351,81 @@ impl<'a> CIInfoParser<'a> {
                     .splitn(3, "/")
                     .last();
                 self.ci_info.pr_number = Self::parse_pr_number(stripped_ref);
+                self.record_provenance("pr_number", "GITHUB_REF");
             }
-            if let Some(gh_head_ref) = self.get_env_var("GITHUB_HEAD_REF") {
+            if let Some(gh_head_ref) = self.get_env_var_for("branch", "GITHUB_HEAD_REF") {
                 self.ci_info.branch = Some(gh_head_ref);
             } else {
+                self.record_provenance("branch", "GITHUB_REF");
                 self.ci_info.branch = Some(gh_ref);
             }
         }
 
-        self.ci_info.actor = self.get_env_var("GITHUB_ACTOR");
+        self.ci_info.actor = self.get_env_var_for("actor", "GITHUB_ACTOR");
         if let (Some(repo_name), Some(run_id)) = (
             self.get_env_var("GITHUB_REPOSITORY"),
             self.get_env_var("GITHUB_RUN_ID"),
         ) {
-            let mut job_url = format!("https://github.com/{repo_name}/actions/runs/{run_id}");
+            let server_url = match github_server_url(self.env_vars) {
+                Some((server_url, env_var)) => {
+                    self.record_provenance("job_url", env_var);
+                    server_url
+                }
+                None => String::from(GITHUB_DEFAULT_SERVER_URL),
+            };
+            let mut job_url = format!("{server_url}/{repo_name}/actions/runs/{run_id}");
             if let Some(pr_number) = self.ci_info.pr_number {
                 job_url = format!("{job_url}?pr={pr_number}");
             }
             self.ci_info.job_url = Some(job_url);
+            self.record_provenance("job_url", "GITHUB_REPOSITORY");
+            self.record_provenance("job_url", "GITHUB_RUN_ID");
         }
-        self.ci_info.workflow = self.get_env_var("GITHUB_WORKFLOW");
-        self.ci_info.job = self.get_env_var("GITHUB_JOB");
+        self.ci_info.workflow = self.get_env_var_for("workflow", "GITHUB_WORKFLOW");
+        self.ci_info.job = self.get_env_var_for("job", "GITHUB_JOB");
     }
 
-    fn parse_jenkins_pipeline(&mut self) {
-        self.ci_info.job_url = self.get_env_var("BUILD_URL");
+    fn parse_jenkins(&mut self) {
+        self.ci_info.job_url = self.get_env_var_for("job_url", "BUILD_URL");
+        // Multibranch pipelines set `CHANGE_*` when building a PR, with `BRANCH_NAME` e.g. `PR-7`.
+        // Other jobs only have the remote branch the Git plugin checked out, e.g. `origin/main`.
         self.ci_info.branch = self
-            .get_env_var("CHANGE_BRANCH")
-            .or_else(|| self.get_env_var("BRANCH_NAME"));
-        self.ci_info.pr_number = Self::parse_pr_number(self.get_env_var("CHANGE_ID"));
-        self.ci_info.actor = self.get_env_var("CHANGE_AUTHOR_EMAIL");
-        self.ci_info.committer_name = self.get_env_var("CHANGE_AUTHOR_DISPLAY_NAME");
-        self.ci_info.committer_email = self.get_env_var("CHANGE_AUTHOR_EMAIL");
-        self.ci_info.author_name = self.get_env_var("CHANGE_AUTHOR_DISPLAY_NAME");
-        self.ci_info.author_email = self.get_env_var("CHANGE_AUTHOR_EMAIL");
+            .get_env_var_for("branch", "CHANGE_BRANCH")
+            .or_else(|| self.get_env_var_for("branch", "BRANCH_NAME"))
+            .or_else(|| {
+                self.get_env_var_for("branch", "GIT_BRANCH").map(|branch| {
+                    let branch = branch.strip_prefix("refs/").unwrap_or(&branch);
+                    String::from(branch.strip_prefix("remotes/").unwrap_or(branch))
+                })
+            });
+        self.ci_info.pr_number =
+            Self::parse_pr_number(self.get_env_var_for("pr_number", "CHANGE_ID"));
+        self.ci_info.target_branch = self.get_env_var_for("target_branch", "CHANGE_TARGET");
+        self.ci_info.actor = self.get_env_var_for("actor", "CHANGE_AUTHOR_EMAIL");
+        self.ci_info.committer_name =
+            self.get_env_var_for("committer_name", "CHANGE_AUTHOR_DISPLAY_NAME");
+        self.ci_info.committer_email =
+            self.get_env_var_for("committer_email", "CHANGE_AUTHOR_EMAIL");
+        self.ci_info.author_name =
+            self.get_env_var_for("author_name", "CHANGE_AUTHOR_DISPLAY_NAME");
+        self.ci_info.author_email = self.get_env_var_for("author_email", "CHANGE_AUTHOR_EMAIL");
+        self.ci_info.build_number = self.get_env_var_for("build_number", "BUILD_NUMBER");
+        self.ci_info.node_name = self.get_env_var_for("node_name", "NODE_NAME");
     }
 
     fn parse_buildkite(&mut self) {
-        self.ci_info.job_url = self.get_env_var("BUILDKITE_BUILD_URL");
-        self.ci_info.branch = self.get_env_var("BUILDKITE_BRANCH");
-        self.ci_info.pr_number = Self::parse_pr_number(self.get_env_var("BUILDKITE_PULL_REQUEST"));
-        self.ci_info.actor = self.get_env_var("BUILDKITE_BUILD_AUTHOR_EMAIL");
-        self.ci_info.committer_name = self.get_env_var("BUILDKITE_BUILD_AUTHOR");
-        self.ci_info.committer_email = self.get_env_var("BUILDKITE_BUILD_AUTHOR_EMAIL");
-        self.ci_info.author_name = self.get_env_var("BUILDKITE_BUILD_AUTHOR");
-        self.ci_info.author_email = self.get_env_var("BUILDKITE_BUILD_AUTHOR_EMAIL");
+        self.ci_info.job_url = self.get_env_var_for("job_url", "BUILDKITE_BUILD_URL");
+        self.ci_info.branch = self.get_env_var_for("branch", "BUILDKITE_BRANCH");
+        self.ci_info.pr_number =
+            Self::parse_pr_number(self.get_env_var_for("pr_number", "BUILDKITE_PULL_REQUEST"));
+        self.ci_info.actor = self.get_env_var_for("actor", "BUILDKITE_BUILD_AUTHOR_EMAIL");
+        self.ci_info.committer_name =
+            self.get_env_var_for("committer_name", "BUILDKITE_BUILD_AUTHOR");
+        self.ci_info.committer_email =
+            self.get_env_var_for("committer_email", "BUILDKITE_BUILD_AUTHOR_EMAIL");
+        self.ci_info.author_name = self.get_env_var_for("author_name", "BUILDKITE_BUILD_AUTHOR");
+        self.ci_info.author_email =
+            self.get_env_var_for("author_email", "BUILDKITE_BUILD_AUTHOR_EMAIL");
     }
 
     fn parse_semaphore(&mut self) {
@@ -330,26 +435,34 @@ impl<'a> CIInfoParser<'a> {
             self.get_env_var("SEMAPHORE_JOB_ID"),
         ) {
             self.ci_info.job_url = Some(format!("{org_url}/projects/{project_id}/jobs/{job_id}"));
+            self.record_provenance("job_url", "SEMAPHORE_ORGANIZATION_URL");
+            self.record_provenance("job_url", "SEMAPHORE_PROJECT_ID");
+            self.record_provenance("job_url", "SEMAPHORE_JOB_ID");
         }
         self.ci_info.branch = self
-            .get_env_var("SEMAPHORE_GIT_PR_BRANCH")
-            .or_else(|| self.get_env_var("SEMAPHORE_GIT_WORKING_BRANCH"))
-            .or_else(|| self.get_env_var("SEMAPHORE_GIT_BRANCH"));
-        self.ci_info.pr_number = Self::parse_pr_number(self.get_env_var("SEMAPHORE_GIT_PR_NUMBER"));
-        self.ci_info.actor = self.get_env_var("SEMAPHORE_GIT_COMMIT_AUTHOR");
-        self.ci_info.committer_name = self.get_env_var("SEMAPHORE_GIT_COMMITTER");
-        self.ci_info.author_name = self.get_env_var("SEMAPHORE_GIT_COMMIT_AUTHOR");
+            .get_env_var_for("branch", "SEMAPHORE_GIT_PR_BRANCH")
+            .or_else(|| self.get_env_var_for("branch", "SEMAPHORE_GIT_WORKING_BRANCH"))
+            .or_else(|| self.get_env_var_for("branch", "SEMAPHORE_GIT_BRANCH"));
+        self.ci_info.pr_number =
+            Self::parse_pr_number(self.get_env_var_for("pr_number", "SEMAPHORE_GIT_PR_NUMBER"));
+        self.ci_info.actor = self.get_env_var_for("actor", "SEMAPHORE_GIT_COMMIT_AUTHOR");
+        self.ci_info.committer_name =
+            self.get_env_var_for("committer_name", "SEMAPHORE_GIT_COMMITTER");
+        self.ci_info.author_name =
+            self.get_env_var_for("author_name", "SEMAPHORE_GIT_COMMIT_AUTHOR");
 
-        self.ci_info.workflow = self.get_env_var("SEMAPHORE_PROJECT_NAME");
-        self.ci_info.job = self.get_env_var("SEMAPHORE_JOB_NAME");
+        self.ci_info.workflow = self.get_env_var_for("workflow", "SEMAPHORE_PROJECT_NAME");
+        self.ci_info.job = self.get_env_var_for("job", "SEMAPHORE_JOB_NAME");
     }
 
     fn parse_gitlab_ci(&mut self) {
-        self.ci_info.job_url = self.get_env_var("CI_JOB_URL");
+        self.ci_info.job_url = self.get_env_var_for("job_url", "CI_JOB_URL");
+        // Merge request pipelines check out a detached `refs/merge-requests/N/head` (or `merge`)
+        // ref, so the source branch name is the only reliable branch there.
         if let Some(branch) = self
-            .get_env_var("CI_COMMIT_REF_NAME")
-            .or_else(|| self.get_env_var("CI_COMMIT_BRANCH"))
-            .or_else(|| self.get_env_var("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME"))
+            .get_env_var_for("branch", "CI_MERGE_REQUEST_SOURCE_BRANCH_NAME")
+            .or_else(|| self.get_env_var_for("branch", "CI_COMMIT_REF_NAME"))
+            .or_else(|| self.get_env_var_for("branch", "CI_COMMIT_BRANCH"))
         {
             self.ci_info.branch = Some(if branch.starts_with("remotes/") {
                 branch.replacen("remotes/", "", 1)
@@ -357,7 +470,24 @@ impl<'a> CIInfoParser<'a> {
                 branch
             });
         }
-        self.ci_info.pr_number = Self::parse_pr_number(self.get_env_var("CI_MERGE_REQUEST_IID"));
+        self.ci_info.pr_number =
+            Self::parse_pr_number(self.get_env_var_for("pr_number", "CI_MERGE_REQUEST_IID"));
+        self.ci_info.target_branch =
+            self.get_env_var_for("target_branch", "CI_MERGE_REQUEST_TARGET_BRANCH_NAME");
+        if let Some(env_event_type) = self.get_env_var("CI_MERGE_REQUEST_EVENT_TYPE") {
+            match GitLabMergeRequestEventType::try_from(env_event_type.as_str()) {
+                Ok(event_type) => {
+                    self.ci_info.merge_request_event_type = Some(event_type);
+                    self.record_provenance(
+                        "merge_request_event_type",
+                        "CI_MERGE_REQUEST_EVENT_TYPE",
+                    );
+                }
+                Err(err) => {
+                    self.errors.push(err);
+                }
+            }
+        }
         // `CI_COMMIT_AUTHOR` has format `Name <email>`
         // https://docs.gitlab.com/ee/ci/variables/predefined_variables.html
         if let Some((name, email)) = self
@@ -376,23 +506,48 @@ impl<'a> CIInfoParser<'a> {
             self.ci_info.committer_email = Some(email.clone());
             self.ci_info.author_name = Some(name);
             self.ci_info.author_email = Some(email);
+            for field in [
+                "actor",
+                "committer_name",
+                "committer_email",
+                "author_name",
+                "author_email",
+            ] {
+                self.record_provenance(field, "CI_COMMIT_AUTHOR");
+            }
         }
-        self.ci_info.commit_message = self.get_env_var("CI_COMMIT_MESSAGE");
-        self.ci_info.title = self.get_env_var("CI_MERGE_REQUEST_TITLE");
-        self.ci_info.workflow = self.get_env_var("CI_JOB_NAME");
-        self.ci_info.job = self.get_env_var("CI_JOB_STAGE");
+        self.ci_info.commit_message = self.get_env_var_for("commit_message", "CI_COMMIT_MESSAGE");
+        self.ci_info.title = self.get_env_var_for("title", "CI_MERGE_REQUEST_TITLE");
+        self.ci_info.workflow = self.get_env_var_for("workflow", "CI_JOB_NAME");
+        self.ci_info.job = self.get_env_var_for("job", "CI_JOB_STAGE");
     }
 
     fn parse_drone(&mut self) {
-        self.ci_info.branch = self.get_env_var("DRONE_SOURCE_BRANCH");
-        self.ci_info.pr_number = Self::parse_pr_number(self.get_env_var("DRONE_PULL_REQUEST"));
-        self.ci_info.actor = self.get_env_var("DRONE_COMMIT_AUTHOR");
-        self.ci_info.committer_name = self.get_env_var("DRONE_COMMIT_AUTHOR_NAME");
-        self.ci_info.committer_email = self.get_env_var("DRONE_COMMIT_AUTHOR_EMAIL");
-        self.ci_info.author_name = self.get_env_var("DRONE_COMMIT_AUTHOR_NAME");
-        self.ci_info.author_email = self.get_env_var("DRONE_COMMIT_AUTHOR_EMAIL");
-        self.ci_info.title = self.get_env_var("DRONE_PULL_REQUEST_TITLE");
-        self.ci_info.job_url = self.get_env_var("DRONE_BUILD_LINK");
+        self.ci_info.branch = self.get_env_var_for("branch", "DRONE_SOURCE_BRANCH");
+        self.ci_info.pr_number =
+            Self::parse_pr_number(self.get_env_var_for("pr_number", "DRONE_PULL_REQUEST"));
+        self.ci_info.actor = self.get_env_var_for("actor", "DRONE_COMMIT_AUTHOR");
+        self.ci_info.committer_name =
+            self.get_env_var_for("committer_name", "DRONE_COMMIT_AUTHOR_NAME");
+        self.ci_info.committer_email =
+            self.get_env_var_for("committer_email", "DRONE_COMMIT_AUTHOR_EMAIL");
+        self.ci_info.author_name = self.get_env_var_for("author_name", "DRONE_COMMIT_AUTHOR_NAME");
+        self.ci_info.author_email =
+            self.get_env_var_for("author_email", "DRONE_COMMIT_AUTHOR_EMAIL");
+        self.ci_info.title = self.get_env_var_for("title", "DRONE_PULL_REQUEST_TITLE");
+        self.ci_info.job_url = self.get_env_var_for("job_url", "DRONE_BUILD_LINK");
+    }
+
+    fn get_env_var_for(&mut self, field: &'static str, env_var: &str) -> Option<String> {
+        let value = self.get_env_var(env_var);
+        if value.is_some() {
+            self.record_provenance(field, env_var);
+        }
+        value
+    }
+
+    fn record_provenance(&mut self, field: &'static str, env_var: &str) {
+        self.provenance.record(field, env_var);
     }
 
     fn get_env_var<T: AsRef<str>>(&self, env_var: T) -> Option<String> {
@@ -420,6 +575,8 @@ pub struct CIInfo {
     pub branch: Option<String>,
     pub branch_class: Option<BranchClass>,
     pub pr_number: Option<usize>,
+    pub target_branch: Option<String>,
+    pub merge_request_event_type: Option<GitLabMergeRequestEventType>,
     pub actor: Option<String>,
     pub committer_name: Option<String>,
     pub committer_email: Option<String>,
@@ -429,6 +586,35 @@ pub struct CIInfo {
     pub title: Option<String>,
     pub workflow: Option<String>,
     pub job: Option<String>,
+    /// Number of the build of the job, only parsed for Jenkins
+    pub build_number: Option<String>,
+    /// Agent the job ran on, only parsed for Jenkins
+    pub node_name: Option<String>,
+}
+
+/// Maps each `CIInfo` field to the env var(s) its value was read from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CIInfoProvenance(BTreeMap<&'static str, Vec<String>>);
+
+impl CIInfoProvenance {
+    pub fn get(&self, field: &str) -> Option<&Vec<String>> {
+        self.0.get(field)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&&'static str, &Vec<String>)> {
+        self.0.iter()
+    }
+
+    pub fn env_vars(&self) -> impl Iterator<Item = &String> {
+        self.0.values().flatten()
+    }
+
+    fn record(&mut self, field: &'static str, env_var: &str) {
+        let env_vars = self.0.entry(field).or_default();
+        if !env_vars.iter().any(|v| v == env_var) {
+            env_vars.push(env_var.to_string());
+        }
+    }
 }
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
@@ -454,6 +640,16 @@ impl TryFrom<&str> for GitLabMergeRequestEventType {
     }
 }
 
+impl From<GitLabMergeRequestEventType> for &'static str {
+    fn from(value: GitLabMergeRequestEventType) -> Self {
+        match value {
+            GitLabMergeRequestEventType::Detached => "detached",
+            GitLabMergeRequestEventType::MergedResult => "merged_result",
+            GitLabMergeRequestEventType::MergeTrain => "merge_train",
+        }
+    }
+}
+
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[cfg_attr(feature = "ruby", magnus::wrap(class = "BranchClass"))]
@@ -468,6 +664,32 @@ pub enum BranchClass {
 impl From<(&str, Option<usize>, Option<GitLabMergeRequestEventType>)> for BranchClass {
     fn from(value: (&str, Option<usize>, Option<GitLabMergeRequestEventType>)) -> Self {
         let (branch_name, pr_number, merge_request_event_type) = value;
+        BranchClass::from((
+            branch_name,
+            pr_number,
+            merge_request_event_type,
+            &StableBranches::default(),
+        ))
+    }
+}
+
+impl
+    From<(
+        &str,
+        Option<usize>,
+        Option<GitLabMergeRequestEventType>,
+        &StableBranches,
+    )> for BranchClass
+{
+    fn from(
+        value: (
+            &str,
+            Option<usize>,
+            Option<GitLabMergeRequestEventType>,
+            &StableBranches,
+        ),
+    ) -> Self {
+        let (branch_name, pr_number, merge_request_event_type, stable_branches) = value;
         if branch_name.contains("trunk-merge/")
             || branch_name.contains("gh-readonly-queue/")
             || branch_name.contains("/gtmq_")
@@ -481,7 +703,7 @@ impl From<(&str, Option<usize>, Option<GitLabMergeRequestEventType>)> for Branch
             BranchClass::PullRequest
         } else if branch_name.starts_with("remotes/pull/") || branch_name.starts_with("pull/") {
             BranchClass::PullRequest
-        } else if matches!(branch_name, "master" | "main") {
+        } else if stable_branches.matches(branch_name) {
             BranchClass::ProtectedBranch
         } else {
             BranchClass::None
@@ -500,6 +722,42 @@ impl ToString for BranchClass {
     }
 }
 
+const GITHUB_DEFAULT_SERVER_URL: &str = "https://github.com";
+/// GitHub Enterprise Server serves its REST API at `<server url>/api/v3`
+const GITHUB_ENTERPRISE_API_PATH: &str = "/api/v3";
+
+/// URL of the GitHub server running the workflow, with the env var it was read from. Only
+/// GitHub Enterprise Server's `GITHUB_API_URL` can be turned back into a server URL, since the
+/// API of github.com is on another host.
+fn github_server_url(env_vars: &EnvVars) -> Option<(String, &'static str)> {
+    let get_url = |env_var: &str| {
+        env_vars
+            .get(env_var)
+            .map(|url| url.trim().trim_end_matches('/'))
+            .filter(|url| !url.is_empty())
+    };
+    get_url("GITHUB_SERVER_URL")
+        .map(|server_url| (String::from(server_url), "GITHUB_SERVER_URL"))
+        .or_else(|| {
+            get_url("GITHUB_API_URL")
+                .and_then(|api_url| api_url.strip_suffix(GITHUB_ENTERPRISE_API_PATH))
+                .map(|server_url| (String::from(server_url), "GITHUB_API_URL"))
+        })
+}
+
+/// URL of the repository of a GitHub Actions workflow, on the GitHub Enterprise Server host when
+/// there is one, to parse into `RepoUrlParts` when git has no remote.
+pub fn github_repo_url(env_vars: &EnvVars) -> Option<String> {
+    let repository = env_vars
+        .get("GITHUB_REPOSITORY")
+        .map(|repository| repository.trim().trim_matches('/'))
+        .filter(|repository| !repository.is_empty())?;
+    let server_url = github_server_url(env_vars)
+        .map(|(server_url, _)| server_url)
+        .unwrap_or_else(|| String::from(GITHUB_DEFAULT_SERVER_URL));
+    Some(format!("{server_url}/{repository}"))
+}
+
 pub fn clean_branch(branch: &str) -> String {
     let new_branch = branch
         .replace("refs/heads/", "")
@@ -517,6 +775,8 @@ impl CIInfo {
             branch: None,
             branch_class: None,
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: None,
             committer_name: None,
             committer_email: None,
@@ -526,6 +786,8 @@ impl CIInfo {
             title: None,
             workflow: None,
             job: None,
+            build_number: None,
+            node_name: None,
         }
     }
 }
@@ -547,6 +809,12 @@ impl CIInfo {
     pub fn pr_number(&self) -> Option<usize> {
         self.pr_number
     }
+    pub fn target_branch(&self) -> Option<&str> {
+        self.target_branch.as_deref()
+    }
+    pub fn merge_request_event_type(&self) -> Option<GitLabMergeRequestEventType> {
+        self.merge_request_event_type
+    }
     pub fn actor(&self) -> Option<&str> {
         self.actor.as_deref()
     }
@@ -574,11 +842,18 @@ impl CIInfo {
     pub fn job(&self) -> Option<&str> {
         self.job.as_deref()
     }
+    pub fn build_number(&self) -> Option<&str> {
+        self.build_number.as_deref()
+    }
+    pub fn node_name(&self) -> Option<&str> {
+        self.node_name.as_deref()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct EnvParser<'a> {
     ci_info_parser: Option<CIInfoParser<'a>>,
+    stable_branches: StableBranches,
 }
 
 impl<'a> EnvParser<'a> {
@@ -586,6 +861,13 @@ impl<'a> EnvParser<'a> {
         Default::default()
     }
 
+    pub fn with_stable_branches(stable_branches: StableBranches) -> Self {
+        Self {
+            stable_branches,
+            ..Default::default()
+        }
+    }
+
     pub fn ci_info_parser(&self) -> &Option<CIInfoParser> {
         &self.ci_info_parser
     }
@@ -602,7 +884,9 @@ impl<'a> EnvParser<'a> {
     }
 
     fn parse_ci_platform(&mut self, env_vars: &'a EnvVars) {
-        self.ci_info_parser = Some(CIInfoParser::new(CIPlatform::from(env_vars), &env_vars));
+        let mut ci_info_parser = CIInfoParser::new(CIPlatform::from(env_vars), env_vars);
+        ci_info_parser.stable_branches = self.stable_branches.clone();
+        self.ci_info_parser = Some(ci_info_parser);
     }
 }
 
@@ -617,6 +901,11 @@ pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
     ci_info.define_method("branch", magnus::method!(CIInfo::branch, 0))?;
     ci_info.define_method("branch_class", magnus::method!(CIInfo::branch_class, 0))?;
     ci_info.define_method("pr_number", magnus::method!(CIInfo::pr_number, 0))?;
+    ci_info.define_method("target_branch", magnus::method!(CIInfo::target_branch, 0))?;
+    ci_info.define_method(
+        "merge_request_event_type",
+        magnus::method!(CIInfo::merge_request_event_type, 0),
+    )?;
     ci_info.define_method("actor", magnus::method!(CIInfo::actor, 0))?;
     ci_info.define_method("committer_name", magnus::method!(CIInfo::committer_name, 0))?;
     ci_info.define_method(
@@ -629,5 +918,7 @@ pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
     ci_info.define_method("title", magnus::method!(CIInfo::title, 0))?;
     ci_info.define_method("workflow", magnus::method!(CIInfo::workflow, 0))?;
     ci_info.define_method("job", magnus::method!(CIInfo::job, 0))?;
+    ci_info.define_method("build_number", magnus::method!(CIInfo::build_number, 0))?;
+    ci_info.define_method("node_name", magnus::method!(CIInfo::node_name, 0))?;
     Ok(())
 }
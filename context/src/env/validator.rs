@@ -1,3 +1,5 @@
+#[cfg(feature = "ruby")]
+use magnus::Module;
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
@@ -6,7 +8,7 @@ use thiserror::Error;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use super::parser::{BranchClass, CIInfo};
+use super::parser::{BranchClass, CIInfo, CIPlatform};
 use crate::string_safety::{validate_field_len, FieldLen};
 
 pub const MAX_BRANCH_NAME_LEN: usize = 36;
@@ -15,6 +17,7 @@ pub const MAX_FIELD_LEN: usize = 1000;
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "ruby", magnus::wrap(class = "EnvValidationLevel"))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EnvValidationLevel {
     Valid = 0,
@@ -53,6 +56,16 @@ impl ToString for EnvValidationIssue {
     }
 }
 
+impl EnvValidationIssue {
+    /// The `CIInfo` field the issue is about, which is stable across releases unlike the message
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::SubOptimal(i) => i.field(),
+            Self::Invalid(i) => i.field(),
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum EnvValidationIssueSubOptimal {
     #[error("CI info actor too short")]
@@ -97,8 +110,46 @@ pub enum EnvValidationIssueSubOptimal {
     CIInfoTitleTooLong(String),
 }
 
+impl EnvValidationIssueSubOptimal {
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::CIInfoActorTooShort(..) | Self::CIInfoActorTooLong(..) => "actor",
+            Self::CIInfoAuthorEmailTooShort(..) | Self::CIInfoAuthorEmailTooLong(..) => {
+                "author_email"
+            }
+            Self::CIInfoAuthorNameTooShort(..) | Self::CIInfoAuthorNameTooLong(..) => "author_name",
+            Self::CIInfoBranchNameTooShort(..) | Self::CIInfoBranchNameTooLong(..) => "branch",
+            Self::CIInfoCommitMessageTooShort(..) | Self::CIInfoCommitMessageTooLong(..) => {
+                "commit_message"
+            }
+            Self::CIInfoCommitterEmailTooShort(..) | Self::CIInfoCommitterEmailTooLong(..) => {
+                "committer_email"
+            }
+            Self::CIInfoCommitterNameTooShort(..) | Self::CIInfoCommitterNameTooLong(..) => {
+                "committer_name"
+            }
+            Self::CIInfoJobURLTooShort(..) | Self::CIInfoJobURLTooLong(..) => "job_url",
+            Self::CIInfoPRNumberMissing | Self::CIInfoPRNumberConflictsWithBranchClass => {
+                "pr_number"
+            }
+            Self::CIInfoTitleTooShort(..) | Self::CIInfoTitleTooLong(..) => "title",
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
-pub enum EnvValidationIssueInvalid {}
+pub enum EnvValidationIssueInvalid {
+    #[error("CI info has no job URL, which is required for Jenkins")]
+    CIInfoJobURLMissing,
+}
+
+impl EnvValidationIssueInvalid {
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::CIInfoJobURLMissing => "job_url",
+        }
+    }
+}
 
 impl From<&EnvValidationIssue> for EnvValidationLevel {
     fn from(value: &EnvValidationIssue) -> Self {
@@ -223,6 +274,18 @@ pub fn validate(ci_info: &CIInfo) -> EnvValidation {
 
     match validate_field_len::<MAX_FIELD_LEN, _>(optional_string_to_empty_str(&ci_info.job_url)) {
         FieldLen::Valid => (),
+        // Jenkins has no other way to link back to the build, e.g. a run ID on a known host
+        FieldLen::TooShort(..)
+            if ci_info.job_url.is_none()
+                && matches!(
+                    ci_info.platform,
+                    CIPlatform::JenkinsPipeline | CIPlatform::Jenkins
+                ) =>
+        {
+            env_validation.add_issue(EnvValidationIssue::Invalid(
+                EnvValidationIssueInvalid::CIInfoJobURLMissing,
+            ));
+        }
         FieldLen::TooShort(s) => {
             env_validation.add_issue(EnvValidationIssue::SubOptimal(
                 EnvValidationIssueSubOptimal::CIInfoJobURLTooShort(s),
@@ -271,6 +334,10 @@ pub fn validate(ci_info: &CIInfo) -> EnvValidation {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass)]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "EnvValidation", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct EnvValidation {
     level: EnvValidationLevel,
@@ -279,9 +346,14 @@ pub struct EnvValidation {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "EnvValidationFlatIssue", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct EnvValidationFlatIssue {
     pub level: EnvValidationLevel,
+    pub field: String,
     pub error_message: String,
 }
 
@@ -297,6 +369,7 @@ impl EnvValidation {
             .iter()
             .map(|i| EnvValidationFlatIssue {
                 level: EnvValidationLevel::from(i),
+                field: i.field().to_string(),
                 error_message: i.to_string(),
             })
             .collect()
@@ -318,6 +391,44 @@ impl EnvValidation {
     }
 }
 
+#[cfg(feature = "ruby")]
+impl EnvValidationFlatIssue {
+    pub fn level(&self) -> EnvValidationLevel {
+        self.level
+    }
+    pub fn field(&self) -> String {
+        self.field.clone()
+    }
+    pub fn error_message(&self) -> String {
+        self.error_message.clone()
+    }
+}
+
+#[cfg(feature = "ruby")]
+pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let env_validation_level = ruby.define_class("EnvValidationLevel", ruby.class_object())?;
+    env_validation_level
+        .define_method("to_s", magnus::method!(EnvValidationLevel::to_string, 0))?;
+    let env_validation = ruby.define_class("EnvValidation", ruby.class_object())?;
+    env_validation.define_method("level", magnus::method!(EnvValidation::level, 0))?;
+    env_validation.define_method("max_level", magnus::method!(EnvValidation::max_level, 0))?;
+    env_validation.define_method(
+        "issues_flat",
+        magnus::method!(EnvValidation::issues_flat, 0),
+    )?;
+    let env_validation_flat_issue =
+        ruby.define_class("EnvValidationFlatIssue", ruby.class_object())?;
+    env_validation_flat_issue
+        .define_method("level", magnus::method!(EnvValidationFlatIssue::level, 0))?;
+    env_validation_flat_issue
+        .define_method("field", magnus::method!(EnvValidationFlatIssue::field, 0))?;
+    env_validation_flat_issue.define_method(
+        "error_message",
+        magnus::method!(EnvValidationFlatIssue::error_message, 0),
+    )?;
+    Ok(())
+}
+
 fn optional_string_to_empty_str<'a>(optional_string: &'a Option<String>) -> &'a str {
     optional_string.as_ref().map_or("", |s| &s)
 }
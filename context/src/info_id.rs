@@ -0,0 +1,839 @@
+//! The ID of a test case, which is how Trunk tells tests apart across uploads.
+//!
+//! Unless the test runner provides one, e.g. with an `id` attribute of a junit `testcase`, the ID
+//! is a UUIDv5 in the URL namespace of these fields joined with `#`:
+//!
+//! ```text
+//! org_url_slug#repo_full_name#file#classname#parent_name#name#JUNIT_TESTCASE[#variant]
+//! ```
+//!
+//! `repo_full_name` is `host/owner/name` as parsed from the repo URL, and a missing `file` or
+//! `classname` is an empty string. The variant is only appended when it is not empty, so that IDs
+//! from before variants were supported are unchanged. Fields are used as is, without trimming,
+//! case folding or Unicode normalization.
+//!
+//! [`gen_info_id_vectors`] are inputs with their expected IDs, for other implementations of this
+//! algorithm to check themselves against.
+
+#[cfg(feature = "ruby")]
+use magnus::Module;
+#[cfg(feature = "pyo3")]
+use pyo3::prelude::*;
+#[cfg(feature = "pyo3")]
+use pyo3_stub_gen::derive::gen_stub_pyclass;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// The type of the test cases of junit reports, which is part of their IDs
+pub const INFO_ID_TYPE_JUNIT_TESTCASE: &str = "JUNIT_TESTCASE";
+
+/// The ID of a test case, which is `info_id` if it is provided and not empty.
+#[allow(clippy::too_many_arguments)]
+pub fn gen_info_id(
+    org_url_slug: &str,
+    repo_full_name: &str,
+    file: Option<&str>,
+    classname: Option<&str>,
+    parent_name: &str,
+    name: &str,
+    info_id: Option<&str>,
+    variant: Option<&str>,
+) -> String {
+    if let Some(info_id) = info_id.filter(|info_id| !info_id.is_empty()) {
+        return String::from(info_id);
+    }
+    let mut info_id_parts = vec![
+        org_url_slug,
+        repo_full_name,
+        file.unwrap_or(""),
+        classname.unwrap_or(""),
+        parent_name,
+        name,
+        INFO_ID_TYPE_JUNIT_TESTCASE,
+    ];
+    if let Some(variant) = variant.filter(|variant| !variant.is_empty()) {
+        info_id_parts.push(variant);
+    }
+    let info_id_input = info_id_parts.join("#");
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, info_id_input.as_bytes()).to_string()
+}
+
+/// Inputs of [`gen_info_id`] and the ID they must generate.
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "InfoIdVector", free_immediately, size)
+)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InfoIdVector {
+    /// What the vector covers
+    pub note: String,
+    pub org_url_slug: String,
+    pub repo_full_name: String,
+    pub file: Option<String>,
+    pub classname: Option<String>,
+    pub parent_name: String,
+    pub name: String,
+    pub info_id: Option<String>,
+    pub variant: Option<String>,
+    pub expected_id: String,
+}
+
+impl InfoIdVector {
+    pub fn gen_info_id(&self) -> String {
+        gen_info_id(
+            &self.org_url_slug,
+            &self.repo_full_name,
+            self.file.as_deref(),
+            self.classname.as_deref(),
+            &self.parent_name,
+            &self.name,
+            self.info_id.as_deref(),
+            self.variant.as_deref(),
+        )
+    }
+}
+
+#[cfg(feature = "ruby")]
+impl InfoIdVector {
+    pub fn note(&self) -> String {
+        self.note.clone()
+    }
+    pub fn org_url_slug(&self) -> String {
+        self.org_url_slug.clone()
+    }
+    pub fn repo_full_name(&self) -> String {
+        self.repo_full_name.clone()
+    }
+    pub fn file(&self) -> Option<String> {
+        self.file.clone()
+    }
+    pub fn classname(&self) -> Option<String> {
+        self.classname.clone()
+    }
+    pub fn parent_name(&self) -> String {
+        self.parent_name.clone()
+    }
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+    pub fn info_id(&self) -> Option<String> {
+        self.info_id.clone()
+    }
+    pub fn variant(&self) -> Option<String> {
+        self.variant.clone()
+    }
+    pub fn expected_id(&self) -> String {
+        self.expected_id.clone()
+    }
+}
+
+#[cfg(feature = "ruby")]
+pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let info_id_vector = ruby.define_class("InfoIdVector", ruby.class_object())?;
+    info_id_vector.define_method("note", magnus::method!(InfoIdVector::note, 0))?;
+    info_id_vector.define_method(
+        "org_url_slug",
+        magnus::method!(InfoIdVector::org_url_slug, 0),
+    )?;
+    info_id_vector.define_method(
+        "repo_full_name",
+        magnus::method!(InfoIdVector::repo_full_name, 0),
+    )?;
+    info_id_vector.define_method("file", magnus::method!(InfoIdVector::file, 0))?;
+    info_id_vector.define_method("classname", magnus::method!(InfoIdVector::classname, 0))?;
+    info_id_vector.define_method("parent_name", magnus::method!(InfoIdVector::parent_name, 0))?;
+    info_id_vector.define_method("name", magnus::method!(InfoIdVector::name, 0))?;
+    info_id_vector.define_method("info_id", magnus::method!(InfoIdVector::info_id, 0))?;
+    info_id_vector.define_method("variant", magnus::method!(InfoIdVector::variant, 0))?;
+    info_id_vector.define_method("expected_id", magnus::method!(InfoIdVector::expected_id, 0))?;
+    Ok(())
+}
+
+/// A canonical table of inputs and the IDs they generate, covering the edge cases of the
+/// algorithm. An entry must never change, since that would change the IDs of existing tests.
+pub fn gen_info_id_vectors() -> Vec<InfoIdVector> {
+    INFO_ID_VECTORS
+        .iter()
+        .map(|raw| InfoIdVector {
+            note: String::from(raw.note),
+            org_url_slug: String::from(raw.org_url_slug),
+            repo_full_name: String::from(raw.repo_full_name),
+            file: raw.file.map(String::from),
+            classname: raw.classname.map(String::from),
+            parent_name: String::from(raw.parent_name),
+            name: String::from(raw.name),
+            info_id: raw.info_id.map(String::from),
+            variant: raw.variant.map(String::from),
+            expected_id: String::from(raw.expected_id),
+        })
+        .collect()
+}
+
+struct RawInfoIdVector {
+    note: &'static str,
+    org_url_slug: &'static str,
+    repo_full_name: &'static str,
+    file: Option<&'static str>,
+    classname: Option<&'static str>,
+    parent_name: &'static str,
+    name: &'static str,
+    info_id: Option<&'static str>,
+    variant: Option<&'static str>,
+    expected_id: &'static str,
+}
+
+const INFO_ID_VECTORS: &[RawInfoIdVector] = &[
+    RawInfoIdVector {
+        note: "all fields",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "271ee02b-3e98-5e28-bf26-25ac18a878fd",
+    },
+    RawInfoIdVector {
+        note: "no file",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "75f05923-1d2f-55b8-9a73-4c622eee76fb",
+    },
+    RawInfoIdVector {
+        note: "no classname",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "b21aa26e-ed6c-50eb-83f3-018d64a3e98a",
+    },
+    RawInfoIdVector {
+        note: "no file or classname",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "90e2f3a9-8a46-53fd-9e0f-09f54bf81ade",
+    },
+    RawInfoIdVector {
+        note: "empty file is the same as no file",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some(""),
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "90e2f3a9-8a46-53fd-9e0f-09f54bf81ade",
+    },
+    RawInfoIdVector {
+        note: "empty classname is the same as no classname",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some(""),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "90e2f3a9-8a46-53fd-9e0f-09f54bf81ade",
+    },
+    RawInfoIdVector {
+        note: "empty variant is the same as no variant",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: Some(""),
+        expected_id: "271ee02b-3e98-5e28-bf26-25ac18a878fd",
+    },
+    RawInfoIdVector {
+        note: "variant",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: Some("linux"),
+        expected_id: "15d44da4-9071-574e-a155-4735787153e9",
+    },
+    RawInfoIdVector {
+        note: "other variant",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: Some("macos"),
+        expected_id: "81db336d-42ef-5746-b35b-cb891ec49289",
+    },
+    RawInfoIdVector {
+        note: "whitespace-only variant is a variant",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: Some(" "),
+        expected_id: "08a5923f-d2d5-5196-a329-3c0870e341af",
+    },
+    RawInfoIdVector {
+        note: "unicode variant",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: Some("ünïcödé"),
+        expected_id: "b9499fed-10b9-5991-acd1-ac88470cb64d",
+    },
+    RawInfoIdVector {
+        note: "whitespace-only classname",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some(" "),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "db50ce61-d307-5818-bda8-539a2fe05f41",
+    },
+    RawInfoIdVector {
+        note: "tab classname",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some("\t"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "70a03184-de9c-58a4-854f-e44b8e0d0afe",
+    },
+    RawInfoIdVector {
+        note: "whitespace-only file",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("   "),
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "2ce286bb-2420-5a7a-ba1b-0180e8fca99c",
+    },
+    RawInfoIdVector {
+        note: "leading and trailing whitespace in name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: " adds_numbers ",
+        info_id: None,
+        variant: None,
+        expected_id: "71c53337-dfd9-5e9d-9c52-27ad7dec8677",
+    },
+    RawInfoIdVector {
+        note: "empty name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "",
+        info_id: None,
+        variant: None,
+        expected_id: "031e603f-41e0-579a-bf0f-34e537f156d6",
+    },
+    RawInfoIdVector {
+        note: "empty parent name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "7c88db55-7c7d-50e8-be67-a5933d5d82b9",
+    },
+    RawInfoIdVector {
+        note: "empty everything",
+        org_url_slug: "",
+        repo_full_name: "",
+        file: None,
+        classname: None,
+        parent_name: "",
+        name: "",
+        info_id: None,
+        variant: None,
+        expected_id: "8bc0204e-7f39-573b-be9c-97d77d59bfbf",
+    },
+    RawInfoIdVector {
+        note: "unicode name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "Kaffeemaschine",
+        name: "zahle_5_€",
+        info_id: None,
+        variant: None,
+        expected_id: "0346d413-d554-58b5-b303-cfd4193d9f39",
+    },
+    RawInfoIdVector {
+        note: "japanese name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "テストスイート",
+        name: "足し算のテスト",
+        info_id: None,
+        variant: None,
+        expected_id: "11d9cf25-cff6-5753-801c-3bc444af551a",
+    },
+    RawInfoIdVector {
+        note: "emoji name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "rockets",
+        name: "🚀 launches",
+        info_id: None,
+        variant: None,
+        expected_id: "5fe12939-3872-5833-8996-443c1211e578",
+    },
+    RawInfoIdVector {
+        note: "precomposed accent",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "café",
+        info_id: None,
+        variant: None,
+        expected_id: "999691a8-24fa-5cc6-a755-18d53e065c2e",
+    },
+    RawInfoIdVector {
+        note: "decomposed accent is not normalized",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "cafe\u{301}",
+        info_id: None,
+        variant: None,
+        expected_id: "8f519535-7a3c-5ff6-8b1f-3f025a303ae9",
+    },
+    RawInfoIdVector {
+        note: "right-to-left name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "اختبار",
+        info_id: None,
+        variant: None,
+        expected_id: "06e64d1c-ed60-5479-b551-441e56f55168",
+    },
+    RawInfoIdVector {
+        note: "newline in name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "line one\nline two",
+        info_id: None,
+        variant: None,
+        expected_id: "94862373-ed18-5a4d-8649-817746e5f2e3",
+    },
+    RawInfoIdVector {
+        note: "delimiter in name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "a",
+        name: "b#c",
+        info_id: None,
+        variant: None,
+        expected_id: "e47494bb-708f-5bcb-8c46-724e1fa0ddfd",
+    },
+    RawInfoIdVector {
+        note: "delimiter in parent name collides with the above",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "a#b",
+        name: "c",
+        info_id: None,
+        variant: None,
+        expected_id: "e47494bb-708f-5bcb-8c46-724e1fa0ddfd",
+    },
+    RawInfoIdVector {
+        note: "windows file path",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src\\tests\\lib_test.rs"),
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "46da665d-65e0-59a5-b9d4-a1cf0b5ed004",
+    },
+    RawInfoIdVector {
+        note: "file path with spaces",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/my tests/lib test.rs"),
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "c6c59d1d-5811-5575-b75c-faef07d1029d",
+    },
+    RawInfoIdVector {
+        note: "very long file path",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("very_long_directory_name_00/very_long_directory_name_01/very_long_directory_name_02/very_long_directory_name_03/very_long_directory_name_04/very_long_directory_name_05/very_long_directory_name_06/very_long_directory_name_07/very_long_directory_name_08/very_long_directory_name_09/very_long_directory_name_10/very_long_directory_name_11/very_long_directory_name_12/very_long_directory_name_13/very_long_directory_name_14/very_long_directory_name_15/very_long_directory_name_16/very_long_directory_name_17/very_long_directory_name_18/very_long_directory_name_19/very_long_directory_name_20/very_long_directory_name_21/very_long_directory_name_22/very_long_directory_name_23/deeply_nested_test.rs"),
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "5a3235f4-b086-556a-bce7-d511a9c20ac5",
+    },
+    RawInfoIdVector {
+        note: "very long name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "parameterized[0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,33,34,35,36,37,38,39,40,41,42,43,44,45,46,47,48,49,50,51,52,53,54,55,56,57,58,59,60,61,62,63,64,65,66,67,68,69,70,71,72,73,74,75,76,77,78,79,80,81,82,83,84,85,86,87,88,89,90,91,92,93,94,95,96,97,98,99,100,101,102,103,104,105,106,107,108,109,110,111,112,113,114,115,116,117,118,119,120,121,122,123,124,125,126,127,128,129,130,131,132,133,134,135,136,137,138,139,140,141,142,143,144,145,146,147,148,149]",
+        info_id: None,
+        variant: None,
+        expected_id: "1a535156-56c8-5d5f-9fbd-1fd57239ae23",
+    },
+    RawInfoIdVector {
+        note: "classname equal to parent name",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some("unit"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "b05ed875-9673-5aab-9524-559347278227",
+    },
+    RawInfoIdVector {
+        note: "org is case sensitive",
+        org_url_slug: "Trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "9d1fb80e-aaaf-5c20-a4fb-533430c3626d",
+    },
+    RawInfoIdVector {
+        note: "org is case sensitive, upper",
+        org_url_slug: "TRUNK",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "11bd6156-ce60-563b-a30c-5eaac5ed3c96",
+    },
+    RawInfoIdVector {
+        note: "repo owner is case sensitive",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/Trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "defe4c58-e8ae-5d3b-804c-3cee2ee05afb",
+    },
+    RawInfoIdVector {
+        note: "repo name is case sensitive",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/Analytics-CLI",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "737dfa6e-12cc-5bbd-b60d-e2b733f71d4f",
+    },
+    RawInfoIdVector {
+        note: "repo host is case sensitive",
+        org_url_slug: "trunk",
+        repo_full_name: "GitHub.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "5354cd76-be62-5769-bc99-1a5d2fc155dc",
+    },
+    RawInfoIdVector {
+        note: "name is case sensitive",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "Adds_Numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "d2f50bec-c1bd-5edb-b451-f05d0ad8f049",
+    },
+    RawInfoIdVector {
+        note: "other host",
+        org_url_slug: "trunk",
+        repo_full_name: "gitlab.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "0eda1830-36c6-501a-b288-d41b6495f798",
+    },
+    RawInfoIdVector {
+        note: "nested group",
+        org_url_slug: "trunk",
+        repo_full_name: "gitlab.com/trunk-io/tools/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "282ede5c-062f-5b04-b722-fb30dd6c3a83",
+    },
+    RawInfoIdVector {
+        note: "self-hosted host",
+        org_url_slug: "trunk",
+        repo_full_name: "git.example.com/platform/monorepo",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: None,
+        variant: None,
+        expected_id: "8e6c58a0-195d-59a9-97a4-1c435c41de81",
+    },
+    RawInfoIdVector {
+        note: "provided info id is passed through",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: Some("8f1b8a3e-6c51-4d4b-9a5e-0c1c3f2b7d10"),
+        variant: None,
+        expected_id: "8f1b8a3e-6c51-4d4b-9a5e-0c1c3f2b7d10",
+    },
+    RawInfoIdVector {
+        note: "provided info id ignores the variant",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/lib_test.rs"),
+        classname: Some("lib_test"),
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: Some("8f1b8a3e-6c51-4d4b-9a5e-0c1c3f2b7d10"),
+        variant: Some("linux"),
+        expected_id: "8f1b8a3e-6c51-4d4b-9a5e-0c1c3f2b7d10",
+    },
+    RawInfoIdVector {
+        note: "provided info id need not be a uuid",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: Some("custom-id-123"),
+        variant: None,
+        expected_id: "custom-id-123",
+    },
+    RawInfoIdVector {
+        note: "provided info id keeps its case",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: Some("8F1B8A3E-6C51-4D4B-9A5E-0C1C3F2B7D10"),
+        variant: None,
+        expected_id: "8F1B8A3E-6C51-4D4B-9A5E-0C1C3F2B7D10",
+    },
+    RawInfoIdVector {
+        note: "empty info id is generated",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: Some(""),
+        variant: None,
+        expected_id: "90e2f3a9-8a46-53fd-9e0f-09f54bf81ade",
+    },
+    RawInfoIdVector {
+        note: "whitespace-only info id is passed through",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: None,
+        parent_name: "unit",
+        name: "adds_numbers",
+        info_id: Some(" "),
+        variant: None,
+        expected_id: " ",
+    },
+    RawInfoIdVector {
+        note: "rspec style",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("./spec/models/user_spec.rb"),
+        classname: Some("spec.models.user_spec"),
+        parent_name: "User",
+        name: "User validates the email",
+        info_id: None,
+        variant: None,
+        expected_id: "7770e441-ebca-59bb-9026-0e197549e672",
+    },
+    RawInfoIdVector {
+        note: "pytest style",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("tests/test_math.py"),
+        classname: Some("tests.test_math.TestAdd"),
+        parent_name: "pytest",
+        name: "test_adds[1-2-3]",
+        info_id: None,
+        variant: None,
+        expected_id: "f40afc85-10f8-53d3-ad7b-e4dac26384f3",
+    },
+    RawInfoIdVector {
+        note: "jest style",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: Some("src/__tests__/sum.test.ts"),
+        classname: None,
+        parent_name: "sum",
+        name: "sum adds 1 + 2 to equal 3",
+        info_id: None,
+        variant: None,
+        expected_id: "f485a79c-4a07-5bef-8c3e-34565b586705",
+    },
+    RawInfoIdVector {
+        note: "gradle style",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some("com.example.app.CalculatorTest"),
+        parent_name: "com.example.app.CalculatorTest",
+        name: "addsNumbers()",
+        info_id: None,
+        variant: None,
+        expected_id: "1cd00b01-bb5a-5305-96ee-220041b7720d",
+    },
+    RawInfoIdVector {
+        note: "go style",
+        org_url_slug: "trunk",
+        repo_full_name: "github.com/trunk-io/analytics-cli",
+        file: None,
+        classname: Some("github.com/trunk-io/analytics-cli/pkg"),
+        parent_name: "github.com/trunk-io/analytics-cli/pkg",
+        name: "TestAdds/subtest_with_spaces",
+        info_id: None,
+        variant: None,
+        expected_id: "8860269a-948c-595a-8ab4-a0f6f3f2f38a",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_match() {
+        let vectors = gen_info_id_vectors();
+        assert_eq!(vectors.len(), INFO_ID_VECTORS.len());
+        for vector in &vectors {
+            assert_eq!(vector.gen_info_id(), vector.expected_id, "{}", vector.note);
+        }
+    }
+
+    #[test]
+    fn vectors_are_distinct_cases() {
+        let vectors = gen_info_id_vectors();
+        let mut notes = vectors
+            .iter()
+            .map(|vector| vector.note.as_str())
+            .collect::<Vec<_>>();
+        notes.sort();
+        notes.dedup();
+        assert_eq!(notes.len(), vectors.len());
+    }
+}
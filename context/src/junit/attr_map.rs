@@ -0,0 +1,122 @@
+use std::{
+    fmt::{Display, Formatter, Result},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use super::parser::extra_attrs;
+
+/// Attributes that can be read from an attribute of another name
+pub const MAPPABLE_ATTRS: &[&str] = &[
+    extra_attrs::FILE,
+    extra_attrs::FILEPATH,
+    extra_attrs::LINE,
+    extra_attrs::ID,
+];
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum JunitAttrMappingError {
+    #[error("expected `<attribute>=<junit attribute>`, e.g. `file=source`, but got {0:?}")]
+    Malformed(String),
+    #[error("cannot map {0:?}, only one of {mappable} can be mapped", mappable = MAPPABLE_ATTRS.join(", "))]
+    UnknownAttr(String),
+}
+
+/// Reads `attr` from `junit_attr` when a test suite or test case has no `attr`, e.g. `file` from
+/// `source` for Catch2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JunitAttrMapping {
+    pub attr: &'static str,
+    pub junit_attr: String,
+}
+
+impl FromStr for JunitAttrMapping {
+    type Err = JunitAttrMappingError;
+
+    fn from_str(mapping: &str) -> std::result::Result<Self, Self::Err> {
+        let (attr, junit_attr) = mapping
+            .split_once('=')
+            .map(|(attr, junit_attr)| (attr.trim(), junit_attr.trim()))
+            .filter(|(attr, junit_attr)| !attr.is_empty() && !junit_attr.is_empty())
+            .ok_or_else(|| JunitAttrMappingError::Malformed(String::from(mapping)))?;
+        let attr = MAPPABLE_ATTRS
+            .iter()
+            .find(|mappable_attr| **mappable_attr == attr)
+            .ok_or_else(|| JunitAttrMappingError::UnknownAttr(String::from(attr)))?;
+        Ok(Self {
+            attr,
+            junit_attr: String::from(junit_attr),
+        })
+    }
+}
+
+impl Display for JunitAttrMapping {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}={}", self.attr, self.junit_attr)
+    }
+}
+
+/// The mappings of `--junit-attr-map`, of which the last one of an attribute wins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JunitAttrMap(Vec<JunitAttrMapping>);
+
+impl JunitAttrMap {
+    pub fn parse<T: AsRef<str>>(
+        mappings: &[T],
+    ) -> std::result::Result<Self, JunitAttrMappingError> {
+        mappings
+            .iter()
+            .map(|mapping| mapping.as_ref().parse())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map(Self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, attr: &str) -> Option<&JunitAttrMapping> {
+        self.0.iter().rev().find(|mapping| mapping.attr == attr)
+    }
+}
+
+impl From<Vec<JunitAttrMapping>> for JunitAttrMap {
+    fn from(mappings: Vec<JunitAttrMapping>) -> Self {
+        Self(mappings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_mappings() {
+        let attr_map =
+            JunitAttrMap::parse(&["file=source", " line = lineno ", "file=path"]).unwrap();
+        assert_eq!(attr_map.get(extra_attrs::FILE).unwrap().junit_attr, "path");
+        assert_eq!(
+            attr_map.get(extra_attrs::LINE).unwrap().to_string(),
+            "line=lineno"
+        );
+        assert_eq!(attr_map.get(extra_attrs::ID), None);
+        assert!(JunitAttrMap::parse::<&str>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_attrs_and_malformed_mappings() {
+        assert_eq!(
+            JunitAttrMap::parse(&["name=title"])
+                .unwrap_err()
+                .to_string(),
+            r#"cannot map "name", only one of file, filepath, line, id can be mapped"#
+        );
+        for mapping in ["file", "file=", "=source"] {
+            assert_eq!(
+                mapping.parse::<JunitAttrMapping>(),
+                Err(JunitAttrMappingError::Malformed(String::from(mapping)))
+            );
+        }
+    }
+}
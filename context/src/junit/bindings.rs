@@ -1,7 +1,8 @@
 use std::{collections::HashMap, time::Duration};
 
-use chrono::{DateTime, TimeDelta};
-use proto::test_context::test_run::{TestCaseRun, TestCaseRunStatus, TestResult};
+use chrono::{DateTime, TimeDelta, Utc};
+use prost_wkt_types::Timestamp;
+use proto::test_context::test_run::{SkipKind, TestCaseRun, TestCaseRunStatus, TestResult};
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
@@ -9,28 +10,80 @@ use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyclass_enum, gen_stub_py
 use quick_junit::{
     NonSuccessKind, Property, Report, TestCase, TestCaseStatus, TestRerun, TestSuite,
 };
+use serde::Serialize;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 use super::{
-    parser::JunitParseFlatIssue,
+    parser::{extra_attrs, JunitParseFlatIssue, JunitParser},
+    skip_reason::{extract_skip_reason, infer_skip_kind},
     validator::{
-        JunitReportValidation, JunitReportValidationFlatIssue, JunitTestSuiteValidation,
+        validate, JunitReportValidation, JunitReportValidationFlatIssue, JunitTestSuiteValidation,
         JunitValidationLevel, JunitValidationType,
     },
 };
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsParseResult {
     pub report: Option<BindingsReport>,
     pub issues: Vec<JunitParseFlatIssue>,
 }
 
+impl From<JunitParser> for BindingsParseResult {
+    /// Only has a `report` if the xml has exactly one
+    fn from(junit_parser: JunitParser) -> Self {
+        let issues = junit_parser.issues_flat();
+        let mut parsed_reports = junit_parser.into_reports();
+
+        let report = if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop())
+        {
+            Some(BindingsReport::from(parsed_report))
+        } else {
+            None
+        };
+
+        Self { report, issues }
+    }
+}
+
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
 #[derive(Clone, Debug)]
+pub struct BindingsParseAndValidateResult {
+    pub report: Option<BindingsReport>,
+    pub issues: Vec<JunitParseFlatIssue>,
+    /// `None` whenever `report` is, i.e. unless the xml has exactly one report
+    pub validation: Option<BindingsJunitReportValidation>,
+}
+
+impl From<JunitParser> for BindingsParseAndValidateResult {
+    /// Validates the parsed report before converting it, instead of converting it to a
+    /// `BindingsReport` and back like calling `junit_parse` and then `junit_validate` does.
+    fn from(junit_parser: JunitParser) -> Self {
+        let issues = junit_parser.issues_flat();
+        let mut parsed_reports = junit_parser.into_reports();
+
+        let (report, validation) =
+            if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop()) {
+                let validation = BindingsJunitReportValidation::from(validate(&parsed_report));
+                (Some(BindingsReport::from(parsed_report)), Some(validation))
+            } else {
+                (None, None)
+            };
+
+        Self {
+            report,
+            issues,
+            validation,
+        }
+    }
+}
+
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsReport {
     pub name: String,
     pub uuid: Option<String>,
@@ -92,6 +145,10 @@ impl From<TestResult> for BindingsReport {
                     .map(|tc| tc.timestamp_micros.unwrap_or(0))
                     .max();
                 let time = testcases.iter().map(|tc| tc.time.unwrap_or(0.0)).sum();
+                let suite_attr =
+                    |key: &str| testcases.iter().find_map(|tc| tc.extra.get(key)).cloned();
+                let hostname = suite_attr(extra_attrs::HOSTNAME);
+                let package = suite_attr(extra_attrs::PACKAGE);
                 BindingsTestSuite {
                     name,
                     tests,
@@ -105,6 +162,8 @@ impl From<TestResult> for BindingsReport {
                     properties: vec![],
                     system_out: None,
                     system_err: None,
+                    hostname,
+                    package,
                     extra: HashMap::new(),
                 }
             })
@@ -160,6 +219,15 @@ impl From<TestCaseRun> for BindingsTestCase {
             file,
             line,
             attempt_number,
+            hostname,
+            package,
+            skip_reason,
+            skip_kind,
+            source_file,
+            is_quarantined,
+            ci_job_url,
+            ci_workflow_name,
+            ci_external_id,
         }: TestCaseRun,
     ) -> Self {
         let started_at = started_at.unwrap_or_default();
@@ -170,6 +238,7 @@ impl From<TestCaseRun> for BindingsTestCase {
             .unwrap_or_default();
         let typed_status =
             TestCaseRunStatus::try_from(status).unwrap_or(TestCaseRunStatus::Unspecified);
+        let skipped = typed_status == TestCaseRunStatus::Skipped;
         Self {
             name,
             classname: Some(classname),
@@ -213,14 +282,44 @@ impl From<TestCaseRun> for BindingsTestCase {
             },
             system_err: None,
             system_out: None,
-            extra: HashMap::from([
-                ("id".to_string(), id.to_string()),
-                ("file".to_string(), file),
-                ("line".to_string(), line.to_string()),
-                ("attempt_number".to_string(), attempt_number.to_string()),
-                ("parent_name".to_string(), parent_name),
-            ]),
+            extra: HashMap::from_iter(
+                [
+                    ("id".to_string(), id.to_string()),
+                    ("file".to_string(), file),
+                    ("line".to_string(), line.to_string()),
+                    ("attempt_number".to_string(), attempt_number.to_string()),
+                    ("parent_name".to_string(), parent_name),
+                ]
+                .into_iter()
+                .chain(
+                    [
+                        (extra_attrs::HOSTNAME.to_string(), hostname),
+                        (extra_attrs::PACKAGE.to_string(), package),
+                        (
+                            extra_attrs::QUARANTINED.to_string(),
+                            if is_quarantined {
+                                String::from("true")
+                            } else {
+                                String::new()
+                            },
+                        ),
+                    ]
+                    .into_iter()
+                    .filter(|(_, value)| !value.is_empty()),
+                ),
+            ),
             properties: vec![],
+            skip_reason: Some(skip_reason).filter(|reason| skipped && !reason.is_empty()),
+            skip_kind: SkipKind::try_from(skip_kind)
+                .ok()
+                .filter(|_| skipped)
+                .and_then(|skip_kind| BindingsSkipKind::try_from(skip_kind).ok()),
+            source_file: Some(source_file).filter(|source_file| !source_file.is_empty()),
+            ci_job_url: Some(ci_job_url).filter(|ci_job_url| !ci_job_url.is_empty()),
+            ci_workflow_name: Some(ci_workflow_name)
+                .filter(|ci_workflow_name| !ci_workflow_name.is_empty()),
+            ci_external_id: Some(ci_external_id)
+                .filter(|ci_external_id| !ci_external_id.is_empty()),
         }
     }
 }
@@ -294,9 +393,32 @@ impl Into<Report> for BindingsReport {
     }
 }
 
+impl BindingsReport {
+    /// The test case runs of an internal bin for the report, the inverse of its `From<TestResult>`
+    /// for test cases read from an internal bin. Test cases parsed from junit belong to their test
+    /// suite, and keep the id, file and line they have in it.
+    pub fn into_test_case_runs(self) -> Vec<TestCaseRun> {
+        self.test_suites
+            .into_iter()
+            .flat_map(|test_suite| {
+                let BindingsTestSuite {
+                    name,
+                    hostname,
+                    package,
+                    test_cases,
+                    ..
+                } = test_suite;
+                test_cases.into_iter().map(move |test_case| {
+                    test_case.into_test_case_run(&name, hostname.as_deref(), package.as_deref())
+                })
+            })
+            .collect()
+    }
+}
+
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestSuite {
     pub name: String,
     pub tests: usize,
@@ -310,6 +432,9 @@ pub struct BindingsTestSuite {
     pub properties: Vec<BindingsProperty>,
     pub system_out: Option<String>,
     pub system_err: Option<String>,
+    /// e.g. the device serial of sharded tests
+    pub hostname: Option<String>,
+    pub package: Option<String>,
     extra: HashMap<String, String>,
 }
 
@@ -373,6 +498,8 @@ impl From<TestSuite> for BindingsTestSuite {
             properties: properties.into_iter().map(BindingsProperty::from).collect(),
             system_out: system_out.map(|s| s.to_string()),
             system_err: system_err.map(|s| s.to_string()),
+            hostname: extra.get(extra_attrs::HOSTNAME).map(|h| h.to_string()),
+            package: extra.get(extra_attrs::PACKAGE).map(|p| p.to_string()),
             extra: HashMap::from_iter(
                 extra
                     .into_iter()
@@ -397,8 +524,18 @@ impl Into<TestSuite> for BindingsTestSuite {
             properties,
             system_out,
             system_err,
-            extra,
+            hostname,
+            package,
+            mut extra,
         } = self;
+        for (key, value) in [
+            (extra_attrs::HOSTNAME, hostname),
+            (extra_attrs::PACKAGE, package),
+        ] {
+            if let Some(value) = value {
+                extra.insert(key.to_string(), value);
+            }
+        }
         let mut test_suite = TestSuite::new(name);
         test_suite.tests = tests;
         test_suite.disabled = disabled;
@@ -435,7 +572,7 @@ impl Into<TestSuite> for BindingsTestSuite {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsProperty {
     pub name: String,
     pub value: String,
@@ -462,7 +599,7 @@ impl Into<Property> for BindingsProperty {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestCase {
     pub name: String,
     pub classname: Option<String>,
@@ -475,6 +612,18 @@ pub struct BindingsTestCase {
     pub system_err: Option<String>,
     extra: HashMap<String, String>,
     pub properties: Vec<BindingsProperty>,
+    /// Only set for skipped test cases, see [`extract_skip_reason`]
+    pub skip_reason: Option<String>,
+    /// Only set for skipped test cases, see [`infer_skip_kind`]
+    pub skip_kind: Option<BindingsSkipKind>,
+    /// Only set for test cases parsed from an internal bin, the path of the junit file in the
+    /// bundle they were parsed from, e.g. `junit/3`
+    pub source_file: Option<String>,
+    /// Only set for test cases parsed from an internal bin, the CI job that ran them
+    pub ci_job_url: Option<String>,
+    pub ci_workflow_name: Option<String>,
+    /// Only set for test cases parsed from an internal bin, the id of the GitHub Actions job
+    pub ci_external_id: Option<String>,
 }
 
 #[cfg(feature = "pyo3")]
@@ -522,6 +671,8 @@ impl From<TestCase> for BindingsTestCase {
             ..
         }: TestCase,
     ) -> Self {
+        let skip_reason = Some(extract_skip_reason(&status)).filter(|reason| !reason.is_empty());
+        let skip_kind = BindingsSkipKind::try_from(infer_skip_kind(&status)).ok();
         Self {
             name: name.into_string(),
             classname: classname.map(|c| c.to_string()),
@@ -538,10 +689,124 @@ impl From<TestCase> for BindingsTestCase {
                     .map(|(k, v)| (k.to_string(), v.to_string())),
             ),
             properties: properties.into_iter().map(BindingsProperty::from).collect(),
+            skip_reason,
+            skip_kind,
+            source_file: None,
+            ci_job_url: None,
+            ci_workflow_name: None,
+            ci_external_id: None,
         }
     }
 }
 
+impl BindingsTestCase {
+    /// Test cases read from an internal bin, which have a `parent_name`, only take attributes of
+    /// their own rather than those their test suite took from its first test case.
+    fn into_test_case_run(
+        self,
+        suite_name: &str,
+        suite_hostname: Option<&str>,
+        suite_package: Option<&str>,
+    ) -> TestCaseRun {
+        let Self {
+            name,
+            classname,
+            timestamp,
+            timestamp_micros,
+            time,
+            status,
+            extra,
+            skip_reason,
+            skip_kind,
+            source_file,
+            ci_job_url,
+            ci_workflow_name,
+            ci_external_id,
+            ..
+        } = self;
+        let extra_attr = |key: &str| extra.get(key).cloned();
+        let parent_name = extra_attr("parent_name");
+        let suite_attr = |suite_value: Option<&str>| {
+            suite_value
+                .filter(|_| parent_name.is_none())
+                .map(String::from)
+        };
+        let status_output_message = match status.status {
+            BindingsTestCaseStatusStatus::NonSuccess => status
+                .non_success
+                .and_then(|non_success| non_success.message),
+            BindingsTestCaseStatusStatus::Skipped => {
+                status.skipped.and_then(|skipped| skipped.message)
+            }
+            _ => None,
+        };
+        let started_at = test_case_started_at(timestamp, timestamp_micros);
+        let finished_at = started_at.map(|started_at| {
+            started_at
+                + time
+                    .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    .and_then(|time| TimeDelta::from_std(time).ok())
+                    .unwrap_or_default()
+        });
+        TestCaseRun {
+            id: extra_attr(extra_attrs::ID).unwrap_or_default(),
+            name,
+            classname: classname.unwrap_or_default(),
+            file: extra_attr(extra_attrs::FILE)
+                .or_else(|| extra_attr(extra_attrs::FILE_INFERRED_FROM_CLASSNAME))
+                .unwrap_or_default(),
+            parent_name: parent_name
+                .clone()
+                .unwrap_or_else(|| suite_name.to_string()),
+            line: extra_attr(extra_attrs::LINE)
+                .and_then(|line| line.parse().ok())
+                .unwrap_or_default(),
+            status: match status.status {
+                BindingsTestCaseStatusStatus::Success => TestCaseRunStatus::Success,
+                BindingsTestCaseStatusStatus::NonSuccess => TestCaseRunStatus::Failure,
+                BindingsTestCaseStatusStatus::Skipped => TestCaseRunStatus::Skipped,
+                BindingsTestCaseStatusStatus::Unspecified => TestCaseRunStatus::Unspecified,
+            }
+            .into(),
+            attempt_number: extra_attr("attempt_number")
+                .and_then(|attempt_number| attempt_number.parse().ok())
+                .unwrap_or_default(),
+            started_at: started_at.map(Timestamp::from),
+            finished_at: finished_at.map(Timestamp::from),
+            status_output_message: status_output_message.unwrap_or_default(),
+            hostname: extra_attr(extra_attrs::HOSTNAME)
+                .or_else(|| suite_attr(suite_hostname))
+                .unwrap_or_default(),
+            package: extra_attr(extra_attrs::PACKAGE)
+                .or_else(|| suite_attr(suite_package))
+                .unwrap_or_default(),
+            skip_reason: skip_reason.unwrap_or_default(),
+            skip_kind: skip_kind.map(SkipKind::from).unwrap_or_default().into(),
+            source_file: source_file.unwrap_or_default(),
+            is_quarantined: extra_attr(extra_attrs::QUARANTINED).as_deref() == Some("true"),
+            ci_job_url: ci_job_url.unwrap_or_default(),
+            ci_workflow_name: ci_workflow_name.unwrap_or_default(),
+            ci_external_id: ci_external_id.unwrap_or_default(),
+        }
+    }
+}
+
+/// `timestamp_micros` is the whole timestamp in microseconds for test cases parsed from junit, but
+/// only its fraction of a second for those read from an internal bin.
+fn test_case_started_at(
+    timestamp: Option<i64>,
+    timestamp_micros: Option<i64>,
+) -> Option<DateTime<Utc>> {
+    match (timestamp, timestamp_micros) {
+        (Some(secs), Some(micros)) if micros.div_euclid(1_000_000) != secs => {
+            DateTime::from_timestamp(secs, u32::try_from(micros).ok()?.checked_mul(1_000)?)
+        }
+        (_, Some(micros)) => DateTime::from_timestamp_micros(micros),
+        (Some(secs), None) => DateTime::from_timestamp(secs, 0),
+        (None, None) => None,
+    }
+}
+
 impl TryInto<TestCase> for BindingsTestCase {
     type Error = ();
 
@@ -558,6 +823,13 @@ impl TryInto<TestCase> for BindingsTestCase {
             system_err,
             extra,
             properties,
+            // derived from the status
+            skip_reason: _,
+            skip_kind: _,
+            source_file: _,
+            ci_job_url: _,
+            ci_workflow_name: _,
+            ci_external_id: _,
         } = self;
         let mut test_case = TestCase::new(name, status.try_into()?);
         test_case.classname = classname.map(|c| c.into());
@@ -585,7 +857,7 @@ impl TryInto<TestCase> for BindingsTestCase {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestCaseStatus {
     pub status: BindingsTestCaseStatusStatus,
     pub success: Option<BindingsTestCaseStatusSuccess>,
@@ -670,7 +942,7 @@ impl TryInto<TestCaseStatus> for BindingsTestCaseStatus {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
 pub enum BindingsTestCaseStatusStatus {
     Success,
     NonSuccess,
@@ -680,7 +952,7 @@ pub enum BindingsTestCaseStatusStatus {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestCaseStatusSuccess {
     pub flaky_runs: Vec<BindingsTestRerun>,
 }
@@ -699,7 +971,7 @@ impl Into<TestCaseStatus> for BindingsTestCaseStatusSuccess {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestCaseStatusNonSuccess {
     pub kind: BindingsNonSuccessKind,
     pub message: Option<String>,
@@ -729,7 +1001,7 @@ impl Into<TestCaseStatus> for BindingsTestCaseStatusNonSuccess {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestCaseStatusSkipped {
     pub message: Option<String>,
     pub ty: Option<String>,
@@ -753,7 +1025,7 @@ impl Into<TestCaseStatus> for BindingsTestCaseStatusSkipped {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BindingsTestRerun {
     pub kind: BindingsNonSuccessKind,
     pub timestamp: Option<i64>,
@@ -834,7 +1106,7 @@ impl Into<TestRerun> for BindingsTestRerun {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
 pub enum BindingsNonSuccessKind {
     Failure,
     Error,
@@ -858,6 +1130,38 @@ impl Into<NonSuccessKind> for BindingsNonSuccessKind {
     }
 }
 
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum BindingsSkipKind {
+    Disabled,
+    Assumption,
+    Other,
+}
+
+impl TryFrom<SkipKind> for BindingsSkipKind {
+    type Error = ();
+
+    fn try_from(value: SkipKind) -> Result<Self, Self::Error> {
+        match value {
+            SkipKind::Disabled => Ok(BindingsSkipKind::Disabled),
+            SkipKind::Assumption => Ok(BindingsSkipKind::Assumption),
+            SkipKind::Other => Ok(BindingsSkipKind::Other),
+            SkipKind::Unspecified => Err(()),
+        }
+    }
+}
+
+impl From<BindingsSkipKind> for SkipKind {
+    fn from(value: BindingsSkipKind) -> Self {
+        match value {
+            BindingsSkipKind::Disabled => SkipKind::Disabled,
+            BindingsSkipKind::Assumption => SkipKind::Assumption,
+            BindingsSkipKind::Other => SkipKind::Other,
+        }
+    }
+}
+
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
 #[derive(Clone, Debug)]
@@ -865,6 +1169,8 @@ pub struct BindingsJunitReportValidation {
     all_issues: Vec<JunitReportValidationFlatIssue>,
     level: JunitValidationLevel,
     test_suites: Vec<JunitTestSuiteValidation>,
+    /// Only read through the getters of the Python and JS bindings
+    #[cfg_attr(not(any(feature = "pyo3", feature = "wasm")), allow(dead_code))]
     valid_test_suites: Vec<BindingsTestSuite>,
 }
 
@@ -875,6 +1181,8 @@ impl From<JunitReportValidation> for BindingsJunitReportValidation {
             level,
             test_suites,
             valid_test_suites,
+            duration_coverage: _,
+            schema_rule_checks: _,
         }: JunitReportValidation,
     ) -> Self {
         Self {
@@ -967,6 +1275,9 @@ fn parse_test_report_to_bindings() {
         started_at: Some(test_started_at.clone()),
         finished_at: Some(test_finished_at),
         status_output_message: "test_status_output_message".into(),
+        hostname: "emulator-5554".into(),
+        package: "com.example.app".into(),
+        source_file: "junit/3".into(),
         ..Default::default()
     };
 
@@ -988,6 +1299,9 @@ fn parse_test_report_to_bindings() {
         // swap them for convenience
         (test_suite1, test_suite2) = (test_suite2, test_suite1);
     }
+    assert_eq!(test_suite1.hostname, None);
+    assert_eq!(test_suite2.hostname, Some("emulator-5554".into()));
+    assert_eq!(test_suite2.package, Some("com.example.app".into()));
     let test_case1 = &test_suite1.test_cases[0];
     assert_eq!(test_case1.name, test1.name);
     assert_eq!(test_case1.classname, Some(test1.classname));
@@ -1011,6 +1325,7 @@ fn parse_test_report_to_bindings() {
         test1.attempt_number.to_string()
     );
     assert_eq!(test_case1.properties.len(), 0);
+    assert_eq!(test_case1.source_file, None);
 
     assert_eq!(test_suite2.test_cases.len(), 1);
     let test_case2 = &test_suite2.test_cases[0];
@@ -1036,6 +1351,7 @@ fn parse_test_report_to_bindings() {
         test2.attempt_number.to_string()
     );
     assert_eq!(test_case2.properties.len(), 0);
+    assert_eq!(test_case2.source_file, Some("junit/3".into()));
 
     // verify that the test report is valid
     let results = validate(&converted_bindings.clone().into());
@@ -1071,3 +1387,253 @@ fn parse_test_report_to_bindings() {
         converted_bindings.test_suites[1].tests
     );
 }
+
+#[cfg(feature = "bindings")]
+#[test]
+fn parse_and_validate_matches_parse_then_validate() {
+    use std::io::BufReader;
+
+    let xmls = [
+        r#"<testsuites name="run" tests="1" failures="1">
+            <testsuite name="suite" tests="1" failures="1" timestamp="2020-01-01T00:00:00Z">
+              <testcase name="case" file="test.py" classname="MyClass" time="1">
+                <failure message="boom"/>
+              </testcase>
+              <testcase name="no-file" classname="MyClass"/>
+            </testsuite>
+          </testsuites>"#,
+        r#"<testsuite name="suite"><testcase name="" classname="MyClass"/></testsuite>"#,
+        r#"<testsuites name="a"/><testsuites name="b"/>"#,
+        r#"<not-junit/>"#,
+    ];
+
+    for xml in xmls {
+        let mut junit_parser = JunitParser::new();
+        junit_parser.parse(BufReader::new(xml.as_bytes())).unwrap();
+        let combined = BindingsParseAndValidateResult::from(junit_parser);
+
+        let mut junit_parser = JunitParser::new();
+        junit_parser.parse(BufReader::new(xml.as_bytes())).unwrap();
+        let issues = junit_parser.issues_flat();
+        let mut parsed_reports = junit_parser.into_reports();
+        let report = if let (1, Some(parsed_report)) = (parsed_reports.len(), parsed_reports.pop())
+        {
+            Some(BindingsReport::from(parsed_report))
+        } else {
+            None
+        };
+        let validation = report
+            .clone()
+            .map(|report| BindingsJunitReportValidation::from(validate(&report.into())));
+
+        assert_eq!(combined.issues, issues, "{}", xml);
+        assert_eq!(
+            combined.report.as_ref().map(|report| report.tests),
+            report.as_ref().map(|report| report.tests),
+            "{}",
+            xml
+        );
+        assert_eq!(
+            combined
+                .validation
+                .as_ref()
+                .map(|validation| (validation.max_level(), validation.all_issues_owned())),
+            validation
+                .as_ref()
+                .map(|validation| (validation.max_level(), validation.all_issues_owned())),
+            "{}",
+            xml
+        );
+    }
+}
+
+#[cfg(feature = "bindings")]
+#[test]
+fn parse_surefire_reruns_to_bindings() {
+    use std::io::BufReader;
+
+    use test_utils::inputs::get_test_file_path;
+
+    let junit_xml = std::fs::read(get_test_file_path(
+        "test_fixtures/junit_surefire_reruns.xml",
+    ))
+    .unwrap();
+    let mut junit_parser = JunitParser::new();
+    junit_parser
+        .parse(BufReader::new(junit_xml.as_slice()))
+        .unwrap();
+    let report = BindingsReport::from(junit_parser.into_reports().pop().unwrap());
+    let test_cases = &report.test_suites[0].test_cases;
+
+    let flaky_runs = &test_cases[0].status.success.as_ref().unwrap().flaky_runs;
+    let reruns = &test_cases[1].status.non_success.as_ref().unwrap().reruns;
+    let summaries: Vec<_> = flaky_runs
+        .iter()
+        .chain(reruns)
+        .map(|rerun| {
+            (
+                rerun.kind,
+                rerun.time,
+                rerun.message.as_deref(),
+                rerun.ty.as_deref(),
+                rerun.system_out.as_deref(),
+                rerun.system_err.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summaries,
+        vec![
+            (
+                BindingsNonSuccessKind::Failure,
+                Some(1.204),
+                Some("expected: <APPROVED> but was: <PENDING>"),
+                Some("org.opentest4j.AssertionFailedError"),
+                Some("Charging card ending 4242\nGateway responded PENDING"),
+                Some("WARN gateway latency 1180ms"),
+            ),
+            (
+                BindingsNonSuccessKind::Error,
+                Some(2.001),
+                Some("Read timed out"),
+                Some("java.net.SocketTimeoutException"),
+                Some("Charging card ending 4242"),
+                None,
+            ),
+            (
+                BindingsNonSuccessKind::Failure,
+                Some(0.147),
+                Some("expected: <204> but was: <409>"),
+                Some("org.opentest4j.AssertionFailedError"),
+                Some("Refunding charge ch_2"),
+                None,
+            ),
+            (
+                BindingsNonSuccessKind::Error,
+                Some(0.051),
+                Some("Connection reset"),
+                Some("java.net.SocketException"),
+                None,
+                Some("ERROR gateway connection reset by peer"),
+            ),
+        ]
+    );
+    for rerun in flaky_runs.iter().chain(reruns) {
+        assert!(rerun
+            .stack_trace
+            .as_ref()
+            .unwrap()
+            .contains(rerun.ty.as_deref().unwrap()));
+    }
+
+    // and back, as the bindings are used to write junit files
+    let round_tripped: Report = report.clone().into();
+    let round_tripped = BindingsReport::from(round_tripped);
+    let round_tripped_test_cases = &round_tripped.test_suites[0].test_cases;
+    assert_eq!(
+        serde_json::to_value(&round_tripped_test_cases[0].status).unwrap(),
+        serde_json::to_value(&test_cases[0].status).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&round_tripped_test_cases[1].status).unwrap(),
+        serde_json::to_value(&test_cases[1].status).unwrap()
+    );
+}
+
+#[cfg(feature = "bindings")]
+#[test]
+fn into_test_case_runs_inverts_from_test_result() {
+    use std::io::BufReader;
+
+    use prost_wkt_types::Timestamp;
+
+    let test_case_runs = vec![
+        TestCaseRun {
+            id: "test_id1".into(),
+            name: "test_name1".into(),
+            classname: "test_classname".into(),
+            file: "test_file".into(),
+            parent_name: "test_parent_name".into(),
+            line: 1,
+            status: TestCaseRunStatus::Failure.into(),
+            attempt_number: 2,
+            started_at: Some(Timestamp {
+                seconds: 1000,
+                nanos: 250_000_000,
+            }),
+            finished_at: Some(Timestamp {
+                seconds: 1001,
+                nanos: 750_000_000,
+            }),
+            status_output_message: "expected 1, got 2".into(),
+            hostname: "emulator-5554".into(),
+            package: "com.example.app".into(),
+            source_file: "junit/3".into(),
+            is_quarantined: true,
+            ci_job_url: "https://github.com/trunk-io/analytics-cli/actions/runs/1/job/2".into(),
+            ci_workflow_name: "CI".into(),
+            ci_external_id: "2".into(),
+            ..Default::default()
+        },
+        TestCaseRun {
+            id: "test_id2".into(),
+            name: "test_name2".into(),
+            classname: "test_classname".into(),
+            parent_name: "test_parent_name".into(),
+            status: TestCaseRunStatus::Skipped.into(),
+            started_at: Some(Timestamp {
+                seconds: 1000,
+                nanos: 0,
+            }),
+            finished_at: Some(Timestamp {
+                seconds: 1000,
+                nanos: 0,
+            }),
+            status_output_message: "not on CI".into(),
+            skip_reason: "not on CI".into(),
+            skip_kind: SkipKind::Disabled.into(),
+            ..Default::default()
+        },
+    ];
+    let test_result = TestResult {
+        test_case_runs: test_case_runs.clone(),
+        ..Default::default()
+    };
+    assert_eq!(
+        BindingsReport::from(test_result).into_test_case_runs(),
+        test_case_runs
+    );
+
+    // test cases parsed from junit belong to their test suite
+    let xml = r#"<testsuites name="run">
+        <testsuite name="suite" hostname="emulator-5554">
+          <testcase name="case" id="abc" file="test.py" line="3" classname="MyClass" time="1.5"
+            timestamp="2020-01-01T00:00:00.5Z">
+            <failure message="boom"/>
+          </testcase>
+        </testsuite>
+      </testsuites>"#;
+    let mut junit_parser = JunitParser::new();
+    junit_parser.parse(BufReader::new(xml.as_bytes())).unwrap();
+    let report = BindingsReport::from(junit_parser.into_reports().pop().unwrap());
+    let started_at = DateTime::parse_from_rfc3339("2020-01-01T00:00:00.5Z")
+        .unwrap()
+        .to_utc();
+    assert_eq!(
+        report.into_test_case_runs(),
+        vec![TestCaseRun {
+            id: "abc".into(),
+            name: "case".into(),
+            classname: "MyClass".into(),
+            file: "test.py".into(),
+            parent_name: "suite".into(),
+            line: 3,
+            status: TestCaseRunStatus::Failure.into(),
+            started_at: Some(started_at.into()),
+            finished_at: Some((started_at + TimeDelta::milliseconds(1500)).into()),
+            status_output_message: "boom".into(),
+            hostname: "emulator-5554".into(),
+            ..Default::default()
+        }]
+    );
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use quick_junit::{NonSuccessKind, Report, TestCase, TestCaseStatus};
+use serde::Serialize;
+
+use super::parser::extra_attrs;
+
+const DIFFED_EXTRA_ATTRS: &[&str] = &[
+    extra_attrs::FILE,
+    extra_attrs::FILEPATH,
+    extra_attrs::LINE,
+    extra_attrs::ID,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct JunitDiffTestKey {
+    pub classname: String,
+    pub name: String,
+}
+
+impl std::fmt::Display for JunitDiffTestKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.classname.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}::{}", self.classname, self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JunitDiffStatusChange {
+    pub test: JunitDiffTestKey,
+    pub base: String,
+    pub head: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JunitDiffDurationChange {
+    pub test: JunitDiffTestKey,
+    pub base_ms: u128,
+    pub head_ms: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JunitDiffAttributeChange {
+    pub test: JunitDiffTestKey,
+    pub attribute: String,
+    pub base: Option<String>,
+    pub head: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct JunitDiff {
+    pub added: Vec<JunitDiffTestKey>,
+    pub removed: Vec<JunitDiffTestKey>,
+    pub status_changes: Vec<JunitDiffStatusChange>,
+    pub duration_changes: Vec<JunitDiffDurationChange>,
+    pub attribute_changes: Vec<JunitDiffAttributeChange>,
+    /// Keys shared by more than one test case of the base reports, only the first of which is
+    /// compared
+    pub base_duplicates: Vec<JunitDiffTestKey>,
+    /// Keys shared by more than one test case of the head reports, only the first of which is
+    /// compared
+    pub head_duplicates: Vec<JunitDiffTestKey>,
+}
+
+impl JunitDiff {
+    /// Duplicate keys are not differences, since they may be the same in both reports
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.status_changes.is_empty()
+            && self.duration_changes.is_empty()
+            && self.attribute_changes.is_empty()
+    }
+}
+
+/// Compares the test cases of two sets of reports, keyed by classname and name.
+/// Duration changes are only reported when they differ by more than `duration_threshold`.
+/// Test cases that share a key with an earlier one are reported as duplicates instead of compared.
+pub fn diff_reports(
+    base_reports: &[Report],
+    head_reports: &[Report],
+    duration_threshold: Duration,
+) -> JunitDiff {
+    let (base_test_cases, base_duplicates) = collect_test_cases(base_reports);
+    let (head_test_cases, head_duplicates) = collect_test_cases(head_reports);
+
+    let mut diff = JunitDiff {
+        removed: base_test_cases
+            .keys()
+            .filter(|key| !head_test_cases.contains_key(*key))
+            .cloned()
+            .collect(),
+        added: head_test_cases
+            .keys()
+            .filter(|key| !base_test_cases.contains_key(*key))
+            .cloned()
+            .collect(),
+        base_duplicates,
+        head_duplicates,
+        ..Default::default()
+    };
+
+    for (key, base_test_case) in &base_test_cases {
+        let Some(head_test_case) = head_test_cases.get(key) else {
+            continue;
+        };
+
+        let base_status = status_name(&base_test_case.status);
+        let head_status = status_name(&head_test_case.status);
+        if base_status != head_status {
+            diff.status_changes.push(JunitDiffStatusChange {
+                test: key.clone(),
+                base: base_status.to_string(),
+                head: head_status.to_string(),
+            });
+        }
+
+        if let (Some(base_time), Some(head_time)) = (base_test_case.time, head_test_case.time) {
+            let delta = base_time.abs_diff(head_time);
+            if delta > duration_threshold {
+                diff.duration_changes.push(JunitDiffDurationChange {
+                    test: key.clone(),
+                    base_ms: base_time.as_millis(),
+                    head_ms: head_time.as_millis(),
+                });
+            }
+        }
+
+        for attribute in DIFFED_EXTRA_ATTRS {
+            let base_value = extra_attr(base_test_case, attribute);
+            let head_value = extra_attr(head_test_case, attribute);
+            if base_value != head_value {
+                diff.attribute_changes.push(JunitDiffAttributeChange {
+                    test: key.clone(),
+                    attribute: attribute.to_string(),
+                    base: base_value,
+                    head: head_value,
+                });
+            }
+        }
+    }
+
+    diff
+}
+
+/// The test cases of `reports` by key, and the keys shared by more than one test case.
+fn collect_test_cases(
+    reports: &[Report],
+) -> (BTreeMap<JunitDiffTestKey, &TestCase>, Vec<JunitDiffTestKey>) {
+    let mut test_cases = BTreeMap::new();
+    let mut duplicates = BTreeSet::new();
+    for test_case in reports
+        .iter()
+        .flat_map(|report| &report.test_suites)
+        .flat_map(|test_suite| &test_suite.test_cases)
+    {
+        let key = JunitDiffTestKey {
+            classname: test_case
+                .classname
+                .as_ref()
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            name: test_case.name.to_string(),
+        };
+        match test_cases.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(test_case);
+            }
+            Entry::Occupied(entry) => {
+                duplicates.insert(entry.key().clone());
+            }
+        }
+    }
+    (test_cases, duplicates.into_iter().collect())
+}
+
+fn status_name(status: &TestCaseStatus) -> &'static str {
+    match status {
+        TestCaseStatus::Success { .. } => "success",
+        TestCaseStatus::NonSuccess {
+            kind: NonSuccessKind::Failure,
+            ..
+        } => "failure",
+        TestCaseStatus::NonSuccess {
+            kind: NonSuccessKind::Error,
+            ..
+        } => "error",
+        TestCaseStatus::Skipped { .. } => "skipped",
+    }
+}
+
+fn extra_attr(test_case: &TestCase, attribute: &str) -> Option<String> {
+    test_case
+        .extra
+        .get(attribute)
+        .map(|value| value.to_string())
+}
@@ -0,0 +1,413 @@
+use std::{borrow::Cow, io::Read};
+
+use encoding_rs::{Decoder, DecoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+const XML_DECLARATION_START: &[u8] = b"<?xml";
+const XML_DECLARATION_END: &[u8] = b"?>";
+const XML_DECLARATION_ENCODING: &[u8] = b"encoding";
+const XML_DECLARATION_MAX_LEN: usize = 1_024;
+/// Bytes of the start of the XML the encoding can be detected from, as if from all of it
+const ENCODING_DETECTION_LEN: usize = XML_DECLARATION_START.len() + XML_DECLARATION_MAX_LEN;
+
+/// Junit XML transcoded to UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedXml {
+    pub xml: String,
+    pub encoding: &'static Encoding,
+    /// Number of undecodable byte sequences replaced with U+FFFD
+    pub replacements: usize,
+}
+
+/// Transcodes junit XML to UTF-8, stripping any BOM.
+///
+/// The encoding is taken from the BOM (UTF-8, UTF-16LE or UTF-16BE), then from the byte pattern
+/// of a UTF-16 `<?` without a BOM, then from the `encoding` of the XML declaration, and otherwise
+/// defaults to UTF-8.
+pub fn decode_xml(bytes: Vec<u8>) -> DecodedXml {
+    let (encoding, bom_len) = detect_encoding(&bytes);
+
+    if encoding == UTF_8 {
+        // avoid copying valid UTF-8, which nearly every junit file is
+        let bytes = if bom_len > 0 {
+            bytes[bom_len..].to_vec()
+        } else {
+            bytes
+        };
+        return match String::from_utf8(bytes) {
+            Ok(xml) => DecodedXml {
+                xml,
+                encoding,
+                replacements: 0,
+            },
+            Err(e) => decode_with_replacements(encoding, e.as_bytes()),
+        };
+    }
+
+    decode_with_replacements(encoding, &bytes[bom_len..])
+}
+
+/// Like [`decode_xml`] for XML held in memory, e.g. a memory-mapped file, which is borrowed
+/// instead of copied when it is valid UTF-8.
+///
+/// Returns the XML and the number of undecodable byte sequences replaced with U+FFFD.
+pub fn decode_xml_slice(bytes: &[u8]) -> (Cow<'_, str>, usize) {
+    let (encoding, bom_len) = detect_encoding(bytes);
+    if encoding == UTF_8 {
+        if let Ok(xml) = std::str::from_utf8(&bytes[bom_len..]) {
+            return (Cow::Borrowed(xml), 0);
+        }
+    }
+    let decoded = decode_with_replacements(encoding, &bytes[bom_len..]);
+    (Cow::Owned(decoded.xml), decoded.replacements)
+}
+
+fn detect_encoding(bytes: &[u8]) -> (&'static Encoding, usize) {
+    if let Some(encoding_with_bom_len) = Encoding::for_bom(bytes) {
+        return encoding_with_bom_len;
+    }
+
+    match bytes {
+        [b'<', 0, b'?', 0, ..] => return (UTF_16LE, 0),
+        [0, b'<', 0, b'?', ..] => return (UTF_16BE, 0),
+        _ => (),
+    };
+
+    // a declared encoding that is not ASCII compatible (e.g. UTF-16) can't be right if we could read
+    // the declaration as ASCII
+    let encoding = declared_encoding(bytes)
+        .and_then(Encoding::for_label)
+        .filter(|encoding| encoding.is_ascii_compatible())
+        .unwrap_or(UTF_8);
+    (encoding, 0)
+}
+
+/// Reads the label of `encoding="..."` in the XML declaration, if any.
+fn declared_encoding(bytes: &[u8]) -> Option<&[u8]> {
+    let declaration = bytes.strip_prefix(XML_DECLARATION_START)?;
+    let declaration = &declaration[..declaration.len().min(XML_DECLARATION_MAX_LEN)];
+    let declaration = &declaration[..find(declaration, XML_DECLARATION_END)?];
+
+    let encoding_start =
+        find(declaration, XML_DECLARATION_ENCODING)? + XML_DECLARATION_ENCODING.len();
+    let value = declaration[encoding_start..].trim_ascii_start();
+    let value = value.strip_prefix(b"=")?.trim_ascii_start();
+    let (&quote, value) = value.split_first()?;
+    if quote != b'"' && quote != b'\'' {
+        return None;
+    }
+    let value_end = value.iter().position(|&b| b == quote)?;
+    Some(&value[..value_end])
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn decode_with_replacements(encoding: &'static Encoding, bytes: &[u8]) -> DecodedXml {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut xml = String::new();
+    let replacements = decode_into(&mut decoder, bytes, &mut xml, true);
+    DecodedXml {
+        xml,
+        encoding,
+        replacements,
+    }
+}
+
+/// Appends `bytes` transcoded to UTF-8 to `xml`, returning the number of undecodable byte
+/// sequences replaced with U+FFFD. Unless `last`, a sequence cut off at the end of `bytes` is kept
+/// by the decoder for the next call.
+fn decode_into(decoder: &mut Decoder, bytes: &[u8], xml: &mut String, last: bool) -> usize {
+    let mut replacements = 0;
+    let mut remaining = bytes;
+    loop {
+        if let Some(max_len) = decoder.max_utf8_buffer_length_without_replacement(remaining.len()) {
+            xml.reserve(max_len);
+        }
+        let (result, read) = decoder.decode_to_string_without_replacement(remaining, xml, last);
+        remaining = &remaining[read..];
+        match result {
+            DecoderResult::InputEmpty => break,
+            DecoderResult::OutputFull => continue,
+            DecoderResult::Malformed(..) => {
+                xml.push(char::REPLACEMENT_CHARACTER);
+                replacements += 1;
+            }
+        }
+    }
+    replacements
+}
+
+/// Transcodes junit XML to UTF-8 as it arrives in chunks, to the same result as [`decode_xml`] on
+/// all of it. The start of the XML is held back until the encoding can be detected from it.
+#[derive(Default)]
+pub struct XmlStreamDecoder {
+    head: Vec<u8>,
+    decoder: Option<Decoder>,
+    bom_len: usize,
+    replacements: usize,
+}
+
+impl XmlStreamDecoder {
+    /// Appends the UTF-8 of as much of `bytes` as can be decoded yet to `xml`
+    pub fn decode(&mut self, bytes: &[u8], xml: &mut String) {
+        match self.decoder.as_mut() {
+            Some(decoder) => self.replacements += decode_into(decoder, bytes, xml, false),
+            None => {
+                self.head.extend_from_slice(bytes);
+                if self.head.len() >= ENCODING_DETECTION_LEN {
+                    self.start_decoding(xml);
+                }
+            }
+        }
+    }
+
+    /// Appends the UTF-8 of the rest of the XML to `xml`, returning the number of undecodable
+    /// byte sequences replaced with U+FFFD in all of it
+    pub fn finish(&mut self, xml: &mut String) -> usize {
+        if self.decoder.is_none() {
+            self.start_decoding(xml);
+        }
+        if let Some(decoder) = self.decoder.as_mut() {
+            self.replacements += decode_into(decoder, &[], xml, true);
+        }
+        self.replacements
+    }
+
+    /// Whether the XML decoded so far differs from its bytes, i.e. it is not UTF-8, had a BOM or
+    /// had undecodable bytes
+    pub fn transcoded(&self) -> bool {
+        self.decoder
+            .as_ref()
+            .is_some_and(|decoder| decoder.encoding() != UTF_8)
+            || self.bom_len > 0
+            || self.replacements > 0
+    }
+
+    fn start_decoding(&mut self, xml: &mut String) {
+        let head = std::mem::take(&mut self.head);
+        let (encoding, bom_len) = detect_encoding(&head);
+        let mut decoder = encoding.new_decoder_without_bom_handling();
+        self.replacements += decode_into(&mut decoder, &head[bom_len..], xml, false);
+        self.decoder = Some(decoder);
+        self.bom_len = bom_len;
+    }
+}
+
+const XML_DECODING_READER_CHUNK_LEN: usize = 8 * 1024;
+
+/// Reads junit XML as UTF-8, transcoding it with an [`XmlStreamDecoder`] a chunk at a time as it is
+/// read from `inner`, so that a large file is never held in memory.
+pub struct XmlDecodingReader<R: Read> {
+    inner: R,
+    decoder: XmlStreamDecoder,
+    chunk: Vec<u8>,
+    /// Decoded XML of the last chunk, of which `decoded[decoded_start..]` is yet to be read
+    decoded: String,
+    decoded_start: usize,
+    bytes_in: u64,
+    finished: bool,
+}
+
+impl<R: Read> XmlDecodingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decoder: Default::default(),
+            chunk: vec![0; XML_DECODING_READER_CHUNK_LEN],
+            decoded: Default::default(),
+            decoded_start: 0,
+            bytes_in: 0,
+            finished: false,
+        }
+    }
+
+    /// Number of bytes read from `inner` so far
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in
+    }
+
+    pub fn decoder(&self) -> &XmlStreamDecoder {
+        &self.decoder
+    }
+}
+
+impl<R: Read> Read for XmlDecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.decoded_start == self.decoded.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.decoded.clear();
+            self.decoded_start = 0;
+            let read = self.inner.read(&mut self.chunk)?;
+            if read == 0 {
+                self.decoder.finish(&mut self.decoded);
+                self.finished = true;
+            } else {
+                self.bytes_in += read as u64;
+                self.decoder.decode(&self.chunk[..read], &mut self.decoded);
+            }
+        }
+
+        let decoded = &self.decoded.as_bytes()[self.decoded_start..];
+        let len = decoded.len().min(buf.len());
+        buf[..len].copy_from_slice(&decoded[..len]);
+        self.decoded_start += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::WINDOWS_1252;
+
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0"?><testsuites name="Café €"/>"#;
+
+    #[test]
+    fn leaves_utf8_alone() {
+        let decoded = decode_xml(XML.as_bytes().to_vec());
+        assert_eq!(decoded.xml, XML);
+        assert_eq!(decoded.encoding, UTF_8);
+        assert_eq!(decoded.replacements, 0);
+    }
+
+    #[test]
+    fn borrows_utf8_slices() {
+        let utf8_with_bom = [b"\xEF\xBB\xBF".as_slice(), XML.as_bytes()].concat();
+        let (xml, replacements) = decode_xml_slice(&utf8_with_bom);
+        assert!(matches!(xml, Cow::Borrowed(XML)));
+        assert_eq!(replacements, 0);
+
+        let invalid_utf8 = [XML.as_bytes(), b"\xFF"].concat();
+        let (xml, replacements) = decode_xml_slice(&invalid_utf8);
+        assert_eq!(xml, format!("{XML}\u{FFFD}"));
+        assert_eq!(replacements, 1);
+
+        let utf16le = XML
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(decode_xml_slice(&utf16le).0, XML);
+    }
+
+    #[test]
+    fn strips_boms() {
+        let utf8_with_bom = [b"\xEF\xBB\xBF".as_slice(), XML.as_bytes()].concat();
+        assert_eq!(decode_xml(utf8_with_bom).xml, XML);
+
+        let utf16le_with_bom = [0xFF, 0xFE]
+            .into_iter()
+            .chain(XML.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let decoded = decode_xml(utf16le_with_bom);
+        assert_eq!(decoded.xml, XML);
+        assert_eq!(decoded.encoding, UTF_16LE);
+
+        let utf16be_with_bom = [0xFE, 0xFF]
+            .into_iter()
+            .chain(XML.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        let decoded = decode_xml(utf16be_with_bom);
+        assert_eq!(decoded.xml, XML);
+        assert_eq!(decoded.encoding, UTF_16BE);
+    }
+
+    #[test]
+    fn detects_utf16_without_bom() {
+        let utf16le = XML.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode_xml(utf16le).xml, XML);
+    }
+
+    #[test]
+    fn honors_declared_encoding() {
+        let xml = r#"<?xml version="1.0" encoding='windows-1252'?><testsuites name="Café €"/>"#;
+        let (windows_1252, ..) = WINDOWS_1252.encode(xml);
+        let decoded = decode_xml(windows_1252.into_owned());
+        assert_eq!(decoded.xml, xml);
+        assert_eq!(decoded.encoding, WINDOWS_1252);
+
+        // ISO-8859-1 is decoded as its superset, windows-1252
+        let xml = r#"<?xml version="1.0" encoding = "ISO-8859-1" ?><testsuites name="Café"/>"#;
+        let (iso_8859_1, ..) = WINDOWS_1252.encode(xml);
+        assert_eq!(decode_xml(iso_8859_1.into_owned()).xml, xml);
+
+        // the declaration can't be UTF-16 if it could be read as ASCII
+        let xml = r#"<?xml version="1.0" encoding="UTF-16"?><testsuites name="Café"/>"#;
+        let decoded = decode_xml(xml.as_bytes().to_vec());
+        assert_eq!(decoded.xml, xml);
+        assert_eq!(decoded.encoding, UTF_8);
+    }
+
+    fn decode_in_chunks(bytes: &[u8], chunk_len: usize) -> (String, usize) {
+        let mut decoder = XmlStreamDecoder::default();
+        let mut xml = String::new();
+        for chunk in bytes.chunks(chunk_len) {
+            decoder.decode(chunk, &mut xml);
+        }
+        let replacements = decoder.finish(&mut xml);
+        (xml, replacements)
+    }
+
+    #[test]
+    fn decodes_in_chunks() {
+        let utf16le_with_bom: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(XML.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let xml = r#"<?xml version="1.0" encoding='windows-1252'?><testsuites name="Café €"/>"#;
+        let (windows_1252, ..) = WINDOWS_1252.encode(xml);
+        let undecodable = [b"<testsuites name=\"".as_slice(), b"\xFFCaf\xC3", b"\"/>"].concat();
+        let long_utf8 = format!("{}{}", XML, "€".repeat(2_000));
+
+        for bytes in [
+            XML.as_bytes(),
+            &utf16le_with_bom,
+            &windows_1252,
+            &undecodable,
+            long_utf8.as_bytes(),
+        ] {
+            let decoded = decode_xml(bytes.to_vec());
+            for chunk_len in [1, 2, 3, 7, 1_000, bytes.len()] {
+                assert_eq!(
+                    decode_in_chunks(bytes, chunk_len),
+                    (decoded.xml.clone(), decoded.replacements)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reads_decoded() {
+        let utf16le_with_bom: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(XML.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let long_utf8 = format!("{}{}", XML, "€".repeat(20_000));
+
+        for (bytes, transcoded) in [
+            (XML.as_bytes(), false),
+            (&utf16le_with_bom, true),
+            (long_utf8.as_bytes(), false),
+        ] {
+            let mut reader = XmlDecodingReader::new(bytes);
+            let mut xml = String::new();
+            reader.read_to_string(&mut xml).unwrap();
+            assert_eq!(xml, decode_xml(bytes.to_vec()).xml);
+            assert_eq!(reader.bytes_in(), bytes.len() as u64);
+            assert_eq!(reader.decoder().transcoded(), transcoded);
+        }
+    }
+
+    #[test]
+    fn replaces_undecodable_bytes() {
+        let bytes = [b"<testsuites name=\"".as_slice(), b"\xFFCaf\xC3", b"\"/>"].concat();
+        let decoded = decode_xml(bytes);
+        assert_eq!(decoded.xml, "<testsuites name=\"\u{FFFD}Caf\u{FFFD}\"/>");
+        assert_eq!(decoded.replacements, 2);
+    }
+}
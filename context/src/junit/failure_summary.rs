@@ -0,0 +1,145 @@
+use quick_junit::TestCaseStatus;
+
+use super::test_name::{skip_escape_sequence, ESCAPE};
+
+/// Some frameworks put whole HTML pages or stack traces in their failures, which are of no use past
+/// the first few lines
+pub const DEFAULT_FAILURE_SUMMARY_MAX_LEN: usize = 4 * 1024;
+const TRUNCATION_MARKER: &str = "…";
+
+/// Summarizes the failure of a test case, for everywhere failures are sent or stored rather than
+/// uploaded as part of the junit. See [`extract_failure_summary_with_max_len`].
+pub fn extract_failure_summary(status: &TestCaseStatus) -> String {
+    extract_failure_summary_with_max_len(status, DEFAULT_FAILURE_SUMMARY_MAX_LEN)
+}
+
+/// Takes the failure's message, or the first non-empty line of its description when it has no
+/// message, without ANSI escape sequences and truncated to at most `max_len` bytes.
+///
+/// Returns an empty string for test cases that didn't fail.
+pub fn extract_failure_summary_with_max_len(status: &TestCaseStatus, max_len: usize) -> String {
+    let TestCaseStatus::NonSuccess {
+        message,
+        description,
+        ..
+    } = status
+    else {
+        return String::new();
+    };
+
+    let message = message
+        .as_ref()
+        .map(|message| strip_ansi_escape_sequences(message.as_str()))
+        .filter(|message| !message.trim().is_empty());
+    let summary = message.or_else(|| {
+        description.as_ref().and_then(|description| {
+            strip_ansi_escape_sequences(description.as_str())
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(String::from)
+        })
+    });
+    summary
+        .map(|summary| truncate_failure_summary(summary.trim(), max_len))
+        .unwrap_or_default()
+}
+
+/// Caps a failure message that was already extracted, e.g. the output of a test case run.
+pub fn summarize_failure_message(message: &str, max_len: usize) -> String {
+    truncate_failure_summary(strip_ansi_escape_sequences(message).trim(), max_len)
+}
+
+fn strip_ansi_escape_sequences(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ESCAPE {
+            skip_escape_sequence(&mut chars);
+            continue;
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+/// Keeps the marker within `max_len` too, unless `max_len` can't even fit the marker.
+fn truncate_failure_summary(summary: &str, max_len: usize) -> String {
+    if summary.len() <= max_len {
+        return String::from(summary);
+    }
+    let end = summary.floor_char_boundary(max_len.saturating_sub(TRUNCATION_MARKER.len()));
+    format!("{}{}", &summary[..end], TRUNCATION_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_junit::NonSuccessKind;
+
+    use super::*;
+
+    fn failure(message: Option<&str>, description: Option<&str>) -> TestCaseStatus {
+        let mut status = TestCaseStatus::non_success(NonSuccessKind::Failure);
+        if let Some(message) = message {
+            status.set_message(message);
+        }
+        if let Some(description) = description {
+            status.set_description(description);
+        }
+        status
+    }
+
+    #[test]
+    fn prefers_message_over_description() {
+        assert_eq!(
+            extract_failure_summary(&failure(Some("expected 1, got 2"), Some("Traceback"))),
+            "expected 1, got 2"
+        );
+        assert_eq!(
+            extract_failure_summary(&failure(Some(" "), Some("\n\n  first line\nsecond line"))),
+            "first line"
+        );
+        assert_eq!(extract_failure_summary(&failure(None, None)), "");
+        assert_eq!(extract_failure_summary(&TestCaseStatus::success()), "");
+    }
+
+    #[test]
+    fn strips_ansi_from_pytest_output() {
+        let description = "\u{1b}[1m\u{1b}[31mE       AssertionError: assert 1 == 2\u{1b}[0m\n\u{1b}[1m\u{1b}[31mE        +  where 1 = foo()\u{1b}[0m";
+        assert_eq!(
+            extract_failure_summary(&failure(None, Some(description))),
+            "E       AssertionError: assert 1 == 2"
+        );
+        assert_eq!(
+            extract_failure_summary(&failure(Some("\u{1b}[31massert 1 == 2\u{1b}[0m"), None)),
+            "assert 1 == 2"
+        );
+    }
+
+    #[test]
+    fn truncates_giant_java_stack_trace() {
+        let mut message = String::from(
+            "org.opentest4j.AssertionFailedError: expected: <true> but was: <false>\n",
+        );
+        for i in 0..50_000 {
+            message.push_str(&format!(
+                "\tat com.example.FooTest.frame{}(FooTest.java:{})\n",
+                i, i
+            ));
+        }
+
+        let summary = extract_failure_summary(&failure(Some(&message), None));
+        assert_eq!(summary.len(), DEFAULT_FAILURE_SUMMARY_MAX_LEN);
+        assert!(summary.starts_with("org.opentest4j.AssertionFailedError: expected: <true>"));
+        assert!(summary.ends_with(TRUNCATION_MARKER));
+
+        let summary = extract_failure_summary_with_max_len(&failure(Some(&message), None), 40);
+        assert_eq!(summary, "org.opentest4j.AssertionFailedError: …");
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        assert_eq!(summarize_failure_message("ééééé", 6), "é…");
+        assert_eq!(summarize_failure_message("ééééé", 10), "ééééé");
+    }
+}
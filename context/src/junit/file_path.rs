@@ -0,0 +1,143 @@
+const WINDOWS_SEPARATOR: char = '\\';
+const SEPARATOR: char = '/';
+const TEST_FILE_EXTENSIONS: &[&str] = &[
+    ".ts", ".tsx", ".mts", ".cts", ".js", ".jsx", ".mjs", ".cjs", ".py", ".rb", ".go",
+];
+
+/// Normalizes a test file path reported by a junit `file` or `filepath` attribute.
+///
+/// Windows separators are converted to `/`, and absolute Windows paths inside `repo_root` are made
+/// relative to it. Absolute paths outside of `repo_root` are left as is.
+pub fn normalize_file_path<T: AsRef<str>>(file_path: &str, repo_root: Option<T>) -> String {
+    let file_path = file_path.replace(WINDOWS_SEPARATOR, &SEPARATOR.to_string());
+    if !is_windows_absolute_path(&file_path) {
+        return file_path;
+    }
+
+    let Some(repo_root) = repo_root else {
+        return file_path;
+    };
+    let repo_root = repo_root
+        .as_ref()
+        .replace(WINDOWS_SEPARATOR, &SEPARATOR.to_string());
+    let repo_root = repo_root.trim_end_matches(SEPARATOR);
+    if repo_root.is_empty() || !is_windows_absolute_path(repo_root) {
+        return file_path;
+    }
+
+    // Windows paths are case-insensitive, e.g. `C:/` and `c:/` are the same drive
+    let relative_path = file_path
+        .get(..repo_root.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(repo_root))
+        .and_then(|_| file_path[repo_root.len()..].strip_prefix(SEPARATOR));
+    match relative_path {
+        Some(relative_path) if !relative_path.is_empty() => String::from(relative_path),
+        _ => file_path,
+    }
+}
+
+/// Whether `file_path` starts with a drive letter, e.g. `C:\` or `C:/`
+pub fn is_windows_absolute_path(file_path: &str) -> bool {
+    let mut chars = file_path.chars();
+    matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(drive), Some(':'), Some(WINDOWS_SEPARATOR | SEPARATOR)) if drive.is_ascii_alphabetic()
+    )
+}
+
+/// Returns `classname` if it looks like the path of a test file, as reported by e.g. Vitest, which
+/// leaves the `file` attribute empty. Dotted classnames like `com.foo.Bar` are not paths.
+pub fn file_path_from_classname(classname: &str) -> Option<&str> {
+    let classname = classname.trim();
+    let is_path = classname.contains([SEPARATOR, WINDOWS_SEPARATOR])
+        && !classname.contains(char::is_whitespace)
+        && TEST_FILE_EXTENSIONS
+            .iter()
+            .any(|extension| classname.ends_with(extension));
+    is_path.then_some(classname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPO_ROOT: &str = r"D:\a\analytics-cli\analytics-cli";
+
+    #[test]
+    fn converts_windows_separators() {
+        assert_eq!(
+            normalize_file_path(r"src\Foo.Tests\BarTests.cs", Some(REPO_ROOT)),
+            "src/Foo.Tests/BarTests.cs"
+        );
+        assert_eq!(
+            normalize_file_path("src/foo/bar_test.rs", None::<&str>),
+            "src/foo/bar_test.rs"
+        );
+    }
+
+    #[test]
+    fn strips_repo_root_from_windows_absolute_paths() {
+        assert_eq!(
+            normalize_file_path(
+                r"D:\a\analytics-cli\analytics-cli\src\Foo.Tests\BarTests.cs",
+                Some(REPO_ROOT)
+            ),
+            "src/Foo.Tests/BarTests.cs"
+        );
+        assert_eq!(
+            normalize_file_path(
+                r"d:\A\analytics-cli\analytics-cli\src\Foo.Tests\BarTests.cs",
+                Some(format!(r"{REPO_ROOT}\"))
+            ),
+            "src/Foo.Tests/BarTests.cs"
+        );
+    }
+
+    #[test]
+    fn leaves_paths_outside_repo_root_absolute() {
+        assert_eq!(
+            normalize_file_path(
+                r"C:\Users\runneradmin\.nuget\packages\xunit\Assert.cs",
+                Some(REPO_ROOT)
+            ),
+            "C:/Users/runneradmin/.nuget/packages/xunit/Assert.cs"
+        );
+        assert_eq!(
+            normalize_file_path(
+                r"D:\a\analytics-cli\analytics-cli-other\src\BarTests.cs",
+                Some(REPO_ROOT)
+            ),
+            "D:/a/analytics-cli/analytics-cli-other/src/BarTests.cs"
+        );
+        assert_eq!(
+            normalize_file_path(r"D:\a\src\BarTests.cs", None::<&str>),
+            "D:/a/src/BarTests.cs"
+        );
+    }
+
+    #[test]
+    fn detects_windows_absolute_paths() {
+        assert!(is_windows_absolute_path(r"C:\src\BarTests.cs"));
+        assert!(is_windows_absolute_path("c:/src/BarTests.cs"));
+        assert!(!is_windows_absolute_path("src/BarTests.cs"));
+        assert!(!is_windows_absolute_path("/home/runner/src/bar_test.rs"));
+        assert!(!is_windows_absolute_path("C:"));
+    }
+
+    #[test]
+    fn infers_file_paths_from_path_like_classnames() {
+        assert_eq!(
+            file_path_from_classname("src/components/Button.test.tsx"),
+            Some("src/components/Button.test.tsx")
+        );
+        assert_eq!(
+            file_path_from_classname(r"spec\models\user_spec.rb"),
+            Some(r"spec\models\user_spec.rb")
+        );
+        assert_eq!(file_path_from_classname("com.foo.Bar"), None);
+        assert_eq!(file_path_from_classname("tests.test_foo.TestFoo"), None);
+        assert_eq!(file_path_from_classname("Button.test.tsx"), None);
+        assert_eq!(file_path_from_classname("src/components/Button"), None);
+        assert_eq!(file_path_from_classname("Button > renders a/b.tsx"), None);
+    }
+}
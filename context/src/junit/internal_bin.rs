@@ -0,0 +1,723 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use prost::Message;
+use prost_wkt_types::Timestamp;
+use proto::test_context::test_run::{TestCaseRun, TestCaseRunStatus, TestResult};
+use quick_junit::{NonSuccessKind, Report, TestCase, TestCaseStatus, TestRerun, TestSuite};
+
+use super::{
+    parser::extra_attrs,
+    test_name::{sanitize_test_name, truncate_test_name},
+};
+use crate::env::parser::{CIInfo, CIPlatform};
+
+const TIMESTAMP_FUTURE_MAX_DAYS: i64 = 1;
+
+/// Reads an internal bin, a serialized [`TestResult`], from `path`.
+pub fn read_internal_bin<T: AsRef<Path>>(path: T) -> anyhow::Result<TestResult> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read internal bin {:?}: {}", path, e))?;
+    let test_result = TestResult::decode(bytes.as_slice())
+        .map_err(|e| anyhow::anyhow!("Failed to decode internal bin {:?}: {}", path, e))?;
+    validate_internal_bin(path, &test_result)?;
+    Ok(test_result)
+}
+
+/// Every test case run needs an id to be quarantined, so a missing id is an error. Timestamps that
+/// are missing, out of range, in the future, or that end before they start are only warned about.
+fn validate_internal_bin(path: &Path, test_result: &TestResult) -> anyhow::Result<()> {
+    let max_timestamp = Utc::now() + TimeDelta::days(TIMESTAMP_FUTURE_MAX_DAYS);
+    for (i, test_case_run) in test_result.test_case_runs.iter().enumerate() {
+        if test_case_run.id.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid internal bin {:?}: test case run {} ({:?}) has no id",
+                path,
+                i,
+                test_case_run.name
+            ));
+        }
+
+        let started_at = test_case_run.started_at.as_ref().map(to_date_time);
+        let finished_at = test_case_run.finished_at.as_ref().map(to_date_time);
+        let timestamp_issue = match (started_at, finished_at) {
+            (Some(None), _) => Some("has an out of range start time"),
+            (_, Some(None)) => Some("has an out of range finish time"),
+            (None, _) => Some("has no start time"),
+            (Some(Some(started_at)), _) if started_at > max_timestamp => {
+                Some("starts in the future")
+            }
+            (Some(Some(started_at)), Some(Some(finished_at))) if finished_at < started_at => {
+                Some("finishes before it starts")
+            }
+            _ => None,
+        };
+        if let Some(timestamp_issue) = timestamp_issue {
+            log::warn!(
+                "Test case run {} ({:?}) in internal bin {:?} {}",
+                test_case_run.id,
+                test_case_run.name,
+                path,
+                timestamp_issue
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Timestamps out of the range of [`DateTime`], which converting them with `From` panics on, are
+/// `None`.
+fn to_date_time(timestamp: &Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp.seconds, u32::try_from(timestamp.nanos).ok()?)
+}
+
+/// Sanitizes the names and classnames of the test case runs and truncates names longer than
+/// `max_test_name_len` bytes, like the junit parser does, so that the internal bin agrees with the
+/// junit synthesized from it. Returns the number of test case runs that were changed.
+pub fn sanitize_test_case_run_names(
+    test_result: &mut TestResult,
+    max_test_name_len: usize,
+) -> usize {
+    let mut num_changed = 0;
+    for test_case_run in &mut test_result.test_case_runs {
+        let mut changed = false;
+        if let Some(name) = sanitize_test_name(&test_case_run.name) {
+            test_case_run.name = name;
+            changed = true;
+        }
+        if let Some(name) = truncate_test_name(&test_case_run.name, max_test_name_len) {
+            test_case_run.name = name;
+            changed = true;
+        }
+        if let Some(classname) = sanitize_test_name(&test_case_run.classname) {
+            test_case_run.classname = classname;
+            changed = true;
+        }
+        num_changed += usize::from(changed);
+    }
+    num_changed
+}
+
+/// Concatenates the test case runs of `test_results`, keeping the first uploader metadata.
+pub fn merge_internal_bins<I: IntoIterator<Item = TestResult>>(test_results: I) -> TestResult {
+    test_results
+        .into_iter()
+        .fold(TestResult::default(), |mut merged, test_result| {
+            merged.test_case_runs.extend(test_result.test_case_runs);
+            merged.uploader_metadata = merged.uploader_metadata.or(test_result.uploader_metadata);
+            merged
+        })
+}
+
+/// Splits `test_result` into test results of consecutive test case runs, each encoding to at most
+/// `max_bytes` unless a single test case run is larger than that. Every shard keeps the uploader
+/// metadata, so that concatenating their encodings decodes back to `test_result`.
+pub fn shard_internal_bin(test_result: TestResult, max_bytes: usize) -> Vec<TestResult> {
+    let TestResult {
+        test_case_runs: all_test_case_runs,
+        uploader_metadata,
+    } = test_result;
+    let shard = |test_case_runs| TestResult {
+        test_case_runs,
+        uploader_metadata: uploader_metadata.clone(),
+    };
+    let base_len = shard(Vec::new()).encoded_len();
+
+    let mut shards = Vec::new();
+    let mut test_case_runs = Vec::new();
+    let mut shard_len = base_len;
+    for test_case_run in all_test_case_runs {
+        let test_case_run_len = prost::encoding::message::encoded_len(1, &test_case_run);
+        if !test_case_runs.is_empty() && shard_len + test_case_run_len > max_bytes {
+            shards.push(shard(std::mem::take(&mut test_case_runs)));
+            shard_len = base_len;
+        }
+        test_case_runs.push(test_case_run);
+        shard_len += test_case_run_len;
+    }
+    if !test_case_runs.is_empty() || shards.is_empty() {
+        shards.push(shard(test_case_runs));
+    }
+    shards
+}
+
+/// Links every test case run of `test_result` to the CI job of `ci_info`. On GitHub Actions, the
+/// id of the job, `github_external_id`, narrows the link from the workflow run to the job, which
+/// tells apart the jobs of a matrix.
+pub fn set_ci_job_links(
+    test_result: &mut TestResult,
+    ci_info: &CIInfo,
+    github_external_id: Option<&str>,
+) {
+    let ci_external_id = github_external_id
+        .filter(|external_id| !external_id.is_empty())
+        .filter(|_| ci_info.platform == CIPlatform::GitHubActions);
+    let ci_job_url = ci_info
+        .job_url
+        .as_deref()
+        .map(|job_url| match ci_external_id {
+            Some(external_id) => github_job_url(job_url, external_id),
+            None => job_url.to_string(),
+        });
+    for test_case_run in test_result.test_case_runs.iter_mut() {
+        if let Some(ci_job_url) = ci_job_url.as_ref() {
+            test_case_run.ci_job_url.clone_from(ci_job_url);
+        }
+        if let Some(ci_workflow_name) = ci_info.workflow.as_ref() {
+            test_case_run.ci_workflow_name.clone_from(ci_workflow_name);
+        }
+        if let Some(ci_external_id) = ci_external_id {
+            test_case_run.ci_external_id = ci_external_id.to_string();
+        }
+    }
+}
+
+/// The page of the job `job_id` of the workflow run at `run_url`, keeping its query, e.g. `?pr=1`
+fn github_job_url(run_url: &str, job_id: &str) -> String {
+    match run_url.split_once('?') {
+        Some((run_url, query)) => format!("{run_url}/job/{job_id}?{query}"),
+        None => format!("{run_url}/job/{job_id}"),
+    }
+}
+
+/// Synthesizes a minimal junit report from an internal bin, with a test suite per parent name.
+/// Test case ids are kept in the `id` attribute.
+pub fn internal_bin_to_report(test_result: &TestResult) -> Report {
+    let mut report = Report::new(report_name(test_result));
+
+    let mut test_suites: BTreeMap<&str, TestSuite> = BTreeMap::new();
+    for test_case_run in &test_result.test_case_runs {
+        add_test_case(
+            &mut test_suites,
+            test_case_run,
+            test_case_run_to_test_case(test_case_run),
+        );
+    }
+    report.add_test_suites(test_suites.into_values());
+
+    report
+}
+
+/// Reconstructs the junit reports of an internal bin, e.g. to inspect it with junit tooling.
+///
+/// Like [`internal_bin_to_report`], except that the earlier failed attempts of a test case id
+/// become reruns of its last attempt, or flaky runs if it passed. Fields that internal bins don't
+/// keep, such as `system-out`, stack traces and properties, are lost.
+///
+/// An internal bin without test case runs has no reports.
+pub fn bin_to_junit(test_result: &TestResult) -> Vec<Report> {
+    if test_result.test_case_runs.is_empty() {
+        return Vec::new();
+    }
+
+    let mut attempt_indices: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut attempts_of_test_cases: Vec<Vec<&TestCaseRun>> = Vec::new();
+    for test_case_run in &test_result.test_case_runs {
+        let key = (
+            test_case_run.parent_name.as_str(),
+            test_case_run.id.as_str(),
+        );
+        let index = *attempt_indices.entry(key).or_insert_with(|| {
+            attempts_of_test_cases.push(Vec::new());
+            attempts_of_test_cases.len() - 1
+        });
+        attempts_of_test_cases[index].push(test_case_run);
+    }
+
+    let mut report = Report::new(report_name(test_result));
+    let mut test_suites: BTreeMap<&str, TestSuite> = BTreeMap::new();
+    for mut attempts in attempts_of_test_cases {
+        attempts.sort_by_key(|attempt| {
+            (
+                attempt.attempt_number,
+                attempt.started_at.as_ref().and_then(to_date_time),
+            )
+        });
+        let Some((last_attempt, earlier_attempts)) = attempts.split_last() else {
+            continue;
+        };
+        let mut test_case = test_case_run_to_test_case(last_attempt);
+        test_case.status.add_reruns(
+            earlier_attempts
+                .iter()
+                .filter(|attempt| attempt.status == i32::from(TestCaseRunStatus::Failure))
+                .map(|attempt| test_case_run_to_test_rerun(attempt)),
+        );
+        add_test_case(&mut test_suites, last_attempt, test_case);
+    }
+    report.add_test_suites(test_suites.into_values());
+
+    vec![report]
+}
+
+fn report_name(test_result: &TestResult) -> &str {
+    test_result
+        .uploader_metadata
+        .as_ref()
+        .map(|uploader_metadata| uploader_metadata.origin.as_str())
+        .filter(|origin| !origin.is_empty())
+        .unwrap_or("internal bin")
+}
+
+/// Adds `test_case` to the test suite of the parent name of `test_case_run`, which takes the
+/// hostname and package of the first test case run that has them.
+fn add_test_case<'a>(
+    test_suites: &mut BTreeMap<&'a str, TestSuite>,
+    test_case_run: &'a TestCaseRun,
+    test_case: TestCase,
+) {
+    let test_suite = test_suites
+        .entry(test_case_run.parent_name.as_str())
+        .or_insert_with(|| TestSuite::new(test_case_run.parent_name.as_str()));
+    for (key, value) in [
+        (extra_attrs::HOSTNAME, &test_case_run.hostname),
+        (extra_attrs::PACKAGE, &test_case_run.package),
+    ] {
+        if !value.is_empty() && !test_suite.extra.contains_key(key) {
+            test_suite.extra.insert(key.into(), value.as_str().into());
+        }
+    }
+    test_suite.add_test_case(test_case);
+}
+
+fn test_case_run_to_test_rerun(test_case_run: &TestCaseRun) -> TestRerun {
+    let mut test_rerun = TestRerun::new(NonSuccessKind::Failure);
+    if !test_case_run.status_output_message.is_empty() {
+        test_rerun.set_message(test_case_run.status_output_message.as_str());
+    }
+    if let Some(started_at) = test_case_run.started_at.as_ref().and_then(to_date_time) {
+        test_rerun.set_timestamp(started_at.fixed_offset());
+        if let Some(time) = test_case_run
+            .finished_at
+            .as_ref()
+            .and_then(to_date_time)
+            .and_then(|finished_at| (finished_at - started_at).to_std().ok())
+        {
+            test_rerun.set_time(time);
+        }
+    }
+    test_rerun
+}
+
+fn test_case_run_to_test_case(test_case_run: &TestCaseRun) -> TestCase {
+    let status = match TestCaseRunStatus::try_from(test_case_run.status) {
+        Ok(TestCaseRunStatus::Failure) => {
+            let mut status = TestCaseStatus::non_success(NonSuccessKind::Failure);
+            if !test_case_run.status_output_message.is_empty() {
+                status.set_message(test_case_run.status_output_message.as_str());
+            }
+            status
+        }
+        Ok(TestCaseRunStatus::Skipped) => {
+            let mut status = TestCaseStatus::skipped();
+            if !test_case_run.skip_reason.is_empty() {
+                status.set_message(test_case_run.skip_reason.as_str());
+            }
+            status
+        }
+        _ => TestCaseStatus::success(),
+    };
+    let mut test_case = TestCase::new(test_case_run.name.as_str(), status);
+
+    if !test_case_run.classname.is_empty() {
+        test_case.set_classname(test_case_run.classname.as_str());
+    }
+
+    let started_at = test_case_run.started_at.as_ref().and_then(to_date_time);
+    if let Some(started_at) = started_at {
+        test_case.set_timestamp(started_at.fixed_offset());
+        if let Some(time) = test_case_run
+            .finished_at
+            .as_ref()
+            .and_then(to_date_time)
+            .and_then(|finished_at| (finished_at - started_at).to_std().ok())
+        {
+            test_case.set_time(time);
+        }
+    } else {
+        test_case.set_time(Duration::ZERO);
+    }
+
+    test_case
+        .extra
+        .insert(extra_attrs::ID.into(), test_case_run.id.as_str().into());
+    if !test_case_run.file.is_empty() {
+        test_case
+            .extra
+            .insert(extra_attrs::FILE.into(), test_case_run.file.as_str().into());
+    }
+    if test_case_run.line > 0 {
+        test_case.extra.insert(
+            extra_attrs::LINE.into(),
+            test_case_run.line.to_string().into(),
+        );
+    }
+    if test_case_run.is_quarantined {
+        test_case
+            .extra
+            .insert(extra_attrs::QUARANTINED.into(), "true".into());
+    }
+
+    test_case
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use proto::test_context::test_run::UploaderMetadata;
+
+    use super::*;
+    use crate::junit::parser::JunitParser;
+
+    fn test_case_run(id: &str, parent_name: &str, status: TestCaseRunStatus) -> TestCaseRun {
+        TestCaseRun {
+            id: String::from(id),
+            name: format!("test_{id}"),
+            classname: String::from("FooTest"),
+            file: String::from("src/foo_test.rs"),
+            parent_name: String::from(parent_name),
+            line: 7,
+            status: status.into(),
+            attempt_number: 0,
+            started_at: Some(Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 0,
+            }),
+            finished_at: Some(Timestamp {
+                seconds: 1_700_000_002,
+                nanos: 0,
+            }),
+            status_output_message: String::from("assertion failed"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merges_and_synthesizes_junit() {
+        let merged = merge_internal_bins([
+            TestResult {
+                test_case_runs: vec![
+                    test_case_run("1", "suite_b", TestCaseRunStatus::Success),
+                    TestCaseRun {
+                        hostname: String::from("emulator-5554"),
+                        package: String::from("com.example.app"),
+                        is_quarantined: true,
+                        ..test_case_run("2", "suite_a", TestCaseRunStatus::Failure)
+                    },
+                ],
+                uploader_metadata: Some(UploaderMetadata {
+                    origin: String::from("orchestrator"),
+                    ..Default::default()
+                }),
+            },
+            TestResult {
+                test_case_runs: vec![test_case_run("3", "suite_b", TestCaseRunStatus::Skipped)],
+                uploader_metadata: None,
+            },
+        ]);
+        assert_eq!(merged.test_case_runs.len(), 3);
+
+        let junit_xml = internal_bin_to_report(&merged).to_string().unwrap();
+        let mut junit_parser = JunitParser::new();
+        junit_parser
+            .parse(BufReader::new(junit_xml.as_bytes()))
+            .unwrap();
+        assert_eq!(junit_parser.issues(), &[]);
+        let report = junit_parser.into_reports().pop().unwrap();
+
+        assert_eq!(report.name.as_str(), "orchestrator");
+        assert_eq!(report.tests, 3);
+        assert_eq!(report.failures, 1);
+        let test_suite_names: Vec<_> = report
+            .test_suites
+            .iter()
+            .map(|test_suite| test_suite.name.as_str())
+            .collect();
+        assert_eq!(test_suite_names, ["suite_a", "suite_b"]);
+        let test_suite = &report.test_suites[0];
+        assert_eq!(
+            test_suite
+                .extra
+                .get(extra_attrs::HOSTNAME)
+                .map(|h| h.as_str()),
+            Some("emulator-5554")
+        );
+        assert_eq!(
+            test_suite
+                .extra
+                .get(extra_attrs::PACKAGE)
+                .map(|p| p.as_str()),
+            Some("com.example.app")
+        );
+        assert!(report.test_suites[1].extra.is_empty());
+
+        let test_case = &report.test_suites[0].test_cases[0];
+        assert_eq!(test_case.name.as_str(), "test_2");
+        assert_eq!(
+            test_case.extra.get(extra_attrs::ID).map(|id| id.as_str()),
+            Some("2")
+        );
+        assert_eq!(
+            test_case.extra.get(extra_attrs::FILE).map(|f| f.as_str()),
+            Some("src/foo_test.rs")
+        );
+        assert_eq!(
+            test_case
+                .extra
+                .get(extra_attrs::QUARANTINED)
+                .map(|q| q.as_str()),
+            Some("true")
+        );
+        assert!(!report.test_suites[1].test_cases[0]
+            .extra
+            .contains_key(extra_attrs::QUARANTINED));
+        assert_eq!(test_case.time, Some(Duration::from_secs(2)));
+        assert!(matches!(
+            test_case.status,
+            TestCaseStatus::NonSuccess {
+                kind: NonSuccessKind::Failure,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn shards_within_max_bytes() {
+        let test_result = TestResult {
+            test_case_runs: (0..10)
+                .map(|i| test_case_run(&i.to_string(), "suite", TestCaseRunStatus::Success))
+                .collect(),
+            uploader_metadata: Some(UploaderMetadata {
+                origin: String::from("orchestrator"),
+                ..Default::default()
+            }),
+        };
+        let test_case_run_len =
+            prost::encoding::message::encoded_len(1, &test_result.test_case_runs[0]);
+        let max_bytes = TestResult {
+            test_case_runs: test_result.test_case_runs[..3].to_vec(),
+            ..test_result.clone()
+        }
+        .encoded_len();
+
+        let shards = shard_internal_bin(test_result.clone(), max_bytes);
+        assert_eq!(
+            shards
+                .iter()
+                .map(|shard| shard.test_case_runs.len())
+                .collect::<Vec<_>>(),
+            vec![3, 3, 3, 1]
+        );
+        for shard in &shards {
+            assert!(shard.encoded_len() <= max_bytes);
+            assert_eq!(shard.uploader_metadata, test_result.uploader_metadata);
+        }
+        let concatenated: Vec<u8> = shards.iter().flat_map(Message::encode_to_vec).collect();
+        assert_eq!(
+            TestResult::decode(concatenated.as_slice()).unwrap(),
+            test_result
+        );
+
+        // Test case runs larger than the max get a shard each
+        let shards = shard_internal_bin(test_result.clone(), test_case_run_len);
+        assert_eq!(shards.len(), 10);
+        assert_eq!(merge_internal_bins(shards), test_result);
+
+        // Under the max, and when empty, there is a single shard
+        assert_eq!(
+            shard_internal_bin(test_result.clone(), usize::MAX),
+            vec![test_result]
+        );
+        assert_eq!(
+            shard_internal_bin(TestResult::default(), 1),
+            vec![TestResult::default()]
+        );
+    }
+
+    #[test]
+    fn sets_ci_job_links() {
+        let mut ci_info = CIInfo::new(CIPlatform::GitHubActions);
+        ci_info.job_url = Some(String::from(
+            "https://github.com/trunk-io/analytics-cli/actions/runs/123?pr=7",
+        ));
+        ci_info.workflow = Some(String::from("CI"));
+        let test_result = TestResult {
+            test_case_runs: vec![
+                test_case_run("1", "suite", TestCaseRunStatus::Success),
+                test_case_run("2", "suite", TestCaseRunStatus::Failure),
+            ],
+            ..Default::default()
+        };
+
+        let mut linked = test_result.clone();
+        set_ci_job_links(&mut linked, &ci_info, Some("456"));
+        for test_case_run in &linked.test_case_runs {
+            assert_eq!(
+                test_case_run.ci_job_url,
+                "https://github.com/trunk-io/analytics-cli/actions/runs/123/job/456?pr=7"
+            );
+            assert_eq!(test_case_run.ci_workflow_name, "CI");
+            assert_eq!(test_case_run.ci_external_id, "456");
+        }
+
+        // Without the id of the job, the link is to the workflow run
+        let mut linked = test_result.clone();
+        set_ci_job_links(&mut linked, &ci_info, None);
+        assert_eq!(
+            linked.test_case_runs[0].ci_job_url,
+            "https://github.com/trunk-io/analytics-cli/actions/runs/123?pr=7"
+        );
+        assert_eq!(linked.test_case_runs[0].ci_external_id, "");
+
+        // External ids are only those of GitHub Actions jobs
+        let mut ci_info = CIInfo::new(CIPlatform::Buildkite);
+        ci_info.job_url = Some(String::from("https://buildkite.com/trunk/builds/1"));
+        let mut linked = test_result.clone();
+        set_ci_job_links(&mut linked, &ci_info, Some("456"));
+        assert_eq!(
+            linked.test_case_runs[1].ci_job_url,
+            "https://buildkite.com/trunk/builds/1"
+        );
+        assert_eq!(linked.test_case_runs[1].ci_workflow_name, "");
+        assert_eq!(linked.test_case_runs[1].ci_external_id, "");
+    }
+
+    #[test]
+    fn groups_attempts_into_reruns() {
+        let test_result = TestResult {
+            test_case_runs: vec![
+                TestCaseRun {
+                    attempt_number: 1,
+                    ..test_case_run("1", "suite", TestCaseRunStatus::Success)
+                },
+                test_case_run("1", "suite", TestCaseRunStatus::Failure),
+                test_case_run("2", "suite", TestCaseRunStatus::Failure),
+                TestCaseRun {
+                    attempt_number: 1,
+                    started_at: Some(Timestamp {
+                        seconds: i64::MAX,
+                        nanos: 0,
+                    }),
+                    status_output_message: "expected true, got false;".repeat(250),
+                    ..test_case_run("2", "suite", TestCaseRunStatus::Failure)
+                },
+                TestCaseRun {
+                    skip_reason: String::from("requires docker"),
+                    ..test_case_run("3", "suite", TestCaseRunStatus::Skipped)
+                },
+            ],
+            uploader_metadata: None,
+        };
+        assert!(bin_to_junit(&TestResult::default()).is_empty());
+
+        let mut reports = bin_to_junit(&test_result);
+        assert_eq!(reports.len(), 1);
+        let junit_xml = reports.pop().unwrap().to_string().unwrap();
+        let mut junit_parser = JunitParser::new();
+        junit_parser
+            .parse(BufReader::new(junit_xml.as_bytes()))
+            .unwrap();
+        assert_eq!(junit_parser.issues(), &[]);
+        let report = junit_parser.into_reports().pop().unwrap();
+        assert_eq!(report.name.as_str(), "internal bin");
+        assert_eq!(report.tests, 3);
+
+        let test_cases = &report.test_suites[0].test_cases;
+        let ids: Vec<_> = test_cases
+            .iter()
+            .map(|test_case| test_case.extra.get(extra_attrs::ID).unwrap().as_str())
+            .collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert!(matches!(
+            &test_cases[0].status,
+            TestCaseStatus::Success { flaky_runs } if flaky_runs.len() == 1
+        ));
+        assert!(matches!(
+            &test_cases[1].status,
+            TestCaseStatus::NonSuccess { reruns, .. } if reruns.len() == 1
+        ));
+        assert_eq!(test_cases[1].timestamp, None);
+        assert!(matches!(
+            &test_cases[1].status,
+            TestCaseStatus::NonSuccess { message: Some(message), .. }
+                if message.as_str() == "expected true, got false;".repeat(250)
+        ));
+        assert!(matches!(
+            &test_cases[2].status,
+            TestCaseStatus::Skipped { message: Some(message), .. } if message.as_str() == "requires docker"
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_internal_bins() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let garbage_path = temp_dir.path().join("garbage.bin");
+        std::fs::write(&garbage_path, b"\xff\xff\xff").unwrap();
+        let err = read_internal_bin(&garbage_path).unwrap_err().to_string();
+        assert!(err.starts_with("Failed to decode internal bin"));
+        assert!(err.contains("garbage.bin"));
+
+        let missing_id_path = temp_dir.path().join("missing_id.bin");
+        let test_result = TestResult {
+            test_case_runs: vec![test_case_run(" ", "suite", TestCaseRunStatus::Success)],
+            uploader_metadata: None,
+        };
+        std::fs::write(&missing_id_path, test_result.encode_to_vec()).unwrap();
+        let err = read_internal_bin(&missing_id_path).unwrap_err().to_string();
+        assert!(err.contains("has no id"));
+        assert!(err.contains("missing_id.bin"));
+
+        let out_of_range_path = temp_dir.path().join("out_of_range.bin");
+        let mut out_of_range = test_case_run("1", "suite", TestCaseRunStatus::Success);
+        out_of_range.started_at = Some(Timestamp {
+            seconds: i64::MAX,
+            nanos: 0,
+        });
+        out_of_range.finished_at = Some(Timestamp {
+            seconds: 0,
+            nanos: -1,
+        });
+        let test_result = TestResult {
+            test_case_runs: vec![out_of_range],
+            uploader_metadata: None,
+        };
+        std::fs::write(&out_of_range_path, test_result.encode_to_vec()).unwrap();
+        assert_eq!(read_internal_bin(&out_of_range_path).unwrap(), test_result);
+
+        let valid_path = temp_dir.path().join("valid.bin");
+        let test_result = TestResult {
+            test_case_runs: vec![test_case_run("1", "suite", TestCaseRunStatus::Success)],
+            uploader_metadata: None,
+        };
+        std::fs::write(&valid_path, test_result.encode_to_vec()).unwrap();
+        assert_eq!(read_internal_bin(&valid_path).unwrap(), test_result);
+    }
+
+    #[test]
+    fn sanitizes_test_case_run_names() {
+        let mut dirty = test_case_run("1", "suite", TestCaseRunStatus::Failure);
+        dirty.name = String::from("\u{1b}[31mtest_foo\u{1b}[0m\nfails");
+        dirty.classname = String::from("Foo\tTest");
+        let mut long = test_case_run("2", "suite", TestCaseRunStatus::Success);
+        long.name = "a".repeat(30);
+        let clean = test_case_run("3", "suite", TestCaseRunStatus::Success);
+        let mut test_result = TestResult {
+            test_case_runs: vec![dirty, long, clean.clone()],
+            uploader_metadata: None,
+        };
+
+        assert_eq!(sanitize_test_case_run_names(&mut test_result, 20), 2);
+
+        assert_eq!(test_result.test_case_runs[0].name, "test_foo fails");
+        assert_eq!(test_result.test_case_runs[0].classname, "Foo Test");
+        assert_eq!(test_result.test_case_runs[1].name, "a".repeat(20));
+        assert_eq!(test_result.test_case_runs[2], clean);
+    }
+}
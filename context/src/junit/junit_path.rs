@@ -19,19 +19,97 @@ pub enum JunitReportStatus {
     Flaky,
 }
 
-impl TryFrom<TestStatus> for JunitReportStatus {
+impl JunitReportStatus {
+    /// Resolves the status of a test target from the statuses of its attempts, in the order they
+    /// ran, the way the test runner does when retrying failures:
+    ///
+    /// - `Failed` when the last attempt failed
+    /// - `Flaky` when an earlier attempt failed but the last one passed
+    /// - `Passed` when every attempt passed
+    ///
+    /// Skipped attempts are ignored, so there is no status when no attempt passed or failed.
+    pub fn from_attempt_statuses(attempts: &[JunitReportAttemptStatus]) -> Option<Self> {
+        let mut ran_attempts = attempts
+            .iter()
+            .filter(|attempt| **attempt != JunitReportAttemptStatus::Skipped);
+        let last_attempt = ran_attempts.next_back()?;
+        if *last_attempt == JunitReportAttemptStatus::Failed {
+            Some(JunitReportStatus::Failed)
+        } else if ran_attempts.any(|attempt| *attempt == JunitReportAttemptStatus::Failed) {
+            Some(JunitReportStatus::Flaky)
+        } else {
+            Some(JunitReportStatus::Passed)
+        }
+    }
+}
+
+impl std::fmt::Display for JunitReportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JunitReportStatus::Passed => write!(f, "Passed"),
+            JunitReportStatus::Failed => write!(f, "Failed"),
+            JunitReportStatus::Flaky => write!(f, "Flaky"),
+        }
+    }
+}
+
+impl std::str::FromStr for JunitReportStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "passed" => Ok(JunitReportStatus::Passed),
+            "failed" => Ok(JunitReportStatus::Failed),
+            "flaky" => Ok(JunitReportStatus::Flaky),
+            _ => Err(anyhow::anyhow!(
+                "invalid junit report status {:?}, expected Passed, Failed or Flaky",
+                s
+            )),
+        }
+    }
+}
+
+/// Status of a single attempt of a test target, see [`JunitReportStatus::from_attempt_statuses`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
+#[cfg_attr(feature = "wasm", derive(Tsify))]
+pub enum JunitReportAttemptStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Status of a single Bazel test attempt. Other statuses, e.g. a timeout, are not resolved.
+impl TryFrom<TestStatus> for JunitReportAttemptStatus {
     type Error = ();
 
     fn try_from(status: TestStatus) -> Result<Self, Self::Error> {
         match status {
-            TestStatus::Passed => Ok(JunitReportStatus::Passed),
-            TestStatus::Failed => Ok(JunitReportStatus::Failed),
-            TestStatus::Flaky => Ok(JunitReportStatus::Flaky),
+            TestStatus::Passed => Ok(JunitReportAttemptStatus::Passed),
+            TestStatus::Failed => Ok(JunitReportAttemptStatus::Failed),
             _ => Err(()),
         }
     }
 }
 
+/// Resolves the overall status of a Bazel test target like
+/// [`JunitReportStatus::from_attempt_statuses`], with `Flaky` standing for a failed attempt
+/// followed by a passing one, so that both always agree.
+impl TryFrom<TestStatus> for JunitReportStatus {
+    type Error = ();
+
+    fn try_from(status: TestStatus) -> Result<Self, Self::Error> {
+        let attempts = match status {
+            TestStatus::Flaky => vec![
+                JunitReportAttemptStatus::Failed,
+                JunitReportAttemptStatus::Passed,
+            ],
+            _ => vec![JunitReportAttemptStatus::try_from(status)?],
+        };
+        JunitReportStatus::from_attempt_statuses(&attempts).ok_or(())
+    }
+}
+
 /// Encapsulates the glob path for a junit and, if applicable, the flakiness already
 /// assigned by the user's test runner. See bazel_bep/parser.rs for more.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -41,6 +119,9 @@ pub struct JunitReportFileWithStatus {
     /// Refers to an optional status parsed from the test runner's output, before junits have been parsed.
     /// TODO(TRUNK-13911): We should populate the status for all junits, regardless of the presence of a test runner status.
     pub status: Option<JunitReportStatus>,
+    /// Variant of the test cases in the junit files, e.g. the platform they ran on, which gives them
+    /// IDs of their own.
+    pub variant: Option<String>,
 }
 
 impl From<String> for JunitReportFileWithStatus {
@@ -48,6 +129,158 @@ impl From<String> for JunitReportFileWithStatus {
         Self {
             junit_path,
             status: None,
+            variant: None,
+        }
+    }
+}
+
+/// A glob path to junit files whose test cases belong to a variant, given as `<glob>=<variant>`,
+/// e.g. `ios/**/*.xml=ios`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JunitPathWithVariant {
+    pub junit_path: String,
+    pub variant: String,
+}
+
+impl std::str::FromStr for JunitPathWithVariant {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // globs may contain `=`, variants may not
+        let (junit_path, variant) = s
+            .rsplit_once('=')
+            .map(|(junit_path, variant)| (junit_path.trim(), variant.trim()))
+            .filter(|(junit_path, variant)| !junit_path.is_empty() && !variant.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "expected `<glob>=<variant>`, e.g. `ios/**/*.xml=ios`, but got {:?}",
+                    s
+                )
+            })?;
+        Ok(Self {
+            junit_path: String::from(junit_path),
+            variant: String::from(variant),
+        })
+    }
+}
+
+impl std::fmt::Display for JunitPathWithVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.junit_path, self.variant)
+    }
+}
+
+impl From<JunitPathWithVariant> for JunitReportFileWithStatus {
+    fn from(
+        JunitPathWithVariant {
+            junit_path,
+            variant,
+        }: JunitPathWithVariant,
+    ) -> Self {
+        Self {
+            junit_path,
+            status: None,
+            variant: Some(variant),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JunitReportAttemptStatus::*, *};
+
+    #[test]
+    fn resolves_status_from_attempts() {
+        let cases: &[(&[JunitReportAttemptStatus], Option<JunitReportStatus>)] = &[
+            (&[], None),
+            (&[Skipped, Skipped], None),
+            (&[Passed], Some(JunitReportStatus::Passed)),
+            (&[Failed], Some(JunitReportStatus::Failed)),
+            (&[Passed, Skipped], Some(JunitReportStatus::Passed)),
+            (&[Failed, Failed, Failed], Some(JunitReportStatus::Failed)),
+            (&[Failed, Passed], Some(JunitReportStatus::Flaky)),
+            (&[Failed, Skipped, Passed], Some(JunitReportStatus::Flaky)),
+            (&[Passed, Failed], Some(JunitReportStatus::Failed)),
+            (&[Failed, Passed, Skipped], Some(JunitReportStatus::Flaky)),
+        ];
+        for (attempts, expected) in cases {
+            assert_eq!(
+                JunitReportStatus::from_attempt_statuses(attempts),
+                *expected,
+                "{:?}",
+                attempts
+            );
+        }
+    }
+
+    #[test]
+    fn resolves_bazel_statuses_like_attempts() {
+        let cases = [
+            (TestStatus::NoStatus, None),
+            (TestStatus::Passed, Some(JunitReportStatus::Passed)),
+            (TestStatus::Flaky, Some(JunitReportStatus::Flaky)),
+            (TestStatus::Timeout, None),
+            (TestStatus::Failed, Some(JunitReportStatus::Failed)),
+            (TestStatus::Incomplete, None),
+            (TestStatus::RemoteFailure, None),
+            (TestStatus::FailedToBuild, None),
+            (TestStatus::ToolHaltedBeforeTesting, None),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(
+                JunitReportStatus::try_from(status).ok(),
+                expected,
+                "{:?}",
+                status
+            );
+        }
+        assert_eq!(
+            JunitReportStatus::from_attempt_statuses(&[
+                JunitReportAttemptStatus::try_from(TestStatus::Failed).unwrap(),
+                JunitReportAttemptStatus::try_from(TestStatus::Passed).unwrap(),
+            ]),
+            JunitReportStatus::try_from(TestStatus::Flaky).ok()
+        );
+    }
+
+    #[test]
+    fn parses_status_from_string() {
+        for status in [
+            JunitReportStatus::Passed,
+            JunitReportStatus::Failed,
+            JunitReportStatus::Flaky,
+        ] {
+            assert_eq!(
+                status.to_string().parse::<JunitReportStatus>().unwrap(),
+                status
+            );
+        }
+        assert_eq!(
+            "FLAKY".parse::<JunitReportStatus>().unwrap(),
+            JunitReportStatus::Flaky
+        );
+        assert!("skipped".parse::<JunitReportStatus>().is_err());
+    }
+
+    #[test]
+    fn parses_junit_path_with_variant() {
+        let junit_path_with_variant = "ios/**/report=*.xml = ios"
+            .parse::<JunitPathWithVariant>()
+            .unwrap();
+        assert_eq!(junit_path_with_variant.junit_path, "ios/**/report=*.xml");
+        assert_eq!(junit_path_with_variant.variant, "ios");
+        assert_eq!(
+            JunitReportFileWithStatus::from(junit_path_with_variant),
+            JunitReportFileWithStatus {
+                junit_path: String::from("ios/**/report=*.xml"),
+                status: None,
+                variant: Some(String::from("ios")),
+            }
+        );
+        for junit_path_with_variant in ["ios/**/*.xml", "ios/**/*.xml=", "=ios"] {
+            assert!(junit_path_with_variant
+                .parse::<JunitPathWithVariant>()
+                .is_err());
         }
     }
 }
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use quick_xml::{escape::escape, events::Event, Reader};
+
+use super::parser::{TAG_REPORT, TAG_TEST_SUITE};
+
+/// Largest merged junit file, above which its test suites are split into more files
+pub const MERGED_JUNIT_FILE_MAX_BYTES: usize = 10 * 1024 * 1024;
+
+/// How junit reports are combined, e.g. those of runners that write a file per test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JunitMergeGrouping {
+    /// A report per test suite name
+    BySuite,
+    /// A single report
+    All,
+}
+
+/// The `<testsuite>` elements of a junit file, copied as written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JunitXmlTestSuites {
+    /// `name` of the `<testsuites>` root, if any
+    pub report_name: Option<String>,
+    /// `name` and XML of each `<testsuite>` element
+    pub test_suites: Vec<(String, String)>,
+}
+
+/// Finds the `<testsuite>` elements of junit `xml`, either the root element or those under a
+/// `<testsuites>` root. Anything else under the root, e.g. its `<properties>`, is left out.
+pub fn extract_test_suites(xml: &str) -> anyhow::Result<JunitXmlTestSuites> {
+    let mut reader = Reader::from_str(xml);
+    let mut extracted = JunitXmlTestSuites::default();
+    let mut in_report = false;
+    let mut found_root = false;
+    loop {
+        let start = usize::try_from(reader.buffer_position())?;
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if !in_report && found_root => {
+                return Err(anyhow::anyhow!(
+                    "unexpected <{}> after the root element",
+                    String::from_utf8_lossy(e.name().as_ref())
+                ));
+            }
+            Event::Start(e) if !in_report && e.name().as_ref() == TAG_REPORT => {
+                extracted.report_name = name_of(&e)?;
+                in_report = true;
+                found_root = true;
+            }
+            Event::Empty(e) if !in_report && e.name().as_ref() == TAG_REPORT => {
+                extracted.report_name = name_of(&e)?;
+                found_root = true;
+            }
+            Event::Start(e) if e.name().as_ref() == TAG_TEST_SUITE => {
+                reader.read_to_end(e.name())?;
+                let end = usize::try_from(reader.buffer_position())?;
+                let name = name_of(&e)?.unwrap_or_default();
+                extracted
+                    .test_suites
+                    .push((name, xml[start..end].to_string()));
+                found_root = true;
+            }
+            Event::Empty(e) if e.name().as_ref() == TAG_TEST_SUITE => {
+                let end = usize::try_from(reader.buffer_position())?;
+                let name = name_of(&e)?.unwrap_or_default();
+                extracted
+                    .test_suites
+                    .push((name, xml[start..end].to_string()));
+                found_root = true;
+            }
+            Event::Start(e) if in_report => {
+                reader.read_to_end(e.name())?;
+            }
+            Event::Start(e) | Event::Empty(e) if !in_report => {
+                return Err(anyhow::anyhow!(
+                    "<{}> is not a junit root element",
+                    String::from_utf8_lossy(e.name().as_ref())
+                ));
+            }
+            Event::End(..) => in_report = false,
+            Event::Eof => break,
+            // declaration, comments, text and elements other than test suites under the root
+            _ => (),
+        }
+    }
+    if !found_root {
+        return Err(anyhow::anyhow!("no junit root element found"));
+    }
+    Ok(extracted)
+}
+
+fn name_of(e: &quick_xml::events::BytesStart) -> anyhow::Result<Option<String>> {
+    Ok(match e.try_get_attribute("name")? {
+        Some(attribute) => Some(attribute.unescape_value()?.into_owned()),
+        None => None,
+    })
+}
+
+/// Combines the test suites of junit files into as few files as `grouping` allows, each of at
+/// most `max_bytes` unless a single test suite is larger. Test suites are copied as written, so
+/// that nothing the parser doesn't keep is lost; those of the same name become siblings.
+pub fn merge_test_suites(
+    files: Vec<JunitXmlTestSuites>,
+    grouping: JunitMergeGrouping,
+    max_bytes: usize,
+) -> Vec<String> {
+    let mut groups: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+    let report_name = files.iter().find_map(|file| file.report_name.clone());
+    for file in files {
+        for (name, test_suite) in file.test_suites {
+            let report_name = match grouping {
+                JunitMergeGrouping::All => report_name.clone(),
+                JunitMergeGrouping::BySuite => Some(name),
+            };
+            groups.entry(report_name).or_default().push(test_suite);
+        }
+    }
+
+    let mut merged_files = Vec::new();
+    for (report_name, test_suites) in groups {
+        let start = match &report_name {
+            Some(report_name) => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{}\">\n",
+                escape(report_name.as_str())
+            ),
+            None => String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n"),
+        };
+        let end = "</testsuites>\n";
+        let mut merged_file = start.clone();
+        let mut num_test_suites = 0;
+        for test_suite in test_suites {
+            if num_test_suites > 0
+                && merged_file.len() + test_suite.len() + 1 + end.len() > max_bytes
+            {
+                merged_file.push_str(end);
+                merged_files.push(std::mem::replace(&mut merged_file, start.clone()));
+                num_test_suites = 0;
+            }
+            merged_file.push_str(&test_suite);
+            merged_file.push('\n');
+            num_test_suites += 1;
+        }
+        merged_file.push_str(end);
+        merged_files.push(merged_file);
+    }
+    merged_files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::junit::parser::JunitParser;
+
+    const SHARD_A: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="shard-a">
+  <properties><property name="ignored" value="true"/></properties>
+  <testsuite name="auth" tests="1" custom="kept">
+    <properties><property name="seed" value="42"/></properties>
+    <testcase name="logs in" classname="auth"/>
+    <system-out>it's &lt;kept&gt;</system-out>
+  </testsuite>
+  <testsuite name="billing" tests="1"><testcase name="charges" classname="billing"/></testsuite>
+</testsuites>
+"#;
+    const SHARD_B: &str = r#"<testsuite name="auth" tests="1" failures="1">
+  <testcase name="logs out" classname="auth"><failure message="expected &quot;out&quot;"/></testcase>
+</testsuite>"#;
+
+    fn extracted() -> Vec<JunitXmlTestSuites> {
+        vec![
+            extract_test_suites(SHARD_A).unwrap(),
+            extract_test_suites(SHARD_B).unwrap(),
+        ]
+    }
+
+    fn test_case_names(xml: &str) -> Vec<(String, String)> {
+        let mut junit_parser = JunitParser::new();
+        junit_parser.parse(xml.as_bytes()).unwrap();
+        junit_parser
+            .into_reports()
+            .into_iter()
+            .flat_map(|report| report.test_suites)
+            .flat_map(|test_suite| {
+                test_suite
+                    .test_cases
+                    .into_iter()
+                    .map(move |test_case| (test_suite.name.to_string(), test_case.name.to_string()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn extracts_test_suites_as_written() {
+        let extracted = extracted();
+        assert_eq!(extracted[0].report_name.as_deref(), Some("shard-a"));
+        assert_eq!(
+            extracted[0].test_suites,
+            vec![
+                (
+                    String::from("auth"),
+                    String::from(
+                        r#"<testsuite name="auth" tests="1" custom="kept">
+    <properties><property name="seed" value="42"/></properties>
+    <testcase name="logs in" classname="auth"/>
+    <system-out>it's &lt;kept&gt;</system-out>
+  </testsuite>"#
+                    )
+                ),
+                (
+                    String::from("billing"),
+                    String::from(
+                        r#"<testsuite name="billing" tests="1"><testcase name="charges" classname="billing"/></testsuite>"#
+                    )
+                ),
+            ]
+        );
+        assert_eq!(extracted[1].report_name, None);
+        assert_eq!(
+            extracted[1].test_suites,
+            vec![(String::from("auth"), String::from(SHARD_B))]
+        );
+
+        assert!(extract_test_suites("<checkstyle/>").is_err());
+        assert!(extract_test_suites("<testsuite></testsuites>").is_err());
+        assert!(extract_test_suites("").is_err());
+    }
+
+    #[test]
+    fn merges_test_suites_of_the_same_name() {
+        let merged_files = merge_test_suites(extracted(), JunitMergeGrouping::BySuite, usize::MAX);
+        assert_eq!(merged_files.len(), 2);
+        assert!(merged_files[0].contains(r#"<testsuites name="auth">"#));
+        assert!(merged_files[0].contains(r#"custom="kept""#));
+        assert!(merged_files[0].contains(r#"<property name="seed" value="42"/>"#));
+        assert!(merged_files[0].contains("it's &lt;kept&gt;"));
+        assert_eq!(
+            test_case_names(&merged_files[0]),
+            vec![
+                (String::from("auth"), String::from("logs in")),
+                (String::from("auth"), String::from("logs out")),
+            ]
+        );
+        assert_eq!(
+            test_case_names(&merged_files[1]),
+            vec![(String::from("billing"), String::from("charges"))]
+        );
+
+        let merged_files = merge_test_suites(extracted(), JunitMergeGrouping::All, usize::MAX);
+        assert_eq!(merged_files.len(), 1);
+        assert!(merged_files[0].contains(r#"<testsuites name="shard-a">"#));
+        assert_eq!(test_case_names(&merged_files[0]).len(), 3);
+    }
+
+    #[test]
+    fn splits_merged_files_over_max_bytes() {
+        let merged_file =
+            merge_test_suites(extracted(), JunitMergeGrouping::All, usize::MAX).remove(0);
+        let split_files =
+            merge_test_suites(extracted(), JunitMergeGrouping::All, merged_file.len() - 1);
+        assert_eq!(split_files.len(), 2);
+        assert!(split_files
+            .iter()
+            .all(|split_file| split_file.len() < merged_file.len()));
+        assert_eq!(
+            split_files
+                .iter()
+                .flat_map(|split_file| test_case_names(split_file))
+                .collect::<Vec<_>>(),
+            test_case_names(&merged_file)
+        );
+
+        // a test suite larger than max bytes is kept in a file of its own
+        let split_files = merge_test_suites(extracted(), JunitMergeGrouping::All, 1);
+        assert_eq!(split_files.len(), 3);
+    }
+}
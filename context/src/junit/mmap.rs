@@ -0,0 +1,29 @@
+//! Memory-mapping of large junit files on 64-bit platforms, which parses them without a read
+//! syscall per buffer and without copying them through a buffer on the way to the parser.
+
+/// Smaller files are read through a buffer, since mapping them costs more than it saves
+pub const MMAP_MIN_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Set to read junit files through a buffer even where memory-mapping them was opted into, e.g.
+/// on network filesystems, where a mapped file that is truncated while it is parsed crashes the
+/// process
+pub const NO_MMAP_ENV_VAR: &str = "TRUNK_NO_MMAP";
+
+/// The mapped contents of `file`, unless it should be read through a buffer instead
+#[cfg(target_pointer_width = "64")]
+pub fn map_junit_file(file: &std::fs::File) -> Option<memmap2::Mmap> {
+    if std::env::var_os(NO_MMAP_ENV_VAR).is_some_and(|no_mmap| !no_mmap.is_empty()) {
+        return None;
+    }
+    if file.metadata().ok()?.len() < MMAP_MIN_FILE_SIZE {
+        return None;
+    }
+    // SAFETY: the mapping is only read while parsing. Junit files are written before they are
+    // parsed, and `TRUNK_NO_MMAP` is for where they could be truncated meanwhile.
+    match unsafe { memmap2::Mmap::map(file) } {
+        Ok(mmap) => Some(mmap),
+        Err(e) => {
+            log::debug!("Failed to memory-map junit file, reading it instead: {}", e);
+            None
+        }
+    }
+}
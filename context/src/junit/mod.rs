@@ -1,6 +1,22 @@
+pub mod attr_map;
 #[cfg(feature = "bindings")]
 pub mod bindings;
 mod date_parser;
+pub mod diff;
+pub mod encoding;
+pub mod failure_summary;
+pub mod file_path;
+pub mod internal_bin;
 pub mod junit_path;
+pub mod merge;
+pub mod mmap;
 pub mod parser;
+pub mod root_sniff;
+pub mod sanitize;
+pub mod schema;
+pub mod skip_reason;
+pub mod stats;
+pub mod stream;
+pub mod test_name;
+pub mod timings;
 pub mod validator;
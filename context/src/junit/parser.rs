@@ -1,26 +1,42 @@
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fmt::{Display, Formatter, Result},
-    io::BufRead,
+    fs::File,
+    io::{BufRead, BufReader},
     mem,
+    path::Path,
+    time::Duration,
 };
 
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
 use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pyclass_enum};
-use quick_junit::{NonSuccessKind, Report, TestCase, TestCaseStatus, TestRerun, TestSuite};
+use quick_junit::{
+    NonSuccessKind, Report, TestCase, TestCaseStatus, TestRerun, TestSuite, XmlString,
+};
 use quick_xml::{
     events::{BytesStart, BytesText, Event},
     Reader,
 };
+use serde::Serialize;
 use thiserror::Error;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use super::date_parser::JunitDateParser;
+#[cfg(target_pointer_width = "64")]
+use super::mmap::map_junit_file;
+use super::{
+    attr_map::JunitAttrMap,
+    date_parser::JunitDateParser,
+    encoding::{decode_xml, decode_xml_slice},
+    file_path::{file_path_from_classname, normalize_file_path},
+    test_name::{sanitize_test_name, truncate_test_name},
+};
 
-const TAG_REPORT: &[u8] = b"testsuites";
-const TAG_TEST_SUITE: &[u8] = b"testsuite";
+pub(crate) const TAG_REPORT: &[u8] = b"testsuites";
+pub(crate) const TAG_TEST_SUITE: &[u8] = b"testsuite";
 const TAG_TEST_CASE: &[u8] = b"testcase";
 const TAG_TEST_CASE_STATUS_FAILURE: &[u8] = b"failure";
 const TAG_TEST_CASE_STATUS_ERROR: &[u8] = b"error";
@@ -33,11 +49,41 @@ const TAG_TEST_RERUN_STACK_TRACE: &[u8] = b"stackTrace";
 const TAG_SYSTEM_OUT: &[u8] = b"system-out";
 const TAG_SYSTEM_ERR: &[u8] = b"system-err";
 
+pub const DEFAULT_MAX_TEST_NAME_LEN: usize = 1_000;
+pub const DEFAULT_TEST_SUITE_NAME_SEPARATOR: &str = " > ";
+/// Test suites nested deeper than this are merged into their parent instead of being flattened
+pub const MAX_TEST_SUITE_DEPTH: usize = 32;
+/// Longer durations are assumed to be bogus and are clamped
+pub const MAX_TIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 pub mod extra_attrs {
     pub const FILE: &str = "file";
     pub const FILEPATH: &str = "filepath";
     pub const LINE: &str = "line";
     pub const ID: &str = "id";
+    /// Test suite attributes, e.g. the device and module of sharded Android tests
+    pub const HOSTNAME: &str = "hostname";
+    pub const PACKAGE: &str = "package";
+    /// Set when control characters or repeated whitespace were removed from the name or classname
+    pub const NAME_SANITIZED: &str = "name_sanitized";
+    /// Set to the length of the name before it was truncated
+    pub const ORIGINAL_NAME_LEN: &str = "original_name_len";
+    /// Set to the file inferred from a path-like classname, when there is no `file` or `filepath`
+    pub const FILE_INFERRED_FROM_CLASSNAME: &str = "file_inferred_from_classname";
+    /// Set to the mappings of the attr map that were applied, e.g. `file=source,line=lineno`
+    pub const MAPPED_ATTRS: &str = "mapped_attrs";
+    /// Set when the test runner already knew the test case to be quarantined
+    pub const QUARANTINED: &str = "quarantined";
+}
+
+/// The `file` of a test case, or else the file inferred from its classname. Only `file` is part of
+/// the test's ID, so inferring a file doesn't change the IDs of existing tests.
+pub fn file_or_inferred(test_case: &TestCase) -> Option<&XmlString> {
+    test_case.extra.get(extra_attrs::FILE).or_else(|| {
+        test_case
+            .extra
+            .get(extra_attrs::FILE_INFERRED_FROM_CLASSNAME)
+    })
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -57,7 +103,7 @@ impl Display for JunitParseIssue {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum JunitParseIssueLevel {
     Valid = 0,
     SubOptimal = 1,
@@ -83,6 +129,12 @@ impl From<&JunitParseIssue> for JunitParseIssueLevel {
 pub enum JunitParseIssueSubOptimal {
     #[error("no reports found")]
     ReportNotFound,
+    #[error("{0} undecodable byte sequences replaced with U+FFFD")]
+    UndecodableBytesReplaced(usize),
+    #[error("test suites nested more than {0} deep were merged into their parent")]
+    TestSuiteMaxDepthExceeded(usize),
+    #[error("durations written in a non-standard number format were normalized")]
+    TimeNonStandardFormat,
 }
 
 #[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
@@ -109,7 +161,7 @@ pub enum JunitParseIssueInvalid {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct JunitParseFlatIssue {
     pub level: JunitParseIssueLevel,
     pub error_message: String,
@@ -122,6 +174,12 @@ enum Text {
     StackTrace(Option<String>),
 }
 
+#[derive(Debug, Clone)]
+struct OpenTestSuite {
+    test_suite: TestSuite,
+    has_nested_test_suites: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum CurrentReportState {
     Default,
@@ -136,11 +194,19 @@ pub struct JunitParser {
     reports: Vec<Report>,
     current_report: Report,
     current_report_state: CurrentReportState,
-    current_test_suite: Option<TestSuite>,
+    current_test_suites: Vec<OpenTestSuite>,
     current_test_suite_depth: usize,
     current_test_case: Option<TestCase>,
     current_test_rerun: Option<TestRerun>,
     current_text: Option<Text>,
+    repo_root: Option<String>,
+    max_test_name_len: usize,
+    infer_file_from_classname: bool,
+    test_suite_name_separator: String,
+    attr_map: JunitAttrMap,
+    #[cfg_attr(not(target_pointer_width = "64"), allow(dead_code))]
+    mmap: bool,
+    xml_strings: XmlStrings,
 }
 
 impl Default for JunitParser {
@@ -157,14 +223,73 @@ impl JunitParser {
             reports: Default::default(),
             current_report: Report::new(""),
             current_report_state: CurrentReportState::Default,
-            current_test_suite: Default::default(),
+            current_test_suites: Default::default(),
             current_test_suite_depth: Default::default(),
             current_test_case: Default::default(),
             current_test_rerun: Default::default(),
             current_text: Default::default(),
+            repo_root: Default::default(),
+            max_test_name_len: DEFAULT_MAX_TEST_NAME_LEN,
+            infer_file_from_classname: true,
+            test_suite_name_separator: String::from(DEFAULT_TEST_SUITE_NAME_SEPARATOR),
+            attr_map: Default::default(),
+            mmap: false,
+            xml_strings: Default::default(),
         }
     }
 
+    /// Test file paths inside `repo_root` are made relative to it while parsing.
+    pub fn with_repo_root<T: Into<String>>(repo_root: T) -> Self {
+        Self {
+            repo_root: Some(repo_root.into()),
+            ..Self::new()
+        }
+    }
+
+    /// Test case names longer than `max_test_name_len` bytes are truncated while parsing.
+    pub fn with_max_test_name_len(self, max_test_name_len: usize) -> Self {
+        Self {
+            max_test_name_len,
+            ..self
+        }
+    }
+
+    /// Test cases without a `file` or `filepath` whose classname looks like a test file path, as
+    /// reported by e.g. Vitest, get the classname as their inferred file, see
+    /// [`file_or_inferred`]. Enabled by default.
+    pub fn with_infer_file_from_classname(self, infer_file_from_classname: bool) -> Self {
+        Self {
+            infer_file_from_classname,
+            ..self
+        }
+    }
+
+    /// Test suites nested inside other test suites are flattened into their own test suite, named
+    /// after all of the test suites they're nested in, joined by `test_suite_name_separator`.
+    pub fn with_test_suite_name_separator<T: Into<String>>(
+        self,
+        test_suite_name_separator: T,
+    ) -> Self {
+        Self {
+            test_suite_name_separator: test_suite_name_separator.into(),
+            ..self
+        }
+    }
+
+    /// Test suites and test cases without a `file`, `filepath`, `line` or `id` read it from the
+    /// attribute `attr_map` maps it to, if any.
+    pub fn with_attr_map(self, attr_map: JunitAttrMap) -> Self {
+        Self { attr_map, ..self }
+    }
+
+    /// Files parsed with [`Self::parse_file`] of at least
+    /// [`MMAP_MIN_FILE_SIZE`](super::mmap::MMAP_MIN_FILE_SIZE) bytes are memory-mapped instead of
+    /// read through a buffer on 64-bit platforms, unless
+    /// [`NO_MMAP_ENV_VAR`](super::mmap::NO_MMAP_ENV_VAR) is set. Disabled by default.
+    pub fn with_mmap(self, mmap: bool) -> Self {
+        Self { mmap, ..self }
+    }
+
     pub fn issues(&self) -> &Vec<JunitParseIssue> {
         &self.issues
     }
@@ -188,19 +313,64 @@ impl JunitParser {
         self.reports
     }
 
-    pub fn parse<R: BufRead>(&mut self, xml: R) -> anyhow::Result<()> {
-        let mut reader = Reader::from_reader(xml);
+    /// Parses junit XML, transcoding it to UTF-8 first if it has a BOM or declares another encoding.
+    pub fn parse<R: BufRead>(&mut self, mut xml: R) -> anyhow::Result<()> {
+        let mut bytes = Vec::new();
+        xml.read_to_end(&mut bytes)?;
+        let decoded_xml = decode_xml(bytes);
+        self.parse_decoded(&decoded_xml.xml, decoded_xml.replacements)
+    }
+
+    /// Like [`Self::parse`] for XML held in memory, e.g. a memory-mapped file, which is parsed
+    /// where it is unless it needs transcoding.
+    pub fn parse_bytes(&mut self, xml: &[u8]) -> anyhow::Result<()> {
+        let (xml, replacements) = decode_xml_slice(xml);
+        self.parse_decoded(&xml, replacements)
+    }
+
+    fn parse_decoded(&mut self, xml: &str, replacements: usize) -> anyhow::Result<()> {
+        // events borrow from the decoded XML instead of being copied into a buffer
+        let mut reader = Reader::from_str(xml);
         reader.config_mut().trim_text(true);
 
-        let mut buf = Vec::new();
-        loop {
-            if self
-                .match_event(reader.read_event_into(&mut buf)?)
-                .is_none()
-            {
-                break;
+        let issues_start = self.start_parse();
+        while self.match_event(reader.read_event()?).is_some() {}
+        self.finish_parse(issues_start, replacements);
+
+        Ok(())
+    }
+
+    /// Parses the junit file at `path`, falling back to reading it through a buffer when it can't
+    /// be memory-mapped.
+    pub fn parse_file<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        let file = File::open(path)?;
+        #[cfg(target_pointer_width = "64")]
+        if self.mmap {
+            if let Some(mmap) = map_junit_file(&file) {
+                return self.parse_bytes(&mmap);
             }
-            buf.clear();
+        }
+        self.parse(BufReader::new(file))
+    }
+
+    /// Returns where the issues found while parsing start
+    pub(crate) fn start_parse(&mut self) -> usize {
+        self.xml_strings.clear();
+        self.issues.len()
+    }
+
+    /// Records the issues only known once all of the XML was parsed, with undecodable bytes
+    /// reported before the issues found while parsing, at `issues_start`
+    pub(crate) fn finish_parse(&mut self, issues_start: usize, replacements: usize) {
+        self.xml_strings.clear();
+
+        if replacements > 0 {
+            self.issues.insert(
+                issues_start,
+                JunitParseIssue::SubOptimal(JunitParseIssueSubOptimal::UndecodableBytesReplaced(
+                    replacements,
+                )),
+            );
         }
 
         match self.reports.len() {
@@ -214,11 +384,9 @@ impl JunitParser {
                 JunitParseIssueInvalid::ReportMultipleFound,
             )),
         };
-
-        Ok(())
     }
 
-    fn match_event(&mut self, event: Event) -> Option<()> {
+    pub(crate) fn match_event(&mut self, event: Event) -> Option<()> {
         match event {
             Event::Eof => {
                 self.close_default_report();
@@ -333,7 +501,7 @@ impl JunitParser {
             report.set_timestamp(timestamp);
         }
 
-        if let Some(time) = parse_attr::time(e) {
+        if let Some(time) = self.parse_time(e) {
             report.set_time(time);
         }
 
@@ -361,59 +529,159 @@ impl JunitParser {
         self.reports.push(report);
     }
 
+    fn parse_time(&mut self, e: &BytesStart) -> Option<Duration> {
+        let parse_attr::ParsedTime {
+            duration,
+            non_standard_format,
+        } = parse_attr::time(e)?;
+        if non_standard_format {
+            let issue =
+                JunitParseIssue::SubOptimal(JunitParseIssueSubOptimal::TimeNonStandardFormat);
+            if !self.issues.contains(&issue) {
+                self.issues.push(issue);
+            }
+        }
+        Some(duration)
+    }
+
+    /// Parses `attr`, or the attribute the attr map maps it to if there is no `attr`, in which
+    /// case the mapping is added to `mapped_attrs`.
+    fn parse_mapped_attr<'a>(
+        &self,
+        e: &'a BytesStart<'a>,
+        attr: &'static str,
+        mapped_attrs: &mut Vec<String>,
+    ) -> Option<Cow<'a, str>> {
+        if let Some(value) = parse_attr::string(e, attr) {
+            return Some(value);
+        }
+        let mapping = self.attr_map.get(attr)?;
+        let value = parse_attr::string(e, &mapping.junit_attr)?;
+        mapped_attrs.push(mapping.to_string());
+        Some(value)
+    }
+
     fn open_test_suite(&mut self, e: &BytesStart) {
         self.current_test_suite_depth += 1;
-        if self.current_test_suite_depth > 1 {
-            return; // Ignore all but outermost test suite in set of nested test suites
+        if self.current_test_suite_depth > MAX_TEST_SUITE_DEPTH {
+            // Test cases of test suites nested too deep are added to the deepest open test suite
+            let issue = JunitParseIssue::SubOptimal(
+                JunitParseIssueSubOptimal::TestSuiteMaxDepthExceeded(MAX_TEST_SUITE_DEPTH),
+            );
+            if !self.issues.contains(&issue) {
+                self.issues.push(issue);
+            }
+            return;
         }
 
         let test_suite_name = parse_attr::name(e).unwrap_or_default();
-        let mut test_suite = TestSuite::new(test_suite_name);
+        let test_suite_name = match self.current_test_suites.last() {
+            Some(parent) if test_suite_name.is_empty() => {
+                String::from(parent.test_suite.name.as_str())
+            }
+            Some(parent) => format!(
+                "{}{}{}",
+                parent.test_suite.name.as_str(),
+                self.test_suite_name_separator,
+                test_suite_name
+            ),
+            None => String::from(test_suite_name),
+        };
+        let mut test_suite = TestSuite::new(self.xml_strings.get(&test_suite_name));
 
         if let Some(timestamp) = parse_attr::timestamp(e, &mut self.date_parser) {
             test_suite.set_timestamp(timestamp);
         }
 
-        if let Some(time) = parse_attr::time(e) {
+        if let Some(time) = self.parse_time(e) {
             test_suite.set_time(time);
         }
 
-        if let Some(file) = parse_attr::file(e) {
+        let mut mapped_attrs = Vec::new();
+        for key in [extra_attrs::FILE, extra_attrs::FILEPATH] {
+            if let Some(file) = self.parse_mapped_attr(e, key, &mut mapped_attrs) {
+                let file = self.xml_strings.file_path(&file, self.repo_root.as_deref());
+                test_suite.extra.insert(self.xml_strings.get(key), file);
+            }
+        }
+
+        if let Some(id) = self.parse_mapped_attr(e, extra_attrs::ID, &mut mapped_attrs) {
             test_suite
                 .extra
-                .insert(extra_attrs::FILE.into(), file.into());
+                .insert(self.xml_strings.get(extra_attrs::ID), id.into());
         }
 
-        if let Some(filepath) = parse_attr::filepath(e) {
+        if let Some(line) = self
+            .parse_mapped_attr(e, extra_attrs::LINE, &mut mapped_attrs)
+            .and_then(canonical_line)
+        {
             test_suite
                 .extra
-                .insert(extra_attrs::FILEPATH.into(), filepath.into());
+                .insert(self.xml_strings.get(extra_attrs::LINE), line.into());
         }
 
-        if let Some(id) = parse_attr::id(e) {
-            test_suite.extra.insert(extra_attrs::ID.into(), id.into());
+        if !mapped_attrs.is_empty() {
+            test_suite.extra.insert(
+                self.xml_strings.get(extra_attrs::MAPPED_ATTRS),
+                mapped_attrs.join(",").into(),
+            );
         }
 
-        if let Some(line) = parse_attr::line(e) {
-            test_suite
-                .extra
-                .insert(extra_attrs::LINE.into(), line.to_string().into());
+        for key in [extra_attrs::HOSTNAME, extra_attrs::PACKAGE] {
+            if let Some(value) = parse_attr::suite_attr(e, key) {
+                test_suite
+                    .extra
+                    .insert(self.xml_strings.get(key), self.xml_strings.get(&value));
+            }
+        }
+
+        if let Some(parent) = self.current_test_suites.last_mut() {
+            parent.has_nested_test_suites = true;
+            let parent = &parent.test_suite;
+            if test_suite.timestamp.is_none() {
+                test_suite.timestamp = parent.timestamp;
+            }
+            for key in [
+                extra_attrs::FILE,
+                extra_attrs::FILEPATH,
+                extra_attrs::HOSTNAME,
+                extra_attrs::PACKAGE,
+            ] {
+                if let (false, Some(value)) =
+                    (test_suite.extra.contains_key(key), parent.extra.get(key))
+                {
+                    test_suite
+                        .extra
+                        .insert(self.xml_strings.get(key), value.clone());
+                }
+            }
         }
 
-        self.current_test_suite = Some(test_suite);
+        self.current_test_suites.push(OpenTestSuite {
+            test_suite,
+            has_nested_test_suites: false,
+        });
     }
 
     fn close_test_suite(&mut self) {
-        self.current_test_suite_depth -= 1;
-        if self.current_test_suite_depth > 0 {
-            return; // Ignore all but outermost test suite in set of nested test suites
+        let test_suite_depth = self.current_test_suite_depth;
+        self.current_test_suite_depth = test_suite_depth.saturating_sub(1);
+        if test_suite_depth > MAX_TEST_SUITE_DEPTH {
+            return;
         }
 
-        if let Some(test_suite) = self.current_test_suite.take() {
+        if let Some(OpenTestSuite {
+            test_suite,
+            has_nested_test_suites,
+        }) = self.current_test_suites.pop()
+        {
             if self.current_report_state == CurrentReportState::Default {
                 self.current_report_state = CurrentReportState::DefaultWithTestSuites
             }
-            self.current_report.add_test_suite(test_suite);
+            // Test suites that only group other test suites are replaced by the flattened ones
+            if !has_nested_test_suites || !test_suite.test_cases.is_empty() {
+                self.current_report.add_test_suite(test_suite);
+            }
         } else {
             self.issues.push(JunitParseIssue::Invalid(
                 JunitParseIssueInvalid::TestSuiteStartTagNotFound,
@@ -422,7 +690,17 @@ impl JunitParser {
     }
 
     fn open_test_case(&mut self, e: &BytesStart) {
-        let test_case_name = parse_attr::name(e).unwrap_or_default();
+        let raw_test_case_name = parse_attr::untruncated_name(e).unwrap_or_default();
+        let (test_case_name, mut name_sanitized) = match sanitize_test_name(&raw_test_case_name) {
+            Some(sanitized_name) => (Cow::Owned(sanitized_name), true),
+            None => (Cow::Borrowed(raw_test_case_name.trim()), false),
+        };
+        let original_name_len = test_case_name.len();
+        let (test_case_name, name_truncated) =
+            match truncate_test_name(&test_case_name, self.max_test_name_len) {
+                Some(truncated_name) => (Cow::Owned(truncated_name), true),
+                None => (test_case_name, false),
+            };
         if test_case_name.is_empty() {
             self.issues.push(JunitParseIssue::Invalid(
                 JunitParseIssueInvalid::TestCaseName,
@@ -430,11 +708,18 @@ impl JunitParser {
         };
         let mut test_case = TestCase::new(test_case_name, TestCaseStatus::success());
 
+        if name_truncated {
+            test_case.extra.insert(
+                self.xml_strings.get(extra_attrs::ORIGINAL_NAME_LEN),
+                original_name_len.to_string().into(),
+            );
+        }
+
         if let Some(timestamp) = parse_attr::timestamp(e, &mut self.date_parser) {
             test_case.set_timestamp(timestamp);
         }
 
-        if let Some(time) = parse_attr::time(e) {
+        if let Some(time) = self.parse_time(e) {
             test_case.set_time(time);
         }
 
@@ -443,36 +728,81 @@ impl JunitParser {
         }
 
         if let Some(classname) = parse_attr::classname(e) {
-            test_case.set_classname(classname);
+            if let Some(sanitized_classname) = sanitize_test_name(&classname) {
+                test_case.set_classname(self.xml_strings.get(&sanitized_classname));
+                name_sanitized = true;
+            } else {
+                test_case.set_classname(self.xml_strings.get(&classname));
+            }
+        }
+
+        if name_sanitized {
+            test_case.extra.insert(
+                self.xml_strings.get(extra_attrs::NAME_SANITIZED),
+                self.xml_strings.get("true"),
+            );
+        }
+
+        let mut mapped_attrs = Vec::new();
+        for key in [extra_attrs::FILE, extra_attrs::FILEPATH] {
+            if let Some(file) = self.parse_mapped_attr(e, key, &mut mapped_attrs) {
+                let file = self.xml_strings.file_path(&file, self.repo_root.as_deref());
+                test_case.extra.insert(self.xml_strings.get(key), file);
+            }
         }
 
-        if let Some(file) = parse_attr::file(e) {
+        if self.infer_file_from_classname
+            && !test_case.extra.contains_key(extra_attrs::FILE)
+            && !test_case.extra.contains_key(extra_attrs::FILEPATH)
+        {
+            if let Some(file) = test_case
+                .classname
+                .as_ref()
+                .and_then(|classname| file_path_from_classname(classname.as_str()))
+                .map(|file| self.xml_strings.file_path(file, self.repo_root.as_deref()))
+            {
+                test_case.extra.insert(
+                    self.xml_strings
+                        .get(extra_attrs::FILE_INFERRED_FROM_CLASSNAME),
+                    file,
+                );
+            }
+        }
+
+        if let Some(id) = self.parse_mapped_attr(e, extra_attrs::ID, &mut mapped_attrs) {
             test_case
                 .extra
-                .insert(extra_attrs::FILE.into(), file.into());
+                .insert(self.xml_strings.get(extra_attrs::ID), id.into());
         }
 
-        if let Some(filepath) = parse_attr::filepath(e) {
+        if let Some(line) = self
+            .parse_mapped_attr(e, extra_attrs::LINE, &mut mapped_attrs)
+            .and_then(canonical_line)
+        {
             test_case
                 .extra
-                .insert(extra_attrs::FILEPATH.into(), filepath.into());
+                .insert(self.xml_strings.get(extra_attrs::LINE), line.into());
         }
 
-        if let Some(id) = parse_attr::id(e) {
-            test_case.extra.insert(extra_attrs::ID.into(), id.into());
+        if !mapped_attrs.is_empty() {
+            test_case.extra.insert(
+                self.xml_strings.get(extra_attrs::MAPPED_ATTRS),
+                mapped_attrs.join(",").into(),
+            );
         }
 
-        if let Some(line) = parse_attr::line(e) {
-            test_case
-                .extra
-                .insert(extra_attrs::LINE.into(), line.to_string().into());
+        if parse_attr::quarantined(e) {
+            test_case.extra.insert(
+                self.xml_strings.get(extra_attrs::QUARANTINED),
+                self.xml_strings.get("true"),
+            );
         }
 
         self.current_test_case = Some(test_case);
     }
 
     fn close_test_case(&mut self) {
-        if let Some(test_suite) = self.current_test_suite.as_mut() {
+        if let Some(OpenTestSuite { test_suite, .. }) = self.current_test_suites.last_mut() {
             if let Some(test_case) = self.current_test_case.take() {
                 test_suite.add_test_case(test_case);
             } else {
@@ -509,9 +839,13 @@ impl JunitParser {
             }
 
             if let Some(r#type) = parse_attr::r#type(e) {
-                test_case_status.set_type(r#type);
+                test_case_status.set_type(self.xml_strings.get(&r#type));
             }
 
+            // reruns written before the status element were taken as flaky runs of a success
+            if let TestCaseStatus::Success { flaky_runs } = &mut test_case.status {
+                test_case_status.add_reruns(mem::take(flaky_runs));
+            }
             test_case.status = test_case_status;
         } else {
             self.issues.push(JunitParseIssue::Invalid(
@@ -541,7 +875,7 @@ impl JunitParser {
             test_rerun.set_timestamp(timestamp);
         }
 
-        if let Some(time) = parse_attr::time(e) {
+        if let Some(time) = self.parse_time(e) {
             test_rerun.set_time(time);
         }
 
@@ -550,7 +884,7 @@ impl JunitParser {
         }
 
         if let Some(r#type) = parse_attr::r#type(e) {
-            test_rerun.set_type(r#type);
+            test_rerun.set_type(self.xml_strings.get(&r#type));
         }
 
         self.current_test_rerun = Some(test_rerun);
@@ -592,7 +926,8 @@ impl JunitParser {
                 Text::SystemErr(v) => v,
                 Text::StackTrace(v) => v,
             };
-            *inner_value = Some(String::from(value));
+            // text can come in several events, e.g. plain text followed by CDATA
+            inner_value.get_or_insert_with(String::new).push_str(&value);
         }
     }
 
@@ -620,7 +955,7 @@ impl JunitParser {
                 }
                 _ => (),
             };
-        } else if let Some(test_suite) = self.current_test_suite.as_mut() {
+        } else if let Some(OpenTestSuite { test_suite, .. }) = self.current_test_suites.last_mut() {
             match self.current_text.take() {
                 Some(Text::SystemOut(Some(s))) => {
                     test_suite.set_system_out(s);
@@ -634,19 +969,74 @@ impl JunitParser {
     }
 }
 
+/// The line as it's written if it's written the way it would be formatted, as nearly every line is
+fn canonical_line(line: Cow<'_, str>) -> Option<Cow<'_, str>> {
+    let parsed_line = line.parse::<usize>().ok()?;
+    let canonical =
+        line.bytes().all(|b| b.is_ascii_digit()) && (line == "0" || !line.starts_with('0'));
+    if canonical {
+        Some(line)
+    } else {
+        Some(Cow::Owned(parsed_line.to_string()))
+    }
+}
+
+/// Strings that repeat across the test cases of a junit file, e.g. classnames, file paths and
+/// extra attr names, so that each of them is stripped of ANSI escapes and allocated once, and
+/// only cloned afterwards. Only lives as long as a parse, so that it doesn't grow across files.
+#[derive(Debug, Clone, Default)]
+struct XmlStrings {
+    xml_strings: HashMap<Box<str>, XmlString>,
+    file_paths: HashMap<Box<str>, XmlString>,
+}
+
+impl XmlStrings {
+    fn get(&mut self, value: &str) -> XmlString {
+        if let Some(xml_string) = self.xml_strings.get(value) {
+            return xml_string.clone();
+        }
+        let xml_string = XmlString::new(value);
+        self.xml_strings.insert(value.into(), xml_string.clone());
+        xml_string
+    }
+
+    /// The file path normalized by [`normalize_file_path`]
+    fn file_path(&mut self, file_path: &str, repo_root: Option<&str>) -> XmlString {
+        if let Some(xml_string) = self.file_paths.get(file_path) {
+            return xml_string.clone();
+        }
+        let xml_string = XmlString::new(normalize_file_path(file_path, repo_root));
+        self.file_paths.insert(file_path.into(), xml_string.clone());
+        xml_string
+    }
+
+    fn clear(&mut self) {
+        self.xml_strings.clear();
+        self.file_paths.clear();
+    }
+}
+
 mod parse_attr {
     use std::{borrow::Cow, str::FromStr, time::Duration};
 
     use chrono::{DateTime, FixedOffset};
     use quick_xml::events::BytesStart;
 
-    use super::{extra_attrs, unescape_and_truncate};
+    use super::{unescape_and_truncate, MAX_TIME};
     use crate::junit::date_parser::JunitDateParser;
 
     pub fn name<'a>(e: &'a BytesStart<'a>) -> Option<Cow<'a, str>> {
         parse_string_attr(e, "name")
     }
 
+    /// Test case names are truncated separately, after they have been sanitized
+    pub fn untruncated_name<'a>(e: &'a BytesStart<'a>) -> Option<Cow<'a, str>> {
+        e.try_get_attribute("name")
+            .ok()
+            .flatten()
+            .and_then(|attr| attr.unescape_value().ok())
+    }
+
     pub fn timestamp(
         e: &BytesStart,
         date_parser: &mut JunitDateParser,
@@ -654,9 +1044,72 @@ mod parse_attr {
         parse_string_attr(e, "timestamp").and_then(|value| date_parser.parse_date(&value))
     }
 
-    pub fn time(e: &BytesStart) -> Option<Duration> {
-        parse_string_attr_into_other_type(e, "time")
-            .map(|seconds: f64| Duration::from_secs_f64(seconds))
+    pub struct ParsedTime {
+        pub duration: Duration,
+        /// Set when the value was not a plain decimal number, e.g. `0,0123` or `1.5e-3`
+        pub non_standard_format: bool,
+    }
+
+    pub fn time(e: &BytesStart) -> Option<ParsedTime> {
+        let value = parse_string_attr(e, "time")?;
+        let (seconds, non_standard_format) = parse_seconds(&value)?;
+        let duration = if seconds < 0.0 {
+            log::warn!("Negative time {:?} clamped to 0", value);
+            Duration::ZERO
+        } else if seconds > MAX_TIME.as_secs_f64() {
+            log::warn!("Time {:?} clamped to {:?}", value, MAX_TIME);
+            MAX_TIME
+        } else {
+            Duration::from_secs_f64(seconds)
+        };
+        Some(ParsedTime {
+            duration,
+            non_standard_format,
+        })
+    }
+
+    /// Parses seconds written as a plain decimal number, falling back to locale specific decimal
+    /// and thousands separators (`0,0123`, `1.234,56`) and scientific notation. Surrounding
+    /// whitespace is trimmed along with the attribute value.
+    fn parse_seconds(value: &str) -> Option<(f64, bool)> {
+        let trimmed = value.trim();
+        if is_plain_decimal(trimmed) {
+            if let Ok(seconds) = trimmed.parse::<f64>() {
+                return Some((seconds, false));
+            }
+        }
+
+        let (mantissa, exponent) =
+            trimmed.split_at(trimmed.find(['e', 'E']).unwrap_or(trimmed.len()));
+        // The last separator is the decimal separator, unless it is repeated, e.g. `1.234.567`
+        let decimal_separator = match (mantissa.rfind('.'), mantissa.rfind(',')) {
+            (Some(dot), Some(comma)) if comma > dot => Some(','),
+            (Some(_), Some(_)) => Some('.'),
+            (None, Some(_)) if mantissa.matches(',').count() == 1 => Some(','),
+            (Some(_), None) if mantissa.matches('.').count() == 1 => Some('.'),
+            _ => None,
+        };
+        let normalized: String = mantissa
+            .chars()
+            .filter_map(|c| match c {
+                c if Some(c) == decimal_separator => Some('.'),
+                ',' | '.' => None,
+                c => Some(c),
+            })
+            .chain(exponent.chars())
+            .collect();
+        normalized
+            .parse::<f64>()
+            .ok()
+            .filter(|seconds| seconds.is_finite())
+            .map(|seconds| (seconds, true))
+    }
+
+    fn is_plain_decimal(value: &str) -> bool {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        !digits.is_empty()
+            && digits.matches('.').count() <= 1
+            && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
     }
 
     pub fn assertions(e: &BytesStart) -> Option<usize> {
@@ -675,26 +1128,25 @@ mod parse_attr {
         parse_string_attr(e, "type")
     }
 
-    pub fn file<'a>(e: &'a BytesStart<'a>) -> Option<Cow<'a, str>> {
-        parse_string_attr(e, extra_attrs::FILE)
-    }
-
-    pub fn filepath<'a>(e: &'a BytesStart<'a>) -> Option<Cow<'a, str>> {
-        parse_string_attr(e, extra_attrs::FILEPATH)
+    /// `file`, `filepath`, `line`, `id` or the attribute they are mapped to
+    pub fn string<'a>(e: &'a BytesStart<'a>, attr_name: &str) -> Option<Cow<'a, str>> {
+        parse_string_attr(e, attr_name)
     }
 
-    pub fn id<'a>(e: &'a BytesStart<'a>) -> Option<Cow<'a, str>> {
-        parse_string_attr(e, extra_attrs::ID)
+    /// Only `true` and `1` mark a test case as quarantined
+    pub fn quarantined(e: &BytesStart) -> bool {
+        parse_string_attr(e, "quarantined").is_some_and(|value| {
+            let value = value.trim();
+            value == "1" || value.eq_ignore_ascii_case("true")
+        })
     }
 
-    pub fn line<'a>(e: &'a BytesStart<'a>) -> Option<usize> {
-        parse_string_attr_into_other_type(e, extra_attrs::LINE)
+    /// `hostname` or `package`
+    pub fn suite_attr<'a>(e: &'a BytesStart<'a>, attr_name: &'static str) -> Option<Cow<'a, str>> {
+        parse_string_attr(e, attr_name).filter(|value| !value.is_empty())
     }
 
-    fn parse_string_attr<'a>(
-        e: &'a BytesStart<'a>,
-        attr_name: &'static str,
-    ) -> Option<Cow<'a, str>> {
+    fn parse_string_attr<'a>(e: &'a BytesStart<'a>, attr_name: &str) -> Option<Cow<'a, str>> {
         e.try_get_attribute(attr_name)
             .ok()
             .flatten()
@@ -709,15 +1161,17 @@ mod parse_attr {
     }
 }
 
+/// Longest text or attribute value kept by the parser, in bytes
+pub const MAX_TEXT_FIELD_SIZE: usize = 8_000;
+
 mod unescape_and_truncate {
     use std::borrow::Cow;
 
     use quick_xml::events::{attributes::Attribute, BytesText};
 
+    use super::MAX_TEXT_FIELD_SIZE;
     use crate::string_safety::safe_truncate_str;
 
-    const MAX_TEXT_FIELD_SIZE: usize = 8_000;
-
     pub fn attr<'a>(v: &Attribute<'a>) -> Option<Cow<'a, str>> {
         v.unescape_value()
             .ok()
@@ -732,8 +1186,8 @@ mod unescape_and_truncate {
 
     fn safe_truncate_cow<const MAX_LEN: usize>(value: Cow<'_, str>) -> Cow<'_, str> {
         match value {
-            Cow::Borrowed(b) => Cow::Borrowed(safe_truncate_str::<MAX_LEN>(b)),
-            Cow::Owned(b) => Cow::Owned(String::from(safe_truncate_str::<MAX_LEN>(b.as_str()))),
+            Cow::Borrowed(b) => Cow::Borrowed(safe_truncate_str(b, MAX_LEN)),
+            Cow::Owned(b) => Cow::Owned(String::from(safe_truncate_str(b.as_str(), MAX_LEN))),
         }
     }
 }
@@ -0,0 +1,128 @@
+use std::io::Read;
+
+use quick_xml::{events::Event, Reader};
+
+use super::{
+    encoding::decode_xml,
+    parser::{TAG_REPORT, TAG_TEST_SUITE},
+};
+
+/// Bytes read from the start of a file to find its root element
+pub const ROOT_SNIFF_MAX_BYTES: usize = 8 * 1024;
+
+/// Whether the file read by `reader` may be junit, judging by the root element within its first
+/// [`ROOT_SNIFF_MAX_BYTES`] bytes. Only a root element other than `testsuites` or `testsuite`
+/// rules a file out, so files whose root element can't be found, e.g. after a long prolog, are
+/// kept to be parsed in full.
+pub fn may_be_junit<R: Read>(reader: R) -> std::io::Result<bool> {
+    let mut bytes = Vec::with_capacity(ROOT_SNIFF_MAX_BYTES);
+    reader
+        .take(ROOT_SNIFF_MAX_BYTES as u64)
+        .read_to_end(&mut bytes)?;
+    // strips any BOM and transcodes UTF-16, a character cut off at the end is replaced
+    let decoded_xml = decode_xml(bytes);
+
+    let mut reader = Reader::from_str(&decoded_xml.xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                let name = e.name();
+                return Ok(name.as_ref() == TAG_REPORT
+                    || name.as_ref() == TAG_TEST_SUITE
+                    // a malformed tag is left to the parser to report
+                    || !is_xml_name(name.as_ref()));
+            }
+            // the root element is cut off or the file is not XML, leave it to the parser
+            Ok(Event::Eof) | Err(..) => return Ok(true),
+            // declaration, comments, processing instructions, doctype and whitespace
+            Ok(..) => continue,
+        }
+    }
+}
+
+fn is_xml_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name
+            .iter()
+            .all(|&b| !b.is_ascii() || b.is_ascii_alphanumeric() || b"-_.:".contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn sniff(xml: &str) -> bool {
+        may_be_junit(Cursor::new(xml.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn finds_junit_root_elements() {
+        assert!(sniff(
+            r#"<?xml version="1.0"?><testsuites name="a"></testsuites>"#
+        ));
+        assert!(sniff(r#"<testsuite name="a" tests="1"/>"#));
+        assert!(sniff(
+            "<?xml version=\"1.0\"?>\n<!-- generated by a test runner -->\n<!DOCTYPE testsuites>\n<?xml-stylesheet href=\"junit.xsl\"?>\n<testsuites>"
+        ));
+        assert!(sniff("\u{feff}<?xml version=\"1.0\"?><testsuites>"));
+    }
+
+    #[test]
+    fn finds_junit_root_elements_in_utf16() {
+        let mut bytes = vec![0xff, 0xfe];
+        for unit in "<!-- comment --><testsuites>".encode_utf16() {
+            bytes.extend(unit.to_le_bytes());
+        }
+        assert!(may_be_junit(Cursor::new(bytes)).unwrap());
+    }
+
+    #[test]
+    fn rules_out_other_root_elements() {
+        assert!(!sniff(
+            r#"<?xml version="1.0"?><checkstyle version="10.0"><file name="a.java"/></checkstyle>"#
+        ));
+        assert!(!sniff(
+            "<!-- <testsuites> --><project><testsuite/></project>"
+        ));
+        assert!(!sniff("\u{feff}<module/>"));
+    }
+
+    #[test]
+    fn keeps_files_without_a_root_element_in_reach() {
+        assert!(sniff(""));
+        assert!(sniff("not xml at all"));
+        assert!(sniff("<bad<attrs<><><"));
+        assert!(sniff(&format!(
+            "<!--{}--><checkstyle/>",
+            "x".repeat(ROOT_SNIFF_MAX_BYTES)
+        )));
+    }
+
+    #[test]
+    fn reads_at_most_the_sniffed_bytes() {
+        struct CountingReader<R> {
+            inner: R,
+            bytes_read: usize,
+        }
+        impl<R: Read> Read for CountingReader<R> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let bytes_read = self.inner.read(buf)?;
+                self.bytes_read += bytes_read;
+                Ok(bytes_read)
+            }
+        }
+
+        let xml = format!(
+            "<checkstyle>{}</checkstyle>",
+            "<file name=\"a.java\"/>".repeat(100_000)
+        );
+        let mut reader = CountingReader {
+            inner: Cursor::new(xml.as_bytes()),
+            bytes_read: 0,
+        };
+        assert!(!may_be_junit(&mut reader).unwrap());
+        assert!(reader.bytes_read <= ROOT_SNIFF_MAX_BYTES);
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    borrow::Cow,
+    io::{BufReader, Read, Write},
+};
+
+use encoding_rs::{Encoding, UTF_8};
+use quick_xml::{
+    events::{BytesCData, BytesDecl, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+
+use super::{
+    encoding::XmlDecodingReader, parser::MAX_TEXT_FIELD_SIZE, test_name::sanitize_test_name,
+};
+use crate::string_safety::safe_truncate_str;
+
+/// What [`sanitize_junit_xml`] did to a junit file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JunitSanitization {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Whether the file was transcoded to UTF-8, or had a BOM or undecodable bytes
+    pub transcoded: bool,
+    /// Number of characters that are not allowed in XML removed
+    pub num_control_chars_removed: usize,
+    /// Number of texts and attribute values truncated to [`MAX_TEXT_FIELD_SIZE`]
+    pub num_texts_truncated: usize,
+}
+
+impl JunitSanitization {
+    pub fn changed(&self) -> bool {
+        self.transcoded || self.num_control_chars_removed > 0 || self.num_texts_truncated > 0
+    }
+
+    /// Size of the sanitized file minus the size of the original
+    pub fn size_delta(&self) -> i64 {
+        self.bytes_out as i64 - self.bytes_in as i64
+    }
+}
+
+/// Writes junit XML to `writer` as UTF-8, without the characters that are not allowed in XML and,
+/// with `truncate_text`, with texts and attribute values cut to the length the parser keeps. The
+/// result parses to the same test suites and test cases as the original, but for the removed
+/// characters, as everything else is written back as it was read, event by event. Test case names
+/// and classnames that have such characters are sanitized like the parser does, ANSI escape
+/// sequences included, so that their test IDs stay the same.
+///
+/// The XML is read from `xml` and written to `writer` as it is sanitized, event by event, so that a
+/// large file is never held in memory.
+///
+/// Fails on XML the parser can't read either, leaving what was written so far in `writer`.
+pub fn sanitize_junit_xml<R: Read, W: Write>(
+    xml: R,
+    writer: W,
+    truncate_text: bool,
+) -> anyhow::Result<JunitSanitization> {
+    let mut sanitization = JunitSanitization::default();
+
+    let mut reader = Reader::from_reader(BufReader::new(XmlDecodingReader::new(xml)));
+    let mut writer = Writer::new(CountingWriter {
+        inner: writer,
+        count: 0,
+    });
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf)? {
+            Event::Eof => break,
+            Event::Decl(e) => {
+                let declared_encoding = e
+                    .encoding()
+                    .and_then(|encoding| encoding.ok())
+                    .map(|encoding| Encoding::for_label(&encoding));
+                match declared_encoding {
+                    Some(encoding) if encoding != Some(UTF_8) => {
+                        let version = e.version()?;
+                        let standalone = e.standalone().and_then(|standalone| standalone.ok());
+                        writer.write_event(Event::Decl(BytesDecl::new(
+                            &String::from_utf8_lossy(&version),
+                            Some("UTF-8"),
+                            standalone
+                                .as_ref()
+                                .map(|standalone| String::from_utf8_lossy(standalone))
+                                .as_deref(),
+                        )))?;
+                        sanitization.transcoded = true;
+                    }
+                    _ => writer.write_event(Event::Decl(e))?,
+                }
+            }
+            Event::Start(e) => {
+                let e = sanitize_attributes(e, truncate_text, &mut sanitization);
+                writer.write_event(Event::Start(e))?;
+            }
+            Event::Empty(e) => {
+                let e = sanitize_attributes(e, truncate_text, &mut sanitization);
+                writer.write_event(Event::Empty(e))?;
+            }
+            Event::Text(e) => {
+                // text the parser can't unescape is ignored by it, so it is kept as is
+                let sanitized = e.unescape().ok().and_then(|text| {
+                    sanitize_text(&text, truncate_text, &mut sanitization).map(String::from)
+                });
+                match sanitized {
+                    Some(text) => writer.write_event(Event::Text(BytesText::new(&text)))?,
+                    None => writer.write_event(Event::Text(e))?,
+                }
+            }
+            Event::CData(e) => {
+                let sanitized = std::str::from_utf8(&e).ok().and_then(|text| {
+                    sanitize_text(text, truncate_text, &mut sanitization).map(String::from)
+                });
+                match sanitized {
+                    Some(text) => writer.write_event(Event::CData(BytesCData::new(text)))?,
+                    None => writer.write_event(Event::CData(e))?,
+                }
+            }
+            e => writer.write_event(e)?,
+        }
+    }
+
+    let mut counting_writer = writer.into_inner();
+    counting_writer.flush()?;
+    sanitization.bytes_out = counting_writer.count;
+    let decoding_reader = reader.into_inner().into_inner();
+    sanitization.bytes_in = decoding_reader.bytes_in();
+    sanitization.transcoded |= decoding_reader.decoder().transcoded();
+    Ok(sanitization)
+}
+
+/// Elements with nothing to remove or truncate, or with an attribute the parser can't read, are kept
+/// as is. Test case names are truncated by the parser to a length of their own, so they are not.
+fn sanitize_attributes<'a>(
+    e: BytesStart<'a>,
+    truncate_text: bool,
+    sanitization: &mut JunitSanitization,
+) -> BytesStart<'a> {
+    let is_test_case = e.name().as_ref() == b"testcase";
+    let Some(attributes) = e
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.ok()?;
+            let value = attribute.unescape_value().ok()?.into_owned();
+            Some((
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                value,
+            ))
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return e;
+    };
+
+    let mut num_control_chars_removed = 0;
+    let mut num_texts_truncated = 0;
+    let attributes: Vec<_> = attributes
+        .into_iter()
+        .map(|(key, value)| {
+            let is_test_case_name = is_test_case && (key == "name" || key == "classname");
+            let (mut sanitized, num_removed) = strip_invalid_chars(&value);
+            if num_removed > 0 && is_test_case_name {
+                if let Some(sanitized_name) = sanitize_test_name(&value) {
+                    sanitized = Cow::Owned(sanitized_name);
+                }
+            }
+            num_control_chars_removed += num_removed;
+            if truncate_text
+                && !(is_test_case && key == "name")
+                && sanitized.len() > MAX_TEXT_FIELD_SIZE
+            {
+                sanitized = Cow::Owned(String::from(safe_truncate_str(
+                    &sanitized,
+                    MAX_TEXT_FIELD_SIZE,
+                )));
+                num_texts_truncated += 1;
+            }
+            (key, sanitized.into_owned())
+        })
+        .collect();
+    if num_control_chars_removed == 0 && num_texts_truncated == 0 {
+        return e;
+    }
+    sanitization.num_control_chars_removed += num_control_chars_removed;
+    sanitization.num_texts_truncated += num_texts_truncated;
+
+    let mut sanitized = e.to_owned();
+    sanitized.clear_attributes();
+    for (key, value) in &attributes {
+        sanitized.push_attribute((key.as_str(), value.as_str()));
+    }
+    sanitized
+}
+
+/// Returns `None` if the text is kept as is.
+fn sanitize_text<'a>(
+    text: &'a str,
+    truncate_text: bool,
+    sanitization: &mut JunitSanitization,
+) -> Option<Cow<'a, str>> {
+    let (mut sanitized, num_removed) = strip_invalid_chars(text);
+    sanitization.num_control_chars_removed += num_removed;
+    if truncate_text && sanitized.trim().len() > MAX_TEXT_FIELD_SIZE {
+        sanitized = Cow::Owned(String::from(safe_truncate_str(
+            &sanitized,
+            MAX_TEXT_FIELD_SIZE,
+        )));
+        sanitization.num_texts_truncated += 1;
+    }
+    match sanitized {
+        Cow::Borrowed(_) => None,
+        sanitized => Some(sanitized),
+    }
+}
+
+/// Removes the characters that are not allowed in XML 1.0, i.e. the control characters other
+/// than tab, line feed and carriage return, and U+FFFE and U+FFFF.
+///
+/// Returns the number of characters removed.
+fn strip_invalid_chars(value: &str) -> (Cow<'_, str>, usize) {
+    if value.chars().all(is_allowed_in_xml) {
+        return (Cow::Borrowed(value), 0);
+    }
+
+    let mut sanitized = String::with_capacity(value.len());
+    let mut num_removed = 0;
+    for c in value.chars() {
+        if is_allowed_in_xml(c) {
+            sanitized.push(c);
+        } else {
+            num_removed += 1;
+        }
+    }
+    (Cow::Owned(sanitized), num_removed)
+}
+
+fn is_allowed_in_xml(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize(xml: &[u8]) -> (String, JunitSanitization) {
+        let mut sanitized = Vec::new();
+        let sanitization = sanitize_junit_xml(xml, &mut sanitized, true).unwrap();
+        (String::from_utf8(sanitized).unwrap(), sanitization)
+    }
+
+    #[test]
+    fn leaves_clean_xml_alone() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <!-- comment -->
+  <testsuite name="suite" tests="1">
+    <testcase name="test &amp; &lt;more&gt;" classname="a.b" time="0.1">
+      <failure message="boom"><![CDATA[at a.b(c.java:1)]]></failure>
+      <system-out>a &quot;quoted&quot; line</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        let (sanitized, sanitization) = sanitize(xml.as_bytes());
+        assert_eq!(sanitized, xml);
+        assert!(!sanitization.changed());
+        assert_eq!(sanitization.size_delta(), 0);
+    }
+
+    #[test]
+    fn strips_invalid_chars() {
+        let (sanitized, sanitization) = sanitize(
+            b"<testsuite><testcase name=\"\x1b[31mtest\x1b[0m\x01\"><system-out>a\x00b\tc\x1b[1m</system-out><failure><![CDATA[x\x08y]]></failure></testcase></testsuite>",
+        );
+        assert_eq!(
+            sanitized,
+            "<testsuite><testcase name=\"test\"><system-out>ab\tc[1m</system-out><failure><![CDATA[xy]]></failure></testcase></testsuite>"
+        );
+        assert_eq!(sanitization.num_control_chars_removed, 6);
+        assert!(sanitization.changed());
+    }
+
+    #[test]
+    fn transcodes_to_utf8() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><testsuite name=\"caf\u{e9}\"/>";
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(xml.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let (sanitized, sanitization) = sanitize(&utf16);
+        assert_eq!(
+            sanitized,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><testsuite name=\"caf\u{e9}\"/>"
+        );
+        assert!(sanitization.transcoded);
+        assert!(sanitization.size_delta() < 0);
+    }
+
+    #[test]
+    fn truncates_text() {
+        let long_name = "n".repeat(MAX_TEXT_FIELD_SIZE + 10);
+        let xml = format!(
+            "<testsuite><testcase name=\"{long_name}\"><failure message=\"{}\"/><system-out>{}</system-out></testcase></testsuite>",
+            "m".repeat(MAX_TEXT_FIELD_SIZE + 10),
+            "a".repeat(MAX_TEXT_FIELD_SIZE + 10)
+        );
+        let (sanitized, sanitization) = sanitize(xml.as_bytes());
+        assert_eq!(
+            sanitized,
+            format!(
+                "<testsuite><testcase name=\"{long_name}\"><failure message=\"{}\"/><system-out>{}</system-out></testcase></testsuite>",
+                "m".repeat(MAX_TEXT_FIELD_SIZE),
+                "a".repeat(MAX_TEXT_FIELD_SIZE)
+            )
+        );
+        assert_eq!(sanitization.num_texts_truncated, 2);
+        assert_eq!(sanitization.size_delta(), -20);
+
+        let mut untruncated = Vec::new();
+        let sanitization = sanitize_junit_xml(xml.as_bytes(), &mut untruncated, false).unwrap();
+        assert_eq!(untruncated, xml.as_bytes());
+        assert!(!sanitization.changed());
+    }
+
+    #[test]
+    fn fails_on_malformed_xml() {
+        assert!(sanitize_junit_xml(&b"<testsuite></testcase>"[..], std::io::sink(), true).is_err());
+    }
+}
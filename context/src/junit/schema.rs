@@ -0,0 +1,230 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use quick_junit::{Report, TestCaseStatus, TestRerun};
+
+use super::{parser::extra_attrs, validator::JunitReportValidationIssueInvalid};
+
+/// A dialect of junit. Reports are validated with the rules of plain junit, and with the rules of
+/// the dialect on top of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum JunitSchema {
+    /// Ant JUnit, whose rules every dialect is validated with
+    #[default]
+    Junit,
+    /// xunit2, e.g. written by pytest, which locates test cases and timestamps test suites
+    Xunit2,
+    /// Maven Surefire, which writes reruns as `rerunFailure`, `flakyFailure` etc. elements
+    Surefire,
+}
+
+impl JunitSchema {
+    pub const ALL: [JunitSchema; 3] = [
+        JunitSchema::Junit,
+        JunitSchema::Xunit2,
+        JunitSchema::Surefire,
+    ];
+
+    /// The rules the dialect adds to those of plain junit
+    pub fn rules(&self) -> &'static [JunitSchemaRule] {
+        match self {
+            JunitSchema::Junit => &[],
+            JunitSchema::Xunit2 => &[
+                JunitSchemaRule::TestCaseFile,
+                JunitSchemaRule::TestCaseLine,
+                JunitSchemaRule::TestSuiteTimestamp,
+            ],
+            JunitSchema::Surefire => {
+                &[JunitSchemaRule::RerunType, JunitSchemaRule::RerunStackTrace]
+            }
+        }
+    }
+}
+
+impl fmt::Display for JunitSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JunitSchema::Junit => write!(f, "junit"),
+            JunitSchema::Xunit2 => write!(f, "xunit2"),
+            JunitSchema::Surefire => write!(f, "surefire"),
+        }
+    }
+}
+
+impl FromStr for JunitSchema {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JunitSchema::ALL
+            .into_iter()
+            .find(|schema| schema.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown junit schema {:?}, expected one of junit, xunit2, surefire",
+                    s
+                )
+            })
+    }
+}
+
+/// A rule one of the dialects adds to those of plain junit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JunitSchemaRule {
+    /// Test cases have a `file` attribute, rather than one inferred from their classname
+    TestCaseFile,
+    /// Test cases have a `line` attribute
+    TestCaseLine,
+    /// Test suites have a `timestamp` attribute
+    TestSuiteTimestamp,
+    /// Reruns have a `type` attribute
+    RerunType,
+    /// Reruns have a `stackTrace` element
+    RerunStackTrace,
+}
+
+impl JunitSchemaRule {
+    pub const ALL: [JunitSchemaRule; 5] = [
+        JunitSchemaRule::TestCaseFile,
+        JunitSchemaRule::TestCaseLine,
+        JunitSchemaRule::TestSuiteTimestamp,
+        JunitSchemaRule::RerunType,
+        JunitSchemaRule::RerunStackTrace,
+    ];
+
+    pub fn schema(&self) -> JunitSchema {
+        match self {
+            JunitSchemaRule::TestCaseFile
+            | JunitSchemaRule::TestCaseLine
+            | JunitSchemaRule::TestSuiteTimestamp => JunitSchema::Xunit2,
+            JunitSchemaRule::RerunType | JunitSchemaRule::RerunStackTrace => JunitSchema::Surefire,
+        }
+    }
+
+    pub(crate) fn issue(&self, count: usize) -> JunitReportValidationIssueInvalid {
+        match self {
+            JunitSchemaRule::TestCaseFile => {
+                JunitReportValidationIssueInvalid::TestCasesFileMissing { count }
+            }
+            JunitSchemaRule::TestCaseLine => {
+                JunitReportValidationIssueInvalid::TestCasesLineMissing { count }
+            }
+            JunitSchemaRule::TestSuiteTimestamp => {
+                JunitReportValidationIssueInvalid::TestSuitesTimestampMissing { count }
+            }
+            JunitSchemaRule::RerunType => {
+                JunitReportValidationIssueInvalid::TestRerunsTypeMissing { count }
+            }
+            JunitSchemaRule::RerunStackTrace => {
+                JunitReportValidationIssueInvalid::TestRerunsStackTraceMissing { count }
+            }
+        }
+    }
+}
+
+/// How many elements of a report a rule was checked on, and how many of them broke it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JunitSchemaRuleCheck {
+    pub rule: JunitSchemaRule,
+    pub num_checked: usize,
+    pub num_failed: usize,
+}
+
+/// Checks `report` against the rules of every dialect, not only the one it's validated with, so
+/// that [`closest_schema`] can compare them.
+pub fn check_rules(report: &Report) -> Vec<JunitSchemaRuleCheck> {
+    let test_suites = &report.test_suites;
+    let test_cases = || {
+        test_suites
+            .iter()
+            .flat_map(|test_suite| &test_suite.test_cases)
+    };
+    let reruns = || {
+        test_cases().flat_map(|test_case| -> &[TestRerun] {
+            match &test_case.status {
+                TestCaseStatus::Success { flaky_runs } => flaky_runs,
+                TestCaseStatus::NonSuccess { reruns, .. } => reruns,
+                TestCaseStatus::Skipped { .. } => &[],
+            }
+        })
+    };
+
+    JunitSchemaRule::ALL
+        .into_iter()
+        .map(|rule| {
+            let (num_checked, num_failed) = match rule {
+                JunitSchemaRule::TestCaseFile => count(test_cases(), |test_case| {
+                    test_case.extra.contains_key(extra_attrs::FILE)
+                }),
+                JunitSchemaRule::TestCaseLine => count(test_cases(), |test_case| {
+                    test_case.extra.contains_key(extra_attrs::LINE)
+                }),
+                JunitSchemaRule::TestSuiteTimestamp => count(test_suites.iter(), |test_suite| {
+                    test_suite.timestamp.is_some()
+                }),
+                JunitSchemaRule::RerunType => count(reruns(), |rerun| rerun.ty.is_some()),
+                JunitSchemaRule::RerunStackTrace => {
+                    count(reruns(), |rerun| rerun.stack_trace.is_some())
+                }
+            };
+            JunitSchemaRuleCheck {
+                rule,
+                num_checked,
+                num_failed,
+            }
+        })
+        .collect()
+}
+
+/// Returns the number of `elements` and the number of them not passing `passes`.
+fn count<T, I: Iterator<Item = T>, F: Fn(&T) -> bool>(elements: I, passes: F) -> (usize, usize) {
+    elements.fold((0, 0), |(num_checked, num_failed), element| {
+        (num_checked + 1, num_failed + usize::from(!passes(&element)))
+    })
+}
+
+/// The dialect to suggest instead of `schema`, when at least half of the rules of `schema` fail
+/// on reports where another dialect mostly passes: the one with the most rules passing over
+/// those failing, with plain junit on ties. `None` when `schema` mostly passes.
+pub fn closest_schema<'a, T: IntoIterator<Item = &'a JunitSchemaRuleCheck>>(
+    schema: JunitSchema,
+    rule_checks: T,
+) -> Option<JunitSchema> {
+    let mut checked_and_failed_rules: HashMap<JunitSchemaRule, (bool, bool)> = HashMap::new();
+    for rule_check in rule_checks {
+        let (checked, failed) = checked_and_failed_rules.entry(rule_check.rule).or_default();
+        *checked |= rule_check.num_checked > 0;
+        *failed |= rule_check.num_failed > 0;
+    }
+    let num_passing_and_failing_rules = |schema: JunitSchema| {
+        schema.rules().iter().fold(
+            (0, 0),
+            |(passing, failing), rule| match checked_and_failed_rules.get(rule) {
+                Some((_, true)) => (passing, failing + 1),
+                Some((true, false)) => (passing + 1, failing),
+                _ => (passing, failing),
+            },
+        )
+    };
+    let mostly_fails = |schema: JunitSchema| {
+        let (_, failing) = num_passing_and_failing_rules(schema);
+        failing > 0 && failing * 2 >= schema.rules().len()
+    };
+
+    if !mostly_fails(schema) {
+        return None;
+    }
+    JunitSchema::ALL
+        .into_iter()
+        .filter(|other_schema| *other_schema != schema && !mostly_fails(*other_schema))
+        .fold(
+            None,
+            |closest: Option<(JunitSchema, isize)>, other_schema| {
+                let (passing, failing) = num_passing_and_failing_rules(other_schema);
+                let score = passing as isize - failing as isize;
+                match closest {
+                    Some((_, closest_score)) if closest_score >= score => closest,
+                    _ => Some((other_schema, score)),
+                }
+            },
+        )
+        .map(|(closest_schema, _)| closest_schema)
+}
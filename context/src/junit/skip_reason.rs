@@ -0,0 +1,168 @@
+use proto::test_context::test_run::SkipKind;
+use quick_junit::TestCaseStatus;
+
+use super::failure_summary::{summarize_failure_message, DEFAULT_FAILURE_SUMMARY_MAX_LEN};
+
+/// Where a [`SkipHeuristic`] looks for its wording
+#[derive(Debug, Clone, Copy)]
+enum SkipPattern {
+    MessageEquals(&'static str),
+    MessageStartsWith(&'static str),
+    MessageContains(&'static str),
+    TypeContains(&'static str),
+}
+
+type SkipHeuristic = (SkipPattern, SkipKind);
+
+/// Wordings of test frameworks that tell a disabled test from one skipped at runtime. Anything not
+/// matched is [`SkipKind::Other`], since a reason written by hand, e.g. of `@Disabled("...")` or
+/// `pytest.skip("...")`, says nothing about how the test was skipped.
+const SKIP_HEURISTICS: &[SkipHeuristic] = &[
+    // JUnit 5 through Surefire, e.g. "void com.example.LoginTest.logsIn() is @Disabled"
+    (
+        SkipPattern::MessageContains("is @disabled"),
+        SkipKind::Disabled,
+    ),
+    (
+        SkipPattern::MessageContains("disabled via annotation"),
+        SkipKind::Disabled,
+    ),
+    // JUnit 5 assumptions, e.g. "Assumption failed: assumption is not true"
+    (
+        SkipPattern::MessageStartsWith("assumption failed"),
+        SkipKind::Assumption,
+    ),
+    (
+        SkipPattern::TypeContains("testabortedexception"),
+        SkipKind::Assumption,
+    ),
+    // JUnit 4 assumptions
+    (
+        SkipPattern::TypeContains("assumptionviolatedexception"),
+        SkipKind::Assumption,
+    ),
+    // pytest, `@pytest.mark.skip` without a reason
+    (
+        SkipPattern::MessageEquals("unconditional skip"),
+        SkipKind::Disabled,
+    ),
+    // pytest, `pytest.importorskip`
+    (
+        SkipPattern::MessageStartsWith("could not import"),
+        SkipKind::Assumption,
+    ),
+    // xUnit, `Skip.If` of Xunit.SkippableFact
+    (
+        SkipPattern::TypeContains("skipexception"),
+        SkipKind::Assumption,
+    ),
+];
+
+/// The reason a test case was skipped: the message of `<skipped>`, or the first non-empty line of
+/// its text when it has no message.
+///
+/// Returns an empty string for test cases that weren't skipped.
+pub fn extract_skip_reason(status: &TestCaseStatus) -> String {
+    let TestCaseStatus::Skipped {
+        message,
+        description,
+        ..
+    } = status
+    else {
+        return String::new();
+    };
+
+    message
+        .as_ref()
+        .map(|message| message.as_str())
+        .filter(|message| !message.trim().is_empty())
+        .or_else(|| {
+            description.as_ref().and_then(|description| {
+                description
+                    .as_str()
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+            })
+        })
+        .map(|reason| summarize_failure_message(reason, DEFAULT_FAILURE_SUMMARY_MAX_LEN))
+        .unwrap_or_default()
+}
+
+/// Infers how a test case was skipped from the message and type of `<skipped>`, see
+/// [`SKIP_HEURISTICS`].
+///
+/// Returns [`SkipKind::Unspecified`] for test cases that weren't skipped.
+pub fn infer_skip_kind(status: &TestCaseStatus) -> SkipKind {
+    let TestCaseStatus::Skipped { message, ty, .. } = status else {
+        return SkipKind::Unspecified;
+    };
+
+    let message = message
+        .as_ref()
+        .map(|message| message.trim().to_lowercase())
+        .unwrap_or_default();
+    let ty = ty
+        .as_ref()
+        .map(|ty| ty.trim().to_lowercase())
+        .unwrap_or_default();
+    SKIP_HEURISTICS
+        .iter()
+        .find(|(pattern, _)| match pattern {
+            SkipPattern::MessageEquals(wording) => message == *wording,
+            SkipPattern::MessageStartsWith(wording) => message.starts_with(wording),
+            SkipPattern::MessageContains(wording) => message.contains(wording),
+            SkipPattern::TypeContains(wording) => ty.contains(wording),
+        })
+        .map(|(_, skip_kind)| *skip_kind)
+        .unwrap_or(SkipKind::Other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skipped(message: Option<&str>, ty: Option<&str>) -> TestCaseStatus {
+        let mut status = TestCaseStatus::skipped();
+        if let Some(message) = message {
+            status.set_message(message);
+        }
+        if let Some(ty) = ty {
+            status.set_type(ty);
+        }
+        status
+    }
+
+    #[test]
+    fn defaults_to_other() {
+        assert_eq!(infer_skip_kind(&skipped(None, None)), SkipKind::Other);
+        assert_eq!(
+            infer_skip_kind(&skipped(Some("Flaky on CI, see #123"), None)),
+            SkipKind::Other
+        );
+        assert_eq!(
+            infer_skip_kind(&skipped(Some("Disabled until the migration lands"), None)),
+            SkipKind::Other
+        );
+        assert_eq!(
+            infer_skip_kind(&TestCaseStatus::success()),
+            SkipKind::Unspecified
+        );
+    }
+
+    #[test]
+    fn extracts_reason_from_message_or_text() {
+        assert_eq!(
+            extract_skip_reason(&skipped(Some(" requires docker "), None)),
+            "requires docker"
+        );
+        let mut status = TestCaseStatus::skipped();
+        status.set_description("\n  tests/test_api.py:12: requires docker\n");
+        assert_eq!(
+            extract_skip_reason(&status),
+            "tests/test_api.py:12: requires docker"
+        );
+        assert_eq!(extract_skip_reason(&TestCaseStatus::skipped()), "");
+        assert_eq!(extract_skip_reason(&TestCaseStatus::success()), "");
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::BufRead;
+
+use quick_junit::{NonSuccessKind, Report, TestCaseStatus};
+use serde::Serialize;
+
+use super::parser::{extra_attrs, JunitParseIssue, JunitParser};
+
+/// Counts of the test suites and test cases of junit files, for profiling the test output of a
+/// repo
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JunitCounts {
+    pub num_reports: usize,
+    pub num_test_suites: usize,
+    pub num_test_cases: usize,
+    pub num_success: usize,
+    pub num_failure: usize,
+    pub num_error: usize,
+    pub num_skipped: usize,
+    /// Test cases with a `file` or `filepath`, of their own or of their test suite
+    pub num_with_file: usize,
+    /// Test cases with a timestamp, of their own or of their test suite
+    pub num_with_timestamp: usize,
+    /// Test cases with a duration
+    pub num_with_time: usize,
+    pub num_parse_issues_suboptimal: usize,
+    pub num_parse_issues_invalid: usize,
+}
+
+impl JunitCounts {
+    fn add(&mut self, other: &JunitCounts) {
+        self.num_reports += other.num_reports;
+        self.num_test_suites += other.num_test_suites;
+        self.num_test_cases += other.num_test_cases;
+        self.num_success += other.num_success;
+        self.num_failure += other.num_failure;
+        self.num_error += other.num_error;
+        self.num_skipped += other.num_skipped;
+        self.num_with_file += other.num_with_file;
+        self.num_with_timestamp += other.num_with_timestamp;
+        self.num_with_time += other.num_with_time;
+        self.num_parse_issues_suboptimal += other.num_parse_issues_suboptimal;
+        self.num_parse_issues_invalid += other.num_parse_issues_invalid;
+    }
+
+    /// Percentage of the test cases that have a file
+    pub fn file_coverage(&self) -> f64 {
+        percentage(self.num_with_file, self.num_test_cases)
+    }
+
+    /// Percentage of the test cases that have a timestamp
+    pub fn timestamp_coverage(&self) -> f64 {
+        percentage(self.num_with_timestamp, self.num_test_cases)
+    }
+
+    /// Percentage of the test cases that have a duration
+    pub fn time_coverage(&self) -> f64 {
+        percentage(self.num_with_time, self.num_test_cases)
+    }
+}
+
+fn percentage(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    count as f64 * 100.0 / total as f64
+}
+
+/// Nearest-rank percentiles of test case durations, in seconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DurationPercentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl DurationPercentiles {
+    /// `None` without any durations
+    pub fn new(durations_secs: &mut [f64]) -> Option<Self> {
+        if durations_secs.is_empty() {
+            return None;
+        }
+        durations_secs.sort_by(f64::total_cmp);
+        let percentile = |p: usize| {
+            let rank = (p * durations_secs.len()).div_ceil(100);
+            durations_secs[rank.saturating_sub(1)]
+        };
+        Some(Self {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JunitFileStats {
+    pub path: String,
+    pub counts: JunitCounts,
+    pub durations: Option<DurationPercentiles>,
+    /// Why the file could not be parsed, in which case it is left out of the totals
+    pub error: Option<String>,
+}
+
+/// Statistics of many junit files, added one at a time so that only their counts and test case
+/// durations are kept in memory, not their reports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JunitStats {
+    pub files: Vec<JunitFileStats>,
+    pub num_unparsable_files: usize,
+    pub totals: JunitCounts,
+    /// Set by [`JunitStats::finish`]
+    pub durations: Option<DurationPercentiles>,
+    #[serde(skip)]
+    durations_secs: Vec<f64>,
+}
+
+impl JunitStats {
+    /// Parses the junit file at `path` from `reader`. Files that can't be parsed are recorded with
+    /// their error.
+    pub fn add_file<T: Into<String>, R: BufRead>(&mut self, path: T, reader: R) {
+        // files are not inferred so that only the attributes that were written are counted
+        let mut junit_parser = JunitParser::new().with_infer_file_from_classname(false);
+        if let Err(e) = junit_parser.parse(reader) {
+            self.add_unparsable_file(path, e.to_string());
+            return;
+        }
+
+        let mut counts = JunitCounts::default();
+        for issue in junit_parser.issues() {
+            match issue {
+                JunitParseIssue::SubOptimal(..) => counts.num_parse_issues_suboptimal += 1,
+                JunitParseIssue::Invalid(..) => counts.num_parse_issues_invalid += 1,
+            }
+        }
+        let mut durations_secs = Vec::new();
+        for report in junit_parser.reports() {
+            add_report(&mut counts, &mut durations_secs, report);
+        }
+
+        self.totals.add(&counts);
+        self.files.push(JunitFileStats {
+            path: path.into(),
+            counts,
+            durations: DurationPercentiles::new(&mut durations_secs),
+            error: None,
+        });
+        self.durations_secs.append(&mut durations_secs);
+    }
+
+    /// Computes the duration percentiles of all test cases, once every file was added
+    pub fn finish(&mut self) {
+        self.durations = DurationPercentiles::new(&mut self.durations_secs);
+    }
+
+    /// Records a file that could not be read or parsed
+    pub fn add_unparsable_file<T: Into<String>, U: Into<String>>(&mut self, path: T, error: U) {
+        self.num_unparsable_files += 1;
+        self.files.push(JunitFileStats {
+            path: path.into(),
+            error: Some(error.into()),
+            ..Default::default()
+        });
+    }
+}
+
+fn add_report(counts: &mut JunitCounts, durations_secs: &mut Vec<f64>, report: &Report) {
+    counts.num_reports += 1;
+    for test_suite in &report.test_suites {
+        counts.num_test_suites += 1;
+        let test_suite_has_file = test_suite.extra.contains_key(extra_attrs::FILE)
+            || test_suite.extra.contains_key(extra_attrs::FILEPATH);
+        for test_case in &test_suite.test_cases {
+            counts.num_test_cases += 1;
+            match &test_case.status {
+                TestCaseStatus::Success { .. } => counts.num_success += 1,
+                TestCaseStatus::NonSuccess {
+                    kind: NonSuccessKind::Failure,
+                    ..
+                } => counts.num_failure += 1,
+                TestCaseStatus::NonSuccess {
+                    kind: NonSuccessKind::Error,
+                    ..
+                } => counts.num_error += 1,
+                TestCaseStatus::Skipped { .. } => counts.num_skipped += 1,
+            }
+            if test_suite_has_file
+                || test_case.extra.contains_key(extra_attrs::FILE)
+                || test_case.extra.contains_key(extra_attrs::FILEPATH)
+            {
+                counts.num_with_file += 1;
+            }
+            if test_case.timestamp.or(test_suite.timestamp).is_some() {
+                counts.num_with_timestamp += 1;
+            }
+            if let Some(time) = test_case.time {
+                counts.num_with_time += 1;
+                durations_secs.push(time.as_secs_f64());
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Parsing of junit XML as it arrives in chunks, e.g. when reading a file too large to be held in
+//! memory at once. Only the XML after the last complete markup is kept between chunks, and every
+//! run of complete markup is read as it would be as part of all of the XML.
+
+use quick_xml::{
+    errors::{Error, IllFormedError},
+    events::Event,
+    Reader,
+};
+
+use super::{encoding::XmlStreamDecoder, parser::JunitParser};
+
+pub struct JunitStreamParser {
+    parser: JunitParser,
+    decoder: XmlStreamDecoder,
+    /// Decoded XML after the last complete markup that was parsed
+    xml: String,
+    scanner: MarkupScanner,
+    /// Names of the open elements, since the end tags of elements opened in an earlier run of
+    /// markup can't be checked by quick-xml
+    open_elements: Vec<Vec<u8>>,
+    issues_start: usize,
+}
+
+impl JunitStreamParser {
+    pub fn new(mut parser: JunitParser) -> Self {
+        let issues_start = parser.start_parse();
+        Self {
+            parser,
+            decoder: Default::default(),
+            xml: Default::default(),
+            scanner: Default::default(),
+            open_elements: Default::default(),
+            issues_start,
+        }
+    }
+
+    /// Parses the complete markup of the XML so far, keeping the rest for the next chunk
+    pub fn push(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        self.decoder.decode(chunk, &mut self.xml);
+        if let Some(end) = self.scanner.scan(self.xml.as_bytes()) {
+            parse_events(
+                &mut self.parser,
+                &mut self.open_elements,
+                &self.xml[..end],
+                false,
+            )?;
+            self.xml.drain(..end);
+            self.scanner.shift(end);
+        }
+        Ok(())
+    }
+
+    /// Parses the rest of the XML, to the same result as [`JunitParser::parse`] on all of it
+    pub fn finish(mut self) -> anyhow::Result<JunitParser> {
+        let replacements = self.decoder.finish(&mut self.xml);
+        parse_events(&mut self.parser, &mut self.open_elements, &self.xml, true)?;
+        self.parser.finish_parse(self.issues_start, replacements);
+        Ok(self.parser)
+    }
+}
+
+fn parse_events(
+    parser: &mut JunitParser,
+    open_elements: &mut Vec<Vec<u8>>,
+    xml: &str,
+    last: bool,
+) -> anyhow::Result<()> {
+    let mut reader = Reader::from_str(xml);
+    let config = reader.config_mut();
+    config.trim_text(true);
+    config.check_end_names = false;
+    config.allow_unmatched_ends = true;
+
+    loop {
+        let event = reader.read_event()?;
+        match &event {
+            Event::Start(e) => open_elements.push(e.name().as_ref().to_vec()),
+            Event::End(e) => close_element(open_elements, e.name().as_ref())?,
+            Event::Eof if !last => return Ok(()),
+            _ => (),
+        }
+        if parser.match_event(event).is_none() {
+            return Ok(());
+        }
+    }
+}
+
+/// Fails like quick-xml does when checking end names against all of the XML
+fn close_element(open_elements: &mut Vec<Vec<u8>>, name: &[u8]) -> Result<(), Error> {
+    match open_elements.pop() {
+        Some(open_name) if open_name == name => Ok(()),
+        Some(open_name) => Err(Error::IllFormed(IllFormedError::MismatchedEndTag {
+            expected: String::from_utf8_lossy(&open_name).into_owned(),
+            found: String::from_utf8_lossy(name).into_owned(),
+        })),
+        None => Err(Error::IllFormed(IllFormedError::UnmatchedEndTag(
+            String::from_utf8_lossy(name).into_owned(),
+        ))),
+    }
+}
+
+/// What the markup being scanned is, by the position of its `<`. Each kind of markup ends where
+/// quick-xml ends it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Markup {
+    #[default]
+    None,
+    Unknown {
+        start: usize,
+    },
+    Bang {
+        start: usize,
+    },
+    Comment {
+        start: usize,
+    },
+    CData {
+        start: usize,
+    },
+    DocType {
+        depth: usize,
+    },
+    ProcessingInstruction {
+        start: usize,
+    },
+    Element {
+        quote: Option<u8>,
+    },
+}
+
+/// Finds the end of the last complete markup of XML that is still arriving
+#[derive(Debug, Default)]
+struct MarkupScanner {
+    markup: Markup,
+    pos: usize,
+}
+
+impl MarkupScanner {
+    /// Scans `xml` from where the last scan stopped, returning where the last complete markup ends
+    fn scan(&mut self, xml: &[u8]) -> Option<usize> {
+        let mut end = None;
+        while let Some(&byte) = xml.get(self.pos) {
+            let mut complete = false;
+            self.markup = match self.markup {
+                Markup::None if byte == b'<' => Markup::Unknown { start: self.pos },
+                Markup::None => Markup::None,
+                Markup::Unknown { start } => match byte {
+                    b'!' => Markup::Bang { start },
+                    b'?' => Markup::ProcessingInstruction { start },
+                    b'>' => {
+                        complete = true;
+                        Markup::None
+                    }
+                    b'\'' | b'"' => Markup::Element { quote: Some(byte) },
+                    _ => Markup::Element { quote: None },
+                },
+                Markup::Bang { start } => match byte {
+                    b'-' => Markup::Comment { start },
+                    b'[' => Markup::CData { start },
+                    b'D' | b'd' => Markup::DocType { depth: 0 },
+                    // fails to be read, which it is as part of the next run of markup
+                    _ => {
+                        complete = true;
+                        Markup::None
+                    }
+                },
+                Markup::Comment { start } => {
+                    complete = byte == b'>'
+                        && self.pos - start - 1 > 4
+                        && xml[start + 1..self.pos].ends_with(b"--");
+                    self.markup
+                }
+                Markup::CData { start } => {
+                    complete = byte == b'>' && xml[start + 1..self.pos].ends_with(b"]]");
+                    self.markup
+                }
+                Markup::DocType { depth } => match byte {
+                    b'<' => Markup::DocType { depth: depth + 1 },
+                    b'>' if depth == 0 => {
+                        complete = true;
+                        Markup::None
+                    }
+                    b'>' => Markup::DocType { depth: depth - 1 },
+                    _ => self.markup,
+                },
+                Markup::ProcessingInstruction { start } => {
+                    complete = byte == b'>' && self.pos > start + 1 && xml[self.pos - 1] == b'?';
+                    self.markup
+                }
+                Markup::Element { quote: None } => match byte {
+                    b'>' => {
+                        complete = true;
+                        Markup::None
+                    }
+                    b'\'' | b'"' => Markup::Element { quote: Some(byte) },
+                    _ => self.markup,
+                },
+                Markup::Element { quote: Some(quote) } if byte == quote => {
+                    Markup::Element { quote: None }
+                }
+                Markup::Element { .. } => self.markup,
+            };
+            self.pos += 1;
+            if complete {
+                self.markup = Markup::None;
+                end = Some(self.pos);
+            }
+        }
+        end
+    }
+
+    /// Moves the scan back by the `len` bytes removed from the start of the XML
+    fn shift(&mut self, len: usize) {
+        self.pos -= len;
+        self.markup = match self.markup {
+            Markup::Unknown { start } => Markup::Unknown { start: start - len },
+            Markup::Bang { start } => Markup::Bang { start: start - len },
+            Markup::Comment { start } => Markup::Comment { start: start - len },
+            Markup::CData { start } => Markup::CData { start: start - len },
+            Markup::ProcessingInstruction { start } => {
+                Markup::ProcessingInstruction { start: start - len }
+            }
+            markup => markup,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::junit::parser::JunitParseIssue;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE testsuites [<!ELEMENT testsuites ANY>]>
+<!-- generated by a -> b > c -->
+<testsuites name="Café € 🚀" tests="3">
+  <testsuite name="a > b" tests="3" timestamp="2024-10-01T12:00:00">
+    <testcase name="passes 'with' quotes" classname="a.b" file="src/a.test.ts" time="0.1"/>
+    <testcase name='fails "with" quotes >' classname="a.b" time="1">
+      <failure message="expected 1 &gt; 2">at a.test.ts:1 — ünïcödé</failure>
+      <system-out><![CDATA[<not a="tag"> ]] ]> done]]></system-out>
+    </testcase>
+    <?processing instruction ?>
+    <testcase name="skipped" classname="a.b"><skipped/></testcase>
+  </testsuite>
+</testsuites>
+"#;
+
+    fn parse(xml: &[u8]) -> Result<(String, Vec<JunitParseIssue>), String> {
+        let mut parser = JunitParser::new();
+        parser.parse(xml).map_err(|e| e.to_string())?;
+        Ok((format!("{:?}", parser.reports()), parser.issues().clone()))
+    }
+
+    fn parse_in_chunks(
+        xml: &[u8],
+        chunk_len: usize,
+    ) -> Result<(String, Vec<JunitParseIssue>), String> {
+        let mut stream_parser = JunitStreamParser::new(JunitParser::new());
+        for chunk in xml.chunks(chunk_len) {
+            stream_parser.push(chunk).map_err(|e| e.to_string())?;
+        }
+        let parser = stream_parser.finish().map_err(|e| e.to_string())?;
+        Ok((format!("{:?}", parser.reports()), parser.issues().clone()))
+    }
+
+    fn assert_parses_in_chunks(xml: &[u8]) {
+        let parsed = parse(xml);
+        for chunk_len in [1, 2, 7, 64, 64 * 1024, xml.len().max(1)] {
+            assert_eq!(
+                parse_in_chunks(xml, chunk_len),
+                parsed,
+                "chunks of {chunk_len}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_in_chunks() {
+        let parsed = parse(XML.as_bytes()).unwrap();
+        assert!(parsed.0.contains("Café € 🚀"));
+        assert_parses_in_chunks(XML.as_bytes());
+
+        let test_cases = (0..2_000)
+            .map(|i| format!(r#"<testcase name="test {i} ✓" classname="a" time="0.{i}"/>"#))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let large =
+            format!(r#"<testsuites><testsuite name="a">{test_cases}</testsuite></testsuites>"#);
+        assert!(large.len() > 64 * 1024);
+        assert_parses_in_chunks(large.as_bytes());
+    }
+
+    #[test]
+    fn parses_in_chunks_with_other_encodings() {
+        let utf16le_with_bom: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(XML.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_parses_in_chunks(&utf16le_with_bom);
+
+        let (windows_1252, ..) = encoding_rs::WINDOWS_1252.encode(
+            r#"<?xml version="1.0" encoding="windows-1252"?><testsuites name="Café"></testsuites>"#,
+        );
+        assert_parses_in_chunks(&windows_1252);
+
+        let undecodable = [
+            b"<testsuites name=\"".as_slice(),
+            b"\xFFCaf\xC3",
+            b"\"></testsuites>",
+        ]
+        .concat();
+        assert_parses_in_chunks(&undecodable);
+    }
+
+    #[test]
+    fn parses_incomplete_or_invalid_xml_in_chunks() {
+        for xml in [
+            "",
+            "<testsuites><testsuite name=\"a\"><testcase name=\"b\">",
+            "<testsuites><testsuite name=\"a\"><testcase name=\"b",
+            "<testsuites><testsuite name=\"a\"></testcase></testsuites>",
+            "<testsuites></testsuites></testsuites>",
+            "<testsuites><!-- unclosed comment -></testsuites>",
+            "<testsuites><![CDATA[unclosed]]</testsuites>",
+            "<testsuites><!invalid></testsuites>",
+            "<testsuites><!",
+        ] {
+            assert_parses_in_chunks(xml.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::string_safety::safe_truncate_str;
+
+pub(crate) const ESCAPE: char = '\u{1b}';
+const CSI_START: char = '[';
+
+/// Strips control characters (including ANSI escape sequences) from a test name or classname and
+/// collapses runs of whitespace into a single space.
+///
+/// Returns `None` if the name was already clean, ignoring leading and trailing whitespace.
+pub fn sanitize_test_name(name: &str) -> Option<String> {
+    if is_clean_test_name(name) {
+        return None;
+    }
+
+    let mut sanitized = String::with_capacity(name.len());
+    let mut pending_space = false;
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ESCAPE {
+            skip_escape_sequence(&mut chars);
+            continue;
+        }
+
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+
+        if c.is_control() {
+            continue;
+        }
+
+        if pending_space && !sanitized.is_empty() {
+            sanitized.push(' ');
+        }
+        pending_space = false;
+        sanitized.push(c);
+    }
+
+    if sanitized == name.trim() {
+        None
+    } else {
+        Some(sanitized)
+    }
+}
+
+/// Whether [`sanitize_test_name`] would leave the name as it is, which nearly every name is, without
+/// allocating.
+fn is_clean_test_name(name: &str) -> bool {
+    let mut previous_whitespace = false;
+    name.trim().chars().all(|c| {
+        let whitespace = c.is_whitespace();
+        let clean = if whitespace {
+            c == ' ' && !previous_whitespace
+        } else {
+            !c.is_control()
+        };
+        previous_whitespace = whitespace;
+        clean
+    })
+}
+
+/// Skips the rest of an escape sequence after its `ESC`, e.g. `[31m` of `\x1b[31m`: parameters
+/// and intermediates followed by a final byte in `@`..=`~`.
+pub(crate) fn skip_escape_sequence(chars: &mut Peekable<Chars>) {
+    if chars.next_if_eq(&CSI_START).is_some() {
+        while chars.next().is_some_and(|c| !('@'..='~').contains(&c)) {}
+    }
+}
+
+/// Truncates the trimmed `name` to at most `max_len` bytes on a char boundary.
+///
+/// Returns `None` if the name already fits.
+pub fn truncate_test_name(name: &str, max_len: usize) -> Option<String> {
+    if name.len() <= max_len {
+        return None;
+    }
+    Some(String::from(safe_truncate_str(name, max_len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_clean_names_alone() {
+        assert_eq!(sanitize_test_name("test_foo[param-1]"), None);
+        assert_eq!(sanitize_test_name("Foo should do the thing"), None);
+        assert_eq!(sanitize_test_name(" test_foo "), None);
+        assert_eq!(truncate_test_name("test_foo", 8), None);
+    }
+
+    #[test]
+    fn strips_control_characters_and_collapses_whitespace() {
+        assert_eq!(
+            sanitize_test_name("  test_foo\n\twith a\r\nnewline  "),
+            Some(String::from("test_foo with a newline"))
+        );
+        assert_eq!(
+            sanitize_test_name("test_foo\u{0}\u{7}bar"),
+            Some(String::from("test_foobar"))
+        );
+    }
+
+    #[test]
+    fn strips_ansi_escape_sequences() {
+        assert_eq!(
+            sanitize_test_name("\u{1b}[1;31mtest_foo\u{1b}[0m fails"),
+            Some(String::from("test_foo fails"))
+        );
+        assert_eq!(
+            sanitize_test_name("test_foo\u{1b}"),
+            Some(String::from("test_foo"))
+        );
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        assert_eq!(truncate_test_name("abcdef", 3), Some(String::from("abc")));
+        assert_eq!(truncate_test_name("aé", 2), Some(String::from("a")));
+    }
+}
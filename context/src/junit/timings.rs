@@ -0,0 +1,142 @@
+use std::{fmt, time::Duration};
+
+use quick_junit::Report;
+use serde::Serialize;
+
+pub const NUM_SLOWEST_TEST_CASES: usize = 20;
+pub const NUM_SLOWEST_TEST_SUITES: usize = 10;
+
+/// Coarse ranges of test case durations, for tuning CI parallelism
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationBucket {
+    UnderOneHundredMillis,
+    UnderOneSecond,
+    UnderTenSeconds,
+    TenSecondsOrMore,
+    /// Test cases without a duration
+    Unknown,
+}
+
+impl DurationBucket {
+    pub const ALL: [DurationBucket; 5] = [
+        DurationBucket::UnderOneHundredMillis,
+        DurationBucket::UnderOneSecond,
+        DurationBucket::UnderTenSeconds,
+        DurationBucket::TenSecondsOrMore,
+        DurationBucket::Unknown,
+    ];
+
+    pub fn of(time: Option<Duration>) -> Self {
+        match time {
+            None => DurationBucket::Unknown,
+            Some(time) if time < Duration::from_millis(100) => {
+                DurationBucket::UnderOneHundredMillis
+            }
+            Some(time) if time < Duration::from_secs(1) => DurationBucket::UnderOneSecond,
+            Some(time) if time < Duration::from_secs(10) => DurationBucket::UnderTenSeconds,
+            Some(_) => DurationBucket::TenSecondsOrMore,
+        }
+    }
+}
+
+impl fmt::Display for DurationBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DurationBucket::UnderOneHundredMillis => "<100ms",
+            DurationBucket::UnderOneSecond => "100ms-1s",
+            DurationBucket::UnderTenSeconds => "1-10s",
+            DurationBucket::TenSecondsOrMore => ">10s",
+            DurationBucket::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DurationBucketCount {
+    pub bucket: DurationBucket,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestCaseTiming {
+    pub name: String,
+    pub test_suite: String,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestSuiteTiming {
+    pub name: String,
+    pub num_test_cases: usize,
+    pub duration_secs: f64,
+}
+
+/// Where the time of a test run went, from the durations of its test cases and test suites
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JunitTimings {
+    /// The [`NUM_SLOWEST_TEST_CASES`] slowest test cases, slowest first
+    pub slowest_test_cases: Vec<TestCaseTiming>,
+    /// The [`NUM_SLOWEST_TEST_SUITES`] slowest test suites, slowest first
+    pub slowest_test_suites: Vec<TestSuiteTiming>,
+    /// Number of test cases per bucket, in the order of [`DurationBucket::ALL`]
+    pub histogram: Vec<DurationBucketCount>,
+}
+
+impl JunitTimings {
+    /// Test suites without a `time` of their own take the total time of their test cases, and
+    /// are left out when none of them has one.
+    pub fn from_reports<'a, T: IntoIterator<Item = &'a Report>>(reports: T) -> Self {
+        let mut test_cases = Vec::new();
+        let mut test_suites = Vec::new();
+        let mut histogram =
+            DurationBucket::ALL.map(|bucket| DurationBucketCount { bucket, count: 0 });
+        for test_suite in reports.into_iter().flat_map(|report| &report.test_suites) {
+            let mut test_cases_time: Option<Duration> = None;
+            for test_case in &test_suite.test_cases {
+                let bucket = DurationBucket::of(test_case.time);
+                if let Some(bucket_count) = histogram
+                    .iter_mut()
+                    .find(|bucket_count| bucket_count.bucket == bucket)
+                {
+                    bucket_count.count += 1;
+                }
+                let Some(time) = test_case.time else {
+                    continue;
+                };
+                *test_cases_time.get_or_insert_with(Duration::default) += time;
+                test_cases.push(TestCaseTiming {
+                    name: test_case.name.to_string(),
+                    test_suite: test_suite.name.to_string(),
+                    duration_secs: time.as_secs_f64(),
+                });
+            }
+            if let Some(time) = test_suite.time.or(test_cases_time) {
+                test_suites.push(TestSuiteTiming {
+                    name: test_suite.name.to_string(),
+                    num_test_cases: test_suite.test_cases.len(),
+                    duration_secs: time.as_secs_f64(),
+                });
+            }
+        }
+
+        test_cases.sort_by(|a, b| b.duration_secs.total_cmp(&a.duration_secs));
+        test_cases.truncate(NUM_SLOWEST_TEST_CASES);
+        test_suites.sort_by(|a, b| b.duration_secs.total_cmp(&a.duration_secs));
+        test_suites.truncate(NUM_SLOWEST_TEST_SUITES);
+        Self {
+            slowest_test_cases: test_cases,
+            slowest_test_suites: test_suites,
+            histogram: histogram.to_vec(),
+        }
+    }
+
+    pub fn count(&self, bucket: DurationBucket) -> usize {
+        self.histogram
+            .iter()
+            .find(|bucket_count| bucket_count.bucket == bucket)
+            .map(|bucket_count| bucket_count.count)
+            .unwrap_or_default()
+    }
+}
@@ -1,4 +1,9 @@
-use std::{cmp::Ordering, collections::HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    time::Duration,
+};
 
 use chrono::{DateTime, FixedOffset, Utc};
 #[cfg(feature = "pyo3")]
@@ -10,13 +15,27 @@ use thiserror::Error;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use super::parser::extra_attrs;
+use super::{
+    file_path::is_windows_absolute_path,
+    parser::extra_attrs,
+    schema::{check_rules, JunitSchema, JunitSchemaRule, JunitSchemaRuleCheck},
+};
 use crate::string_safety::{validate_field_len, FieldLen};
 
 pub const MAX_FIELD_LEN: usize = 1_000;
 
 const TIMESTAMP_OLD_DAYS: u32 = 30;
 const TIMESTAMP_STALE_HOURS: u32 = 1;
+/// More test cases than this sharing one `id` means the attribute is templated wrong, e.g. set to the
+/// same literal UUID on every test case, rather than a test case being repeated
+pub const DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD: usize = 50;
+const DUPLICATE_TEST_CASE_ID_MAX_EXAMPLES: usize = 3;
+/// Test case durations of a suite adding up to this many times less or more than the wall clock
+/// time of the suite means the runner reports durations wrong, e.g. all of them as `0.0`
+pub const DEFAULT_DURATION_VARIANCE_FACTOR: f64 = 10.0;
+/// Smaller gaps between test case durations and wall clock time are setup and teardown as much
+/// as wrong durations, so they aren't an issue however far apart the two are
+const DURATION_VARIANCE_MIN_GAP: Duration = Duration::from_secs(60);
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
@@ -63,10 +82,56 @@ impl Default for JunitValidationType {
     }
 }
 
+/// Thresholds of the issues [`validate_with_options`] finds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JunitValidationOptions {
+    /// Test cases sharing an `id` are only an issue when more than this many of them do
+    pub duplicate_id_threshold: usize,
+    /// How many times less or more than the wall clock time of their suite its test case
+    /// durations may add up to
+    pub duration_variance_factor: f64,
+    /// Dialect whose rules are checked on top of those of plain junit
+    pub schema: JunitSchema,
+}
+
+impl Default for JunitValidationOptions {
+    fn default() -> Self {
+        Self {
+            duplicate_id_threshold: DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD,
+            duration_variance_factor: DEFAULT_DURATION_VARIANCE_FACTOR,
+            schema: JunitSchema::default(),
+        }
+    }
+}
+
 pub fn validate(report: &Report) -> JunitReportValidation {
+    validate_with_options(report, &JunitValidationOptions::default())
+}
+
+/// Like [`validate`], with test cases sharing an `id` only an issue when more than
+/// `duplicate_id_threshold` of them do.
+pub fn validate_with_duplicate_id_threshold(
+    report: &Report,
+    duplicate_id_threshold: usize,
+) -> JunitReportValidation {
+    validate_with_options(
+        report,
+        &JunitValidationOptions {
+            duplicate_id_threshold,
+            ..Default::default()
+        },
+    )
+}
+
+/// Like [`validate`], with the thresholds of `options`.
+pub fn validate_with_options(
+    report: &Report,
+    options: &JunitValidationOptions,
+) -> JunitReportValidation {
     let mut report_validation = JunitReportValidation::default();
+    let timestamp_deltas = test_suite_timestamp_deltas(&report.test_suites);
 
-    for test_suite in report.test_suites.iter() {
+    for (test_suite, timestamp_delta) in report.test_suites.iter().zip(timestamp_deltas) {
         let mut test_suite_validation = JunitTestSuiteValidation::default();
 
         match validate_field_len::<MAX_FIELD_LEN, _>(test_suite.name.as_str()) {
@@ -94,6 +159,52 @@ pub fn validate(report: &Report) -> JunitReportValidation {
             }
         }
 
+        let declared_duration: Duration = test_suite
+            .test_cases
+            .iter()
+            .filter_map(|test_case| test_case.time)
+            .sum();
+        let wall_clock_durations = [
+            (WallClockSource::SuiteTime, test_suite.time),
+            (WallClockSource::SuiteTimestamps, timestamp_delta),
+        ];
+        for (wall_clock_source, wall_clock_duration) in wall_clock_durations {
+            let Some(wall_clock_duration) = wall_clock_duration else {
+                continue;
+            };
+            // Suites running in parallel start closer together than they take, so timestamps
+            // only tell that test case durations are too short
+            if wall_clock_source == WallClockSource::SuiteTimestamps
+                && declared_duration >= wall_clock_duration
+            {
+                continue;
+            }
+            if is_duration_variance_extreme(
+                declared_duration,
+                wall_clock_duration,
+                options.duration_variance_factor,
+            ) {
+                test_suite_validation.add_issue(JunitValidationIssue::SubOptimal(
+                    JunitTestSuiteValidationIssueSubOptimal::TestSuiteDurationVariance {
+                        name: test_suite.name.to_string(),
+                        declared_duration,
+                        wall_clock_duration,
+                        wall_clock_source,
+                    },
+                ));
+            }
+        }
+        if let Some(wall_clock_duration) = wall_clock_durations
+            .into_iter()
+            .find_map(|(_, wall_clock_duration)| wall_clock_duration)
+        {
+            let duration_coverage = report_validation
+                .duration_coverage
+                .get_or_insert_with(Default::default);
+            duration_coverage.declared_duration += declared_duration;
+            duration_coverage.wall_clock_duration += wall_clock_duration;
+        }
+
         let mut valid_test_cases: Vec<TestCase> = Vec::new();
         for test_case in test_suite.test_cases.iter() {
             let mut test_case_validation = JunitTestCaseValidation::default();
@@ -112,6 +223,26 @@ pub fn validate(report: &Report) -> JunitReportValidation {
                 }
             };
 
+            if test_case.extra.contains_key(extra_attrs::NAME_SANITIZED) {
+                test_case_validation.add_issue(JunitValidationIssue::SubOptimal(
+                    JunitTestCaseValidationIssueSubOptimal::TestCaseNameSanitized(
+                        test_case.name.to_string(),
+                    ),
+                ));
+            }
+
+            if let Some(original_name_len) = test_case
+                .extra
+                .get(extra_attrs::ORIGINAL_NAME_LEN)
+                .and_then(|len| len.parse().ok())
+            {
+                test_case_validation.add_issue(JunitValidationIssue::SubOptimal(
+                    JunitTestCaseValidationIssueSubOptimal::TestCaseNameTruncated(
+                        original_name_len,
+                    ),
+                ));
+            }
+
             if let Some(raw_test_case_id) = test_case.extra.get("id") {
                 let test_case_id = uuid::Uuid::parse_str(raw_test_case_id).unwrap_or_default();
                 if test_case_id.get_version() != Some(uuid::Version::Sha1) {
@@ -123,16 +254,26 @@ pub fn validate(report: &Report) -> JunitReportValidation {
                 }
             }
 
-            match validate_field_len::<MAX_FIELD_LEN, _>(
-                test_case
+            let file_or_filepath = test_case
+                .extra
+                .get(extra_attrs::FILE)
+                .or(test_case.extra.get(extra_attrs::FILEPATH))
+                .or(test_case
                     .extra
-                    .get(extra_attrs::FILE)
-                    .or(test_case.extra.get(extra_attrs::FILEPATH))
-                    .as_ref()
-                    .map(|s| s.as_str())
-                    .unwrap_or_default(),
-            ) {
-                FieldLen::Valid => (),
+                    .get(extra_attrs::FILE_INFERRED_FROM_CLASSNAME))
+                .map(|s| s.as_str())
+                .unwrap_or_default();
+            match validate_field_len::<MAX_FIELD_LEN, _>(file_or_filepath) {
+                FieldLen::Valid => {
+                    // Paths inside the repo have been made relative by the parser at this point
+                    if is_windows_absolute_path(file_or_filepath) {
+                        test_case_validation.add_issue(JunitValidationIssue::SubOptimal(
+                            JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathOutsideRepo(
+                                file_or_filepath.to_string(),
+                            ),
+                        ));
+                    }
+                }
                 FieldLen::TooShort(s) => {
                     test_case_validation.add_issue(JunitValidationIssue::SubOptimal(
                         JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathTooShort(s),
@@ -214,17 +355,141 @@ pub fn validate(report: &Report) -> JunitReportValidation {
         report_validation.test_suites.push(test_suite_validation);
     }
 
-    report_validation.derive_all_issues();
+    let mut report_level_issues: HashSet<JunitReportValidationIssue> =
+        find_duplicate_test_case_ids([report], options.duplicate_id_threshold)
+            .into_iter()
+            .map(|DuplicateTestCaseId { id, count, .. }| {
+                JunitValidationIssue::Invalid(JunitReportValidationIssueInvalid::TestCasesShareId {
+                    id,
+                    count,
+                })
+            })
+            .collect();
+    if options.schema != JunitSchema::Junit {
+        report_validation.schema_rule_checks = check_rules(report);
+        report_level_issues.extend(
+            report_validation
+                .schema_rule_checks
+                .iter()
+                .filter(|rule_check| {
+                    rule_check.num_failed > 0 && options.schema.rules().contains(&rule_check.rule)
+                })
+                .map(|rule_check| {
+                    JunitValidationIssue::Invalid(rule_check.rule.issue(rule_check.num_failed))
+                }),
+        );
+    }
+    report_validation.derive_all_issues(report_level_issues);
 
     report_validation
 }
 
+/// The time between the timestamp of each test suite and that of the next one, which is how
+/// long the suite took when the suites ran one after another.
+fn test_suite_timestamp_deltas(test_suites: &[TestSuite]) -> Vec<Option<Duration>> {
+    let mut timestamp_deltas: Vec<Option<Duration>> = test_suites
+        .windows(2)
+        .map(
+            |test_suites| match (test_suites[0].timestamp, test_suites[1].timestamp) {
+                (Some(timestamp), Some(next_timestamp)) => {
+                    (next_timestamp - timestamp).to_std().ok()
+                }
+                _ => None,
+            },
+        )
+        .collect();
+    timestamp_deltas.resize(test_suites.len(), None);
+    timestamp_deltas
+}
+
+fn is_duration_variance_extreme(
+    declared_duration: Duration,
+    wall_clock_duration: Duration,
+    duration_variance_factor: f64,
+) -> bool {
+    let (shorter, longer) = if declared_duration < wall_clock_duration {
+        (declared_duration, wall_clock_duration)
+    } else {
+        (wall_clock_duration, declared_duration)
+    };
+    longer - shorter > DURATION_VARIANCE_MIN_GAP
+        && longer.as_secs_f64() > shorter.as_secs_f64() * duration_variance_factor
+}
+
+/// An `id` shared by more test cases than a threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateTestCaseId {
+    pub id: String,
+    pub count: usize,
+    /// `classname.name` of the first few test cases with the id
+    pub example_names: Vec<String>,
+}
+
+/// Returns the `id`s shared by more than `threshold` test cases across `reports`, most shared first.
+pub fn find_duplicate_test_case_ids<'a, T: IntoIterator<Item = &'a Report>>(
+    reports: T,
+    threshold: usize,
+) -> Vec<DuplicateTestCaseId> {
+    let mut test_cases_by_id: HashMap<&str, (usize, Vec<String>)> = HashMap::new();
+    for test_case in reports
+        .into_iter()
+        .flat_map(|report| &report.test_suites)
+        .flat_map(|test_suite| &test_suite.test_cases)
+    {
+        let Some(id) = test_case.extra.get(extra_attrs::ID) else {
+            continue;
+        };
+        let (count, example_names) = test_cases_by_id.entry(id.as_str()).or_default();
+        *count += 1;
+        if example_names.len() < DUPLICATE_TEST_CASE_ID_MAX_EXAMPLES {
+            example_names.push(match &test_case.classname {
+                Some(classname) => format!("{}.{}", classname.as_str(), test_case.name.as_str()),
+                None => test_case.name.as_str().to_string(),
+            });
+        }
+    }
+
+    let mut duplicate_test_case_ids = test_cases_by_id
+        .into_iter()
+        .filter(|(_, (count, _))| *count > threshold)
+        .map(|(id, (count, example_names))| DuplicateTestCaseId {
+            id: id.to_string(),
+            count,
+            example_names,
+        })
+        .collect::<Vec<_>>();
+    duplicate_test_case_ids.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.id.cmp(&b.id)));
+    duplicate_test_case_ids
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct JunitReportValidation {
     pub all_issues: Vec<JunitValidationIssueType>,
     pub level: JunitValidationLevel,
     pub test_suites: Vec<JunitTestSuiteValidation>,
     pub valid_test_suites: Vec<TestSuite>,
+    /// Over the test suites with a wall clock time, `None` when none have one
+    pub duration_coverage: Option<DurationCoverage>,
+    /// The rules of every dialect checked on the report, empty when validating plain junit
+    pub schema_rule_checks: Vec<JunitSchemaRuleCheck>,
+}
+
+/// Test case durations compared to the wall clock time of their suites
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DurationCoverage {
+    pub declared_duration: Duration,
+    pub wall_clock_duration: Duration,
+}
+
+impl DurationCoverage {
+    /// Share of the wall clock time covered by test case durations, `None` without wall clock
+    /// time
+    pub fn ratio(&self) -> Option<f64> {
+        if self.wall_clock_duration.is_zero() {
+            return None;
+        }
+        Some(self.declared_duration.as_secs_f64() / self.wall_clock_duration.as_secs_f64())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -244,6 +509,30 @@ impl ToString for JunitValidationIssueType {
     }
 }
 
+impl JunitValidationIssueType {
+    /// Stable name of the rule the issue breaks, e.g. for counting issues by rule across runs
+    pub fn rule(&self) -> &'static str {
+        match self {
+            JunitValidationIssueType::Report(JunitValidationIssue::SubOptimal(i)) => i.rule(),
+            JunitValidationIssueType::Report(JunitValidationIssue::Invalid(i)) => i.rule(),
+            JunitValidationIssueType::TestSuite(JunitValidationIssue::SubOptimal(i)) => i.rule(),
+            JunitValidationIssueType::TestSuite(JunitValidationIssue::Invalid(i)) => i.rule(),
+            JunitValidationIssueType::TestCase(JunitValidationIssue::SubOptimal(i)) => i.rule(),
+            JunitValidationIssueType::TestCase(JunitValidationIssue::Invalid(i)) => i.rule(),
+        }
+    }
+
+    /// The dialect whose rules produced the issue
+    pub fn schema(&self) -> JunitSchema {
+        match self {
+            JunitValidationIssueType::Report(JunitValidationIssue::Invalid(i)) => i
+                .schema_rule()
+                .map_or(JunitSchema::Junit, |schema_rule| schema_rule.schema()),
+            _ => JunitSchema::Junit,
+        }
+    }
+}
+
 impl From<&JunitValidationIssueType> for JunitValidationType {
     fn from(value: &JunitValidationIssueType) -> Self {
         match value {
@@ -329,8 +618,7 @@ impl JunitReportValidation {
             .count()
     }
 
-    fn derive_all_issues(&mut self) {
-        let mut report_level_issues: HashSet<JunitReportValidationIssue> = HashSet::new();
+    fn derive_all_issues(&mut self, mut report_level_issues: HashSet<JunitReportValidationIssue>) {
         let mut other_issues: Vec<JunitValidationIssueType> = Vec::new();
 
         for test_suite in &self.test_suites {
@@ -435,7 +723,57 @@ pub enum JunitReportValidationIssueSubOptimal {
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
-pub enum JunitReportValidationIssueInvalid {}
+pub enum JunitReportValidationIssueInvalid {
+    #[error("report has {count} test cases sharing the id {id}, which makes them a single test")]
+    TestCasesShareId { id: String, count: usize },
+    #[error("report has {count} test cases without a file attribute")]
+    TestCasesFileMissing { count: usize },
+    #[error("report has {count} test cases without a line attribute")]
+    TestCasesLineMissing { count: usize },
+    #[error("report has {count} test suites without a timestamp")]
+    TestSuitesTimestampMissing { count: usize },
+    #[error("report has {count} reruns without a type attribute")]
+    TestRerunsTypeMissing { count: usize },
+    #[error("report has {count} reruns without a stack trace")]
+    TestRerunsStackTraceMissing { count: usize },
+}
+
+impl JunitReportValidationIssueSubOptimal {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TestCasesFileOrFilepathMissing => "TestCasesFileOrFilepathMissing",
+            Self::MissingTimestamps => "MissingTimestamps",
+            Self::FutureTimestamps => "FutureTimestamps",
+            Self::OldTimestamps => "OldTimestamps",
+            Self::StaleTimestamps => "StaleTimestamps",
+        }
+    }
+}
+
+impl JunitReportValidationIssueInvalid {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TestCasesShareId { .. } => "TestCasesShareId",
+            Self::TestCasesFileMissing { .. } => "TestCasesFileMissing",
+            Self::TestCasesLineMissing { .. } => "TestCasesLineMissing",
+            Self::TestSuitesTimestampMissing { .. } => "TestSuitesTimestampMissing",
+            Self::TestRerunsTypeMissing { .. } => "TestRerunsTypeMissing",
+            Self::TestRerunsStackTraceMissing { .. } => "TestRerunsStackTraceMissing",
+        }
+    }
+
+    /// The dialect rule the issue breaks, `None` for the rules of plain junit
+    pub fn schema_rule(&self) -> Option<JunitSchemaRule> {
+        match self {
+            Self::TestCasesShareId { .. } => None,
+            Self::TestCasesFileMissing { .. } => Some(JunitSchemaRule::TestCaseFile),
+            Self::TestCasesLineMissing { .. } => Some(JunitSchemaRule::TestCaseLine),
+            Self::TestSuitesTimestampMissing { .. } => Some(JunitSchemaRule::TestSuiteTimestamp),
+            Self::TestRerunsTypeMissing { .. } => Some(JunitSchemaRule::RerunType),
+            Self::TestRerunsStackTraceMissing { .. } => Some(JunitSchemaRule::RerunStackTrace),
+        }
+    }
+}
 
 pub type JunitTestSuiteValidationIssue = JunitValidationIssue<
     JunitTestSuiteValidationIssueSubOptimal,
@@ -519,6 +857,47 @@ pub enum JunitTestSuiteValidationIssueSubOptimal {
     TestSuiteInvalidId(String),
     #[error("test suite name too long, truncated to {}", MAX_FIELD_LEN)]
     TestSuiteNameTooLong(String),
+    #[error(
+        "test suite {name} has test case durations adding up to {:.1}s, but {wall_clock_source} {:.1}s",
+        declared_duration.as_secs_f64(),
+        wall_clock_duration.as_secs_f64()
+    )]
+    TestSuiteDurationVariance {
+        name: String,
+        declared_duration: Duration,
+        wall_clock_duration: Duration,
+        wall_clock_source: WallClockSource,
+    },
+}
+
+/// Where the wall clock time of a test suite comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallClockSource {
+    /// The `time` attribute of the suite
+    SuiteTime,
+    /// The `timestamp` attributes of the suite and the next one
+    SuiteTimestamps,
+}
+
+impl fmt::Display for WallClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WallClockSource::SuiteTime => write!(f, "its time is"),
+            WallClockSource::SuiteTimestamps => {
+                write!(f, "its timestamp is followed by the next suite's after")
+            }
+        }
+    }
+}
+
+impl JunitTestSuiteValidationIssueSubOptimal {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TestSuiteInvalidId(..) => "TestSuiteInvalidId",
+            Self::TestSuiteNameTooLong(..) => "TestSuiteNameTooLong",
+            Self::TestSuiteDurationVariance { .. } => "TestSuiteDurationVariance",
+        }
+    }
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -527,6 +906,14 @@ pub enum JunitTestSuiteValidationIssueInvalid {
     TestSuiteNameTooShort(String),
 }
 
+impl JunitTestSuiteValidationIssueInvalid {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TestSuiteNameTooShort(..) => "TestSuiteNameTooShort",
+        }
+    }
+}
+
 pub type JunitTestCaseValidationIssue = JunitValidationIssue<
     JunitTestCaseValidationIssueSubOptimal,
     JunitTestCaseValidationIssueInvalid,
@@ -583,10 +970,18 @@ impl JunitTestCaseValidation {
 pub enum JunitTestCaseValidationIssueSubOptimal {
     #[error("test case name too long, truncated to {}", MAX_FIELD_LEN)]
     TestCaseNameTooLong(String),
+    #[error("test case name or classname had control characters or repeated whitespace removed")]
+    TestCaseNameSanitized(String),
+    #[error("test case name was truncated from {0} bytes")]
+    TestCaseNameTruncated(usize),
     #[error("test case file or filepath too short")]
     TestCaseFileOrFilepathTooShort(String),
     #[error("test case file or filepath too long")]
     TestCaseFileOrFilepathTooLong(String),
+    /// Depends on the repo root the report was parsed with, since the parser makes the absolute
+    /// paths inside of it relative, and only the ones outside of it stay absolute
+    #[error("test case file or filepath is an absolute path outside of the repo")]
+    TestCaseFileOrFilepathOutsideRepo(String),
     #[error("test case classname too short")]
     TestCaseClassnameTooShort(String),
     #[error("test case classname too long, truncated to {}", MAX_FIELD_LEN)]
@@ -611,8 +1006,37 @@ pub enum JunitTestCaseValidationIssueSubOptimal {
     TestCaseInvalidId(String),
 }
 
+impl JunitTestCaseValidationIssueSubOptimal {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TestCaseNameTooLong(..) => "TestCaseNameTooLong",
+            Self::TestCaseNameSanitized(..) => "TestCaseNameSanitized",
+            Self::TestCaseNameTruncated(..) => "TestCaseNameTruncated",
+            Self::TestCaseFileOrFilepathTooShort(..) => "TestCaseFileOrFilepathTooShort",
+            Self::TestCaseFileOrFilepathTooLong(..) => "TestCaseFileOrFilepathTooLong",
+            Self::TestCaseFileOrFilepathOutsideRepo(..) => "TestCaseFileOrFilepathOutsideRepo",
+            Self::TestCaseClassnameTooShort(..) => "TestCaseClassnameTooShort",
+            Self::TestCaseClassnameTooLong(..) => "TestCaseClassnameTooLong",
+            Self::TestCaseNoTimeDuration => "TestCaseNoTimeDuration",
+            Self::TestCaseNoTimestamp => "TestCaseNoTimestamp",
+            Self::TestCaseFutureTimestamp(..) => "TestCaseFutureTimestamp",
+            Self::TestCaseOldTimestamp(..) => "TestCaseOldTimestamp",
+            Self::TestCaseStaleTimestamp(..) => "TestCaseStaleTimestamp",
+            Self::TestCaseInvalidId(..) => "TestCaseInvalidId",
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum JunitTestCaseValidationIssueInvalid {
     #[error("test case name too short")]
     TestCaseNameTooShort(String),
 }
+
+impl JunitTestCaseValidationIssueInvalid {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            Self::TestCaseNameTooShort(..) => "TestCaseNameTooShort",
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod bazel_bep;
 pub mod env;
+pub mod info_id;
 pub mod junit;
 pub mod meta;
 pub mod repo;
@@ -1,3 +1,5 @@
+#[cfg(feature = "ruby")]
+use magnus::Module;
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
@@ -13,6 +15,7 @@ use crate::env::validator::{
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "ruby", magnus::wrap(class = "MetaValidationLevel"))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MetaValidationLevel {
     Valid = 0,
@@ -51,6 +54,22 @@ impl ToString for MetaValidationIssue {
     }
 }
 
+impl MetaValidationIssue {
+    /// The `CIInfo` field the issue is about, named as in [`EnvValidationIssue::field`]
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::SubOptimal(MetaValidationIssueSubOptimal::EnvValidationIssueSubOptimal(i)) => {
+                i.field()
+            }
+            Self::Invalid(MetaValidationIssueInvalid::CIInfoBranchNameTooShort(..)) => "branch",
+            Self::Invalid(
+                MetaValidationIssueInvalid::CIInfoPRNumberMissing
+                | MetaValidationIssueInvalid::CIInfoPRNumberConflictsWithBranchClass,
+            ) => "pr_number",
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum MetaValidationIssueInvalid {
     #[error("CI info branch name too short")]
@@ -124,6 +143,10 @@ pub fn validate(meta_context: &MetaContext) -> MetaValidation {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass)]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "MetaValidation", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MetaValidation {
     level: MetaValidationLevel,
@@ -132,9 +155,14 @@ pub struct MetaValidation {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "MetaValidationFlatIssue", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MetaValidationFlatIssue {
     pub level: MetaValidationLevel,
+    pub field: String,
     pub error_message: String,
 }
 
@@ -150,6 +178,7 @@ impl MetaValidation {
             .iter()
             .map(|i| MetaValidationFlatIssue {
                 level: MetaValidationLevel::from(i),
+                field: i.field().to_string(),
                 error_message: i.to_string(),
             })
             .collect()
@@ -170,3 +199,41 @@ impl MetaValidation {
         self.issues.push(issue);
     }
 }
+
+#[cfg(feature = "ruby")]
+impl MetaValidationFlatIssue {
+    pub fn level(&self) -> MetaValidationLevel {
+        self.level
+    }
+    pub fn field(&self) -> String {
+        self.field.clone()
+    }
+    pub fn error_message(&self) -> String {
+        self.error_message.clone()
+    }
+}
+
+#[cfg(feature = "ruby")]
+pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let meta_validation_level = ruby.define_class("MetaValidationLevel", ruby.class_object())?;
+    meta_validation_level
+        .define_method("to_s", magnus::method!(MetaValidationLevel::to_string, 0))?;
+    let meta_validation = ruby.define_class("MetaValidation", ruby.class_object())?;
+    meta_validation.define_method("level", magnus::method!(MetaValidation::level, 0))?;
+    meta_validation.define_method("max_level", magnus::method!(MetaValidation::max_level, 0))?;
+    meta_validation.define_method(
+        "issues_flat",
+        magnus::method!(MetaValidation::issues_flat, 0),
+    )?;
+    let meta_validation_flat_issue =
+        ruby.define_class("MetaValidationFlatIssue", ruby.class_object())?;
+    meta_validation_flat_issue
+        .define_method("level", magnus::method!(MetaValidationFlatIssue::level, 0))?;
+    meta_validation_flat_issue
+        .define_method("field", magnus::method!(MetaValidationFlatIssue::field, 0))?;
+    meta_validation_flat_issue.define_method(
+        "error_message",
+        magnus::method!(MetaValidationFlatIssue::error_message, 0),
+    )?;
+    Ok(())
+}
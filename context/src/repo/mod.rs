@@ -1,9 +1,15 @@
 use std::path::PathBuf;
 #[cfg(feature = "git-access")]
-use std::process::Command;
+use std::{
+    collections::{BTreeSet, HashMap},
+    process::Command,
+};
 
 use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime};
 use lazy_static::lazy_static;
+#[cfg(feature = "ruby")]
+use magnus::Module;
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
@@ -13,13 +19,28 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+use crate::env::{parser::github_repo_url, EnvVars};
+
 pub mod validator;
 
 pub const GIT_REMOTE_ORIGIN_URL_CONFIG: &str = "remote.origin.url";
+/// Commit epochs at or above this are assumed to be in milliseconds, as in seconds they would be
+/// past the year 5000.
+const MILLISECOND_EPOCH_THRESHOLD: i64 = 100_000_000_000;
+#[cfg(feature = "git-access")]
+const GITLAB_MERGE_REQUEST_SOURCE_BRANCH_NAME_ENV: &str = "CI_MERGE_REQUEST_SOURCE_BRANCH_NAME";
+#[cfg(feature = "git-access")]
+const GITLAB_MERGE_REQUEST_SOURCE_BRANCH_SHA_ENV: &str = "CI_MERGE_REQUEST_SOURCE_BRANCH_SHA";
+
+const REPO_URL_SCHEMES: &[&str] = &["ssh", "git", "git+ssh", "http", "https", "ftp", "ftps"];
 
 lazy_static! {
+    /// `[user@]host:path`, where the host can't contain a slash so that local paths don't match
+    static ref SCP_LIKE_REPO_URL_REGEX: Regex = Regex::new(r"^(?:[^@/]+@)?([^@/:]+):(.+)$").unwrap();
     static ref GH_MERGE_BRANCH_REGEX: Regex =
         Regex::new(r"refs\/remotes\/pull\/[0-9]+\/merge").unwrap();
+    static ref GITLAB_MERGE_REQUEST_REF_REGEX: Regex =
+        Regex::new(r"refs\/merge-requests\/[0-9]+\/(head|merge)").unwrap();
 }
 
 #[derive(Debug, Clone, Default)]
@@ -29,10 +50,17 @@ struct BundleRepoOptions {
     repo_head_sha: Option<String>,
     repo_head_branch: Option<String>,
     repo_head_commit_epoch: Option<i64>,
+    repo_head_author_name: Option<String>,
+    repo_head_author_email: Option<String>,
+    repo_head_commit_message: Option<String>,
 }
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "BundleRepo", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BundleRepo {
     pub repo: RepoUrlParts,
@@ -46,17 +74,34 @@ pub struct BundleRepo {
     pub repo_head_commit_message: String,
     pub repo_head_author_name: String,
     pub repo_head_author_email: String,
+    /// Whether tracked files have uncommitted changes, `None` when the working tree status could
+    /// not be read. Recorded in the meta as `working_tree` rather than with the repo.
+    #[serde(skip)]
+    pub dirty: Option<bool>,
+    /// Number of tracked files with uncommitted changes, read along with `dirty`
+    #[serde(skip)]
+    pub num_modified_files: Option<usize>,
+    /// URL of the git remote of the checkout, when `repo_url` overrides it
+    #[serde(default)]
+    pub git_remote_url: Option<String>,
 }
 
 impl BundleRepo {
     const SHORT_SHA_LEN: usize = 7;
 
+    /// Reads the repo at `repo_root`, or the current directory, with the given overrides. CI
+    /// env vars, e.g. the source branch of a GitLab merge request, are taken from `env_vars`
+    /// rather than from the process.
     pub fn new(
         repo_root: Option<String>,
         repo_url: Option<String>,
         repo_head_sha: Option<String>,
         repo_head_branch: Option<String>,
         repo_head_commit_epoch: Option<String>,
+        repo_head_author_name: Option<String>,
+        repo_head_author_email: Option<String>,
+        repo_head_commit_message: Option<String>,
+        env_vars: &EnvVars,
     ) -> anyhow::Result<BundleRepo> {
         #[allow(unused_mut)]
         let mut bundle_repo_options = BundleRepoOptions {
@@ -67,12 +112,22 @@ impl BundleRepo {
             repo_url,
             repo_head_sha,
             repo_head_branch,
-            repo_head_commit_epoch: repo_head_commit_epoch.and_then(|s| s.parse().ok()),
+            repo_head_commit_epoch: repo_head_commit_epoch
+                .as_deref()
+                .map(parse_commit_epoch)
+                .transpose()?,
+            repo_head_author_name,
+            repo_head_author_email,
+            repo_head_commit_message,
         };
         #[allow(unused_mut)]
         let mut head_commit_message = None;
         #[allow(unused_mut)]
         let mut head_commit_author = None;
+        #[allow(unused_mut, unused_assignments)]
+        let mut num_modified_files = None;
+        #[allow(unused_mut)]
+        let mut git_remote_url = None;
 
         #[cfg(feature = "git-access")]
         {
@@ -88,17 +143,27 @@ impl BundleRepo {
                         .unwrap_or(PathBuf::from(""))
                 ))?;
 
-            bundle_repo_options.repo_url = bundle_repo_options.repo_url.or_else(|| {
-                git_repo
-                    .config_snapshot()
-                    .string(GIT_REMOTE_ORIGIN_URL_CONFIG)
-                    .map(|s| s.to_string())
-            });
+            let git_remote_origin_url = git_repo
+                .config_snapshot()
+                .string(GIT_REMOTE_ORIGIN_URL_CONFIG)
+                .map(|s| s.to_string());
+            if bundle_repo_options.repo_url.is_some() {
+                git_remote_url = git_remote_origin_url;
+            } else {
+                bundle_repo_options.repo_url = git_remote_origin_url;
+            }
 
             if let Ok(mut git_head) = git_repo.head() {
+                let git_head_branch = git_head.referent_name().map(|s| s.as_bstr().to_string());
+                let merge_request_source_branch = Self::gitlab_merge_request_source_branch(
+                    git_head.is_detached(),
+                    git_head_branch.as_deref(),
+                    env_vars,
+                );
                 bundle_repo_options.repo_head_branch = bundle_repo_options
                     .repo_head_branch
-                    .or_else(|| git_head.referent_name().map(|s| s.as_bstr().to_string()))
+                    .or(merge_request_source_branch)
+                    .or(git_head_branch)
                     .or_else(|| {
                         Self::git_head_branch_from_remote_branches(&git_repo)
                             .ok()
@@ -113,6 +178,7 @@ impl BundleRepo {
                             .repo_head_branch
                             .clone()
                             .unwrap_or_default(),
+                        env_vars,
                     );
 
                     bundle_repo_options.repo_head_sha = bundle_repo_options
@@ -129,16 +195,28 @@ impl BundleRepo {
                         .map(|a| (a.name.to_string(), a.email.to_string()));
                 }
             }
+
+            num_modified_files = count_modified_files(&git_repo)
+                .map_err(|e| log::debug!("Failed to get git working tree status: {}", e))
+                .ok();
         }
 
-        // Require URL which should be known at this point.
+        // Require URL which should be known at this point, falling back to GitHub Actions' env
+        // vars for checkouts without a remote.
         let repo_url = bundle_repo_options
             .repo_url
+            .or_else(|| github_repo_url(env_vars))
             .context("failed to get repo URL")?;
         let repo_url_parts =
             RepoUrlParts::from_url(&repo_url).context("failed to parse repo URL")?;
-        let (repo_head_author_name, repo_head_author_email) =
+        let (head_commit_author_name, head_commit_author_email) =
             head_commit_author.unwrap_or_default();
+        let repo_head_author_name = bundle_repo_options
+            .repo_head_author_name
+            .unwrap_or(head_commit_author_name);
+        let repo_head_author_email = bundle_repo_options
+            .repo_head_author_email
+            .unwrap_or(head_commit_author_email);
         let repo_head_sha = bundle_repo_options.repo_head_sha.unwrap_or_default();
         let repo_head_sha_short =
             &repo_head_sha[..std::cmp::min(Self::SHORT_SHA_LEN, repo_head_sha.len())];
@@ -155,12 +233,59 @@ impl BundleRepo {
             repo_head_commit_epoch: bundle_repo_options
                 .repo_head_commit_epoch
                 .unwrap_or_default(),
-            repo_head_commit_message: head_commit_message.unwrap_or_default(),
+            repo_head_commit_message: bundle_repo_options
+                .repo_head_commit_message
+                .or(head_commit_message)
+                .unwrap_or_default(),
             repo_head_author_name,
             repo_head_author_email,
+            dirty: num_modified_files.map(|num_modified_files| num_modified_files > 0),
+            num_modified_files,
+            git_remote_url,
+        })
+    }
+
+    /// A repo without any git metadata, for test results of checkouts that aren't available, e.g.
+    /// of tests run against a prebuilt artifact. Only the URL is known, so the head sha, branch,
+    /// commit and author are left empty.
+    pub fn repo_less(repo_root: Option<String>, repo_url: String) -> anyhow::Result<BundleRepo> {
+        let repo_url_parts =
+            RepoUrlParts::from_url(&repo_url).context("failed to parse repo URL")?;
+        Ok(BundleRepo {
+            repo: repo_url_parts,
+            repo_root: repo_root
+                .or_else(|| {
+                    std::env::current_dir()
+                        .ok()
+                        .and_then(|p| p.to_str().map(String::from))
+                })
+                .unwrap_or_default(),
+            repo_url,
+            ..Default::default()
         })
     }
 
+    /// Parts of the git remote, when `repo_url` overrides it with the URL of another repo, e.g. a
+    /// fork. Remotes that can't be parsed are not compared.
+    pub fn git_remote_mismatch(&self) -> Option<RepoUrlParts> {
+        let git_remote_url = self.git_remote_url.as_ref()?;
+        let git_remote = match RepoUrlParts::from_url(git_remote_url) {
+            Ok(git_remote) => git_remote,
+            Err(e) => {
+                log::debug!("Not comparing the repo URL to the git remote: {}", e);
+                return None;
+            }
+        };
+        if git_remote.host.eq_ignore_ascii_case(&self.repo.host)
+            && git_remote.owner.eq_ignore_ascii_case(&self.repo.owner)
+            && git_remote.name.eq_ignore_ascii_case(&self.repo.name)
+        {
+            None
+        } else {
+            Some(git_remote)
+        }
+    }
+
     #[cfg(feature = "git-access")]
     fn git_head_branch_from_remote_branches(
         git_repo: &gix::Repository,
@@ -179,12 +304,71 @@ impl BundleRepo {
         Ok(None)
     }
 
+    /// GitLab merge request pipelines check out a detached `refs/merge-requests/N/head` (or
+    /// `merge`) ref, so git can't tell us the source branch.
+    #[cfg(feature = "git-access")]
+    fn gitlab_merge_request_source_branch(
+        is_detached: bool,
+        git_head_branch: Option<&str>,
+        env_vars: &EnvVars,
+    ) -> Option<String> {
+        let is_merge_request_ref =
+            git_head_branch.is_some_and(|branch| GITLAB_MERGE_REQUEST_REF_REGEX.is_match(branch));
+        if !is_detached && !is_merge_request_ref {
+            return None;
+        }
+        env_vars
+            .get(GITLAB_MERGE_REQUEST_SOURCE_BRANCH_NAME_ENV)
+            .filter(|branch| !branch.is_empty())
+            .cloned()
+    }
+
+    /// For GitLab merged results pipelines, HEAD is the merge of the source and target branches,
+    /// so grab the source branch HEAD commit instead.
+    #[cfg(feature = "git-access")]
+    fn gitlab_merge_request_source_commit<'a>(
+        git_repo: &'a gix::Repository,
+        current_commit: &gix::Commit<'a>,
+        env_vars: &EnvVars,
+    ) -> Option<gix::Commit<'a>> {
+        let source_sha = env_vars
+            .get(GITLAB_MERGE_REQUEST_SOURCE_BRANCH_SHA_ENV)
+            .filter(|sha| !sha.is_empty())?;
+        if *source_sha == current_commit.id().to_string() {
+            return None;
+        }
+
+        log::info!("Detected merge request source branch SHA {}", source_sha);
+        let source_commit = gix::ObjectId::from_hex(source_sha.as_bytes())
+            .ok()
+            .and_then(|source_id| git_repo.find_commit(source_id).ok());
+        if let Some(source_commit) = &source_commit {
+            log::info!(
+                "Found merge request source branch HEAD commit with SHA {}, using this as commit",
+                source_commit.id().to_string()
+            );
+        } else {
+            log::info!(
+                "Merge request source branch HEAD commit not found. Defaulting to commit with SHA {}",
+                current_commit.id().to_string()
+            );
+        }
+        source_commit
+    }
+
     #[cfg(feature = "git-access")]
     fn resolve_repo_head_commit<'a>(
         git_repo: &'a gix::Repository,
         current_commit: gix::Commit<'a>,
         repo_head_branch: String,
+        env_vars: &EnvVars,
     ) -> gix::Commit<'a> {
+        if let Some(source_commit) =
+            Self::gitlab_merge_request_source_commit(git_repo, &current_commit, env_vars)
+        {
+            return source_commit;
+        }
+
         // for GH actions, grab PR branch HEAD commit, not the PR merge commit
         if GH_MERGE_BRANCH_REGEX.is_match(&repo_head_branch)
             && current_commit.parent_ids().count() == 2
@@ -251,11 +435,89 @@ impl BundleRepo {
     }
 }
 
+/// Parses a commit epoch given as seconds, milliseconds (converted to seconds with a warning) or
+/// an ISO-8601 datetime. Datetimes without an offset are interpreted as UTC.
+pub fn parse_commit_epoch<T: AsRef<str>>(commit_epoch: T) -> anyhow::Result<i64> {
+    let commit_epoch = commit_epoch.as_ref().trim();
+
+    if let Ok(epoch) = commit_epoch.parse::<i64>() {
+        if epoch >= MILLISECOND_EPOCH_THRESHOLD {
+            log::warn!(
+                "Repo head commit epoch {} looks like milliseconds, converting it to seconds",
+                epoch
+            );
+            return Ok(epoch / 1000);
+        }
+        return Ok(epoch);
+    }
+
+    DateTime::parse_from_rfc3339(commit_epoch)
+        .map(|datetime| datetime.timestamp())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(commit_epoch, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|datetime| datetime.and_utc().timestamp())
+        })
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "invalid repo head commit epoch {:?}, expected seconds since the Unix epoch or an ISO-8601 datetime",
+                commit_epoch
+            )
+        })
+}
+
+/// Counts the files with staged or unstaged changes, ignoring untracked files.
+#[cfg(feature = "git-access")]
+pub fn count_modified_files(git_repo: &gix::Repository) -> anyhow::Result<usize> {
+    let mut modified_files = BTreeSet::new();
+
+    for item in git_repo
+        .status(gix::progress::Discard)?
+        .index_worktree_rewrites(None)
+        .index_worktree_options_mut(|opts| {
+            opts.dirwalk_options = None;
+        })
+        .into_index_worktree_iter(Vec::new())?
+    {
+        modified_files.insert(item?.rela_path().to_owned());
+    }
+
+    let index = git_repo.index_or_empty()?;
+    let head_index = match git_repo.head_tree_id() {
+        Ok(head_tree_id) => Some(git_repo.index_from_tree(&head_tree_id)?),
+        // No commits yet, so everything in the index is staged
+        Err(_) => None,
+    };
+    let head_entries: HashMap<_, _> = head_index
+        .iter()
+        .flat_map(|head_index| {
+            head_index
+                .entries()
+                .iter()
+                .map(|entry| (entry.path(head_index), (entry.id, entry.mode)))
+        })
+        .collect();
+    for entry in index.entries() {
+        let path = entry.path(&index);
+        if head_entries.get(path) != Some(&(entry.id, entry.mode)) {
+            modified_files.insert(path.to_owned());
+        }
+    }
+    let index_paths: BTreeSet<_> = index.entries().iter().map(|e| e.path(&index)).collect();
+    for path in head_entries.keys() {
+        if !index_paths.contains(path) {
+            modified_files.insert((*path).to_owned());
+        }
+    }
+
+    Ok(modified_files.len())
+}
+
 #[cfg(feature = "pyo3")]
 #[gen_stub_pymethods]
 #[pymethods]
 impl BundleRepo {
     #[new]
+    #[pyo3(signature = (repo, repo_root, repo_url, repo_head_sha, repo_head_sha_short, repo_head_branch, repo_head_commit_epoch, repo_head_commit_message, repo_head_author_name, repo_head_author_email, dirty=None, git_remote_url=None, num_modified_files=None))]
     fn py_new(
         repo: RepoUrlParts,
         repo_root: String,
@@ -267,6 +529,9 @@ impl BundleRepo {
         repo_head_commit_message: String,
         repo_head_author_name: String,
         repo_head_author_email: String,
+        dirty: Option<bool>,
+        git_remote_url: Option<String>,
+        num_modified_files: Option<usize>,
     ) -> Self {
         Self {
             repo,
@@ -279,6 +544,9 @@ impl BundleRepo {
             repo_head_commit_message,
             repo_head_author_name,
             repo_head_author_email,
+            dirty,
+            num_modified_files,
+            git_remote_url,
         }
     }
 }
@@ -298,6 +566,9 @@ impl BundleRepo {
         repo_head_commit_message: String,
         repo_head_author_name: String,
         repo_head_author_email: String,
+        dirty: Option<bool>,
+        git_remote_url: Option<String>,
+        num_modified_files: Option<usize>,
     ) -> Self {
         Self {
             repo,
@@ -310,14 +581,64 @@ impl BundleRepo {
             repo_head_commit_message,
             repo_head_author_name,
             repo_head_author_email,
+            dirty,
+            num_modified_files,
+            git_remote_url,
         }
     }
 }
 
+#[cfg(feature = "ruby")]
+impl BundleRepo {
+    pub fn repo(&self) -> RepoUrlParts {
+        self.repo.clone()
+    }
+    pub fn repo_root(&self) -> String {
+        self.repo_root.clone()
+    }
+    pub fn repo_url(&self) -> String {
+        self.repo_url.clone()
+    }
+    pub fn repo_head_sha(&self) -> String {
+        self.repo_head_sha.clone()
+    }
+    pub fn repo_head_sha_short(&self) -> Option<String> {
+        self.repo_head_sha_short.clone()
+    }
+    pub fn repo_head_branch(&self) -> String {
+        self.repo_head_branch.clone()
+    }
+    pub fn repo_head_commit_epoch(&self) -> i64 {
+        self.repo_head_commit_epoch
+    }
+    pub fn repo_head_commit_message(&self) -> String {
+        self.repo_head_commit_message.clone()
+    }
+    pub fn repo_head_author_name(&self) -> String {
+        self.repo_head_author_name.clone()
+    }
+    pub fn repo_head_author_email(&self) -> String {
+        self.repo_head_author_email.clone()
+    }
+    pub fn dirty(&self) -> Option<bool> {
+        self.dirty
+    }
+    pub fn num_modified_files(&self) -> Option<usize> {
+        self.num_modified_files
+    }
+    pub fn git_remote_url(&self) -> Option<String> {
+        self.git_remote_url.clone()
+    }
+}
+
 /// The [`Repo` common type](https://github.com/trunk-io/trunk/blob/518397f/trunk/services/common/types/repo.ts#L10)
 // NOTE: This is named `RepoUrlParts` to prevent confusion as to its purpose
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "RepoUrlParts", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RepoUrlParts {
     pub host: String,
@@ -326,58 +647,73 @@ pub struct RepoUrlParts {
 }
 
 impl RepoUrlParts {
+    /// Parses a git remote, either a URL (`https://host/owner/name.git`, `ssh://git@host:22/...`)
+    /// or SCP-like syntax (`git@host:owner/name.git`).
+    ///
+    /// Everything in the path before the name is the owner, so GitLab subgroups give an owner of
+    /// `group/subgroup` and Azure DevOps gives `organization/project`. Ports and credentials are
+    /// never part of the parsed parts.
     pub fn from_url(url: &str) -> anyhow::Result<Self> {
-        let re1 = Regex::new(r"^(ssh|git|http|https|ftp|ftps)://([^/]*?@)?([^/]*)/(.+)/([^/]+)")?;
-        let re2 = Regex::new(r"^([^/]*?@)([^/]*):(.+)/([^/]+)")?;
-
-        let parts = if re1.is_match(url) {
-            let caps = re1.captures(url).expect("failed to parse url");
-            if caps.len() != 6 {
-                return Err(anyhow::anyhow!(
-                    "Invalid repo url format. Expected 6 parts: {:?} (url: {})",
-                    caps,
-                    url
-                ));
-            }
-            let domain = caps.get(3).map(|m| m.as_str()).unwrap_or("");
-            let owner = caps.get(4).map(|m| m.as_str()).unwrap_or("");
-            let name = caps.get(5).map(|m| m.as_str()).unwrap_or("");
-            (domain, owner, name)
-        } else if re2.is_match(url) {
-            let caps = re2.captures(url).expect("failed to parse url");
-            if caps.len() != 5 {
+        let url = url.trim();
+        let (host, path) = if url.contains("://") {
+            let parsed_url = url::Url::parse(url)
+                .map_err(|e| anyhow::anyhow!("Invalid repo url {:?}: {}", url, e))?;
+            if !REPO_URL_SCHEMES.contains(&parsed_url.scheme()) {
                 return Err(anyhow::anyhow!(
-                    "Invalid repo url format. Expected 6 parts: {:?} (url: {})",
-                    caps,
-                    url
+                    "Invalid repo url {:?}: unsupported scheme {:?}",
+                    url,
+                    parsed_url.scheme()
                 ));
             }
-            let domain = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-            let owner = caps.get(3).map(|m| m.as_str()).unwrap_or("");
-            let name = caps.get(4).map(|m| m.as_str()).unwrap_or("");
-            (domain, owner, name)
+            let host = parsed_url
+                .host_str()
+                .filter(|host| !host.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Invalid repo url {:?}: missing host", url))?
+                .to_string();
+            (host, parsed_url.path().to_string())
+        } else if let Some(caps) = SCP_LIKE_REPO_URL_REGEX.captures(url) {
+            (caps[1].to_string(), caps[2].to_string())
         } else {
             return Err(anyhow::anyhow!("Invalid repo url format: {}", url));
         };
 
-        let host = parts.0.trim().to_string();
-        let owner = parts.1.trim().to_string();
-        let name = parts
-            .2
-            .trim()
+        let host = host.to_ascii_lowercase();
+        let path = path
+            .trim_start_matches('/')
             .trim_end_matches('/')
-            .trim_end_matches(".git")
-            .to_string();
-
-        if host.is_empty() || owner.is_empty() || name.is_empty() {
+            .trim_end_matches(".git");
+        let mut segments = path.split('/').collect::<Vec<_>>();
+        if segments.iter().any(|segment| segment.trim().is_empty()) {
             return Err(anyhow::anyhow!(
-                "Invalid repo url format. Expected non-empty parts: {:?} (url: {})",
-                parts,
-                url
+                "Invalid repo url {:?}: empty owner or name in path {:?}",
+                url,
+                path
             ));
         }
+        // Azure DevOps, i.e. `v3/org/project/repo` over SSH and `org/project/_git/repo` over HTTPS
+        if host.ends_with("dev.azure.com") && segments.first() == Some(&"v3") {
+            segments.remove(0);
+        }
+        if let Some(git_index) = segments.iter().position(|segment| *segment == "_git") {
+            segments.remove(git_index);
+        }
 
-        Ok(Self { host, owner, name })
+        let (name, owner) = segments
+            .split_last()
+            .filter(|(_, owner)| !owner.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid repo url {:?}: expected an owner and a name in path {:?}",
+                    url,
+                    path
+                )
+            })?;
+
+        Ok(Self {
+            host,
+            owner: owner.join("/"),
+            name: name.to_string(),
+        })
     }
 
     pub fn repo_full_name(&self) -> String {
@@ -403,3 +739,70 @@ impl RepoUrlParts {
         Self { host, owner, name }
     }
 }
+
+#[cfg(feature = "ruby")]
+impl RepoUrlParts {
+    pub fn host(&self) -> String {
+        self.host.clone()
+    }
+    pub fn owner(&self) -> String {
+        self.owner.clone()
+    }
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+#[cfg(feature = "ruby")]
+pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let repo_url_parts = ruby.define_class("RepoUrlParts", ruby.class_object())?;
+    repo_url_parts.define_method("host", magnus::method!(RepoUrlParts::host, 0))?;
+    repo_url_parts.define_method("owner", magnus::method!(RepoUrlParts::owner, 0))?;
+    repo_url_parts.define_method("name", magnus::method!(RepoUrlParts::name, 0))?;
+    repo_url_parts.define_method(
+        "repo_full_name",
+        magnus::method!(RepoUrlParts::repo_full_name, 0),
+    )?;
+    let bundle_repo = ruby.define_class("BundleRepo", ruby.class_object())?;
+    bundle_repo.define_method("repo", magnus::method!(BundleRepo::repo, 0))?;
+    bundle_repo.define_method("repo_root", magnus::method!(BundleRepo::repo_root, 0))?;
+    bundle_repo.define_method("repo_url", magnus::method!(BundleRepo::repo_url, 0))?;
+    bundle_repo.define_method(
+        "repo_head_sha",
+        magnus::method!(BundleRepo::repo_head_sha, 0),
+    )?;
+    bundle_repo.define_method(
+        "repo_head_sha_short",
+        magnus::method!(BundleRepo::repo_head_sha_short, 0),
+    )?;
+    bundle_repo.define_method(
+        "repo_head_branch",
+        magnus::method!(BundleRepo::repo_head_branch, 0),
+    )?;
+    bundle_repo.define_method(
+        "repo_head_commit_epoch",
+        magnus::method!(BundleRepo::repo_head_commit_epoch, 0),
+    )?;
+    bundle_repo.define_method(
+        "repo_head_commit_message",
+        magnus::method!(BundleRepo::repo_head_commit_message, 0),
+    )?;
+    bundle_repo.define_method(
+        "repo_head_author_name",
+        magnus::method!(BundleRepo::repo_head_author_name, 0),
+    )?;
+    bundle_repo.define_method(
+        "repo_head_author_email",
+        magnus::method!(BundleRepo::repo_head_author_email, 0),
+    )?;
+    bundle_repo.define_method("dirty", magnus::method!(BundleRepo::dirty, 0))?;
+    bundle_repo.define_method(
+        "num_modified_files",
+        magnus::method!(BundleRepo::num_modified_files, 0),
+    )?;
+    bundle_repo.define_method(
+        "git_remote_url",
+        magnus::method!(BundleRepo::git_remote_url, 0),
+    )?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+#[cfg(feature = "ruby")]
+use magnus::Module;
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
 #[cfg(feature = "pyo3")]
@@ -15,11 +17,13 @@ pub const MAX_EMAIL_LEN: usize = 254;
 pub const MAX_FIELD_LEN: usize = 1000;
 pub const MAX_SHA_FIELD_LEN: usize = 40;
 
+const TIMESTAMP_FUTURE_MAX_DAYS: u32 = 1;
 const TIMESTAMP_OLD_DAYS: u32 = 30;
 const TIMESTAMP_STALE_HOURS: u32 = 1;
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass_enum, pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "ruby", magnus::wrap(class = "RepoValidationLevel"))]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RepoValidationLevel {
     Valid = 0,
@@ -58,6 +62,46 @@ impl ToString for RepoValidationIssue {
     }
 }
 
+impl RepoValidationIssue {
+    /// The `BundleRepo` field the issue is about, which is stable across releases unlike the
+    /// message
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::SubOptimal(i) => match i {
+                RepoValidationIssueSubOptimal::RepoAuthorEmailTooShort(..)
+                | RepoValidationIssueSubOptimal::RepoAuthorEmailTooLong(..) => {
+                    "repo_head_author_email"
+                }
+                RepoValidationIssueSubOptimal::RepoAuthorNameTooShort(..)
+                | RepoValidationIssueSubOptimal::RepoAuthorNameTooLong(..) => {
+                    "repo_head_author_name"
+                }
+                RepoValidationIssueSubOptimal::RepoBranchNameTooShort(..)
+                | RepoValidationIssueSubOptimal::RepoBranchNameTooLong(..) => "repo_head_branch",
+                RepoValidationIssueSubOptimal::RepoCommitMessageTooShort(..)
+                | RepoValidationIssueSubOptimal::RepoCommitMessageTooLong(..) => {
+                    "repo_head_commit_message"
+                }
+                RepoValidationIssueSubOptimal::RepoCommitFutureTimestamp(..)
+                | RepoValidationIssueSubOptimal::RepoCommitOldTimestamp(..)
+                | RepoValidationIssueSubOptimal::RepoCommitStaleTimestamp(..) => {
+                    "repo_head_commit_epoch"
+                }
+                RepoValidationIssueSubOptimal::RepoShaTooLong(..) => "repo_head_sha",
+                RepoValidationIssueSubOptimal::RepoWorkingTreeDirty(..) => "dirty",
+                RepoValidationIssueSubOptimal::RepoUrlGitRemoteMismatch(..) => "repo_url",
+            },
+            Self::Invalid(i) => match i {
+                RepoValidationIssueInvalid::RepoShaTooShort(..) => "repo_head_sha",
+                RepoValidationIssueInvalid::RepoCommitNegativeTimestamp(..)
+                | RepoValidationIssueInvalid::RepoCommitFarFutureTimestamp(..) => {
+                    "repo_head_commit_epoch"
+                }
+            },
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum RepoValidationIssueSubOptimal {
     #[error("repo head commit author email too short")]
@@ -93,12 +137,27 @@ pub enum RepoValidationIssueSubOptimal {
     RepoCommitStaleTimestamp(DateTime<Utc>),
     #[error("repo sha too long, truncated to {}", MAX_SHA_FIELD_LEN)]
     RepoShaTooLong(String),
+    #[error(
+        "repo working tree has {0} modified file(s), test results may not match the head commit"
+    )]
+    RepoWorkingTreeDirty(usize),
+    #[error(
+        "repo URL {0} does not match the git remote {1}, test results are uploaded to the repo of {0}"
+    )]
+    RepoUrlGitRemoteMismatch(String, String),
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum RepoValidationIssueInvalid {
     #[error("repo sha too short")]
     RepoShaTooShort(String),
+    #[error("repo head commit epoch {0} is negative")]
+    RepoCommitNegativeTimestamp(i64),
+    #[error(
+        "repo head commit epoch {0} is more than {} day(s) in the future",
+        TIMESTAMP_FUTURE_MAX_DAYS
+    )]
+    RepoCommitFarFutureTimestamp(i64),
 }
 
 impl From<&RepoValidationIssue> for RepoValidationLevel {
@@ -183,8 +242,22 @@ pub fn validate(bundle_repo: &BundleRepo) -> RepoValidation {
         }
     };
 
-    if let Some(timestamp) = DateTime::from_timestamp(bundle_repo.repo_head_commit_epoch, 0) {
-        let now = Utc::now();
+    let now = Utc::now();
+    let max_timestamp = now + TimeDelta::days(i64::from(TIMESTAMP_FUTURE_MAX_DAYS));
+    if bundle_repo.repo_head_commit_epoch < 0 {
+        repo_validation.add_issue(RepoValidationIssue::Invalid(
+            RepoValidationIssueInvalid::RepoCommitNegativeTimestamp(
+                bundle_repo.repo_head_commit_epoch,
+            ),
+        ));
+    } else if bundle_repo.repo_head_commit_epoch > max_timestamp.timestamp() {
+        repo_validation.add_issue(RepoValidationIssue::Invalid(
+            RepoValidationIssueInvalid::RepoCommitFarFutureTimestamp(
+                bundle_repo.repo_head_commit_epoch,
+            ),
+        ));
+    } else if let Some(timestamp) = DateTime::from_timestamp(bundle_repo.repo_head_commit_epoch, 0)
+    {
         let time_since_timestamp = now - timestamp;
 
         if timestamp > now {
@@ -202,11 +275,30 @@ pub fn validate(bundle_repo: &BundleRepo) -> RepoValidation {
         }
     }
 
+    if bundle_repo.git_remote_mismatch().is_some() {
+        repo_validation.add_issue(RepoValidationIssue::SubOptimal(
+            RepoValidationIssueSubOptimal::RepoUrlGitRemoteMismatch(
+                bundle_repo.repo_url.clone(),
+                bundle_repo.git_remote_url.clone().unwrap_or_default(),
+            ),
+        ));
+    }
+
+    if let Some(num_modified_files @ 1..) = bundle_repo.num_modified_files {
+        repo_validation.add_issue(RepoValidationIssue::SubOptimal(
+            RepoValidationIssueSubOptimal::RepoWorkingTreeDirty(num_modified_files),
+        ));
+    }
+
     repo_validation
 }
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(eq))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "RepoValidation", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RepoValidation {
     level: RepoValidationLevel,
@@ -215,9 +307,14 @@ pub struct RepoValidation {
 
 #[cfg_attr(feature = "pyo3", gen_stub_pyclass, pyclass(get_all))]
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(
+    feature = "ruby",
+    magnus::wrap(class = "RepoValidationFlatIssue", free_immediately, size)
+)]
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RepoValidationFlatIssue {
     pub level: RepoValidationLevel,
+    pub field: String,
     pub error_message: String,
 }
 
@@ -233,6 +330,7 @@ impl RepoValidation {
             .iter()
             .map(|i| RepoValidationFlatIssue {
                 level: RepoValidationLevel::from(i),
+                field: i.field().to_string(),
                 error_message: i.to_string(),
             })
             .collect()
@@ -253,3 +351,41 @@ impl RepoValidation {
         self.issues.push(issue);
     }
 }
+
+#[cfg(feature = "ruby")]
+impl RepoValidationFlatIssue {
+    pub fn level(&self) -> RepoValidationLevel {
+        self.level
+    }
+    pub fn field(&self) -> String {
+        self.field.clone()
+    }
+    pub fn error_message(&self) -> String {
+        self.error_message.clone()
+    }
+}
+
+#[cfg(feature = "ruby")]
+pub fn ruby_init(ruby: &magnus::Ruby) -> Result<(), magnus::Error> {
+    let repo_validation_level = ruby.define_class("RepoValidationLevel", ruby.class_object())?;
+    repo_validation_level
+        .define_method("to_s", magnus::method!(RepoValidationLevel::to_string, 0))?;
+    let repo_validation = ruby.define_class("RepoValidation", ruby.class_object())?;
+    repo_validation.define_method("level", magnus::method!(RepoValidation::level, 0))?;
+    repo_validation.define_method("max_level", magnus::method!(RepoValidation::max_level, 0))?;
+    repo_validation.define_method(
+        "issues_flat",
+        magnus::method!(RepoValidation::issues_flat, 0),
+    )?;
+    let repo_validation_flat_issue =
+        ruby.define_class("RepoValidationFlatIssue", ruby.class_object())?;
+    repo_validation_flat_issue
+        .define_method("level", magnus::method!(RepoValidationFlatIssue::level, 0))?;
+    repo_validation_flat_issue
+        .define_method("field", magnus::method!(RepoValidationFlatIssue::field, 0))?;
+    repo_validation_flat_issue.define_method(
+        "error_message",
+        magnus::method!(RepoValidationFlatIssue::error_message, 0),
+    )?;
+    Ok(())
+}
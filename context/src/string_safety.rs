@@ -1,9 +1,9 @@
 pub fn safe_truncate_string<'a, const MAX_LEN: usize, T: AsRef<str>>(value: &'a T) -> &'a str {
-    safe_truncate_str::<MAX_LEN>(value.as_ref())
+    safe_truncate_str(value.as_ref(), MAX_LEN)
 }
 
-pub fn safe_truncate_str<'a, const MAX_LEN: usize>(value: &'a str) -> &'a str {
-    &value.trim()[..value.trim().floor_char_boundary(MAX_LEN)]
+pub fn safe_truncate_str(value: &str, max_len: usize) -> &str {
+    &value.trim()[..value.trim().floor_char_boundary(max_len)]
 }
 
 #[derive(Debug, Clone)]
@@ -34,8 +34,8 @@ mod tests {
 
     #[test]
     fn test_safe_truncate_str() {
-        pretty_assertions::assert_eq!("trunk", safe_truncate_str::<5>("trunkate me!"));
-        pretty_assertions::assert_eq!("trunkate me!", safe_truncate_str::<100>(" trunkate me! "));
-        pretty_assertions::assert_eq!("trunk", safe_truncate_str::<5>(" trunkate me! "));
+        pretty_assertions::assert_eq!("trunk", safe_truncate_str("trunkate me!", 5));
+        pretty_assertions::assert_eq!("trunkate me!", safe_truncate_str(" trunkate me! ", 100));
+        pretty_assertions::assert_eq!("trunk", safe_truncate_str(" trunkate me! ", 5));
     }
 }
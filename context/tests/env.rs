@@ -1,9 +1,16 @@
 use context::env::{
     self,
-    parser::{BranchClass, CIInfo, CIPlatform, EnvParser},
-    validator::{EnvValidationIssue, EnvValidationIssueSubOptimal, EnvValidationLevel},
+    parser::{
+        github_repo_url, BranchClass, CIInfo, CIPlatform, EnvParser, GitLabMergeRequestEventType,
+        StableBranches,
+    },
+    validator::{
+        EnvValidationIssue, EnvValidationIssueInvalid, EnvValidationIssueSubOptimal,
+        EnvValidationLevel,
+    },
     EnvVars,
 };
+use context::repo::RepoUrlParts;
 
 #[test]
 fn test_simple_buildkite() {
@@ -39,6 +46,8 @@ fn test_simple_buildkite() {
             branch: Some(branch),
             branch_class: Some(BranchClass::None),
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: None,
             committer_name: None,
             committer_email: None,
@@ -48,6 +57,8 @@ fn test_simple_buildkite() {
             title: None,
             workflow: None,
             job: None,
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -79,6 +90,23 @@ fn test_simple_buildkite() {
             ),),
         ]
     );
+    pretty_assertions::assert_eq!(
+        env_validation
+            .issues_flat()
+            .into_iter()
+            .map(|issue| (issue.level, issue.field))
+            .collect::<Vec<_>>(),
+        [
+            "actor",
+            "author_email",
+            "author_name",
+            "commit_message",
+            "committer_email",
+            "committer_name",
+            "title"
+        ]
+        .map(|field| (EnvValidationLevel::SubOptimal, String::from(field)))
+    );
 }
 
 #[test]
@@ -126,6 +154,8 @@ fn test_simple_drone() {
             branch: Some(branch),
             branch_class: Some(BranchClass::PullRequest),
             pr_number: Some(pr_number),
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor),
             committer_name: Some(name.clone()),
             committer_email: Some(email.clone()),
@@ -135,6 +165,8 @@ fn test_simple_drone() {
             title: Some(title),
             workflow: None,
             job: None,
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -189,6 +221,8 @@ fn test_simple_github() {
             branch: Some(branch),
             branch_class: Some(BranchClass::None),
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor),
             committer_name: None,
             committer_email: None,
@@ -198,6 +232,8 @@ fn test_simple_github() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -274,6 +310,8 @@ fn test_simple_github_pr() {
             branch: Some(branch),
             branch_class: Some(BranchClass::PullRequest),
             pr_number: Some(pr_number),
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor),
             committer_name: None,
             committer_email: None,
@@ -283,6 +321,8 @@ fn test_simple_github_pr() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -313,6 +353,179 @@ fn test_simple_github_pr() {
     );
 }
 
+#[test]
+fn test_github_enterprise_server_pr() {
+    let run_id = String::from("42069");
+    let pr_number = 123;
+    let actor = String::from("username");
+    let repository = String::from("test/tester");
+    let branch = String::from("some-branch-name");
+    let workflow = String::from("Pull Request");
+    let job = String::from("test-job");
+
+    let env_vars = EnvVars::from_iter(vec![
+        (String::from("GITHUB_ACTIONS"), String::from("true")),
+        (
+            String::from("GITHUB_SERVER_URL"),
+            String::from("https://github.mycorp.com"),
+        ),
+        (
+            String::from("GITHUB_API_URL"),
+            String::from("https://github.mycorp.com/api/v3"),
+        ),
+        (
+            String::from("GITHUB_EVENT_NAME"),
+            String::from("pull_request"),
+        ),
+        (String::from("GITHUB_RUN_ID"), String::from(&run_id)),
+        (String::from("GITHUB_ACTOR"), String::from(&actor)),
+        (String::from("GITHUB_REPOSITORY"), String::from(&repository)),
+        (String::from("GITHUB_HEAD_REF"), String::from(&branch)),
+        (
+            String::from("GITHUB_REF"),
+            format!("refs/pull/{pr_number}/merge"),
+        ),
+        (String::from("GITHUB_WORKFLOW"), String::from(&workflow)),
+        (String::from("GITHUB_JOB"), String::from(&job)),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info_parser = env_parser.into_ci_info_parser().unwrap();
+    assert_eq!(
+        ci_info_parser.provenance().get("job_url"),
+        Some(&vec![
+            String::from("GITHUB_SERVER_URL"),
+            String::from("GITHUB_REPOSITORY"),
+            String::from("GITHUB_RUN_ID"),
+        ])
+    );
+
+    pretty_assertions::assert_eq!(
+        ci_info_parser.info_ci_info(),
+        CIInfo {
+            platform: CIPlatform::GitHubActions,
+            job_url: Some(format!(
+                "https://github.mycorp.com/{repository}/actions/runs/{run_id}?pr={pr_number}"
+            )),
+            branch: Some(branch),
+            branch_class: Some(BranchClass::PullRequest),
+            pr_number: Some(pr_number),
+            target_branch: None,
+            merge_request_event_type: None,
+            actor: Some(actor),
+            committer_name: None,
+            committer_email: None,
+            author_name: None,
+            author_email: None,
+            commit_message: None,
+            title: None,
+            workflow: Some(workflow),
+            job: Some(job),
+            build_number: None,
+            node_name: None,
+        }
+    );
+}
+
+#[test]
+fn test_github_enterprise_server_api_url_only() {
+    let env_vars = EnvVars::from_iter(vec![
+        (String::from("GITHUB_ACTIONS"), String::from("true")),
+        (
+            String::from("GITHUB_API_URL"),
+            String::from("https://github.mycorp.com/api/v3/"),
+        ),
+        (String::from("GITHUB_RUN_ID"), String::from("42069")),
+        (
+            String::from("GITHUB_REPOSITORY"),
+            String::from("test/tester"),
+        ),
+        (String::from("GITHUB_REF"), String::from("refs/heads/main")),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info = env_parser.into_ci_info_parser().unwrap().info_ci_info();
+    assert_eq!(
+        ci_info.job_url,
+        Some(String::from(
+            "https://github.mycorp.com/test/tester/actions/runs/42069"
+        ))
+    );
+    assert_eq!(ci_info.branch_class, Some(BranchClass::ProtectedBranch));
+}
+
+#[test]
+fn test_github_api_url_of_github_com() {
+    let env_vars = EnvVars::from_iter(vec![
+        (String::from("GITHUB_ACTIONS"), String::from("true")),
+        (
+            String::from("GITHUB_API_URL"),
+            String::from("https://api.github.com"),
+        ),
+        (String::from("GITHUB_RUN_ID"), String::from("42069")),
+        (
+            String::from("GITHUB_REPOSITORY"),
+            String::from("test/tester"),
+        ),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info = env_parser.into_ci_info_parser().unwrap().info_ci_info();
+    assert_eq!(
+        ci_info.job_url,
+        Some(String::from(
+            "https://github.com/test/tester/actions/runs/42069"
+        ))
+    );
+}
+
+#[test]
+fn test_github_repo_url() {
+    let github_com_env_vars = EnvVars::from_iter(vec![(
+        String::from("GITHUB_REPOSITORY"),
+        String::from("test/tester"),
+    )]);
+    let repo_url = github_repo_url(&github_com_env_vars).unwrap();
+    assert_eq!(repo_url, "https://github.com/test/tester");
+    assert_eq!(
+        RepoUrlParts::from_url(&repo_url).unwrap(),
+        RepoUrlParts {
+            host: String::from("github.com"),
+            owner: String::from("test"),
+            name: String::from("tester"),
+        }
+    );
+
+    let enterprise_env_vars = EnvVars::from_iter(vec![
+        (
+            String::from("GITHUB_SERVER_URL"),
+            String::from("https://GitHub.MyCorp.com/"),
+        ),
+        (
+            String::from("GITHUB_REPOSITORY"),
+            String::from("test/tester"),
+        ),
+    ]);
+    let repo_url = github_repo_url(&enterprise_env_vars).unwrap();
+    assert_eq!(repo_url, "https://GitHub.MyCorp.com/test/tester");
+    assert_eq!(
+        RepoUrlParts::from_url(&repo_url).unwrap(),
+        RepoUrlParts {
+            host: String::from("github.mycorp.com"),
+            owner: String::from("test"),
+            name: String::from("tester"),
+        }
+    );
+
+    assert_eq!(github_repo_url(&EnvVars::default()), None);
+}
+
 #[test]
 fn test_simple_github_merge_queue() {
     let run_id = String::from("42069");
@@ -357,6 +570,8 @@ fn test_simple_github_merge_queue() {
             branch: Some(branch),
             branch_class: Some(BranchClass::Merge),
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor),
             committer_name: None,
             committer_email: None,
@@ -366,6 +581,8 @@ fn test_simple_github_merge_queue() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -440,6 +657,8 @@ fn test_simple_github_trunk_merge_queue() {
             branch: Some(branch),
             branch_class: Some(BranchClass::Merge),
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor),
             committer_name: None,
             committer_email: None,
@@ -449,6 +668,8 @@ fn test_simple_github_trunk_merge_queue() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 }
@@ -497,6 +718,8 @@ fn test_simple_github_graphite_merge_queue() {
             branch: Some(branch),
             branch_class: Some(BranchClass::Merge),
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor),
             committer_name: None,
             committer_email: None,
@@ -506,6 +729,8 @@ fn test_simple_github_graphite_merge_queue() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 }
@@ -562,6 +787,8 @@ fn test_simple_semaphore() {
             branch: Some(branch),
             branch_class: Some(BranchClass::None),
             pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor.clone()),
             committer_name: None,
             committer_email: None,
@@ -571,6 +798,8 @@ fn test_simple_semaphore() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -641,6 +870,8 @@ fn test_simple_gitlab_pr() {
             branch: Some(branch),
             branch_class: Some(BranchClass::PullRequest),
             pr_number: Some(pr_number),
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(actor.clone()),
             committer_name: Some(actor.clone()),
             committer_email: Some(email.clone()),
@@ -650,6 +881,8 @@ fn test_simple_gitlab_pr() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -715,6 +948,8 @@ fn test_simple_gitlab_merge_branch() {
             branch: Some(branch),
             branch_class: Some(BranchClass::Merge),
             pr_number: Some(pr_number),
+            target_branch: None,
+            merge_request_event_type: Some(GitLabMergeRequestEventType::MergeTrain),
             actor: Some(actor.clone()),
             committer_name: Some(actor.clone()),
             committer_email: Some(email.clone()),
@@ -724,6 +959,8 @@ fn test_simple_gitlab_merge_branch() {
             title: None,
             workflow: Some(workflow),
             job: Some(job),
+            build_number: None,
+            node_name: None,
         }
     );
 
@@ -742,6 +979,197 @@ fn test_simple_gitlab_merge_branch() {
     );
 }
 
+#[test]
+fn test_gitlab_merge_request_detached_head() {
+    let pr_number = 42;
+    let source_branch = String::from("feature/some-change");
+    let target_branch = String::from("main");
+
+    let env_vars = EnvVars::from_iter(vec![
+        (String::from("GITLAB_CI"), String::from("true")),
+        (
+            String::from("CI_COMMIT_REF_NAME"),
+            format!("refs/merge-requests/{pr_number}/head"),
+        ),
+        (
+            String::from("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME"),
+            String::from(&source_branch),
+        ),
+        (
+            String::from("CI_MERGE_REQUEST_TARGET_BRANCH_NAME"),
+            String::from(&target_branch),
+        ),
+        (String::from("CI_MERGE_REQUEST_IID"), pr_number.to_string()),
+        (
+            String::from("CI_MERGE_REQUEST_EVENT_TYPE"),
+            String::from("detached"),
+        ),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info_parser = env_parser.into_ci_info_parser().unwrap();
+    assert_eq!(
+        ci_info_parser.provenance().get("branch"),
+        Some(&vec![String::from("CI_MERGE_REQUEST_SOURCE_BRANCH_NAME")])
+    );
+    let ci_info = ci_info_parser.info_ci_info();
+
+    assert_eq!(ci_info.branch, Some(source_branch));
+    assert_eq!(ci_info.pr_number, Some(pr_number));
+    assert_eq!(ci_info.target_branch, Some(target_branch));
+    assert_eq!(
+        ci_info.merge_request_event_type,
+        Some(GitLabMergeRequestEventType::Detached)
+    );
+    assert_eq!(ci_info.branch_class, Some(BranchClass::PullRequest));
+}
+
+#[test]
+fn test_simple_jenkins() {
+    let job_url = String::from("https://jenkins.example.com/job/analytics-cli/42/");
+    let build_number = String::from("42");
+    let node_name = String::from("linux-agent-1");
+
+    let env_vars = EnvVars::from_iter(vec![
+        (
+            String::from("JENKINS_URL"),
+            String::from("https://jenkins.example.com/"),
+        ),
+        (String::from("BUILD_URL"), String::from(&job_url)),
+        (String::from("GIT_BRANCH"), String::from("origin/main")),
+        (String::from("BUILD_NUMBER"), String::from(&build_number)),
+        (String::from("NODE_NAME"), String::from(&node_name)),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info_parser = env_parser.into_ci_info_parser().unwrap();
+    assert_eq!(
+        ci_info_parser.provenance().get("branch"),
+        Some(&vec![String::from("GIT_BRANCH")])
+    );
+    let ci_info = ci_info_parser.info_ci_info();
+
+    pretty_assertions::assert_eq!(
+        ci_info,
+        CIInfo {
+            platform: CIPlatform::Jenkins,
+            job_url: Some(job_url),
+            branch: Some(String::from("main")),
+            branch_class: Some(BranchClass::ProtectedBranch),
+            pr_number: None,
+            target_branch: None,
+            merge_request_event_type: None,
+            actor: None,
+            committer_name: None,
+            committer_email: None,
+            author_name: None,
+            author_email: None,
+            commit_message: None,
+            title: None,
+            workflow: None,
+            job: None,
+            build_number: Some(build_number),
+            node_name: Some(node_name),
+        }
+    );
+}
+
+#[test]
+fn test_jenkins_git_branch_remote_ref() {
+    let env_vars = EnvVars::from_iter(vec![
+        (
+            String::from("JENKINS_URL"),
+            String::from("https://jenkins.example.com/"),
+        ),
+        (
+            String::from("GIT_BRANCH"),
+            String::from("refs/remotes/origin/feature/some-change"),
+        ),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info = env_parser.into_ci_info_parser().unwrap().info_ci_info();
+
+    assert_eq!(ci_info.platform, CIPlatform::Jenkins);
+    assert_eq!(ci_info.branch, Some(String::from("feature/some-change")));
+    assert_eq!(ci_info.branch_class, Some(BranchClass::None));
+}
+
+#[test]
+fn test_jenkins_multibranch_pr() {
+    let pr_number = 7;
+    let source_branch = String::from("feature/some-change");
+    let target_branch = String::from("main");
+
+    let env_vars = EnvVars::from_iter(vec![
+        (
+            String::from("JENKINS_URL"),
+            String::from("https://jenkins.example.com/"),
+        ),
+        (String::from("BUILD_ID"), String::from("3")),
+        (
+            String::from("BUILD_URL"),
+            String::from("https://jenkins.example.com/job/analytics-cli/job/PR-7/3/"),
+        ),
+        (String::from("BRANCH_NAME"), format!("PR-{pr_number}")),
+        (String::from("GIT_BRANCH"), format!("PR-{pr_number}")),
+        (String::from("CHANGE_ID"), pr_number.to_string()),
+        (String::from("CHANGE_BRANCH"), String::from(&source_branch)),
+        (String::from("CHANGE_TARGET"), String::from(&target_branch)),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info_parser = env_parser.into_ci_info_parser().unwrap();
+    assert_eq!(
+        ci_info_parser.provenance().get("branch"),
+        Some(&vec![String::from("CHANGE_BRANCH")])
+    );
+    let ci_info = ci_info_parser.info_ci_info();
+
+    assert_eq!(ci_info.platform, CIPlatform::JenkinsPipeline);
+    assert_eq!(ci_info.branch, Some(source_branch));
+    assert_eq!(ci_info.branch_class, Some(BranchClass::PullRequest));
+    assert_eq!(ci_info.pr_number, Some(pr_number));
+    assert_eq!(ci_info.target_branch, Some(target_branch));
+}
+
+#[test]
+fn test_jenkins_requires_job_url() {
+    let env_vars = EnvVars::from_iter(vec![
+        (
+            String::from("JENKINS_URL"),
+            String::from("https://jenkins.example.com/"),
+        ),
+        (String::from("GIT_BRANCH"), String::from("origin/main")),
+    ]);
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info = env_parser.into_ci_info_parser().unwrap().info_ci_info();
+    let env_validation = env::validator::validate(&ci_info);
+
+    assert_eq!(env_validation.max_level(), EnvValidationLevel::Invalid);
+    assert!(env_validation
+        .issues()
+        .contains(&EnvValidationIssue::Invalid(
+            EnvValidationIssueInvalid::CIInfoJobURLMissing
+        )));
+    assert!(!env_validation
+        .issues()
+        .contains(&EnvValidationIssue::SubOptimal(
+            EnvValidationIssueSubOptimal::CIInfoJobURLTooShort(String::from(""))
+        )));
+}
+
 #[test]
 fn test_custom_config() {
     let job_url = String::from("https://example.com");
@@ -781,6 +1209,8 @@ fn test_custom_config() {
             branch: Some(commit_branch),
             branch_class: Some(BranchClass::PullRequest),
             pr_number: Some(pr_number),
+            target_branch: None,
+            merge_request_event_type: None,
             actor: Some(author_email.clone()),
             committer_name: Some(author_name.clone()),
             committer_email: Some(author_email.clone()),
@@ -790,6 +1220,99 @@ fn test_custom_config() {
             title: Some(pr_title),
             workflow: Some(job_name.clone()),
             job: Some(job_name),
+            build_number: None,
+            node_name: None,
         }
     );
 }
+
+#[test]
+fn test_github_provenance() {
+    let env_vars = EnvVars::from_iter(
+        vec![
+            (String::from("GITHUB_ACTIONS"), String::from("true")),
+            (
+                String::from("GITHUB_REF"),
+                String::from("refs/pull/123/merge"),
+            ),
+            (String::from("GITHUB_HEAD_REF"), String::from("feature")),
+            (String::from("GITHUB_ACTOR"), String::from("username")),
+            (
+                String::from("GITHUB_REPOSITORY"),
+                String::from("owner/repo"),
+            ),
+            (String::from("GITHUB_RUN_ID"), String::from("42")),
+            (String::from("GITHUB_WORKFLOW"), String::from("")),
+        ]
+        .into_iter(),
+    );
+
+    let mut env_parser = EnvParser::new();
+    env_parser.parse(&env_vars);
+
+    let ci_info_parser = env_parser.into_ci_info_parser().unwrap();
+    let provenance = ci_info_parser.provenance();
+
+    assert_eq!(
+        provenance.get("branch"),
+        Some(&vec![String::from("GITHUB_HEAD_REF")])
+    );
+    assert_eq!(
+        provenance.get("pr_number"),
+        Some(&vec![String::from("GITHUB_REF")])
+    );
+    assert_eq!(
+        provenance.get("job_url"),
+        Some(&vec![
+            String::from("GITHUB_REPOSITORY"),
+            String::from("GITHUB_RUN_ID")
+        ])
+    );
+    assert_eq!(
+        provenance.get("actor"),
+        Some(&vec![String::from("GITHUB_ACTOR")])
+    );
+    assert_eq!(provenance.get("workflow"), None);
+}
+
+#[test]
+fn test_stable_branch_patterns() {
+    let stable_branches =
+        StableBranches::new(&["main", "re:release/.*", "re:hotfix/[a-z-]+"]).unwrap();
+    let classify = |branch: &str| BranchClass::from((branch, None, None, &stable_branches));
+
+    assert_eq!(classify("main"), BranchClass::ProtectedBranch);
+    assert_eq!(classify("release/2024.11"), BranchClass::ProtectedBranch);
+    assert_eq!(classify("hotfix/urgent-fix"), BranchClass::ProtectedBranch);
+    assert_eq!(classify("master"), BranchClass::None);
+    assert_eq!(classify("feature/release/2024.11"), BranchClass::None);
+    assert_eq!(classify("hotfix/Urgent"), BranchClass::None);
+
+    let default_stable_branches = StableBranches::new::<&str>(&[]).unwrap();
+    assert!(default_stable_branches.matches("master"));
+    assert!(default_stable_branches.matches("main"));
+
+    assert!(StableBranches::new(&["re:release/("])
+        .unwrap_err()
+        .to_string()
+        .starts_with("invalid stable branch pattern \"release/(\""));
+}
+
+#[test]
+fn test_env_parser_with_stable_branches() {
+    let env_vars = EnvVars::from_iter(vec![
+        (String::from("GITHUB_ACTIONS"), String::from("true")),
+        (
+            String::from("GITHUB_REF"),
+            String::from("refs/heads/release/2024.11"),
+        ),
+    ]);
+
+    let mut env_parser =
+        EnvParser::with_stable_branches(StableBranches::new(&["re:release/.*"]).unwrap());
+    env_parser.parse(&env_vars);
+    let ci_info = env_parser.into_ci_info_parser().unwrap().info_ci_info();
+
+    assert_eq!(ci_info.branch, Some(String::from("release/2024.11")));
+    assert_eq!(ci_info.branch_class, Some(BranchClass::ProtectedBranch));
+}
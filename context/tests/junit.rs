@@ -1,20 +1,39 @@
-use std::{fs, io::BufReader, time::Duration};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader},
+    time::Duration,
+};
 
 use chrono::{NaiveTime, TimeDelta, Utc};
 use context::junit::{
     self,
+    attr_map::JunitAttrMap,
+    diff::{diff_reports, JunitDiffAttributeChange, JunitDiffStatusChange, JunitDiffTestKey},
+    mmap::{MMAP_MIN_FILE_SIZE, NO_MMAP_ENV_VAR},
     parser::extra_attrs,
-    parser::JunitParser,
+    parser::{
+        JunitParseIssue, JunitParseIssueSubOptimal, JunitParser, MAX_TEST_SUITE_DEPTH,
+        MAX_TEXT_FIELD_SIZE, MAX_TIME,
+    },
+    sanitize::sanitize_junit_xml,
+    schema::{check_rules, closest_schema, JunitSchema},
+    skip_reason::{extract_skip_reason, infer_skip_kind},
     validator::{
-        JunitTestCaseValidationIssue, JunitTestCaseValidationIssueInvalid,
-        JunitTestCaseValidationIssueSubOptimal, JunitTestSuiteValidationIssue,
-        JunitTestSuiteValidationIssueInvalid, JunitTestSuiteValidationIssueSubOptimal,
-        JunitValidationIssue, JunitValidationLevel,
+        find_duplicate_test_case_ids, validate_with_options, DuplicateTestCaseId,
+        JunitReportValidation, JunitReportValidationIssueInvalid, JunitTestCaseValidationIssue,
+        JunitTestCaseValidationIssueInvalid, JunitTestCaseValidationIssueSubOptimal,
+        JunitTestSuiteValidationIssue, JunitTestSuiteValidationIssueInvalid,
+        JunitTestSuiteValidationIssueSubOptimal, JunitValidationIssue, JunitValidationIssueType,
+        JunitValidationLevel, JunitValidationOptions, WallClockSource,
+        DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD,
     },
 };
 use junit_mock::JunitMock;
-use quick_junit::Report;
+use proto::test_context::test_run::SkipKind;
+use quick_junit::{NonSuccessKind, Report, TestCaseStatus, TestRerun};
 use tempfile::TempDir;
+use test_utils::inputs::get_test_file_path;
 
 fn new_mock_junit_options(
     report_count: usize,
@@ -79,6 +98,18 @@ fn parse_report<T: AsRef<[u8]>>(serialized_report: T) -> Report {
     parsed_reports.pop().unwrap()
 }
 
+fn parse_report_with_issues<R: BufRead>(xml: R, issues: &[JunitParseIssue]) -> Report {
+    let mut junit_parser = JunitParser::new();
+    junit_parser.parse(xml).unwrap();
+
+    assert_eq!(junit_parser.issues(), issues);
+
+    let mut parsed_reports = junit_parser.into_reports();
+    assert_eq!(parsed_reports.len(), 1);
+
+    parsed_reports.pop().unwrap()
+}
+
 #[test]
 fn validate_test_suite_name_too_short() {
     let (seed, mut generated_reports) = generate_mock_junit_reports(1, Some(1), None);
@@ -224,6 +255,74 @@ fn validate_test_case_invalid_id() {
     );
 }
 
+fn junit_with_test_case_ids<T: Fn(usize) -> String>(num_test_cases: usize, id: T) -> Report {
+    let test_cases = (0..num_test_cases)
+        .map(|i| {
+            format!(
+                r#"<testcase name="test_{i}" classname="MyClass" id="{}" time="1" />"#,
+                id(i)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+    parse_report(format!(
+        r#"<testsuites><testsuite name="suite">{}</testsuite></testsuites>"#,
+        test_cases
+    ))
+}
+
+#[test]
+fn validate_test_cases_sharing_an_id() {
+    let constant_id = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, "constant".as_bytes());
+    let report = junit_with_test_case_ids(DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD + 10, |_| {
+        constant_id.to_string()
+    });
+
+    pretty_assertions::assert_eq!(
+        find_duplicate_test_case_ids([&report], DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD),
+        vec![DuplicateTestCaseId {
+            id: constant_id.to_string(),
+            count: DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD + 10,
+            example_names: vec![
+                String::from("MyClass.test_0"),
+                String::from("MyClass.test_1"),
+                String::from("MyClass.test_2"),
+            ],
+        }]
+    );
+
+    let report_validation = junit::validator::validate(&report);
+    assert_eq!(report_validation.max_level(), JunitValidationLevel::Invalid);
+    assert!(report_validation.valid_test_suites.is_empty());
+    pretty_assertions::assert_eq!(
+        report_validation.all_issues()[0],
+        JunitValidationIssueType::Report(JunitValidationIssue::Invalid(
+            JunitReportValidationIssueInvalid::TestCasesShareId {
+                id: constant_id.to_string(),
+                count: DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD + 10,
+            }
+        ))
+    );
+
+    // a higher threshold lets the same report through
+    let report_validation = junit::validator::validate_with_duplicate_id_threshold(
+        &report,
+        DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD + 10,
+    );
+    assert_eq!(report_validation.num_invalid_issues(), 0);
+}
+
+#[test]
+fn validate_test_cases_with_unique_ids() {
+    let report = junit_with_test_case_ids(DEFAULT_DUPLICATE_TEST_CASE_ID_THRESHOLD + 10, |i| {
+        uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, i.to_string().as_bytes()).to_string()
+    });
+
+    assert!(find_duplicate_test_case_ids([&report], 1).is_empty());
+    let report_validation = junit::validator::validate(&report);
+    assert_eq!(report_validation.num_invalid_issues(), 0);
+}
+
 #[test]
 fn validate_test_case_name_too_short() {
     let (seed, mut generated_reports) = generate_mock_junit_reports(1, Some(1), Some(1));
@@ -477,3 +576,1587 @@ fn parse_without_testsuites_element() {
 
     pretty_assertions::assert_eq!(reports_with_default_testsuites, parsed_reports)
 }
+
+#[test]
+fn diff_reports_between_junit_files() {
+    let base = parse_report(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="base">
+    <testsuite name="suite">
+        <testcase classname="a" name="unchanged" time="1.0" file="a.rs" />
+        <testcase classname="a" name="removed" time="1.0" />
+        <testcase classname="a" name="starts_failing" time="1.0" />
+        <testcase classname="a" name="slows_down" time="1.0" />
+        <testcase classname="a" name="moves_file" time="1.0" file="a.rs" line="1" />
+    </testsuite>
+</testsuites>
+"#,
+    );
+    let head = parse_report(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="head">
+    <testsuite name="suite">
+        <testcase classname="a" name="unchanged" time="1.5" file="a.rs" />
+        <testcase classname="a" name="added" time="1.0" />
+        <testcase classname="a" name="starts_failing" time="1.0">
+            <failure message="boom" />
+        </testcase>
+        <testcase classname="a" name="slows_down" time="5.0" />
+        <testcase classname="a" name="moves_file" time="1.0" file="b.rs" line="1" />
+    </testsuite>
+</testsuites>
+"#,
+    );
+
+    let test_key = |name: &str| JunitDiffTestKey {
+        classname: String::from("a"),
+        name: String::from(name),
+    };
+
+    let diff = diff_reports(&[base.clone()], &[head], Duration::from_secs(1));
+    assert_eq!(diff.added, vec![test_key("added")]);
+    assert_eq!(diff.removed, vec![test_key("removed")]);
+    assert_eq!(
+        diff.status_changes,
+        vec![JunitDiffStatusChange {
+            test: test_key("starts_failing"),
+            base: String::from("success"),
+            head: String::from("failure"),
+        }]
+    );
+    assert_eq!(diff.duration_changes.len(), 1);
+    assert_eq!(diff.duration_changes[0].test, test_key("slows_down"));
+    assert_eq!(
+        diff.attribute_changes,
+        vec![JunitDiffAttributeChange {
+            test: test_key("moves_file"),
+            attribute: String::from(extra_attrs::FILE),
+            base: Some(String::from("a.rs")),
+            head: Some(String::from("b.rs")),
+        }]
+    );
+
+    assert!(diff_reports(&[base.clone()], &[base], Duration::ZERO).is_empty());
+}
+
+#[test]
+fn diff_reports_with_duplicate_test_keys() {
+    let base = parse_report(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="base">
+    <testsuite name="suite">
+        <testcase classname="a" name="duplicated" time="1.0" />
+        <testcase classname="a" name="duplicated" time="1.0">
+            <failure message="boom" />
+        </testcase>
+    </testsuite>
+</testsuites>
+"#,
+    );
+    let head = parse_report(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="head">
+    <testsuite name="suite">
+        <testcase classname="a" name="duplicated" time="1.0" />
+    </testsuite>
+</testsuites>
+"#,
+    );
+
+    let diff = diff_reports(&[base], &[head], Duration::ZERO);
+    assert_eq!(
+        diff.base_duplicates,
+        vec![JunitDiffTestKey {
+            classname: String::from("a"),
+            name: String::from("duplicated"),
+        }]
+    );
+    assert!(diff.head_duplicates.is_empty());
+    // the first test case with a key is compared, rather than the last overwriting it
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn parse_and_validate_windows_file_paths() {
+    // Trimmed output of a VSTest run on a Windows runner using JunitXml.TestLogger
+    let vstest_junit_xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites>
+  <testsuite name="Foo.Tests.dll" tests="3" skipped="0" failures="1" errors="0" time="0.4212" timestamp="2024-11-07T18:22:10" hostname="fv-az1234-567" id="0" package="Foo.Tests.dll">
+    <properties />
+    <testcase classname="Foo.Tests.BarTests" name="Adds_two_numbers" time="0.0123" file="D:\a\foo\foo\src\Foo.Tests\BarTests.cs" />
+    <testcase classname="Foo.Tests.BarTests" name="Subtracts_two_numbers" time="0.0045" filepath="src\Foo.Tests\BarTests.cs" />
+    <testcase classname="Foo.Tests.SharedFixtureTests" name="Loads_shared_fixture" time="0.2871" file="C:\Users\runneradmin\.nuget\packages\foo.fixtures\1.2.0\SharedFixture.cs">
+      <failure type="failure" message="Assert.Equal() Failure">   at Foo.Tests.SharedFixtureTests.Loads_shared_fixture() in C:\Users\runneradmin\.nuget\packages\foo.fixtures\1.2.0\SharedFixture.cs:line 42</failure>
+    </testcase>
+    <system-out>Data collector 'Blame' message: All tests finished running.</system-out>
+  </testsuite>
+</testsuites>
+"#;
+
+    let mut junit_parser = JunitParser::with_repo_root(r"D:\a\foo\foo");
+    junit_parser
+        .parse(BufReader::new(vstest_junit_xml.as_bytes()))
+        .unwrap();
+    assert!(junit_parser.issues().is_empty());
+    let report = junit_parser.into_reports().pop().unwrap();
+
+    let test_cases = &report.test_suites[0].test_cases;
+    assert_eq!(
+        test_cases[0]
+            .extra
+            .get(extra_attrs::FILE)
+            .map(|f| f.as_str()),
+        Some("src/Foo.Tests/BarTests.cs")
+    );
+    assert_eq!(
+        test_cases[1]
+            .extra
+            .get(extra_attrs::FILEPATH)
+            .map(|f| f.as_str()),
+        Some("src/Foo.Tests/BarTests.cs")
+    );
+    let outside_repo_path =
+        "C:/Users/runneradmin/.nuget/packages/foo.fixtures/1.2.0/SharedFixture.cs";
+    assert_eq!(
+        test_cases[2]
+            .extra
+            .get(extra_attrs::FILE)
+            .map(|f| f.as_str()),
+        Some(outside_repo_path)
+    );
+
+    let report_validation = junit::validator::validate(&report);
+    let file_path_issues = report_validation
+        .test_suites()
+        .iter()
+        .flat_map(|test_suite| test_suite.test_cases())
+        .flat_map(|test_case| Vec::from(test_case.issues()))
+        .filter(|issue| {
+            matches!(
+                issue,
+                JunitValidationIssue::SubOptimal(
+                    JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathTooShort(..)
+                        | JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathTooLong(..)
+                        | JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathOutsideRepo(
+                            ..
+                        )
+                )
+            )
+        })
+        .collect::<Vec<JunitTestCaseValidationIssue>>();
+    pretty_assertions::assert_eq!(
+        file_path_issues,
+        vec![JunitValidationIssue::SubOptimal(
+            JunitTestCaseValidationIssueSubOptimal::TestCaseFileOrFilepathOutsideRepo(
+                String::from(outside_repo_path)
+            ),
+        )]
+    );
+}
+
+#[test]
+fn parse_and_validate_sanitized_test_names() {
+    let long_name = format!("test_data_table[{}]", "row,".repeat(5_000));
+    let junit_xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="suite" time="1">
+    <testcase classname="foo&#10;bar" name="&#27;[31mtest_foo&#27;[0m  fails&#10;on&#9;newline" time="0.1" file="foo_test.rs" />
+    <testcase classname="foo" name="{long_name}" time="0.1" file="foo_test.rs" />
+    <testcase classname="foo" name="test_bar" time="0.1" file="foo_test.rs" />
+  </testsuite>
+</testsuites>
+"#
+    );
+
+    let mut junit_parser = JunitParser::new().with_max_test_name_len(100);
+    junit_parser
+        .parse(BufReader::new(junit_xml.as_bytes()))
+        .unwrap();
+    assert_eq!(junit_parser.issues(), &[]);
+    let report = junit_parser.into_reports().pop().unwrap();
+
+    let test_cases = &report.test_suites[0].test_cases;
+    assert_eq!(test_cases[0].name.as_str(), "test_foo fails on newline");
+    assert_eq!(
+        test_cases[0].classname.as_ref().map(|c| c.as_str()),
+        Some("foo bar")
+    );
+    assert_eq!(
+        test_cases[0]
+            .extra
+            .get(extra_attrs::NAME_SANITIZED)
+            .map(|s| s.as_str()),
+        Some("true")
+    );
+    assert_eq!(test_cases[1].name.as_str(), &long_name[..100]);
+    assert_eq!(
+        test_cases[1]
+            .extra
+            .get(extra_attrs::ORIGINAL_NAME_LEN)
+            .map(|s| s.as_str()),
+        Some(long_name.len().to_string().as_str())
+    );
+    assert!(test_cases[2]
+        .extra
+        .get(extra_attrs::NAME_SANITIZED)
+        .is_none());
+    assert!(test_cases[2]
+        .extra
+        .get(extra_attrs::ORIGINAL_NAME_LEN)
+        .is_none());
+
+    let report_validation = junit::validator::validate(&report);
+    let name_issues = report_validation
+        .test_suites()
+        .iter()
+        .flat_map(|test_suite| test_suite.test_cases())
+        .flat_map(|test_case| Vec::from(test_case.issues()))
+        .filter(|issue| {
+            matches!(
+                issue,
+                JunitValidationIssue::SubOptimal(
+                    JunitTestCaseValidationIssueSubOptimal::TestCaseNameSanitized(..)
+                        | JunitTestCaseValidationIssueSubOptimal::TestCaseNameTruncated(..)
+                )
+            )
+        })
+        .collect::<Vec<_>>();
+    pretty_assertions::assert_eq!(
+        name_issues,
+        vec![
+            JunitValidationIssue::SubOptimal(
+                JunitTestCaseValidationIssueSubOptimal::TestCaseNameSanitized(String::from(
+                    "test_foo fails on newline"
+                ))
+            ),
+            JunitValidationIssue::SubOptimal(
+                JunitTestCaseValidationIssueSubOptimal::TestCaseNameTruncated(long_name.len())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn parse_and_validate_file_inferred_from_classname() {
+    let vitest_junit_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="vitest tests" tests="2" failures="0" errors="0" time="0.5">
+  <testsuite name="src/components/Button.test.tsx" timestamp="2024-11-14T18:28:18.000Z" hostname="localhost" tests="2" failures="0" errors="0" skipped="0" time="0.5">
+    <testcase classname="src/components/Button.test.tsx" name="Button &gt; renders" time="0.2" />
+    <testcase classname="src/components/Button.test.tsx" name="Button &gt; clicks" time="0.3" file="src/components/Other.test.tsx" />
+  </testsuite>
+</testsuites>
+"#;
+    let pytest_junit_xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites>
+  <testsuite name="pytest" errors="0" failures="0" skipped="0" tests="2" time="0.1" timestamp="2024-11-14T18:28:18.000000" hostname="localhost">
+    <testcase classname="tests.test_foo" name="test_foo" time="0.05" />
+    <testcase classname="tests/test_bar.py" name="test_bar" time="0.05" />
+  </testsuite>
+</testsuites>
+"#;
+    let java_junit_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="com.foo.BarTest" tests="1" failures="0" errors="0" skipped="0" time="0.1" timestamp="2024-11-14T18:28:18">
+  <testcase name="testBar" classname="com.foo.Bar" time="0.1" />
+</testsuite>
+"#;
+
+    let parse = |junit_xml: &str, infer_file_from_classname: bool| -> Report {
+        let mut junit_parser =
+            JunitParser::new().with_infer_file_from_classname(infer_file_from_classname);
+        junit_parser
+            .parse(BufReader::new(junit_xml.as_bytes()))
+            .unwrap();
+        assert_eq!(junit_parser.issues(), &[]);
+        junit_parser.into_reports().pop().unwrap()
+    };
+    let file_and_inferred = |report: &Report| -> Vec<(Option<String>, Option<String>)> {
+        report.test_suites[0]
+            .test_cases
+            .iter()
+            .map(|test_case| {
+                (
+                    test_case
+                        .extra
+                        .get(extra_attrs::FILE)
+                        .map(|file| String::from(file.as_str())),
+                    test_case
+                        .extra
+                        .get(extra_attrs::FILE_INFERRED_FROM_CLASSNAME)
+                        .map(|file| String::from(file.as_str())),
+                )
+            })
+            .collect()
+    };
+
+    let vitest_report = parse(vitest_junit_xml, true);
+    assert_eq!(
+        file_and_inferred(&vitest_report),
+        vec![
+            (None, Some(String::from("src/components/Button.test.tsx"))),
+            (Some(String::from("src/components/Other.test.tsx")), None),
+        ]
+    );
+    let report_validation = junit::validator::validate(&vitest_report);
+    assert!(!report_validation
+        .all_issues_flat()
+        .iter()
+        .any(|issue| issue.error_message.contains("file or filepath")));
+
+    assert_eq!(
+        file_and_inferred(&parse(pytest_junit_xml, true)),
+        vec![
+            (None, None),
+            (None, Some(String::from("tests/test_bar.py")))
+        ]
+    );
+    assert_eq!(
+        file_and_inferred(&parse(java_junit_xml, true)),
+        vec![(None, None)]
+    );
+    assert_eq!(
+        file_and_inferred(&parse(vitest_junit_xml, false)),
+        vec![
+            (None, None),
+            (Some(String::from("src/components/Other.test.tsx")), None),
+        ]
+    );
+}
+
+#[test]
+fn parse_utf16_and_windows_1252_junit_files() {
+    let parse_fixture = |fixture: &str| -> Report {
+        let file = fs::File::open(get_test_file_path(fixture)).unwrap();
+        parse_report_with_issues(BufReader::new(file), &[])
+    };
+
+    let utf8_report = parse_fixture("test_fixtures/junit_utf8.xml");
+    let test_case = &utf8_report.test_suites[0].test_cases[1];
+    assert_eq!(test_case.name.as_str(), "zahle_5_€");
+    assert_eq!(
+        test_case.extra.get(extra_attrs::FILE).map(|f| f.as_str()),
+        Some("tests/café_test.py")
+    );
+
+    // `Report` isn't `PartialEq`, so compare the re-serialized reports
+    let utf8_xml = utf8_report.to_string().unwrap();
+    assert_eq!(
+        parse_fixture("test_fixtures/junit_utf16le.xml")
+            .to_string()
+            .unwrap(),
+        utf8_xml
+    );
+    assert_eq!(
+        parse_fixture("test_fixtures/junit_windows1252.xml")
+            .to_string()
+            .unwrap(),
+        utf8_xml
+    );
+
+    let utf8_with_bom = [
+        b"\xEF\xBB\xBF".as_slice(),
+        &fs::read(get_test_file_path("test_fixtures/junit_utf8.xml")).unwrap(),
+    ]
+    .concat();
+    assert_eq!(
+        parse_report_with_issues(utf8_with_bom.as_slice(), &[])
+            .to_string()
+            .unwrap(),
+        utf8_xml
+    );
+}
+
+#[test]
+fn parse_junit_with_undecodable_bytes() {
+    let junit_xml = [
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"".as_slice(),
+        b"caf\xE9 \xFF",
+        b"\">\n  <testsuite name=\"suite\">\n    <testcase name=\"test\" />\n  </testsuite>\n</testsuites>\n",
+    ]
+    .concat();
+
+    let report = parse_report_with_issues(
+        junit_xml.as_slice(),
+        &[JunitParseIssue::SubOptimal(
+            JunitParseIssueSubOptimal::UndecodableBytesReplaced(2),
+        )],
+    );
+    assert_eq!(report.name.as_str(), "caf\u{FFFD} \u{FFFD}");
+    assert_eq!(report.test_suites[0].test_cases.len(), 1);
+}
+
+#[test]
+fn parse_nested_test_suites_from_surefire_and_jest() {
+    let parse_fixture = |fixture: &str| -> Report {
+        let file = fs::File::open(get_test_file_path(fixture)).unwrap();
+        parse_report_with_issues(BufReader::new(file), &[])
+    };
+    let test_suite_summaries = |report: &Report| -> Vec<(String, usize)> {
+        report
+            .test_suites
+            .iter()
+            .map(|test_suite| {
+                (
+                    String::from(test_suite.name.as_str()),
+                    test_suite.test_cases.len(),
+                )
+            })
+            .collect()
+    };
+
+    // the outer test suite declares tests="6"
+    let surefire_report = parse_fixture("test_fixtures/junit_surefire_nested.xml");
+    assert_eq!(
+        test_suite_summaries(&surefire_report),
+        vec![
+            (
+                String::from(
+                    "com.example.orders.OrdersTestSuite > com.example.orders.OrderServiceTest"
+                ),
+                4
+            ),
+            (
+                String::from(
+                    "com.example.orders.OrdersTestSuite > com.example.orders.OrderRepositoryTest"
+                ),
+                2
+            ),
+        ]
+    );
+    assert_eq!(surefire_report.tests, 6);
+    assert_eq!(surefire_report.failures, 1);
+    assert_eq!(surefire_report.errors, 1);
+    let order_service_test_suite = &surefire_report.test_suites[0];
+    assert_eq!(
+        order_service_test_suite
+            .extra
+            .get(extra_attrs::FILE)
+            .map(|f| f.as_str()),
+        Some("src/test/java/com/example/orders/OrderServiceTest.java")
+    );
+    // inherited from the outer test suite
+    assert_eq!(
+        order_service_test_suite.timestamp,
+        surefire_report.test_suites[1].timestamp
+    );
+    assert!(order_service_test_suite.timestamp.is_some());
+
+    // the report declares tests="4", with a test case directly in the outer `web` test suite
+    let jest_report = parse_fixture("test_fixtures/junit_jest_projects_nested.xml");
+    assert_eq!(
+        test_suite_summaries(&jest_report),
+        vec![
+            (String::from("web > Button"), 2),
+            (String::from("web"), 1),
+            (String::from("api"), 1),
+        ]
+    );
+    assert_eq!(jest_report.tests, 4);
+    assert_eq!(
+        jest_report.test_suites[0].timestamp,
+        jest_report.test_suites[1].timestamp
+    );
+}
+
+#[test]
+fn parse_nested_test_suites_with_separator_and_max_depth() {
+    let junit_xml = r#"
+        <testsuites>
+            <testsuite name="outer">
+                <testsuite name="inner">
+                    <testcase name="test" />
+                </testsuite>
+            </testsuite>
+        </testsuites>
+    "#;
+    let mut junit_parser = JunitParser::new().with_test_suite_name_separator("::");
+    junit_parser.parse(junit_xml.as_bytes()).unwrap();
+    assert_eq!(junit_parser.issues(), &[]);
+    assert_eq!(
+        junit_parser.reports()[0].test_suites[0].name.as_str(),
+        "outer::inner"
+    );
+
+    let depth = 100;
+    let junit_xml = format!(
+        "<testsuites>{}<testcase name=\"test\" />{}</testsuites>",
+        (0..depth).fold(String::new(), |mut test_suites, i| {
+            test_suites.push_str(&format!("<testsuite name=\"suite{}\">", i));
+            test_suites
+        }),
+        "</testsuite>".repeat(depth),
+    );
+    let report = parse_report_with_issues(
+        junit_xml.as_bytes(),
+        &[JunitParseIssue::SubOptimal(
+            JunitParseIssueSubOptimal::TestSuiteMaxDepthExceeded(MAX_TEST_SUITE_DEPTH),
+        )],
+    );
+    assert_eq!(report.test_suites.len(), 1);
+    assert_eq!(report.test_suites[0].test_cases.len(), 1);
+    assert_eq!(
+        report.test_suites[0].name.as_str(),
+        (0..MAX_TEST_SUITE_DEPTH)
+            .map(|i| format!("suite{}", i))
+            .collect::<Vec<_>>()
+            .join(" > ")
+    );
+}
+
+#[test]
+fn parse_test_suite_hostname_and_package() {
+    let junit_xml = fs::read_to_string(get_test_file_path(
+        "test_fixtures/junit_android_sharded.xml",
+    ))
+    .unwrap();
+    let report = parse_report_with_issues(junit_xml.as_bytes(), &[]);
+    let suite_attrs: Vec<(Option<&str>, Option<&str>)> = report
+        .test_suites
+        .iter()
+        .map(|test_suite| {
+            (
+                test_suite
+                    .extra
+                    .get(extra_attrs::HOSTNAME)
+                    .map(|h| h.as_str()),
+                test_suite
+                    .extra
+                    .get(extra_attrs::PACKAGE)
+                    .map(|p| p.as_str()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        suite_attrs,
+        vec![
+            (Some("emulator-5554"), Some("com.example.app")),
+            (Some("emulator-5556"), Some("com.example.app.checkout")),
+        ]
+    );
+
+    // nested test suites inherit them
+    let nested_report = parse_report_with_issues(
+        r#"<testsuites><testsuite name="outer" hostname="emulator-5554" package="com.example.app"><testsuite name="inner" package="com.example.app.inner"><testcase name="test" /></testsuite></testsuite></testsuites>"#.as_bytes(),
+        &[],
+    );
+    let inner = &nested_report.test_suites[0];
+    assert_eq!(
+        inner.extra.get(extra_attrs::HOSTNAME).map(|h| h.as_str()),
+        Some("emulator-5554")
+    );
+    assert_eq!(
+        inner.extra.get(extra_attrs::PACKAGE).map(|p| p.as_str()),
+        Some("com.example.app.inner")
+    );
+
+    // and their absence is not a validation issue
+    let without_attrs = junit_xml
+        .replace(r#" hostname="emulator-5554" package="com.example.app""#, "")
+        .replace(
+            r#" hostname="emulator-5556" package="com.example.app.checkout""#,
+            "",
+        );
+    let report_without_attrs = parse_report_with_issues(without_attrs.as_bytes(), &[]);
+    assert!(report_without_attrs.test_suites[0].extra.is_empty());
+    assert_eq!(
+        junit::validator::validate(&report_without_attrs).all_issues_flat(),
+        junit::validator::validate(&report).all_issues_flat()
+    );
+}
+
+#[test]
+fn parse_time_in_non_standard_formats() {
+    let parse_fixture = |fixture: &str, issues: &[JunitParseIssue]| -> Report {
+        let file = fs::File::open(get_test_file_path(fixture)).unwrap();
+        parse_report_with_issues(BufReader::new(file), issues)
+    };
+    let test_case_times = |report: &Report| -> Vec<Option<Duration>> {
+        report.test_suites[0]
+            .test_cases
+            .iter()
+            .map(|test_case| test_case.time)
+            .collect()
+    };
+    let non_standard_format = [JunitParseIssue::SubOptimal(
+        JunitParseIssueSubOptimal::TimeNonStandardFormat,
+    )];
+
+    let report = parse_fixture(
+        "test_fixtures/junit_time_comma_decimal.xml",
+        &non_standard_format,
+    );
+    assert_eq!(report.time, Some(Duration::from_secs_f64(1235.0045)));
+    assert_eq!(
+        report.test_suites[0].time,
+        Some(Duration::from_secs_f64(1234.6045))
+    );
+    assert_eq!(
+        test_case_times(&report),
+        vec![
+            Some(Duration::from_secs_f64(0.0123)),
+            Some(Duration::from_secs_f64(1234.56)),
+            Some(Duration::from_secs_f64(0.0322)),
+        ]
+    );
+
+    let report = parse_fixture(
+        "test_fixtures/junit_time_scientific.xml",
+        &non_standard_format,
+    );
+    assert_eq!(
+        report.test_suites[0].time,
+        Some(Duration::from_secs_f64(20.015))
+    );
+    assert_eq!(
+        test_case_times(&report),
+        vec![
+            Some(Duration::from_secs_f64(0.0015)),
+            Some(Duration::from_secs(20)),
+        ]
+    );
+
+    // Surrounding whitespace is trimmed from every attribute, so it isn't an issue
+    let report = parse_fixture("test_fixtures/junit_time_whitespace.xml", &[]);
+    assert_eq!(
+        report.test_suites[0].time,
+        Some(Duration::from_secs_f64(0.25))
+    );
+    assert_eq!(
+        test_case_times(&report),
+        vec![Some(Duration::from_secs_f64(0.25))]
+    );
+
+    // Out of range durations are clamped, but are not written in a non-standard format
+    let report = parse_fixture("test_fixtures/junit_time_out_of_range.xml", &[]);
+    assert_eq!(
+        test_case_times(&report),
+        vec![Some(Duration::ZERO), Some(MAX_TIME)]
+    );
+}
+
+#[test]
+fn validate_duration_variance() {
+    let parse_fixture = |fixture: &str| -> Report {
+        let file = fs::File::open(get_test_file_path(fixture)).unwrap();
+        parse_report_with_issues(BufReader::new(file), &[])
+    };
+    let duration_variance_issues = |report_validation: &JunitReportValidation| {
+        report_validation
+            .test_suites()
+            .iter()
+            .flat_map(|test_suite| test_suite.issues())
+            .filter_map(|issue| match issue {
+                JunitValidationIssue::SubOptimal(
+                    JunitTestSuiteValidationIssueSubOptimal::TestSuiteDurationVariance {
+                        name,
+                        declared_duration,
+                        wall_clock_duration,
+                        wall_clock_source,
+                    },
+                ) => Some((
+                    name.clone(),
+                    declared_duration.as_secs(),
+                    wall_clock_duration.as_secs(),
+                    *wall_clock_source,
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let duration_coverage_ratio = |report_validation: &JunitReportValidation| {
+        report_validation
+            .duration_coverage
+            .and_then(|duration_coverage| duration_coverage.ratio())
+    };
+
+    let report_validation =
+        junit::validator::validate(&parse_fixture("test_fixtures/junit_durations_zeroed.xml"));
+    assert_eq!(
+        duration_variance_issues(&report_validation),
+        vec![
+            (
+                String::from("integration.CheckoutTest"),
+                0,
+                1800,
+                WallClockSource::SuiteTime
+            ),
+            (
+                String::from("integration.CheckoutTest"),
+                0,
+                1800,
+                WallClockSource::SuiteTimestamps
+            ),
+            (
+                String::from("integration.LoginTest"),
+                0,
+                600,
+                WallClockSource::SuiteTime
+            ),
+        ]
+    );
+    assert_eq!(
+        report_validation.test_suites()[0].issues()[0].to_string(),
+        "test suite integration.CheckoutTest has test case durations adding up to 0.0s, but its time is 1800.0s"
+    );
+    assert_eq!(duration_coverage_ratio(&report_validation), Some(0.0));
+
+    let mut report = parse_fixture("test_fixtures/junit_durations_accurate.xml");
+    let report_validation = junit::validator::validate(&report);
+    assert_eq!(duration_variance_issues(&report_validation), vec![]);
+    assert_eq!(
+        duration_coverage_ratio(&report_validation),
+        Some(2370.0 / 2400.0)
+    );
+
+    // 90s of 600s is within the default factor, but not within a stricter one
+    report.test_suites[1].test_cases[0].time = Some(Duration::from_secs(90));
+    assert_eq!(
+        duration_variance_issues(&junit::validator::validate(&report)),
+        vec![]
+    );
+    assert_eq!(
+        duration_variance_issues(&validate_with_options(
+            &report,
+            &JunitValidationOptions {
+                duration_variance_factor: 5.0,
+                ..Default::default()
+            }
+        )),
+        vec![(
+            String::from("integration.LoginTest"),
+            90,
+            600,
+            WallClockSource::SuiteTime
+        )]
+    );
+
+    // Without a time attribute, only the timestamps of consecutive suites give wall clock time
+    let report_validation = junit::validator::validate(&parse_fixture(
+        "test_fixtures/junit_durations_missing_suite_time.xml",
+    ));
+    assert_eq!(
+        duration_variance_issues(&report_validation),
+        vec![(
+            String::from("integration.CheckoutTest"),
+            0,
+            1800,
+            WallClockSource::SuiteTimestamps
+        )]
+    );
+    assert_eq!(duration_coverage_ratio(&report_validation), Some(0.0));
+
+    let mut report = parse_fixture("test_fixtures/junit_durations_missing_suite_time.xml");
+    report.test_suites.remove(0);
+    let report_validation = junit::validator::validate(&report);
+    assert_eq!(duration_variance_issues(&report_validation), vec![]);
+    assert_eq!(report_validation.duration_coverage, None);
+}
+
+#[test]
+fn validate_schema_conformance_of_runner_fixtures() {
+    let parse_fixture = |fixture: &str| -> Report {
+        let file = fs::File::open(get_test_file_path(fixture)).unwrap();
+        parse_report_with_issues(BufReader::new(file), &[])
+    };
+    let schema_issues = |report: &Report, schema: JunitSchema| {
+        validate_with_options(
+            report,
+            &JunitValidationOptions {
+                schema,
+                ..Default::default()
+            },
+        )
+        .all_issues()
+        .iter()
+        .filter(|issue| issue.schema() != JunitSchema::Junit)
+        .map(|issue| (issue.schema(), issue.to_string()))
+        .collect::<Vec<_>>()
+    };
+
+    let ant_report = parse_fixture("test_fixtures/junit_schema_ant.xml");
+    let xunit2_report = parse_fixture("test_fixtures/junit_schema_xunit2.xml");
+    let surefire_report = parse_fixture("test_fixtures/junit_surefire_reruns.xml");
+
+    for report in [&ant_report, &xunit2_report, &surefire_report] {
+        assert_eq!(schema_issues(report, JunitSchema::Junit), vec![]);
+    }
+
+    assert_eq!(
+        schema_issues(&ant_report, JunitSchema::Xunit2),
+        vec![
+            (
+                JunitSchema::Xunit2,
+                String::from("report has 2 test cases without a file attribute")
+            ),
+            (
+                JunitSchema::Xunit2,
+                String::from("report has 2 test cases without a line attribute")
+            ),
+        ]
+    );
+    assert_eq!(schema_issues(&xunit2_report, JunitSchema::Xunit2), vec![]);
+    assert_eq!(
+        schema_issues(&surefire_report, JunitSchema::Xunit2),
+        vec![
+            (
+                JunitSchema::Xunit2,
+                String::from("report has 3 test cases without a file attribute")
+            ),
+            (
+                JunitSchema::Xunit2,
+                String::from("report has 3 test cases without a line attribute")
+            ),
+        ]
+    );
+
+    // Without reruns, there's nothing for the surefire rules to fail on
+    for report in [&ant_report, &xunit2_report, &surefire_report] {
+        assert_eq!(schema_issues(report, JunitSchema::Surefire), vec![]);
+    }
+    let broken_reruns_report = parse_report_with_issues(
+        r#"
+        <testsuite name="com.example.payments.PaymentGatewayIT" timestamp="2024-11-05T10:20:00">
+          <testcase name="chargesCard" classname="com.example.payments.PaymentGatewayIT" time="0.812">
+            <flakyFailure message="expected: APPROVED but was: PENDING" time="1.204"/>
+          </testcase>
+          <testcase name="refundsCharge" classname="com.example.payments.PaymentGatewayIT" time="0.153">
+            <failure message="expected: 204 but was: 409" type="org.opentest4j.AssertionFailedError"/>
+            <rerunFailure message="expected: 204 but was: 409" type="org.opentest4j.AssertionFailedError" time="0.147"/>
+          </testcase>
+        </testsuite>
+        "#
+        .as_bytes(),
+        &[],
+    );
+    let report_validation = validate_with_options(
+        &broken_reruns_report,
+        &JunitValidationOptions {
+            schema: JunitSchema::Surefire,
+            ..Default::default()
+        },
+    );
+    assert_eq!(report_validation.max_level(), JunitValidationLevel::Invalid);
+    assert_eq!(
+        schema_issues(&broken_reruns_report, JunitSchema::Surefire),
+        vec![
+            (
+                JunitSchema::Surefire,
+                String::from("report has 1 reruns without a type attribute")
+            ),
+            (
+                JunitSchema::Surefire,
+                String::from("report has 2 reruns without a stack trace")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn closest_schema_of_runner_fixtures() {
+    let parse_fixture = |fixture: &str| -> Report {
+        let file = fs::File::open(get_test_file_path(fixture)).unwrap();
+        parse_report_with_issues(BufReader::new(file), &[])
+    };
+    let closest = |fixture: &str, schema: JunitSchema| {
+        closest_schema(schema, &check_rules(&parse_fixture(fixture)))
+    };
+
+    // Surefire reruns that pass their rules beat plain junit
+    assert_eq!(
+        closest(
+            "test_fixtures/junit_surefire_reruns.xml",
+            JunitSchema::Xunit2
+        ),
+        Some(JunitSchema::Surefire)
+    );
+    // Nothing beats plain junit without reruns
+    assert_eq!(
+        closest("test_fixtures/junit_schema_ant.xml", JunitSchema::Xunit2),
+        Some(JunitSchema::Junit)
+    );
+    // Reports mostly conforming to the schema get no suggestion
+    for (fixture, schema) in [
+        ("test_fixtures/junit_schema_ant.xml", JunitSchema::Junit),
+        ("test_fixtures/junit_schema_ant.xml", JunitSchema::Surefire),
+        ("test_fixtures/junit_schema_xunit2.xml", JunitSchema::Xunit2),
+        (
+            "test_fixtures/junit_surefire_reruns.xml",
+            JunitSchema::Surefire,
+        ),
+    ] {
+        assert_eq!(closest(fixture, schema), None, "{fixture} as {schema}");
+    }
+    // One of the three xunit2 rules failing isn't mostly failing
+    let mut report = parse_fixture("test_fixtures/junit_schema_xunit2.xml");
+    report.test_suites[0].test_cases[0]
+        .extra
+        .shift_remove(extra_attrs::LINE);
+    assert_eq!(
+        closest_schema(JunitSchema::Xunit2, &check_rules(&report)),
+        None
+    );
+}
+
+#[test]
+fn infer_skip_kinds_of_test_frameworks() {
+    for (fixture, expected) in [
+        (
+            "test_fixtures/junit_skipped_junit5.xml",
+            vec![
+                (
+                    "void com.example.app.LoginTest.logsInWithSso() is @Disabled",
+                    SkipKind::Disabled,
+                ),
+                ("Test disabled via annotation", SkipKind::Disabled),
+                (
+                    "Assumption failed: assumption is not true",
+                    SkipKind::Assumption,
+                ),
+                ("Waiting on LDAP test server", SkipKind::Other),
+            ],
+        ),
+        (
+            "test_fixtures/junit_skipped_pytest.xml",
+            vec![
+                ("unconditional skip", SkipKind::Disabled),
+                (
+                    "could not import 'docker': No module named 'docker'",
+                    SkipKind::Assumption,
+                ),
+                ("requires windows", SkipKind::Other),
+                ("see issue 42", SkipKind::Other),
+            ],
+        ),
+        (
+            "test_fixtures/junit_skipped_xunit.xml",
+            vec![
+                ("Payments sandbox is down", SkipKind::Other),
+                ("Apple Pay requires macOS", SkipKind::Assumption),
+            ],
+        ),
+    ] {
+        let junit_xml = fs::read_to_string(get_test_file_path(fixture)).unwrap();
+        let report = parse_report_with_issues(junit_xml.as_bytes(), &[]);
+        let skipped: Vec<(String, SkipKind)> = report
+            .test_suites
+            .iter()
+            .flat_map(|test_suite| test_suite.test_cases.iter())
+            .map(|test_case| {
+                (
+                    extract_skip_reason(&test_case.status),
+                    infer_skip_kind(&test_case.status),
+                )
+            })
+            .collect();
+        let expected: Vec<(String, SkipKind)> = expected
+            .into_iter()
+            .map(|(reason, skip_kind)| (String::from(reason), skip_kind))
+            .collect();
+        assert_eq!(skipped, expected, "{fixture}");
+    }
+}
+
+#[test]
+fn parse_mapped_file_and_line_attrs() {
+    let junit_xml = fs::read(get_test_file_path(
+        "test_fixtures/junit_catch2_source_lineno.xml",
+    ))
+    .unwrap();
+    let test_case_attrs = |junit_parser: JunitParser| -> Vec<[Option<String>; 3]> {
+        let report = junit_parser.into_reports().pop().unwrap();
+        report.test_suites[0]
+            .test_cases
+            .iter()
+            .map(|test_case| {
+                [
+                    extra_attrs::FILE,
+                    extra_attrs::LINE,
+                    extra_attrs::MAPPED_ATTRS,
+                ]
+                .map(|key| test_case.extra.get(key).map(|value| value.to_string()))
+            })
+            .collect()
+    };
+
+    let mut junit_parser = JunitParser::new()
+        .with_attr_map(JunitAttrMap::parse(&["file=source", "line=lineno"]).unwrap());
+    junit_parser.parse(junit_xml.as_slice()).unwrap();
+    assert_eq!(junit_parser.issues(), &[]);
+    assert_eq!(
+        test_case_attrs(junit_parser),
+        [
+            [
+                Some("tests/vector_test.cpp"),
+                Some("12"),
+                Some("file=source,line=lineno"),
+            ],
+            [
+                Some("tests/vector_test.cpp"),
+                Some("27"),
+                Some("file=source,line=lineno"),
+            ],
+            // canonical attributes win
+            [Some("tests/matrix_test.cpp"), Some("8"), None],
+        ]
+        .map(|attrs| attrs.map(|attr| attr.map(String::from)))
+    );
+
+    let mut junit_parser = JunitParser::new();
+    junit_parser.parse(junit_xml.as_slice()).unwrap();
+    assert_eq!(
+        test_case_attrs(junit_parser),
+        [
+            [None, None, None],
+            [None, None, None],
+            [
+                Some(String::from("tests/matrix_test.cpp")),
+                Some(String::from("8")),
+                None,
+            ],
+        ]
+    );
+}
+
+#[test]
+fn junit_stats_of_mock_junit_files() {
+    let tempdir = TempDir::new().unwrap();
+    let mut paths = Vec::new();
+    let mut num_skipped = 0;
+    for (report_count, statuses) in [(2, vec![100, 0, 0, 0]), (1, vec![0, 100, 0, 0])] {
+        let mut options = new_mock_junit_options(report_count, Some(4), Some(5), false);
+        options
+            .test_case
+            .test_case_success_to_skip_to_fail_to_error_percentage = vec![statuses];
+        options.test_rerun.test_rerun_count_range = vec![0, 0];
+        let mut jm = JunitMock::new(options);
+        let reports = jm.generate_reports();
+        // a status with a 0% chance is still picked on the edge of the range before it
+        num_skipped += reports
+            .iter()
+            .flat_map(|report| &report.test_suites)
+            .flat_map(|test_suite| &test_suite.test_cases)
+            .filter(|test_case| matches!(test_case.status, TestCaseStatus::Skipped { .. }))
+            .count();
+        let report_dir = tempdir.path().join(paths.len().to_string());
+        fs::create_dir(&report_dir).unwrap();
+        paths.extend(jm.write_reports_to_file(&report_dir, reports).unwrap());
+    }
+    let unparsable_path = tempdir.path().join("unparsable.xml");
+    fs::write(&unparsable_path, "<testsuites><testsuite></testcase>").unwrap();
+    paths.push(unparsable_path);
+
+    let mut stats = junit::stats::JunitStats::default();
+    for path in &paths {
+        stats.add_file(
+            path.to_string_lossy(),
+            BufReader::new(fs::File::open(path).unwrap()),
+        );
+    }
+    stats.finish();
+
+    assert_eq!(stats.files.len(), 4);
+    assert_eq!(stats.num_unparsable_files, 1);
+    assert!(stats.files[3].error.is_some());
+    assert_eq!(stats.files[0].counts.num_test_cases, 20);
+    assert!(num_skipped > 0);
+    pretty_assertions::assert_eq!(
+        stats.totals,
+        junit::stats::JunitCounts {
+            num_reports: 3,
+            num_test_suites: 12,
+            num_test_cases: 60,
+            num_success: 60 - num_skipped,
+            num_failure: 0,
+            num_error: 0,
+            num_skipped,
+            num_with_file: 60,
+            num_with_timestamp: 60,
+            num_with_time: 60,
+            num_parse_issues_suboptimal: 0,
+            num_parse_issues_invalid: 0,
+        }
+    );
+    assert_eq!(stats.totals.file_coverage(), 100.0);
+    // skipped test cases take no time, and the others 1 to 2 seconds
+    let durations = stats.durations.unwrap();
+    assert!((1.0..=2.0).contains(&durations.p90));
+    assert!((1.0..=2.0).contains(&durations.p99));
+    assert!(durations.p50 <= durations.p90 && durations.p90 <= durations.p99);
+}
+
+#[test]
+fn junit_stats_duration_percentiles() {
+    let mut durations_secs: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+    let durations = junit::stats::DurationPercentiles::new(&mut durations_secs).unwrap();
+    assert_eq!(
+        (durations.p50, durations.p90, durations.p99),
+        (50.0, 90.0, 99.0)
+    );
+    assert_eq!(
+        junit::stats::DurationPercentiles::new(&mut [3.0]),
+        Some(junit::stats::DurationPercentiles {
+            p50: 3.0,
+            p90: 3.0,
+            p99: 3.0
+        })
+    );
+    assert_eq!(junit::stats::DurationPercentiles::new(&mut []), None);
+}
+
+#[test]
+fn parse_surefire_reruns() {
+    let file = fs::File::open(get_test_file_path(
+        "test_fixtures/junit_surefire_reruns.xml",
+    ))
+    .unwrap();
+    let report = parse_report_with_issues(BufReader::new(file), &[]);
+    let test_cases = &report.test_suites[0].test_cases;
+    // quick-junit strips control characters like tabs from its strings
+    let stack_trace_line = "\nat com.example.payments.";
+
+    let TestCaseStatus::Success { flaky_runs } = &test_cases[0].status else {
+        panic!(
+            "expected a success with flaky runs: {:?}",
+            test_cases[0].status
+        );
+    };
+    assert_eq!(flaky_runs.len(), 2);
+    let flaky_failure = &flaky_runs[0];
+    assert_eq!(flaky_failure.kind, NonSuccessKind::Failure);
+    assert_eq!(flaky_failure.time, Some(Duration::from_millis(1204)));
+    assert_eq!(
+        flaky_failure.message.as_ref().map(|m| m.as_str()),
+        Some("expected: <APPROVED> but was: <PENDING>")
+    );
+    assert_eq!(
+        flaky_failure.ty.as_ref().map(|t| t.as_str()),
+        Some("org.opentest4j.AssertionFailedError")
+    );
+    let stack_trace = flaky_failure.stack_trace.as_ref().unwrap().as_str();
+    assert!(stack_trace.starts_with("org.opentest4j.AssertionFailedError: expected"));
+    assert!(stack_trace.contains(stack_trace_line));
+    assert_eq!(
+        flaky_failure.system_out.as_ref().map(|s| s.as_str()),
+        Some("Charging card ending 4242\nGateway responded PENDING")
+    );
+    assert_eq!(
+        flaky_failure.system_err.as_ref().map(|s| s.as_str()),
+        Some("WARN gateway latency 1180ms")
+    );
+    let flaky_error = &flaky_runs[1];
+    assert_eq!(flaky_error.kind, NonSuccessKind::Error);
+    assert_eq!(flaky_error.time, Some(Duration::from_millis(2001)));
+    assert_eq!(
+        flaky_error.ty.as_ref().map(|t| t.as_str()),
+        Some("java.net.SocketTimeoutException")
+    );
+    assert_eq!(flaky_error.system_err, None);
+    // the output of the flaky runs is not the output of the test case
+    assert_eq!(test_cases[0].system_out, None);
+
+    let TestCaseStatus::NonSuccess {
+        kind,
+        message,
+        reruns,
+        ..
+    } = &test_cases[1].status
+    else {
+        panic!("expected a failure with reruns: {:?}", test_cases[1].status);
+    };
+    assert_eq!(*kind, NonSuccessKind::Failure);
+    assert_eq!(
+        message.as_ref().map(|m| m.as_str()),
+        Some("expected: <204> but was: <409>")
+    );
+    assert_eq!(
+        test_cases[1].system_out.as_ref().map(|s| s.as_str()),
+        Some("Refunding charge ch_3")
+    );
+    assert_eq!(reruns.len(), 2);
+    let rerun_failure = &reruns[0];
+    assert_eq!(rerun_failure.kind, NonSuccessKind::Failure);
+    assert_eq!(rerun_failure.time, Some(Duration::from_millis(147)));
+    assert_eq!(
+        rerun_failure.system_out.as_ref().map(|s| s.as_str()),
+        Some("Refunding charge ch_2")
+    );
+    assert!(rerun_failure
+        .stack_trace
+        .as_ref()
+        .unwrap()
+        .as_str()
+        .contains(stack_trace_line));
+    let rerun_error = &reruns[1];
+    assert_eq!(rerun_error.kind, NonSuccessKind::Error);
+    assert_eq!(rerun_error.time, Some(Duration::from_millis(51)));
+    assert_eq!(
+        rerun_error.message.as_ref().map(|m| m.as_str()),
+        Some("Connection reset")
+    );
+    assert_eq!(
+        rerun_error.ty.as_ref().map(|t| t.as_str()),
+        Some("java.net.SocketException")
+    );
+    assert_eq!(rerun_error.system_out, None);
+    assert_eq!(
+        rerun_error.system_err.as_ref().map(|s| s.as_str()),
+        Some("ERROR gateway connection reset by peer")
+    );
+
+    assert!(matches!(
+        &test_cases[2].status,
+        TestCaseStatus::Success { flaky_runs } if flaky_runs.is_empty()
+    ));
+}
+
+#[test]
+fn parse_reruns_before_status_and_split_rerun_output() {
+    let junit_xml = r#"
+    <testsuite name="suite">
+      <testcase name="case" classname="Case">
+        <rerunFailure message="first" type="AssertionError" time="0.5">
+          <system-out>rows=1&amp;2<![CDATA[<early exit>]]></system-out>
+        </rerunFailure>
+        <failure message="last" type="AssertionError"/>
+      </testcase>
+    </testsuite>
+    "#;
+    let report = parse_report_with_issues(BufReader::new(junit_xml.as_bytes()), &[]);
+
+    let TestCaseStatus::NonSuccess {
+        message, reruns, ..
+    } = &report.test_suites[0].test_cases[0].status
+    else {
+        panic!("expected a failure");
+    };
+    assert_eq!(message.as_ref().map(|m| m.as_str()), Some("last"));
+    assert_eq!(reruns.len(), 1);
+    assert_eq!(
+        reruns[0].message.as_ref().map(|m| m.as_str()),
+        Some("first")
+    );
+    assert_eq!(reruns[0].time, Some(Duration::from_millis(500)));
+    assert_eq!(
+        reruns[0].system_out.as_ref().map(|s| s.as_str()),
+        Some("rows=1&2<early exit>")
+    );
+}
+
+#[test]
+fn junit_timings_of_mock_junit_reports() {
+    let mut reports = Vec::new();
+    for (start, end) in [
+        (Duration::from_millis(10), Duration::from_millis(50)),
+        (Duration::from_millis(200), Duration::from_millis(800)),
+        (Duration::from_millis(1500), Duration::from_secs(3)),
+    ] {
+        let mut options = new_mock_junit_options(1, Some(2), Some(5), false);
+        options.test_case.test_case_duration_range = vec![start.into(), end.into()];
+        options
+            .test_case
+            .test_case_success_to_skip_to_fail_to_error_percentage = vec![vec![100, 0, 0, 0]];
+        options.test_rerun.test_rerun_count_range = vec![0, 0];
+        // durations are otherwise random
+        options.global.seed = Some(7);
+        let mut jm = JunitMock::new(options);
+        reports.extend(jm.generate_reports());
+    }
+    // longer than a mock suite can take, which is at most 5 test cases of 3 seconds
+    reports.push(parse_report(
+        r#"<testsuites>
+            <testsuite name="slow">
+                <testcase name="very_slow" time="60"/>
+                <testcase name="untimed"/>
+            </testsuite>
+        </testsuites>"#,
+    ));
+
+    let timings = junit::timings::JunitTimings::from_reports(&reports);
+
+    use junit::timings::DurationBucket;
+    let counts: Vec<_> = DurationBucket::ALL
+        .iter()
+        .map(|bucket| timings.count(*bucket))
+        .collect();
+    assert_eq!(counts, vec![10, 10, 10, 1, 1]);
+    assert_eq!(
+        timings.slowest_test_cases.len(),
+        junit::timings::NUM_SLOWEST_TEST_CASES
+    );
+    assert_eq!(timings.slowest_test_cases[0].name, "very_slow");
+    assert_eq!(timings.slowest_test_cases[0].test_suite, "slow");
+    assert_eq!(timings.slowest_test_cases[0].duration_secs, 60.0);
+    assert!(timings
+        .slowest_test_cases
+        .windows(2)
+        .all(|pair| pair[0].duration_secs >= pair[1].duration_secs));
+    assert!(timings.slowest_test_cases[1..]
+        .iter()
+        .all(|test_case| test_case.duration_secs >= 0.2));
+    assert_eq!(timings.slowest_test_suites.len(), 7);
+    assert_eq!(timings.slowest_test_suites[0].name, "slow");
+    assert_eq!(timings.slowest_test_suites[0].num_test_cases, 2);
+}
+
+fn parse_reports(xml: &[u8]) -> Vec<Report> {
+    let mut junit_parser = JunitParser::new();
+    junit_parser.parse(xml).unwrap();
+    junit_parser.into_reports()
+}
+
+/// Everything the parser reads of each test case, texts and reruns included, which sanitizing
+/// must not change but for the characters it removes, which `normalize` removes from both sides.
+/// Whether the parser sanitized a test case name is left out, as the sanitizer already did when the
+/// name had such characters.
+fn test_case_runs(reports: &[Report], normalize: fn(&str) -> String) -> Vec<String> {
+    let text = |value: Option<&str>| value.map(normalize);
+    let rerun = |rerun: &TestRerun| {
+        format!(
+            "{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+            rerun.kind,
+            rerun.timestamp,
+            rerun.time,
+            text(rerun.message.as_deref()),
+            text(rerun.ty.as_deref()),
+            text(rerun.stack_trace.as_deref()),
+            text(rerun.system_out.as_deref()),
+            text(rerun.system_err.as_deref()),
+            text(rerun.description.as_deref()),
+        )
+    };
+    let mut test_case_runs = Vec::new();
+    for test_suite in reports.iter().flat_map(|report| &report.test_suites) {
+        for test_case in &test_suite.test_cases {
+            let status = match &test_case.status {
+                TestCaseStatus::Success { flaky_runs } => format!(
+                    "success {:?}",
+                    flaky_runs.iter().map(rerun).collect::<Vec<_>>()
+                ),
+                TestCaseStatus::NonSuccess {
+                    kind,
+                    message,
+                    ty,
+                    description,
+                    reruns,
+                } => format!(
+                    "{:?} {:?} {:?} {:?} {:?}",
+                    kind,
+                    text(message.as_deref()),
+                    text(ty.as_deref()),
+                    text(description.as_deref()),
+                    reruns.iter().map(rerun).collect::<Vec<_>>()
+                ),
+                TestCaseStatus::Skipped {
+                    message,
+                    ty,
+                    description,
+                } => format!(
+                    "skipped {:?} {:?} {:?}",
+                    text(message.as_deref()),
+                    text(ty.as_deref()),
+                    text(description.as_deref())
+                ),
+            };
+            let extra: BTreeMap<_, _> = test_case
+                .extra
+                .iter()
+                .filter(|(key, _)| key.as_str() != extra_attrs::NAME_SANITIZED)
+                .map(|(key, value)| (key.as_str(), normalize(value)))
+                .collect();
+            test_case_runs.push(format!(
+                "{} {:?} {} {:?} {:?} {:?} {} {:?} {:?} {:?} {:?}",
+                normalize(&test_suite.name),
+                text(test_case.classname.as_deref()),
+                normalize(&test_case.name),
+                test_case.assertions,
+                test_case.timestamp,
+                test_case.time,
+                status,
+                text(test_case.system_out.as_deref()),
+                text(test_case.system_err.as_deref()),
+                extra,
+                test_case.properties,
+            ));
+        }
+    }
+    test_case_runs
+}
+
+/// The parser strips ANSI escape sequences whole and trims texts before that, while the sanitizer
+/// only removes their `ESC`, leaving the parameters of the color codes of junit_control_chars.xml
+fn without_color_codes(text: &str) -> String {
+    ["[0m", "[31m", "[32m", "[33m"]
+        .iter()
+        .fold(String::from(text), |text, color_code| {
+            text.replace(color_code, "")
+        })
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn sanitized_junit_parses_to_the_same_test_case_runs() {
+    let sanitize = |xml: &[u8]| {
+        let mut sanitized = Vec::new();
+        let sanitization = sanitize_junit_xml(xml, &mut sanitized, true).unwrap();
+        assert_eq!(sanitization.bytes_out, sanitized.len() as u64);
+        (sanitized, sanitization)
+    };
+
+    let (seed, reports) = generate_mock_junit_reports(3, Some(3), Some(10));
+    for report in &reports {
+        let xml = serialize_report(report);
+        let (sanitized, sanitization) = sanitize(&xml);
+        assert!(!sanitization.changed(), "seed {seed}");
+        assert_eq!(sanitized, xml, "seed {seed}");
+    }
+
+    for fixture in [
+        "test_fixtures/junit_utf8.xml",
+        "test_fixtures/junit_android_sharded.xml",
+        "test_fixtures/junit_surefire_nested.xml",
+        "test_fixtures/junit_surefire_reruns.xml",
+        "test_fixtures/junit_skipped_pytest.xml",
+        "test_fixtures/junit_time_whitespace.xml",
+    ] {
+        let xml = fs::read(get_test_file_path(fixture)).unwrap();
+        let (sanitized, sanitization) = sanitize(&xml);
+        assert!(!sanitization.changed(), "{fixture}");
+        assert_eq!(sanitized, xml, "{fixture}");
+    }
+
+    let utf8_xml =
+        parse_reports(&fs::read(get_test_file_path("test_fixtures/junit_utf8.xml")).unwrap())[0]
+            .to_string()
+            .unwrap();
+    for fixture in [
+        "test_fixtures/junit_utf16le.xml",
+        "test_fixtures/junit_windows1252.xml",
+    ] {
+        let xml = fs::read(get_test_file_path(fixture)).unwrap();
+        let (sanitized, sanitization) = sanitize(&xml);
+        assert!(sanitization.transcoded, "{fixture}");
+        assert!(String::from_utf8(sanitized.clone())
+            .unwrap()
+            .starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        let sanitized_reports = parse_reports(&sanitized);
+        assert_eq!(sanitized_reports[0].to_string().unwrap(), utf8_xml);
+        assert_eq!(
+            test_case_runs(&sanitized_reports, str::to_string),
+            test_case_runs(&parse_reports(&xml), str::to_string)
+        );
+    }
+
+    let xml = fs::read(get_test_file_path("test_fixtures/junit_control_chars.xml")).unwrap();
+    let (sanitized, sanitization) = sanitize(&xml);
+    assert_eq!(sanitization.num_control_chars_removed, 17);
+    assert!(sanitization.size_delta() < 0);
+    assert!(!sanitized
+        .iter()
+        .any(|b| b.is_ascii_control() && !b.is_ascii_whitespace()));
+    let sanitized_reports = parse_reports(&sanitized);
+    assert_eq!(
+        test_case_runs(&sanitized_reports, without_color_codes),
+        test_case_runs(&parse_reports(&xml), without_color_codes)
+    );
+    let test_cases = &sanitized_reports[0].test_suites[0].test_cases;
+    assert_eq!(test_cases[0].name.as_str(), "test_colored");
+    assert_eq!(test_cases[0].system_out.as_deref(), Some("[32mPASSED[0m"));
+    assert_eq!(test_cases[1].name.as_str(), "test_witha_nul");
+    assert_eq!(test_cases[1].system_err.as_deref(), Some("warning"));
+
+    let system_out = "x".repeat(3 * MAX_TEXT_FIELD_SIZE);
+    let xml = format!(
+        r#"<testsuites><testsuite name="suite"><testcase name="test" time="1.5"><failure message="{system_out}">{system_out}</failure><system-out>{system_out}</system-out></testcase></testsuite></testsuites>"#
+    );
+    let (sanitized, sanitization) = sanitize(xml.as_bytes());
+    assert_eq!(sanitization.num_texts_truncated, 3);
+    assert_eq!(sanitization.size_delta(), -6 * MAX_TEXT_FIELD_SIZE as i64);
+    let sanitized_reports = parse_reports(&sanitized);
+    let reports = parse_reports(xml.as_bytes());
+    assert_eq!(
+        test_case_runs(&sanitized_reports, str::to_string),
+        test_case_runs(&reports, str::to_string)
+    );
+    assert_eq!(
+        sanitized_reports[0].to_string().unwrap(),
+        reports[0].to_string().unwrap()
+    );
+}
+
+#[test]
+fn parse_quarantined_test_cases() {
+    let reports = parse_reports(
+        br#"<testsuites>
+            <testsuite name="suite">
+                <testcase name="marked" quarantined="true"><failure/></testcase>
+                <testcase name="marked_with_1" quarantined=" 1 "><failure/></testcase>
+                <testcase name="not_marked" quarantined="false"><failure/></testcase>
+                <testcase name="unmarked"><failure/></testcase>
+            </testsuite>
+        </testsuites>"#,
+    );
+    let quarantined: Vec<_> = reports[0].test_suites[0]
+        .test_cases
+        .iter()
+        .map(|test_case| {
+            (
+                test_case.name.as_str(),
+                test_case
+                    .extra
+                    .get(extra_attrs::QUARANTINED)
+                    .map(|q| q.as_str()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        quarantined,
+        [
+            ("marked", Some("true")),
+            ("marked_with_1", Some("true")),
+            ("not_marked", None),
+            ("unmarked", None),
+        ]
+    );
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn parse_large_file_memory_mapped_like_buffered() {
+    let test_case = r#"<testcase name="test_café_{i}" classname="a.b" file="src/a.rs" time="0.{i}"><failure message="expected 1 &gt; 2"><![CDATA[at src/a.rs:1]]></failure><system-out>out</system-out></testcase>"#;
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?><testsuites><testsuite name="large">"#,
+    );
+    let mut i = 0;
+    while xml.len() as u64 <= MMAP_MIN_FILE_SIZE {
+        xml.push_str(&test_case.replace("{i}", &i.to_string()));
+        i += 1;
+    }
+    xml.push_str("</testsuite></testsuites>");
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("junit.xml");
+    fs::write(&path, &xml).unwrap();
+
+    let file = fs::File::open(&path).unwrap();
+    assert!(junit::mmap::map_junit_file(&file).is_some());
+
+    let mut mmap_parser = JunitParser::new().with_mmap(true);
+    mmap_parser.parse_file(&path).unwrap();
+    let mut buffered_parser = JunitParser::new();
+    buffered_parser
+        .parse(BufReader::new(fs::File::open(&path).unwrap()))
+        .unwrap();
+    assert_eq!(mmap_parser.issues(), buffered_parser.issues());
+    assert_eq!(mmap_parser.reports()[0].tests, i);
+    assert_eq!(
+        format!("{:?}", mmap_parser.reports()),
+        format!("{:?}", buffered_parser.reports())
+    );
+
+    std::env::set_var(NO_MMAP_ENV_VAR, "1");
+    assert!(junit::mmap::map_junit_file(&file).is_none());
+    std::env::remove_var(NO_MMAP_ENV_VAR);
+
+    fs::write(&path, "<testsuites/>").unwrap();
+    assert!(junit::mmap::map_junit_file(&fs::File::open(&path).unwrap()).is_none());
+}
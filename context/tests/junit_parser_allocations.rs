@@ -0,0 +1,134 @@
+//! Counts what parsing a large junit file allocates. The counting allocator is global, so this
+//! is a test binary of its own with a single test.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use context::junit::parser::JunitParser;
+use quick_junit::{NonSuccessKind, Report, TestCase, TestCaseStatus, TestSuite};
+
+struct CountingAllocator;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static NUM_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static CURRENT_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+impl CountingAllocator {
+    fn allocated(size: usize) {
+        if !COUNTING.load(Ordering::Relaxed) {
+            return;
+        }
+        NUM_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        let current_bytes = CURRENT_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK_BYTES.fetch_max(current_bytes, Ordering::Relaxed);
+    }
+
+    fn deallocated(size: usize) {
+        if COUNTING.load(Ordering::Relaxed) {
+            // frees of what was allocated before counting started can't go below 0
+            let _ = CURRENT_BYTES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                Some(current.saturating_sub(size))
+            });
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::allocated(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::deallocated(layout.size());
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::deallocated(layout.size());
+        Self::allocated(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const NUM_TEST_SUITES: usize = 100;
+const NUM_TEST_CASES_PER_SUITE: usize = 1_000;
+const NUM_TEST_CASES_PER_CLASS: usize = 10;
+/// Parsing used to allocate 31 times per test case of [`large_report`], before strings shared by
+/// test cases were reused, so this allows for 30% less
+const MAX_ALLOCATIONS_PER_TEST_CASE: f64 = 21.7;
+
+/// Test suites of test cases that share their classname and file with the other test cases of
+/// their class, like most test runners write them
+fn large_report() -> Report {
+    let mut report = Report::new("large");
+    for suite_index in 0..NUM_TEST_SUITES {
+        let mut test_suite = TestSuite::new(format!("module{suite_index}"));
+        for test_case_index in 0..NUM_TEST_CASES_PER_SUITE {
+            let class_index = test_case_index / NUM_TEST_CASES_PER_CLASS;
+            let status = if test_case_index % 10 == 0 {
+                let mut status = TestCaseStatus::non_success(NonSuccessKind::Failure);
+                status.set_message(format!("expected {test_case_index} to be odd"));
+                status.set_type("AssertionError");
+                status.set_description(format!(
+                    "at com.example.module{suite_index}.Class{class_index}.test_{test_case_index}(Class{class_index}.java:{test_case_index})"
+                ));
+                status
+            } else {
+                TestCaseStatus::success()
+            };
+            let mut test_case = TestCase::new(format!("test_{test_case_index}"), status);
+            test_case.set_classname(format!(
+                "com.example.module{suite_index}.Class{class_index}"
+            ));
+            test_case.set_time(Duration::from_millis((test_case_index % 100) as u64));
+            test_case.extra.insert(
+                "file".into(),
+                format!("src/module{suite_index}/Class{class_index}.java").into(),
+            );
+            test_case
+                .extra
+                .insert("line".into(), test_case_index.to_string().into());
+            test_suite.add_test_case(test_case);
+        }
+        report.add_test_suite(test_suite);
+    }
+    report
+}
+
+#[test]
+fn parsing_large_junit_file_allocates_little_per_test_case() {
+    let report = large_report();
+    let xml = report.to_string().unwrap();
+    let num_test_cases = NUM_TEST_SUITES * NUM_TEST_CASES_PER_SUITE;
+
+    let mut junit_parser = JunitParser::new();
+    COUNTING.store(true, Ordering::SeqCst);
+    junit_parser.parse(xml.as_bytes()).unwrap();
+    COUNTING.store(false, Ordering::SeqCst);
+    let num_allocations = NUM_ALLOCATIONS.load(Ordering::SeqCst);
+    let peak_bytes = PEAK_BYTES.load(Ordering::SeqCst);
+    let allocations_per_test_case = num_allocations as f64 / num_test_cases as f64;
+    println!(
+        "{} allocations ({:.1} per test case), peak of {} bytes ({:.1} per test case)",
+        num_allocations,
+        allocations_per_test_case,
+        peak_bytes,
+        peak_bytes as f64 / num_test_cases as f64,
+    );
+    assert!(
+        allocations_per_test_case <= MAX_ALLOCATIONS_PER_TEST_CASE,
+        "{allocations_per_test_case:.1} allocations per test case"
+    );
+
+    let reports = junit_parser.into_reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].to_string().unwrap(), xml);
+}
@@ -92,6 +92,9 @@ fn valid_ci_info_and_bundle_repo() -> (CIInfo, BundleRepo) {
         repo_head_sha: String::from("abc"),
         repo_head_sha_short: Some(String::from("abc")),
         repo_head_commit_epoch: 123,
+        dirty: None,
+        num_modified_files: None,
+        git_remote_url: None,
     };
 
     (ci_info, bundle_repo)
@@ -0,0 +1,782 @@
+//! Finds the external ID of the GitHub Actions job the CLI runs in, i.e. the ID GitHub gives the
+//! job itself rather than its workflow run, so that uploads can be linked to their job.
+//!
+//! The ID is scraped from the logs of the runner's worker process first, and looked up with the
+//! Actions API if those can't be read, e.g. in containers or on self-hosted runners. The
+//! environment, processes and filesystem are all read through [`GithubExternalIdExtractor`]'s
+//! sources, so that each runner layout can be tested with fakes.
+//!
+//! [`pr_comment`] comments on the pull request of a run, and [`oidc`] requests the OIDC token of
+//! the job.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use constants::TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV;
+use serde::{Deserialize, Serialize};
+use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
+
+pub mod oidc;
+pub mod pr_comment;
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+pub const DEFAULT_API_TIMEOUT: Duration = Duration::from_secs(10);
+/// The most jobs GitHub returns in one page, which covers all but the largest matrices
+const JOBS_PER_PAGE: usize = 100;
+const WORKER_PROCESS_NAME: &str = "Runner.Worker";
+const DIAG_DIR_NAME: &str = "_diag";
+const WORKER_LOG_PREFIX: &str = "Worker_";
+const WORKER_LOG_EXTENSION: &str = ".log";
+/// The job's ID is its check run ID, which the worker logs as part of the job's context
+const CHECK_RUN_ID_KEY: &str = r#""k": "check_run_id""#;
+const VALUE_KEY: &str = r#""v":"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalIdOptions {
+    /// Whether to call the GitHub REST API, which is opt-in since it uses the network
+    pub use_actions_api: bool,
+    pub api_timeout: Duration,
+}
+
+impl Default for ExternalIdOptions {
+    fn default() -> Self {
+        Self {
+            use_actions_api: false,
+            api_timeout: DEFAULT_API_TIMEOUT,
+        }
+    }
+}
+
+impl ExternalIdOptions {
+    /// Uses the Actions API if `TRUNK_GITHUB_ACTIONS_API_LOOKUP` is `true` or `1`.
+    pub fn from_env() -> Self {
+        Self::from_env_lookup(&SystemEnv)
+    }
+
+    pub fn from_env_lookup<E: EnvLookup>(env: &E) -> Self {
+        let use_actions_api = env
+            .var(TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV)
+            .map(|value| matches!(value.trim().to_lowercase().as_str(), "true" | "1"))
+            .unwrap_or(false);
+        Self {
+            use_actions_api,
+            ..Default::default()
+        }
+    }
+}
+
+pub trait EnvLookup {
+    fn var(&self, key: &str) -> Option<String>;
+}
+
+pub trait ProcessLister {
+    /// The command line of each running process, starting with the program
+    fn process_cmdlines(&self) -> Vec<Vec<String>>;
+}
+
+pub trait DirReader {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEnv;
+
+impl EnvLookup for SystemEnv {
+    fn var(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+    }
+}
+
+/// Only refreshes the command lines of processes, since enumerating everything else about every
+/// process is slow.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemProcesses;
+
+impl ProcessLister for SystemProcesses {
+    fn process_cmdlines(&self) -> Vec<Vec<String>> {
+        let system = System::new_with_specifics(
+            RefreshKind::new()
+                .with_processes(ProcessRefreshKind::new().with_cmd(UpdateKind::OnlyIfNotSet)),
+        );
+        system
+            .processes()
+            .values()
+            .map(|process| process.cmd().to_vec())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemDirs;
+
+impl DirReader for SystemDirs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GithubExternalIdExtractor<E = SystemEnv, P = SystemProcesses, D = SystemDirs> {
+    env: E,
+    processes: P,
+    dirs: D,
+    options: ExternalIdOptions,
+}
+
+impl GithubExternalIdExtractor {
+    pub fn new(options: ExternalIdOptions) -> Self {
+        Self::with_sources(SystemEnv, SystemProcesses, SystemDirs, options)
+    }
+}
+
+impl<E: EnvLookup, P: ProcessLister, D: DirReader> GithubExternalIdExtractor<E, P, D> {
+    pub fn with_sources(env: E, processes: P, dirs: D, options: ExternalIdOptions) -> Self {
+        Self {
+            env,
+            processes,
+            dirs,
+            options,
+        }
+    }
+
+    /// Returns `None` outside of GitHub Actions, or if the external ID could not be found. Why it
+    /// could not be found is only logged at debug level, since the upload doesn't depend on it.
+    pub async fn extract(&self) -> Option<String> {
+        if self.env.var("GITHUB_ACTIONS").as_deref() != Some("true") {
+            return None;
+        }
+
+        if let Some(external_id) = self.extract_from_worker_logs() {
+            log::debug!("Found GitHub job ID {} in the worker logs", external_id);
+            return Some(external_id);
+        }
+        self.extract_from_actions_api().await
+    }
+
+    fn extract_from_worker_logs(&self) -> Option<String> {
+        let diag_dirs = self.diag_dirs();
+        if diag_dirs.is_empty() {
+            log::debug!(
+                "No {} process found to read the worker logs of",
+                WORKER_PROCESS_NAME
+            );
+            return None;
+        }
+
+        for diag_dir in diag_dirs {
+            let mut worker_logs: Vec<PathBuf> = match self.dirs.read_dir(&diag_dir) {
+                Ok(paths) => paths
+                    .into_iter()
+                    .filter(|path| is_worker_log(path))
+                    .collect(),
+                Err(e) => {
+                    log::debug!("Failed to read the worker logs in {:?}: {}", diag_dir, e);
+                    continue;
+                }
+            };
+            // named after when they were started, so the current job's log sorts last
+            worker_logs.sort();
+            for worker_log in worker_logs.iter().rev() {
+                match self.dirs.read_to_string(worker_log) {
+                    Ok(contents) => {
+                        if let Some(check_run_id) = parse_check_run_id(&contents) {
+                            return Some(check_run_id);
+                        }
+                    }
+                    Err(e) => log::debug!("Failed to read worker log {:?}: {}", worker_log, e),
+                }
+            }
+        }
+        log::debug!("No GitHub job ID found in the worker logs");
+        None
+    }
+
+    /// The worker runs from the runner's `bin` directory, next to which it logs to `_diag`.
+    fn diag_dirs(&self) -> Vec<PathBuf> {
+        let mut diag_dirs: Vec<PathBuf> = self
+            .processes
+            .process_cmdlines()
+            .into_iter()
+            .filter_map(|cmdline| cmdline.into_iter().next())
+            .map(PathBuf::from)
+            .filter(|program| {
+                program
+                    .file_name()
+                    .and_then(|file_name| file_name.to_str())
+                    .is_some_and(|file_name| {
+                        file_name.trim_end_matches(".exe") == WORKER_PROCESS_NAME
+                    })
+            })
+            .filter_map(|program| {
+                program
+                    .parent()
+                    .and_then(Path::parent)
+                    .map(|runner_dir| runner_dir.join(DIAG_DIR_NAME))
+            })
+            .collect();
+        diag_dirs.sort();
+        diag_dirs.dedup();
+        diag_dirs
+    }
+
+    async fn extract_from_actions_api(&self) -> Option<String> {
+        if !self.options.use_actions_api {
+            log::debug!(
+                "Not looking up the GitHub job ID with the Actions API, set {}=true to enable it",
+                TRUNK_GITHUB_ACTIONS_API_LOOKUP_ENV
+            );
+            return None;
+        }
+
+        let job_lookup = match JobLookup::from_env_lookup(&self.env) {
+            Ok(job_lookup) => job_lookup,
+            Err(missing_env_vars) => {
+                log::debug!(
+                    "Not looking up the GitHub job ID with the Actions API, missing {}",
+                    missing_env_vars.join(", ")
+                );
+                return None;
+            }
+        };
+        match job_lookup.fetch_job_id(self.options.api_timeout).await {
+            Ok(Some(job_id)) => {
+                log::debug!("Found GitHub job ID {} with the Actions API", job_id);
+                Some(job_id.to_string())
+            }
+            Ok(None) => {
+                log::debug!(
+                    "No single job named {} found with the Actions API",
+                    job_lookup.job
+                );
+                None
+            }
+            Err(e) => {
+                log::debug!(
+                    "Failed to look up the GitHub job ID with the Actions API: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Reads the real environment, processes and filesystem. See [`GithubExternalIdExtractor`].
+pub async fn extract_github_external_id(options: &ExternalIdOptions) -> Option<String> {
+    GithubExternalIdExtractor::new(options.clone())
+        .extract()
+        .await
+}
+
+fn is_worker_log(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .is_some_and(|file_name| {
+            file_name.starts_with(WORKER_LOG_PREFIX) && file_name.ends_with(WORKER_LOG_EXTENSION)
+        })
+}
+
+/// The worker logs the job's context as JSON, where numbers are floats, e.g.
+/// `{ "k": "check_run_id", "v": 12345678.0 }`, spread over multiple lines.
+fn parse_check_run_id(worker_log: &str) -> Option<String> {
+    let (_, after_key) = worker_log.rsplit_once(CHECK_RUN_ID_KEY)?;
+    let (_, after_value_key) = after_key.split_once(VALUE_KEY)?;
+    let value: String = after_value_key
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let check_run_id = value.parse::<f64>().ok()?;
+    if check_run_id < 1.0 || check_run_id.fract() != 0.0 {
+        return None;
+    }
+    Some((check_run_id as u64).to_string())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListJobsResponse {
+    pub total_count: usize,
+    pub jobs: Vec<Job>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub name: String,
+    pub status: String,
+    pub runner_name: Option<String>,
+}
+
+/// Everything needed to find a job of a workflow run with the Actions API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JobLookup {
+    pub api_url: String,
+    pub token: String,
+    /// `owner/name`
+    pub repository: String,
+    pub run_id: String,
+    /// The job's key in the workflow, which is also its name unless it sets one
+    pub job: String,
+    pub runner_name: Option<String>,
+}
+
+impl JobLookup {
+    /// Errors with the names of the missing env vars.
+    pub fn from_env() -> Result<Self, Vec<&'static str>> {
+        Self::from_env_lookup(&SystemEnv)
+    }
+
+    pub fn from_env_lookup<E: EnvLookup>(env: &E) -> Result<Self, Vec<&'static str>> {
+        let token = env
+            .var("GITHUB_TOKEN")
+            .or_else(|| env.var("ACTIONS_RUNTIME_TOKEN"));
+        let repository = env.var("GITHUB_REPOSITORY");
+        let run_id = env.var("GITHUB_RUN_ID");
+        let job = env.var("GITHUB_JOB");
+
+        match (token, repository, run_id, job) {
+            (Some(token), Some(repository), Some(run_id), Some(job)) => Ok(Self {
+                api_url: env
+                    .var("GITHUB_API_URL")
+                    .unwrap_or(String::from(DEFAULT_API_URL)),
+                token,
+                repository,
+                run_id,
+                job,
+                runner_name: env.var("RUNNER_NAME"),
+            }),
+            (token, repository, run_id, job) => Err([
+                (token.is_none(), "GITHUB_TOKEN or ACTIONS_RUNTIME_TOKEN"),
+                (repository.is_none(), "GITHUB_REPOSITORY"),
+                (run_id.is_none(), "GITHUB_RUN_ID"),
+                (job.is_none(), "GITHUB_JOB"),
+            ]
+            .into_iter()
+            .filter(|(is_missing, _)| *is_missing)
+            .map(|(_, env_var)| env_var)
+            .collect()),
+        }
+    }
+
+    pub async fn fetch_job_id(&self, timeout: Duration) -> anyhow::Result<Option<u64>> {
+        let url = format!(
+            "{}/repos/{}/actions/runs/{}/jobs",
+            self.api_url.trim_end_matches('/'),
+            self.repository,
+            self.run_id
+        );
+        log::debug!("Listing the jobs of the workflow run at {}", url);
+        let list_jobs_response = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()?
+            .get(url)
+            .query(&[
+                ("filter", String::from("latest")),
+                ("per_page", JOBS_PER_PAGE.to_string()),
+            ])
+            .bearer_auth(&self.token)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header(reqwest::header::USER_AGENT, "trunk-analytics-cli")
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ListJobsResponse>()
+            .await?;
+
+        Ok(find_job_id(
+            &list_jobs_response.jobs,
+            &self.job,
+            self.runner_name.as_deref(),
+        ))
+    }
+}
+
+/// Finds the one job named after `job`.
+///
+/// Matrix jobs are named after their key with the matrix values as a suffix, e.g. `test (ubuntu,
+/// 18)`, so all of them match `job`. Those are told apart by the runner they run on, and then by
+/// whether they are still in progress.
+pub fn find_job_id(jobs: &[Job], job: &str, runner_name: Option<&str>) -> Option<u64> {
+    let mut candidates: Vec<&Job> = jobs
+        .iter()
+        .filter(|candidate| job_name_matches(&candidate.name, job))
+        .collect();
+
+    if let Some(runner_name) = runner_name {
+        let on_runner: Vec<&Job> = candidates
+            .iter()
+            .copied()
+            .filter(|candidate| candidate.runner_name.as_deref() == Some(runner_name))
+            .collect();
+        if !on_runner.is_empty() {
+            candidates = on_runner;
+        }
+    }
+
+    if candidates.len() > 1 {
+        candidates.retain(|candidate| candidate.status == "in_progress");
+    }
+
+    match candidates.as_slice() {
+        [candidate] => Some(candidate.id),
+        _ => None,
+    }
+}
+
+fn job_name_matches(name: &str, job: &str) -> bool {
+    match name.strip_prefix(job) {
+        Some("") => true,
+        Some(matrix_suffix) => matrix_suffix.starts_with(" (") && matrix_suffix.ends_with(')'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, collections::HashMap};
+
+    use axum::{extract::State, http::Uri, Json};
+    use test_utils::mock_server::{MockServerBuilder, RequestPayload, SharedMockServerState};
+
+    use super::*;
+
+    struct FakeEnv(HashMap<&'static str, String>);
+
+    impl EnvLookup for FakeEnv {
+        fn var(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+    }
+
+    fn github_actions_env() -> FakeEnv {
+        FakeEnv(HashMap::from([("GITHUB_ACTIONS", String::from("true"))]))
+    }
+
+    #[derive(Default)]
+    struct FakeProcesses {
+        cmdlines: Vec<Vec<String>>,
+        listed: Cell<bool>,
+    }
+
+    impl FakeProcesses {
+        fn new(cmdlines: &[&[&str]]) -> Self {
+            Self {
+                cmdlines: cmdlines
+                    .iter()
+                    .map(|cmdline| cmdline.iter().map(|arg| String::from(*arg)).collect())
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl ProcessLister for FakeProcesses {
+        fn process_cmdlines(&self) -> Vec<Vec<String>> {
+            self.listed.set(true);
+            self.cmdlines.clone()
+        }
+    }
+
+    /// Files by path, where a `None` file can't be read
+    #[derive(Default)]
+    struct FakeDirs(HashMap<PathBuf, Option<String>>);
+
+    impl FakeDirs {
+        fn new(files: &[(&str, Option<&str>)]) -> Self {
+            Self(
+                files
+                    .iter()
+                    .map(|(path, contents)| (PathBuf::from(path), contents.map(String::from)))
+                    .collect(),
+            )
+        }
+    }
+
+    impl DirReader for FakeDirs {
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            let paths: Vec<PathBuf> = self
+                .0
+                .keys()
+                .filter(|file| file.parent() == Some(path))
+                .cloned()
+                .collect();
+            if paths.is_empty() {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+            Ok(paths)
+        }
+
+        fn read_to_string(&self, path: &Path) -> io::Result<String> {
+            match self.0.get(path) {
+                Some(Some(contents)) => Ok(contents.clone()),
+                Some(None) => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        }
+    }
+
+    fn worker_log(check_run_id: &str) -> String {
+        format!(
+            r#"[2024-11-20 10:00:00Z INFO Worker] Job message:
+{{
+  "contextData": {{
+    "job": {{
+      "d": [
+        {{
+          "k": "check_run_id",
+          "v": {}
+        }},
+        {{
+          "k": "status",
+          "v": "success"
+        }}
+      ]
+    }}
+  }}
+}}"#,
+            check_run_id
+        )
+    }
+
+    #[test]
+    fn parses_check_run_id_from_worker_log() {
+        assert_eq!(
+            parse_check_run_id(&worker_log("33297458315.0")),
+            Some(String::from("33297458315"))
+        );
+        assert_eq!(
+            parse_check_run_id(&format!("{}{}", worker_log("1"), worker_log("2"))),
+            Some(String::from("2"))
+        );
+        assert_eq!(parse_check_run_id(&worker_log("\"null\"")), None);
+        assert_eq!(parse_check_run_id(&worker_log("1.5")), None);
+        assert_eq!(parse_check_run_id("no job message"), None);
+    }
+
+    #[tokio::test]
+    async fn skips_everything_outside_of_github_actions() {
+        let processes = FakeProcesses::new(&[&["/home/runner/runners/2.321.0/bin/Runner.Worker"]]);
+        let extractor = GithubExternalIdExtractor::with_sources(
+            FakeEnv(HashMap::new()),
+            processes,
+            FakeDirs::default(),
+            ExternalIdOptions::default(),
+        );
+        assert_eq!(extractor.extract().await, None);
+        assert!(!extractor.processes.listed.get());
+    }
+
+    #[tokio::test]
+    async fn extracts_from_hosted_runner_worker_logs() {
+        let log = worker_log("33297458315.0");
+        let extractor = GithubExternalIdExtractor::with_sources(
+            github_actions_env(),
+            FakeProcesses::new(&[
+                &["/home/runner/runners/2.321.0/bin/Runner.Listener", "run"],
+                &[
+                    "/home/runner/runners/2.321.0/bin/Runner.Worker",
+                    "spawnclient",
+                    "150",
+                    "153",
+                ],
+            ]),
+            FakeDirs::new(&[
+                (
+                    "/home/runner/runners/2.321.0/_diag/Worker_20241120-095500-utc.log",
+                    Some(&worker_log("11")),
+                ),
+                (
+                    "/home/runner/runners/2.321.0/_diag/Worker_20241120-100000-utc.log",
+                    Some(&log),
+                ),
+                (
+                    "/home/runner/runners/2.321.0/_diag/Runner_20241120-095000-utc.log",
+                    Some(&worker_log("22")),
+                ),
+            ]),
+            ExternalIdOptions::default(),
+        );
+        assert_eq!(extractor.extract().await, Some(String::from("33297458315")));
+    }
+
+    #[tokio::test]
+    async fn extracts_nothing_in_container_without_worker_process() {
+        let extractor = GithubExternalIdExtractor::with_sources(
+            github_actions_env(),
+            FakeProcesses::new(&[&["/bin/sh", "-e", "/__w/_temp/script.sh"], &[]]),
+            FakeDirs::new(&[(
+                "/home/runner/runners/2.321.0/_diag/Worker_20241120-100000-utc.log",
+                Some(&worker_log("11")),
+            )]),
+            ExternalIdOptions::default(),
+        );
+        assert_eq!(extractor.extract().await, None);
+        assert!(extractor.processes.listed.get());
+    }
+
+    #[tokio::test]
+    async fn skips_worker_logs_without_permission() {
+        let extractor = GithubExternalIdExtractor::with_sources(
+            github_actions_env(),
+            FakeProcesses::new(&[&["/opt/actions-runner/bin/Runner.Worker"]]),
+            FakeDirs::new(&[
+                (
+                    "/opt/actions-runner/_diag/Worker_20241120-095500-utc.log",
+                    Some(&worker_log("11")),
+                ),
+                (
+                    "/opt/actions-runner/_diag/Worker_20241120-100000-utc.log",
+                    None,
+                ),
+            ]),
+            ExternalIdOptions::default(),
+        );
+        assert_eq!(extractor.extract().await, Some(String::from("11")));
+
+        let extractor = GithubExternalIdExtractor::with_sources(
+            github_actions_env(),
+            FakeProcesses::new(&[&["/opt/actions-runner/bin/Runner.Worker"]]),
+            FakeDirs::new(&[(
+                "/opt/actions-runner/_diag/Worker_20241120-100000-utc.log",
+                None,
+            )]),
+            ExternalIdOptions::default(),
+        );
+        assert_eq!(extractor.extract().await, None);
+    }
+
+    fn job(id: u64, name: &str, status: &str, runner_name: Option<&str>) -> Job {
+        Job {
+            id,
+            name: String::from(name),
+            status: String::from(status),
+            runner_name: runner_name.map(String::from),
+        }
+    }
+
+    #[test]
+    fn finds_job_by_name() {
+        let jobs = [
+            job(1, "lint", "completed", Some("runner-1")),
+            job(2, "test", "in_progress", Some("runner-2")),
+            job(3, "test-e2e", "in_progress", Some("runner-3")),
+        ];
+        assert_eq!(find_job_id(&jobs, "test", None), Some(2));
+        assert_eq!(find_job_id(&jobs, "test", Some("runner-9")), Some(2));
+        assert_eq!(find_job_id(&jobs, "build", None), None);
+    }
+
+    #[test]
+    fn finds_matrix_job_by_runner_then_status() {
+        let jobs = [
+            job(
+                1,
+                "test (ubuntu-latest, 18)",
+                "in_progress",
+                Some("runner-1"),
+            ),
+            job(
+                2,
+                "test (ubuntu-latest, 20)",
+                "in_progress",
+                Some("runner-2"),
+            ),
+            job(3, "test (macos-latest, 20)", "queued", None),
+            job(
+                4,
+                "tests (ubuntu-latest, 20)",
+                "in_progress",
+                Some("runner-4"),
+            ),
+        ];
+        assert_eq!(find_job_id(&jobs, "test", Some("runner-2")), Some(2));
+        // ambiguous between both in progress matrix jobs
+        assert_eq!(find_job_id(&jobs, "test", None), None);
+        assert_eq!(find_job_id(&jobs[1..], "test", None), Some(2));
+    }
+
+    #[test]
+    fn matches_matrix_suffixes_only() {
+        assert!(job_name_matches("test", "test"));
+        assert!(job_name_matches("test (18)", "test"));
+        assert!(!job_name_matches("test-e2e", "test"));
+        assert!(!job_name_matches("test (18", "test"));
+        assert!(!job_name_matches("tes", "test"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_job_id_from_actions_api() {
+        let mut mock_server_builder = MockServerBuilder::new();
+        mock_server_builder.set_github_list_jobs_handler(
+            |State(state): State<SharedMockServerState>, uri: Uri| async move {
+                state
+                    .requests
+                    .lock()
+                    .unwrap()
+                    .push(RequestPayload::GitHubListJobs(String::from(uri.path())));
+                Json(ListJobsResponse {
+                    total_count: 2,
+                    jobs: vec![
+                        job(11, "lint", "completed", Some("runner-1")),
+                        job(12, "test (20)", "in_progress", Some("runner-2")),
+                    ],
+                })
+            },
+        );
+        let state = mock_server_builder.spawn_mock_server().await;
+
+        let job_lookup = JobLookup {
+            api_url: state.host.clone(),
+            token: String::from("github-token"),
+            repository: String::from("trunk-io/analytics-cli"),
+            run_id: String::from("42"),
+            job: String::from("test"),
+            runner_name: Some(String::from("runner-2")),
+        };
+        assert_eq!(
+            job_lookup.fetch_job_id(DEFAULT_API_TIMEOUT).await.unwrap(),
+            Some(12)
+        );
+        assert_eq!(
+            state.requests.lock().unwrap().as_slice(),
+            [RequestPayload::GitHubListJobs(String::from(
+                "/repos/trunk-io/analytics-cli/actions/runs/42/jobs"
+            ))]
+        );
+
+        let extractor = GithubExternalIdExtractor::with_sources(
+            FakeEnv(HashMap::from([
+                ("GITHUB_ACTIONS", String::from("true")),
+                ("GITHUB_API_URL", state.host.clone()),
+                ("GITHUB_TOKEN", String::from("github-token")),
+                ("GITHUB_REPOSITORY", String::from("trunk-io/analytics-cli")),
+                ("GITHUB_RUN_ID", String::from("42")),
+                ("GITHUB_JOB", String::from("test")),
+                ("RUNNER_NAME", String::from("runner-2")),
+            ])),
+            FakeProcesses::default(),
+            FakeDirs::default(),
+            ExternalIdOptions {
+                use_actions_api: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(extractor.extract().await, Some(String::from("12")));
+
+        let not_found = JobLookup {
+            api_url: format!("{}/not-found", state.host),
+            ..job_lookup
+        };
+        assert!(not_found.fetch_job_id(DEFAULT_API_TIMEOUT).await.is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Requests the OIDC token GitHub Actions issues to a job, so that the CLI can authenticate as the
+//! repository the job runs for instead of with a long-lived token.
+//!
+//! GitHub only exposes the request URL and token to jobs with the `id-token: write` permission.
+
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{EnvLookup, SystemEnv};
+
+/// The audience Trunk expects in the OIDC tokens it exchanges
+pub const TRUNK_OIDC_AUDIENCE: &str = "trunk.io";
+pub const ID_TOKEN_REQUEST_URL_ENV: &str = "ACTIONS_ID_TOKEN_REQUEST_URL";
+pub const ID_TOKEN_REQUEST_TOKEN_ENV: &str = "ACTIONS_ID_TOKEN_REQUEST_TOKEN";
+
+#[derive(Debug)]
+pub enum IdTokenError {
+    NotInGitHubActions,
+    /// The job lacks the `id-token: write` permission, so the request env vars are missing
+    PermissionMissing,
+    RequestFailed(anyhow::Error),
+}
+
+impl fmt::Display for IdTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInGitHubActions => write!(
+                f,
+                "OIDC authentication is only supported in GitHub Actions, and GITHUB_ACTIONS is not set"
+            ),
+            Self::PermissionMissing => write!(
+                f,
+                "GitHub Actions did not provide {} and {}, give the job the `id-token: write` permission to authenticate with OIDC",
+                ID_TOKEN_REQUEST_URL_ENV, ID_TOKEN_REQUEST_TOKEN_ENV
+            ),
+            Self::RequestFailed(e) => {
+                write!(f, "Failed to request an OIDC token from GitHub Actions: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IdTokenError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdTokenResponse {
+    pub value: String,
+}
+
+/// Everything needed to request the OIDC token of the job.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdTokenRequest {
+    pub url: String,
+    pub token: String,
+}
+
+impl IdTokenRequest {
+    pub fn from_env() -> Result<Self, IdTokenError> {
+        Self::from_env_lookup(&SystemEnv)
+    }
+
+    pub fn from_env_lookup<E: EnvLookup>(env: &E) -> Result<Self, IdTokenError> {
+        if env.var("GITHUB_ACTIONS").as_deref() != Some("true") {
+            return Err(IdTokenError::NotInGitHubActions);
+        }
+        match (
+            env.var(ID_TOKEN_REQUEST_URL_ENV),
+            env.var(ID_TOKEN_REQUEST_TOKEN_ENV),
+        ) {
+            (Some(url), Some(token)) if !url.is_empty() && !token.is_empty() => {
+                Ok(Self { url, token })
+            }
+            _ => Err(IdTokenError::PermissionMissing),
+        }
+    }
+
+    /// The request URL already has a query, to which `audience` is added.
+    pub async fn fetch_id_token(
+        &self,
+        audience: &str,
+        timeout: Duration,
+    ) -> Result<String, IdTokenError> {
+        log::debug!("Requesting an OIDC token for audience {}", audience);
+        let fetch = async {
+            let id_token_response = reqwest::Client::builder()
+                .timeout(timeout)
+                .build()?
+                .get(&self.url)
+                .query(&[("audience", audience)])
+                .bearer_auth(&self.token)
+                .header(reqwest::header::ACCEPT, "application/json")
+                .header(reqwest::header::USER_AGENT, "trunk-analytics-cli")
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<IdTokenResponse>()
+                .await?;
+            anyhow::Ok(id_token_response.value)
+        };
+        match fetch.await {
+            Ok(id_token) if !id_token.is_empty() => Ok(id_token),
+            Ok(_) => Err(IdTokenError::RequestFailed(anyhow::anyhow!(
+                "the response has no token"
+            ))),
+            Err(e) => Err(IdTokenError::RequestFailed(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use test_utils::mock_server::{
+        MockServerBuilder, RequestPayload, GITHUB_ID_TOKEN_PATH, MOCK_GITHUB_OIDC_TOKEN,
+    };
+
+    use super::*;
+
+    struct FakeEnv(HashMap<&'static str, &'static str>);
+
+    impl EnvLookup for FakeEnv {
+        fn var(&self, key: &str) -> Option<String> {
+            self.0.get(key).map(|value| String::from(*value))
+        }
+    }
+
+    #[test]
+    fn tells_apart_missing_permission_from_other_ci() {
+        assert!(matches!(
+            IdTokenRequest::from_env_lookup(&FakeEnv(HashMap::new())),
+            Err(IdTokenError::NotInGitHubActions)
+        ));
+        assert!(matches!(
+            IdTokenRequest::from_env_lookup(&FakeEnv(HashMap::from([
+                ("GITHUB_ACTIONS", "true"),
+                (ID_TOKEN_REQUEST_TOKEN_ENV, "request-token"),
+            ]))),
+            Err(IdTokenError::PermissionMissing)
+        ));
+        assert_eq!(
+            IdTokenRequest::from_env_lookup(&FakeEnv(HashMap::from([
+                ("GITHUB_ACTIONS", "true"),
+                (
+                    ID_TOKEN_REQUEST_URL_ENV,
+                    "https://example.com/idtoken?api-version=2.0"
+                ),
+                (ID_TOKEN_REQUEST_TOKEN_ENV, "request-token"),
+            ])))
+            .unwrap(),
+            IdTokenRequest {
+                url: String::from("https://example.com/idtoken?api-version=2.0"),
+                token: String::from("request-token"),
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches_id_token_for_audience() {
+        let state = MockServerBuilder::new().spawn_mock_server().await;
+
+        let id_token_request = IdTokenRequest {
+            url: format!("{}{}?api-version=2.0", state.host, GITHUB_ID_TOKEN_PATH),
+            token: String::from("request-token"),
+        };
+        assert_eq!(
+            id_token_request
+                .fetch_id_token(TRUNK_OIDC_AUDIENCE, crate::DEFAULT_API_TIMEOUT)
+                .await
+                .unwrap(),
+            MOCK_GITHUB_OIDC_TOKEN
+        );
+        assert_eq!(
+            state.requests.lock().unwrap().as_slice(),
+            [RequestPayload::GitHubIdToken(String::from(
+                "api-version=2.0&audience=trunk.io"
+            ))]
+        );
+
+        let not_found = IdTokenRequest {
+            url: format!("{}/not-found", state.host),
+            ..id_token_request
+        };
+        assert!(matches!(
+            not_found
+                .fetch_id_token(TRUNK_OIDC_AUDIENCE, crate::DEFAULT_API_TIMEOUT)
+                .await,
+            Err(IdTokenError::RequestFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,344 @@
+//! Posts a comment to a pull request with the issues API, or updates the comment posted by an
+//! earlier run, which is found by a hidden HTML marker in its body so that reruns don't pile up
+//! comments.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{EnvLookup, SystemEnv, DEFAULT_API_URL};
+
+/// GitHub rejects comment bodies longer than this many characters
+pub const MAX_COMMENT_BODY_CHARS: usize = 65_536;
+
+const COMMENTS_PER_PAGE: usize = 100;
+/// Pages of comments searched for the marker. A comment of an earlier run past them isn't found,
+/// so another one is created
+const MAX_COMMENT_PAGES: usize = 10;
+const TRUNCATED_NOTICE: &str = "\n\n_The summary was truncated to fit in a comment._";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueComment {
+    pub id: u64,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueCommentRequest {
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrCommentAction {
+    Created(u64),
+    Updated(u64),
+}
+
+/// Everything needed to comment on a pull request with the issues API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrCommenter {
+    pub api_url: String,
+    pub token: String,
+    /// `owner/name`
+    pub repository: String,
+    pub pr_number: usize,
+}
+
+impl PrCommenter {
+    /// Errors with the names of the missing env vars.
+    pub fn from_env(pr_number: usize) -> Result<Self, Vec<&'static str>> {
+        Self::from_env_lookup(&SystemEnv, pr_number)
+    }
+
+    pub fn from_env_lookup<E: EnvLookup>(
+        env: &E,
+        pr_number: usize,
+    ) -> Result<Self, Vec<&'static str>> {
+        match (env.var("GITHUB_TOKEN"), env.var("GITHUB_REPOSITORY")) {
+            (Some(token), Some(repository)) => Ok(Self {
+                api_url: env
+                    .var("GITHUB_API_URL")
+                    .unwrap_or(String::from(DEFAULT_API_URL)),
+                token,
+                repository,
+                pr_number,
+            }),
+            (token, repository) => Err([
+                (token.is_none(), "GITHUB_TOKEN"),
+                (repository.is_none(), "GITHUB_REPOSITORY"),
+            ]
+            .into_iter()
+            .filter(|(is_missing, _)| *is_missing)
+            .map(|(_, env_var)| env_var)
+            .collect()),
+        }
+    }
+
+    /// Updates the comment whose body contains `marker`, or creates one if there is none. The
+    /// body is `marker` followed by `body`, truncated to [`MAX_COMMENT_BODY_CHARS`].
+    pub async fn upsert(
+        &self,
+        marker: &str,
+        body: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<PrCommentAction> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        let body = IssueCommentRequest {
+            body: truncate_comment_body(&format!("{}\n{}", marker, body), MAX_COMMENT_BODY_CHARS),
+        };
+        match self.find_comment(&client, marker).await? {
+            Some(comment_id) => {
+                let url = format!(
+                    "{}/repos/{}/issues/comments/{}",
+                    self.api_url(),
+                    self.repository,
+                    comment_id
+                );
+                log::debug!("Updating the pull request comment at {}", url);
+                self.request(client.patch(url))
+                    .json(&body)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(PrCommentAction::Updated(comment_id))
+            }
+            None => {
+                let url = self.comments_url();
+                log::debug!("Creating a pull request comment at {}", url);
+                let comment = self
+                    .request(client.post(url))
+                    .json(&body)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<IssueComment>()
+                    .await?;
+                Ok(PrCommentAction::Created(comment.id))
+            }
+        }
+    }
+
+    async fn find_comment(
+        &self,
+        client: &reqwest::Client,
+        marker: &str,
+    ) -> anyhow::Result<Option<u64>> {
+        for page in 1..=MAX_COMMENT_PAGES {
+            let comments = self
+                .request(client.get(self.comments_url()))
+                .query(&[
+                    ("per_page", COMMENTS_PER_PAGE.to_string()),
+                    ("page", page.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<IssueComment>>()
+                .await?;
+            if let Some(comment) = comments.iter().find(|comment| {
+                comment
+                    .body
+                    .as_deref()
+                    .is_some_and(|body| body.contains(marker))
+            }) {
+                return Ok(Some(comment.id));
+            }
+            if comments.len() < COMMENTS_PER_PAGE {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    fn api_url(&self) -> &str {
+        self.api_url.trim_end_matches('/')
+    }
+
+    fn comments_url(&self) -> String {
+        format!(
+            "{}/repos/{}/issues/{}/comments",
+            self.api_url(),
+            self.repository,
+            self.pr_number
+        )
+    }
+
+    fn request(&self, request_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request_builder
+            .bearer_auth(&self.token)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header(reqwest::header::USER_AGENT, "trunk-analytics-cli")
+    }
+}
+
+/// Cuts `body` at the last line that fits in fewer than `max_chars` characters along with a
+/// notice that it was truncated.
+pub fn truncate_comment_body(body: &str, max_chars: usize) -> String {
+    if body.chars().count() < max_chars {
+        return String::from(body);
+    }
+    let max_body_chars = max_chars.saturating_sub(TRUNCATED_NOTICE.chars().count() + 1);
+    let truncated = body
+        .char_indices()
+        .nth(max_body_chars)
+        .map(|(byte_index, _)| &body[..byte_index])
+        .unwrap_or(body);
+    let truncated = truncated
+        .rfind('\n')
+        .map(|newline_index| &truncated[..newline_index])
+        .unwrap_or(truncated);
+    format!("{}{}", truncated, TRUNCATED_NOTICE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::{extract::State, http::Uri, Json};
+    use test_utils::mock_server::{MockServerBuilder, RequestPayload, SharedMockServerState};
+
+    use super::*;
+    use crate::DEFAULT_API_TIMEOUT;
+
+    const MARKER: &str = "<!-- trunk-test-summary -->";
+
+    struct FakeEnv(HashMap<&'static str, String>);
+
+    impl EnvLookup for FakeEnv {
+        fn var(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+    }
+
+    fn pr_commenter(api_url: &str) -> PrCommenter {
+        PrCommenter {
+            api_url: String::from(api_url),
+            token: String::from("github-token"),
+            repository: String::from("trunk-io/analytics-cli"),
+            pr_number: 7,
+        }
+    }
+
+    #[test]
+    fn reads_env() {
+        let env = FakeEnv(HashMap::from([
+            ("GITHUB_TOKEN", String::from("github-token")),
+            ("GITHUB_REPOSITORY", String::from("trunk-io/analytics-cli")),
+        ]));
+        assert_eq!(
+            PrCommenter::from_env_lookup(&env, 7),
+            Ok(pr_commenter(DEFAULT_API_URL))
+        );
+        assert_eq!(
+            PrCommenter::from_env_lookup(&FakeEnv(HashMap::new()), 7),
+            Err(vec!["GITHUB_TOKEN", "GITHUB_REPOSITORY"])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn creates_comment_without_marker() {
+        let state = MockServerBuilder::new().spawn_mock_server().await;
+
+        let action = pr_commenter(&state.host)
+            .upsert(MARKER, "1 failure", DEFAULT_API_TIMEOUT)
+            .await
+            .unwrap();
+        assert!(matches!(action, PrCommentAction::Created(..)));
+        assert_eq!(
+            state.requests.lock().unwrap().as_slice(),
+            [
+                RequestPayload::GitHubListIssueComments(String::from(
+                    "/repos/trunk-io/analytics-cli/issues/7/comments"
+                )),
+                RequestPayload::GitHubCreateIssueComment(
+                    String::from("/repos/trunk-io/analytics-cli/issues/7/comments"),
+                    format!("{}\n1 failure", MARKER)
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn updates_comment_with_marker() {
+        let mut mock_server_builder = MockServerBuilder::new();
+        mock_server_builder.set_github_list_issue_comments_handler(
+            |State(state): State<SharedMockServerState>, uri: Uri| async move {
+                state
+                    .requests
+                    .lock()
+                    .unwrap()
+                    .push(RequestPayload::GitHubListIssueComments(String::from(
+                        uri.path(),
+                    )));
+                Json(vec![
+                    IssueComment {
+                        id: 1,
+                        body: Some(String::from("LGTM")),
+                    },
+                    IssueComment {
+                        id: 2,
+                        body: Some(format!("{}\n2 failures", MARKER)),
+                    },
+                ])
+            },
+        );
+        let state = mock_server_builder.spawn_mock_server().await;
+
+        let pr_commenter = pr_commenter(&state.host);
+        for _ in 0..2 {
+            let action = pr_commenter
+                .upsert(MARKER, "1 failure", DEFAULT_API_TIMEOUT)
+                .await
+                .unwrap();
+            assert_eq!(action, PrCommentAction::Updated(2));
+        }
+        let requests = state.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            requests[1],
+            RequestPayload::GitHubUpdateIssueComment(
+                String::from("/repos/trunk-io/analytics-cli/issues/comments/2"),
+                format!("{}\n1 failure", MARKER)
+            )
+        );
+        assert_eq!(requests[1], requests[3]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn truncates_created_comment() {
+        let state = MockServerBuilder::new().spawn_mock_server().await;
+
+        pr_commenter(&state.host)
+            .upsert(MARKER, &"- test\n".repeat(20_000), DEFAULT_API_TIMEOUT)
+            .await
+            .unwrap();
+        let requests = state.requests.lock().unwrap();
+        let RequestPayload::GitHubCreateIssueComment(_, body) = &requests[1] else {
+            panic!("expected a comment to be created, got {:?}", requests[1]);
+        };
+        assert!(body.starts_with(MARKER));
+        assert!(body.ends_with(TRUNCATED_NOTICE));
+        assert!(body.chars().count() < MAX_COMMENT_BODY_CHARS);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fails_on_api_errors() {
+        let state = MockServerBuilder::new().spawn_mock_server().await;
+
+        assert!(pr_commenter(&format!("{}/not-found", state.host))
+            .upsert(MARKER, "1 failure", DEFAULT_API_TIMEOUT)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn truncates_long_bodies_at_a_line() {
+        assert_eq!(truncate_comment_body("short", 100), "short");
+
+        let body = "- test_€\n".repeat(10_000);
+        let truncated = truncate_comment_body(&body, MAX_COMMENT_BODY_CHARS);
+        assert!(truncated.chars().count() < MAX_COMMENT_BODY_CHARS);
+        assert!(truncated.ends_with(&format!("- test_€{}", TRUNCATED_NOTICE)));
+        assert!(body.starts_with(truncated.strip_suffix(TRUNCATED_NOTICE).unwrap()));
+    }
+}
@@ -0,0 +1,204 @@
+//! Canonical proto3 JSON encoding of the test context protos, for inspecting internal bins.
+//!
+//! The derived `serde` implementations mirror the Rust field names and always emit every field,
+//! so they are not what other protobuf tooling expects. This follows the proto3 JSON mapping:
+//! fields are lowerCamelCase, fields with their default value are omitted, enums are written by
+//! name and timestamps are RFC3339 in UTC.
+
+use prost_wkt_types::Timestamp;
+use serde_json::{Map, Value};
+
+use crate::test_context::test_run::{
+    SkipKind, TestCaseRun, TestCaseRunStatus, TestResult, UploaderMetadata,
+};
+
+impl TestResult {
+    pub fn to_canonical_json(&self) -> Value {
+        let mut object = Map::new();
+        if !self.test_case_runs.is_empty() {
+            object.insert(
+                "testCaseRuns".into(),
+                Value::Array(
+                    self.test_case_runs
+                        .iter()
+                        .map(TestCaseRun::to_canonical_json)
+                        .collect(),
+                ),
+            );
+        }
+        if let Some(uploader_metadata) = &self.uploader_metadata {
+            object.insert(
+                "uploaderMetadata".into(),
+                uploader_metadata.to_canonical_json(),
+            );
+        }
+        Value::Object(object)
+    }
+}
+
+impl TestCaseRun {
+    pub fn to_canonical_json(&self) -> Value {
+        let mut object = Map::new();
+        insert_string(&mut object, "id", &self.id);
+        insert_string(&mut object, "name", &self.name);
+        insert_string(&mut object, "classname", &self.classname);
+        insert_string(&mut object, "file", &self.file);
+        insert_string(&mut object, "parentName", &self.parent_name);
+        insert_int(&mut object, "line", self.line);
+        insert_enum(
+            &mut object,
+            "status",
+            self.status,
+            TestCaseRunStatus::try_from(self.status).map(|status| status.as_str_name()),
+        );
+        insert_int(&mut object, "attemptNumber", self.attempt_number);
+        insert_timestamp(&mut object, "startedAt", &self.started_at);
+        insert_timestamp(&mut object, "finishedAt", &self.finished_at);
+        insert_string(
+            &mut object,
+            "statusOutputMessage",
+            &self.status_output_message,
+        );
+        insert_string(&mut object, "hostname", &self.hostname);
+        insert_string(&mut object, "package", &self.package);
+        insert_string(&mut object, "skipReason", &self.skip_reason);
+        insert_enum(
+            &mut object,
+            "skipKind",
+            self.skip_kind,
+            SkipKind::try_from(self.skip_kind).map(|skip_kind| skip_kind.as_str_name()),
+        );
+        insert_string(&mut object, "sourceFile", &self.source_file);
+        insert_bool(&mut object, "isQuarantined", self.is_quarantined);
+        insert_string(&mut object, "ciJobUrl", &self.ci_job_url);
+        insert_string(&mut object, "ciWorkflowName", &self.ci_workflow_name);
+        insert_string(&mut object, "ciExternalId", &self.ci_external_id);
+        Value::Object(object)
+    }
+}
+
+impl UploaderMetadata {
+    pub fn to_canonical_json(&self) -> Value {
+        let mut object = Map::new();
+        insert_string(&mut object, "version", &self.version);
+        insert_string(&mut object, "origin", &self.origin);
+        insert_timestamp(&mut object, "uploadTime", &self.upload_time);
+        Value::Object(object)
+    }
+}
+
+fn insert_string(object: &mut Map<String, Value>, key: &str, value: &str) {
+    if !value.is_empty() {
+        object.insert(key.into(), Value::String(value.into()));
+    }
+}
+
+fn insert_int(object: &mut Map<String, Value>, key: &str, value: i32) {
+    if value != 0 {
+        object.insert(key.into(), Value::from(value));
+    }
+}
+
+fn insert_bool(object: &mut Map<String, Value>, key: &str, value: bool) {
+    if value {
+        object.insert(key.into(), Value::Bool(value));
+    }
+}
+
+/// Enum values this version does not know are written as numbers, as proto3 JSON allows
+fn insert_enum<E>(
+    object: &mut Map<String, Value>,
+    key: &str,
+    value: i32,
+    name: Result<&'static str, E>,
+) {
+    if value == 0 {
+        return;
+    }
+    let value = match name {
+        Ok(name) => Value::String(name.into()),
+        Err(_) => Value::from(value),
+    };
+    object.insert(key.into(), value);
+}
+
+/// Set timestamps are written even at the epoch, since message fields track presence
+fn insert_timestamp(object: &mut Map<String, Value>, key: &str, value: &Option<Timestamp>) {
+    if let Some(timestamp) = value {
+        object.insert(key.into(), Value::String(timestamp.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use prost_wkt_types::Timestamp;
+    use serde_json::Value;
+
+    use crate::test_context::test_run::{
+        SkipKind, TestCaseRun, TestCaseRunStatus, TestResult, UploaderMetadata,
+    };
+
+    #[test]
+    fn canonical_json_of_decoded_bin_matches_fixture() {
+        let test_result = TestResult {
+            test_case_runs: vec![
+                TestCaseRun {
+                    id: String::from("c3b4a3f1-0f2e-5d3a-8b1e-3a8f0b0a7c11"),
+                    name: String::from("test_addition"),
+                    classname: String::from("math.test_arithmetic"),
+                    file: String::from("math/test_arithmetic.py"),
+                    parent_name: String::from("math"),
+                    line: 12,
+                    status: TestCaseRunStatus::Success.into(),
+                    attempt_number: 0,
+                    started_at: Some(Timestamp {
+                        seconds: 1_700_000_000,
+                        nanos: 0,
+                    }),
+                    finished_at: Some(Timestamp {
+                        seconds: 1_700_000_001,
+                        nanos: 250_000_000,
+                    }),
+                    ..Default::default()
+                },
+                TestCaseRun {
+                    name: String::from("test_division"),
+                    status: TestCaseRunStatus::Skipped.into(),
+                    attempt_number: 1,
+                    skip_reason: String::from("flaky on arm64"),
+                    skip_kind: SkipKind::Disabled.into(),
+                    ..Default::default()
+                },
+                TestCaseRun {
+                    name: String::from("test_from_a_newer_uploader"),
+                    status: 42,
+                    ..Default::default()
+                },
+            ],
+            uploader_metadata: Some(UploaderMetadata {
+                version: String::from("v1"),
+                origin: String::from("pytest"),
+                upload_time: Some(Timestamp {
+                    seconds: 0,
+                    nanos: 0,
+                }),
+            }),
+        };
+
+        let bin = test_result.encode_to_vec();
+        let decoded = TestResult::decode(bin.as_slice()).unwrap();
+
+        let expected: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/test_result.json")).unwrap();
+        assert_eq!(decoded.to_canonical_json(), expected);
+    }
+
+    #[test]
+    fn canonical_json_of_default_test_result_is_empty() {
+        assert_eq!(
+            TestResult::default().to_canonical_json(),
+            serde_json::json!({})
+        );
+    }
+}
@@ -1 +1,2 @@
+pub mod canonical_json;
 pub mod test_context;
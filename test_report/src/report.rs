@@ -134,13 +134,26 @@ impl MutTestReport {
         let debug_props = BundleMetaDebugProps {
             command_line: self.0.borrow().command.clone(),
         };
-        if let Ok(pre_test_context) = gather_pre_test_context(upload_args.clone(), debug_props) {
+        let api_client = match upload_args.api_client() {
+            Ok(api_client) => api_client,
+            Err(e) => {
+                println!("Error creating API client: {:?}", e);
+                return false;
+            }
+        };
+        if let Ok(pre_test_context) =
+            gather_pre_test_context(upload_args.clone().into(), debug_props)
+        {
             match tokio::runtime::Builder::new_multi_thread()
                 .enable_all()
                 .build()
                 .unwrap()
-                .block_on(run_upload(upload_args, Some(pre_test_context), None))
-            {
+                .block_on(run_upload(
+                    upload_args,
+                    &api_client,
+                    Some(pre_test_context),
+                    None,
+                )) {
                 Ok(_) => return true,
                 Err(e) => {
                     println!("Error uploading: {:?}", e);
@@ -0,0 +1,156 @@
+use std::{fs, path::Path, sync::Mutex};
+
+use api::{
+    client::ApiClientTrait,
+    message::{
+        CreateBundleUploadRequest, CreateBundleUploadResponse, CreateRepoRequest,
+        CreateRepoResponse, GetQuarantineConfigRequest, GetQuarantineConfigResponse,
+        UpdateBundleUploadRequest, UpdateBundleUploadResponse, ValidateTokenOrgRequest,
+        ValidateTokenOrgResponse,
+    },
+};
+use async_trait::async_trait;
+use tokio::sync::Notify;
+
+use crate::mock_server::{extract_bundle, RequestPayload};
+
+fn create_bundle_upload_response() -> CreateBundleUploadResponse {
+    CreateBundleUploadResponse {
+        id: String::from("test-bundle-upload-id"),
+        id_v2: String::from("test-bundle-upload-id-v2"),
+        url: String::from("s3upload"),
+        key: String::from("unused"),
+        minimum_recommended_cli_version: None,
+    }
+}
+
+/// Succeeds at every call without doing anything, like the mock server with its default handlers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopApiClient;
+
+#[async_trait]
+impl ApiClientTrait for NoopApiClient {
+    async fn create_repo(&self, _: &CreateRepoRequest) -> anyhow::Result<CreateRepoResponse> {
+        Ok(CreateRepoResponse {})
+    }
+
+    async fn create_bundle_upload(
+        &self,
+        _: &CreateBundleUploadRequest,
+    ) -> anyhow::Result<CreateBundleUploadResponse> {
+        Ok(create_bundle_upload_response())
+    }
+
+    async fn get_quarantining_config(
+        &self,
+        _: &GetQuarantineConfigRequest,
+    ) -> anyhow::Result<GetQuarantineConfigResponse> {
+        Ok(GetQuarantineConfigResponse::default())
+    }
+
+    async fn validate_token_org(
+        &self,
+        request: &ValidateTokenOrgRequest,
+    ) -> anyhow::Result<ValidateTokenOrgResponse> {
+        Ok(ValidateTokenOrgResponse {
+            org_url_slug: request.org_url_slug.clone(),
+        })
+    }
+
+    async fn put_bundle_to_s3(&self, _: &str, _: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn update_bundle_upload(
+        &self,
+        _: &UpdateBundleUploadRequest,
+    ) -> anyhow::Result<UpdateBundleUploadResponse> {
+        Ok(UpdateBundleUploadResponse {})
+    }
+}
+
+/// Records every call in memory as the mock server records requests, so that an upload can be
+/// tested without a socket.
+#[derive(Debug, Default)]
+pub struct RecordingApiClient {
+    pub requests: Mutex<Vec<RequestPayload>>,
+    /// Notified when a bundle starts being put to S3
+    pub s3_upload_started: Notify,
+    quarantining_config: GetQuarantineConfigResponse,
+    stall_s3_upload: bool,
+}
+
+impl RecordingApiClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_quarantining_config(
+        mut self,
+        quarantining_config: GetQuarantineConfigResponse,
+    ) -> Self {
+        self.quarantining_config = quarantining_config;
+        self
+    }
+
+    /// Never finishes putting bundles to S3, e.g. to cancel an upload midway.
+    pub fn with_stalled_s3_upload(mut self) -> Self {
+        self.stall_s3_upload = true;
+        self
+    }
+
+    fn record(&self, request: RequestPayload) {
+        self.requests.lock().unwrap().push(request);
+    }
+}
+
+#[async_trait]
+impl ApiClientTrait for RecordingApiClient {
+    async fn create_repo(&self, request: &CreateRepoRequest) -> anyhow::Result<CreateRepoResponse> {
+        self.record(RequestPayload::CreateRepo(request.clone()));
+        NoopApiClient.create_repo(request).await
+    }
+
+    async fn create_bundle_upload(
+        &self,
+        request: &CreateBundleUploadRequest,
+    ) -> anyhow::Result<CreateBundleUploadResponse> {
+        self.record(RequestPayload::CreateBundleUpload(request.clone()));
+        NoopApiClient.create_bundle_upload(request).await
+    }
+
+    async fn get_quarantining_config(
+        &self,
+        request: &GetQuarantineConfigRequest,
+    ) -> anyhow::Result<GetQuarantineConfigResponse> {
+        self.record(RequestPayload::GetQuarantineBulkTestStatus(request.clone()));
+        Ok(self.quarantining_config.clone())
+    }
+
+    async fn validate_token_org(
+        &self,
+        request: &ValidateTokenOrgRequest,
+    ) -> anyhow::Result<ValidateTokenOrgResponse> {
+        self.record(RequestPayload::ValidateTokenOrg(request.clone()));
+        NoopApiClient.validate_token_org(request).await
+    }
+
+    /// Records the directory the bundle is extracted to, like the mock server.
+    async fn put_bundle_to_s3(&self, _: &str, bundle_path: &Path) -> anyhow::Result<()> {
+        self.s3_upload_started.notify_one();
+        if self.stall_s3_upload {
+            std::future::pending::<()>().await;
+        }
+        let bundle = fs::read(bundle_path)?;
+        self.record(RequestPayload::S3Upload(extract_bundle(&bundle)));
+        Ok(())
+    }
+
+    async fn update_bundle_upload(
+        &self,
+        request: &UpdateBundleUploadRequest,
+    ) -> anyhow::Result<UpdateBundleUploadResponse> {
+        self.record(RequestPayload::UpdateBundleUpload(request.clone()));
+        NoopApiClient.update_bundle_upload(request).await
+    }
+}
@@ -1,3 +1,4 @@
+pub mod api_client;
 pub mod inputs;
 pub mod mock_git_repo;
 mod mock_logger;
@@ -39,3 +39,53 @@ pub fn setup_repo_with_commit<T: AsRef<Path>>(root: T) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Removes the `origin` remote, like a CI checkout that git can't tell the repo URL of.
+pub fn remove_origin_remote<T: AsRef<Path>>(root: T) -> anyhow::Result<()> {
+    git2::Repository::open(root)?.remote_delete("origin")?;
+    Ok(())
+}
+
+/// Mimics a GitLab merged results pipeline checkout: a detached HEAD pointing at a merge commit
+/// on top of the source branch HEAD. Returns the SHA of the source branch HEAD commit.
+pub fn setup_repo_with_detached_merge_commit<T: AsRef<Path>>(root: T) -> anyhow::Result<String> {
+    setup_repo_with_commit(&root)?;
+    let repo = git2::Repository::open(root)?;
+
+    let source_commit = repo.head()?.peel_to_commit()?;
+    let signature = git2::Signature::now("Your Name", "your.email@example.com")?;
+    let merge_oid = repo.commit(
+        None,
+        &signature,
+        &signature,
+        "Merge branch into target",
+        &source_commit.tree()?,
+        &[&source_commit],
+    )?;
+    repo.set_head_detached(merge_oid)?;
+
+    Ok(source_commit.id().to_string())
+}
+
+pub const TEST_STAGED_FILE: &str = "staged.txt";
+
+/// Leaves the working tree dirty on top of `setup_repo_with_commit`: an unstaged change to
+/// [`TEST_FILE`] and a staged new file [`TEST_STAGED_FILE`].
+pub fn setup_repo_with_dirty_working_tree<T: AsRef<Path>>(root: T) -> anyhow::Result<()> {
+    setup_repo_with_commit(&root)?;
+    let repo = git2::Repository::open(root)?;
+    let workdir = repo.workdir().unwrap();
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(workdir.join(TEST_FILE))?;
+    writeln!(file, "unstaged content")?;
+
+    let mut staged_file = std::fs::File::create(workdir.join(TEST_STAGED_FILE))?;
+    writeln!(staged_file, "staged content")?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(TEST_STAGED_FILE))?;
+    index.write()?;
+
+    Ok(())
+}
@@ -6,35 +6,66 @@ use std::{
 };
 
 use api::message::{
-    CreateBundleUploadRequest, CreateBundleUploadResponse, CreateRepoRequest, CreateRepoResponse,
-    GetQuarantineConfigRequest, GetQuarantineConfigResponse, UpdateBundleUploadRequest,
-    UpdateBundleUploadResponse,
+    BundleUploadStatus, BundleUploadSummary, CreateBundleUploadRequest, CreateBundleUploadResponse,
+    CreateRepoRequest, CreateRepoResponse, ExchangeOidcTokenRequest, ExchangeOidcTokenResponse,
+    GetQuarantineConfigRequest, GetQuarantineConfigResponse, ListBundleUploadsRequest,
+    ListBundleUploadsResponse, UpdateBundleUploadRequest, UpdateBundleUploadResponse,
+    ValidateTokenOrgRequest, ValidateTokenOrgResponse,
 };
 use axum::{
     body::Bytes,
     extract::State,
     handler::Handler,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, Uri},
     response::Response,
-    routing::{any, patch, post, put, MethodRouter},
+    routing::{any, get, patch, post, put, MethodRouter},
     Json, Router,
 };
+use github_actions::{
+    oidc::IdTokenResponse,
+    pr_comment::{IssueComment, IssueCommentRequest},
+    ListJobsResponse,
+};
 use tempfile::tempdir;
 use tokio::{net::TcpListener, spawn};
 
+/// Path of the URL GitHub Actions gives jobs to request their OIDC token from, in
+/// `ACTIONS_ID_TOKEN_REQUEST_URL`
+pub const GITHUB_ID_TOKEN_PATH: &str = "/github/idtoken";
+pub const MOCK_GITHUB_OIDC_TOKEN: &str = "mock-github-oidc-token";
+pub const MOCK_EXCHANGED_API_TOKEN: &str = "mock-exchanged-api-token";
+/// Path of the Datadog CI Visibility intake, below `DD_CIVISIBILITY_AGENTLESS_URL`
+pub const DATADOG_CI_TEST_CYCLE_PATH: &str = "/api/v2/citestcycle";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RequestPayload {
     CreateRepo(CreateRepoRequest),
     CreateBundleUpload(CreateBundleUploadRequest),
     UpdateBundleUpload(UpdateBundleUploadRequest),
     GetQuarantineBulkTestStatus(GetQuarantineConfigRequest),
+    ListBundleUploads(ListBundleUploadsRequest),
+    ValidateTokenOrg(ValidateTokenOrgRequest),
+    ExchangeOidcToken(ExchangeOidcTokenRequest),
     S3Upload(PathBuf),
+    /// The query of a request for the OIDC token of a GitHub Actions job
+    GitHubIdToken(String),
+    /// The path of a request to list the jobs of a GitHub Actions workflow run
+    GitHubListJobs(String),
+    /// The path of a request to list the comments of a pull request
+    GitHubListIssueComments(String),
+    /// The path and body of a request to comment on a pull request
+    GitHubCreateIssueComment(String, String),
+    /// The path and body of a request to update a pull request comment
+    GitHubUpdateIssueComment(String, String),
+    /// The API key and body of a request to the Datadog CI Visibility intake
+    DatadogCiTestCycle(String, String),
 }
 
 #[derive(Debug, Default)]
 pub struct MockServerState {
     pub requests: Mutex<Vec<RequestPayload>>,
     pub host: String,
+    pub minimum_recommended_cli_version: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,8 +73,18 @@ pub struct MockServerBuilder {
     repo_create_handler: MethodRouter<SharedMockServerState>,
     create_bundle_handler: MethodRouter<SharedMockServerState>,
     get_quarantining_config_handler: MethodRouter<SharedMockServerState>,
+    list_bundle_uploads_handler: MethodRouter<SharedMockServerState>,
+    validate_token_org_handler: MethodRouter<SharedMockServerState>,
+    exchange_oidc_token_handler: MethodRouter<SharedMockServerState>,
     s3_upload_handler: MethodRouter<SharedMockServerState>,
     update_bundle_handler: MethodRouter<SharedMockServerState>,
+    github_id_token_handler: MethodRouter<SharedMockServerState>,
+    github_list_jobs_handler: MethodRouter<SharedMockServerState>,
+    github_list_issue_comments_handler: MethodRouter<SharedMockServerState>,
+    github_create_issue_comment_handler: MethodRouter<SharedMockServerState>,
+    github_update_issue_comment_handler: MethodRouter<SharedMockServerState>,
+    datadog_ci_test_cycle_handler: MethodRouter<SharedMockServerState>,
+    minimum_recommended_cli_version: Option<String>,
 }
 
 impl MockServerBuilder {
@@ -52,8 +93,18 @@ impl MockServerBuilder {
             repo_create_handler: post(repo_create_handler),
             create_bundle_handler: post(create_bundle_handler),
             get_quarantining_config_handler: post(get_quarantining_config_handler),
+            list_bundle_uploads_handler: post(list_bundle_uploads_handler),
+            validate_token_org_handler: post(validate_token_org_handler),
+            exchange_oidc_token_handler: post(exchange_oidc_token_handler),
             s3_upload_handler: put(s3_upload_handler),
             update_bundle_handler: patch(update_bundle_handler),
+            github_id_token_handler: get(github_id_token_handler),
+            github_list_jobs_handler: get(github_list_jobs_handler),
+            github_list_issue_comments_handler: get(github_list_issue_comments_handler),
+            github_create_issue_comment_handler: post(github_create_issue_comment_handler),
+            github_update_issue_comment_handler: patch(github_update_issue_comment_handler),
+            datadog_ci_test_cycle_handler: post(datadog_ci_test_cycle_handler),
+            minimum_recommended_cli_version: None,
         }
     }
 
@@ -81,6 +132,30 @@ impl MockServerBuilder {
         self.get_quarantining_config_handler = post(handler);
     }
 
+    pub fn set_list_bundle_uploads_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.list_bundle_uploads_handler = post(handler);
+    }
+
+    pub fn set_validate_token_org_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.validate_token_org_handler = post(handler);
+    }
+
+    pub fn set_exchange_oidc_token_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.exchange_oidc_token_handler = post(handler);
+    }
+
     pub fn set_s3_upload_handler<H, T>(&mut self, handler: H)
     where
         H: Handler<T, SharedMockServerState>,
@@ -97,6 +172,59 @@ impl MockServerBuilder {
         self.update_bundle_handler = patch(handler);
     }
 
+    pub fn set_datadog_ci_test_cycle_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.datadog_ci_test_cycle_handler = post(handler);
+    }
+
+    pub fn set_github_id_token_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.github_id_token_handler = get(handler);
+    }
+
+    pub fn set_github_list_jobs_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.github_list_jobs_handler = get(handler);
+    }
+
+    pub fn set_github_list_issue_comments_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.github_list_issue_comments_handler = get(handler);
+    }
+
+    pub fn set_github_create_issue_comment_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.github_create_issue_comment_handler = post(handler);
+    }
+
+    pub fn set_github_update_issue_comment_handler<H, T>(&mut self, handler: H)
+    where
+        H: Handler<T, SharedMockServerState>,
+        T: 'static,
+    {
+        self.github_update_issue_comment_handler = patch(handler);
+    }
+
+    /// Returned by the default create bundle handler.
+    pub fn set_minimum_recommended_cli_version<T: Into<String>>(&mut self, version: T) {
+        self.minimum_recommended_cli_version = Some(version.into());
+    }
+
     /// Mock server spawned in a new thread.
     pub async fn spawn_mock_server(self) -> SharedMockServerState {
         let listener = TcpListener::bind("localhost:0").await.unwrap();
@@ -105,6 +233,7 @@ impl MockServerBuilder {
 
         let state = Arc::new(MockServerState {
             host,
+            minimum_recommended_cli_version: self.minimum_recommended_cli_version,
             ..Default::default()
         });
 
@@ -115,8 +244,38 @@ impl MockServerBuilder {
                 "/v1/metrics/getQuarantineConfig",
                 self.get_quarantining_config_handler,
             )
+            .route(
+                "/v1/metrics/listBundleUploads",
+                self.list_bundle_uploads_handler,
+            )
+            .route(
+                "/v1/metrics/validateTokenOrg",
+                self.validate_token_org_handler,
+            )
+            .route(
+                "/v1/metrics/exchangeOidcToken",
+                self.exchange_oidc_token_handler,
+            )
             .route("/s3upload", self.s3_upload_handler)
-            .route("/v1/metrics/updateBundleUpload", self.update_bundle_handler);
+            .route("/v1/metrics/updateBundleUpload", self.update_bundle_handler)
+            .route(GITHUB_ID_TOKEN_PATH, self.github_id_token_handler)
+            .route(
+                "/repos/:owner/:repo/actions/runs/:run_id/jobs",
+                self.github_list_jobs_handler,
+            )
+            .route(
+                "/repos/:owner/:repo/issues/:number/comments",
+                self.github_list_issue_comments_handler
+                    .merge(self.github_create_issue_comment_handler),
+            )
+            .route(
+                "/repos/:owner/:repo/issues/comments/:comment_id",
+                self.github_update_issue_comment_handler,
+            )
+            .route(
+                DATADOG_CI_TEST_CYCLE_PATH,
+                self.datadog_ci_test_cycle_handler,
+            );
 
         app = app.route(
             "/*rest",
@@ -179,6 +338,7 @@ pub async fn create_bundle_handler(
         id_v2: String::from("test-bundle-upload-id-v2"),
         url: format!("{host}/s3upload"),
         key: String::from("unused"),
+        minimum_recommended_cli_version: state.minimum_recommended_cli_version.clone(),
     })
 }
 
@@ -215,12 +375,79 @@ pub async fn get_quarantining_config_handler(
     })
 }
 
+#[axum::debug_handler]
+pub async fn list_bundle_uploads_handler(
+    State(state): State<SharedMockServerState>,
+    Json(list_bundle_uploads_request): Json<ListBundleUploadsRequest>,
+) -> Json<ListBundleUploadsResponse> {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::ListBundleUploads(
+            list_bundle_uploads_request,
+        ));
+    Json(ListBundleUploadsResponse {
+        bundle_uploads: vec![BundleUploadSummary {
+            id: String::from("test-bundle-upload-id"),
+            created_at: String::from("2024-01-01T00:00:00Z"),
+            upload_status: BundleUploadStatus::UploadComplete,
+            variant: Some(String::from("linux")),
+            num_tests: Some(42),
+            num_failures: Some(1),
+        }],
+    })
+}
+
+/// The token always belongs to the org it is used for.
+#[axum::debug_handler]
+pub async fn validate_token_org_handler(
+    State(state): State<SharedMockServerState>,
+    Json(validate_token_org_request): Json<ValidateTokenOrgRequest>,
+) -> Json<ValidateTokenOrgResponse> {
+    let org_url_slug = validate_token_org_request.org_url_slug.clone();
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::ValidateTokenOrg(validate_token_org_request));
+    Json(ValidateTokenOrgResponse { org_url_slug })
+}
+
+/// Every OIDC token is exchanged for [`MOCK_EXCHANGED_API_TOKEN`].
+#[axum::debug_handler]
+pub async fn exchange_oidc_token_handler(
+    State(state): State<SharedMockServerState>,
+    Json(exchange_oidc_token_request): Json<ExchangeOidcTokenRequest>,
+) -> Json<ExchangeOidcTokenResponse> {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::ExchangeOidcToken(
+            exchange_oidc_token_request,
+        ));
+    Json(ExchangeOidcTokenResponse {
+        api_token: String::from(MOCK_EXCHANGED_API_TOKEN),
+    })
+}
+
 #[axum::debug_handler]
 pub async fn s3_upload_handler(
     State(state): State<SharedMockServerState>,
     bytes: Bytes,
 ) -> Response<String> {
-    let uncompressed_bytes = zstd::decode_all(bytes.as_ref()).unwrap();
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::S3Upload(extract_bundle(bytes.as_ref())));
+    Response::new(String::from("OK"))
+}
+
+/// Extracts a compressed bundle into a directory that is left behind for the test to inspect.
+pub(crate) fn extract_bundle(bytes: &[u8]) -> PathBuf {
+    let uncompressed_bytes = zstd::decode_all(bytes).unwrap();
     let mut archive = tar::Archive::new(uncompressed_bytes.as_slice());
     let tar_extract_directory = tempdir().unwrap();
     for file_entry in archive.entries().unwrap() {
@@ -232,10 +459,115 @@ pub async fn s3_upload_handler(
         fs::create_dir_all(file_path.parent().unwrap()).unwrap();
         fs::write(file_path, file_entry_bytes).unwrap();
     }
+    tar_extract_directory.into_path()
+}
+
+#[axum::debug_handler]
+pub async fn datadog_ci_test_cycle_handler(
+    State(state): State<SharedMockServerState>,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    let api_key = headers
+        .get("dd-api-key")
+        .and_then(|api_key| api_key.to_str().ok())
+        .unwrap_or_default();
     state
         .requests
         .lock()
         .unwrap()
-        .push(RequestPayload::S3Upload(tar_extract_directory.into_path()));
-    Response::new(String::from("OK"))
+        .push(RequestPayload::DatadogCiTestCycle(
+            String::from(api_key),
+            body,
+        ));
+    StatusCode::ACCEPTED
+}
+
+/// Issues [`MOCK_GITHUB_OIDC_TOKEN`] for any audience.
+#[axum::debug_handler]
+pub async fn github_id_token_handler(
+    State(state): State<SharedMockServerState>,
+    uri: Uri,
+) -> Json<IdTokenResponse> {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::GitHubIdToken(String::from(
+            uri.query().unwrap_or_default(),
+        )));
+    Json(IdTokenResponse {
+        value: String::from(MOCK_GITHUB_OIDC_TOKEN),
+    })
+}
+
+#[axum::debug_handler]
+pub async fn github_list_jobs_handler(
+    State(state): State<SharedMockServerState>,
+    uri: Uri,
+) -> Json<ListJobsResponse> {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::GitHubListJobs(String::from(uri.path())));
+    Json(ListJobsResponse::default())
+}
+
+#[axum::debug_handler]
+pub async fn github_list_issue_comments_handler(
+    State(state): State<SharedMockServerState>,
+    uri: Uri,
+) -> Json<Vec<IssueComment>> {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::GitHubListIssueComments(String::from(
+            uri.path(),
+        )));
+    Json(Vec::new())
+}
+
+#[axum::debug_handler]
+pub async fn github_create_issue_comment_handler(
+    State(state): State<SharedMockServerState>,
+    uri: Uri,
+    Json(issue_comment_request): Json<IssueCommentRequest>,
+) -> (StatusCode, Json<IssueComment>) {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::GitHubCreateIssueComment(
+            String::from(uri.path()),
+            issue_comment_request.body.clone(),
+        ));
+    (
+        StatusCode::CREATED,
+        Json(IssueComment {
+            id: 1,
+            body: Some(issue_comment_request.body),
+        }),
+    )
+}
+
+#[axum::debug_handler]
+pub async fn github_update_issue_comment_handler(
+    State(state): State<SharedMockServerState>,
+    uri: Uri,
+    Json(issue_comment_request): Json<IssueCommentRequest>,
+) -> Json<IssueComment> {
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(RequestPayload::GitHubUpdateIssueComment(
+            String::from(uri.path()),
+            issue_comment_request.body.clone(),
+        ));
+    Json(IssueComment {
+        id: 1,
+        body: Some(issue_comment_request.body),
+    })
 }
@@ -1,10 +1,11 @@
 use std::str;
-use std::{fs, process::Command};
+use std::{fs, path::Path, process::Command};
 
 use context::repo::RepoUrlParts;
 use indexmap::indexmap;
 use lazy_static::lazy_static;
 use quick_junit::{NonSuccessKind, Report, TestCase, TestCaseStatus, TestSuite, XmlString};
+use serde::{Deserialize, Serialize};
 
 const RESULTS_FIELD_VALUE: &str = "_value";
 const RESULTS_FIELD_VALUES: &str = "_values";
@@ -17,6 +18,17 @@ pub struct XCResult {
     pub org_url_slug: String,
 }
 
+/// A small summary of an xcresult bundle, kept alongside its test results as a record of where
+/// they came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XCResultSummary {
+    /// The root id from the `Info.plist` of the result bundle, which is unique to each bundle
+    pub bundle_identifier: Option<String>,
+    /// Version of Xcode on the host, which is the one that created the result bundle on CI
+    pub xcode_version: Option<String>,
+    pub num_action_records: usize,
+}
+
 const LEGACY_FLAG_MIN_VERSION: i32 = 70;
 
 fn xcrun<T: AsRef<str>>(args: &[T]) -> anyhow::Result<String> {
@@ -45,6 +57,33 @@ fn xcrun_version() -> anyhow::Result<i32> {
         .unwrap_or_else(|| Err(anyhow::anyhow!("failed to parse xcrun version")))
 }
 
+fn xcode_version() -> anyhow::Result<String> {
+    let version_raw = xcrun(&["xcodebuild", "-version"])?;
+    lazy_static! {
+        // regex to match version where the output looks like Xcode 16.0
+        static ref RE: regex::Regex = regex::Regex::new(r"Xcode (\S+)").unwrap();
+    }
+    RE.captures(&version_raw)
+        .and_then(|capture_group| capture_group.get(1))
+        .map(|version| Ok(version.as_str().to_string()))
+        .unwrap_or_else(|| Err(anyhow::anyhow!("failed to parse xcode version")))
+}
+
+/// Reads the root id of the result bundle at `path` from its `Info.plist`, which does not need
+/// xcrun.
+pub fn read_bundle_identifier<T: AsRef<Path>>(path: T) -> Option<String> {
+    let info_plist = fs::read_to_string(path.as_ref().join("Info.plist")).ok()?;
+    lazy_static! {
+        static ref RE: regex::Regex = regex::Regex::new(
+            r"<key>rootId</key>\s*<dict>\s*<key>hash</key>\s*<string>([^<]+)</string>"
+        )
+        .unwrap();
+    }
+    RE.captures(&info_plist)
+        .and_then(|capture_group| capture_group.get(1))
+        .map(|root_id| root_id.as_str().to_string())
+}
+
 fn xcresulttool<T: AsRef<str>>(
     path: T,
     options: Option<&[T]>,
@@ -352,14 +391,26 @@ impl XCResult {
         Ok(testsuites_junit)
     }
 
-    pub fn generate_junits(&self) -> anyhow::Result<Vec<Report>> {
-        let mut report_junits: Vec<Report> = Vec::new();
-        if let Some(actions) = self
-            .results_obj
+    fn action_records(&self) -> Option<&Vec<serde_json::Value>> {
+        self.results_obj
             .get("actions")
             .and_then(|a| a.get(RESULTS_FIELD_VALUES))
             .and_then(|r| r.as_array())
-        {
+    }
+
+    pub fn summary(&self) -> XCResultSummary {
+        XCResultSummary {
+            bundle_identifier: read_bundle_identifier(&self.path),
+            xcode_version: xcode_version()
+                .map_err(|e| log::debug!("failed to get xcode version: {}", e))
+                .ok(),
+            num_action_records: self.action_records().map_or(0, |actions| actions.len()),
+        }
+    }
+
+    pub fn generate_junits(&self) -> anyhow::Result<Vec<Report>> {
+        let mut report_junits: Vec<Report> = Vec::new();
+        if let Some(actions) = self.action_records() {
             for action in actions {
                 let report_junit = self.junit_report(action)?;
                 // only add the report if it has test suites
@@ -5,7 +5,7 @@ use flate2::read::GzDecoder;
 use lazy_static::lazy_static;
 use tar::Archive;
 use temp_testdir::TempDir;
-use xcresult::XCResult;
+use xcresult::{read_bundle_identifier, XCResult};
 
 const ORG_URL_SLUG: &str = "trunk";
 
@@ -112,6 +112,29 @@ fn test_xcresult_with_valid_path_invalid_os() {
     );
 }
 
+#[test]
+fn test_read_bundle_identifier() {
+    let path = TEMP_DIR_TEST_1.as_ref().join("test1.xcresult");
+    assert_eq!(
+        read_bundle_identifier(path).as_deref(),
+        Some("0~C_1tJ9v_IHzvn_jCobF-ANRVdUaOQ9NjiSXf-ocgOt5YRS_7pUIsVEQCBjNlA_k4gC_vY5tU0pNxD1eqSBXGJA==")
+    );
+    assert_eq!(read_bundle_identifier(TEMP_DIR_TEST_1.as_ref()), None);
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_xcresult_summary() {
+    let path = TEMP_DIR_TEST_1.as_ref().join("test1.xcresult");
+    let path_str = path.to_str().unwrap();
+    let summary = XCResult::new(path_str, &REPO, ORG_URL_SLUG)
+        .unwrap()
+        .summary();
+    assert_eq!(summary.bundle_identifier, read_bundle_identifier(&path));
+    assert!(summary.xcode_version.is_some());
+    assert!(summary.num_action_records >= 1);
+}
+
 #[cfg(target_os = "macos")]
 #[test]
 fn test_expected_failures_xcresult_with_valid_path() {